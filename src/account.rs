@@ -0,0 +1,176 @@
+use libsql::Connection;
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::utils::precise_timestamp;
+
+/// Rows each step of `delete_account` removed or changed.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct AccountPurgeReport {
+    pub friendships: u64,
+    pub telegram_links: u64,
+    pub idempotency_keys: u64,
+    /// Pending shares of other users in splits the account initiated, deleted.
+    pub voided_split_shares: u64,
+    /// Other users' records that pointed at the account, kept as plain records.
+    pub detached_records: u64,
+    /// Splits of others the account took part in, now listing it as departed.
+    pub departed_splits: u64,
+    pub records: u64,
+    pub user_deleted: bool,
+}
+
+/// Deletes `user_id` and everything that belongs to it. Run inside a transaction:
+/// the steps are ordered so that split shares are read before the records go.
+pub async fn delete_account(
+    conn: &Connection,
+    user_id: &str,
+    username: &str,
+    now: OffsetDateTime,
+) -> libsql::Result<AccountPurgeReport> {
+    let friendships = delete_friendships(conn, user_id).await?;
+    let telegram_links = delete_telegram_links(conn, user_id).await?;
+    let idempotency_keys = delete_idempotency_keys(conn, user_id).await?;
+    let voided_split_shares = void_initiated_split_shares(conn, user_id).await?;
+    let detached_records = detach_shared_records(conn, user_id).await?;
+    let departed_splits = leave_participant_splits(conn, user_id, username, now).await?;
+    let records = delete_owned_data(conn, user_id).await?;
+    let user_deleted = delete_user_row(conn, user_id).await?;
+
+    Ok(AccountPurgeReport {
+        friendships,
+        telegram_links,
+        idempotency_keys,
+        voided_split_shares,
+        detached_records,
+        departed_splits,
+        records,
+        user_deleted,
+    })
+}
+
+/// Both directions of every friendship, pending requests included.
+pub async fn delete_friendships(conn: &Connection, user_id: &str) -> libsql::Result<u64> {
+    conn.execute(
+        "DELETE FROM friendship WHERE from_user_id = ?1 OR to_user_id = ?1",
+        [user_id],
+    )
+    .await
+}
+
+/// The Telegram link and any bot confirmation still waiting. Returns the links removed.
+pub async fn delete_telegram_links(conn: &Connection, user_id: &str) -> libsql::Result<u64> {
+    conn.execute(
+        "DELETE FROM bot_pending_actions WHERE user_id = ?",
+        [user_id],
+    )
+    .await?;
+    conn.execute("DELETE FROM telegram_users WHERE user_id = ?", [user_id])
+        .await
+}
+
+pub async fn delete_idempotency_keys(conn: &Connection, user_id: &str) -> libsql::Result<u64> {
+    conn.execute("DELETE FROM idempotency_keys WHERE user_id = ?", [user_id])
+        .await
+}
+
+/// Splits the account initiated end with it: participants' shares they have not
+/// accepted yet are deleted, as are the departures recorded on those splits.
+pub async fn void_initiated_split_shares(conn: &Connection, user_id: &str) -> libsql::Result<u64> {
+    conn.execute(
+        "DELETE FROM split_departures WHERE split_id IN
+             (SELECT split_id FROM records WHERE owner_user_id = ?1 AND creditor_user_id = ?1 AND split_id IS NOT NULL)",
+        [user_id],
+    )
+    .await?;
+    conn.execute(
+        "DELETE FROM record_tags WHERE record_id IN
+             (SELECT id FROM records
+              WHERE creditor_user_id = ?1 AND owner_user_id != ?1 AND split_id IS NOT NULL AND pending = 1)",
+        [user_id],
+    )
+    .await?;
+    conn.execute(
+        "DELETE FROM records
+         WHERE creditor_user_id = ?1 AND owner_user_id != ?1 AND split_id IS NOT NULL AND pending = 1",
+        [user_id],
+    )
+    .await
+}
+
+/// Other users' records that still name the account as debtor or creditor (accepted
+/// shares of its splits) lose the link and become plain records of their owner.
+pub async fn detach_shared_records(conn: &Connection, user_id: &str) -> libsql::Result<u64> {
+    conn.execute(
+        "UPDATE records SET split_id = NULL, debtor_user_id = NULL, creditor_user_id = NULL
+         WHERE owner_user_id != ?1 AND (creditor_user_id = ?1 OR debtor_user_id = ?1)",
+        [user_id],
+    )
+    .await
+}
+
+/// Records the account's share of every split someone else initiated in
+/// `split_departures`, so the initiator still sees who took part and for how much.
+/// The share records themselves go with the rest of the account's records.
+pub async fn leave_participant_splits(
+    conn: &Connection,
+    user_id: &str,
+    username: &str,
+    now: OffsetDateTime,
+) -> libsql::Result<u64> {
+    conn.execute(
+        "INSERT OR IGNORE INTO split_departures (split_id, user_id, username, amount, pending, settled, departed_at)
+         SELECT split_id, owner_user_id, ?2, ABS(amount), pending, settle, ?3 FROM records
+         WHERE owner_user_id = ?1 AND split_id IS NOT NULL
+           AND creditor_user_id IS NOT NULL AND creditor_user_id != ?1",
+        libsql::params![user_id, username, precise_timestamp(now)],
+    )
+    .await
+}
+
+/// Everything keyed by the account's id: records and what hangs off them, categories,
+/// settings, audit and history rows, notifications, queued Telegram messages and stored
+/// sessions. Login attempts recorded under the account's current or earlier names go too.
+/// Returns the records removed.
+pub async fn delete_owned_data(conn: &Connection, user_id: &str) -> libsql::Result<u64> {
+    for statement in [
+        "DELETE FROM record_provenance WHERE record_id IN (SELECT id FROM records WHERE owner_user_id = ?)",
+        "DELETE FROM record_tags WHERE record_id IN (SELECT id FROM records WHERE owner_user_id = ?)",
+        "DELETE FROM recategorize_batch_items WHERE undo_token IN (SELECT undo_token FROM recategorize_batches WHERE owner_user_id = ?)",
+        "DELETE FROM recategorize_batches WHERE owner_user_id = ?",
+    ] {
+        conn.execute(statement, [user_id]).await?;
+    }
+    let records = conn
+        .execute("DELETE FROM records WHERE owner_user_id = ?", [user_id])
+        .await?;
+    for statement in [
+        "DELETE FROM categories WHERE owner_user_id = ?",
+        "DELETE FROM user_settings WHERE user_id = ?",
+        "DELETE FROM period_reopen_audit WHERE owner_user_id = ?",
+        "DELETE FROM telegram_outbox WHERE user_id = ?",
+        "DELETE FROM two_factor_recovery_codes WHERE user_id = ?",
+        "DELETE FROM two_factor_challenges WHERE user_id = ?",
+        "DELETE FROM api_tokens WHERE user_id = ?",
+        "DELETE FROM notifications WHERE user_id = ?",
+        // Attempts that found no account carry only the name; match the current one in
+        // any case and every earlier one, before their history goes
+        "DELETE FROM login_attempts WHERE user_id = ?1
+           OR (user_id IS NULL
+               AND (LOWER(username) = (SELECT LOWER(name) FROM users WHERE id = ?1)
+                    OR username IN (SELECT old_name FROM username_history WHERE user_id = ?1)))",
+        "DELETE FROM username_history WHERE user_id = ?",
+        "DELETE FROM sessions WHERE json_extract(data, '$.user_id') = ?",
+    ] {
+        conn.execute(statement, [user_id]).await?;
+    }
+    Ok(records)
+}
+
+/// Whether the `users` row existed.
+pub async fn delete_user_row(conn: &Connection, user_id: &str) -> libsql::Result<bool> {
+    Ok(conn
+        .execute("DELETE FROM users WHERE id = ?", [user_id])
+        .await?
+        > 0)
+}
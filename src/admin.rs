@@ -0,0 +1,627 @@
+use std::future::Future;
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
+};
+use libsql::Connection;
+use serde::Serialize;
+use time::{Duration, OffsetDateTime};
+
+use crate::account;
+use crate::backup;
+use crate::constants::*;
+use crate::crypto::{self, FieldCryptoError};
+use crate::maintenance::status_timestamp;
+use crate::models::{
+    AdminActionResponse, AdminDryRunQuery, AdminPruneFriendshipsQuery, BackupSnapshot,
+    FriendshipPrunePlan, IdempotencyCleanupPlan, MetricsSnapshot, RecordEncryptionPlan,
+    TelegramUnlinkPlan, UserDisablePlan,
+};
+use crate::utils::{database_busy, db_error_with_context};
+use crate::{AppState, Db, TransactionError, friendship_repo, split_repo, with_transaction};
+
+#[derive(Debug)]
+pub enum AdminActionError {
+    Transaction(TransactionError),
+    Db(libsql::Error),
+    NotFound(&'static str),
+    Conflict(&'static str),
+    Encryption(FieldCryptoError),
+}
+
+impl From<TransactionError> for AdminActionError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+impl From<FieldCryptoError> for AdminActionError {
+    fn from(value: FieldCryptoError) -> Self {
+        Self::Encryption(value)
+    }
+}
+
+impl From<libsql::Error> for AdminActionError {
+    fn from(value: libsql::Error) -> Self {
+        Self::Db(value)
+    }
+}
+
+impl From<AdminActionError> for (StatusCode, String) {
+    fn from(value: AdminActionError) -> Self {
+        match value {
+            AdminActionError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction")
+            }
+            AdminActionError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction")
+            }
+            AdminActionError::Transaction(TransactionError::Busy) => database_busy(),
+            AdminActionError::Db(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            AdminActionError::NotFound(what) => {
+                (StatusCode::NOT_FOUND, format!("{what} not found"))
+            }
+            AdminActionError::Conflict(message) => (StatusCode::CONFLICT, message.to_string()),
+            AdminActionError::Encryption(FieldCryptoError::MissingKey) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                FieldCryptoError::MissingKey.to_string(),
+            ),
+            AdminActionError::Encryption(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        }
+    }
+}
+
+/// A destructive operator operation, split into a read-only `plan` and an `apply`
+/// that changes exactly what the plan lists.
+///
+/// `run_admin_action` serves `dry_run=true` from `plan` alone and otherwise runs
+/// `plan` and `apply` in one transaction, so a preview cannot diverge from the action.
+pub trait AdminAction: Clone + Send + Sync + 'static {
+    /// What the action would change; returned to the caller in both modes.
+    type Plan: Serialize + Send + 'static;
+
+    const NAME: &'static str;
+
+    fn plan(
+        &self,
+        conn: &Connection,
+    ) -> impl Future<Output = Result<Self::Plan, AdminActionError>> + Send;
+
+    /// Applies `plan` and returns the number of rows changed.
+    fn apply(
+        &self,
+        conn: &Connection,
+        plan: &Self::Plan,
+    ) -> impl Future<Output = Result<u64, AdminActionError>> + Send;
+}
+
+pub async fn run_admin_action<A: AdminAction>(
+    db: &Db,
+    action: A,
+    dry_run: bool,
+) -> Result<AdminActionResponse<A::Plan>, AdminActionError> {
+    if dry_run {
+        let conn = db.read().await;
+        let plan = action.plan(&conn).await?;
+        return Ok(AdminActionResponse {
+            action: A::NAME.to_string(),
+            dry_run: true,
+            plan,
+            rows_affected: None,
+        });
+    }
+
+    let (plan, rows_affected) = with_transaction(db, |conn| {
+        let action = action.clone();
+        Box::pin(async move {
+            let plan = action.plan(conn).await?;
+            let rows_affected = action.apply(conn, &plan).await?;
+            Ok::<_, AdminActionError>((plan, rows_affected))
+        })
+    })
+    .await?;
+
+    Ok(AdminActionResponse {
+        action: A::NAME.to_string(),
+        dry_run: false,
+        plan,
+        rows_affected: Some(rows_affected),
+    })
+}
+
+/// Compares without short-circuiting so response timing does not reveal the token.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Checks `Authorization: Bearer <ADMIN_TOKEN>`. The admin API is off without a token.
+pub fn require_admin(
+    app_state: &AppState,
+    headers: &HeaderMap,
+) -> Result<(), (StatusCode, String)> {
+    let Some(expected) = app_state.admin_token.as_deref() else {
+        return Err((StatusCode::NOT_FOUND, "Admin API is disabled".to_string()));
+    };
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match given {
+        Some(given) if tokens_match(given, expected) => Ok(()),
+        _ => Err((StatusCode::UNAUTHORIZED, "Invalid admin token".to_string())),
+    }
+}
+
+// ---- Actions ----
+
+/// Deletes friendship rows in `status` whose status changed at or before `cutoff`.
+/// Also used by the background maintenance job.
+#[derive(Clone)]
+pub struct PruneFriendships {
+    pub status: String,
+    pub cutoff: String,
+}
+
+impl PruneFriendships {
+    pub fn older_than(status: &str, days: u32, now: OffsetDateTime) -> Self {
+        Self {
+            status: status.to_string(),
+            cutoff: status_timestamp(now - Duration::days(i64::from(days))),
+        }
+    }
+}
+
+impl AdminAction for PruneFriendships {
+    type Plan = FriendshipPrunePlan;
+
+    const NAME: &'static str = ADMIN_ACTION_PRUNE_FRIENDSHIPS;
+
+    async fn plan(&self, conn: &Connection) -> Result<FriendshipPrunePlan, AdminActionError> {
+        let friendship_ids =
+            friendship_repo::list_ids_with_status_changed_before(conn, &self.status, &self.cutoff)
+                .await?;
+        Ok(FriendshipPrunePlan {
+            status: self.status.clone(),
+            cutoff: self.cutoff.clone(),
+            friendship_ids,
+        })
+    }
+
+    async fn apply(
+        &self,
+        conn: &Connection,
+        plan: &FriendshipPrunePlan,
+    ) -> Result<u64, AdminActionError> {
+        Ok(friendship_repo::delete_by_ids(conn, &plan.friendship_ids).await?)
+    }
+}
+
+/// Deletes idempotency keys that expired at or before `now`.
+#[derive(Clone)]
+pub struct CleanupIdempotencyKeys {
+    pub now: String,
+}
+
+impl AdminAction for CleanupIdempotencyKeys {
+    type Plan = IdempotencyCleanupPlan;
+
+    const NAME: &'static str = ADMIN_ACTION_CLEANUP_IDEMPOTENCY_KEYS;
+
+    async fn plan(&self, conn: &Connection) -> Result<IdempotencyCleanupPlan, AdminActionError> {
+        Ok(IdempotencyCleanupPlan {
+            expired_before: self.now.clone(),
+            key_ids: split_repo::list_expired_idempotency_ids(conn, &self.now).await?,
+        })
+    }
+
+    async fn apply(
+        &self,
+        conn: &Connection,
+        plan: &IdempotencyCleanupPlan,
+    ) -> Result<u64, AdminActionError> {
+        Ok(split_repo::delete_idempotency_entries(conn, &plan.key_ids).await?)
+    }
+}
+
+async fn ensure_user_exists(conn: &Connection, user_id: &str) -> Result<(), AdminActionError> {
+    let mut rows = conn
+        .query("SELECT 1 FROM users WHERE id = ?", [user_id])
+        .await?;
+    match rows.next().await? {
+        Some(_) => Ok(()),
+        None => Err(AdminActionError::NotFound("User")),
+    }
+}
+
+/// Removes every Telegram account linked to `user_id`.
+#[derive(Clone)]
+pub struct UnlinkTelegram {
+    pub user_id: String,
+}
+
+impl AdminAction for UnlinkTelegram {
+    type Plan = TelegramUnlinkPlan;
+
+    const NAME: &'static str = ADMIN_ACTION_UNLINK_TELEGRAM;
+
+    async fn plan(&self, conn: &Connection) -> Result<TelegramUnlinkPlan, AdminActionError> {
+        ensure_user_exists(conn, &self.user_id).await?;
+        let mut rows = conn
+            .query(
+                "SELECT telegram_user_id FROM telegram_users WHERE user_id = ? ORDER BY telegram_user_id",
+                [self.user_id.as_str()],
+            )
+            .await?;
+        let mut telegram_user_ids = Vec::new();
+        while let Some(row) = rows.next().await? {
+            telegram_user_ids.push(row.get(0)?);
+        }
+        Ok(TelegramUnlinkPlan {
+            user_id: self.user_id.clone(),
+            telegram_user_ids,
+        })
+    }
+
+    async fn apply(
+        &self,
+        conn: &Connection,
+        plan: &TelegramUnlinkPlan,
+    ) -> Result<u64, AdminActionError> {
+        let mut deleted = 0;
+        for telegram_user_id in &plan.telegram_user_ids {
+            deleted += conn
+                .execute(
+                    "DELETE FROM telegram_users WHERE telegram_user_id = ? AND user_id = ?",
+                    (telegram_user_id.as_str(), plan.user_id.as_str()),
+                )
+                .await?;
+        }
+        Ok(deleted)
+    }
+}
+
+/// Marks `user_id` disabled so it can no longer log in, and ends what is already signed
+/// in: stored sessions and Telegram links are deleted. Data is kept.
+#[derive(Clone)]
+pub struct DisableUser {
+    pub user_id: String,
+    pub now: String,
+}
+
+impl AdminAction for DisableUser {
+    type Plan = UserDisablePlan;
+
+    const NAME: &'static str = ADMIN_ACTION_DISABLE_USER;
+
+    async fn plan(&self, conn: &Connection) -> Result<UserDisablePlan, AdminActionError> {
+        let mut rows = conn
+            .query(
+                "SELECT name, disabled_at,
+                        (SELECT COUNT(*) FROM sessions WHERE json_extract(data, '$.user_id') = ?1),
+                        (SELECT COUNT(*) FROM telegram_users WHERE user_id = ?1)
+                 FROM users WHERE id = ?1",
+                [self.user_id.as_str()],
+            )
+            .await?;
+        let row = rows
+            .next()
+            .await?
+            .ok_or(AdminActionError::NotFound("User"))?;
+        let disabled_at: Option<String> = row.get(1)?;
+        Ok(UserDisablePlan {
+            user_id: self.user_id.clone(),
+            username: row.get(0)?,
+            already_disabled: disabled_at.is_some(),
+            session_count: row.get(2)?,
+            telegram_link_count: row.get(3)?,
+        })
+    }
+
+    async fn apply(
+        &self,
+        conn: &Connection,
+        plan: &UserDisablePlan,
+    ) -> Result<u64, AdminActionError> {
+        let mut changed = conn
+            .execute(
+                "DELETE FROM sessions WHERE json_extract(data, '$.user_id') = ?",
+                [plan.user_id.as_str()],
+            )
+            .await?;
+        changed += account::delete_telegram_links(conn, &plan.user_id).await?;
+        if !plan.already_disabled {
+            changed += conn
+                .execute(
+                    "UPDATE users SET disabled_at = ? WHERE id = ? AND disabled_at IS NULL",
+                    (self.now.as_str(), plan.user_id.as_str()),
+                )
+                .await?;
+        }
+        Ok(changed)
+    }
+}
+
+async fn list_record_names(
+    conn: &Connection,
+    user_id: &str,
+) -> Result<Vec<(String, String)>, AdminActionError> {
+    let mut rows = conn
+        .query(
+            "SELECT id, name FROM records WHERE owner_user_id = ? ORDER BY seq",
+            [user_id],
+        )
+        .await?;
+    let mut names = Vec::new();
+    while let Some(row) = rows.next().await? {
+        names.push((row.get(0)?, row.get(1)?));
+    }
+    Ok(names)
+}
+
+async fn plan_record_encryption(
+    conn: &Connection,
+    user_id: &str,
+    rotate: bool,
+) -> Result<RecordEncryptionPlan, AdminActionError> {
+    ensure_user_exists(conn, user_id).await?;
+    if crypto::installed_master_key().is_none() {
+        return Err(FieldCryptoError::MissingKey.into());
+    }
+    let from_key_version = crypto::records_key_version(conn, user_id).await?;
+    let to_key_version = match (from_key_version, rotate) {
+        (None, false) => 1,
+        (Some(version), false) => version,
+        (Some(version), true) => version + 1,
+        (None, true) => {
+            return Err(AdminActionError::Conflict(
+                "User's records are not encrypted; encrypt them before rotating the key",
+            ));
+        }
+    };
+    let record_ids = list_record_names(conn, user_id)
+        .await?
+        .into_iter()
+        .filter(|(_, name)| crypto::sealed_key_version(name) != Some(to_key_version))
+        .map(|(id, _)| id)
+        .collect();
+    Ok(RecordEncryptionPlan {
+        user_id: user_id.to_string(),
+        from_key_version,
+        to_key_version,
+        record_ids,
+    })
+}
+
+/// Decrypts each planned record name and note with whatever key sealed it and re-seals
+/// it with `to_key_version`, then points the user at that version.
+async fn apply_record_encryption(
+    conn: &Connection,
+    plan: &RecordEncryptionPlan,
+) -> Result<u64, AdminActionError> {
+    let master = crypto::installed_master_key().ok_or(FieldCryptoError::MissingKey)?;
+    let mut updated = 0;
+    for record_id in &plan.record_ids {
+        let mut rows = conn
+            .query(
+                "SELECT name, note FROM records WHERE id = ? AND owner_user_id = ?",
+                (record_id.as_str(), plan.user_id.as_str()),
+            )
+            .await?;
+        let Some(row) = rows.next().await? else {
+            continue;
+        };
+        let reseal = |stored: String| -> Result<String, FieldCryptoError> {
+            let plaintext = crypto::open_field_with(Some(master), stored)?;
+            Ok(crypto::encrypt_field(
+                master,
+                &plan.user_id,
+                plan.to_key_version,
+                &plaintext,
+            ))
+        };
+        let name = reseal(row.get(0)?)?;
+        let note = row.get::<Option<String>>(1)?.map(reseal).transpose()?;
+        updated += conn
+            .execute(
+                "UPDATE records SET name = ?, note = ? WHERE id = ? AND owner_user_id = ?",
+                (
+                    name.as_str(),
+                    note.as_deref(),
+                    record_id.as_str(),
+                    plan.user_id.as_str(),
+                ),
+            )
+            .await?;
+    }
+    conn.execute(
+        "UPDATE users SET records_key_version = ? WHERE id = ?",
+        (plan.to_key_version, plan.user_id.as_str()),
+    )
+    .await?;
+    Ok(updated)
+}
+
+/// Turns on record name encryption for `user_id` and encrypts their existing plaintext
+/// names. Re-running it encrypts any plaintext rows left behind.
+#[derive(Clone)]
+pub struct EncryptRecords {
+    pub user_id: String,
+}
+
+impl AdminAction for EncryptRecords {
+    type Plan = RecordEncryptionPlan;
+
+    const NAME: &'static str = ADMIN_ACTION_ENCRYPT_RECORDS;
+
+    async fn plan(&self, conn: &Connection) -> Result<RecordEncryptionPlan, AdminActionError> {
+        plan_record_encryption(conn, &self.user_id, false).await
+    }
+
+    async fn apply(
+        &self,
+        conn: &Connection,
+        plan: &RecordEncryptionPlan,
+    ) -> Result<u64, AdminActionError> {
+        apply_record_encryption(conn, plan).await
+    }
+}
+
+/// Moves an encrypted user to a new derived key and re-encrypts every record name.
+#[derive(Clone)]
+pub struct RotateRecordsKey {
+    pub user_id: String,
+}
+
+impl AdminAction for RotateRecordsKey {
+    type Plan = RecordEncryptionPlan;
+
+    const NAME: &'static str = ADMIN_ACTION_ROTATE_RECORDS_KEY;
+
+    async fn plan(&self, conn: &Connection) -> Result<RecordEncryptionPlan, AdminActionError> {
+        plan_record_encryption(conn, &self.user_id, true).await
+    }
+
+    async fn apply(
+        &self,
+        conn: &Connection,
+        plan: &RecordEncryptionPlan,
+    ) -> Result<u64, AdminActionError> {
+        apply_record_encryption(conn, plan).await
+    }
+}
+
+// ---- Handlers ----
+
+type AdminResult<P> = Result<(StatusCode, Json<AdminActionResponse<P>>), (StatusCode, String)>;
+
+pub async fn prune_friendships(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AdminPruneFriendshipsQuery>,
+) -> AdminResult<FriendshipPrunePlan> {
+    require_admin(&app_state, &headers)?;
+    if query.status != FRIENDSHIP_STATUS_UNFRIENDED && query.status != FRIENDSHIP_STATUS_BLOCKED {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "status must be '{}' or '{}'",
+                FRIENDSHIP_STATUS_UNFRIENDED, FRIENDSHIP_STATUS_BLOCKED
+            ),
+        ));
+    }
+
+    let action = PruneFriendships::older_than(
+        &query.status,
+        query.older_than_days,
+        OffsetDateTime::now_utc(),
+    );
+    let response =
+        run_admin_action(&app_state.main_db, action, query.dry_run.unwrap_or(false)).await?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+pub async fn cleanup_idempotency_keys(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AdminDryRunQuery>,
+) -> AdminResult<IdempotencyCleanupPlan> {
+    require_admin(&app_state, &headers)?;
+
+    let action = CleanupIdempotencyKeys {
+        now: status_timestamp(OffsetDateTime::now_utc()),
+    };
+    let response =
+        run_admin_action(&app_state.main_db, action, query.dry_run.unwrap_or(false)).await?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+pub async fn unlink_telegram(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+    Query(query): Query<AdminDryRunQuery>,
+) -> AdminResult<TelegramUnlinkPlan> {
+    require_admin(&app_state, &headers)?;
+
+    let action = UnlinkTelegram { user_id };
+    let response =
+        run_admin_action(&app_state.main_db, action, query.dry_run.unwrap_or(false)).await?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+pub async fn disable_user(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+    Query(query): Query<AdminDryRunQuery>,
+) -> AdminResult<UserDisablePlan> {
+    require_admin(&app_state, &headers)?;
+
+    let action = DisableUser {
+        user_id,
+        now: status_timestamp(OffsetDateTime::now_utc()),
+    };
+    let response =
+        run_admin_action(&app_state.main_db, action, query.dry_run.unwrap_or(false)).await?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+pub async fn encrypt_records(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+    Query(query): Query<AdminDryRunQuery>,
+) -> AdminResult<RecordEncryptionPlan> {
+    require_admin(&app_state, &headers)?;
+
+    let action = EncryptRecords { user_id };
+    let response =
+        run_admin_action(&app_state.main_db, action, query.dry_run.unwrap_or(false)).await?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+pub async fn rotate_records_key(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+    Query(query): Query<AdminDryRunQuery>,
+) -> AdminResult<RecordEncryptionPlan> {
+    require_admin(&app_state, &headers)?;
+
+    let action = RotateRecordsKey { user_id };
+    let response =
+        run_admin_action(&app_state.main_db, action, query.dry_run.unwrap_or(false)).await?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+pub async fn get_metrics(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, Json<MetricsSnapshot>), (StatusCode, String)> {
+    require_admin(&app_state, &headers)?;
+    Ok((StatusCode::OK, Json(app_state.metrics.snapshot())))
+}
+
+pub async fn create_backup(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, Json<BackupSnapshot>), (StatusCode, String)> {
+    require_admin(&app_state, &headers)?;
+    let Some(config) = app_state.backup.as_deref() else {
+        return Err((
+            StatusCode::CONFLICT,
+            "Backups are only taken of a local database; use the provider's for DATABASE_URL"
+                .to_string(),
+        ));
+    };
+    let snapshot =
+        backup::create_snapshot(&app_state.main_db, config, OffsetDateTime::now_utc()).await?;
+    Ok((StatusCode::CREATED, Json(snapshot)))
+}
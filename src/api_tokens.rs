@@ -0,0 +1,311 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Request, State},
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+};
+use password_hash::rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use time::{Duration, OffsetDateTime};
+use tower_sessions::Session;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::auth::session_user;
+use crate::constants::*;
+use crate::crypto::encode_hex;
+use crate::database::Db;
+use crate::error::ApiError;
+use crate::models::{
+    ApiTokenInfo, ApiTokenListResponse, CreateApiTokenPayload, CreatedApiTokenResponse, PublicUser,
+};
+use crate::utils::{db_error_with_context, precise_timestamp};
+
+// Personal access tokens for scripts and integrations that cannot hold a cookie.
+//
+// A request with `Authorization: Bearer kash_pat_...` is resolved here, before any
+// handler, to the token's user; `auth::get_current_user` returns that user for the rest
+// of the request, so every protected handler accepts tokens without knowing about them.
+// Other bearer values (the admin token) pass through untouched. A read-only token is
+// refused on every method that can change data, so no handler can forget the check.
+
+tokio::task_local! {
+    static BEARER_USER: PublicUser;
+}
+
+/// The user a personal access token authenticated the running request as.
+pub fn bearer_user() -> Option<PublicUser> {
+    BEARER_USER.try_with(Clone::clone).ok()
+}
+
+/// Resolves personal access tokens for every route of `router`.
+pub fn layer<S>(router: Router<S>, db: Db) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(middleware::from_fn_with_state(db, resolve_bearer_token))
+}
+
+fn hash_token(token: &str) -> String {
+    encode_hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn invalid_token() -> ApiError {
+    ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        ERROR_CODE_INVALID_API_TOKEN,
+        "API token is invalid, expired or revoked",
+    )
+}
+
+fn is_read_only_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// The `kash_pat_` token in `Authorization: Bearer`, if there is one.
+fn personal_token(request: &Request) -> Option<&str> {
+    let value = request
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case("bearer") && token.starts_with(API_TOKEN_PREFIX)).then_some(token)
+}
+
+struct ResolvedToken {
+    id: String,
+    user: PublicUser,
+    read_only: bool,
+    last_used_at: Option<String>,
+}
+
+/// The live token with `token_hash` of an account that is not disabled.
+async fn find_token(
+    db: &Db,
+    token_hash: &str,
+    now: &str,
+) -> Result<Option<ResolvedToken>, ApiError> {
+    let lookup_error =
+        |_| -> ApiError { db_error_with_context("failed to check API token").into() };
+    let conn = db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT t.id, t.user_id, u.name, t.read_only, t.last_used_at
+             FROM api_tokens t JOIN users u ON u.id = t.user_id
+             WHERE t.token_hash = ? AND (t.expires_at IS NULL OR t.expires_at > ?)
+               AND u.disabled_at IS NULL",
+            [token_hash, now],
+        )
+        .await
+        .map_err(lookup_error)?;
+    let Some(row) = rows.next().await.map_err(lookup_error)? else {
+        return Ok(None);
+    };
+    Ok(Some(ResolvedToken {
+        id: row.get(0).map_err(lookup_error)?,
+        user: PublicUser {
+            id: row.get(1).map_err(lookup_error)?,
+            username: row.get(2).map_err(lookup_error)?,
+        },
+        read_only: row.get::<bool>(3).map_err(lookup_error)?,
+        last_used_at: row.get(4).map_err(lookup_error)?,
+    }))
+}
+
+async fn resolve_bearer_token(State(db): State<Db>, request: Request, next: Next) -> Response {
+    let Some(token_hash) = personal_token(&request).map(hash_token) else {
+        return next.run(request).await;
+    };
+    let now = OffsetDateTime::now_utc();
+    let token = match find_token(&db, &token_hash, &precise_timestamp(now)).await {
+        Ok(Some(token)) => token,
+        Ok(None) => return invalid_token().into_response(),
+        Err(error) => return error.into_response(),
+    };
+    if token.read_only && !is_read_only_method(request.method()) {
+        return ApiError::new(
+            StatusCode::FORBIDDEN,
+            ERROR_CODE_API_TOKEN_READ_ONLY,
+            "This API token is read-only",
+        )
+        .into_response();
+    }
+
+    let stale_before =
+        precise_timestamp(now - Duration::seconds(API_TOKEN_LAST_USED_RESOLUTION_SECS));
+    if token
+        .last_used_at
+        .as_ref()
+        .is_none_or(|used| *used < stale_before)
+    {
+        let conn = db.write().await;
+        // Usage tracking only; the request goes ahead if it cannot be written
+        if let Err(e) = conn
+            .execute(
+                "UPDATE api_tokens SET last_used_at = ? WHERE id = ?",
+                [precise_timestamp(now), token.id],
+            )
+            .await
+        {
+            tracing::warn!(error = %e, "failed to record API token use");
+        }
+    }
+
+    BEARER_USER.scope(token.user, next.run(request)).await
+}
+
+// ---------------------------------------------------------------------------
+// Handlers; tokens are managed from a logged-in session only, so a leaked token
+// cannot mint or revoke others
+// ---------------------------------------------------------------------------
+
+fn validate_token_name(name: Option<String>) -> Result<Option<String>, ApiError> {
+    let Some(name) = name.map(|name| name.trim().to_string()) else {
+        return Ok(None);
+    };
+    if name.chars().count() > MAX_API_TOKEN_NAME_LENGTH {
+        return Err(ApiError::bad_request(format!(
+            "Token name cannot be longer than {} characters",
+            MAX_API_TOKEN_NAME_LENGTH
+        )));
+    }
+    Ok((!name.is_empty()).then_some(name))
+}
+
+/// `POST /auth/tokens`: a new token, returned in plaintext only in this response.
+pub async fn create_token(
+    State(app_state): State<AppState>,
+    session: Session,
+    Json(payload): Json<CreateApiTokenPayload>,
+) -> Result<(StatusCode, Json<CreatedApiTokenResponse>), ApiError> {
+    let user = session_user(&session).await?;
+    let name = validate_token_name(payload.name)?;
+    if let Some(days) = payload.expires_in_days
+        && !(1..=MAX_API_TOKEN_EXPIRY_DAYS).contains(&days)
+    {
+        return Err(ApiError::bad_request(format!(
+            "expires_in_days must be between 1 and {}",
+            MAX_API_TOKEN_EXPIRY_DAYS
+        )));
+    }
+
+    let mut secret = [0u8; API_TOKEN_BYTES];
+    OsRng.fill_bytes(&mut secret);
+    let token = format!("{API_TOKEN_PREFIX}{}", encode_hex(&secret));
+    let now = OffsetDateTime::now_utc();
+    let info = ApiTokenInfo {
+        id: Uuid::new_v4().to_string(),
+        name,
+        read_only: payload.read_only,
+        created_at: precise_timestamp(now),
+        expires_at: payload
+            .expires_in_days
+            .map(|days| precise_timestamp(now + Duration::days(days))),
+        last_used_at: None,
+    };
+
+    let conn = app_state.main_db.write().await;
+    // The count and the insert share the write lock, so the cap holds
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM api_tokens WHERE user_id = ?",
+            [user.id.as_str()],
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to count API tokens"))?;
+    let count: i64 = rows
+        .next()
+        .await
+        .ok()
+        .flatten()
+        .and_then(|row| row.get(0).ok())
+        .ok_or_else(|| db_error_with_context("failed to count API tokens"))?;
+    if count >= MAX_API_TOKENS_PER_USER {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            ERROR_CODE_API_TOKEN_LIMIT,
+            format!(
+                "At most {} API tokens; revoke one first",
+                MAX_API_TOKENS_PER_USER
+            ),
+        ));
+    }
+    conn.execute(
+        "INSERT INTO api_tokens (id, user_id, name, token_hash, read_only, created_at, expires_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+        libsql::params![
+            info.id.as_str(),
+            user.id.as_str(),
+            info.name.clone(),
+            hash_token(&token),
+            info.read_only,
+            info.created_at.as_str(),
+            info.expires_at.clone()
+        ],
+    )
+    .await
+    .map_err(|_| db_error_with_context("failed to create API token"))?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiTokenResponse { token, info }),
+    ))
+}
+
+/// `GET /auth/tokens`: the user's tokens, newest first, expired ones included.
+pub async fn list_tokens(
+    State(app_state): State<AppState>,
+    session: Session,
+) -> Result<Json<ApiTokenListResponse>, ApiError> {
+    let user = session_user(&session).await?;
+    let list_error = |_| -> ApiError { db_error_with_context("failed to list API tokens").into() };
+    let conn = app_state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT id, name, read_only, created_at, expires_at, last_used_at
+             FROM api_tokens WHERE user_id = ? ORDER BY created_at DESC, id",
+            [user.id.as_str()],
+        )
+        .await
+        .map_err(list_error)?;
+    let mut tokens = Vec::new();
+    while let Some(row) = rows.next().await.map_err(list_error)? {
+        tokens.push(ApiTokenInfo {
+            id: row.get(0).map_err(list_error)?,
+            name: row.get(1).map_err(list_error)?,
+            read_only: row.get::<bool>(2).map_err(list_error)?,
+            created_at: row.get(3).map_err(list_error)?,
+            expires_at: row.get(4).map_err(list_error)?,
+            last_used_at: row.get(5).map_err(list_error)?,
+        });
+    }
+    Ok(Json(ApiTokenListResponse { tokens }))
+}
+
+/// `DELETE /auth/tokens/{id}`: revokes the token; its next request is refused.
+pub async fn revoke_token(
+    State(app_state): State<AppState>,
+    session: Session,
+    Path(token_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let user = session_user(&session).await?;
+    let conn = app_state.main_db.write().await;
+    let deleted = conn
+        .execute(
+            "DELETE FROM api_tokens WHERE id = ? AND user_id = ?",
+            [token_id.as_str(), user.id.as_str()],
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to revoke API token"))?;
+    if deleted == 0 {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            ERROR_CODE_API_TOKEN_NOT_FOUND,
+            "API token not found",
+        ));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,15 +1,162 @@
+use std::collections::HashSet;
+use std::sync::{LazyLock, OnceLock};
+use std::time::Instant;
+
 use argon2::{
     Argon2,
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
-use axum::{Json, extract::State, http::StatusCode};
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use time::{Duration, OffsetDateTime, format_description::well_known::Rfc3339};
 use tower_sessions::Session;
 use uuid::Uuid;
 
-use crate::AppState;
+use crate::account;
+use crate::api_tokens;
+use crate::config::{DefaultCategories, PasswordHashParams, PasswordPolicy};
 use crate::constants::*;
 use crate::database::Db;
-use crate::models::{LoginPayload, PublicUser, RegisterPayload, User};
+use crate::error::ApiError;
+use crate::login_attempts;
+use crate::maintenance::status_timestamp;
+use crate::models::{
+    ChangePasswordPayload, ChangeUsernamePayload, DeleteAccountPayload, LoginHistoryQuery,
+    LoginHistoryResponse, LoginPayload, LoginResponse, PublicUser, RegisterPayload, User,
+    UserSummary,
+};
+use crate::rate_limit::{ClientIp, login_key, register_key, too_many_attempts};
+use crate::two_factor;
+use crate::utils::{conditional_json, database_busy, db_error_with_context, validate_limit};
+use crate::whats_new;
+use crate::{AppState, TransactionError, with_transaction};
+
+enum RegisterError {
+    Transaction(TransactionError),
+    Db(libsql::Error),
+    Taken,
+}
+
+impl From<TransactionError> for RegisterError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+impl From<libsql::Error> for RegisterError {
+    fn from(value: libsql::Error) -> Self {
+        Self::Db(value)
+    }
+}
+
+impl From<RegisterError> for (StatusCode, String) {
+    fn from(value: RegisterError) -> Self {
+        match value {
+            RegisterError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction")
+            }
+            RegisterError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction")
+            }
+            RegisterError::Transaction(TransactionError::Busy) => database_busy(),
+            RegisterError::Db(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            RegisterError::Taken => (StatusCode::CONFLICT, "Username already exists".to_string()),
+        }
+    }
+}
+
+enum ChangeUsernameError {
+    Transaction(TransactionError),
+    Db(libsql::Error),
+    NotFound,
+    Taken,
+    TooSoon { last_changed_at: String },
+}
+
+impl From<TransactionError> for ChangeUsernameError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+impl From<libsql::Error> for ChangeUsernameError {
+    fn from(value: libsql::Error) -> Self {
+        Self::Db(value)
+    }
+}
+
+enum DeleteAccountError {
+    Transaction(TransactionError),
+    Db(libsql::Error),
+    NotFound,
+}
+
+impl From<TransactionError> for DeleteAccountError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+impl From<libsql::Error> for DeleteAccountError {
+    fn from(value: libsql::Error) -> Self {
+        Self::Db(value)
+    }
+}
+
+impl From<DeleteAccountError> for ApiError {
+    fn from(value: DeleteAccountError) -> Self {
+        let error: (StatusCode, String) = match value {
+            DeleteAccountError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction")
+            }
+            DeleteAccountError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction")
+            }
+            DeleteAccountError::Transaction(TransactionError::Busy) => database_busy(),
+            DeleteAccountError::Db(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            DeleteAccountError::NotFound => (StatusCode::UNAUTHORIZED, "Not logged in".to_string()),
+        };
+        error.into()
+    }
+}
+
+impl From<ChangeUsernameError> for ApiError {
+    fn from(value: ChangeUsernameError) -> Self {
+        let error: (StatusCode, String) = match value {
+            ChangeUsernameError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction")
+            }
+            ChangeUsernameError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction")
+            }
+            ChangeUsernameError::Transaction(TransactionError::Busy) => database_busy(),
+            ChangeUsernameError::Db(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            ChangeUsernameError::NotFound => {
+                (StatusCode::UNAUTHORIZED, "Not logged in".to_string())
+            }
+            ChangeUsernameError::Taken => {
+                (StatusCode::CONFLICT, "Username already exists".to_string())
+            }
+            ChangeUsernameError::TooSoon { last_changed_at } => {
+                let next_allowed = OffsetDateTime::parse(&last_changed_at, &Rfc3339)
+                    .map(|at| status_timestamp(at + Duration::days(USERNAME_CHANGE_COOLDOWN_DAYS)))
+                    .unwrap_or(last_changed_at);
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    format!(
+                        "Username can only be changed once every {} days; next change allowed after {}",
+                        USERNAME_CHANGE_COOLDOWN_DAYS, next_allowed
+                    ),
+                )
+            }
+        };
+        error.into()
+    }
+}
 
 pub async fn get_user_by_username_public(
     db: &Db,
@@ -29,15 +176,138 @@ pub async fn get_user_by_username_public(
     }
 }
 
-async fn create_user(db: &Db, username: &str, password: &str) -> anyhow::Result<PublicUser> {
+static PASSWORD_HASH_PARAMS: OnceLock<PasswordHashParams> = OnceLock::new();
+
+/// Installs the process-wide Argon2 cost for new hashes. Returns `false` if one was already installed.
+pub fn install_password_hash_params(params: PasswordHashParams) -> bool {
+    PASSWORD_HASH_PARAMS.set(params).is_ok()
+}
+
+/// Argon2 cost applied to new password hashes.
+///
+/// Installed parameters win. Without them the `test-fast-hash` feature swaps in
+/// minimum-cost parameters so the test suite does not spend most of its time
+/// hashing; it only takes effect in debug builds, release builds fall back to the
+/// default parameters. Verification reads the parameters from the stored hash, so
+/// every kind of hash verifies correctly.
+fn current_hash_params() -> argon2::Params {
+    if let Some(installed) = PASSWORD_HASH_PARAMS.get() {
+        return argon2::Params::new(
+            installed.memory_kib,
+            installed.iterations,
+            installed.parallelism,
+            None,
+        )
+        .expect("PasswordHashParams ranges are within Argon2 limits");
+    }
+    #[cfg(all(feature = "test-fast-hash", debug_assertions))]
+    {
+        argon2::Params::new(
+            argon2::Params::MIN_M_COST,
+            argon2::Params::MIN_T_COST,
+            argon2::Params::MIN_P_COST,
+            None,
+        )
+        .expect("minimum Argon2 params are valid")
+    }
+    #[cfg(not(all(feature = "test-fast-hash", debug_assertions)))]
+    {
+        argon2::Params::default()
+    }
+}
+
+fn hash_password(password: &str) -> anyhow::Result<String> {
     let salt = SaltString::generate(&mut OsRng);
-    let hash = Argon2::default()
+    let hasher = Argon2::new(
+        argon2::Algorithm::Argon2id,
+        argon2::Version::V0x13,
+        current_hash_params(),
+    );
+    Ok(hasher
         .hash_password(password.as_bytes(), &salt)
         .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?
-        .to_string();
+        .to_string())
+}
+
+/// Whether a stored hash uses another algorithm or version, or any cost below the
+/// current parameters. Only the PHC string is parsed, so current hashes cost
+/// nothing extra at login.
+fn needs_rehash(stored_hash: &str) -> bool {
+    let Ok(parsed) = PasswordHash::new(stored_hash) else {
+        return false;
+    };
+    if parsed.algorithm != argon2::Algorithm::Argon2id.ident()
+        || parsed.version != Some(argon2::Version::V0x13.into())
+    {
+        return true;
+    }
+    let Ok(stored) = argon2::Params::try_from(&parsed) else {
+        return true;
+    };
+    let current = current_hash_params();
+    stored.m_cost() < current.m_cost()
+        || stored.t_cost() < current.t_cost()
+        || stored.p_cost() < current.p_cost()
+}
+
+/// Replaces an outdated hash after the password was verified against it.
+///
+/// Only rewrites the row while it still holds the hash that was verified, so a
+/// concurrent password change is never overwritten. Failures are logged and leave
+/// the login unaffected; the next login tries again.
+async fn rehash_if_outdated(db: &Db, user: &User, password: &str) {
+    if !needs_rehash(&user.password_hash) {
+        return;
+    }
+    let new_hash = match hash_password(password) {
+        Ok(hash) => hash,
+        Err(e) => {
+            println!(
+                "Login: rehashing password of user {} failed: {}",
+                user.id, e
+            );
+            return;
+        }
+    };
+    let conn = db.write().await;
+    if let Err(e) = conn
+        .execute(
+            "UPDATE users SET password_hash = ? WHERE id = ? AND password_hash = ?",
+            (
+                new_hash.as_str(),
+                user.id.as_str(),
+                user.password_hash.as_str(),
+            ),
+        )
+        .await
+    {
+        println!(
+            "Login: storing rehashed password of user {} failed: {}",
+            user.id, e
+        );
+    }
+}
+
+/// Whether another account already uses `name` in any letter case. Registration and
+/// renames share this rule; the column's own `UNIQUE` only catches exact matches.
+async fn name_taken(conn: &libsql::Connection, name: &str, user_id: &str) -> libsql::Result<bool> {
+    let mut rows = conn
+        .query(
+            "SELECT 1 FROM users WHERE LOWER(name) = LOWER(?) AND id != ?",
+            (name, user_id),
+        )
+        .await?;
+    Ok(rows.next().await?.is_some())
+}
+
+pub async fn create_user(db: &Db, username: &str, password: &str) -> anyhow::Result<PublicUser> {
+    let hash = hash_password(password)?;
     let id = Uuid::new_v4().to_string();
     let conn = db.write().await;
 
+    if name_taken(&conn, username, &id).await? {
+        anyhow::bail!("Username already exists");
+    }
     conn.execute(
         "INSERT INTO users (id, name, password_hash) VALUES (?, ?, ?)",
         (id.as_str(), username, hash.as_str()),
@@ -50,19 +320,119 @@ async fn create_user(db: &Db, username: &str, password: &str) -> anyhow::Result<
     })
 }
 
-pub async fn register(
-    State(app_state): State<AppState>,
-    Json(payload): Json<RegisterPayload>,
-) -> Result<(StatusCode, Json<PublicUser>), (StatusCode, String)> {
-    // Input validation
-    if payload.username.trim().is_empty() {
+/// Creates the user and `categories` in one transaction, so a failed seed leaves no account.
+pub async fn create_user_with_categories(
+    db: &Db,
+    username: &str,
+    password: &str,
+    categories: &DefaultCategories,
+) -> Result<PublicUser, (StatusCode, String)> {
+    let hash =
+        hash_password(password).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let id = Uuid::new_v4().to_string();
+    let user_id = id.clone();
+    let name = username.to_string();
+    let categories: Vec<(String, bool)> = categories
+        .iter()
+        .map(|(category, is_income)| (category.to_string(), is_income))
+        .collect();
+
+    with_transaction(db, |conn| {
+        Box::pin(async move {
+            if name_taken(conn, &name, &user_id).await? {
+                return Err(RegisterError::Taken);
+            }
+            conn.execute(
+                "INSERT INTO users (id, name, password_hash) VALUES (?, ?, ?)",
+                (user_id.as_str(), name.as_str(), hash.as_str()),
+            )
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("UNIQUE constraint failed") {
+                    RegisterError::Taken
+                } else {
+                    RegisterError::Db(e)
+                }
+            })?;
+            for (category, is_income) in categories {
+                conn.execute(
+                    "INSERT INTO categories (id, owner_user_id, name, is_income) VALUES (?, ?, ?, ?)",
+                    (
+                        Uuid::new_v4().to_string(),
+                        user_id.as_str(),
+                        category,
+                        is_income,
+                    ),
+                )
+                .await?;
+            }
+            Ok::<(), RegisterError>(())
+        })
+    })
+    .await?;
+
+    Ok(PublicUser {
+        id,
+        username: username.to_string(),
+    })
+}
+
+/// Every account ordered by username, for operators.
+pub async fn list_users(conn: &libsql::Connection) -> Result<Vec<UserSummary>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT id, name, disabled_at, last_login_at FROM users ORDER BY name",
+            (),
+        )
+        .await?;
+    let mut users = Vec::new();
+    while let Some(row) = rows.next().await? {
+        users.push(UserSummary {
+            id: row.get(0)?,
+            username: row.get(1)?,
+            disabled_at: row.get(2)?,
+            last_login_at: row.get(3)?,
+        });
+    }
+    Ok(users)
+}
+
+/// Replaces the password of `user_id`, hashed with the installed parameters.
+/// Returns whether the user exists.
+pub async fn set_password(db: &Db, user_id: &str, password: &str) -> anyhow::Result<bool> {
+    let hash = hash_password(password)?;
+    let conn = db.write().await;
+    let updated = conn
+        .execute(
+            "UPDATE users SET password_hash = ? WHERE id = ?",
+            (hash.as_str(), user_id),
+        )
+        .await?;
+    Ok(updated == 1)
+}
+
+/// Clears `disabled_at` so a disabled user can log in again. Returns whether
+/// the user was disabled.
+pub async fn enable_user(db: &Db, user_id: &str) -> Result<bool, libsql::Error> {
+    let conn = db.write().await;
+    let updated = conn
+        .execute(
+            "UPDATE users SET disabled_at = NULL WHERE id = ? AND disabled_at IS NOT NULL",
+            [user_id],
+        )
+        .await?;
+    Ok(updated == 1)
+}
+
+/// Username policy shared by registration and username changes.
+pub fn validate_username(username: &str) -> Result<(), (StatusCode, String)> {
+    if username.trim().is_empty() {
         return Err((
             StatusCode::BAD_REQUEST,
             "Username cannot be empty".to_string(),
         ));
     }
-    if payload.username.len() < MIN_USERNAME_LENGTH || payload.username.len() > MAX_USERNAME_LENGTH
-    {
+    if username.len() < MIN_USERNAME_LENGTH || username.len() > MAX_USERNAME_LENGTH {
         return Err((
             StatusCode::BAD_REQUEST,
             format!(
@@ -71,45 +441,101 @@ pub async fn register(
             ),
         ));
     }
-    if payload.password.len() < MIN_PASSWORD_LENGTH {
+    if !username
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
         return Err((
             StatusCode::BAD_REQUEST,
+            "Username can only contain alphanumeric characters, underscores, and hyphens"
+                .to_string(),
+        ));
+    }
+    if username == SELFTEST_USERNAME {
+        return Err((StatusCode::BAD_REQUEST, "Username is reserved".to_string()));
+    }
+    Ok(())
+}
+
+/// Lower-cased common passwords, one per line, bundled at compile time.
+static COMMON_PASSWORDS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
+    include_str!("common_passwords.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect()
+});
+
+/// Password policy shared by registration and password changes. Stored passwords
+/// are never rechecked, so accounts created under an older policy can still log in.
+pub fn validate_password(
+    policy: &PasswordPolicy,
+    username: &str,
+    password: &str,
+) -> Result<(), ApiError> {
+    if password.chars().count() < policy.min_length {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ERROR_CODE_PASSWORD_TOO_SHORT,
             format!(
                 "Password must be at least {} characters long",
-                MIN_PASSWORD_LENGTH
+                policy.min_length
             ),
         ));
     }
-    if !payload
-        .username
-        .chars()
-        .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
-    {
-        return Err((
+    let lowered = password.to_lowercase();
+    if lowered == username.to_lowercase() {
+        return Err(ApiError::new(
             StatusCode::BAD_REQUEST,
-            "Username can only contain alphanumeric characters, underscores, and hyphens"
-                .to_string(),
+            ERROR_CODE_PASSWORD_MATCHES_USERNAME,
+            "Password cannot be the same as the username",
         ));
     }
+    if COMMON_PASSWORDS.contains(lowered.as_str()) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ERROR_CODE_PASSWORD_TOO_COMMON,
+            "Password is too common; choose one that is harder to guess",
+        ));
+    }
+    Ok(())
+}
 
-    let user = create_user(&app_state.main_db, &payload.username, &payload.password)
-        .await
-        .map_err(|e| {
-            if e.to_string().contains("UNIQUE constraint failed") {
-                (StatusCode::CONFLICT, "Username already exists".to_string())
-            } else {
-                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
-            }
-        })?;
+pub async fn register(
+    State(app_state): State<AppState>,
+    client: ClientIp,
+    Json(payload): Json<RegisterPayload>,
+) -> Result<(StatusCode, Json<PublicUser>), ApiError> {
+    // Every attempt counts, so one client cannot mass-create accounts or probe names
+    app_state
+        .auth_rate_limiter
+        .consume(&register_key(&client.to_string()), Instant::now())
+        .map_err(too_many_attempts)?;
+
+    // Input validation
+    validate_username(&payload.username)?;
+    validate_password(
+        &app_state.password_policy,
+        &payload.username,
+        &payload.password,
+    )?;
+
+    let user = create_user_with_categories(
+        &app_state.main_db,
+        &payload.username,
+        &payload.password,
+        &app_state.default_categories,
+    )
+    .await?;
 
     Ok((StatusCode::CREATED, Json(user)))
 }
 
-async fn get_user_by_username(db: &Db, username: &str) -> anyhow::Result<Option<User>> {
+pub async fn get_user_by_username(db: &Db, username: &str) -> anyhow::Result<Option<User>> {
     let conn = db.read().await;
     let mut rows = conn
         .query(
-            "SELECT id, name, password_hash FROM users WHERE name = ?",
+            "SELECT id, name, password_hash, disabled_at FROM users WHERE name = ?",
             [username],
         )
         .await?;
@@ -118,17 +544,44 @@ async fn get_user_by_username(db: &Db, username: &str) -> anyhow::Result<Option<
         let id: String = row.get(0)?;
         let username: String = row.get(1)?;
         let password_hash: String = row.get(2)?;
+        let disabled_at: Option<String> = row.get(3)?;
         Ok(Some(User {
             id,
             username,
             password_hash,
+            disabled_at,
         }))
     } else {
         Ok(None)
     }
 }
 
-fn verify_password(password: &str, hash: &str) -> anyhow::Result<bool> {
+pub(crate) async fn get_user_by_id(db: &Db, user_id: &str) -> anyhow::Result<Option<User>> {
+    let conn = db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT id, name, password_hash, disabled_at FROM users WHERE id = ?",
+            [user_id],
+        )
+        .await?;
+
+    if let Some(row) = rows.next().await? {
+        let id: String = row.get(0)?;
+        let username: String = row.get(1)?;
+        let password_hash: String = row.get(2)?;
+        let disabled_at: Option<String> = row.get(3)?;
+        Ok(Some(User {
+            id,
+            username,
+            password_hash,
+            disabled_at,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub(crate) fn verify_password(password: &str, hash: &str) -> anyhow::Result<bool> {
     let parsed_hash = PasswordHash::new(hash)
         .map_err(|e| anyhow::anyhow!("Failed to parse password hash: {}", e))?;
     Ok(Argon2::default()
@@ -136,10 +589,27 @@ fn verify_password(password: &str, hash: &str) -> anyhow::Result<bool> {
         .is_ok())
 }
 
+/// Checks a username and password, recording the attempt in `login_attempts` with
+/// `source` (`LOGIN_SOURCE_*`). A locked account is refused before the password is
+/// checked, with the same message whether or not it was right.
 pub async fn authenticate_user(
     db: &Db,
     username: &str,
     password: &str,
+    source: &str,
+) -> Result<PublicUser, (StatusCode, String)> {
+    let user = check_credentials(db, username, password, source).await?;
+    record_attempt(db, username, source, None).await?;
+    Ok(user)
+}
+
+/// `authenticate_user` without recording the success, for logins that still need a
+/// second factor. Failures are recorded the same way.
+pub async fn check_credentials(
+    db: &Db,
+    username: &str,
+    password: &str,
+    source: &str,
 ) -> Result<PublicUser, (StatusCode, String)> {
     // Input validation
     if username.trim().is_empty() {
@@ -154,22 +624,56 @@ pub async fn authenticate_user(
             "Password cannot be empty".to_string(),
         ));
     }
+    // The startup self-test user is never a real account.
+    if username == SELFTEST_USERNAME {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
+    }
+
+    // Checked before the lookup so unknown names lock exactly like real ones
+    let locked = {
+        let conn = db.read().await;
+        login_attempts::locked_until(&conn, username, OffsetDateTime::now_utc())
+            .await
+            .map_err(|_| db_error_with_context("failed to check account lockout"))?
+    };
+    if locked.is_some() {
+        record_attempt(db, username, source, Some(LOGIN_FAILURE_LOCKED)).await?;
+        return Err((StatusCode::LOCKED, ACCOUNT_LOCKED_MESSAGE.to_string()));
+    }
 
     let user_data = get_user_by_username(db, username)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    let user = match user_data {
-        Some(data) => data,
-        None => return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string())),
+    let Some(user) = user_data else {
+        record_attempt(
+            db,
+            username,
+            source,
+            Some(LOGIN_FAILURE_INVALID_CREDENTIALS),
+        )
+        .await?;
+        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
     };
 
     let is_valid = verify_password(password, &user.password_hash)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     if !is_valid {
+        record_attempt(
+            db,
+            username,
+            source,
+            Some(LOGIN_FAILURE_INVALID_CREDENTIALS),
+        )
+        .await?;
         return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
     }
+    if user.disabled_at.is_some() {
+        record_attempt(db, username, source, Some(LOGIN_FAILURE_DISABLED)).await?;
+        return Err((StatusCode::FORBIDDEN, "Account disabled".to_string()));
+    }
+    rehash_if_outdated(db, &user, password).await;
 
     Ok(PublicUser {
         id: user.id,
@@ -177,12 +681,96 @@ pub async fn authenticate_user(
     })
 }
 
+/// Adds a `login_attempts` row; `failure_reason` is a `LOGIN_FAILURE_*` or `None`.
+pub async fn record_attempt(
+    db: &Db,
+    username: &str,
+    source: &str,
+    failure_reason: Option<&str>,
+) -> Result<(), (StatusCode, String)> {
+    let conn = db.write().await;
+    login_attempts::record_login_attempt(
+        &conn,
+        username,
+        source,
+        failure_reason,
+        OffsetDateTime::now_utc(),
+    )
+    .await
+    .map_err(|_| db_error_with_context("failed to record login attempt"))
+}
+
+/// Answers `200` with a session, or `202` with a `TwoFactorChallengeResponse` and no
+/// session when the account has two-factor login enabled.
 pub async fn login(
     State(app_state): State<AppState>,
+    client: ClientIp,
     session: Session,
     Json(payload): Json<LoginPayload>,
-) -> Result<(StatusCode, Json<PublicUser>), (StatusCode, String)> {
-    let user = authenticate_user(&app_state.main_db, &payload.username, &payload.password).await?;
+) -> Result<Response, ApiError> {
+    // Only wrong passwords spend attempts; a correct one is refused too while none are left
+    let limiter = &app_state.auth_rate_limiter;
+    let key = login_key(&client.to_string(), &payload.username);
+    if let Err(wait) = limiter.check(&key, Instant::now()) {
+        record_attempt(
+            &app_state.main_db,
+            &payload.username,
+            LOGIN_SOURCE_WEB,
+            Some(LOGIN_FAILURE_RATE_LIMITED),
+        )
+        .await?;
+        return Err(too_many_attempts(wait));
+    }
+    let user = match check_credentials(
+        &app_state.main_db,
+        &payload.username,
+        &payload.password,
+        LOGIN_SOURCE_WEB,
+    )
+    .await
+    {
+        Ok(user) => user,
+        Err((StatusCode::UNAUTHORIZED, message)) => {
+            let _ = limiter.consume(&key, Instant::now());
+            return Err((StatusCode::UNAUTHORIZED, message).into());
+        }
+        Err(error) => return Err(error.into()),
+    };
+    limiter.reset(&key);
+
+    if two_factor::is_enabled(&app_state.main_db, &user.id).await? {
+        let challenge =
+            two_factor::create_challenge(&app_state.main_db, &user.id, &payload.username).await?;
+        return Ok((StatusCode::ACCEPTED, Json(challenge)).into_response());
+    }
+    record_attempt(
+        &app_state.main_db,
+        &payload.username,
+        LOGIN_SOURCE_WEB,
+        None,
+    )
+    .await?;
+
+    let response = start_session(&app_state, &session, user).await?;
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+/// Logs `user` in on `session` and opens a new "what's new" window. The password,
+/// and the second factor where enabled, must already have been checked.
+pub(crate) async fn start_session(
+    app_state: &AppState,
+    session: &Session,
+    user: PublicUser,
+) -> Result<LoginResponse, ApiError> {
+    let whats_new = {
+        let conn = app_state.main_db.write().await;
+        whats_new::record_login(&conn, &user.id)
+            .await
+            .map_err(|_| db_error_with_context("failed to record login"))?;
+        whats_new::whats_new_counts(&conn, &user.id)
+            .await
+            .map_err(|_| db_error_with_context("failed to count what's new"))?
+    };
 
     // Set user session
     session
@@ -194,10 +782,20 @@ pub async fn login(
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok((StatusCode::OK, Json(user)))
+    Ok(LoginResponse { user, whats_new })
 }
 
+/// The user of a personal access token sent with the request, otherwise the logged-in
+/// session's user.
 pub async fn get_current_user(session: &Session) -> Result<PublicUser, (StatusCode, String)> {
+    if let Some(user) = api_tokens::bearer_user() {
+        return Ok(user);
+    }
+    session_user(session).await
+}
+
+/// The logged-in session's user, ignoring any API token.
+pub async fn session_user(session: &Session) -> Result<PublicUser, (StatusCode, String)> {
     let user_id: Option<String> = session
         .get("user_id")
         .await
@@ -214,9 +812,52 @@ pub async fn get_current_user(session: &Session) -> Result<PublicUser, (StatusCo
     }
 }
 
-pub async fn me(session: Session) -> Result<(StatusCode, Json<PublicUser>), (StatusCode, String)> {
+/// `user` with the name currently stored for its id. The session keeps the name from
+/// login, which is stale in every other session after a rename.
+pub async fn refresh_user(
+    conn: &libsql::Connection,
+    user: PublicUser,
+) -> Result<PublicUser, (StatusCode, String)> {
+    let mut rows = conn
+        .query("SELECT name FROM users WHERE id = ?", [user.id.as_str()])
+        .await
+        .map_err(|_| db_error_with_context("failed to load user"))?;
+    let row = rows
+        .next()
+        .await
+        .map_err(|_| db_error_with_context("failed to load user"))?
+        .ok_or((StatusCode::UNAUTHORIZED, "Not logged in".to_string()))?;
+    let username: String = row
+        .get(0)
+        .map_err(|_| db_error_with_context("failed to load user"))?;
+    Ok(PublicUser { username, ..user })
+}
+
+pub async fn me(
+    State(app_state): State<AppState>,
+    session: Session,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
     let user = get_current_user(&session).await?;
-    Ok((StatusCode::OK, Json(user)))
+    let conn = app_state.main_db.read().await;
+    let user = refresh_user(&conn, user).await?;
+    Ok(conditional_json(&headers, user))
+}
+
+/// `GET /auth/login-history`: the current user's recent login attempts, newest first.
+pub async fn get_login_history(
+    State(app_state): State<AppState>,
+    session: Session,
+    Query(query): Query<LoginHistoryQuery>,
+) -> Result<Json<LoginHistoryResponse>, (StatusCode, String)> {
+    let user = get_current_user(&session).await?;
+    let limit = validate_limit(query.limit, DEFAULT_LOGIN_HISTORY_LIMIT)?;
+
+    let conn = app_state.main_db.read().await;
+    let attempts = login_attempts::list_login_attempts(&conn, &user.id, limit)
+        .await
+        .map_err(|_| db_error_with_context("failed to list login attempts"))?;
+    Ok(Json(LoginHistoryResponse { attempts }))
 }
 
 pub async fn logout(session: Session) -> Result<StatusCode, (StatusCode, String)> {
@@ -224,3 +865,217 @@ pub async fn logout(session: Session) -> Result<StatusCode, (StatusCode, String)
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// Re-checks the password of an already logged-in `user` before a credential change.
+/// Each try spends one of the failed-login attempts for their name, so a hijacked
+/// session cannot guess freely; the right password gives the budget back.
+async fn reverify_password(
+    app_state: &AppState,
+    client: &ClientIp,
+    user: &User,
+    password: &str,
+) -> Result<(), ApiError> {
+    let limiter = &app_state.auth_rate_limiter;
+    let key = login_key(&client.to_string(), &user.username);
+    limiter
+        .consume(&key, Instant::now())
+        .map_err(too_many_attempts)?;
+    let is_valid = verify_password(password, &user.password_hash)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !is_valid {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()).into());
+    }
+    limiter.reset(&key);
+    Ok(())
+}
+
+/// Renames the current user after re-checking their password.
+///
+/// Everything else references users by id, so friendships, splits and the
+/// Telegram link follow the rename without further writes. The old name is kept
+/// in `username_history` and the session id is rotated.
+pub async fn change_username(
+    State(app_state): State<AppState>,
+    client: ClientIp,
+    session: Session,
+    Json(payload): Json<ChangeUsernamePayload>,
+) -> Result<(StatusCode, Json<PublicUser>), ApiError> {
+    let current_user = session_user(&session).await?;
+    validate_username(&payload.new_username)?;
+    if payload.current_password.is_empty() {
+        return Err(ApiError::bad_request("Password cannot be empty"));
+    }
+
+    let user = get_user_by_id(&app_state.main_db, &current_user.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::UNAUTHORIZED, "Not logged in".to_string()))?;
+    reverify_password(&app_state, &client, &user, &payload.current_password).await?;
+
+    if payload.new_username == user.username {
+        return Err(ApiError::bad_request(
+            "New username must differ from the current one",
+        ));
+    }
+
+    let now = OffsetDateTime::now_utc();
+    let changed_at = status_timestamp(now);
+    let cooldown_start = status_timestamp(now - Duration::days(USERNAME_CHANGE_COOLDOWN_DAYS));
+    let history_id = Uuid::new_v4().to_string();
+    let user_id = user.id.clone();
+    let old_name = user.username.clone();
+    let new_name = payload.new_username.clone();
+
+    with_transaction(&app_state.main_db, |conn| {
+        Box::pin(async move {
+            let mut rows = conn
+                .query(
+                    "SELECT username_changed_at FROM users WHERE id = ?",
+                    [user_id.as_str()],
+                )
+                .await?;
+            let row = rows.next().await?.ok_or(ChangeUsernameError::NotFound)?;
+            let last_changed_at: Option<String> = row.get(0)?;
+            drop(rows);
+            if let Some(last_changed_at) = last_changed_at
+                && last_changed_at > cooldown_start
+            {
+                return Err(ChangeUsernameError::TooSoon { last_changed_at });
+            }
+
+            if name_taken(conn, &new_name, &user_id).await? {
+                return Err(ChangeUsernameError::Taken);
+            }
+
+            conn.execute(
+                "UPDATE users SET name = ?, username_changed_at = ? WHERE id = ?",
+                (new_name.as_str(), changed_at.as_str(), user_id.as_str()),
+            )
+            .await?;
+            conn.execute(
+                "INSERT INTO username_history (id, user_id, old_name, new_name, changed_at) \
+                 VALUES (?, ?, ?, ?, ?)",
+                (
+                    history_id.as_str(),
+                    user_id.as_str(),
+                    old_name.as_str(),
+                    new_name.as_str(),
+                    changed_at.as_str(),
+                ),
+            )
+            .await?;
+            Ok::<(), ChangeUsernameError>(())
+        })
+    })
+    .await?;
+
+    // New session id after a credential change; keep the user logged in under the new name.
+    session
+        .cycle_id()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    session
+        .insert("username", &payload.new_username)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(PublicUser {
+            id: user.id,
+            username: payload.new_username,
+        }),
+    ))
+}
+
+/// Replaces the current user's password after re-checking the old one.
+///
+/// The new hash uses the installed Argon2 parameters. The session id is rotated,
+/// so a cookie captured before the change stops working.
+pub async fn change_password(
+    State(app_state): State<AppState>,
+    client: ClientIp,
+    session: Session,
+    Json(payload): Json<ChangePasswordPayload>,
+) -> Result<StatusCode, ApiError> {
+    let current_user = session_user(&session).await?;
+    if payload.current_password.is_empty() {
+        return Err(ApiError::bad_request("Password cannot be empty"));
+    }
+
+    let user = get_user_by_id(&app_state.main_db, &current_user.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::UNAUTHORIZED, "Not logged in".to_string()))?;
+    validate_password(
+        &app_state.password_policy,
+        &user.username,
+        &payload.new_password,
+    )?;
+
+    reverify_password(&app_state, &client, &user, &payload.current_password).await?;
+    if payload.new_password == payload.current_password {
+        return Err(ApiError::bad_request(
+            "New password must differ from the current one",
+        ));
+    }
+
+    let updated = set_password(&app_state.main_db, &user.id, &payload.new_password)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !updated {
+        return Err((StatusCode::UNAUTHORIZED, "Not logged in".to_string()).into());
+    }
+
+    // New session id after a credential change; the user stays logged in.
+    session
+        .cycle_id()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Deletes the current user and their data after re-checking their password.
+///
+/// One `with_transaction` runs `account::delete_account`: friendships, the Telegram
+/// link, idempotency keys, splits they initiated and everything they own go; in other
+/// people's splits they are listed as departed. Their stored sessions are removed and
+/// this one is flushed.
+pub async fn delete_account(
+    State(app_state): State<AppState>,
+    client: ClientIp,
+    session: Session,
+    Json(payload): Json<DeleteAccountPayload>,
+) -> Result<StatusCode, ApiError> {
+    let current_user = session_user(&session).await?;
+    if payload.password.is_empty() {
+        return Err(ApiError::bad_request("Password cannot be empty"));
+    }
+
+    let user = get_user_by_id(&app_state.main_db, &current_user.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::UNAUTHORIZED, "Not logged in".to_string()))?;
+
+    reverify_password(&app_state, &client, &user, &payload.password).await?;
+
+    let now = OffsetDateTime::now_utc();
+    with_transaction(&app_state.main_db, |conn| {
+        Box::pin(async move {
+            let report = account::delete_account(conn, &user.id, &user.username, now).await?;
+            if !report.user_deleted {
+                return Err(DeleteAccountError::NotFound);
+            }
+            Ok::<(), DeleteAccountError>(())
+        })
+    })
+    .await?;
+
+    session
+        .flush()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
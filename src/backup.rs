@@ -0,0 +1,174 @@
+use std::path::{Path, PathBuf};
+
+use axum::http::StatusCode;
+use serde_json::Value;
+use time::{Duration, OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+use crate::Db;
+use crate::config::BackupConfig;
+use crate::constants::*;
+use crate::jobs::{JobFuture, JobHandler};
+use crate::maintenance::status_timestamp;
+use crate::models::{BackupFile, BackupSnapshot};
+
+/// File name of the main database inside a snapshot folder, as in the data directory.
+const SNAPSHOT_DB_FILE: &str = "users.db";
+
+#[derive(Debug)]
+pub enum BackupError {
+    Io(std::io::Error),
+    Db(libsql::Error),
+    /// A snapshot was already taken within the same second.
+    Exists(PathBuf),
+}
+
+impl From<std::io::Error> for BackupError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<libsql::Error> for BackupError {
+    fn from(value: libsql::Error) -> Self {
+        Self::Db(value)
+    }
+}
+
+impl From<BackupError> for (StatusCode, String) {
+    fn from(value: BackupError) -> Self {
+        match value {
+            BackupError::Io(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to write backup: {}", e),
+            ),
+            BackupError::Db(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Database error: failed to copy database: {}", e),
+            ),
+            BackupError::Exists(path) => (
+                StatusCode::CONFLICT,
+                format!("A snapshot already exists at {}", path.display()),
+            ),
+        }
+    }
+}
+
+fn snapshot_name_format() -> Vec<time::format_description::BorrowedFormatItem<'static>> {
+    time::format_description::parse(BACKUP_SNAPSHOT_NAME_FORMAT)
+        .expect("BACKUP_SNAPSHOT_NAME_FORMAT is a valid format description")
+}
+
+/// Folder name for a snapshot taken at `at`.
+pub fn snapshot_name(at: OffsetDateTime) -> String {
+    at.to_offset(UtcOffset::UTC)
+        .format(&snapshot_name_format())
+        .unwrap_or_default()
+}
+
+/// When the snapshot in folder `name` was taken; `None` for folders we did not write.
+pub fn snapshot_taken_at(name: &str) -> Option<OffsetDateTime> {
+    PrimitiveDateTime::parse(name, &snapshot_name_format())
+        .ok()
+        .map(PrimitiveDateTime::assume_utc)
+}
+
+/// Writes a consistent copy of the main database into a new folder named for `now`
+/// under `config.path`, then prunes snapshots older than `config.retention_days`.
+///
+/// `VACUUM INTO` copies what one read transaction sees, so writes from the bot or the
+/// CLI cannot tear it; holding the write guard keeps this process's own transactions
+/// (and their open statements) off the connection meanwhile.
+pub async fn create_snapshot(
+    db: &Db,
+    config: &BackupConfig,
+    now: OffsetDateTime,
+) -> Result<BackupSnapshot, BackupError> {
+    let root = Path::new(&config.path);
+    tokio::fs::create_dir_all(root).await?;
+    let dir = root.join(snapshot_name(now));
+    match tokio::fs::create_dir(&dir).await {
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err(BackupError::Exists(dir));
+        }
+        result => result?,
+    }
+
+    let file = dir.join(SNAPSHOT_DB_FILE);
+    let copied = {
+        let conn = db.write().await;
+        conn.execute("VACUUM INTO ?", [file.to_string_lossy().to_string()])
+            .await
+    };
+    if let Err(e) = copied {
+        // Leave no half-written folder for a restore to pick up
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+        return Err(e.into());
+    }
+    let size_bytes = tokio::fs::metadata(&file).await?.len();
+
+    let cutoff = now - Duration::days(i64::from(config.retention_days));
+    let pruned = prune_snapshots(root, cutoff).await?;
+
+    Ok(BackupSnapshot {
+        path: dir.to_string_lossy().to_string(),
+        created_at: status_timestamp(now),
+        files: vec![BackupFile {
+            name: SNAPSHOT_DB_FILE.to_string(),
+            size_bytes,
+        }],
+        total_bytes: size_bytes,
+        pruned,
+    })
+}
+
+/// Deletes snapshot folders under `root` taken before `cutoff`. Anything else in the
+/// folder is left alone. Returns the deleted folder names, oldest first.
+pub async fn prune_snapshots(
+    root: &Path,
+    cutoff: OffsetDateTime,
+) -> Result<Vec<String>, BackupError> {
+    let mut pruned = Vec::new();
+    let mut entries = tokio::fs::read_dir(root).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let expired = snapshot_taken_at(&name).is_some_and(|taken_at| taken_at < cutoff);
+        if expired && entry.file_type().await?.is_dir() {
+            tokio::fs::remove_dir_all(entry.path()).await?;
+            pruned.push(name);
+        }
+    }
+    pruned.sort();
+    Ok(pruned)
+}
+
+/// Recurring job running `create_snapshot` every `BackupConfig.interval_hours`.
+pub struct BackupDatabaseJob {
+    pub config: BackupConfig,
+}
+
+impl JobHandler for BackupDatabaseJob {
+    fn job_type(&self) -> &'static str {
+        JOB_TYPE_BACKUP_DATABASE
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        self.config
+            .interval_hours
+            .map(|hours| Duration::hours(i64::from(hours)))
+    }
+
+    fn run<'a>(&'a self, db: &'a Db, _payload: &'a Value, now: OffsetDateTime) -> JobFuture<'a> {
+        Box::pin(async move {
+            let snapshot = create_snapshot(db, &self.config, now)
+                .await
+                .map_err(|e| format!("database backup failed: {:?}", e))?;
+            tracing::info!(
+                path = %snapshot.path,
+                bytes = snapshot.total_bytes,
+                pruned = snapshot.pruned.len(),
+                "database backup written"
+            );
+            Ok(())
+        })
+    }
+}
@@ -6,6 +6,26 @@ pub const DEFAULT_WHISPER_MODEL: &str = "whisper-1";
 pub const MAX_VOICE_FILE_SIZE: usize = 3 * 1024 * 1024;
 pub const MAX_PHOTO_FILE_SIZE: usize = 10 * 1024 * 1024;
 
+pub const RECENT_RECORDS_LIMIT: u32 = 5;
+pub const SUMMARY_TOP_EXPENSE_CATEGORIES: usize = 5;
+
 pub const TOOL_MAX_ROUNDS: usize = 6;
 pub const CONTEXT_MAX_TURNS: usize = 3;
 pub const CONTEXT_TTL_SECONDS: i64 = 600;
+
+pub const LIST_RECORDS_TRUNCATED_HINT: &str = "More records match than were returned. Narrow the dates, category or name, or call sum_records for totals.";
+
+pub const FALLBACK_CLARIFICATION: &str = "I couldn't read that as a record. Send it as \"name amount [date]\", e.g. \"lunch 180\" or \"taxi 250 yesterday\".";
+
+pub const PERIOD_CLOSED_BOT_HINT: &str = "Closed periods can only be reopened from the app.";
+
+pub const OUTBOX_POLL_INTERVAL_SECS: u64 = 60;
+
+/// `callback_data` prefixes of the pending-change buttons; the action id follows.
+pub const CONFIRM_CALLBACK_PREFIX: &str = "confirm:";
+pub const CANCEL_CALLBACK_PREFIX: &str = "cancel:";
+
+pub const REFERENCE_DELETED_BOT_HINT: &str =
+    "Nothing was changed. Name another record or category, or drop the edit.";
+pub const LAST_RECORD_DELETED_BOT_HINT: &str =
+    "The record added last in this chat was deleted. Please include record_id.";
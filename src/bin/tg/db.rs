@@ -1,18 +1,38 @@
 use std::collections::HashMap;
 
+use axum::http::StatusCode;
 use serde::Deserialize;
 use serde_json::json;
-use time::OffsetDateTime;
+use time::{Date, OffsetDateTime, UtcOffset};
 use uuid::Uuid;
 
 use kash_server::Db;
-use kash_server::categories::validate_category_name;
-use kash_server::models::{CreateRecordPayload, Record};
+use kash_server::categories::{self, get_or_create_category};
+use kash_server::constants::{
+    CREATED_VIA_BOT_AI, CREATED_VIA_BOT_COMMAND, DEFAULT_UTC_OFFSET_MINUTES,
+    RECORD_TRASH_RETENTION_DAYS,
+};
+use kash_server::crypto;
+use kash_server::models::AiAccuracyResponse;
+use kash_server::models::{
+    CreateRecordPayload, PendingCategoryEdit, Record, RecordProvenance, RecordSummaryQuery,
+};
+use kash_server::money::{Money, format_amount, format_amount_change};
+use kash_server::record_repo::{self, RECORD_COLUMNS, RecordSearch};
 use kash_server::records;
-use kash_server::utils::{validate_date, validate_offset, validate_records_limit};
-
+use kash_server::settings::{fetch_user_settings, guard_closed_period};
+use kash_server::stats;
+use kash_server::utils::{to_db_date, validate_date};
+
+use crate::constants::{
+    CONTEXT_TTL_SECONDS, LAST_RECORD_DELETED_BOT_HINT, LIST_RECORDS_TRUNCATED_HINT,
+    PERIOD_CLOSED_BOT_HINT, REFERENCE_DELETED_BOT_HINT,
+};
+use crate::fallback::FallbackRecord;
 use crate::helpers::{normalize_amount_by_category, resolve_category_id};
-use crate::models::{BotState, CategoryInfo};
+use crate::models::{
+    BotState, CategoryInfo, MonthSummary, PendingAction, PendingActionType, TurnState,
+};
 
 // ---------------------------------------------------------------------------
 // Telegram user link
@@ -70,60 +90,260 @@ pub async fn fetch_linked_user_id(
     }
 }
 
-// ---------------------------------------------------------------------------
-// Category helpers
-// ---------------------------------------------------------------------------
+/// Removes the Telegram user's link and anything pending for it. Returns whether a link
+/// existed.
+pub async fn delete_telegram_link(db: &Db, telegram_user_id: i64) -> Result<bool, String> {
+    let conn = db.write().await;
+    conn.execute(
+        "DELETE FROM bot_pending_actions WHERE telegram_user_id = ?",
+        [telegram_user_id.to_string()],
+    )
+    .await
+    .map_err(|_| "Failed to unlink Telegram user".to_string())?;
+    let deleted = conn
+        .execute(
+            "DELETE FROM telegram_users WHERE telegram_user_id = ?",
+            [telegram_user_id.to_string()],
+        )
+        .await
+        .map_err(|_| "Failed to unlink Telegram user".to_string())?;
 
-pub async fn load_categories(db: &Db, user_id: &str) -> Result<Vec<CategoryInfo>, String> {
+    Ok(deleted > 0)
+}
+
+/// Username of the account the Telegram user is linked to, if any.
+pub async fn fetch_linked_username(
+    db: &Db,
+    telegram_user_id: i64,
+) -> Result<Option<String>, String> {
     let conn = db.read().await;
     let mut rows = conn
         .query(
-            "SELECT id, name, is_income FROM categories WHERE owner_user_id = ? ORDER BY name ASC",
-            [user_id],
+            "SELECT u.name FROM telegram_users t JOIN users u ON u.id = t.user_id \
+             WHERE t.telegram_user_id = ?",
+            [telegram_user_id.to_string()],
         )
         .await
-        .map_err(|_| "Failed to query categories".to_string())?;
+        .map_err(|_| "Failed to lookup Telegram user".to_string())?;
 
-    let mut categories = Vec::new();
-    while let Some(row) = rows
+    match rows
         .next()
         .await
-        .map_err(|_| "Failed to query categories".to_string())?
+        .map_err(|_| "Failed to lookup Telegram user".to_string())?
     {
-        let id: String = row.get(0).map_err(|_| "Invalid category".to_string())?;
-        let name: String = row.get(1).map_err(|_| "Invalid category".to_string())?;
-        let is_income: bool = row.get(2).map_err(|_| "Invalid category".to_string())?;
-        categories.push(CategoryInfo {
-            id,
-            name,
-            is_income,
-        });
+        Some(row) => row
+            .get(0)
+            .map(Some)
+            .map_err(|_| "Failed to read Telegram user".to_string()),
+        None => Ok(None),
     }
+}
 
-    Ok(categories)
+// ---------------------------------------------------------------------------
+// Pending actions
+// ---------------------------------------------------------------------------
+
+/// Stores the change awaiting `/confirm`, replacing any earlier one for this Telegram user,
+/// and returns its id for the confirm/cancel buttons.
+pub async fn save_pending_action(
+    db: &Db,
+    telegram_user_id: i64,
+    user_id: &str,
+    pending: &PendingAction,
+) -> Result<String, String> {
+    let id = Uuid::new_v4().to_string();
+    let action = serde_json::to_string(&pending.action)
+        .map_err(|_| "Failed to save the pending change".to_string())?;
+    let expires_at = OffsetDateTime::now_utc().unix_timestamp() + CONTEXT_TTL_SECONDS;
+
+    let conn = db.write().await;
+    conn.execute(
+        "DELETE FROM bot_pending_actions WHERE telegram_user_id = ?",
+        [telegram_user_id.to_string()],
+    )
+    .await
+    .map_err(|_| "Failed to save the pending change".to_string())?;
+    conn.execute(
+        "INSERT INTO bot_pending_actions (id, telegram_user_id, user_id, summary, action, expires_at) \
+         VALUES (?, ?, ?, ?, ?, ?)",
+        (
+            id.as_str(),
+            telegram_user_id.to_string(),
+            user_id,
+            pending.summary.as_str(),
+            action,
+            expires_at,
+        ),
+    )
+    .await
+    .map_err(|_| "Failed to save the pending change".to_string())?;
+
+    Ok(id)
 }
 
-pub async fn get_or_create_category(
+/// Removes and returns the Telegram user's unexpired pending change for `user_id`. A
+/// change made while linked to another account is dropped, not applied. With
+/// `action_id` (from a button) only that change is taken, so a button left on an older
+/// message cannot apply or drop a newer change.
+pub async fn take_pending_action(
     db: &Db,
+    telegram_user_id: i64,
     user_id: &str,
-    name: &str,
-    is_income: bool,
-) -> Result<CategoryInfo, String> {
-    let trimmed = name.trim();
-    let fallback = if trimmed.is_empty() { "Other" } else { trimmed };
-    validate_category_name(fallback).map_err(|(_, message)| message)?;
+    action_id: Option<&str>,
+) -> Result<Option<PendingAction>, String> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let conn = db.write().await;
+    let mut rows = conn
+        .query(
+            "SELECT summary, action FROM bot_pending_actions \
+             WHERE telegram_user_id = ?1 AND user_id = ?2 AND expires_at > ?3 AND (?4 IS NULL OR id = ?4) \
+             ORDER BY expires_at DESC LIMIT 1",
+            (telegram_user_id.to_string(), user_id, now, action_id),
+        )
+        .await
+        .map_err(|_| "Failed to load the pending change".to_string())?;
+    let pending = match rows
+        .next()
+        .await
+        .map_err(|_| "Failed to load the pending change".to_string())?
+    {
+        Some(row) => {
+            let summary: String = row
+                .get(0)
+                .map_err(|_| "Failed to read the pending change".to_string())?;
+            let action: String = row
+                .get(1)
+                .map_err(|_| "Failed to read the pending change".to_string())?;
+            let action = serde_json::from_str(&action)
+                .map_err(|_| "Failed to read the pending change".to_string())?;
+            Some(PendingAction { summary, action })
+        }
+        None => None,
+    };
+    drop(rows);
 
+    conn.execute(
+        "DELETE FROM bot_pending_actions WHERE telegram_user_id = ?1 AND (?2 IS NULL OR id = ?2)",
+        (telegram_user_id.to_string(), action_id),
+    )
+    .await
+    .map_err(|_| "Failed to clear the pending change".to_string())?;
+
+    Ok(pending)
+}
+
+/// The Telegram user a pending change belongs to, while it is stored. Buttons can be
+/// pressed by anyone in a group chat, so the callback handler checks this first.
+pub async fn fetch_pending_action_owner(db: &Db, action_id: &str) -> Result<Option<i64>, String> {
+    let conn = db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT telegram_user_id FROM bot_pending_actions WHERE id = ?",
+            [action_id],
+        )
+        .await
+        .map_err(|_| "Failed to load the pending change".to_string())?;
+    match rows
+        .next()
+        .await
+        .map_err(|_| "Failed to load the pending change".to_string())?
+    {
+        Some(row) => {
+            let owner: String = row
+                .get(0)
+                .map_err(|_| "Failed to read the pending change".to_string())?;
+            Ok(owner.parse().ok())
+        }
+        None => Ok(None),
+    }
+}
+
+/// Deletes pending actions past their `expires_at`, whoever they belong to.
+pub async fn purge_expired_pending_actions(db: &Db) -> Result<u64, String> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
     let conn = db.write().await;
+    conn.execute(
+        "DELETE FROM bot_pending_actions WHERE expires_at <= ?",
+        [now],
+    )
+    .await
+    .map_err(|_| "Failed to purge pending changes".to_string())
+}
 
-    let mut existing = conn
+// ---------------------------------------------------------------------------
+// Fallback parser
+// ---------------------------------------------------------------------------
+
+/// Today in the user's `utc_offset_minutes`, so "yesterday" means the user's yesterday.
+pub async fn fetch_user_today(db: &Db, user_id: &str) -> Result<Date, String> {
+    let conn = db.read().await;
+    let settings = fetch_user_settings(&conn, user_id)
+        .await
+        .map_err(|(_, message)| message)?;
+    let offset_minutes = settings
+        .utc_offset_minutes
+        .unwrap_or(DEFAULT_UTC_OFFSET_MINUTES);
+    let offset = UtcOffset::from_whole_seconds(offset_minutes * 60).unwrap_or(UtcOffset::UTC);
+    Ok(OffsetDateTime::now_utc().to_offset(offset).date())
+}
+
+/// Saves a record read by `fallback::parse_record_message` (`created_via = bot_command`,
+/// no AI provenance) and returns the `[RECORD_ADDED]` reply with the record's `seq`.
+pub async fn create_fallback_record(
+    db: &Db,
+    user_id: &str,
+    record: FallbackRecord,
+) -> Result<(String, i64), String> {
+    let provenance = RecordProvenance {
+        created_via: CREATED_VIA_BOT_COMMAND.to_string(),
+        model: None,
+        prompt_hash: None,
+        ai_category_confidence: None,
+    };
+    let payload = CreateRecordPayload {
+        name: record.name,
+        amount: record.amount,
+        currency: None,
+        category_id: record.category.id,
+        date: record.date,
+        original_amount: None,
+        original_currency: None,
+        note: None,
+        tags: Vec::new(),
+    };
+    let created =
+        records::create_records_for_user(db, user_id, vec![(payload, Some(provenance))], false)
+            .await
+            .map_err(closed_period_refusal)?
+            .remove(0);
+
+    let reply = format!(
+        "[RECORD_ADDED]\nid: {}\nname: {}\namount: {}\ncategory: {}\ndate: {}",
+        created.id,
+        created.name,
+        format_amount(created.amount, &created.currency),
+        record.category.name,
+        created.date
+    );
+    Ok((reply, created.seq))
+}
+
+// ---------------------------------------------------------------------------
+// Category helpers
+// ---------------------------------------------------------------------------
+
+pub async fn load_categories(db: &Db, user_id: &str) -> Result<Vec<CategoryInfo>, String> {
+    let conn = db.read().await;
+    let mut rows = conn
         .query(
-            "SELECT id, name, is_income FROM categories WHERE owner_user_id = ? AND LOWER(name) = LOWER(?)",
-            (user_id, fallback),
+            "SELECT id, name, is_income FROM categories WHERE owner_user_id = ? AND archived = FALSE ORDER BY name ASC",
+            [user_id],
         )
         .await
         .map_err(|_| "Failed to query categories".to_string())?;
 
-    if let Some(row) = existing
+    let mut categories = Vec::new();
+    while let Some(row) = rows
         .next()
         .await
         .map_err(|_| "Failed to query categories".to_string())?
@@ -131,26 +351,14 @@ pub async fn get_or_create_category(
         let id: String = row.get(0).map_err(|_| "Invalid category".to_string())?;
         let name: String = row.get(1).map_err(|_| "Invalid category".to_string())?;
         let is_income: bool = row.get(2).map_err(|_| "Invalid category".to_string())?;
-        return Ok(CategoryInfo {
+        categories.push(CategoryInfo {
             id,
             name,
             is_income,
         });
     }
 
-    let category_id = Uuid::new_v4().to_string();
-    conn.execute(
-        "INSERT INTO categories (id, owner_user_id, name, is_income) VALUES (?, ?, ?, ?)",
-        (category_id.as_str(), user_id, fallback, is_income),
-    )
-    .await
-    .map_err(|_| "Failed to create category".to_string())?;
-
-    Ok(CategoryInfo {
-        id: category_id,
-        name: fallback.to_string(),
-        is_income,
-    })
+    Ok(categories)
 }
 
 // ---------------------------------------------------------------------------
@@ -160,11 +368,19 @@ pub async fn get_or_create_category(
 #[derive(Deserialize)]
 struct CreateRecordToolInput {
     name: String,
-    amount: f64,
+    amount: Money,
+    currency: Option<String>,
     category_id: Option<String>,
     category_name: Option<String>,
     date: Option<String>,
     is_income: Option<bool>,
+    category_confidence: Option<f64>,
+    note: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CreateRecordsToolInput {
+    records: Vec<CreateRecordToolInput>,
 }
 
 #[derive(Default, Deserialize)]
@@ -173,7 +389,7 @@ struct EditRecordToolInput {
     record_id: Option<String>,
     record_name: Option<String>,
     name: Option<String>,
-    amount: Option<f64>,
+    amount: Option<Money>,
     category_id: Option<String>,
     category_name: Option<String>,
     date: Option<String>,
@@ -181,16 +397,39 @@ struct EditRecordToolInput {
 
 #[derive(Default, Deserialize)]
 #[serde(default)]
-struct ListRecordsToolInput {
+struct DeleteRecordToolInput {
+    record_id: Option<String>,
+    record_name: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct EditCategoryToolInput {
+    category_id: Option<String>,
+    category_name: Option<String>,
+    new_name: Option<String>,
+    new_is_income: Option<bool>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RecordFilterToolInput {
     start_date: Option<String>,
     end_date: Option<String>,
-    limit: Option<u32>,
-    offset: Option<u32>,
     category_id: Option<String>,
     category_name: Option<String>,
     name_contains: Option<String>,
-    min_amount: Option<f64>,
-    max_amount: Option<f64>,
+    min_amount: Option<Money>,
+    max_amount: Option<Money>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ListRecordsToolInput {
+    #[serde(flatten)]
+    filter: RecordFilterToolInput,
+    limit: Option<u32>,
+    offset: Option<u32>,
 }
 
 pub async fn execute_tool_call(
@@ -198,103 +437,174 @@ pub async fn execute_tool_call(
     user_id: &str,
     tool_name: &str,
     arguments: &str,
+    prompt_hash: &str,
+    turn: &mut TurnState,
 ) -> Result<serde_json::Value, String> {
     match tool_name {
         "create_record" => {
-            let input: CreateRecordToolInput = parse_tool_arguments(arguments)?;
-            create_record_tool(&state.main_db, user_id, input).await
+            let input: CreateRecordsToolInput = parse_tool_arguments(arguments)?;
+            let (output, seq) = create_record_tool(
+                &state.main_db,
+                user_id,
+                input.records,
+                &state.openai_model,
+                prompt_hash,
+            )
+            .await?;
+            turn.last_record_seq = Some(seq);
+            Ok(output)
         }
         "edit_record" => {
             let input: EditRecordToolInput = parse_tool_arguments(arguments)?;
-            edit_record_tool(&state.main_db, user_id, input).await
+            edit_record_tool(&state.main_db, user_id, input, turn.last_record_seq).await
+        }
+        "edit_category" => {
+            let input: EditCategoryToolInput = parse_tool_arguments(arguments)?;
+            let (output, pending) = edit_category_tool(&state.main_db, user_id, input).await?;
+            turn.pending_action = Some(PendingAction {
+                summary: pending.summary,
+                action: PendingActionType::CategoryEdit(pending.action),
+            });
+            Ok(output)
+        }
+        "delete_record" => {
+            let input: DeleteRecordToolInput = parse_tool_arguments(arguments)?;
+            let (output, pending) =
+                delete_record_tool(&state.main_db, user_id, input, turn.last_record_seq).await?;
+            turn.pending_action = Some(pending);
+            Ok(output)
         }
         "list_records" => {
             let input: ListRecordsToolInput = parse_tool_arguments(arguments)?;
             list_records_tool(&state.main_db, user_id, input).await
         }
+        "sum_records" => {
+            let input: RecordFilterToolInput = parse_tool_arguments(arguments)?;
+            sum_records_tool(&state.main_db, user_id, input).await
+        }
         _ => Err(format!("Unknown tool: {tool_name}")),
     }
 }
 
+/// The bot never reopens closed periods, so a 409 from the period guard is final.
+fn closed_period_refusal((status, message): (StatusCode, String)) -> String {
+    if status == StatusCode::CONFLICT {
+        format!("{message}. {PERIOD_CLOSED_BOT_HINT}")
+    } else {
+        message
+    }
+}
+
 fn parse_tool_arguments<T: for<'de> Deserialize<'de>>(arguments: &str) -> Result<T, String> {
     serde_json::from_str(arguments).map_err(|_| "Tool arguments are invalid JSON".to_string())
 }
 
+/// Creates every record of one `create_record` call together, so a message listing
+/// several expenses either lands whole or not at all. Returns the last record's `seq`.
 async fn create_record_tool(
     db: &Db,
     user_id: &str,
-    input: CreateRecordToolInput,
-) -> Result<serde_json::Value, String> {
-    let categories = load_categories(db, user_id).await?;
-    let category = resolve_or_create_category(
-        db,
-        user_id,
-        &categories,
-        input.category_id.as_deref(),
-        input.category_name.as_deref(),
-        input.is_income,
-    )
-    .await?;
-
-    let date = match input
-        .date
-        .as_deref()
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-    {
-        Some(value) => value.to_string(),
-        None => OffsetDateTime::now_utc().date().to_string(),
-    };
-    validate_date(&date).map_err(|(_, message)| message)?;
+    inputs: Vec<CreateRecordToolInput>,
+    model: &str,
+    prompt_hash: &str,
+) -> Result<(serde_json::Value, i64), String> {
+    if inputs.is_empty() {
+        return Err("Provide at least one record to create".to_string());
+    }
+    let mut categories = load_categories(db, user_id).await?;
+    let today = OffsetDateTime::now_utc().date().to_string();
+
+    let mut payloads = Vec::with_capacity(inputs.len());
+    let mut category_names = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let category = resolve_or_create_category(
+            db,
+            user_id,
+            &categories,
+            input.category_id.as_deref(),
+            input.category_name.as_deref(),
+            input.is_income,
+        )
+        .await?;
+        if !categories.iter().any(|known| known.id == category.id) {
+            categories.push(category.clone());
+        }
 
-    let payload = CreateRecordPayload {
-        name: input.name.trim().to_string(),
-        amount: input.amount,
-        category_id: category.id.clone(),
-        date,
-    };
+        let date = input
+            .date
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .unwrap_or(&today)
+            .to_string();
+        validate_date(&date).map_err(|(_, message)| message)?;
+
+        let provenance = RecordProvenance {
+            created_via: CREATED_VIA_BOT_AI.to_string(),
+            model: Some(model.to_string()),
+            prompt_hash: Some(prompt_hash.to_string()),
+            ai_category_confidence: input
+                .category_confidence
+                .filter(|confidence| (0.0..=1.0).contains(confidence)),
+        };
+        payloads.push((
+            CreateRecordPayload {
+                name: input.name.trim().to_string(),
+                amount: input.amount,
+                currency: input.currency,
+                category_id: category.id,
+                date,
+                original_amount: None,
+                original_currency: None,
+                note: input.note,
+                tags: Vec::new(),
+            },
+            Some(provenance),
+        ));
+        category_names.push(category.name);
+    }
 
-    let record = records::create_record_for_user(db, user_id, payload)
+    let created = records::create_records_for_user(db, user_id, payloads, false)
         .await
-        .map_err(|(_, message)| message)?;
-
-    Ok(json!({
-        "ok": true,
-        "record": {
-            "id": record.id,
-            "name": record.name,
-            "amount": record.amount,
-            "category_id": record.category_id,
-            "category_name": category.name,
-            "date": record.date,
-        }
-    }))
+        .map_err(closed_period_refusal)?;
+
+    let last_seq = created.last().map(|record| record.seq).unwrap_or_default();
+    let records: Vec<serde_json::Value> = created
+        .into_iter()
+        .zip(category_names)
+        .map(|(record, category_name)| {
+            json!({
+                "id": record.id,
+                "name": record.name,
+                "amount": record.amount,
+                "amount_display": format_amount(record.amount, &record.currency),
+                "category_id": record.category_id,
+                "category_name": category_name,
+                "date": record.date,
+                "note": record.note,
+                "seq": record.seq,
+            })
+        })
+        .collect();
+    Ok((json!({ "ok": true, "records": records }), last_seq))
 }
 
 async fn edit_record_tool(
     db: &Db,
     user_id: &str,
     input: EditRecordToolInput,
+    last_record_seq: Option<i64>,
 ) -> Result<serde_json::Value, String> {
     let categories = load_categories(db, user_id).await?;
 
-    let existing = if let Some(record_id) = input
-        .record_id
-        .as_deref()
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-    {
-        fetch_record_by_id(db, user_id, record_id).await?
-    } else if let Some(record_name) = input
-        .record_name
-        .as_deref()
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-    {
-        fetch_record_by_exact_name(db, user_id, record_name).await?
-    } else {
-        return Err("edit_record requires record_id or record_name".to_string());
-    };
+    let existing = resolve_target_record(
+        db,
+        user_id,
+        input.record_id.as_deref(),
+        input.record_name.as_deref(),
+        last_record_seq,
+    )
+    .await?;
 
     let new_name = input
         .name
@@ -363,6 +673,29 @@ async fn edit_record_tool(
 
     let conn = db.write().await;
 
+    let new_category = updated_category_id
+        .as_deref()
+        .filter(|category_id| Some(*category_id) != existing.category_id.as_deref())
+        .and_then(|category_id| {
+            categories
+                .iter()
+                .find(|category| category.id == category_id)
+                .map(|category| (category.id.as_str(), category.name.as_str()))
+        });
+    records::guard_edit_references(&conn, user_id, &existing, new_category)
+        .await
+        .map_err(|(_, message)| format!("{message}. {REFERENCE_DELETED_BOT_HINT}"))?;
+
+    let split_id = records::fetch_record_split_id(&conn, user_id, &existing.id)
+        .await
+        .map_err(|(_, message)| message)?;
+    records::guard_split_record_edit(
+        split_id.as_deref(),
+        records::amount_differs(input.amount, existing.amount),
+        updated_date != existing.date,
+    )
+    .map_err(|(_, message)| message)?;
+
     let updated_amount = if let Some(amount) = input.amount {
         if let Some(ref category_id) = updated_category_id {
             let is_income = get_category_is_income(&conn, user_id, category_id).await?;
@@ -375,7 +708,7 @@ async fn edit_record_tool(
     };
 
     let name_unchanged = updated_name == existing.name;
-    let amount_unchanged = (updated_amount - existing.amount).abs() < f64::EPSILON;
+    let amount_unchanged = updated_amount == existing.amount;
     let category_unchanged = updated_category_id == existing.category_id;
     let date_unchanged = updated_date == existing.date;
 
@@ -386,14 +719,29 @@ async fn edit_record_tool(
         );
     }
 
+    guard_closed_period(
+        &conn,
+        user_id,
+        Some(&existing.id),
+        "update",
+        &[&existing.date, &updated_date],
+        false,
+    )
+    .await
+    .map_err(closed_period_refusal)?;
+
+    let stored_name = crypto::seal_record_name(&conn, user_id, &updated_name)
+        .await
+        .map_err(|_| "Failed to update record".to_string())?;
+    let stored_date = to_db_date(&updated_date).map_err(|e| e.to_string())?;
     let affected_rows = conn
         .execute(
-            "UPDATE records SET name = ?, amount = ?, category_id = ?, date = ? WHERE id = ? AND owner_user_id = ?",
+            "UPDATE records SET name = ?, amount = ?, category_id = ?, date = ? WHERE id = ? AND owner_user_id = ? AND deleted_at IS NULL",
             (
-                updated_name.as_str(),
+                stored_name.as_str(),
                 updated_amount,
                 updated_category_id.as_deref(),
-                updated_date.as_str(),
+                stored_date,
                 existing.id.as_str(),
                 user_id,
             ),
@@ -421,157 +769,257 @@ async fn edit_record_tool(
             "id": existing.id,
             "name": updated_name,
             "amount": updated_amount,
+            "amount_display": format_amount_change(existing.amount, updated_amount, &existing.currency),
             "category_id": updated_category_id,
             "category_name": category_name,
             "date": updated_date,
+            "note": existing.note,
         }
     }))
 }
 
-async fn list_records_tool(
+/// Prepares a category change; nothing is written until the user sends `/confirm`.
+async fn edit_category_tool(
     db: &Db,
     user_id: &str,
-    input: ListRecordsToolInput,
-) -> Result<serde_json::Value, String> {
-    if let Some(start_date) = &input.start_date {
-        validate_date(start_date).map_err(|(_, message)| message)?;
-    }
-    if let Some(end_date) = &input.end_date {
-        validate_date(end_date).map_err(|(_, message)| message)?;
+    input: EditCategoryToolInput,
+) -> Result<(serde_json::Value, PendingCategoryEdit), String> {
+    let categories = load_categories(db, user_id).await?;
+    let category_id = resolve_category_id(
+        &categories,
+        input.category_id.as_deref().unwrap_or(""),
+        input.category_name.as_deref().unwrap_or(""),
+    )
+    .ok_or_else(|| {
+        format!(
+            "Category not found. Available categories: {}",
+            format_category_options(&categories)
+        )
+    })?;
+
+    let pending = categories::build_pending_category_edit(
+        db,
+        user_id,
+        &category_id,
+        input.new_name.as_deref(),
+        input.new_is_income,
+    )
+    .await
+    .map_err(|(_, message)| message)?;
+
+    let output = json!({
+        "ok": true,
+        "needs_confirmation": true,
+        "summary": pending.summary,
+    });
+    Ok((output, pending))
+}
+
+/// Prepares moving one record to the trash; nothing is deleted until the user sends
+/// `/confirm`. The target resolves like `edit_record`'s, so a name shared by several
+/// records asks for the record id.
+async fn delete_record_tool(
+    db: &Db,
+    user_id: &str,
+    input: DeleteRecordToolInput,
+    last_record_seq: Option<i64>,
+) -> Result<(serde_json::Value, PendingAction), String> {
+    let record = resolve_target_record(
+        db,
+        user_id,
+        input.record_id.as_deref(),
+        input.record_name.as_deref(),
+        last_record_seq,
+    )
+    .await?;
+
+    let summary = format!(
+        "Delete \"{}\" ({}, {}).",
+        record.name,
+        format_amount(record.amount, &record.currency),
+        record.date
+    );
+    let output = json!({
+        "ok": true,
+        "needs_confirmation": true,
+        "summary": summary,
+    });
+    let pending = PendingAction {
+        summary,
+        action: PendingActionType::RecordDelete {
+            record_id: record.id,
+            name: record.name,
+        },
+    };
+    Ok((output, pending))
+}
+
+/// Applies a change the user confirmed with `/confirm`, re-checking it first.
+pub async fn execute_pending_action(
+    db: &Db,
+    user_id: &str,
+    pending: &PendingAction,
+) -> Result<String, String> {
+    match &pending.action {
+        PendingActionType::CategoryEdit(action) => {
+            categories::execute_category_edit(db, user_id, action)
+                .await
+                .map_err(|(_, message)| message)
+        }
+        PendingActionType::RecordDelete { record_id, name } => {
+            records::delete_record_for_user(db, user_id, record_id, false)
+                .await
+                .map_err(|e| closed_period_refusal(e.into()))?;
+            Ok(format!(
+                "Deleted \"{name}\". It stays in the app's trash for {RECORD_TRASH_RETENTION_DAYS} days."
+            ))
+        }
     }
+}
 
-    let start_date = input.start_date.unwrap_or_else(|| "0000-01-01".to_string());
-    let end_date = input.end_date.unwrap_or_else(|| "9999-12-31".to_string());
+/// A `list_records`/`sum_records` filter with the category resolved to an id.
+struct ResolvedRecordFilter {
+    start_date: String,
+    end_date: String,
+    category_id: Option<String>,
+    name_contains: Option<String>,
+    min_amount: Option<Money>,
+    max_amount: Option<Money>,
+}
 
-    let limit = validate_records_limit(input.limit).map_err(|(_, message)| message)?;
-    let offset = validate_offset(input.offset).map_err(|(_, message)| message)?;
+impl ResolvedRecordFilter {
+    fn search(&self) -> RecordSearch<'_> {
+        RecordSearch {
+            start_date: &self.start_date,
+            end_date: &self.end_date,
+            category_id: self.category_id.as_deref(),
+            name_contains: self.name_contains.as_deref(),
+            min_amount: self.min_amount,
+            max_amount: self.max_amount,
+        }
+    }
 
-    if let (Some(min), Some(max)) = (input.min_amount, input.max_amount)
-        && min > max
-    {
-        return Err("min_amount cannot be greater than max_amount".to_string());
+    /// Echoes the applied filters back to the model, leaving out unset ones.
+    fn to_json(&self) -> serde_json::Value {
+        let mut filters = serde_json::Map::new();
+        filters.insert("start_date".to_string(), json!(self.start_date));
+        filters.insert("end_date".to_string(), json!(self.end_date));
+        if let Some(category_id) = &self.category_id {
+            filters.insert("category_id".to_string(), json!(category_id));
+        }
+        if let Some(name_contains) = &self.name_contains {
+            filters.insert("name_contains".to_string(), json!(name_contains));
+        }
+        if let Some(min_amount) = self.min_amount {
+            filters.insert("min_amount".to_string(), json!(min_amount));
+        }
+        if let Some(max_amount) = self.max_amount {
+            filters.insert("max_amount".to_string(), json!(max_amount));
+        }
+        serde_json::Value::Object(filters)
     }
-    let min_amount = input.min_amount.unwrap_or(-1.0e15);
-    let max_amount = input.max_amount.unwrap_or(1.0e15);
+}
 
-    let categories = load_categories(db, user_id).await?;
+fn resolve_record_filter(
+    categories: &[CategoryInfo],
+    input: RecordFilterToolInput,
+) -> Result<ResolvedRecordFilter, String> {
     let category_filter = resolve_category_filter_id(
-        &categories,
+        categories,
         input.category_id.as_deref(),
         input.category_name.as_deref(),
     )?;
 
-    let name_filter = input
-        .name_contains
-        .as_deref()
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-        .map(str::to_string)
-        .unwrap_or_default();
-
-    let conn = db.read().await;
+    Ok(ResolvedRecordFilter {
+        start_date: input.start_date.unwrap_or_else(|| "0000-01-01".to_string()),
+        end_date: input.end_date.unwrap_or_else(|| "9999-12-31".to_string()),
+        category_id: Some(category_filter).filter(|value| !value.is_empty()),
+        name_contains: input
+            .name_contains
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string),
+        min_amount: input.min_amount,
+        max_amount: input.max_amount,
+    })
+}
 
-    let mut count_rows = conn
-        .query(
-            "SELECT COUNT(*) FROM records \
-             WHERE owner_user_id = ? \
-             AND date BETWEEN ? AND ? \
-             AND (? = '' OR category_id = ?) \
-             AND (? = '' OR INSTR(LOWER(name), LOWER(?)) > 0) \
-             AND amount BETWEEN ? AND ?",
-            (
-                user_id,
-                start_date.as_str(),
-                end_date.as_str(),
-                category_filter.as_str(),
-                category_filter.as_str(),
-                name_filter.as_str(),
-                name_filter.as_str(),
-                min_amount,
-                max_amount,
-            ),
-        )
-        .await
-        .map_err(|_| "Failed to count records".to_string())?;
+async fn list_records_tool(
+    db: &Db,
+    user_id: &str,
+    input: ListRecordsToolInput,
+) -> Result<serde_json::Value, String> {
+    let categories = load_categories(db, user_id).await?;
+    let filter = resolve_record_filter(&categories, input.filter)?;
 
-    let total_count: u32 = if let Some(row) = count_rows
-        .next()
-        .await
-        .map_err(|_| "Failed to count records".to_string())?
-    {
-        row.get(0).map_err(|_| "Invalid record count".to_string())?
-    } else {
-        0
-    };
+    let page =
+        records::search_records_for_user(db, user_id, &filter.search(), input.limit, input.offset)
+            .await
+            .map_err(|(_, message)| message)?;
 
-    let mut rows = conn
-        .query(
-            "SELECT id, name, amount, category_id, date FROM records \
-             WHERE owner_user_id = ? \
-             AND date BETWEEN ? AND ? \
-             AND (? = '' OR category_id = ?) \
-             AND (? = '' OR INSTR(LOWER(name), LOWER(?)) > 0) \
-             AND amount BETWEEN ? AND ? \
-             ORDER BY date DESC, id DESC LIMIT ? OFFSET ?",
-            (
-                user_id,
-                start_date.as_str(),
-                end_date.as_str(),
-                category_filter.as_str(),
-                category_filter.as_str(),
-                name_filter.as_str(),
-                name_filter.as_str(),
-                min_amount,
-                max_amount,
-                i64::from(limit),
-                i64::from(offset),
-            ),
-        )
-        .await
-        .map_err(|_| "Failed to query records".to_string())?;
+    let category_name_map: HashMap<&str, &str> = categories
+        .iter()
+        .map(|category| (category.id.as_str(), category.name.as_str()))
+        .collect();
 
-    let category_name_map: HashMap<String, String> = categories
+    // Short keys keep large pages cheap in tokens; the tool description documents them.
+    let records_output: Vec<serde_json::Value> = page
+        .records
         .iter()
-        .map(|category| (category.id.clone(), category.name.clone()))
+        .map(|record| {
+            let mut item = serde_json::Map::new();
+            item.insert("id".to_string(), json!(record.id));
+            item.insert("n".to_string(), json!(record.name));
+            item.insert("a".to_string(), json!(record.amount));
+            if let Some(category_name) = record
+                .category_id
+                .as_deref()
+                .and_then(|category_id| category_name_map.get(category_id))
+            {
+                item.insert("c".to_string(), json!(category_name));
+            }
+            item.insert("d".to_string(), json!(record.date));
+            serde_json::Value::Object(item)
+        })
         .collect();
 
-    let mut records_output = Vec::new();
-    while let Some(row) = rows
-        .next()
-        .await
-        .map_err(|_| "Failed to query records".to_string())?
-    {
-        let record = records::extract_record_from_row(row).map_err(|(_, message)| message)?;
-        let category_name = record
-            .category_id
-            .as_ref()
-            .and_then(|cat_id| category_name_map.get(cat_id).cloned())
-            .unwrap_or_else(|| "Unknown".to_string());
-
-        records_output.push(json!({
-            "id": record.id,
-            "name": record.name,
-            "amount": record.amount,
-            "category_id": record.category_id,
-            "category_name": category_name,
-            "date": record.date,
-        }));
+    let mut output = json!({
+        "ok": true,
+        "total_count": page.total_count,
+        "returned": records_output.len(),
+        "offset": page.offset,
+        "truncated": page.truncated,
+        "filters": filter.to_json(),
+        "records": records_output
+    });
+    if page.truncated {
+        output["hint"] = json!(LIST_RECORDS_TRUNCATED_HINT);
     }
+    Ok(output)
+}
+
+async fn sum_records_tool(
+    db: &Db,
+    user_id: &str,
+    input: RecordFilterToolInput,
+) -> Result<serde_json::Value, String> {
+    let categories = load_categories(db, user_id).await?;
+    let filter = resolve_record_filter(&categories, input)?;
+
+    let totals = records::sum_records_for_user(db, user_id, &filter.search())
+        .await
+        .map_err(|(_, message)| message)?;
 
     Ok(json!({
         "ok": true,
-        "total_count": total_count,
-        "limit": limit,
-        "offset": offset,
-        "filters": {
-            "start_date": start_date,
-            "end_date": end_date,
-            "category_id": if category_filter.is_empty() { serde_json::Value::Null } else { json!(category_filter) },
-            "name_contains": if name_filter.is_empty() { serde_json::Value::Null } else { json!(name_filter) },
-            "min_amount": if input.min_amount.is_some() { json!(min_amount) } else { serde_json::Value::Null },
-            "max_amount": if input.max_amount.is_some() { json!(max_amount) } else { serde_json::Value::Null }
-        },
-        "records": records_output
+        "record_count": totals.record_count,
+        "income_count": totals.income_count,
+        "income_total": totals.income_total,
+        "expense_count": totals.expense_count,
+        "expense_total": totals.expense_total,
+        "net": totals.net(),
+        "filters": filter.to_json()
     }))
 }
 
@@ -617,7 +1065,14 @@ async fn resolve_or_create_category(
     if !provided_name.is_empty()
         && let Some(income_flag) = is_income
     {
-        return get_or_create_category(db, user_id, provided_name, income_flag).await;
+        let category = get_or_create_category(db, user_id, provided_name, income_flag)
+            .await
+            .map_err(|(_, message)| message)?;
+        return Ok(CategoryInfo {
+            id: category.id,
+            name: category.name,
+            is_income: category.is_income,
+        });
     }
 
     if categories.is_empty() {
@@ -656,25 +1111,10 @@ async fn fetch_record_by_exact_name(
     }
 
     let conn = db.read().await;
-
-    let mut rows = conn
-        .query(
-            "SELECT id, name, amount, category_id, date FROM records WHERE LOWER(name) = LOWER(?) AND owner_user_id = ? ORDER BY date DESC LIMIT 3",
-            (trimmed, user_id),
-        )
+    let mut matches = record_repo::find_by_exact_name(&conn, user_id, trimmed, 3)
         .await
         .map_err(|_| "Failed to query record by name".to_string())?;
 
-    let mut matches = Vec::new();
-    while let Some(row) = rows
-        .next()
-        .await
-        .map_err(|_| "Failed to query record by name".to_string())?
-    {
-        let record = records::extract_record_from_row(row).map_err(|(_, message)| message)?;
-        matches.push(record);
-    }
-
     match matches.len() {
         0 => Err("Record not found. Please include record_id.".to_string()),
         1 => Ok(matches.remove(0)),
@@ -713,11 +1153,115 @@ async fn get_category_is_income(
     }
 }
 
+/// The record a tool call targets: by id, else by exact name (asking for the id when
+/// several match), else the chat's last created record.
+async fn resolve_target_record(
+    db: &Db,
+    user_id: &str,
+    record_id: Option<&str>,
+    record_name: Option<&str>,
+    last_record_seq: Option<i64>,
+) -> Result<Record, String> {
+    if let Some(record_id) = record_id.map(str::trim).filter(|value| !value.is_empty()) {
+        fetch_record_by_id(db, user_id, record_id).await
+    } else if let Some(record_name) = record_name.map(str::trim).filter(|value| !value.is_empty()) {
+        fetch_record_by_exact_name(db, user_id, record_name).await
+    } else {
+        fetch_last_created_record(db, user_id, last_record_seq).await
+    }
+}
+
+/// Resolves "the record I just added": the one this chat created last, or else the
+/// user's newest record by `seq`. Never guesses from dates, and never substitutes
+/// another record when the chat's last one has been deleted.
+async fn fetch_last_created_record(
+    db: &Db,
+    user_id: &str,
+    last_record_seq: Option<i64>,
+) -> Result<Record, String> {
+    if let Some(seq) = last_record_seq {
+        // Falling back to the newest record here would edit a record the user never meant.
+        return records::fetch_record_by_seq(db, user_id, seq)
+            .await
+            .map_err(|(_, message)| message)?
+            .ok_or_else(|| LAST_RECORD_DELETED_BOT_HINT.to_string());
+    }
+
+    records::list_recent_records(db, user_id, 1)
+        .await
+        .map_err(|(_, message)| message)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| "No records yet. Please include record_id.".to_string())
+}
+
+pub async fn fetch_recent_record_lines(
+    db: &Db,
+    user_id: &str,
+    limit: u32,
+) -> Result<Vec<String>, String> {
+    records::recent_record_lines(db, user_id, limit)
+        .await
+        .map_err(|(_, message)| message)
+}
+
+/// Income, expenses and per-category totals for `month` (`YYYY-MM`), summed in SQL by
+/// `records::summarize_records_for_user`. Without a month it is the current one in the
+/// user's `utc_offset_minutes`.
+pub async fn fetch_month_summary(
+    db: &Db,
+    user_id: &str,
+    month: Option<&str>,
+) -> Result<MonthSummary, String> {
+    let conn = db.read().await;
+    let settings = fetch_user_settings(&conn, user_id)
+        .await
+        .map_err(|(_, message)| message)?;
+    let month = match month {
+        Some(month) => month.to_string(),
+        None => stats::local_month(
+            OffsetDateTime::now_utc(),
+            settings
+                .utc_offset_minutes
+                .unwrap_or(DEFAULT_UTC_OFFSET_MINUTES),
+        ),
+    };
+    let (start_date, end_date) = stats::month_date_range(&month)
+        .map_err(|_| "Usage: /summary or /summary YYYY-MM.".to_string())?;
+    let totals = records::summarize_records_for_user(
+        &conn,
+        user_id,
+        RecordSummaryQuery {
+            start_date: start_date.clone(),
+            end_date,
+            include_pending: None,
+        },
+    )
+    .await
+    .map_err(|(_, message)| message)?;
+
+    Ok(MonthSummary {
+        month: start_date[..7].to_string(),
+        totals,
+    })
+}
+
+/// This month's AI categorization accuracy for the `/summary` command.
+pub async fn fetch_ai_accuracy_this_month(
+    db: &Db,
+    user_id: &str,
+) -> Result<AiAccuracyResponse, String> {
+    let conn = db.read().await;
+    stats::ai_accuracy_for_user(&conn, user_id, 1, &stats::current_month())
+        .await
+        .map_err(|(_, message)| message)
+}
+
 pub async fn fetch_record_by_id(db: &Db, user_id: &str, record_id: &str) -> Result<Record, String> {
     let conn = db.read().await;
     let mut rows = conn
         .query(
-            "SELECT id, name, amount, category_id, date FROM records WHERE id = ? AND owner_user_id = ?",
+            &format!("SELECT {RECORD_COLUMNS} FROM records WHERE id = ? AND owner_user_id = ? AND deleted_at IS NULL"),
             (record_id, user_id),
         )
         .await
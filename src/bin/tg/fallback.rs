@@ -0,0 +1,118 @@
+use kash_server::money::Money;
+use time::{Date, Duration};
+
+use crate::models::CategoryInfo;
+
+// Deterministic record parsing for when OpenAI is unavailable or turned off
+// (`BOT_FALLBACK_PARSER_ONLY`). Only "name amount [date] [category]" is understood;
+// everything else gets `FALLBACK_CLARIFICATION`.
+
+/// Currency prefixes and suffixes allowed around an amount (`$180`, `NT$180`, `180元`).
+const AMOUNT_PREFIXES: [&str; 6] = ["NT$", "HK$", "$", "¥", "€", "£"];
+const AMOUNT_SUFFIXES: [&str; 2] = ["元", "塊"];
+
+/// A record read from a message such as "lunch 180" or "taxi 250 yesterday".
+pub struct FallbackRecord {
+    pub name: String,
+    /// Unsigned; the category decides income or expense.
+    pub amount: Money,
+    /// `YYYY-MM-DD`.
+    pub date: String,
+    pub category: CategoryInfo,
+}
+
+pub enum FallbackParse {
+    Record(FallbackRecord),
+    /// The message looked like a record, but no category matched.
+    NeedsCategory,
+    NotARecord,
+}
+
+/// Reads "name amount [date] [category]". The amount is the last number in the message;
+/// only a date word (`today`, `yesterday`, `YYYY-MM-DD`) and a category name may follow
+/// it. Without an explicit category, one whose name appears as a word of the record name
+/// is used.
+pub fn parse_record_message(text: &str, categories: &[CategoryInfo], today: Date) -> FallbackParse {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let Some(amount_index) = words.iter().rposition(|word| parse_amount(word).is_some()) else {
+        return FallbackParse::NotARecord;
+    };
+    let amount = parse_amount(words[amount_index]).unwrap_or_default();
+    let name_words = &words[..amount_index];
+    if name_words.is_empty() {
+        return FallbackParse::NotARecord;
+    }
+
+    let mut date = None;
+    let mut category = None;
+    for word in &words[amount_index + 1..] {
+        if date.is_none()
+            && let Some(parsed) = parse_date_word(word, today)
+        {
+            date = Some(parsed);
+        } else if category.is_none()
+            && let Some(found) = find_category(categories, word)
+        {
+            category = Some(found);
+        } else {
+            return FallbackParse::NotARecord;
+        }
+    }
+
+    let category = category.or_else(|| {
+        name_words
+            .iter()
+            .find_map(|word| find_category(categories, word))
+    });
+    let Some(category) = category else {
+        return FallbackParse::NeedsCategory;
+    };
+
+    FallbackParse::Record(FallbackRecord {
+        name: name_words.join(" "),
+        amount,
+        date: date.unwrap_or_else(|| today.to_string()),
+        category: category.clone(),
+    })
+}
+
+fn parse_amount(word: &str) -> Option<Money> {
+    let mut digits = word.trim_start_matches('+');
+    for prefix in AMOUNT_PREFIXES {
+        digits = digits.strip_prefix(prefix).unwrap_or(digits);
+    }
+    for suffix in AMOUNT_SUFFIXES {
+        digits = digits.strip_suffix(suffix).unwrap_or(digits);
+    }
+    if digits.is_empty()
+        || !digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+    {
+        return None;
+    }
+    digits
+        .replace(',', "")
+        .parse::<Money>()
+        .ok()
+        .filter(|amount| amount.is_positive())
+}
+
+fn parse_date_word(word: &str, today: Date) -> Option<String> {
+    match word.to_lowercase().as_str() {
+        "today" | "今天" => Some(today.to_string()),
+        "yesterday" | "昨天" => Some((today - Duration::days(1)).to_string()),
+        other
+            if other.len() == 10 && other.as_bytes()[4] == b'-' && other.as_bytes()[7] == b'-' =>
+        {
+            Some(other.to_string())
+        }
+        _ => None,
+    }
+}
+
+fn find_category<'a>(categories: &'a [CategoryInfo], word: &str) -> Option<&'a CategoryInfo> {
+    categories
+        .iter()
+        .find(|category| category.name.eq_ignore_ascii_case(word))
+}
@@ -1,16 +1,33 @@
+use std::time::Instant;
+
+use axum::http::StatusCode;
 use base64::Engine as _;
 use teloxide::prelude::*;
 use teloxide::types::ChatAction;
 
-use kash_server::auth;
+use kash_server::constants::{JOB_TYPE_DRAIN_OUTBOX, LOGIN_SOURCE_TELEGRAM};
+use kash_server::jobs::{JobFuture, JobHandler};
+use kash_server::rate_limit::{login_key, retry_after_secs};
+use kash_server::{Db, auth, outbox, two_factor};
 
-use crate::constants::{MAX_PHOTO_FILE_SIZE, MAX_VOICE_FILE_SIZE};
-use crate::db::{fetch_linked_user_id, load_categories, upsert_telegram_link};
+use crate::constants::{
+    FALLBACK_CLARIFICATION, MAX_PHOTO_FILE_SIZE, MAX_VOICE_FILE_SIZE, OUTBOX_POLL_INTERVAL_SECS,
+    RECENT_RECORDS_LIMIT,
+};
+use crate::db::{
+    create_fallback_record, delete_telegram_link, execute_pending_action,
+    fetch_ai_accuracy_this_month, fetch_linked_user_id, fetch_linked_username, fetch_month_summary,
+    fetch_pending_action_owner, fetch_recent_record_lines, fetch_user_today, load_categories,
+    purge_expired_pending_actions, save_pending_action, take_pending_action, upsert_telegram_link,
+};
+use crate::fallback::{FallbackParse, parse_record_message};
 use crate::helpers::{
-    cleanup_expired_contexts, get_context_messages, push_context_turn, telegram_user_id,
+    cleanup_expired_contexts, forget_contexts, format_ai_accuracy_summary, format_month_summary,
+    get_context_messages, get_last_record_seq, parse_pending_action_callback,
+    pending_action_keyboard, push_context_turn, telegram_user_id,
 };
-use crate::models::{BotError, BotState, ContextKey};
-use crate::openai::{respond_with_tools, transcribe_voice};
+use crate::models::{BotError, BotState, CategoryInfo, ContextKey, TurnState};
+use crate::openai::{RespondError, respond_with_tools, transcribe_voice};
 
 // ---------------------------------------------------------------------------
 // Top-level message dispatcher
@@ -18,6 +35,8 @@ use crate::openai::{respond_with_tools, transcribe_voice};
 
 pub async fn handle_message(bot: Bot, msg: Message, state: BotState) -> Result<(), BotError> {
     cleanup_expired_contexts(&state).await;
+    // Best effort: take_pending_action ignores expired rows anyway.
+    let _ = purge_expired_pending_actions(&state.main_db).await;
 
     if let Some(text) = msg.text() {
         return handle_text_message(&bot, &msg, &state, text.trim().to_string()).await;
@@ -60,6 +79,37 @@ async fn handle_text_message(
         }
     };
 
+    if text.eq_ignore_ascii_case("/unlink") {
+        return handle_unlink(bot, msg.chat.id, state, tg_user_id).await;
+    }
+
+    if text.eq_ignore_ascii_case("/whoami") {
+        return handle_whoami(bot, msg.chat.id, state, tg_user_id).await;
+    }
+
+    if text.eq_ignore_ascii_case("/recent") {
+        return handle_recent(bot, msg.chat.id, state, tg_user_id).await;
+    }
+
+    let mut words = text.split_whitespace();
+    if words
+        .next()
+        .is_some_and(|command| command.eq_ignore_ascii_case("/summary"))
+    {
+        let (month, extra) = (words.next(), words.next());
+        if extra.is_some() {
+            bot.send_message(msg.chat.id, "Usage: /summary or /summary YYYY-MM.")
+                .await?;
+            return Ok(());
+        }
+        return handle_summary(bot, msg.chat.id, state, tg_user_id, month).await;
+    }
+
+    if text.eq_ignore_ascii_case("/confirm") || text.eq_ignore_ascii_case("/cancel") {
+        let confirmed = text.eq_ignore_ascii_case("/confirm");
+        return handle_pending_action(bot, msg.chat.id, state, tg_user_id, confirmed).await;
+    }
+
     handle_ai_turn(bot, msg.chat.id, state, tg_user_id, &text, None, &text).await
 }
 
@@ -80,6 +130,15 @@ async fn handle_voice_message(bot: &Bot, msg: &Message, state: &BotState) -> Res
         return Ok(());
     }
 
+    let Some(api_key) = state.openai_api_key.as_deref() else {
+        bot.send_message(
+            msg.chat.id,
+            "Voice messages need OpenAI, which is turned off. Please type the record instead.",
+        )
+        .await?;
+        return Ok(());
+    };
+
     if voice.file.size as usize > MAX_VOICE_FILE_SIZE {
         bot.send_message(msg.chat.id, "Voice message is too large (max 3MB).")
             .await?;
@@ -103,14 +162,7 @@ async fn handle_voice_message(bot: &Bot, msg: &Message, state: &BotState) -> Res
 
     send_typing(bot, msg.chat.id).await;
 
-    let transcript = match transcribe_voice(
-        &state.http,
-        &state.openai_api_key,
-        audio_bytes,
-        "voice.oga",
-    )
-    .await
-    {
+    let transcript = match transcribe_voice(&state.http, api_key, audio_bytes, "voice.oga").await {
         Ok(text) if !text.trim().is_empty() => text,
         Ok(_) => {
             bot.send_message(
@@ -241,43 +293,321 @@ async fn handle_ai_turn(
 
     let context_key: ContextKey = (chat_id.0, tg_user_id);
     let history = get_context_messages(state, context_key).await;
+    let mut turn = TurnState {
+        last_record_seq: get_last_record_seq(state, context_key).await,
+        ..TurnState::default()
+    };
 
     send_typing(bot, chat_id).await;
-    let response = match respond_with_tools(
+    let mut response = match respond_with_tools(
         state,
         &user_id,
         text,
         image_data_url,
         &categories,
         &history,
+        &mut turn,
     )
     .await
     {
         Ok(message) if !message.trim().is_empty() => message,
         Ok(_) => "Done.".to_string(),
-        Err(message) => message,
+        Err(RespondError::Failed(message)) => message,
+        Err(RespondError::Unavailable(message)) => {
+            if state.openai_api_key.is_some() {
+                println!("OpenAI unavailable, using the fallback parser: {}", message);
+            }
+            fallback_reply(state, &user_id, text, &categories, &mut turn).await
+        }
     };
+    let mut keyboard = None;
+    if let Some(pending) = &turn.pending_action {
+        match save_pending_action(&state.main_db, tg_user_id, &user_id, pending).await {
+            Ok(action_id) => keyboard = Some(pending_action_keyboard(&action_id)),
+            Err(message) => {
+                response = format!("{message}. Nothing was changed; please ask again.");
+            }
+        }
+    }
 
-    bot.send_message(chat_id, &response).await?;
-    push_context_turn(state, context_key, context_input, &response).await;
+    let reply = bot.send_message(chat_id, &response);
+    match keyboard {
+        Some(keyboard) => reply.reply_markup(keyboard).await?,
+        None => reply.await?,
+    };
+    push_context_turn(
+        state,
+        context_key,
+        context_input,
+        &response,
+        turn.last_record_seq,
+    )
+    .await;
 
     Ok(())
 }
 
+/// Handles a message without OpenAI: one "name amount [date]" record, or the usual
+/// clarification prompt for anything else.
+async fn fallback_reply(
+    state: &BotState,
+    user_id: &str,
+    text: &str,
+    categories: &[CategoryInfo],
+    turn: &mut TurnState,
+) -> String {
+    let today = match fetch_user_today(&state.main_db, user_id).await {
+        Ok(today) => today,
+        Err(message) => return message,
+    };
+
+    match parse_record_message(text, categories, today) {
+        FallbackParse::Record(record) => {
+            match create_fallback_record(&state.main_db, user_id, record).await {
+                Ok((reply, seq)) => {
+                    turn.last_record_seq = Some(seq);
+                    reply
+                }
+                Err(message) => format!("Nothing was saved: {message}"),
+            }
+        }
+        FallbackParse::NeedsCategory if categories.is_empty() => {
+            "Add a category in the app first, then send the record again.".to_string()
+        }
+        FallbackParse::NeedsCategory => format!(
+            "Which category? Put it after the amount, e.g. \"lunch 180 {}\". Categories: {}.",
+            categories[0].name,
+            categories
+                .iter()
+                .map(|category| category.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        FallbackParse::NotARecord => FALLBACK_CLARIFICATION.to_string(),
+    }
+}
+
 async fn send_typing(bot: &Bot, chat_id: ChatId) {
     let _ = bot.send_chat_action(chat_id, ChatAction::Typing).await;
 }
 
+// ---------------------------------------------------------------------------
+// /recent
+// ---------------------------------------------------------------------------
+
+async fn handle_recent(
+    bot: &Bot,
+    chat_id: ChatId,
+    state: &BotState,
+    tg_user_id: i64,
+) -> Result<(), BotError> {
+    let user_id = match fetch_linked_user_id(&state.main_db, tg_user_id).await {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => {
+            send_help(bot, chat_id).await?;
+            return Ok(());
+        }
+        Err(message) => {
+            bot.send_message(chat_id, message).await?;
+            return Ok(());
+        }
+    };
+
+    let message =
+        match fetch_recent_record_lines(&state.main_db, &user_id, RECENT_RECORDS_LIMIT).await {
+            Ok(lines) if lines.is_empty() => "No records yet.".to_string(),
+            Ok(lines) => lines.join("\n"),
+            Err(message) => message,
+        };
+
+    bot.send_message(chat_id, message).await?;
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// /summary
+// ---------------------------------------------------------------------------
+
+async fn handle_summary(
+    bot: &Bot,
+    chat_id: ChatId,
+    state: &BotState,
+    tg_user_id: i64,
+    month: Option<&str>,
+) -> Result<(), BotError> {
+    let user_id = match fetch_linked_user_id(&state.main_db, tg_user_id).await {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => {
+            send_help(bot, chat_id).await?;
+            return Ok(());
+        }
+        Err(message) => {
+            bot.send_message(chat_id, message).await?;
+            return Ok(());
+        }
+    };
+
+    let mut message = match fetch_month_summary(&state.main_db, &user_id, month).await {
+        Ok(summary) => format_month_summary(&summary),
+        Err(message) => message,
+    };
+    // AI accuracy is only tracked for the current month.
+    if month.is_none() {
+        let accuracy = match fetch_ai_accuracy_this_month(&state.main_db, &user_id).await {
+            Ok(report) => format_ai_accuracy_summary(&report),
+            Err(message) => message,
+        };
+        message.push_str("\n\n");
+        message.push_str(&accuracy);
+    }
+
+    bot.send_message(chat_id, message).await?;
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// /confirm and /cancel
+// ---------------------------------------------------------------------------
+
+async fn handle_pending_action(
+    bot: &Bot,
+    chat_id: ChatId,
+    state: &BotState,
+    tg_user_id: i64,
+    confirmed: bool,
+) -> Result<(), BotError> {
+    let user_id = match fetch_linked_user_id(&state.main_db, tg_user_id).await {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => {
+            send_help(bot, chat_id).await?;
+            return Ok(());
+        }
+        Err(message) => {
+            bot.send_message(chat_id, message).await?;
+            return Ok(());
+        }
+    };
+
+    let message =
+        decide_pending_action(state, chat_id, tg_user_id, &user_id, confirmed, None).await;
+    bot.send_message(chat_id, &message).await?;
+    Ok(())
+}
+
+/// ✅ Confirm / ❌ Cancel buttons under a pending change. Decides like `/confirm` and
+/// `/cancel`, then replaces the buttons with the outcome so they cannot be pressed twice.
+pub async fn handle_callback_query(
+    bot: Bot,
+    query: CallbackQuery,
+    state: BotState,
+) -> Result<(), BotError> {
+    let (Some((confirmed, action_id)), Some(message)) = (
+        query
+            .data
+            .as_deref()
+            .and_then(parse_pending_action_callback),
+        query.regular_message(),
+    ) else {
+        bot.answer_callback_query(query.id.clone()).await?;
+        return Ok(());
+    };
+    let chat_id = message.chat.id;
+    let tg_user_id = match i64::try_from(query.from.id.0) {
+        Ok(value) => value,
+        Err(_) => {
+            bot.answer_callback_query(query.id.clone())
+                .text("Invalid Telegram user id.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    match fetch_pending_action_owner(&state.main_db, action_id).await {
+        Ok(Some(owner)) if owner != tg_user_id => {
+            bot.answer_callback_query(query.id.clone())
+                .text("Only the person who asked can decide this change.")
+                .await?;
+            return Ok(());
+        }
+        Ok(_) => {}
+        Err(message) => {
+            bot.answer_callback_query(query.id.clone())
+                .text(message)
+                .await?;
+            return Ok(());
+        }
+    }
+    bot.answer_callback_query(query.id.clone()).await?;
+
+    let user_id = match fetch_linked_user_id(&state.main_db, tg_user_id).await {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => {
+            send_help(&bot, chat_id).await?;
+            return Ok(());
+        }
+        Err(message) => {
+            bot.send_message(chat_id, message).await?;
+            return Ok(());
+        }
+    };
+
+    let outcome = decide_pending_action(
+        &state,
+        chat_id,
+        tg_user_id,
+        &user_id,
+        confirmed,
+        Some(action_id),
+    )
+    .await;
+    let text = match message.text() {
+        Some(original) => format!("{original}\n\n{outcome}"),
+        None => outcome,
+    };
+    // Editing without `reply_markup` also removes the buttons.
+    bot.edit_message_text(chat_id, message.id, text).await?;
+    Ok(())
+}
+
+/// Takes the pending change and applies or drops it, returning the reply. Shared by the
+/// text commands and the buttons; `action_id` limits it to the change a button belongs to.
+async fn decide_pending_action(
+    state: &BotState,
+    chat_id: ChatId,
+    tg_user_id: i64,
+    user_id: &str,
+    confirmed: bool,
+    action_id: Option<&str>,
+) -> String {
+    let context_key: ContextKey = (chat_id.0, tg_user_id);
+    let command = if confirmed { "/confirm" } else { "/cancel" };
+    let message = match take_pending_action(&state.main_db, tg_user_id, user_id, action_id).await {
+        Err(message) => message,
+        Ok(None) => "Nothing is waiting for confirmation.".to_string(),
+        Ok(Some(_)) if !confirmed => "Cancelled. Nothing was changed.".to_string(),
+        Ok(Some(pending)) => execute_pending_action(&state.main_db, user_id, &pending)
+            .await
+            .unwrap_or_else(|message| format!("Nothing was changed: {message}")),
+    };
+
+    push_context_turn(state, context_key, command, &message, None).await;
+    message
+}
+
 // ---------------------------------------------------------------------------
 // /start help
 // ---------------------------------------------------------------------------
 
 async fn send_help(bot: &Bot, chat_id: ChatId) -> Result<(), BotError> {
-    let message = "Hi! Link your account with /link <username> <password>.\n\
+    let message = "Hi! Link your account with /link <username> <password> [two-factor code].\n\
                    Then ask naturally, for example:\n\
                    - create: lunch 180 today\n\
                    - edit: change taxi amount to 220\n\
-                   - list: show my records from this week";
+                   - list: show my records from this week\n\
+                   - delete: delete the taxi record, then /confirm\n\
+                   - category: make Freelance an income category, then /confirm\n\
+                   Use /recent to see your latest records and /summary (or /summary 2024-05) for a month's totals.\n\
+                   /whoami shows the linked account; /unlink disconnects it.";
     bot.send_message(chat_id, message).await?;
     Ok(())
 }
@@ -291,18 +621,12 @@ async fn handle_link(bot: &Bot, msg: &Message, state: &BotState) -> Result<(), B
     let mut parts = text.split_whitespace();
     let _ = parts.next();
     let (Some(username), Some(password)) = (parts.next(), parts.next()) else {
-        bot.send_message(msg.chat.id, "Usage: /link <username> <password>.")
+        bot.send_message(msg.chat.id, "Usage: /link <username> <password> [code].")
             .await?;
         return Ok(());
     };
-
-    let user = match auth::authenticate_user(&state.main_db, username, password).await {
-        Ok(user) => user,
-        Err((_, message)) => {
-            bot.send_message(msg.chat.id, message).await?;
-            return Ok(());
-        }
-    };
+    // Needed only when the account has two-factor login on
+    let code = parts.next();
 
     let tg_user_id = match telegram_user_id(msg) {
         Ok(value) => value,
@@ -312,6 +636,74 @@ async fn handle_link(bot: &Bot, msg: &Message, state: &BotState) -> Result<(), B
         }
     };
 
+    let key = login_key(&format!("telegram:{}", tg_user_id), username);
+    if let Err(wait) = state.link_rate_limiter.check(&key, Instant::now()) {
+        let message = format!(
+            "Too many failed attempts. Try /link again in {} seconds.",
+            retry_after_secs(wait)
+        );
+        bot.send_message(msg.chat.id, message).await?;
+        return Ok(());
+    }
+
+    let user =
+        match auth::check_credentials(&state.main_db, username, password, LOGIN_SOURCE_TELEGRAM)
+            .await
+        {
+            Ok(user) => user,
+            Err((status, message)) => {
+                if status == StatusCode::UNAUTHORIZED {
+                    let _ = state.link_rate_limiter.consume(&key, Instant::now());
+                }
+                bot.send_message(msg.chat.id, message).await?;
+                return Ok(());
+            }
+        };
+
+    // The password alone links only accounts without two-factor login
+    let second_factor = match two_factor::is_enabled(&state.main_db, &user.id).await {
+        Ok(true) => match code {
+            Some(code) => {
+                two_factor::verify_login_code(
+                    &state.main_db,
+                    &user.id,
+                    username,
+                    LOGIN_SOURCE_TELEGRAM,
+                    code,
+                )
+                .await
+            }
+            None => {
+                bot.send_message(
+                    msg.chat.id,
+                    "This account uses two-factor login. \
+                     Send /link <username> <password> <code> with a code from your app.",
+                )
+                .await?;
+                return Ok(());
+            }
+        },
+        Ok(false) => auth::record_attempt(&state.main_db, username, LOGIN_SOURCE_TELEGRAM, None)
+            .await
+            .map(|()| true)
+            .map_err(Into::into),
+        Err(error) => Err(error),
+    };
+    match second_factor {
+        Ok(true) => {}
+        Ok(false) => {
+            let _ = state.link_rate_limiter.consume(&key, Instant::now());
+            bot.send_message(msg.chat.id, "Invalid two-factor code.")
+                .await?;
+            return Ok(());
+        }
+        Err(error) => {
+            bot.send_message(msg.chat.id, error.message).await?;
+            return Ok(());
+        }
+    }
+    state.link_rate_limiter.reset(&key);
+
     let chat_id = msg.chat.id.0;
     if let Err(message) = upsert_telegram_link(&state.main_db, tg_user_id, chat_id, &user.id).await
     {
@@ -324,6 +716,48 @@ async fn handle_link(bot: &Bot, msg: &Message, state: &BotState) -> Result<(), B
     Ok(())
 }
 
+// ---------------------------------------------------------------------------
+// /unlink and /whoami
+// ---------------------------------------------------------------------------
+
+async fn handle_unlink(
+    bot: &Bot,
+    chat_id: ChatId,
+    state: &BotState,
+    tg_user_id: i64,
+) -> Result<(), BotError> {
+    let message = match delete_telegram_link(&state.main_db, tg_user_id).await {
+        Ok(true) => {
+            forget_contexts(state, tg_user_id).await;
+            "Unlinked. I can no longer read or change your budget; use /link to connect again."
+                .to_string()
+        }
+        Ok(false) => {
+            "This Telegram account isn't linked, so there is nothing to unlink.".to_string()
+        }
+        Err(message) => message,
+    };
+
+    bot.send_message(chat_id, message).await?;
+    Ok(())
+}
+
+async fn handle_whoami(
+    bot: &Bot,
+    chat_id: ChatId,
+    state: &BotState,
+    tg_user_id: i64,
+) -> Result<(), BotError> {
+    let message = match fetch_linked_username(&state.main_db, tg_user_id).await {
+        Ok(Some(username)) => format!("Linked to {username}."),
+        Ok(None) => "Not linked. Use /link <username> <password>.".to_string(),
+        Err(message) => message,
+    };
+
+    bot.send_message(chat_id, message).await?;
+    Ok(())
+}
+
 async fn download_telegram_file_bytes(
     bot: &Bot,
     http: &reqwest::Client,
@@ -387,3 +821,88 @@ async fn ensure_user_is_linked(
         }
     }
 }
+
+// ---------------------------------------------------------------------------
+// Outbox delivery
+// ---------------------------------------------------------------------------
+
+/// Recurring job sending due outbox messages every `OUTBOX_POLL_INTERVAL_SECS`: budget
+/// alerts as one message per user, split notices one by one.
+pub struct OutboxDrainJob {
+    pub bot: Bot,
+    pub state: BotState,
+}
+
+impl JobHandler for OutboxDrainJob {
+    fn job_type(&self) -> &'static str {
+        JOB_TYPE_DRAIN_OUTBOX
+    }
+
+    fn interval(&self) -> Option<time::Duration> {
+        Some(time::Duration::seconds(OUTBOX_POLL_INTERVAL_SECS as i64))
+    }
+
+    fn run<'a>(
+        &'a self,
+        _db: &'a Db,
+        _payload: &'a serde_json::Value,
+        now: time::OffsetDateTime,
+    ) -> JobFuture<'a> {
+        Box::pin(async move {
+            drain_outbox(&self.bot, &self.state, now)
+                .await
+                .map_err(|e| e.to_string())
+        })
+    }
+}
+
+async fn drain_outbox(
+    bot: &Bot,
+    state: &BotState,
+    now: time::OffsetDateTime,
+) -> Result<(), BotError> {
+    let messages = {
+        let conn = state.main_db.read().await;
+        let mut messages = outbox::due_budget_alert_messages(&conn, now)
+            .await
+            .map_err(|(_, message)| message)?;
+        messages.extend(
+            outbox::due_split_notice_messages(&conn, now)
+                .await
+                .map_err(|(_, message)| message)?,
+        );
+        messages
+    };
+
+    let mut undelivered = 0;
+    for message in messages {
+        // Users who unlinked Telegram have nowhere to receive the message; drop it.
+        let mut sent = true;
+        for chat_id in &message.chat_ids {
+            let Ok(chat_id) = chat_id.parse::<i64>() else {
+                continue;
+            };
+            if let Err(e) = bot
+                .send_message(ChatId(chat_id), message.text.as_str())
+                .await
+            {
+                println!("Outbox: sending to user {} failed: {}", message.user_id, e);
+                sent = false;
+            }
+        }
+        if !sent {
+            // Left undelivered so the next run retries.
+            undelivered += 1;
+            continue;
+        }
+
+        let conn = state.main_db.write().await;
+        outbox::mark_delivered(&conn, &message.entry_ids, now)
+            .await
+            .map_err(|(_, message)| message)?;
+    }
+    if undelivered > 0 {
+        return Err(format!("{undelivered} outbox message(s) could not be sent").into());
+    }
+    Ok(())
+}
@@ -1,5 +1,13 @@
-use crate::models::{BotState, CategoryInfo, ChatContext, ContextKey};
 use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+use kash_server::models::AiAccuracyResponse;
+use kash_server::money::{Money, format_amount};
+
+use crate::constants::{
+    CANCEL_CALLBACK_PREFIX, CONFIRM_CALLBACK_PREFIX, SUMMARY_TOP_EXPENSE_CATEGORIES,
+};
+use crate::models::{BotState, CategoryInfo, ChatContext, ContextKey, MonthSummary};
 
 // ---------------------------------------------------------------------------
 // Telegram user helpers
@@ -14,11 +22,122 @@ pub fn telegram_user_id(msg: &Message) -> Result<i64, String> {
     i64::try_from(user.id.0).map_err(|_| "Invalid Telegram user id.".to_string())
 }
 
+// ---------------------------------------------------------------------------
+// Pending-change buttons
+// ---------------------------------------------------------------------------
+
+/// ✅ Confirm and ❌ Cancel buttons for the pending change `action_id`.
+pub fn pending_action_keyboard(action_id: &str) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([[
+        InlineKeyboardButton::callback(
+            "✅ Confirm",
+            format!("{CONFIRM_CALLBACK_PREFIX}{action_id}"),
+        ),
+        InlineKeyboardButton::callback("❌ Cancel", format!("{CANCEL_CALLBACK_PREFIX}{action_id}")),
+    ]])
+}
+
+/// Whether a button's `callback_data` confirms, and the action id it carries.
+pub fn parse_pending_action_callback(data: &str) -> Option<(bool, &str)> {
+    let (confirmed, action_id) = if let Some(action_id) = data.strip_prefix(CONFIRM_CALLBACK_PREFIX)
+    {
+        (true, action_id)
+    } else {
+        (false, data.strip_prefix(CANCEL_CALLBACK_PREFIX)?)
+    };
+    (!action_id.is_empty()).then_some((confirmed, action_id))
+}
+
+// ---------------------------------------------------------------------------
+// /summary formatting
+// ---------------------------------------------------------------------------
+
+/// Income, expenses and net for the month in the user's currency, a net line for each other
+/// currency spent in, then the largest expense categories in the user's currency.
+pub fn format_month_summary(summary: &MonthSummary) -> String {
+    let totals = &summary.totals;
+    if totals.categories.is_empty() {
+        return format!("No records in {}.", summary.month);
+    }
+
+    let currency_code = totals.currency.as_str();
+    let mut message = format!(
+        "{}\nIncome: {}\nExpenses: {}\nNet: {}",
+        summary.month,
+        format_amount(totals.income, currency_code),
+        format_amount(totals.expense, currency_code),
+        format_amount(totals.net, currency_code)
+    );
+    for other in totals
+        .currencies
+        .iter()
+        .filter(|other| other.currency != totals.currency)
+    {
+        message.push_str(&format!(
+            "\nNet in {}: {}",
+            other.currency,
+            format_amount(other.net, &other.currency)
+        ));
+    }
+
+    let mut expenses: Vec<_> = totals
+        .categories
+        .iter()
+        .filter(|category| {
+            category.currency == totals.currency
+                && !category.is_income
+                && category.total.is_negative()
+        })
+        .collect();
+    expenses.sort_by_key(|category| category.total);
+    if !expenses.is_empty() {
+        message.push_str("\nTop expenses:");
+    }
+    for category in expenses.into_iter().take(SUMMARY_TOP_EXPENSE_CATEGORIES) {
+        message.push_str(&format!(
+            "\n- {}: {}",
+            category.category_name.as_deref().unwrap_or("Uncategorized"),
+            format_amount(category.total, currency_code)
+        ));
+    }
+    message
+}
+
+/// One line on how many AI category picks were kept this month, plus a hint naming the
+/// category pair the user corrects most often.
+pub fn format_ai_accuracy_summary(report: &AiAccuracyResponse) -> String {
+    let Some(accuracy) = report.accuracy_percent else {
+        return "No records categorized by me this month yet.".to_string();
+    };
+
+    let mut message = format!(
+        "Categories I picked this month: {} of {} kept ({}%).",
+        report.total - report.corrected,
+        report.total,
+        accuracy
+    );
+    if let Some(confusion) = report.top_confusions.first() {
+        let ai_category = confusion
+            .ai_category_name
+            .as_deref()
+            .unwrap_or("a deleted category");
+        let user_category = confusion
+            .user_category_name
+            .as_deref()
+            .unwrap_or("no category");
+        message.push_str(&format!(
+            "\nHint: I often pick {} where you choose {} ({}x). Mentioning the category helps.",
+            ai_category, user_category, confusion.count
+        ));
+    }
+    message
+}
+
 // ---------------------------------------------------------------------------
 // Amount normalization
 // ---------------------------------------------------------------------------
 
-pub fn normalize_amount_by_category(amount: f64, is_income: bool) -> f64 {
+pub fn normalize_amount_by_category(amount: Money, is_income: bool) -> Money {
     if is_income {
         amount.abs()
     } else {
@@ -59,6 +178,12 @@ pub async fn cleanup_expired_contexts(state: &BotState) {
     contexts.retain(|_, ctx| !ctx.is_expired());
 }
 
+/// Drops every chat context of a Telegram user, e.g. after `/unlink`.
+pub async fn forget_contexts(state: &BotState, telegram_user_id: i64) {
+    let mut contexts = state.chat_contexts.write().await;
+    contexts.retain(|(_, user_id), _| *user_id != telegram_user_id);
+}
+
 pub async fn get_context_messages(state: &BotState, key: ContextKey) -> Vec<serde_json::Value> {
     let contexts = state.chat_contexts.read().await;
     match contexts.get(&key) {
@@ -67,8 +192,25 @@ pub async fn get_context_messages(state: &BotState, key: ContextKey) -> Vec<serd
     }
 }
 
-pub async fn push_context_turn(state: &BotState, key: ContextKey, user_msg: &str, bot_msg: &str) {
+pub async fn get_last_record_seq(state: &BotState, key: ContextKey) -> Option<i64> {
+    let contexts = state.chat_contexts.read().await;
+    match contexts.get(&key) {
+        Some(ctx) if !ctx.is_expired() => ctx.last_record_seq,
+        _ => None,
+    }
+}
+
+pub async fn push_context_turn(
+    state: &BotState,
+    key: ContextKey,
+    user_msg: &str,
+    bot_msg: &str,
+    last_record_seq: Option<i64>,
+) {
     let mut contexts = state.chat_contexts.write().await;
     let ctx = contexts.entry(key).or_insert_with(ChatContext::new);
     ctx.push_turn(user_msg, bot_msg);
+    if last_record_seq.is_some() {
+        ctx.last_record_seq = last_record_seq;
+    }
 }
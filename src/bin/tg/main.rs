@@ -5,11 +5,18 @@ use reqwest::Client;
 use teloxide::dispatching::UpdateFilterExt;
 use tokio::sync::RwLock;
 
-use kash_server::constants::DEFAULT_DATA_PATH;
+use kash_server::auth;
+use kash_server::config::{CliConfig, LoginRateLimit};
+use kash_server::config_sources::ConfigSources;
+use kash_server::crypto;
 use kash_server::database;
+use kash_server::jobs::{self, JobRegistry};
+use kash_server::rate_limit::RateLimiter;
+use kash_server::two_factor;
 
 mod constants;
 mod db;
+mod fallback;
 mod handlers;
 mod helpers;
 mod models;
@@ -21,22 +28,56 @@ use models::{BotError, BotState};
 async fn main() -> Result<(), BotError> {
     dotenv::dotenv().ok();
 
-    let bot_token =
-        std::env::var("TELEGRAM_BOT_TOKEN").map_err(|_| "TELEGRAM_BOT_TOKEN is required")?;
+    // Same environment-over-kash.toml settings as the server
+    let sources = ConfigSources::load()?;
+
+    let bot_token = sources
+        .value("TELEGRAM_BOT_TOKEN")
+        .ok_or("TELEGRAM_BOT_TOKEN is required")?;
     let bot = teloxide::Bot::new(bot_token);
 
-    let openai_api_key =
-        std::env::var("OPENAI_API_KEY").map_err(|_| "OPENAI_API_KEY is required")?;
-    let openai_model = std::env::var("OPENAI_MODEL")
-        .unwrap_or_else(|_| constants::DEFAULT_OPENAI_MODEL.to_string());
-    let openai_reasoning_effort = std::env::var("OPENAI_REASONING_EFFORT")
-        .unwrap_or_else(|_| constants::DEFAULT_REASONING_EFFORT.to_string());
-    let timezone =
-        std::env::var("BOT_TIMEZONE").unwrap_or_else(|_| constants::DEFAULT_TIMEZONE.to_string());
+    // Without OpenAI, text messages go to the fallback parser and voice is refused.
+    let fallback_only = sources
+        .value("BOT_FALLBACK_PARSER_ONLY")
+        .is_some_and(|val| val.to_lowercase() == "true");
+    let openai_api_key = if fallback_only {
+        None
+    } else {
+        Some(
+            sources
+                .value("OPENAI_API_KEY")
+                .ok_or("OPENAI_API_KEY is required unless BOT_FALLBACK_PARSER_ONLY=true")?
+                .to_string(),
+        )
+    };
+    let openai_model = sources
+        .value("OPENAI_MODEL")
+        .unwrap_or(constants::DEFAULT_OPENAI_MODEL)
+        .to_string();
+    let openai_reasoning_effort = sources
+        .value("OPENAI_REASONING_EFFORT")
+        .unwrap_or(constants::DEFAULT_REASONING_EFFORT)
+        .to_string();
+    let timezone = sources
+        .value("BOT_TIMEZONE")
+        .unwrap_or(constants::DEFAULT_TIMEZONE)
+        .to_string();
 
-    let data_path =
-        std::env::var("DATABASE_PATH").unwrap_or_else(|_| DEFAULT_DATA_PATH.to_string());
-    let main_db = database::init_main_db(&data_path).await?;
+    // Data directory, database, record key and hashing cost, read like the server's
+    let config = CliConfig::from_sources(&sources)?;
+    // Shared with the server process, so both wait out each other's writes
+    database::install_busy_retry(config.db_busy_retry);
+    let main_db = database::init_main_db_at(&config.data_path, &config.database).await?;
+    crypto::init_record_encryption(&main_db, config.record_encryption_key).await?;
+    // /link logs in too, so it must not undo or skip the server's rehashing.
+    auth::install_password_hash_params(config.password_hash);
+    // Two-factor secrets are sealed under the server's session secret; without it,
+    // /link cannot check codes and accounts using two-factor login cannot link.
+    if let Some(session_secret) = sources.value("SESSION_SECRET") {
+        two_factor::install_two_factor_key(two_factor::TwoFactorKey::from_session_secret(
+            session_secret,
+        ));
+    }
 
     let state = BotState {
         main_db,
@@ -46,9 +87,24 @@ async fn main() -> Result<(), BotError> {
         openai_reasoning_effort,
         timezone,
         chat_contexts: Arc::new(RwLock::new(HashMap::new())),
+        link_rate_limiter: Arc::new(RateLimiter::new(LoginRateLimit::from_sources(&sources)?)),
     };
 
-    let handler = teloxide::prelude::Update::filter_message().endpoint(handlers::handle_message);
+    // Only the bot can deliver Telegram messages, so its worker claims just the outbox job.
+    jobs::spawn_job_worker(
+        state.main_db.clone(),
+        JobRegistry::new().register(handlers::OutboxDrainJob {
+            bot: bot.clone(),
+            state: state.clone(),
+        }),
+    );
+
+    let handler = teloxide::dptree::entry()
+        .branch(teloxide::prelude::Update::filter_message().endpoint(handlers::handle_message))
+        .branch(
+            teloxide::prelude::Update::filter_callback_query()
+                .endpoint(handlers::handle_callback_query),
+        );
     teloxide::prelude::Dispatcher::builder(bot, handler)
         .dependencies(teloxide::dptree::deps![state])
         .build()
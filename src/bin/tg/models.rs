@@ -2,11 +2,14 @@ use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use time::OffsetDateTime;
 use tokio::sync::RwLock;
 
 use kash_server::Db;
+use kash_server::models::{CategoryEditAction, RecordSummaryResponse};
+use kash_server::rate_limit::RateLimiter;
 
 use crate::constants::{CONTEXT_MAX_TURNS, CONTEXT_TTL_SECONDS};
 
@@ -20,11 +23,15 @@ pub type BotError = Box<dyn std::error::Error + Send + Sync>;
 pub struct BotState {
     pub main_db: Db,
     pub http: Client,
-    pub openai_api_key: String,
+    /// `None` with `BOT_FALLBACK_PARSER_ONLY`: text goes straight to the fallback parser
+    /// and voice messages are refused.
+    pub openai_api_key: Option<String>,
     pub openai_model: String,
     pub openai_reasoning_effort: String,
     pub timezone: String,
     pub chat_contexts: Arc<RwLock<HashMap<ContextKey, ChatContext>>>,
+    /// Failed `/link` passwords per Telegram user and username, limited like `/auth/login`.
+    pub link_rate_limiter: Arc<RateLimiter>,
 }
 
 // ---------------------------------------------------------------------------
@@ -53,12 +60,54 @@ pub struct ChatMessage {
 
 pub struct ChatContext {
     pub messages: VecDeque<ChatMessage>,
+    /// `seq` of the last record created in this chat; targets follow-up corrections.
+    pub last_record_seq: Option<i64>,
+}
+
+/// Chat state the AI tools may update during one turn.
+#[derive(Default)]
+pub struct TurnState {
+    pub last_record_seq: Option<i64>,
+    /// Change from `edit_category` or `delete_record`; saved to `bot_pending_actions`
+    /// after the turn.
+    pub pending_action: Option<PendingAction>,
+}
+
+// ---------------------------------------------------------------------------
+// /summary
+// ---------------------------------------------------------------------------
+
+/// One month's totals for `/summary`.
+pub struct MonthSummary {
+    /// `YYYY-MM`.
+    pub month: String,
+    pub totals: RecordSummaryResponse,
+}
+
+// ---------------------------------------------------------------------------
+// Pending actions
+// ---------------------------------------------------------------------------
+
+/// A change held until the user sends `/confirm`.
+pub struct PendingAction {
+    /// What confirming will do, shown to the user before they decide.
+    pub summary: String,
+    pub action: PendingActionType,
+}
+
+/// Stored as JSON in `bot_pending_actions.action`.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum PendingActionType {
+    CategoryEdit(CategoryEditAction),
+    RecordDelete { record_id: String, name: String },
 }
 
 impl ChatContext {
     pub fn new() -> Self {
         Self {
             messages: VecDeque::new(),
+            last_record_seq: None,
         }
     }
 
@@ -3,9 +3,12 @@ use serde::Deserialize;
 use serde_json::json;
 use time::OffsetDateTime;
 
+use kash_server::constants::MAX_RECORDS_PER_BATCH;
+use kash_server::utils::fnv1a_64_hex;
+
 use crate::constants::{DEFAULT_WHISPER_MODEL, TOOL_MAX_ROUNDS};
 use crate::db::execute_tool_call;
-use crate::models::{BotState, CategoryInfo};
+use crate::models::{BotState, CategoryInfo, TurnState};
 
 #[derive(Deserialize)]
 struct WhisperTranscriptionResponse {
@@ -18,6 +21,14 @@ struct ToolCall {
     arguments: String,
 }
 
+/// Why `respond_with_tools` has no reply.
+pub enum RespondError {
+    /// OpenAI is turned off or its first request failed. No tool has run, so the
+    /// fallback parser can take the message instead.
+    Unavailable(String),
+    Failed(String),
+}
+
 pub async fn respond_with_tools(
     state: &BotState,
     user_id: &str,
@@ -25,7 +36,13 @@ pub async fn respond_with_tools(
     image_data_url: Option<&str>,
     categories: &[CategoryInfo],
     history: &[serde_json::Value],
-) -> Result<String, String> {
+    turn: &mut TurnState,
+) -> Result<String, RespondError> {
+    let Some(api_key) = state.openai_api_key.as_deref() else {
+        return Err(RespondError::Unavailable(
+            "OpenAI is turned off (BOT_FALLBACK_PARSER_ONLY)".to_string(),
+        ));
+    };
     let category_list = if categories.is_empty() {
         "(none)".to_string()
     } else {
@@ -44,29 +61,39 @@ pub async fn respond_with_tools(
     let now_date = OffsetDateTime::now_utc().date().to_string();
     let system_prompt = format!(
         "You are a budget assistant for a Telegram bot.\n\
-         You can use three tools: create_record, edit_record, list_records.\n\
+         You can use six tools: create_record, edit_record, delete_record, edit_category, list_records, sum_records.\n\
+         For totals (\"how much did I spend on food this month\"), call sum_records instead of listing records.\n\
          Decide which tool(s) to use based on the user's request.\n\
+         When a message mentions several records (\"lunch 180, coffee 60, taxi 250\"), create them all in one create_record call.\n\
          Never fabricate success. For add/edit/list requests, you MUST call the relevant tool first, then reply from tool results only.\n\
          Do not ask for confirmation before editing records. Apply edits directly.\n\
-         Never ask the user to use confirm/cancel commands.\n\
-         For delete requests, clearly state delete is not supported by this assistant.\n\
+         Never ask the user to use confirm/cancel commands for record edits.\n\
+         Category changes (renaming, or switching between income and expense) go through edit_category, which only prepares the change. When it returns ok=true, reply with its summary verbatim and ask the user to tap Confirm to apply it or Cancel to drop it (or send /confirm or /cancel).\n\
+         Pass only one change per edit_category call; if the user asks for several, report the tool's error and ask which change to make first.\n\
+         Record deletes go through delete_record, which only prepares the delete; it targets records like edit_record. When it returns ok=true, reply with its summary verbatim and ask the user to tap Confirm to delete or Cancel to keep the record (or send /confirm or /cancel). If it reports several matching records, list their ids and ask which one.\n\
+         Deleting categories is not supported here; say so and suggest the app.\n\
+         Correction rule: for follow-ups like \"actually it was 200\" that don't name a record, call edit_record without record_id or record_name; it targets the record created last.\n\
          Edit intent rule: when user says \"change to ...\" / \"改成...\" without a field name, treat it as renaming the record, so pass the new value in `name` (not category_name).\n\
          Use concise, friendly replies.\n\
          Output format rules:\n\
-         - If create_record succeeds, reply in EXACTLY this block format and nothing else:\n\
+         - If create_record succeeds, reply with one block per created record, in EXACTLY this format and nothing else:\n\
            [RECORD_ADDED]\n\
            id: <id>\n\
            name: <name>\n\
-           amount: <amount>\n\
+           amount: <amount_display>\n\
            category: <category_name>\n\
            date: <YYYY-MM-DD>\n\
+           note: <note>\n\
          - If edit_record succeeds, reply in EXACTLY this block format and nothing else:\n\
             [RECORD_EDITED]\n\
             id: <id>\n\
             name: <name>\n\
-            amount: <amount>\n\
+            amount: <amount_display>\n\
             category: <category_name>\n\
             date: <YYYY-MM-DD>\n\
+            note: <note>\n\
+         - Copy amount_display from the tool result verbatim; never reformat or re-sign amounts yourself.\n\
+         - Include the note line only when the tool result has a non-null note; otherwise leave it out.\n\
          - Never output [RECORD_ADDED] or [RECORD_EDITED] unless the corresponding tool returned ok=true.\n\
          - If multiple records are added/edited, repeat the same block for each record with one blank line between blocks.\n\
          - If a tool returns an error, reply in EXACTLY this format and nothing else:\n\
@@ -107,21 +134,29 @@ pub async fn respond_with_tools(
         "content": user_content
     }));
 
+    let prompt_hash = fnv1a_64_hex(message.as_bytes());
     let tools = build_tools_schema();
     let mut previous_response_id: Option<String> = None;
     let mut input = json!(input_messages);
 
-    for _round in 0..TOOL_MAX_ROUNDS {
+    for round in 0..TOOL_MAX_ROUNDS {
         let response_value = send_responses_request(
             &state.http,
-            &state.openai_api_key,
+            api_key,
             &state.openai_model,
             &state.openai_reasoning_effort,
             input,
             &tools,
             previous_response_id.as_deref(),
         )
-        .await?;
+        .await
+        .map_err(|message| {
+            if round == 0 {
+                RespondError::Unavailable(message)
+            } else {
+                RespondError::Failed(message)
+            }
+        })?;
 
         if let Some(response_id) = response_value.get("id").and_then(|value| value.as_str()) {
             previous_response_id = Some(response_id.to_string());
@@ -129,7 +164,7 @@ pub async fn respond_with_tools(
 
         let tool_calls = extract_tool_calls(&response_value);
         if tool_calls.is_empty() {
-            let reply = extract_output_text(&response_value)?;
+            let reply = extract_output_text(&response_value).map_err(RespondError::Failed)?;
             if reply.trim().is_empty() {
                 return Ok("I couldn't generate a reply. Please try again.".to_string());
             }
@@ -143,6 +178,8 @@ pub async fn respond_with_tools(
                 user_id,
                 &tool_call.name,
                 &tool_call.arguments,
+                &prompt_hash,
+                turn,
             )
             .await
             {
@@ -163,7 +200,9 @@ pub async fn respond_with_tools(
         input = json!(tool_outputs);
     }
 
-    Err("Tool-call loop limit reached. Please try a simpler request.".to_string())
+    Err(RespondError::Failed(
+        "Tool-call loop limit reached. Please try a simpler request.".to_string(),
+    ))
 }
 
 pub async fn transcribe_voice(
@@ -209,25 +248,40 @@ fn build_tools_schema() -> serde_json::Value {
         {
             "type": "function",
             "name": "create_record",
-            "description": "Create one income/expense record.",
+            "description": "Create income/expense records. Pass every record from one message in a single call; they are saved together or not at all.",
             "parameters": {
                 "type": "object",
                 "properties": {
-                    "name": { "type": "string" },
-                    "amount": { "type": "number" },
-                    "category_id": { "type": "string" },
-                    "category_name": { "type": "string" },
-                    "date": { "type": "string", "description": "YYYY-MM-DD" },
-                    "is_income": { "type": "boolean", "description": "Required only when creating a new category by category_name." }
+                    "records": {
+                        "type": "array",
+                        "minItems": 1,
+                        "maxItems": MAX_RECORDS_PER_BATCH,
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": { "type": "string" },
+                                "amount": { "type": "number" },
+                                "currency": { "type": "string", "description": "ISO 4217 code, only when the user names a currency other than their usual one, e.g. JPY for yen." },
+                                "category_id": { "type": "string" },
+                                "category_name": { "type": "string" },
+                                "date": { "type": "string", "description": "YYYY-MM-DD" },
+                                "is_income": { "type": "boolean", "description": "Required only when creating a new category by category_name." },
+                                "category_confidence": { "type": "number", "description": "Your confidence from 0 to 1 that the chosen category is correct." },
+                                "note": { "type": "string", "description": "Optional free-text context the user gave beyond the name, e.g. who it was shared with." }
+                            },
+                            "required": ["name", "amount"],
+                            "additionalProperties": false
+                        }
+                    }
                 },
-                "required": ["name", "amount"],
+                "required": ["records"],
                 "additionalProperties": false
             }
         },
         {
             "type": "function",
             "name": "edit_record",
-            "description": "Edit an existing record immediately. No confirmation step.",
+            "description": "Edit an existing record immediately. No confirmation step. Omit record_id and record_name to edit the most recently created record.",
             "parameters": {
                 "type": "object",
                 "properties": {
@@ -242,17 +296,63 @@ fn build_tools_schema() -> serde_json::Value {
                 "additionalProperties": false
             }
         },
+        {
+            "type": "function",
+            "name": "delete_record",
+            "description": "Prepare moving one record to the trash. Nothing is deleted until the user sends /confirm. Omit record_id and record_name to target the most recently created record.",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "record_id": { "type": "string", "description": "Preferred target identifier for the record to delete." },
+                    "record_name": { "type": "string", "description": "Fallback target identifier when record_id is unknown." }
+                },
+                "additionalProperties": false
+            }
+        },
+        {
+            "type": "function",
+            "name": "edit_category",
+            "description": "Prepare renaming a category or switching it between income and expense. Nothing changes until the user sends /confirm. Switching type re-signs the category's records; the summary says how many.",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "category_id": { "type": "string" },
+                    "category_name": { "type": "string", "description": "Fallback when category_id is unknown." },
+                    "new_name": { "type": "string" },
+                    "new_is_income": { "type": "boolean", "description": "true to make it an income category, false for expense." }
+                },
+                "additionalProperties": false
+            }
+        },
         {
             "type": "function",
             "name": "list_records",
-            "description": "List records with optional filters.",
+            "description": "List individual records, newest first, at most 50 per call. Each record is {id, n: name, a: amount, c: category name, d: date}; expenses are negative. When truncated is true, narrow the filters or page with offset. For totals use sum_records instead.",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "limit": { "type": "integer", "description": "At most 50." },
+                    "offset": { "type": "integer" },
+                    "start_date": { "type": "string", "description": "YYYY-MM-DD" },
+                    "end_date": { "type": "string", "description": "YYYY-MM-DD" },
+                    "category_id": { "type": "string" },
+                    "category_name": { "type": "string" },
+                    "name_contains": { "type": "string" },
+                    "min_amount": { "type": "number" },
+                    "max_amount": { "type": "number" }
+                },
+                "additionalProperties": false
+            }
+        },
+        {
+            "type": "function",
+            "name": "sum_records",
+            "description": "Totals and counts of the records matching the filters, without listing them. Prefer this for \"how much did I spend/earn\" questions. expense_total is negative; net = income_total + expense_total.",
             "parameters": {
                 "type": "object",
                 "properties": {
                     "start_date": { "type": "string", "description": "YYYY-MM-DD" },
                     "end_date": { "type": "string", "description": "YYYY-MM-DD" },
-                    "limit": { "type": "integer" },
-                    "offset": { "type": "integer" },
                     "category_id": { "type": "string" },
                     "category_name": { "type": "string" },
                     "name_contains": { "type": "string" },
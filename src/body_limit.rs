@@ -0,0 +1,76 @@
+use axum::{
+    Router,
+    body::Body,
+    extract::{DefaultBodyLimit, Request, State},
+    http::{StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+};
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+
+use crate::config::BodyLimits;
+use crate::constants::*;
+use crate::error::ApiError;
+
+/// Caps every request before a handler sees it: a query string longer than
+/// `MAX_QUERY_STRING_LENGTH` gets 414, and a body over `BodyLimits::for_path` gets 413,
+/// both as JSON errors. A declared `Content-Length` is refused without reading the
+/// body; otherwise reading stops at the cap, so a chunked upload cannot run on either.
+pub fn layer<S>(router: Router<S>, limits: BodyLimits) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router
+        .layer(middleware::from_fn_with_state(limits, limit_request))
+        // The caps above replace axum's 2 MB extractor default, which would cut imports short
+        .layer(DefaultBodyLimit::disable())
+}
+
+fn too_large(max_bytes: usize) -> Response {
+    ApiError::new(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        ERROR_CODE_PAYLOAD_TOO_LARGE,
+        format!("Request body is larger than {} bytes", max_bytes),
+    )
+    .into_response()
+}
+
+async fn limit_request(State(limits): State<BodyLimits>, request: Request, next: Next) -> Response {
+    if request
+        .uri()
+        .query()
+        .is_some_and(|query| query.len() > MAX_QUERY_STRING_LENGTH)
+    {
+        return ApiError::new(
+            StatusCode::URI_TOO_LONG,
+            ERROR_CODE_URI_TOO_LONG,
+            format!(
+                "Query string is longer than {} characters",
+                MAX_QUERY_STRING_LENGTH
+            ),
+        )
+        .into_response();
+    }
+
+    let max_bytes = limits.for_path(request.uri().path());
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > max_bytes as u64) {
+        return too_large(max_bytes);
+    }
+
+    let (parts, body) = request.into_parts();
+    let bytes = match Limited::new(body, max_bytes).collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) if e.is::<LengthLimitError>() => return too_large(max_bytes),
+        Err(e) => {
+            return ApiError::bad_request(format!("Failed to read request body: {}", e))
+                .into_response();
+        }
+    };
+    next.run(Request::from_parts(parts, Body::from(bytes)))
+        .await
+}
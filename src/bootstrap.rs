@@ -0,0 +1,114 @@
+use axum::{Json, extract::State, http::StatusCode};
+use serde::Serialize;
+use tower_sessions::Session;
+
+use crate::AppState;
+use crate::auth::{get_current_user, refresh_user};
+use crate::categories::list_all_categories;
+use crate::constants::*;
+use crate::crypto::installed_master_key;
+use crate::friendship_repo::{self, FriendDirection, FriendListKind};
+use crate::models::{BootstrapResponse, BootstrapSection, Capabilities, InboxCounts, PublicUser};
+use crate::record_repo::{RecordFilter, RecordSort};
+use crate::records::list_records_page_with_settings;
+use crate::settings::fetch_user_settings;
+use crate::split_repo;
+use crate::utils::{db_error_with_context, json_version};
+
+fn section<T: Serialize>(data: T) -> BootstrapSection<T> {
+    BootstrapSection {
+        version: json_version(&data),
+        data,
+    }
+}
+
+fn capabilities() -> Capabilities {
+    Capabilities {
+        record_encryption: installed_master_key().is_some(),
+        max_page_size: MAX_LIMIT,
+        currency_codes: ISO_CURRENCY_CODES
+            .iter()
+            .map(|code| code.to_string())
+            .collect(),
+    }
+}
+
+async fn inbox_counts(
+    conn: &libsql::Connection,
+    user_id: &str,
+) -> Result<InboxCounts, (StatusCode, String)> {
+    let friend_requests = friendship_repo::count_friends(
+        conn,
+        user_id,
+        FriendListKind::Pending,
+        Some(FriendDirection::Incoming),
+        None,
+    )
+    .await
+    .map_err(|_| db_error_with_context("failed to count friend requests"))?;
+    let pending_splits = split_repo::count_pending(conn, user_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to count pending splits"))?;
+    Ok(InboxCounts {
+        friend_requests: u32::try_from(friend_requests).unwrap_or(u32::MAX),
+        pending_splits: u32::try_from(pending_splits).unwrap_or(u32::MAX),
+    })
+}
+
+/// Assembles `GET /bootstrap` for `user` from the same helpers as the standalone
+/// endpoints, so each section matches their body. At most `BOOTSTRAP_QUERY_BUDGET`
+/// queries however many records the user has: one each for categories and
+/// settings, at most four for the records page and two for the inbox. Only an
+/// account with `MAX_LIMIT` or more categories needs one more, to count them.
+pub async fn bootstrap_for_user(
+    conn: &libsql::Connection,
+    user: PublicUser,
+) -> Result<BootstrapResponse, (StatusCode, String)> {
+    let categories = list_all_categories(conn, &user.id).await?;
+    let settings = fetch_user_settings(conn, &user.id).await?;
+    let filter = RecordFilter {
+        start_date: "0000-01-01",
+        end_date: "9999-12-31",
+        pending: None,
+        settle: None,
+        category_id: None,
+        name_contains: None,
+        min_amount: None,
+        max_amount: None,
+        tag: None,
+        sort: RecordSort::default(),
+        after: None,
+    };
+    // `next_cursor` continues this page through `GET /records`
+    let records = list_records_page_with_settings(
+        conn,
+        &user.id,
+        &settings,
+        &filter,
+        BOOTSTRAP_RECORDS_LIMIT,
+        0,
+        true,
+    )
+    .await?;
+    let inbox = inbox_counts(conn, &user.id).await?;
+
+    Ok(BootstrapResponse {
+        user: section(user),
+        categories: section(categories),
+        settings: section(settings),
+        records: section(records),
+        inbox: section(inbox),
+        capabilities: section(capabilities()),
+    })
+}
+
+pub async fn get_bootstrap(
+    State(app_state): State<AppState>,
+    session: Session,
+) -> Result<(StatusCode, Json<BootstrapResponse>), (StatusCode, String)> {
+    let user = get_current_user(&session).await?;
+    let conn = app_state.main_db.read().await;
+    let user = refresh_user(&conn, user).await?;
+    let response = bootstrap_for_user(&conn, user).await?;
+    Ok((StatusCode::OK, Json(response)))
+}
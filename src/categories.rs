@@ -1,7 +1,8 @@
 use axum::{
     Json,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
+    response::Response,
 };
 use tower_sessions::Session;
 use uuid::Uuid;
@@ -9,12 +10,20 @@ use uuid::Uuid;
 use crate::auth::get_current_user;
 use crate::constants::*;
 use crate::models::{
-    Category, CreateCategoryPayload, GetCategoriesQuery, GetCategoriesResponse,
+    ActivityQuery, BudgetStatusQuery, BudgetStatusResponse, Category, CategoryBudgetStatus,
+    CategoryConversionPlan, CategoryEditAction, ConvertCategoryPayload, ConvertCategoryResponse,
+    CreateCategoryPayload, DeleteCategoryQuery, GetCategoriesQuery, GetCategoriesResponse,
+    GetRecordsResponse, MergeCategoriesPayload, MergeCategoriesResponse, PendingCategoryEdit,
     UpdateCategoryPayload,
 };
+use crate::money::Money;
+use crate::record_repo::{RecordFilter, RecordSort};
+use crate::records::list_records_page;
+use crate::settings::guard_closed_period;
+use crate::stats::{current_month, month_window};
 use crate::utils::{
-    db_error, db_error_with_context, validate_categories_limit, validate_offset,
-    validate_string_length,
+    conditional_json, database_busy, db_error, db_error_with_context, sql_placeholders,
+    validate_categories_limit, validate_offset, validate_records_limit, validate_string_length,
 };
 use crate::{AppState, Db, TransactionError, with_transaction};
 
@@ -32,47 +41,192 @@ pub fn extract_category_from_row(row: libsql::Row) -> Result<Category, (StatusCo
     let is_income: bool = row
         .get(2)
         .map_err(|_| db_error_with_context("invalid category data"))?;
+    let parent_id: Option<String> = row
+        .get(3)
+        .map_err(|_| db_error_with_context("invalid category data"))?;
+    let monthly_budget = row
+        .get::<Option<i64>>(4)
+        .map_err(|_| db_error_with_context("invalid category data"))?
+        .map(Money::from_cents);
+    let archived: bool = row
+        .get(5)
+        .map_err(|_| db_error_with_context("invalid category data"))?;
 
     Ok(Category {
         id,
         name,
         is_income,
+        parent_id,
+        monthly_budget,
+        archived,
     })
 }
 
-pub async fn validate_category_not_in_use(
-    db: &Db,
+/// Budgets are positive and only for expense categories.
+fn validate_monthly_budget(budget: Money, is_income: bool) -> Result<(), (StatusCode, String)> {
+    if is_income {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Income categories cannot have a monthly budget".to_string(),
+        ));
+    }
+    if !budget.is_positive() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "monthly_budget must be a positive number".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn fetch_category(
+    conn: &libsql::Connection,
     user_id: &str,
     category_id: &str,
-) -> Result<(), (StatusCode, String)> {
-    let conn = db.read().await;
+) -> Result<Option<Category>, (StatusCode, String)> {
+    let mut rows = conn
+        .query(
+            "SELECT id, name, is_income, parent_id, monthly_budget, archived FROM categories WHERE id = ? AND owner_user_id = ?",
+            (category_id, user_id),
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query category"))?;
+
+    match rows.next().await.map_err(|_| db_error())? {
+        Some(row) => Ok(Some(extract_category_from_row(row)?)),
+        None => Ok(None),
+    }
+}
 
+async fn count_child_categories(
+    conn: &libsql::Connection,
+    user_id: &str,
+    category_id: &str,
+) -> Result<u32, (StatusCode, String)> {
     let mut rows = conn
         .query(
-            "SELECT COUNT(*) FROM records WHERE category_id = ? AND owner_user_id = ?",
+            "SELECT COUNT(*) FROM categories WHERE parent_id = ? AND owner_user_id = ?",
             (category_id, user_id),
         )
         .await
-        .map_err(|_| db_error_with_context("failed to check category usage"))?;
+        .map_err(|_| db_error_with_context("failed to count subcategories"))?;
 
     if let Some(row) = rows.next().await.map_err(|_| db_error())? {
-        let count: u32 = row.get(0).map_err(|_| db_error())?;
-        if count > 0 {
-            return Err((
-                StatusCode::CONFLICT,
-                "Cannot delete category: it has associated records".to_string(),
-            ));
-        }
+        row.get(0).map_err(|_| db_error())
+    } else {
+        Ok(0)
+    }
+}
+
+/// Checks that `parent_id` can parent a category of the given type.
+///
+/// The hierarchy is at most two levels deep, so the parent must be a top-level
+/// category of the same `is_income` type, and a category that already has
+/// children cannot itself become a child. Together these rules rule out cycles.
+async fn validate_parent_category(
+    conn: &libsql::Connection,
+    user_id: &str,
+    category_id: Option<&str>,
+    parent_id: &str,
+    is_income: bool,
+) -> Result<(), (StatusCode, String)> {
+    if category_id == Some(parent_id) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Category cannot be its own parent".to_string(),
+        ));
+    }
+
+    let parent = fetch_category(conn, user_id, parent_id)
+        .await?
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                "Parent category does not exist".to_string(),
+            )
+        })?;
+
+    if parent.is_income != is_income {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Parent category must have the same is_income type".to_string(),
+        ));
+    }
+
+    if parent.parent_id.is_some() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Parent category cannot itself be a subcategory".to_string(),
+        ));
+    }
+
+    if let Some(category_id) = category_id
+        && count_child_categories(conn, user_id, category_id).await? > 0
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "A category with subcategories cannot become a subcategory".to_string(),
+        ));
     }
 
     Ok(())
 }
 
+/// 409 when another of the user's categories already has `name`, ignoring case.
+async fn ensure_category_name_free(
+    conn: &libsql::Connection,
+    user_id: &str,
+    name: &str,
+    category_id: &str,
+) -> Result<(), (StatusCode, String)> {
+    let mut conflict_rows = conn
+        .query(
+            "SELECT id FROM categories WHERE owner_user_id = ? AND LOWER(name) = LOWER(?) AND id != ?",
+            (user_id, name, category_id),
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to check name conflict"))?;
+
+    if conflict_rows
+        .next()
+        .await
+        .map_err(|_| db_error())?
+        .is_some()
+    {
+        return Err((
+            StatusCode::CONFLICT,
+            "Category name already exists (case-insensitive)".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// The user's live records in `category_id`; trashed ones do not hold a category.
+async fn count_category_records(
+    conn: &libsql::Connection,
+    user_id: &str,
+    category_id: &str,
+) -> Result<u32, (StatusCode, String)> {
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM records WHERE category_id = ? AND owner_user_id = ? AND deleted_at IS NULL",
+            (category_id, user_id),
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to check category usage"))?;
+
+    match rows.next().await.map_err(|_| db_error())? {
+        Some(row) => row.get(0).map_err(|_| db_error()),
+        None => Ok(0),
+    }
+}
+
 enum CreateCategoryError {
     Transaction(TransactionError),
     DbCheck,
     DbInsert,
     Conflict,
+    InvalidParent((StatusCode, String)),
 }
 
 impl From<TransactionError> for CreateCategoryError {
@@ -90,6 +244,7 @@ impl From<CreateCategoryError> for (StatusCode, String) {
             CreateCategoryError::Transaction(TransactionError::Commit) => {
                 db_error_with_context("failed to commit transaction")
             }
+            CreateCategoryError::Transaction(TransactionError::Busy) => database_busy(),
             CreateCategoryError::DbCheck => {
                 db_error_with_context("failed to check existing category")
             }
@@ -98,6 +253,7 @@ impl From<CreateCategoryError> for (StatusCode, String) {
                 StatusCode::CONFLICT,
                 "Category name already exists (case-insensitive)".to_string(),
             ),
+            CreateCategoryError::InvalidParent(error) => error,
         }
     }
 }
@@ -108,15 +264,41 @@ pub async fn create_category(
     Json(payload): Json<CreateCategoryPayload>,
 ) -> Result<(StatusCode, Json<Category>), (StatusCode, String)> {
     let user = get_current_user(&session).await?;
+    let category = create_category_for_user(&app_state.main_db, &user.id, payload).await?;
+
+    Ok((StatusCode::CREATED, Json(category)))
+}
+
+pub async fn create_category_for_user(
+    db: &Db,
+    user_id: &str,
+    payload: CreateCategoryPayload,
+) -> Result<Category, (StatusCode, String)> {
     validate_category_name(&payload.name)?;
     let category_name = payload.name.trim().to_string();
     let is_income = payload.is_income;
-    let db = &app_state.main_db;
+    let monthly_budget = payload.monthly_budget;
+    if let Some(budget) = monthly_budget {
+        validate_monthly_budget(budget, is_income)?;
+    }
+    let parent_id = payload
+        .parent_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string);
 
-    let category = with_transaction(db, |conn| {
+    with_transaction(db, |conn| {
         let name = category_name.clone();
-        let owner_user_id = user.id.clone();
+        let owner_user_id = user_id.to_string();
+        let parent_id = parent_id.clone();
         Box::pin(async move {
+            if let Some(ref parent_id) = parent_id {
+                validate_parent_category(conn, &owner_user_id, None, parent_id, is_income)
+                    .await
+                    .map_err(CreateCategoryError::InvalidParent)?;
+            }
+
             let mut existing_rows = conn
                 .query(
                     "SELECT id FROM categories WHERE owner_user_id = ? AND LOWER(name) = LOWER(?)",
@@ -136,35 +318,130 @@ pub async fn create_category(
 
             let category_id = Uuid::new_v4().to_string();
             conn.execute(
-                "INSERT INTO categories (id, owner_user_id, name, is_income) VALUES (?, ?, ?, ?)",
+                "INSERT INTO categories (id, owner_user_id, name, is_income, parent_id, monthly_budget) VALUES (?, ?, ?, ?, ?, ?)",
                 (
                     category_id.as_str(),
                     owner_user_id.as_str(),
                     name.as_str(),
                     is_income,
+                    parent_id.as_deref(),
+                    monthly_budget,
                 ),
             )
             .await
-            .map_err(|_| CreateCategoryError::DbInsert)?;
+            .map_err(|e| {
+                // A concurrent create can pass the check above; the NOCASE index catches it.
+                if e.to_string().contains("UNIQUE constraint failed") {
+                    CreateCategoryError::Conflict
+                } else {
+                    CreateCategoryError::DbInsert
+                }
+            })?;
 
             Ok(Category {
                 id: category_id,
                 name,
                 is_income,
+                parent_id,
+                monthly_budget,
+                archived: false,
             })
         })
     })
     .await
-    .map_err(|e: CreateCategoryError| -> (StatusCode, String) { e.into() })?;
+    .map_err(|e: CreateCategoryError| -> (StatusCode, String) { e.into() })
+}
 
-    Ok((StatusCode::CREATED, Json(category)))
+/// Returns `user_id`'s category named `name` (compared case-insensitively), creating it when
+/// missing. A blank name falls back to `DEFAULT_CATEGORY_NAME`.
+///
+/// The insert is `ON CONFLICT DO NOTHING` against the `NOCASE` unique index, so concurrent
+/// callers (including the bot, which is a separate process) all end up with the same row.
+/// An existing category keeps its own casing and `is_income`.
+pub async fn get_or_create_category(
+    db: &Db,
+    user_id: &str,
+    name: &str,
+    is_income: bool,
+) -> Result<Category, (StatusCode, String)> {
+    let trimmed = name.trim();
+    let name = if trimmed.is_empty() {
+        DEFAULT_CATEGORY_NAME
+    } else {
+        trimmed
+    };
+    validate_category_name(name)?;
+
+    let conn = db.write().await;
+    conn.execute(
+        "INSERT INTO categories (id, owner_user_id, name, is_income) VALUES (?, ?, ?, ?) ON CONFLICT DO NOTHING",
+        (Uuid::new_v4().to_string(), user_id, name, is_income),
+    )
+    .await
+    .map_err(|_| db_error_with_context("failed to create category"))?;
+
+    let mut rows = conn
+        .query(
+            "SELECT id, name, is_income, parent_id, monthly_budget, archived FROM categories WHERE owner_user_id = ? AND name = ? COLLATE NOCASE",
+            (user_id, name),
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query category"))?;
+    match rows.next().await.map_err(|_| db_error())? {
+        Some(row) => extract_category_from_row(row),
+        None => Err(db_error_with_context("category missing after insert")),
+    }
 }
 
+/// The body `GET /categories?limit=MAX_LIMIT` returns, so archived categories are left
+/// out. The count query only runs when the page is full.
+pub async fn list_all_categories(
+    conn: &libsql::Connection,
+    user_id: &str,
+) -> Result<GetCategoriesResponse, (StatusCode, String)> {
+    let mut rows = conn
+        .query(
+            "SELECT id, name, is_income, parent_id, monthly_budget, archived FROM categories WHERE owner_user_id = ? AND archived = FALSE ORDER BY name ASC LIMIT ?",
+            (user_id, MAX_LIMIT),
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query categories"))?;
+    let mut categories = Vec::new();
+    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        categories.push(extract_category_from_row(row)?);
+    }
+
+    let total_count = if categories.len() < MAX_LIMIT as usize {
+        categories.len() as u32
+    } else {
+        let mut count_rows = conn
+            .query(
+                "SELECT COUNT(*) FROM categories WHERE owner_user_id = ? AND archived = FALSE",
+                [user_id],
+            )
+            .await
+            .map_err(|_| db_error_with_context("failed to count categories"))?;
+        match count_rows.next().await.map_err(|_| db_error())? {
+            Some(row) => row.get(0).map_err(|_| db_error())?,
+            None => 0,
+        }
+    };
+
+    Ok(GetCategoriesResponse {
+        categories,
+        total_count,
+        limit: MAX_LIMIT,
+        offset: 0,
+    })
+}
+
+/// Archived categories are listed only with `include_archived=true`.
 pub async fn get_categories(
     State(app_state): State<AppState>,
     session: Session,
+    headers: HeaderMap,
     Query(query): Query<GetCategoriesQuery>,
-) -> Result<(StatusCode, Json<GetCategoriesResponse>), (StatusCode, String)> {
+) -> Result<Response, (StatusCode, String)> {
     let user = get_current_user(&session).await?;
     let limit = validate_categories_limit(query.limit)?;
     let offset = validate_offset(query.offset)?;
@@ -178,14 +455,16 @@ pub async fn get_categories(
         validate_string_length(search, "Search term", MAX_SEARCH_TERM_LENGTH)?;
     }
 
+    let include_archived = query.include_archived.unwrap_or(false);
+
     let conn = app_state.main_db.read().await;
 
     let total_count: u32 = if let Some(search) = &search_term {
         let search_pattern = format!("%{}%", search);
         let mut count_rows = conn
             .query(
-                "SELECT COUNT(*) FROM categories WHERE owner_user_id = ? AND name LIKE ? COLLATE NOCASE",
-                (user.id.as_str(), search_pattern.as_str()),
+                "SELECT COUNT(*) FROM categories WHERE owner_user_id = ? AND (? OR archived = FALSE) AND name LIKE ? COLLATE NOCASE",
+                (user.id.as_str(), include_archived, search_pattern.as_str()),
             )
             .await
             .map_err(|_| db_error_with_context("failed to count categories"))?;
@@ -198,8 +477,8 @@ pub async fn get_categories(
     } else {
         let mut count_rows = conn
             .query(
-                "SELECT COUNT(*) FROM categories WHERE owner_user_id = ?",
-                [user.id.as_str()],
+                "SELECT COUNT(*) FROM categories WHERE owner_user_id = ? AND (? OR archived = FALSE)",
+                (user.id.as_str(), include_archived),
             )
             .await
             .map_err(|_| db_error_with_context("failed to count categories"))?;
@@ -214,15 +493,21 @@ pub async fn get_categories(
     let mut rows = if let Some(search) = &search_term {
         let search_pattern = format!("%{}%", search);
         conn.query(
-            "SELECT id, name, is_income FROM categories WHERE owner_user_id = ? AND name LIKE ? COLLATE NOCASE ORDER BY name ASC LIMIT ? OFFSET ?",
-            (user.id.as_str(), search_pattern.as_str(), limit, offset),
+            "SELECT id, name, is_income, parent_id, monthly_budget, archived FROM categories WHERE owner_user_id = ? AND (? OR archived = FALSE) AND name LIKE ? COLLATE NOCASE ORDER BY name ASC LIMIT ? OFFSET ?",
+            (
+                user.id.as_str(),
+                include_archived,
+                search_pattern.as_str(),
+                limit,
+                offset,
+            ),
         )
         .await
         .map_err(|_| db_error_with_context("failed to query categories"))?
     } else {
         conn.query(
-            "SELECT id, name, is_income FROM categories WHERE owner_user_id = ? ORDER BY name ASC LIMIT ? OFFSET ?",
-            (user.id.as_str(), limit, offset),
+            "SELECT id, name, is_income, parent_id, monthly_budget, archived FROM categories WHERE owner_user_id = ? AND (? OR archived = FALSE) ORDER BY name ASC LIMIT ? OFFSET ?",
+            (user.id.as_str(), include_archived, limit, offset),
         )
         .await
         .map_err(|_| db_error_with_context("failed to query categories"))?
@@ -233,14 +518,14 @@ pub async fn get_categories(
         categories.push(extract_category_from_row(row)?);
     }
 
-    Ok((
-        StatusCode::OK,
-        Json(GetCategoriesResponse {
+    Ok(conditional_json(
+        &headers,
+        GetCategoriesResponse {
             categories,
             total_count,
             limit,
             offset,
-        }),
+        },
     ))
 }
 
@@ -251,57 +536,76 @@ pub async fn update_category(
     Json(payload): Json<UpdateCategoryPayload>,
 ) -> Result<(StatusCode, Json<Category>), (StatusCode, String)> {
     let user = get_current_user(&session).await?;
-    let category_name = if let Some(ref name) = payload.name {
-        validate_category_name(name)?;
-        name.trim().to_string()
-    } else {
+
+    if payload.name.is_none()
+        && payload.parent_id.is_none()
+        && payload.monthly_budget.is_none()
+        && payload.archived.is_none()
+    {
         return Err((
             StatusCode::BAD_REQUEST,
-            "Category name is required for update".to_string(),
+            "At least one field must be provided for update".to_string(),
         ));
-    };
+    }
+
+    if let Some(ref name) = payload.name {
+        validate_category_name(name)?;
+    }
 
     let conn = app_state.main_db.write().await;
 
-    let mut existing_rows = conn
-        .query(
-            "SELECT id, name, is_income FROM categories WHERE id = ? AND owner_user_id = ?",
-            (category_id.as_str(), user.id.as_str()),
-        )
-        .await
-        .map_err(|_| db_error_with_context("failed to query existing category"))?;
+    let existing_category = fetch_category(&conn, &user.id, &category_id)
+        .await?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Category not found".to_string()))?;
 
-    let existing_category = if let Some(row) = existing_rows.next().await.map_err(|_| db_error())? {
-        extract_category_from_row(row)?
-    } else {
-        return Err((StatusCode::NOT_FOUND, "Category not found".to_string()));
+    let category_name = payload
+        .name
+        .as_deref()
+        .map(|name| name.trim().to_string())
+        .unwrap_or_else(|| existing_category.name.clone());
+
+    ensure_category_name_free(&conn, &user.id, &category_name, &category_id).await?;
+
+    let monthly_budget = match payload.monthly_budget {
+        Some(Some(budget)) => {
+            validate_monthly_budget(budget, existing_category.is_income)?;
+            Some(budget)
+        }
+        Some(None) => None,
+        None => existing_category.monthly_budget,
     };
+    let archived = payload.archived.unwrap_or(existing_category.archived);
 
-    let mut conflict_rows = conn
-        .query(
-            "SELECT id FROM categories WHERE owner_user_id = ? AND LOWER(name) = LOWER(?) AND id != ?",
-            (user.id.as_str(), category_name.as_str(), category_id.as_str()),
-        )
-        .await
-        .map_err(|_| db_error_with_context("failed to check name conflict"))?;
+    let parent_id = match payload.parent_id {
+        Some(parent_id) => parent_id
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string),
+        None => existing_category.parent_id.clone(),
+    };
 
-    if conflict_rows
-        .next()
-        .await
-        .map_err(|_| db_error())?
-        .is_some()
+    if let Some(ref parent_id) = parent_id
+        && existing_category.parent_id.as_deref() != Some(parent_id.as_str())
     {
-        return Err((
-            StatusCode::CONFLICT,
-            "Category name already exists (case-insensitive)".to_string(),
-        ));
+        validate_parent_category(
+            &conn,
+            &user.id,
+            Some(&category_id),
+            parent_id,
+            existing_category.is_income,
+        )
+        .await?;
     }
 
     let affected_rows = conn
         .execute(
-            "UPDATE categories SET name = ? WHERE id = ? AND owner_user_id = ?",
+            "UPDATE categories SET name = ?, parent_id = ?, monthly_budget = ?, archived = ? WHERE id = ? AND owner_user_id = ?",
             (
                 category_name.as_str(),
+                parent_id.as_deref(),
+                monthly_budget,
+                archived,
                 category_id.as_str(),
                 user.id.as_str(),
             ),
@@ -320,52 +624,805 @@ pub async fn update_category(
         id: category_id,
         name: category_name,
         is_income: existing_category.is_income,
+        parent_id,
+        monthly_budget,
+        archived,
     };
 
     Ok((StatusCode::OK, Json(updated_category)))
 }
 
+enum DeleteCategoryError {
+    Transaction(TransactionError),
+    Db(&'static str),
+    NotFound,
+}
+
+impl From<TransactionError> for DeleteCategoryError {
+    fn from(e: TransactionError) -> Self {
+        DeleteCategoryError::Transaction(e)
+    }
+}
+
+impl From<DeleteCategoryError> for (StatusCode, String) {
+    fn from(e: DeleteCategoryError) -> Self {
+        match e {
+            DeleteCategoryError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction")
+            }
+            DeleteCategoryError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction")
+            }
+            DeleteCategoryError::Transaction(TransactionError::Busy) => database_busy(),
+            DeleteCategoryError::Db(ctx) => db_error_with_context(ctx),
+            DeleteCategoryError::NotFound => {
+                (StatusCode::NOT_FOUND, "Category not found".to_string())
+            }
+        }
+    }
+}
+
+/// Deletes a category. Subcategories need `promote_children` or `reassign_children_to`, and
+/// records need `reassign_to` (same type, so amounts keep their sign) or `force` (they become
+/// uncategorized); without them the delete is 409.
 pub async fn delete_category(
     State(app_state): State<AppState>,
     session: Session,
     Path(category_id): Path<String>,
+    Query(query): Query<DeleteCategoryQuery>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     let user = get_current_user(&session).await?;
+    let promote_children = query.promote_children.unwrap_or(false);
+    let reassign_children_to = query
+        .reassign_children_to
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string);
+    let force = query.force.unwrap_or(false);
+    let reassign_to = query
+        .reassign_to
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string);
+
+    if promote_children && reassign_children_to.is_some() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Use either promote_children or reassign_children_to, not both".to_string(),
+        ));
+    }
+    if force && reassign_to.is_some() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Use either reassign_to or force, not both".to_string(),
+        ));
+    }
 
     {
         let conn = app_state.main_db.read().await;
-        let mut existing_rows = conn
-            .query(
-                "SELECT id FROM categories WHERE id = ? AND owner_user_id = ?",
-                (category_id.as_str(), user.id.as_str()),
+        let existing_category = fetch_category(&conn, &user.id, &category_id)
+            .await?
+            .ok_or_else(|| (StatusCode::NOT_FOUND, "Category not found".to_string()))?;
+
+        let child_count = count_child_categories(&conn, &user.id, &category_id).await?;
+        if child_count > 0 {
+            match reassign_children_to {
+                Some(ref target_id) => {
+                    if target_id == &category_id {
+                        return Err((
+                            StatusCode::BAD_REQUEST,
+                            "Cannot reassign subcategories to the category being deleted"
+                                .to_string(),
+                        ));
+                    }
+                    validate_parent_category(
+                        &conn,
+                        &user.id,
+                        None,
+                        target_id,
+                        existing_category.is_income,
+                    )
+                    .await?;
+                }
+                None if !promote_children => {
+                    return Err((
+                        StatusCode::CONFLICT,
+                        "Cannot delete category: it has subcategories (set promote_children=true or reassign_children_to)"
+                            .to_string(),
+                    ));
+                }
+                None => {}
+            }
+        }
+
+        match reassign_to {
+            Some(ref target_id) => {
+                if target_id == &category_id {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        "Cannot reassign records to the category being deleted".to_string(),
+                    ));
+                }
+                let target = fetch_category(&conn, &user.id, target_id)
+                    .await?
+                    .ok_or_else(|| {
+                        (
+                            StatusCode::BAD_REQUEST,
+                            "reassign_to category does not exist".to_string(),
+                        )
+                    })?;
+                if target.is_income != existing_category.is_income {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        format!(
+                            "Cannot reassign records of an {} category to an {} category",
+                            category_type_name(existing_category.is_income),
+                            category_type_name(target.is_income)
+                        ),
+                    ));
+                }
+            }
+            None if !force => {
+                let record_count = count_category_records(&conn, &user.id, &category_id).await?;
+                if record_count > 0 {
+                    return Err((
+                        StatusCode::CONFLICT,
+                        format!(
+                            "Cannot delete category: it has {} (set reassign_to or force=true)",
+                            record_count_phrase(record_count)
+                        ),
+                    ));
+                }
+            }
+            None => {}
+        }
+    }
+
+    with_transaction(&app_state.main_db, |conn| {
+        let category_id = category_id.clone();
+        let owner_user_id = user.id.clone();
+        let new_parent_id = reassign_children_to.clone();
+        let new_category_id = reassign_to.clone();
+        Box::pin(async move {
+            conn.execute(
+                "UPDATE categories SET parent_id = ? WHERE parent_id = ? AND owner_user_id = ?",
+                (
+                    new_parent_id.as_deref(),
+                    category_id.as_str(),
+                    owner_user_id.as_str(),
+                ),
             )
             .await
-            .map_err(|_| db_error_with_context("failed to query existing category"))?;
+            .map_err(|_| DeleteCategoryError::Db("failed to move subcategories"))?;
 
-        if existing_rows
-            .next()
-            .await
-            .map_err(|_| db_error())?
-            .is_none()
-        {
-            return Err((StatusCode::NOT_FOUND, "Category not found".to_string()));
+            // Trashed records move too, so a restore finds their category
+            if force || new_category_id.is_some() {
+                conn.execute(
+                    "UPDATE records SET category_id = ? WHERE category_id = ? AND owner_user_id = ?",
+                    (
+                        new_category_id.as_deref(),
+                        category_id.as_str(),
+                        owner_user_id.as_str(),
+                    ),
+                )
+                .await
+                .map_err(|_| DeleteCategoryError::Db("failed to move records"))?;
+            }
+
+            let affected_rows = conn
+                .execute(
+                    "DELETE FROM categories WHERE id = ? AND owner_user_id = ?",
+                    (category_id.as_str(), owner_user_id.as_str()),
+                )
+                .await
+                .map_err(|_| DeleteCategoryError::Db("failed to delete category"))?;
+
+            if affected_rows == 0 {
+                return Err(DeleteCategoryError::NotFound);
+            }
+
+            Ok(())
+        })
+    })
+    .await
+    .map_err(|e: DeleteCategoryError| -> (StatusCode, String) { e.into() })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+enum ConvertCategoryError {
+    Transaction(TransactionError),
+    Db(&'static str),
+    Rejected((StatusCode, String)),
+}
+
+impl From<TransactionError> for ConvertCategoryError {
+    fn from(e: TransactionError) -> Self {
+        ConvertCategoryError::Transaction(e)
+    }
+}
+
+impl From<ConvertCategoryError> for (StatusCode, String) {
+    fn from(e: ConvertCategoryError) -> Self {
+        match e {
+            ConvertCategoryError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction")
+            }
+            ConvertCategoryError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction")
+            }
+            ConvertCategoryError::Transaction(TransactionError::Busy) => database_busy(),
+            ConvertCategoryError::Db(ctx) => db_error_with_context(ctx),
+            ConvertCategoryError::Rejected(error) => error,
         }
+    }
+}
+
+fn category_type_name(is_income: bool) -> &'static str {
+    if is_income { "income" } else { "expense" }
+}
 
-        validate_category_not_in_use(&app_state.main_db, &user.id, &category_id).await?;
+fn record_count_phrase(count: u32) -> String {
+    match count {
+        1 => "1 record".to_string(),
+        count => format!("{count} records"),
     }
+}
 
-    let conn = app_state.main_db.write().await;
-    let affected_rows = conn
+/// Records whose sign disagrees with `is_income`: income is positive, expenses negative.
+fn mismatched_sign_filter(is_income: bool) -> &'static str {
+    if is_income {
+        "amount < 0"
+    } else {
+        "amount > 0"
+    }
+}
+
+fn owner_and_category_params(user_id: &str, category_ids: &[String]) -> Vec<libsql::Value> {
+    let mut params = vec![libsql::Value::from(user_id.to_string())];
+    params.extend(category_ids.iter().cloned().map(libsql::Value::from));
+    params
+}
+
+/// Plans switching a top-level category and its subcategories to `is_income`.
+///
+/// Refused when a split record would change sign, since split amounts are fixed, or when a
+/// record that would change sign lies in the closed period.
+pub async fn plan_category_conversion(
+    conn: &libsql::Connection,
+    user_id: &str,
+    category_id: &str,
+    is_income: bool,
+) -> Result<CategoryConversionPlan, (StatusCode, String)> {
+    let category = fetch_category(conn, user_id, category_id)
+        .await?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Category not found".to_string()))?;
+
+    if category.is_income == is_income {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Category is already an {} category",
+                category_type_name(is_income)
+            ),
+        ));
+    }
+    if category.parent_id.is_some() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Subcategories share their parent's type; convert the parent category instead"
+                .to_string(),
+        ));
+    }
+
+    let mut category_ids = vec![category.id.clone()];
+    let mut rows = conn
+        .query(
+            "SELECT id FROM categories WHERE parent_id = ? AND owner_user_id = ? ORDER BY name",
+            (category_id, user_id),
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query subcategories"))?;
+    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        category_ids.push(row.get(0).map_err(|_| db_error())?);
+    }
+    drop(rows);
+
+    let sign_filter = mismatched_sign_filter(is_income);
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT COALESCE(SUM({sign_filter}), 0), \
+                 COALESCE(SUM(split_id IS NOT NULL AND {sign_filter}), 0), \
+                 MIN(CASE WHEN {sign_filter} THEN date END) \
+                 FROM records WHERE owner_user_id = ? AND category_id IN ({})",
+                sql_placeholders(category_ids.len())
+            ),
+            owner_and_category_params(user_id, &category_ids),
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to count records to convert"))?;
+    let row = rows
+        .next()
+        .await
+        .map_err(|_| db_error())?
+        .ok_or_else(db_error)?;
+    let records_to_flip: u32 = row.get(0).map_err(|_| db_error())?;
+    let split_records_to_flip: u32 = row.get(1).map_err(|_| db_error())?;
+    let earliest_date: Option<String> = row.get(2).map_err(|_| db_error())?;
+    drop(rows);
+
+    if split_records_to_flip > 0 {
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "Category has {} from splits, whose amounts cannot change; move them to another category first",
+                record_count_phrase(split_records_to_flip)
+            ),
+        ));
+    }
+    if let Some(ref earliest_date) = earliest_date {
+        guard_closed_period(
+            conn,
+            user_id,
+            None,
+            "convert_category",
+            &[earliest_date],
+            false,
+        )
+        .await?;
+    }
+
+    Ok(CategoryConversionPlan {
+        category_id: category.id,
+        name: category.name,
+        is_income,
+        category_ids,
+        records_to_flip,
+    })
+}
+
+async fn apply_category_conversion(
+    conn: &libsql::Connection,
+    user_id: &str,
+    plan: &CategoryConversionPlan,
+) -> Result<u64, libsql::Error> {
+    let placeholders = sql_placeholders(plan.category_ids.len());
+    let flipped = conn
         .execute(
-            "DELETE FROM categories WHERE id = ? AND owner_user_id = ?",
-            (category_id.as_str(), user.id.as_str()),
+            &format!(
+                "UPDATE records SET amount = -amount WHERE owner_user_id = ? AND category_id IN ({placeholders}) AND {}",
+                mismatched_sign_filter(plan.is_income)
+            ),
+            owner_and_category_params(user_id, &plan.category_ids),
+        )
+        .await?;
+
+    // Income categories cannot carry a budget.
+    let clear_budget = if plan.is_income {
+        ", monthly_budget = NULL"
+    } else {
+        ""
+    };
+    let mut params = vec![libsql::Value::from(plan.is_income)];
+    params.extend(owner_and_category_params(user_id, &plan.category_ids));
+    conn.execute(
+        &format!(
+            "UPDATE categories SET is_income = ?{clear_budget} WHERE owner_user_id = ? AND id IN ({placeholders})"
+        ),
+        params,
+    )
+    .await?;
+    Ok(flipped)
+}
+
+/// The confirmation text for `plan`, stating how many records change sign.
+pub fn category_conversion_summary(plan: &CategoryConversionPlan) -> String {
+    let mut summary = format!(
+        "Switch \"{}\" from {} to {}",
+        plan.name,
+        category_type_name(!plan.is_income),
+        category_type_name(plan.is_income)
+    );
+    match plan.category_ids.len().saturating_sub(1) {
+        0 => {}
+        1 => summary.push_str(" together with its subcategory"),
+        count => summary.push_str(&format!(" together with its {count} subcategories")),
+    }
+    match plan.records_to_flip {
+        0 => summary.push_str(". No records change sign."),
+        count => summary.push_str(&format!(
+            ". This will flip the sign of {}.",
+            record_count_phrase(count)
+        )),
+    }
+    summary
+}
+
+/// Switches a category between income and expense. With `dry_run` only the plan is computed;
+/// otherwise it is recomputed and applied in one transaction.
+pub async fn convert_category_for_user(
+    db: &Db,
+    user_id: &str,
+    category_id: &str,
+    is_income: bool,
+    dry_run: bool,
+) -> Result<ConvertCategoryResponse, (StatusCode, String)> {
+    if dry_run {
+        let conn = db.read().await;
+        let plan = plan_category_conversion(&conn, user_id, category_id, is_income).await?;
+        return Ok(ConvertCategoryResponse {
+            dry_run,
+            plan,
+            records_flipped: 0,
+        });
+    }
+
+    let (plan, records_flipped) = with_transaction(db, |conn| {
+        let user_id = user_id.to_string();
+        let category_id = category_id.to_string();
+        Box::pin(async move {
+            let plan = plan_category_conversion(conn, &user_id, &category_id, is_income)
+                .await
+                .map_err(ConvertCategoryError::Rejected)?;
+            let records_flipped = apply_category_conversion(conn, &user_id, &plan)
+                .await
+                .map_err(|_| ConvertCategoryError::Db("failed to convert category"))?;
+            Ok((plan, records_flipped))
+        })
+    })
+    .await
+    .map_err(|e: ConvertCategoryError| -> (StatusCode, String) { e.into() })?;
+
+    Ok(ConvertCategoryResponse {
+        dry_run,
+        plan,
+        records_flipped: records_flipped as u32,
+    })
+}
+
+pub async fn convert_category(
+    State(app_state): State<AppState>,
+    session: Session,
+    Path(category_id): Path<String>,
+    Json(payload): Json<ConvertCategoryPayload>,
+) -> Result<(StatusCode, Json<ConvertCategoryResponse>), (StatusCode, String)> {
+    let user = get_current_user(&session).await?;
+    let response = convert_category_for_user(
+        &app_state.main_db,
+        &user.id,
+        &category_id,
+        payload.is_income,
+        payload.dry_run,
+    )
+    .await?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Validates a requested category change and describes it, without applying it.
+///
+/// Only one change per edit: renaming together with a type switch is refused so the
+/// confirmation covers exactly one effect. A type switch is planned as a dry run so the
+/// summary can say how many records change sign.
+pub async fn build_pending_category_edit(
+    db: &Db,
+    user_id: &str,
+    category_id: &str,
+    new_name: Option<&str>,
+    new_is_income: Option<bool>,
+) -> Result<PendingCategoryEdit, (StatusCode, String)> {
+    let conn = db.read().await;
+    let category = fetch_category(&conn, user_id, category_id)
+        .await?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Category not found".to_string()))?;
+
+    let new_name = new_name
+        .map(str::trim)
+        .filter(|name| !name.is_empty() && *name != category.name);
+    let new_is_income = new_is_income.filter(|is_income| *is_income != category.is_income);
+
+    match (new_name, new_is_income) {
+        (Some(_), Some(_)) => Err((
+            StatusCode::BAD_REQUEST,
+            CATEGORY_EDIT_ONE_CHANGE_MESSAGE.to_string(),
+        )),
+        (Some(new_name), None) => {
+            validate_category_name(new_name)?;
+            ensure_category_name_free(&conn, user_id, new_name, &category.id).await?;
+            Ok(PendingCategoryEdit {
+                summary: format!("Rename category \"{}\" to \"{}\".", category.name, new_name),
+                action: CategoryEditAction::Rename {
+                    category_id: category.id,
+                    new_name: new_name.to_string(),
+                },
+            })
+        }
+        (None, Some(is_income)) => {
+            let plan = plan_category_conversion(&conn, user_id, &category.id, is_income).await?;
+            Ok(PendingCategoryEdit {
+                summary: category_conversion_summary(&plan),
+                action: CategoryEditAction::Convert {
+                    category_id: category.id,
+                    is_income,
+                },
+            })
+        }
+        (None, None) => Err((
+            StatusCode::BAD_REQUEST,
+            "No change requested for this category".to_string(),
+        )),
+    }
+}
+
+/// Applies a confirmed category edit and describes the outcome. Every check is repeated,
+/// since the category may have changed while the edit waited.
+pub async fn execute_category_edit(
+    db: &Db,
+    user_id: &str,
+    action: &CategoryEditAction,
+) -> Result<String, (StatusCode, String)> {
+    match action {
+        CategoryEditAction::Rename {
+            category_id,
+            new_name,
+        } => {
+            validate_category_name(new_name)?;
+            let conn = db.write().await;
+            let category = fetch_category(&conn, user_id, category_id)
+                .await?
+                .ok_or_else(|| (StatusCode::NOT_FOUND, "Category not found".to_string()))?;
+            ensure_category_name_free(&conn, user_id, new_name, category_id).await?;
+            conn.execute(
+                "UPDATE categories SET name = ? WHERE id = ? AND owner_user_id = ?",
+                (new_name.as_str(), category_id.as_str(), user_id),
+            )
+            .await
+            .map_err(|_| db_error_with_context("failed to update category"))?;
+            Ok(format!(
+                "Renamed \"{}\" to \"{}\".",
+                category.name, new_name
+            ))
+        }
+        CategoryEditAction::Convert {
+            category_id,
+            is_income,
+        } => {
+            let response =
+                convert_category_for_user(db, user_id, category_id, *is_income, false).await?;
+            Ok(format!(
+                "\"{}\" is now an {} category; {} changed sign.",
+                response.plan.name,
+                category_type_name(*is_income),
+                record_count_phrase(response.records_flipped)
+            ))
+        }
+    }
+}
+
+enum MergeCategoriesError {
+    Transaction(TransactionError),
+    Db(&'static str),
+    Rejected((StatusCode, String)),
+}
+
+impl From<TransactionError> for MergeCategoriesError {
+    fn from(e: TransactionError) -> Self {
+        MergeCategoriesError::Transaction(e)
+    }
+}
+
+impl From<MergeCategoriesError> for (StatusCode, String) {
+    fn from(e: MergeCategoriesError) -> Self {
+        match e {
+            MergeCategoriesError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction")
+            }
+            MergeCategoriesError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction")
+            }
+            MergeCategoriesError::Transaction(TransactionError::Busy) => database_busy(),
+            MergeCategoriesError::Db(ctx) => db_error_with_context(ctx),
+            MergeCategoriesError::Rejected(error) => error,
+        }
+    }
+}
+
+/// The source and target of a merge: two distinct categories of the user's of the same type,
+/// the source without subcategories.
+async fn validate_category_merge(
+    conn: &libsql::Connection,
+    user_id: &str,
+    source_id: &str,
+    target_id: &str,
+) -> Result<(Category, Category), (StatusCode, String)> {
+    if source_id == target_id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Cannot merge a category into itself".to_string(),
+        ));
+    }
+    let source = fetch_category(conn, user_id, source_id)
+        .await?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                "Source category not found".to_string(),
+            )
+        })?;
+    let target = fetch_category(conn, user_id, target_id)
+        .await?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                "Target category not found".to_string(),
+            )
+        })?;
+
+    if source.is_income != target.is_income {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Cannot merge an {} category into an {} category",
+                category_type_name(source.is_income),
+                category_type_name(target.is_income)
+            ),
+        ));
+    }
+    if count_child_categories(conn, user_id, source_id).await? > 0 {
+        return Err((
+            StatusCode::CONFLICT,
+            "Cannot merge a category with subcategories; move or delete them first".to_string(),
+        ));
+    }
+    Ok((source, target))
+}
+
+/// Moves every record of `source_id` to `target_id` and deletes the source, in one transaction.
+/// Both are the same type, so amounts keep their sign.
+pub async fn merge_categories_for_user(
+    db: &Db,
+    user_id: &str,
+    source_id: &str,
+    target_id: &str,
+) -> Result<MergeCategoriesResponse, (StatusCode, String)> {
+    with_transaction(db, |conn| {
+        let user_id = user_id.to_string();
+        let source_id = source_id.trim().to_string();
+        let target_id = target_id.trim().to_string();
+        Box::pin(async move {
+            let (source, target) = validate_category_merge(conn, &user_id, &source_id, &target_id)
+                .await
+                .map_err(MergeCategoriesError::Rejected)?;
+            let records_moved = count_category_records(conn, &user_id, &source.id)
+                .await
+                .map_err(MergeCategoriesError::Rejected)?;
+
+            conn.execute(
+                "UPDATE records SET category_id = ? WHERE category_id = ? AND owner_user_id = ?",
+                (target.id.as_str(), source.id.as_str(), user_id.as_str()),
+            )
+            .await
+            .map_err(|_| MergeCategoriesError::Db("failed to move records"))?;
+            conn.execute(
+                "DELETE FROM categories WHERE id = ? AND owner_user_id = ?",
+                (source.id.as_str(), user_id.as_str()),
+            )
+            .await
+            .map_err(|_| MergeCategoriesError::Db("failed to delete category"))?;
+
+            Ok(MergeCategoriesResponse {
+                category: target,
+                records_moved,
+            })
+        })
+    })
+    .await
+    .map_err(|e: MergeCategoriesError| -> (StatusCode, String) { e.into() })
+}
+
+pub async fn merge_categories(
+    State(app_state): State<AppState>,
+    session: Session,
+    Json(payload): Json<MergeCategoriesPayload>,
+) -> Result<(StatusCode, Json<MergeCategoriesResponse>), (StatusCode, String)> {
+    let user = get_current_user(&session).await?;
+    let response = merge_categories_for_user(
+        &app_state.main_db,
+        &user.id,
+        &payload.source_id,
+        &payload.target_id,
+    )
+    .await?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Recent records in one category, newest first: `GET /records` with the category forced.
+pub async fn get_category_activity(
+    State(app_state): State<AppState>,
+    session: Session,
+    Path(category_id): Path<String>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<(StatusCode, Json<GetRecordsResponse>), (StatusCode, String)> {
+    let user = get_current_user(&session).await?;
+    let limit = validate_records_limit(query.limit)?;
+    let offset = validate_offset(query.offset)?;
+
+    let conn = app_state.main_db.read().await;
+    fetch_category(&conn, &user.id, &category_id)
+        .await?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Category not found".to_string()))?;
+
+    let filter = RecordFilter {
+        start_date: "0000-01-01",
+        end_date: "9999-12-31",
+        pending: None,
+        settle: None,
+        category_id: Some(&category_id),
+        name_contains: None,
+        min_amount: None,
+        max_amount: None,
+        tag: None,
+        sort: RecordSort::default(),
+        after: None,
+    };
+    let mut page = list_records_page(&conn, &user.id, &filter, limit, offset, true).await?;
+    // The feed pages by offset; a cursor would continue `GET /records`, not this category
+    page.next_cursor = None;
+
+    Ok((StatusCode::OK, Json(page)))
+}
+
+/// Budget, spending and what is left for each of the user's budgeted categories in `month`
+/// (`YYYY-MM`), by name. Spending counts live, non-pending expense records dated that month.
+pub async fn budget_status_for_user(
+    conn: &libsql::Connection,
+    user_id: &str,
+    month: &str,
+) -> Result<BudgetStatusResponse, (StatusCode, String)> {
+    let month = month_window(month, 1)?.remove(0);
+    let mut rows = conn
+        .query(
+            "SELECT c.id, c.name, c.monthly_budget, COALESCE(-SUM(r.amount), 0) \
+             FROM categories c \
+             LEFT JOIN records r ON r.category_id = c.id AND r.owner_user_id = c.owner_user_id \
+             AND r.deleted_at IS NULL AND r.pending = 0 AND r.amount < 0 AND r.date BETWEEN ? AND ? \
+             WHERE c.owner_user_id = ? AND c.monthly_budget IS NOT NULL \
+             GROUP BY c.id ORDER BY c.name ASC",
+            (format!("{month}-01"), format!("{month}-31"), user_id),
         )
         .await
-        .map_err(|_| db_error_with_context("failed to delete category"))?;
+        .map_err(|_| db_error_with_context("failed to query budgets"))?;
 
-    if affected_rows == 0 {
-        return Err((StatusCode::NOT_FOUND, "Category not found".to_string()));
+    let invalid = |_| db_error_with_context("invalid budget data");
+    let mut categories = Vec::new();
+    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        let monthly_budget = Money::from_cents(row.get(2).map_err(invalid)?);
+        let spent = Money::from_cents(row.get(3).map_err(invalid)?);
+        categories.push(CategoryBudgetStatus {
+            category_id: row.get(0).map_err(invalid)?,
+            category_name: row.get(1).map_err(invalid)?,
+            monthly_budget,
+            spent,
+            remaining: monthly_budget - spent,
+        });
     }
 
-    Ok(StatusCode::NO_CONTENT)
+    Ok(BudgetStatusResponse { month, categories })
+}
+
+pub async fn get_budget_status(
+    State(app_state): State<AppState>,
+    session: Session,
+    Query(query): Query<BudgetStatusQuery>,
+) -> Result<(StatusCode, Json<BudgetStatusResponse>), (StatusCode, String)> {
+    let user = get_current_user(&session).await?;
+    let month = query.month.unwrap_or_else(current_month);
+
+    let conn = app_state.main_db.read().await;
+    let status = budget_status_for_user(&conn, &user.id, &month).await?;
+
+    Ok((StatusCode::OK, Json(status)))
 }
@@ -0,0 +1,423 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use password_hash::rand_core::{OsRng, RngCore};
+use serde_json::{Value, json};
+
+use crate::auth;
+use crate::config::{CliConfig, DatabaseLocation};
+use crate::constants::*;
+use crate::crypto;
+use crate::database::{self, Db};
+use crate::export::{self, ExportOptions};
+use crate::instance_lock;
+use crate::models::PublicUser;
+
+pub const USAGE: &str = "\
+Usage: kash-server [COMMAND] [--json]
+
+Without a command, runs the HTTP server.
+
+Commands:
+  user list                           List accounts
+  user reset-password <name>          Set and print a new random password
+  user unlock <name>                  Re-enable a disabled account
+  db check                            Run integrity and schema checks
+  db migrate                          Create missing tables and columns
+  export --user <name> --out <file>   Write the user's export archive to a file
+
+Options:
+  --json      Print machine-readable JSON instead of text
+  -h, --help  Print this help
+
+Commands refuse to run while a server holds the data directory.";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Help,
+    UserList,
+    UserResetPassword { username: String },
+    UserUnlock { username: String },
+    DbCheck,
+    DbMigrate,
+    Export { username: String, out: PathBuf },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    pub command: Command,
+    pub json: bool,
+}
+
+/// What a command prints and the process exit code (`CLI_EXIT_*`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliOutput {
+    pub exit_code: i32,
+    pub text: String,
+}
+
+/// Parses the arguments after the program name. `Ok(None)` means no command was
+/// given and the server should start; `Err` carries a usage error.
+pub fn parse_args(args: Vec<OsString>) -> Result<Option<Invocation>, String> {
+    let mut args = pico_args::Arguments::from_vec(args);
+    let json = args.contains("--json");
+    if args.contains(["-h", "--help"]) {
+        return Ok(Some(Invocation {
+            command: Command::Help,
+            json,
+        }));
+    }
+
+    let command = match subcommand(&mut args)?.as_deref() {
+        None => {
+            reject_remaining(args)?;
+            return if json {
+                Err("--json needs a command".to_string())
+            } else {
+                Ok(None)
+            };
+        }
+        Some("user") => match subcommand(&mut args)?.as_deref() {
+            Some("list") => Command::UserList,
+            Some("reset-password") => Command::UserResetPassword {
+                username: username_arg(&mut args)?,
+            },
+            Some("unlock") => Command::UserUnlock {
+                username: username_arg(&mut args)?,
+            },
+            Some(other) => return Err(format!("unknown user command '{}'", other)),
+            None => return Err("user needs a command: list, reset-password, unlock".to_string()),
+        },
+        Some("db") => match subcommand(&mut args)?.as_deref() {
+            Some("check") => Command::DbCheck,
+            Some("migrate") => Command::DbMigrate,
+            Some(other) => return Err(format!("unknown db command '{}'", other)),
+            None => return Err("db needs a command: check, migrate".to_string()),
+        },
+        Some("export") => {
+            let username = args
+                .opt_value_from_str("--user")
+                .map_err(|e| e.to_string())?
+                .ok_or("export needs --user <name>")?;
+            let out = args
+                .opt_value_from_os_str("--out", |value| {
+                    Ok::<_, std::convert::Infallible>(PathBuf::from(value))
+                })
+                .map_err(|e| e.to_string())?
+                .ok_or("export needs --out <file>")?;
+            Command::Export { username, out }
+        }
+        Some(other) => return Err(format!("unknown command '{}'", other)),
+    };
+
+    reject_remaining(args)?;
+    Ok(Some(Invocation { command, json }))
+}
+
+fn subcommand(args: &mut pico_args::Arguments) -> Result<Option<String>, String> {
+    args.subcommand().map_err(|e| e.to_string())
+}
+
+fn username_arg(args: &mut pico_args::Arguments) -> Result<String, String> {
+    args.opt_free_from_str()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "missing <name>".to_string())
+}
+
+fn reject_remaining(args: pico_args::Arguments) -> Result<(), String> {
+    match args.finish().first() {
+        Some(extra) => Err(format!("unexpected argument '{}'", extra.to_string_lossy())),
+        None => Ok(()),
+    }
+}
+
+struct Failure {
+    exit_code: i32,
+    message: String,
+}
+
+impl Failure {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            exit_code: CLI_EXIT_FAILURE,
+            message: message.into(),
+        }
+    }
+}
+
+impl<E: std::fmt::Display> From<E> for Failure {
+    fn from(value: E) -> Self {
+        Self::new(value.to_string())
+    }
+}
+
+/// Result of a command: JSON for `--json` and the same facts as text.
+struct Report {
+    exit_code: i32,
+    json: Value,
+    text: String,
+}
+
+impl Report {
+    fn ok(json: Value, text: String) -> Self {
+        Self {
+            exit_code: CLI_EXIT_OK,
+            json,
+            text,
+        }
+    }
+}
+
+/// Runs one operator command against the data directory in `config`.
+///
+/// Everything except `help` takes the instance lock first and gives up with
+/// `CLI_EXIT_LOCKED` while a server holds it, so the CLI is never a second writer
+/// next to a live server. The lock is held until the command finishes.
+pub async fn run(config: &CliConfig, invocation: &Invocation) -> CliOutput {
+    if invocation.command == Command::Help {
+        return CliOutput {
+            exit_code: CLI_EXIT_OK,
+            text: USAGE.to_string(),
+        };
+    }
+
+    let result = match instance_lock::acquire(&config.data_path) {
+        Ok(_lock) => execute(config, &invocation.command).await,
+        Err(e @ instance_lock::InstanceLockError::Held(_)) => Err(Failure {
+            exit_code: CLI_EXIT_LOCKED,
+            message: e.to_string(),
+        }),
+        Err(e) => Err(e.into()),
+    };
+
+    match (result, invocation.json) {
+        (Ok(report), true) => CliOutput {
+            exit_code: report.exit_code,
+            text: serde_json::to_string_pretty(&report.json).unwrap_or_default(),
+        },
+        (Ok(report), false) => CliOutput {
+            exit_code: report.exit_code,
+            text: report.text,
+        },
+        (Err(failure), true) => CliOutput {
+            exit_code: failure.exit_code,
+            text: json!({ "error": failure.message }).to_string(),
+        },
+        (Err(failure), false) => CliOutput {
+            exit_code: failure.exit_code,
+            text: format!("error: {}", failure.message),
+        },
+    }
+}
+
+async fn execute(config: &CliConfig, command: &Command) -> Result<Report, Failure> {
+    match command {
+        Command::Help => Ok(Report::ok(Value::Null, USAGE.to_string())),
+        Command::DbCheck => db_check(config).await,
+        Command::DbMigrate => db_migrate(config).await,
+        Command::UserList => {
+            let db = open_db(config).await?;
+            let users = auth::list_users(&*db.read().await).await?;
+            let mut text = format!(
+                "{:<24} {:<36} {:<10} {}",
+                "USERNAME", "ID", "STATUS", "LAST LOGIN"
+            );
+            for user in &users {
+                text.push_str(&format!(
+                    "\n{:<24} {:<36} {:<10} {}",
+                    user.username,
+                    user.id,
+                    if user.disabled_at.is_some() {
+                        "disabled"
+                    } else {
+                        "active"
+                    },
+                    user.last_login_at.as_deref().unwrap_or("never")
+                ));
+            }
+            Ok(Report::ok(json!({ "users": users }), text))
+        }
+        Command::UserResetPassword { username } => {
+            auth::install_password_hash_params(config.password_hash);
+            let db = open_db(config).await?;
+            let user = find_user(&db, username).await?;
+            let password = generate_password();
+            if !auth::set_password(&db, &user.id, &password).await? {
+                return Err(Failure::new(format!("no user named '{}'", username)));
+            }
+            Ok(Report::ok(
+                json!({ "user_id": user.id, "username": user.username, "password": password }),
+                format!("New password for {}: {}", user.username, password),
+            ))
+        }
+        Command::UserUnlock { username } => {
+            let db = open_db(config).await?;
+            let user = find_user(&db, username).await?;
+            let unlocked = auth::enable_user(&db, &user.id).await?;
+            let text = if unlocked {
+                format!("{} can log in again", user.username)
+            } else {
+                format!("{} was not disabled; nothing changed", user.username)
+            };
+            Ok(Report::ok(
+                json!({ "user_id": user.id, "username": user.username, "unlocked": unlocked }),
+                text,
+            ))
+        }
+        Command::Export { username, out } => {
+            let db = open_db(config).await?;
+            crypto::init_record_encryption(&db, config.record_encryption_key.clone()).await?;
+            let user = find_user(&db, username).await?;
+            let archive =
+                export::build_export(&*db.read().await, user.clone(), &ExportOptions::default())
+                    .await
+                    .map_err(|(_, message)| Failure::new(message))?;
+            let records = archive["records"].as_array().map_or(0, Vec::len);
+            let body = serde_json::to_vec_pretty(&archive)?;
+            tokio::fs::write(out, body)
+                .await
+                .map_err(|e| Failure::new(format!("writing {}: {}", out.display(), e)))?;
+            Ok(Report::ok(
+                json!({
+                    "user_id": user.id,
+                    "username": user.username,
+                    "out": out,
+                    "records": records,
+                }),
+                format!(
+                    "Exported {} ({} records) to {}",
+                    user.username,
+                    records,
+                    out.display()
+                ),
+            ))
+        }
+    }
+}
+
+async fn open_db(config: &CliConfig) -> anyhow::Result<Db> {
+    database::init_main_db_at(&config.data_path, &config.database).await
+}
+
+/// What `db check` and `db migrate` report on: the file, or the remote database's URL.
+fn database_name(config: &CliConfig) -> String {
+    match &config.database {
+        DatabaseLocation::Local => database::main_db_path(&config.data_path)
+            .display()
+            .to_string(),
+        DatabaseLocation::Remote { url, .. } | DatabaseLocation::Replica { url, .. } => url.clone(),
+    }
+}
+
+async fn db_check(config: &CliConfig) -> Result<Report, Failure> {
+    let path = database_name(config);
+    let Some(conn) = database::open_main_db_at(&config.data_path, &config.database).await? else {
+        return Err(Failure::new(format!("no database at {}", path)));
+    };
+    let report = database::check_main_db(&conn).await?;
+
+    let mut text = Vec::new();
+    for message in &report.integrity_errors {
+        text.push(format!("integrity: {}", message));
+    }
+    if !report.missing_tables.is_empty() {
+        text.push(format!(
+            "missing tables (run `db migrate`): {}",
+            report.missing_tables.join(", ")
+        ));
+    }
+    let healthy = report.is_healthy();
+    if healthy {
+        text.push(format!("{}: ok", path));
+    }
+    Ok(Report {
+        exit_code: if healthy {
+            CLI_EXIT_OK
+        } else {
+            CLI_EXIT_FAILURE
+        },
+        json: json!({ "database": path, "healthy": healthy, "report": report }),
+        text: text.join("\n"),
+    })
+}
+
+async fn db_migrate(config: &CliConfig) -> Result<Report, Failure> {
+    let path = database_name(config);
+    let (created_tables, from_version) =
+        match database::open_main_db_at(&config.data_path, &config.database).await? {
+            Some(conn) => (
+                database::check_main_db(&conn).await?.missing_tables,
+                database::applied_schema_version(&conn).await?,
+            ),
+            None => (
+                database::MAIN_DB_TABLES
+                    .iter()
+                    .map(|table| table.to_string())
+                    .collect(),
+                0,
+            ),
+        };
+    open_db(config).await?;
+    let schema_version = database::latest_schema_version();
+    let applied_migrations: Vec<u32> = database::MIGRATIONS
+        .iter()
+        .map(|migration| migration.version)
+        .filter(|version| *version > from_version)
+        .collect();
+
+    let text = if created_tables.is_empty() && applied_migrations.is_empty() {
+        format!(
+            "{}: schema is up to date (version {})",
+            path, schema_version
+        )
+    } else {
+        let mut changes = Vec::new();
+        if !created_tables.is_empty() {
+            changes.push(format!("created {}", created_tables.join(", ")));
+        }
+        if !applied_migrations.is_empty() {
+            changes.push(format!(
+                "applied schema versions {}",
+                applied_migrations
+                    .iter()
+                    .map(|version| version.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        format!("{}: migrated, {}", path, changes.join("; "))
+    };
+    Ok(Report::ok(
+        json!({
+            "database": path,
+            "created_tables": created_tables,
+            "applied_migrations": applied_migrations,
+            "schema_version": schema_version,
+        }),
+        text,
+    ))
+}
+
+async fn find_user(db: &Db, username: &str) -> Result<PublicUser, Failure> {
+    match auth::get_user_by_username(db, username).await? {
+        Some(user) => Ok(PublicUser {
+            id: user.id,
+            username: user.username,
+        }),
+        None => Err(Failure::new(format!("no user named '{}'", username))),
+    }
+}
+
+/// Random letters and digits, `CLI_RESET_PASSWORD_LENGTH` long.
+fn generate_password() -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz23456789";
+    let mut password = String::with_capacity(CLI_RESET_PASSWORD_LENGTH);
+    while password.len() < CLI_RESET_PASSWORD_LENGTH {
+        let byte = (OsRng.next_u32() & 0xff) as usize;
+        // Rejection sampling keeps every character equally likely.
+        if byte < 256 - 256 % ALPHABET.len() {
+            password.push(ALPHABET[byte % ALPHABET.len()] as char);
+        }
+    }
+    password
+}
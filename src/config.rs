@@ -1,5 +1,8 @@
+use crate::config_sources::ConfigSources;
 use crate::constants::*;
-use std::env;
+use crate::crypto::MasterKey;
+use axum::http::HeaderValue;
+use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -7,6 +10,655 @@ pub struct Config {
     pub port: String,
     pub data_path: String,
     pub session_secret: String,
+    pub friendship_retention: FriendshipRetention,
+    /// Bearer token for the `/admin` routes; they are disabled when unset.
+    pub admin_token: Option<String>,
+    /// Responses larger than this are stored as a digest and cannot be replayed.
+    pub idempotency_max_body_bytes: usize,
+    /// Seconds between sweeps of expired idempotency keys.
+    pub idempotency_cleanup_interval_secs: u64,
+    /// Data rows accepted by one `POST /records/import`.
+    pub csv_import_max_rows: usize,
+    /// Master key for encrypting record names at rest; required once any user opts in.
+    pub record_encryption_key: Option<MasterKey>,
+    /// Run `selftest::run_startup_self_test` before accepting traffic.
+    pub startup_self_test: bool,
+    /// Argon2id cost for new password hashes; weaker stored hashes are upgraded at login.
+    pub password_hash: PasswordHashParams,
+    /// Rules for passwords set at registration or changed; existing ones are not rechecked.
+    pub password_policy: PasswordPolicy,
+    /// How long writers wait out another process holding `users.db`.
+    pub db_busy_retry: DbBusyRetry,
+    /// `users.db` under `data_path`, or a hosted libsql database.
+    pub database: DatabaseLocation,
+    /// Categories created for each account registered through `POST /auth/register`.
+    pub default_categories: DefaultCategories,
+    /// Origins the CORS layer allows, with credentials; never empty.
+    pub frontend_origins: Vec<HeaderValue>,
+    /// Where login sessions are kept.
+    pub session_store: SessionStoreKind,
+    /// Failed logins allowed per client and username before `/auth/login` answers 429.
+    pub login_rate_limit: LoginRateLimit,
+    /// Where database snapshots go, how often they are taken and how long they are kept.
+    pub backup: BackupConfig,
+    /// Serve HTTPS with this certificate; `None` serves plain HTTP.
+    pub tls: Option<TlsConfig>,
+    /// Largest request bodies accepted, before any handler reads them.
+    pub body_limits: BodyLimits,
+    /// `PRODUCTION=true`: mark session cookies secure behind an HTTPS reverse proxy.
+    pub production: bool,
+}
+
+/// The subset of `Config` the operator CLI needs. Loads without `SESSION_SECRET`
+/// or the HTTP settings, so a broken server configuration can still be repaired.
+#[derive(Debug, Clone)]
+pub struct CliConfig {
+    pub data_path: String,
+    pub record_encryption_key: Option<MasterKey>,
+    pub password_hash: PasswordHashParams,
+    pub db_busy_retry: DbBusyRetry,
+    pub database: DatabaseLocation,
+}
+
+impl CliConfig {
+    /// Reads the environment and the config file; see `ConfigSources::load`.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::from_sources(&ConfigSources::load()?)
+    }
+
+    /// Also the settings the bot shares with the server.
+    pub fn from_sources(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        let data_path = sources
+            .value("DATABASE_PATH")
+            .unwrap_or(DEFAULT_DATA_PATH)
+            .to_string();
+
+        let record_encryption_key = match sources.get("RECORD_ENCRYPTION_KEY") {
+            Some(setting) if !setting.value.trim().is_empty() => Some(setting.parse(|value| {
+                MasterKey::from_hex(value).map_err(|_| ConfigError::InvalidRecordEncryptionKey)
+            })?),
+            _ => None,
+        };
+
+        let password_hash = PasswordHashParams::from_sources(sources)?;
+        let db_busy_retry = DbBusyRetry::from_sources(sources)?;
+        let database = DatabaseLocation::from_sources(sources)?;
+
+        Ok(CliConfig {
+            data_path,
+            record_encryption_key,
+            password_hash,
+            db_busy_retry,
+            database,
+        })
+    }
+}
+
+/// Argon2id cost parameters, each within its `MIN_ARGON2_*..=MAX_ARGON2_*` range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordHashParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for PasswordHashParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: DEFAULT_ARGON2_MEMORY_KIB,
+            iterations: DEFAULT_ARGON2_ITERATIONS,
+            parallelism: DEFAULT_ARGON2_PARALLELISM,
+        }
+    }
+}
+
+impl PasswordHashParams {
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Self, ConfigError> {
+        for (name, value, min, max) in [
+            (
+                "ARGON2_MEMORY_KIB",
+                memory_kib,
+                MIN_ARGON2_MEMORY_KIB,
+                MAX_ARGON2_MEMORY_KIB,
+            ),
+            (
+                "ARGON2_ITERATIONS",
+                iterations,
+                MIN_ARGON2_ITERATIONS,
+                MAX_ARGON2_ITERATIONS,
+            ),
+            (
+                "ARGON2_PARALLELISM",
+                parallelism,
+                MIN_ARGON2_PARALLELISM,
+                MAX_ARGON2_PARALLELISM,
+            ),
+        ] {
+            if !(min..=max).contains(&value) {
+                return Err(ConfigError::InvalidPasswordHashParams(format!(
+                    "{} must be between {} and {}, got {}",
+                    name, min, max, value
+                )));
+            }
+        }
+        Ok(Self {
+            memory_kib,
+            iterations,
+            parallelism,
+        })
+    }
+
+    /// Reads `ARGON2_MEMORY_KIB`, `ARGON2_ITERATIONS` and `ARGON2_PARALLELISM`;
+    /// unset variables keep their defaults. Shared by the server and the bot.
+    pub fn from_sources(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        let defaults = Self::default();
+        Self::new(
+            argon2_cost(sources, "ARGON2_MEMORY_KIB", defaults.memory_kib)?,
+            argon2_cost(sources, "ARGON2_ITERATIONS", defaults.iterations)?,
+            argon2_cost(sources, "ARGON2_PARALLELISM", defaults.parallelism)?,
+        )
+        .map_err(|e| {
+            sources.blame(
+                &[
+                    "ARGON2_MEMORY_KIB",
+                    "ARGON2_ITERATIONS",
+                    "ARGON2_PARALLELISM",
+                ],
+                e,
+            )
+        })
+    }
+}
+
+/// What a new password must satisfy besides not being common or the username.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    /// Fewest characters, at least `MIN_PASSWORD_LENGTH_FLOOR`.
+    pub min_length: usize,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: DEFAULT_MIN_PASSWORD_LENGTH,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Reads `PASSWORD_MIN_LENGTH`; unset keeps `DEFAULT_MIN_PASSWORD_LENGTH`.
+    pub fn from_sources(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        match sources.get("PASSWORD_MIN_LENGTH") {
+            Some(setting) => setting.parse(|value| {
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|length| *length >= MIN_PASSWORD_LENGTH_FLOOR)
+                    .map(|min_length| Self { min_length })
+                    .ok_or_else(|| {
+                        ConfigError::InvalidPasswordPolicy(format!(
+                            "PASSWORD_MIN_LENGTH must be a whole number of at least {}, got {}",
+                            MIN_PASSWORD_LENGTH_FLOOR, value
+                        ))
+                    })
+            }),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+/// How a write waits when another connection (the bot, the operator CLI) holds the
+/// database: SQLite's own `busy_timeout` first, then `max_retries` more attempts with
+/// doubling, jittered delays starting at `base_delay_ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DbBusyRetry {
+    pub busy_timeout_ms: u64,
+    pub max_retries: u32,
+    pub base_delay_ms: u64,
+}
+
+impl Default for DbBusyRetry {
+    fn default() -> Self {
+        Self {
+            busy_timeout_ms: DEFAULT_DB_BUSY_TIMEOUT_MS,
+            max_retries: DEFAULT_DB_BUSY_MAX_RETRIES,
+            base_delay_ms: DEFAULT_DB_BUSY_BASE_DELAY_MS,
+        }
+    }
+}
+
+impl DbBusyRetry {
+    /// Reads `DB_BUSY_TIMEOUT_MS`, `DB_BUSY_MAX_RETRIES` and `DB_BUSY_BASE_DELAY_MS`;
+    /// unset variables keep their defaults. Shared by the server, the CLI and the bot.
+    pub fn from_sources(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        let defaults = Self::default();
+        let read = |name: &str, default: u64| match sources.get(name) {
+            Some(setting) => setting.parse(|value| {
+                value.trim().parse::<u64>().map_err(|_| {
+                    ConfigError::InvalidDbBusyRetry(format!(
+                        "{name} must be a non-negative integer, got {value}"
+                    ))
+                })
+            }),
+            None => Ok(default),
+        };
+        let max_retries = read("DB_BUSY_MAX_RETRIES", u64::from(defaults.max_retries))?;
+        Ok(Self {
+            busy_timeout_ms: read("DB_BUSY_TIMEOUT_MS", defaults.busy_timeout_ms)?,
+            max_retries: u32::try_from(max_retries).map_err(|_| {
+                sources.blame(
+                    &["DB_BUSY_MAX_RETRIES"],
+                    ConfigError::InvalidDbBusyRetry(format!(
+                        "DB_BUSY_MAX_RETRIES is too large: {max_retries}"
+                    )),
+                )
+            })?,
+            base_delay_ms: read("DB_BUSY_BASE_DELAY_MS", defaults.base_delay_ms)?,
+        })
+    }
+}
+
+/// Where the main database lives. Every mode keeps the single shared database; a
+/// remote one is the same schema on a libsql server such as Turso.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DatabaseLocation {
+    /// `users.db` under `DATABASE_PATH`.
+    #[default]
+    Local,
+    /// Every statement goes to the server at `url`.
+    Remote { url: String, auth_token: String },
+    /// An embedded replica at `users.db` under `DATABASE_PATH`: reads are local, writes
+    /// go to `url`, and the file pulls the server's changes every `sync_interval_secs`.
+    Replica {
+        url: String,
+        auth_token: String,
+        sync_interval_secs: u64,
+    },
+}
+
+impl DatabaseLocation {
+    /// A blank `url` means a local file. A URL must be `libsql://`, `https://` or
+    /// `http://` and comes with a token; `replica_sync_secs` makes it an embedded replica.
+    pub fn new(
+        url: Option<&str>,
+        auth_token: Option<&str>,
+        replica_sync_secs: Option<&str>,
+    ) -> Result<Self, ConfigError> {
+        let Some(url) = url.map(str::trim).filter(|url| !url.is_empty()) else {
+            return Ok(Self::Local);
+        };
+        if !["libsql://", "https://", "http://"]
+            .iter()
+            .any(|scheme| url.starts_with(scheme))
+        {
+            return Err(ConfigError::InvalidDatabaseUrl(url.to_string()));
+        }
+        let auth_token = auth_token
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .ok_or(ConfigError::MissingDatabaseAuthToken)?
+            .to_string();
+        let url = url.to_string();
+        match replica_sync_secs {
+            None => Ok(Self::Remote { url, auth_token }),
+            Some(value) => {
+                let sync_interval_secs = value
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|secs| *secs > 0)
+                    .ok_or_else(|| ConfigError::InvalidDatabaseReplicaSync(value.to_string()))?;
+                Ok(Self::Replica {
+                    url,
+                    auth_token,
+                    sync_interval_secs,
+                })
+            }
+        }
+    }
+
+    /// Reads `DATABASE_URL`, `DATABASE_AUTH_TOKEN` and `DATABASE_REPLICA_SYNC_SECS`.
+    /// Shared by the server, the CLI and the bot.
+    pub fn from_sources(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        const KEYS: [&str; 3] = [
+            "DATABASE_URL",
+            "DATABASE_AUTH_TOKEN",
+            "DATABASE_REPLICA_SYNC_SECS",
+        ];
+        Self::new(
+            sources.value(KEYS[0]),
+            sources.value(KEYS[1]),
+            sources.value(KEYS[2]),
+        )
+        .map_err(|e| sources.blame(&KEYS, e))
+    }
+
+    pub fn is_local(&self) -> bool {
+        matches!(self, Self::Local)
+    }
+}
+
+/// PEM certificate chain and private key for serving HTTPS directly. Both files are
+/// read again on SIGHUP and every `reload_interval_secs`, so a renewed certificate
+/// is picked up without a restart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+    /// `None` reloads on SIGHUP only.
+    pub reload_interval_secs: Option<u64>,
+}
+
+impl TlsConfig {
+    /// `None` (plain HTTP) when neither path is set; the two paths go together.
+    pub fn new(
+        cert_path: Option<&str>,
+        key_path: Option<&str>,
+        reload_interval_secs: Option<&str>,
+    ) -> Result<Option<Self>, ConfigError> {
+        fn set(value: Option<&str>) -> Option<&str> {
+            value.map(str::trim).filter(|v| !v.is_empty())
+        }
+        let (cert_path, key_path) = match (set(cert_path), set(key_path)) {
+            (None, None) => return Ok(None),
+            (Some(cert_path), Some(key_path)) => (cert_path.to_string(), key_path.to_string()),
+            _ => {
+                return Err(ConfigError::InvalidTls(
+                    "TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string(),
+                ));
+            }
+        };
+        let reload_interval_secs = match reload_interval_secs {
+            Some(value) => match value.trim().parse::<u64>() {
+                Ok(0) => None,
+                Ok(secs) => Some(secs),
+                Err(_) => {
+                    return Err(ConfigError::InvalidTls(format!(
+                        "TLS_RELOAD_INTERVAL_SECS must be a whole number of seconds, got {value}"
+                    )));
+                }
+            },
+            None => None,
+        };
+        Ok(Some(Self {
+            cert_path,
+            key_path,
+            reload_interval_secs,
+        }))
+    }
+
+    /// Reads `TLS_CERT_PATH`, `TLS_KEY_PATH` and `TLS_RELOAD_INTERVAL_SECS`.
+    pub fn from_sources(sources: &ConfigSources) -> Result<Option<Self>, ConfigError> {
+        const KEYS: [&str; 3] = ["TLS_CERT_PATH", "TLS_KEY_PATH", "TLS_RELOAD_INTERVAL_SECS"];
+        Self::new(
+            sources.value(KEYS[0]),
+            sources.value(KEYS[1]),
+            sources.value(KEYS[2]),
+        )
+        .map_err(|e| sources.blame(&KEYS, e))
+    }
+}
+
+/// Snapshots of `users.db`, taken by `POST /admin/backup` and, every `interval_hours`,
+/// by a job. Each is a timestamped folder under `path`; taking one deletes those older
+/// than `retention_days`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupConfig {
+    pub path: String,
+    pub retention_days: u32,
+    /// `None` takes snapshots only on request.
+    pub interval_hours: Option<u32>,
+}
+
+impl BackupConfig {
+    /// Snapshots in `backups` under the data directory, kept `DEFAULT_BACKUP_RETENTION_DAYS`.
+    pub fn new(data_path: &str) -> Self {
+        Self {
+            path: std::path::Path::new(data_path)
+                .join(BACKUP_DIR_NAME)
+                .to_string_lossy()
+                .to_string(),
+            retention_days: DEFAULT_BACKUP_RETENTION_DAYS,
+            interval_hours: None,
+        }
+    }
+
+    /// Reads `BACKUP_PATH`, `BACKUP_RETENTION_DAYS` (positive) and `BACKUP_INTERVAL_HOURS`
+    /// (`0` or unset schedules none).
+    pub fn from_sources(sources: &ConfigSources, data_path: &str) -> Result<Self, ConfigError> {
+        let defaults = Self::new(data_path);
+        let path = match sources.value("BACKUP_PATH") {
+            Some(path) if !path.trim().is_empty() => path.trim().to_string(),
+            _ => defaults.path,
+        };
+        let retention_days = match sources.get("BACKUP_RETENTION_DAYS") {
+            Some(setting) => setting.parse(|value| {
+                value
+                    .trim()
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|days| *days > 0)
+                    .ok_or_else(|| {
+                        ConfigError::InvalidBackupSettings(format!(
+                            "BACKUP_RETENTION_DAYS must be a positive integer, got {value}"
+                        ))
+                    })
+            })?,
+            None => defaults.retention_days,
+        };
+        let interval_hours = match sources.get("BACKUP_INTERVAL_HOURS") {
+            Some(setting) => setting.parse(|value| match value.trim().parse::<u32>() {
+                Ok(0) => Ok(None),
+                Ok(hours) => Ok(Some(hours)),
+                Err(_) => Err(ConfigError::InvalidBackupSettings(format!(
+                    "BACKUP_INTERVAL_HOURS must be a whole number of hours, got {value}"
+                ))),
+            })?,
+            None => defaults.interval_hours,
+        };
+        Ok(Self {
+            path,
+            retention_days,
+            interval_hours,
+        })
+    }
+}
+
+/// Attempts allowed per key within `window_secs`: failed logins per client and username,
+/// or registrations per client. Each spent attempt comes back after `window_secs / max_attempts`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoginRateLimit {
+    pub max_attempts: u32,
+    pub window_secs: u64,
+}
+
+impl Default for LoginRateLimit {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_LOGIN_RATE_LIMIT_MAX_ATTEMPTS,
+            window_secs: DEFAULT_LOGIN_RATE_LIMIT_WINDOW_SECS,
+        }
+    }
+}
+
+impl LoginRateLimit {
+    /// Reads `LOGIN_RATE_LIMIT_MAX_ATTEMPTS` and `LOGIN_RATE_LIMIT_WINDOW_SECS`; both must
+    /// be positive. Shared by the server and the bot's `/link`.
+    pub fn from_sources(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        let defaults = Self::default();
+        Ok(Self {
+            max_attempts: rate_limit(
+                sources,
+                "LOGIN_RATE_LIMIT_MAX_ATTEMPTS",
+                defaults.max_attempts,
+            )?,
+            window_secs: rate_limit(
+                sources,
+                "LOGIN_RATE_LIMIT_WINDOW_SECS",
+                defaults.window_secs,
+            )?,
+        })
+    }
+}
+
+/// Request body caps enforced by `body_limit::layer`: `import_max_bytes` on
+/// `IMPORT_BODY_PATHS`, `json_max_bytes` everywhere else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodyLimits {
+    pub json_max_bytes: usize,
+    pub import_max_bytes: usize,
+}
+
+impl Default for BodyLimits {
+    fn default() -> Self {
+        Self {
+            json_max_bytes: DEFAULT_REQUEST_BODY_MAX_BYTES,
+            import_max_bytes: DEFAULT_IMPORT_BODY_MAX_BYTES,
+        }
+    }
+}
+
+impl BodyLimits {
+    /// Reads `REQUEST_BODY_MAX_BYTES` and `IMPORT_BODY_MAX_BYTES`; both must be positive.
+    pub fn from_sources(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        let defaults = Self::default();
+        let read = |name: &str, default: usize| match sources.get(name) {
+            Some(setting) => setting.parse(|value| {
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|bytes| *bytes > 0)
+                    .ok_or_else(|| {
+                        ConfigError::InvalidBodyLimit(format!(
+                            "{name} must be a positive number of bytes, got {value}"
+                        ))
+                    })
+            }),
+            None => Ok(default),
+        };
+        Ok(Self {
+            json_max_bytes: read("REQUEST_BODY_MAX_BYTES", defaults.json_max_bytes)?,
+            import_max_bytes: read("IMPORT_BODY_MAX_BYTES", defaults.import_max_bytes)?,
+        })
+    }
+
+    /// The cap for a request to `path`.
+    pub fn for_path(&self, path: &str) -> usize {
+        if IMPORT_BODY_PATHS.contains(&path) {
+            self.import_max_bytes
+        } else {
+            self.json_max_bytes
+        }
+    }
+}
+
+/// Category names seeded into new accounts. Names are unique ignoring case across
+/// both lists, like a user's own categories; two empty lists disable seeding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefaultCategories {
+    pub expense: Vec<String>,
+    pub income: Vec<String>,
+}
+
+impl Default for DefaultCategories {
+    fn default() -> Self {
+        Self {
+            expense: DEFAULT_EXPENSE_CATEGORIES
+                .iter()
+                .map(|n| n.to_string())
+                .collect(),
+            income: DEFAULT_INCOME_CATEGORIES
+                .iter()
+                .map(|n| n.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl DefaultCategories {
+    pub fn new(expense: Vec<String>, income: Vec<String>) -> Result<Self, ConfigError> {
+        let mut seen = std::collections::HashSet::new();
+        for name in expense.iter().chain(&income) {
+            if name.trim().is_empty() || name.trim() != name {
+                return Err(ConfigError::InvalidDefaultCategories(format!(
+                    "'{}' is not a valid category name",
+                    name
+                )));
+            }
+            if name.len() > MAX_CATEGORY_NAME_LENGTH {
+                return Err(ConfigError::InvalidDefaultCategories(format!(
+                    "'{}' is longer than {} characters",
+                    name, MAX_CATEGORY_NAME_LENGTH
+                )));
+            }
+            if !seen.insert(name.to_lowercase()) {
+                return Err(ConfigError::InvalidDefaultCategories(format!(
+                    "'{}' is listed more than once",
+                    name
+                )));
+            }
+        }
+        Ok(Self { expense, income })
+    }
+
+    /// Reads the comma-separated `DEFAULT_EXPENSE_CATEGORIES` and
+    /// `DEFAULT_INCOME_CATEGORIES`; an unset variable keeps its default list and an
+    /// empty one seeds nothing of that type. A config file may give either as a list.
+    pub fn from_sources(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        const KEYS: [&str; 2] = ["DEFAULT_EXPENSE_CATEGORIES", "DEFAULT_INCOME_CATEGORIES"];
+        let defaults = Self::default();
+        Self::new(
+            category_names(sources, KEYS[0], defaults.expense),
+            category_names(sources, KEYS[1], defaults.income),
+        )
+        .map_err(|e| sources.blame(&KEYS, e))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expense.is_empty() && self.income.is_empty()
+    }
+
+    /// Each name with its `is_income` flag, expenses first.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.expense
+            .iter()
+            .map(|name| (name.as_str(), false))
+            .chain(self.income.iter().map(|name| (name.as_str(), true)))
+    }
+}
+
+/// Backend for login sessions, chosen by `SESSION_STORE`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SessionStoreKind {
+    /// The `sessions` table in the main database; logins survive a restart.
+    #[default]
+    Database,
+    /// Process memory; every restart logs everyone out.
+    Memory,
+}
+
+impl SessionStoreKind {
+    /// Reads `SESSION_STORE` (`database` or `memory`); unset means `database`.
+    pub fn from_sources(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        match sources.get("SESSION_STORE") {
+            Some(setting) => setting.parse(|value| match value.trim().to_lowercase().as_str() {
+                "database" => Ok(Self::Database),
+                "memory" => Ok(Self::Memory),
+                _ => Err(ConfigError::InvalidSessionStore(value.to_string())),
+            }),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+/// How long removed relationships are kept before the maintenance job deletes them.
+/// `None` disables pruning for that status.
+#[derive(Debug, Clone, Default)]
+pub struct FriendshipRetention {
+    pub unfriended_after_days: Option<u32>,
+    pub blocked_after_days: Option<u32>,
 }
 
 #[derive(Debug)]
@@ -14,13 +666,48 @@ pub enum ConfigError {
     MissingSessionSecret,
     InvalidSessionSecret(String),
     InvalidPort(String),
+    InvalidRetentionDays(String),
+    InvalidAdminToken(String),
+    InvalidIdempotencyMaxBodyBytes(String),
+    InvalidIdempotencyCleanupInterval(String),
+    InvalidCsvImportMaxRows(String),
+    InvalidRecordEncryptionKey,
+    InvalidStartupSelfTest(String),
+    InvalidPasswordHashParams(String),
+    InvalidPasswordPolicy(String),
+    InvalidDefaultCategories(String),
+    InvalidFrontendOrigin(String),
+    InvalidSessionStore(String),
+    InvalidLoginRateLimit(String),
+    InvalidDbBusyRetry(String),
+    InvalidDatabaseUrl(String),
+    MissingDatabaseAuthToken,
+    InvalidDatabaseReplicaSync(String),
+    InvalidBackupSettings(String),
+    InvalidTls(String),
+    InvalidBodyLimit(String),
+    /// `error` was caused by the values from `sources` (see `SettingSource`).
+    InSource {
+        sources: Vec<String>,
+        error: Box<ConfigError>,
+    },
+    InvalidConfigFile {
+        path: PathBuf,
+        message: String,
+    },
+    /// A secret and its `_FILE` variant are both set in the same source.
+    ConflictingSecret(String),
+    UnreadableSecretFile(String),
 }
 
 impl std::fmt::Display for ConfigError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ConfigError::MissingSessionSecret => {
-                write!(f, "SESSION_SECRET environment variable is required")
+                write!(
+                    f,
+                    "SESSION_SECRET is required (environment, SESSION_SECRET_FILE or config file)"
+                )
             }
             ConfigError::InvalidSessionSecret(msg) => {
                 write!(f, "Invalid session secret: {}", msg)
@@ -28,6 +715,97 @@ impl std::fmt::Display for ConfigError {
             ConfigError::InvalidPort(port) => {
                 write!(f, "Invalid port number: {}", port)
             }
+            ConfigError::InvalidRetentionDays(msg) => {
+                write!(f, "Invalid retention days: {}", msg)
+            }
+            ConfigError::InvalidAdminToken(msg) => {
+                write!(f, "Invalid admin token: {}", msg)
+            }
+            ConfigError::InvalidIdempotencyMaxBodyBytes(value) => {
+                write!(f, "Invalid IDEMPOTENCY_MAX_BODY_BYTES: {}", value)
+            }
+            ConfigError::InvalidIdempotencyCleanupInterval(value) => {
+                write!(
+                    f,
+                    "IDEMPOTENCY_CLEANUP_INTERVAL_SECS must be a positive integer, got {}",
+                    value
+                )
+            }
+            ConfigError::InvalidCsvImportMaxRows(value) => {
+                write!(
+                    f,
+                    "CSV_IMPORT_MAX_ROWS must be a positive integer, got {}",
+                    value
+                )
+            }
+            ConfigError::InvalidRecordEncryptionKey => {
+                write!(f, "RECORD_ENCRYPTION_KEY must be 64 hex characters")
+            }
+            ConfigError::InvalidStartupSelfTest(value) => {
+                write!(f, "STARTUP_SELF_TEST must be true or false, got {}", value)
+            }
+            ConfigError::InvalidPasswordHashParams(msg) => {
+                write!(f, "Invalid password hash parameters: {}", msg)
+            }
+            ConfigError::InvalidPasswordPolicy(msg) => {
+                write!(f, "Invalid password policy: {}", msg)
+            }
+            ConfigError::InvalidDefaultCategories(msg) => {
+                write!(f, "Invalid default categories: {}", msg)
+            }
+            ConfigError::InvalidFrontendOrigin(origin) => {
+                write!(f, "Invalid frontend origin '{}'", origin)
+            }
+            ConfigError::InvalidSessionStore(value) => {
+                write!(f, "SESSION_STORE must be database or memory, got {}", value)
+            }
+            ConfigError::InvalidLoginRateLimit(msg) => {
+                write!(f, "Invalid login rate limit: {}", msg)
+            }
+            ConfigError::InvalidDbBusyRetry(msg) => {
+                write!(f, "Invalid database busy retry: {}", msg)
+            }
+            ConfigError::InvalidDatabaseUrl(url) => {
+                write!(
+                    f,
+                    "DATABASE_URL must start with libsql://, https:// or http://, got {}",
+                    url
+                )
+            }
+            ConfigError::MissingDatabaseAuthToken => {
+                write!(
+                    f,
+                    "DATABASE_AUTH_TOKEN is required when DATABASE_URL is set"
+                )
+            }
+            ConfigError::InvalidDatabaseReplicaSync(value) => {
+                write!(
+                    f,
+                    "DATABASE_REPLICA_SYNC_SECS must be a positive integer, got {}",
+                    value
+                )
+            }
+            ConfigError::InvalidBackupSettings(msg) => {
+                write!(f, "Invalid backup settings: {}", msg)
+            }
+            ConfigError::InvalidTls(msg) => {
+                write!(f, "Invalid TLS settings: {}", msg)
+            }
+            ConfigError::InvalidBodyLimit(msg) => {
+                write!(f, "Invalid request body limit: {}", msg)
+            }
+            ConfigError::InSource { sources, error } => {
+                write!(f, "{} (from {})", error, sources.join(" and "))
+            }
+            ConfigError::InvalidConfigFile { path, message } => {
+                write!(f, "Invalid config file {}: {}", path.display(), message)
+            }
+            ConfigError::ConflictingSecret(key) => {
+                write!(f, "{} is set both directly and as a _FILE path", key)
+            }
+            ConfigError::UnreadableSecretFile(msg) => {
+                write!(f, "Cannot read secret file: {}", msg)
+            }
         }
     }
 }
@@ -35,32 +813,159 @@ impl std::fmt::Display for ConfigError {
 impl std::error::Error for ConfigError {}
 
 impl Config {
-    pub fn from_env() -> Result<Self, ConfigError> {
-        let host = env::var("SERVER_HOST").unwrap_or_else(|_| DEFAULT_HOST.to_string());
-        let port = env::var("SERVER_PORT").unwrap_or_else(|_| DEFAULT_PORT.to_string());
-        let data_path = env::var("DATABASE_PATH").unwrap_or_else(|_| DEFAULT_DATA_PATH.to_string());
+    /// Reads the environment and the config file; see `ConfigSources::load`.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::from_sources(&ConfigSources::load()?)
+    }
 
-        // Validate port is a valid number
-        if port.parse::<u16>().is_err() {
-            return Err(ConfigError::InvalidPort(port));
-        }
+    pub fn from_sources(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        let host = sources
+            .value("SERVER_HOST")
+            .unwrap_or(DEFAULT_HOST)
+            .to_string();
+        let port = match sources.get("SERVER_PORT") {
+            // Validate port is a valid number
+            Some(setting) => setting.parse(|port| match port.parse::<u16>() {
+                Ok(_) => Ok(port.to_string()),
+                Err(_) => Err(ConfigError::InvalidPort(port.to_string())),
+            })?,
+            None => DEFAULT_PORT.to_string(),
+        };
+        let CliConfig {
+            data_path,
+            record_encryption_key,
+            password_hash,
+            db_busy_retry,
+            database,
+        } = CliConfig::from_sources(sources)?;
 
         // Get and validate session secret
-        let session_secret =
-            env::var("SESSION_SECRET").map_err(|_| ConfigError::MissingSessionSecret)?;
+        let session_secret = sources
+            .get("SESSION_SECRET")
+            .ok_or(ConfigError::MissingSessionSecret)?
+            .parse(|secret| {
+                if secret.len() < MIN_SESSION_SECRET_LENGTH {
+                    return Err(ConfigError::InvalidSessionSecret(format!(
+                        "must be at least {} characters long",
+                        MIN_SESSION_SECRET_LENGTH
+                    )));
+                }
+                Ok(secret.to_string())
+            })?;
 
-        if session_secret.len() < MIN_SESSION_SECRET_LENGTH {
-            return Err(ConfigError::InvalidSessionSecret(format!(
-                "must be at least {} characters long",
-                MIN_SESSION_SECRET_LENGTH
-            )));
-        }
+        let friendship_retention = FriendshipRetention {
+            unfriended_after_days: retention_days(
+                sources,
+                "FRIENDSHIP_PRUNE_UNFRIENDED_DAYS",
+                Some(DEFAULT_PRUNE_UNFRIENDED_AFTER_DAYS),
+            )?,
+            // Blocked pairs are only pruned when explicitly configured
+            blocked_after_days: retention_days(sources, "FRIENDSHIP_PRUNE_BLOCKED_DAYS", None)?,
+        };
+
+        let admin_token = match sources.get("ADMIN_TOKEN") {
+            Some(setting) => Some(setting.parse(|token| {
+                if token.len() < MIN_ADMIN_TOKEN_LENGTH {
+                    return Err(ConfigError::InvalidAdminToken(format!(
+                        "must be at least {} characters long",
+                        MIN_ADMIN_TOKEN_LENGTH
+                    )));
+                }
+                Ok(token.to_string())
+            })?),
+            None => None,
+        };
+
+        let idempotency_max_body_bytes = match sources.get("IDEMPOTENCY_MAX_BODY_BYTES") {
+            Some(setting) => setting.parse(|value| {
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| ConfigError::InvalidIdempotencyMaxBodyBytes(value.to_string()))
+            })?,
+            None => DEFAULT_IDEMPOTENCY_MAX_BODY_BYTES,
+        };
+
+        let idempotency_cleanup_interval_secs =
+            match sources.get("IDEMPOTENCY_CLEANUP_INTERVAL_SECS") {
+                Some(setting) => setting.parse(|value| {
+                    value
+                        .trim()
+                        .parse::<u64>()
+                        .ok()
+                        .filter(|secs| *secs > 0)
+                        .ok_or(ConfigError::InvalidIdempotencyCleanupInterval(
+                            value.to_string(),
+                        ))
+                })?,
+                None => MAINTENANCE_INTERVAL_SECS,
+            };
+
+        let csv_import_max_rows = match sources.get("CSV_IMPORT_MAX_ROWS") {
+            Some(setting) => setting.parse(|value| {
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|rows| *rows > 0)
+                    .ok_or(ConfigError::InvalidCsvImportMaxRows(value.to_string()))
+            })?,
+            None => DEFAULT_CSV_IMPORT_MAX_ROWS,
+        };
+
+        let startup_self_test = match sources.get("STARTUP_SELF_TEST") {
+            Some(setting) => setting.parse(|value| match value.trim().to_lowercase().as_str() {
+                "true" | "1" => Ok(true),
+                "false" | "0" => Ok(false),
+                _ => Err(ConfigError::InvalidStartupSelfTest(value.to_string())),
+            })?,
+            None => true,
+        };
+
+        let password_policy = PasswordPolicy::from_sources(sources)?;
+        let default_categories = DefaultCategories::from_sources(sources)?;
+
+        // FRONTEND_ORIGIN (a single origin) still works and adds to the list.
+        let frontend_origins = parse_frontend_origins(&format!(
+            "{},{}",
+            sources.value("FRONTEND_ORIGINS").unwrap_or_default(),
+            sources.value("FRONTEND_ORIGIN").unwrap_or_default()
+        ))
+        .map_err(|e| sources.blame(&["FRONTEND_ORIGINS", "FRONTEND_ORIGIN"], e))?;
+
+        let session_store = SessionStoreKind::from_sources(sources)?;
+        let login_rate_limit = LoginRateLimit::from_sources(sources)?;
+        let backup = BackupConfig::from_sources(sources, &data_path)?;
+        let tls = TlsConfig::from_sources(sources)?;
+        let body_limits = BodyLimits::from_sources(sources)?;
+        let production = sources
+            .value("PRODUCTION")
+            .is_some_and(|value| value.to_lowercase() == "true");
 
         Ok(Config {
             host,
             port,
             data_path,
             session_secret,
+            friendship_retention,
+            admin_token,
+            idempotency_max_body_bytes,
+            idempotency_cleanup_interval_secs,
+            csv_import_max_rows,
+            record_encryption_key,
+            startup_self_test,
+            password_hash,
+            password_policy,
+            db_busy_retry,
+            database,
+            default_categories,
+            frontend_origins,
+            session_store,
+            login_rate_limit,
+            backup,
+            tls,
+            body_limits,
+            production,
         })
     }
 
@@ -68,3 +973,90 @@ impl Config {
         format!("{}:{}", self.host, self.port)
     }
 }
+
+/// Parses a comma-separated origin list such as `FRONTEND_ORIGINS`, dropping blanks and
+/// duplicates. An empty list allows `DEFAULT_FRONTEND_ORIGIN`. `*` is refused because
+/// the CORS layer allows credentials.
+pub fn parse_frontend_origins(value: &str) -> Result<Vec<HeaderValue>, ConfigError> {
+    let mut origins: Vec<HeaderValue> = Vec::new();
+    for origin in value.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+        let header = match origin.parse::<HeaderValue>() {
+            Ok(header) if origin != "*" => header,
+            _ => return Err(ConfigError::InvalidFrontendOrigin(origin.to_string())),
+        };
+        if !origins.contains(&header) {
+            origins.push(header);
+        }
+    }
+    if origins.is_empty() {
+        origins.push(HeaderValue::from_static(DEFAULT_FRONTEND_ORIGIN));
+    }
+    Ok(origins)
+}
+
+/// Reads a day count where `0` disables pruning and an unset setting uses `default`.
+fn retention_days(
+    sources: &ConfigSources,
+    name: &str,
+    default: Option<u32>,
+) -> Result<Option<u32>, ConfigError> {
+    match sources.get(name) {
+        Some(setting) => setting.parse(|value| match value.trim().parse::<u32>() {
+            Ok(0) => Ok(None),
+            Ok(days) => Ok(Some(days)),
+            Err(_) => Err(ConfigError::InvalidRetentionDays(format!(
+                "{} must be a whole number of days, got {}",
+                name, value
+            ))),
+        }),
+        None => Ok(default),
+    }
+}
+
+fn argon2_cost(sources: &ConfigSources, name: &str, default: u32) -> Result<u32, ConfigError> {
+    match sources.get(name) {
+        Some(setting) => setting.parse(|value| {
+            value.trim().parse::<u32>().map_err(|_| {
+                ConfigError::InvalidPasswordHashParams(format!(
+                    "{} must be a whole number, got {}",
+                    name, value
+                ))
+            })
+        }),
+        None => Ok(default),
+    }
+}
+
+fn rate_limit<T>(sources: &ConfigSources, name: &str, default: T) -> Result<T, ConfigError>
+where
+    T: std::str::FromStr + PartialOrd + Default,
+{
+    match sources.get(name) {
+        Some(setting) => setting.parse(|value| {
+            value
+                .trim()
+                .parse::<T>()
+                .ok()
+                .filter(|parsed| *parsed > T::default())
+                .ok_or_else(|| {
+                    ConfigError::InvalidLoginRateLimit(format!(
+                        "{} must be a positive integer, got {}",
+                        name, value
+                    ))
+                })
+        }),
+        None => Ok(default),
+    }
+}
+
+fn category_names(sources: &ConfigSources, name: &str, default: Vec<String>) -> Vec<String> {
+    match sources.value(name) {
+        Some(value) => value
+            .split(',')
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .map(str::to_string)
+            .collect(),
+        None => default,
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::ConfigError;
+use crate::constants::*;
+
+/// Every setting the server, the operator CLI and the bot read. A config file may only
+/// use these names (lowercased); the environment uses them as they are.
+pub const CONFIG_KEYS: &[&str] = &[
+    "SERVER_HOST",
+    "SERVER_PORT",
+    "DATABASE_PATH",
+    "DATABASE_URL",
+    "DATABASE_AUTH_TOKEN",
+    "DATABASE_REPLICA_SYNC_SECS",
+    "DB_BUSY_TIMEOUT_MS",
+    "DB_BUSY_MAX_RETRIES",
+    "DB_BUSY_BASE_DELAY_MS",
+    "SESSION_SECRET",
+    "SESSION_STORE",
+    "PRODUCTION",
+    "FRONTEND_ORIGINS",
+    "FRONTEND_ORIGIN",
+    "TLS_CERT_PATH",
+    "TLS_KEY_PATH",
+    "TLS_RELOAD_INTERVAL_SECS",
+    "LOGIN_RATE_LIMIT_MAX_ATTEMPTS",
+    "LOGIN_RATE_LIMIT_WINDOW_SECS",
+    "FRIENDSHIP_PRUNE_UNFRIENDED_DAYS",
+    "FRIENDSHIP_PRUNE_BLOCKED_DAYS",
+    "ADMIN_TOKEN",
+    "BACKUP_PATH",
+    "BACKUP_INTERVAL_HOURS",
+    "BACKUP_RETENTION_DAYS",
+    "IDEMPOTENCY_MAX_BODY_BYTES",
+    "IDEMPOTENCY_CLEANUP_INTERVAL_SECS",
+    "CSV_IMPORT_MAX_ROWS",
+    "REQUEST_BODY_MAX_BYTES",
+    "IMPORT_BODY_MAX_BYTES",
+    "DEFAULT_EXPENSE_CATEGORIES",
+    "DEFAULT_INCOME_CATEGORIES",
+    "RECORD_ENCRYPTION_KEY",
+    "STARTUP_SELF_TEST",
+    "ARGON2_MEMORY_KIB",
+    "ARGON2_ITERATIONS",
+    "ARGON2_PARALLELISM",
+    "PASSWORD_MIN_LENGTH",
+    "TELEGRAM_BOT_TOKEN",
+    "BOT_FALLBACK_PARSER_ONLY",
+    "OPENAI_API_KEY",
+    "OPENAI_MODEL",
+    "OPENAI_REASONING_EFFORT",
+    "BOT_TIMEZONE",
+];
+
+/// Settings that may instead name a file holding the value, as `<KEY>_FILE`
+/// (Docker and Kubernetes secrets).
+pub const SECRET_KEYS: &[&str] = &[
+    "SESSION_SECRET",
+    "ADMIN_TOKEN",
+    "RECORD_ENCRYPTION_KEY",
+    "DATABASE_AUTH_TOKEN",
+    "TELEGRAM_BOT_TOKEN",
+    "OPENAI_API_KEY",
+];
+
+/// Where a setting's value came from, for error messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SettingSource {
+    Env(&'static str),
+    /// `<KEY>_FILE` in the environment named this file.
+    EnvSecretFile {
+        key: &'static str,
+        path: String,
+    },
+    ConfigFile {
+        key: String,
+        path: PathBuf,
+    },
+    /// `<key>_file` in the config file named this file.
+    ConfigSecretFile {
+        key: String,
+        path: PathBuf,
+        secret_path: String,
+    },
+}
+
+impl std::fmt::Display for SettingSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingSource::Env(key) => write!(f, "environment variable {}", key),
+            SettingSource::EnvSecretFile { key, path } => {
+                write!(f, "file {} (environment variable {}_FILE)", path, key)
+            }
+            SettingSource::ConfigFile { key, path } => {
+                write!(f, "key `{}` in {}", key, path.display())
+            }
+            SettingSource::ConfigSecretFile {
+                key,
+                path,
+                secret_path,
+            } => write!(
+                f,
+                "file {} (key `{}_file` in {})",
+                secret_path,
+                key,
+                path.display()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    pub value: String,
+    pub source: SettingSource,
+}
+
+impl Setting {
+    /// Runs `parse` on the value; an error is reported along with where the value came from.
+    pub fn parse<T>(
+        &self,
+        parse: impl FnOnce(&str) -> Result<T, ConfigError>,
+    ) -> Result<T, ConfigError> {
+        parse(&self.value).map_err(|error| ConfigError::InSource {
+            sources: vec![self.source.to_string()],
+            error: Box::new(error),
+        })
+    }
+}
+
+/// Settings layered from the environment over an optional TOML config file; whatever
+/// neither sets keeps the default chosen by the reader. Both binaries and the operator
+/// CLI read through this, so `kash.toml` configures all of them.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSources {
+    settings: HashMap<&'static str, Setting>,
+}
+
+impl ConfigSources {
+    /// `env` are environment variables (others than `CONFIG_KEYS` are ignored);
+    /// `file` is a config file's path and contents. Secret `_FILE` paths are read here.
+    pub fn new(
+        env: impl IntoIterator<Item = (String, String)>,
+        file: Option<(&Path, &str)>,
+    ) -> Result<Self, ConfigError> {
+        let env: HashMap<String, String> = env.into_iter().collect();
+        let file_values = match file {
+            Some((path, contents)) => Some((path, parse_config_file(path, contents)?)),
+            None => None,
+        };
+
+        let mut settings = HashMap::new();
+        for &key in CONFIG_KEYS {
+            if let Some(setting) = env_setting(&env, key)? {
+                settings.insert(key, setting);
+            } else if let Some((path, values)) = &file_values
+                && let Some(setting) = file_setting(path, values, key)?
+            {
+                settings.insert(key, setting);
+            }
+        }
+        Ok(Self { settings })
+    }
+
+    /// The process environment over the file named by `KASH_CONFIG`, or over
+    /// `./kash.toml` when that exists.
+    pub fn load() -> Result<Self, ConfigError> {
+        let path = match std::env::var("KASH_CONFIG") {
+            Ok(path) if !path.trim().is_empty() => Some(PathBuf::from(path.trim())),
+            _ => Some(PathBuf::from(DEFAULT_CONFIG_FILE)).filter(|path| path.exists()),
+        };
+        let contents =
+            match &path {
+                Some(path) => Some(std::fs::read_to_string(path).map_err(|e| {
+                    ConfigError::InvalidConfigFile {
+                        path: path.clone(),
+                        message: e.to_string(),
+                    }
+                })?),
+                None => None,
+            };
+        Self::new(std::env::vars(), path.as_deref().zip(contents.as_deref()))
+    }
+
+    /// The environment alone, for readers that must not pick up a config file.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::new(std::env::vars(), None)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Setting> {
+        self.settings.get(key)
+    }
+
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.get(key).map(|setting| setting.value.as_str())
+    }
+
+    /// Reports `error` as caused by whichever of `keys` are set.
+    pub fn blame(&self, keys: &[&str], error: ConfigError) -> ConfigError {
+        let sources: Vec<String> = keys
+            .iter()
+            .filter_map(|key| self.get(key))
+            .map(|setting| setting.source.to_string())
+            .collect();
+        if sources.is_empty() {
+            error
+        } else {
+            ConfigError::InSource {
+                sources,
+                error: Box::new(error),
+            }
+        }
+    }
+}
+
+fn env_setting(
+    env: &HashMap<String, String>,
+    key: &'static str,
+) -> Result<Option<Setting>, ConfigError> {
+    let secret_var = format!("{key}_FILE");
+    let secret_path = SECRET_KEYS
+        .contains(&key)
+        .then(|| env.get(&secret_var))
+        .flatten();
+    match (env.get(key), secret_path) {
+        (Some(_), Some(_)) => Err(ConfigError::InSource {
+            sources: vec![SettingSource::Env(key).to_string()],
+            error: Box::new(ConfigError::ConflictingSecret(key.to_string())),
+        }),
+        (Some(value), None) => Ok(Some(Setting {
+            value: value.clone(),
+            source: SettingSource::Env(key),
+        })),
+        (None, Some(path)) => {
+            let source = SettingSource::EnvSecretFile {
+                key,
+                path: path.clone(),
+            };
+            Ok(Some(Setting {
+                value: read_secret_file(path, &source)?,
+                source,
+            }))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+fn file_setting(
+    path: &Path,
+    values: &HashMap<String, String>,
+    key: &'static str,
+) -> Result<Option<Setting>, ConfigError> {
+    let file_key = key.to_lowercase();
+    let secret_key = format!("{file_key}_file");
+    let secret_path = SECRET_KEYS
+        .contains(&key)
+        .then(|| values.get(&secret_key))
+        .flatten();
+    let source = SettingSource::ConfigFile {
+        key: file_key.clone(),
+        path: path.to_path_buf(),
+    };
+    match (values.get(&file_key), secret_path) {
+        (Some(_), Some(_)) => Err(ConfigError::InSource {
+            sources: vec![source.to_string()],
+            error: Box::new(ConfigError::ConflictingSecret(file_key)),
+        }),
+        (Some(value), None) => Ok(Some(Setting {
+            value: value.clone(),
+            source,
+        })),
+        (None, Some(secret_path)) => {
+            let source = SettingSource::ConfigSecretFile {
+                key: file_key,
+                path: path.to_path_buf(),
+                secret_path: secret_path.clone(),
+            };
+            Ok(Some(Setting {
+                value: read_secret_file(secret_path, &source)?,
+                source,
+            }))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
+/// The file's contents without the trailing newline editors and `echo` leave.
+fn read_secret_file(path: &str, source: &SettingSource) -> Result<String, ConfigError> {
+    std::fs::read_to_string(path)
+        .map(|contents| contents.trim_end_matches(['\r', '\n']).to_string())
+        .map_err(|e| ConfigError::InSource {
+            sources: vec![source.to_string()],
+            error: Box::new(ConfigError::UnreadableSecretFile(e.to_string())),
+        })
+}
+
+/// Flattens a config file into the same strings the environment would hold: numbers
+/// and booleans as written, lists joined with commas. Unknown keys are refused so a
+/// typo does not silently leave a default in place.
+fn parse_config_file(path: &Path, contents: &str) -> Result<HashMap<String, String>, ConfigError> {
+    let invalid = |message: String| ConfigError::InvalidConfigFile {
+        path: path.to_path_buf(),
+        message,
+    };
+    let table = contents
+        .parse::<toml::Table>()
+        .map_err(|e| invalid(e.to_string()))?;
+
+    let mut values = HashMap::new();
+    for (key, value) in table {
+        let known = CONFIG_KEYS.iter().any(|k| k.to_lowercase() == key)
+            || SECRET_KEYS
+                .iter()
+                .any(|k| format!("{}_file", k.to_lowercase()) == key);
+        if !known {
+            return Err(invalid(format!("unknown key `{}`", key)));
+        }
+        let value = match value {
+            toml::Value::Array(items) => items
+                .into_iter()
+                .map(|item| scalar_string(&item))
+                .collect::<Option<Vec<_>>>()
+                .map(|items| items.join(",")),
+            scalar => scalar_string(&scalar),
+        }
+        .ok_or_else(|| {
+            invalid(format!(
+                "key `{}` must be a string, number, boolean or list of those",
+                key
+            ))
+        })?;
+        values.insert(key, value);
+    }
+    Ok(values)
+}
+
+fn scalar_string(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Integer(i) => Some(i.to_string()),
+        toml::Value::Float(f) => Some(f.to_string()),
+        toml::Value::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
@@ -1,34 +1,398 @@
 // Server configuration
 pub const DEFAULT_HOST: &str = "0.0.0.0";
 pub const DEFAULT_PORT: &str = "3000";
+pub const DEFAULT_FRONTEND_ORIGIN: &str = "http://localhost:8080";
 pub const DEFAULT_DATA_PATH: &str = "data";
+/// Read when present and `KASH_CONFIG` is unset; environment variables override it.
+pub const DEFAULT_CONFIG_FILE: &str = "kash.toml";
+
+// Request logging; `RUST_LOG` overrides the filter
+pub const DEFAULT_LOG_FILTER: &str = "info";
+pub const REQUEST_ID_HEADER: axum::http::HeaderName =
+    axum::http::HeaderName::from_static("x-request-id");
+
+// Native TLS (`tls.rs`)
+/// Connections that have not finished the TLS handshake by then are dropped.
+pub const TLS_HANDSHAKE_TIMEOUT_SECS: u64 = 10;
+/// Handshaken connections waiting for the server to pick them up.
+pub const TLS_ACCEPT_QUEUE: usize = 128;
 
 // Session configuration
 pub const SESSION_NAME: &str = "axum_session";
 pub const SESSION_EXPIRY_DAYS: i64 = 30;
 pub const MIN_SESSION_SECRET_LENGTH: usize = 64;
 
+// Waiting out a database another process is writing (`database::retry_if_busy`)
+pub const DEFAULT_DB_BUSY_TIMEOUT_MS: u64 = 2_000;
+pub const DEFAULT_DB_BUSY_MAX_RETRIES: u32 = 4;
+pub const DEFAULT_DB_BUSY_BASE_DELAY_MS: u64 = 25;
+pub const DATABASE_BUSY_MESSAGE: &str = "DATABASE_BUSY: the database is busy; try again shortly";
+
+// Login and registration rate limiting (`rate_limit.rs`)
+pub const DEFAULT_LOGIN_RATE_LIMIT_MAX_ATTEMPTS: u32 = 5;
+pub const DEFAULT_LOGIN_RATE_LIMIT_WINDOW_SECS: u64 = 15 * 60;
+/// Above this many tracked keys, keys whose budget has fully refilled are dropped.
+pub const RATE_LIMIT_MAX_TRACKED_KEYS: usize = 10_000;
+
+// Login audit trail (login_attempts) and account lockout
+pub const LOGIN_SOURCE_WEB: &str = "web";
+pub const LOGIN_SOURCE_TELEGRAM: &str = "telegram";
+pub const LOGIN_FAILURE_INVALID_CREDENTIALS: &str = "invalid_credentials";
+pub const LOGIN_FAILURE_LOCKED: &str = "locked";
+pub const LOGIN_FAILURE_DISABLED: &str = "disabled";
+pub const LOGIN_FAILURE_RATE_LIMITED: &str = "rate_limited";
+pub const LOGIN_FAILURE_INVALID_TWO_FACTOR: &str = "invalid_two_factor_code";
+/// Wrong passwords or two-factor codes in a row that lock an account.
+pub const LOGIN_LOCKOUT_FAILURES: i64 = 10;
+pub const LOGIN_LOCKOUT_MINUTES: i64 = 30;
+pub const LOGIN_ATTEMPT_RETENTION_DAYS: i64 = 90;
+pub const DEFAULT_LOGIN_HISTORY_LIMIT: u32 = 20;
+/// Sent instead of checking the password, so it does not tell whether the password was right.
+pub const ACCOUNT_LOCKED_MESSAGE: &str =
+    "ACCOUNT_LOCKED: account temporarily locked after too many failed logins; try again later";
+
+// Password hashing (Argon2id); the defaults are the argon2 crate's own
+pub const DEFAULT_ARGON2_MEMORY_KIB: u32 = 19 * 1024;
+pub const DEFAULT_ARGON2_ITERATIONS: u32 = 2;
+pub const DEFAULT_ARGON2_PARALLELISM: u32 = 1;
+pub const MIN_ARGON2_MEMORY_KIB: u32 = 1024;
+pub const MAX_ARGON2_MEMORY_KIB: u32 = 1024 * 1024;
+pub const MIN_ARGON2_ITERATIONS: u32 = 1;
+pub const MAX_ARGON2_ITERATIONS: u32 = 10;
+pub const MIN_ARGON2_PARALLELISM: u32 = 1;
+pub const MAX_ARGON2_PARALLELISM: u32 = 16;
+
+// Two-factor authentication (`two_factor.rs`), RFC 6238 TOTP with SHA-1
+pub const TOTP_ISSUER: &str = "Kash";
+pub const TOTP_SECRET_BYTES: usize = 20;
+pub const TOTP_STEP_SECS: i64 = 30;
+pub const TOTP_DIGITS: u32 = 6;
+/// Time steps either side of the current one still accepted, for clock drift.
+pub const TOTP_ALLOWED_DRIFT_STEPS: i64 = 1;
+pub const TWO_FACTOR_RECOVERY_CODE_COUNT: usize = 10;
+/// Characters in a recovery code, shown in two dash-separated halves.
+pub const TWO_FACTOR_RECOVERY_CODE_LENGTH: usize = 10;
+pub const TWO_FACTOR_CHALLENGE_TTL_SECS: i64 = 5 * 60;
+/// Wrong codes one login challenge takes before it is discarded.
+pub const TWO_FACTOR_CHALLENGE_MAX_ATTEMPTS: i64 = 5;
+
+// Personal access tokens (`api_tokens.rs`)
+pub const API_TOKEN_PREFIX: &str = "kash_pat_";
+pub const API_TOKEN_BYTES: usize = 32;
+pub const MAX_API_TOKENS_PER_USER: i64 = 50;
+pub const MAX_API_TOKEN_NAME_LENGTH: usize = 100;
+pub const MAX_API_TOKEN_EXPIRY_DAYS: i64 = 3650;
+/// `last_used_at` is written at most this often per token, so reads stay reads.
+pub const API_TOKEN_LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+// Admin API
+pub const MIN_ADMIN_TOKEN_LENGTH: usize = 32;
+pub const ADMIN_ACTION_PRUNE_FRIENDSHIPS: &str = "prune_friendships";
+pub const ADMIN_ACTION_CLEANUP_IDEMPOTENCY_KEYS: &str = "cleanup_idempotency_keys";
+pub const ADMIN_ACTION_UNLINK_TELEGRAM: &str = "unlink_telegram";
+pub const ADMIN_ACTION_DISABLE_USER: &str = "disable_user";
+pub const ADMIN_ACTION_ENCRYPT_RECORDS: &str = "encrypt_records";
+pub const ADMIN_ACTION_ROTATE_RECORDS_KEY: &str = "rotate_records_key";
+
+// Field-level encryption of record names
+pub const ENCRYPTED_FIELD_PREFIX: &str = "enc:v";
+
+// Idempotency keys
+pub const DEFAULT_IDEMPOTENCY_MAX_BODY_BYTES: usize = 64 * 1024;
+/// Hours an idempotency key is replayed for before it may be reused.
+pub const IDEMPOTENCY_TTL_HOURS: i64 = 24;
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+/// Optional request header carrying the idempotency key of `POST /records` and `/records/batch`.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// Seconds a reservation may stay in flight before another request may reclaim it.
+pub const IDEMPOTENCY_IN_PROGRESS_TIMEOUT_SECS: i64 = 60;
+/// How long a request waits on another request holding its key before giving up.
+pub const IDEMPOTENCY_WAIT_MS: u64 = 5_000;
+pub const IDEMPOTENCY_POLL_INTERVAL_MS: u64 = 20;
+pub const IDEMPOTENCY_IN_PROGRESS_MESSAGE: &str = "IDEMPOTENCY_IN_PROGRESS: a request with this idempotency key is still being processed; retry shortly";
+pub const REPLAY_UNAVAILABLE_MESSAGE: &str = "REPLAY_UNAVAILABLE: the original response was too large to store; retry with a new idempotency key";
+
 // Database limits and defaults
 pub const DEFAULT_CATEGORIES_LIMIT: u32 = 100;
 pub const DEFAULT_RECORDS_LIMIT: u32 = 500;
 pub const MAX_LIMIT: u32 = 1000;
 pub const MAX_OFFSET: u32 = 1_000_000;
+pub const BOOTSTRAP_RECORDS_LIMIT: u32 = 50;
+/// Counted as SQL selects, so the tag subquery of `RECORD_COLUMNS` counts on its own.
+pub const BOOTSTRAP_QUERY_BUDGET: u32 = 9;
 
 // Validation limits
 pub const MAX_CATEGORY_NAME_LENGTH: usize = 100;
+pub const DEFAULT_CATEGORY_NAME: &str = "Other";
+pub const CATEGORY_ARCHIVED_MESSAGE: &str = "Category is archived";
+/// Expense categories given to each new account; `DEFAULT_EXPENSE_CATEGORIES` overrides.
+pub const DEFAULT_EXPENSE_CATEGORIES: &[&str] = &[
+    "Food",
+    "Transport",
+    "Housing",
+    "Entertainment",
+    DEFAULT_CATEGORY_NAME,
+];
+/// Income categories given to each new account; `DEFAULT_INCOME_CATEGORIES` overrides.
+pub const DEFAULT_INCOME_CATEGORIES: &[&str] = &["Salary", "Other Income"];
+pub const CATEGORY_EDIT_ONE_CHANGE_MESSAGE: &str = "Please change one thing at a time: either rename the category or switch it between income and expense";
 pub const MAX_RECORD_NAME_LENGTH: usize = 255;
+pub const MAX_RECORDS_PER_BATCH: usize = 50;
+pub const MAX_RECORD_NOTE_LENGTH: usize = 1000;
+/// Tags are lowercase letters, digits, `-` and `_`, so one never needs quoting.
+pub const MAX_RECORD_TAG_LENGTH: usize = 30;
+pub const MAX_TAGS_PER_RECORD: usize = 10;
+/// Entries in a split's `splits` array; the initiator is not counted.
+pub const MAX_SPLIT_PARTICIPANTS: usize = 20;
 pub const MAX_SEARCH_TERM_LENGTH: usize = 100;
 pub const MAX_USERNAME_LENGTH: usize = 50;
 pub const MIN_USERNAME_LENGTH: usize = 4;
-pub const MIN_PASSWORD_LENGTH: usize = 6;
+/// `PASSWORD_MIN_LENGTH` when unset; counted in characters.
+pub const DEFAULT_MIN_PASSWORD_LENGTH: usize = 10;
+/// Lowest `PASSWORD_MIN_LENGTH` accepted, the length rule before it was configurable.
+pub const MIN_PASSWORD_LENGTH_FLOOR: usize = 6;
 pub const MAX_NICKNAME_LENGTH: usize = 100;
+pub const MAX_FRIEND_REQUEST_MESSAGE_LENGTH: usize = 200;
+pub const USERNAME_CHANGE_COOLDOWN_DAYS: i64 = 30;
+
+// Startup self-test
+pub const SELFTEST_USERNAME: &str = "__selftest__";
+pub const SELFTEST_CATEGORY_NAME: &str = "Self-test";
+pub const SELFTEST_RECORD_NAME: &str = "Self-test record";
+
+// Operator CLI (`kash-server user|db|export ...`)
+/// Locked by a running server inside its data directory; the CLI refuses to run while it is held.
+pub const INSTANCE_LOCK_FILE: &str = "kash-server.lock";
+pub const CLI_EXIT_OK: i32 = 0;
+pub const CLI_EXIT_FAILURE: i32 = 1;
+pub const CLI_EXIT_USAGE: i32 = 2;
+pub const CLI_EXIT_LOCKED: i32 = 3;
+pub const CLI_RESET_PASSWORD_LENGTH: usize = 20;
+
+// Original (pre-conversion) amounts on imported records
+/// Active ISO 4217 currency codes accepted for `original_currency`.
+pub const ISO_CURRENCY_CODES: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD", "CAD",
+    "CDF", "CHF", "CLP", "CNY", "COP", "CRC", "CUP", "CVE", "CZK", "DJF", "DKK", "DOP", "DZD",
+    "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP", "GEL", "GHS", "GIP", "GMD", "GNF", "GTQ",
+    "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS", "INR", "IQD", "IRR", "ISK", "JMD", "JOD",
+    "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW", "KWD", "KYD", "KZT", "LAK", "LBP", "LKR",
+    "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD", "MMK", "MNT", "MOP", "MRU", "MUR", "MVR",
+    "MWK", "MXN", "MYR", "MZN", "NAD", "NGN", "NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN",
+    "PGK", "PHP", "PKR", "PLN", "PYG", "QAR", "RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR",
+    "SDG", "SEK", "SGD", "SHP", "SLE", "SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL", "THB",
+    "TJS", "TMT", "TND", "TOP", "TRY", "TTD", "TWD", "TZS", "UAH", "UGX", "USD", "UYU", "UZS",
+    "VES", "VND", "VUV", "WST", "XAF", "XCD", "XOF", "XPF", "YER", "ZAR", "ZMW", "ZWL",
+];
 
 // Split Status
 pub const SPLIT_STATUS_INITIATED: &str = "initiated";
 pub const SPLIT_STATUS_COMPLETED: &str = "completed";
+pub const SPLIT_STATUS_SETTLED: &str = "settled";
+/// Only ever returned by `POST /splits/{id}/cancel`: a cancelled split has no records left.
+pub const SPLIT_STATUS_CANCELLED: &str = "cancelled";
+pub const SPLIT_STATUSES: &[&str] = &[
+    SPLIT_STATUS_INITIATED,
+    SPLIT_STATUS_COMPLETED,
+    SPLIT_STATUS_SETTLED,
+];
+pub const SPLIT_ROLE_INITIATOR: &str = "initiator";
+pub const SPLIT_ROLE_PARTICIPANT: &str = "participant";
+pub const SPLIT_NOT_FOUND_MESSAGE: &str = "Split not found";
+/// Prefix of the 409 returned when cancelling a split a participant already accepted or settled.
+pub const SPLIT_NOT_CANCELLABLE_PREFIX: &str = "SPLIT_NOT_CANCELLABLE";
+pub const SPLIT_RECORD_IMMUTABLE_MESSAGE: &str = "SPLIT_RECORD_IMMUTABLE: only the name and category of a split record can be edited; amount and date corrections have to come from the split's initiator";
+/// Prefix of the 409 returned when restoring a split record would contradict what the split did since.
+pub const SPLIT_RECORD_UNRESTORABLE_PREFIX: &str = "SPLIT_RECORD_UNRESTORABLE";
+
+/// Prefix of the 409 returned when an edit's record or category was deleted before it applied.
+pub const REFERENCE_DELETED_PREFIX: &str = "REFERENCE_DELETED";
+
+// Friendship status (friendship.status)
+pub const FRIENDSHIP_STATUS_ACTIVE: &str = "active";
+pub const FRIENDSHIP_STATUS_UNFRIENDED: &str = "unfriended";
+pub const FRIENDSHIP_STATUS_BLOCKED: &str = "blocked";
+/// A pending request its recipient turned down; either side may send a new one.
+pub const FRIENDSHIP_STATUS_DECLINED: &str = "declined";
+
+// Friendship direction, from the viewer's side of the request
+pub const FRIEND_DIRECTION_INCOMING: &str = "incoming";
+pub const FRIEND_DIRECTION_OUTGOING: &str = "outgoing";
+
+// Friend search: the searcher's relationship to each result
+pub const FRIEND_RELATIONSHIP_NONE: &str = "none";
+pub const FRIEND_RELATIONSHIP_PENDING_OUTGOING: &str = "pending_outgoing";
+pub const FRIEND_RELATIONSHIP_PENDING_INCOMING: &str = "pending_incoming";
+pub const FRIEND_RELATIONSHIP_ACCEPTED: &str = "accepted";
+/// Shared splits listed on a friend's profile (`GET /friends/{id}`).
+pub const FRIEND_PROFILE_RECENT_SPLITS: u32 = 5;
+
+// Friend activity feed: friendship events besides the unfriended/blocked statuses
+pub const FRIEND_ACTIVITY_REQUESTED: &str = "requested";
+pub const FRIEND_ACTIVITY_ACCEPTED: &str = "accepted";
+
+// Notification feed (notifications.kind)
+pub const NOTIFICATION_KIND_SPLIT_CREATED: &str = "split_created";
+pub const NOTIFICATION_KIND_SPLIT_SETTLED: &str = "split_settled";
+pub const NOTIFICATION_KIND_FRIEND_REQUEST_RECEIVED: &str = "friend_request_received";
+pub const NOTIFICATION_KIND_FRIEND_ACCEPTED: &str = "friend_accepted";
+/// Read notifications are purged this long after they were read; unread ones are kept.
+pub const NOTIFICATION_READ_RETENTION_DAYS: i64 = 60;
+pub const NOTIFICATION_NOT_FOUND_MESSAGE: &str = "Notification not found";
+
+// Maintenance
+pub const DEFAULT_PRUNE_UNFRIENDED_AFTER_DAYS: u32 = 180;
+pub const MAINTENANCE_INTERVAL_SECS: u64 = 60 * 60;
+/// Deleted records stay in the trash, restorable, for this long before they are purged.
+pub const RECORD_TRASH_RETENTION_DAYS: i64 = 30;
+
+// Database backups
+/// Default folder for snapshots, inside the data directory.
+pub const BACKUP_DIR_NAME: &str = "backups";
+pub const DEFAULT_BACKUP_RETENTION_DAYS: u32 = 14;
+/// Snapshot folder names; the time a snapshot was taken, in UTC.
+pub const BACKUP_SNAPSHOT_NAME_FORMAT: &str = "[year][month][day]T[hour][minute][second]Z";
+
+// Job queue (jobs.state, jobs.job_type)
+pub const JOB_STATE_QUEUED: &str = "queued";
+pub const JOB_STATE_RUNNING: &str = "running";
+pub const JOB_STATE_DONE: &str = "done";
+pub const JOB_STATE_FAILED: &str = "failed";
+pub const JOB_TYPE_PRUNE_FRIENDSHIPS: &str = "prune_friendships";
+pub const JOB_TYPE_CLEANUP_IDEMPOTENCY_KEYS: &str = "cleanup_idempotency_keys";
+pub const JOB_TYPE_DRAIN_OUTBOX: &str = "drain_outbox";
+pub const JOB_TYPE_PURGE_DELETED_RECORDS: &str = "purge_deleted_records";
+pub const JOB_TYPE_DELETE_EXPIRED_SESSIONS: &str = "delete_expired_sessions";
+pub const JOB_TYPE_PURGE_LOGIN_ATTEMPTS: &str = "purge_login_attempts";
+pub const JOB_TYPE_PURGE_READ_NOTIFICATIONS: &str = "purge_read_notifications";
+pub const JOB_TYPE_BACKUP_DATABASE: &str = "backup_database";
+pub const JOB_POLL_INTERVAL_SECS: u64 = 10;
+pub const JOB_MAX_ATTEMPTS: u32 = 5;
+pub const JOB_RETRY_BASE_SECS: i64 = 30;
+pub const JOB_RETRY_MAX_SECS: i64 = 60 * 60;
+pub const JOB_LEASE_SECS: i64 = 5 * 60;
+pub const JOB_RETENTION_DAYS: i64 = 7;
+
+// Record origin (records.created_via)
+pub const CREATED_VIA_API: &str = "api";
+pub const CREATED_VIA_BOT_AI: &str = "bot_ai";
+pub const CREATED_VIA_BOT_COMMAND: &str = "bot_command";
+pub const CREATED_VIA_IMPORT: &str = "import";
+/// Residual payment recorded by `POST /friends/{id}/settle-up`, named `Settle-up with <name>`.
+pub const CREATED_VIA_SETTLE_UP: &str = "settle_up";
+pub const SETTLE_UP_RECORD_NAME: &str = "Settle-up with";
+pub const CREATED_VIA_VALUES: [&str; 5] = [
+    CREATED_VIA_API,
+    CREATED_VIA_BOT_AI,
+    CREATED_VIA_BOT_COMMAND,
+    CREATED_VIA_IMPORT,
+    CREATED_VIA_SETTLE_UP,
+];
+
+// Telegram bot tools
+/// Hard cap on records returned by the bot's `list_records` tool, whatever limit the model asks for.
+pub const BOT_LIST_RECORDS_MAX: u32 = 50;
+
+// AI categorization accuracy report
+pub const AI_ACCURACY_DEFAULT_MONTHS: u32 = 3;
+pub const AI_ACCURACY_MAX_MONTHS: u32 = 24;
+pub const AI_ACCURACY_TOP_CONFUSIONS: usize = 3;
+
+// "While you were away" digest
+/// Items returned per section of `GET /whats-new`; the counts are not capped.
+pub const WHATS_NEW_MAX_ITEMS: u32 = 20;
+
+// Account export
+pub const EXPORT_SCHEMA_VERSION: &str = "1";
+pub const EXPORT_REDACT_FRIENDS: &str = "friends";
+pub const EXPORT_REDACT_NOTES: &str = "notes";
+pub const EXPORT_FRIEND_PSEUDONYM_PREFIX: &str = "friend-";
+
+// Account archive
+/// Version of the `GET /auth/export` layout; `POST /auth/import` accepts only this one.
+pub const ACCOUNT_ARCHIVE_SCHEMA_VERSION: u32 = 1;
+/// Records read per query while streaming `GET /auth/export`; the lock is released between pages.
+pub const ACCOUNT_ARCHIVE_PAGE_SIZE: u32 = 500;
+pub const ACCOUNT_NOT_EMPTY_MESSAGE: &str =
+    "ACCOUNT_NOT_EMPTY: import is only possible into an account without records";
+
+// CSV record export
+/// Records read per query while streaming `GET /records/export`; the lock is released between pages.
+pub const RECORDS_CSV_PAGE_SIZE: u32 = 500;
+pub const RECORDS_CSV_HEADER: &str =
+    "id,name,amount,currency,category,date,pending,settle,note,tags";
+/// Columns `POST /records/import` requires in its header row. `currency` is optional; others are ignored.
+pub const RECORDS_CSV_IMPORT_COLUMNS: [&str; 4] = ["name", "amount", "category", "date"];
+pub const DEFAULT_CSV_IMPORT_MAX_ROWS: usize = 5000;
+
+// Request size limits (`body_limit.rs`)
+pub const DEFAULT_REQUEST_BODY_MAX_BYTES: usize = 64 * 1024;
+pub const DEFAULT_IMPORT_BODY_MAX_BYTES: usize = 2 * 1024 * 1024;
+/// Routes taking a whole CSV file or account archive, capped by `IMPORT_BODY_MAX_BYTES`.
+pub const IMPORT_BODY_PATHS: &[&str] = &["/records/import", "/auth/import"];
+pub const MAX_QUERY_STRING_LENGTH: usize = 2048;
+
+// Budget alert outbox
+pub const OUTBOX_KIND_BUDGET_ALERT: &str = "budget_alert";
+/// A split naming the user, sent as soon as the drainer runs.
+pub const OUTBOX_KIND_SPLIT_CREATED: &str = "split_created";
+/// A share of a split the user is in was settled by the other side.
+pub const OUTBOX_KIND_SPLIT_SETTLED: &str = "split_settled";
+/// Local time at which non-urgent budget alerts are delivered as one message.
+pub const DEFAULT_ALERT_BATCH_TIME: &str = "21:00";
+/// UTC+8, matching the bot's default `Asia/Taipei` timezone.
+pub const DEFAULT_CURRENCY_CODE: &str = "TWD";
+pub const DEFAULT_UTC_OFFSET_MINUTES: i32 = 8 * 60;
+pub const MIN_UTC_OFFSET_MINUTES: i32 = -12 * 60;
+pub const MAX_UTC_OFFSET_MINUTES: i32 = 14 * 60;
 
 // Error messages
 pub const ERR_DATABASE_ACCESS: &str = "Database access error";
 pub const ERR_DATABASE_OPERATION: &str = "Database operation failed";
 pub const ERR_INVALID_SESSION: &str = "Invalid session";
 pub const ERR_UNAUTHORIZED: &str = "Not logged in";
+
+// API error codes (`ApiError.code`); the status-derived ones cover errors without their own
+pub const ERROR_CODE_BAD_REQUEST: &str = "bad_request";
+pub const ERROR_CODE_UNAUTHORIZED: &str = "unauthorized";
+pub const ERROR_CODE_FORBIDDEN: &str = "forbidden";
+pub const ERROR_CODE_NOT_FOUND: &str = "not_found";
+pub const ERROR_CODE_CONFLICT: &str = "conflict";
+pub const ERROR_CODE_PAYLOAD_TOO_LARGE: &str = "payload_too_large";
+pub const ERROR_CODE_URI_TOO_LONG: &str = "uri_too_long";
+pub const ERROR_CODE_UNPROCESSABLE: &str = "unprocessable";
+pub const ERROR_CODE_RATE_LIMITED: &str = "rate_limited";
+pub const ERROR_CODE_INTERNAL: &str = "internal_error";
+pub const ERROR_CODE_RECORD_NOT_FOUND: &str = "record_not_found";
+pub const ERROR_CODE_CATEGORY_NOT_FOUND: &str = "category_not_found";
+pub const ERROR_CODE_RECORD_ALREADY_FINALIZED: &str = "record_already_finalized";
+pub const ERROR_CODE_UNDO_TOKEN_NOT_FOUND: &str = "undo_token_not_found";
+pub const ERROR_CODE_INVALID_SORT: &str = "invalid_sort";
+pub const ERROR_CODE_INVALID_CURSOR: &str = "invalid_cursor";
+pub const ERROR_CODE_USER_NOT_FOUND: &str = "user_not_found";
+pub const ERROR_CODE_FRIEND_NOT_FOUND: &str = "friend_not_found";
+pub const ERROR_CODE_FRIEND_REQUEST_NOT_FOUND: &str = "friend_request_not_found";
+pub const ERROR_CODE_FRIEND_REQUEST_EXISTS: &str = "friend_request_exists";
+pub const ERROR_CODE_FRIEND_REQUEST_TO_SELF: &str = "friend_request_to_self";
+pub const ERROR_CODE_FRIEND_REQUEST_BLOCKED: &str = "friend_request_blocked";
+pub const ERROR_CODE_FRIENDSHIP_NOT_UNFRIENDED: &str = "friendship_not_unfriended";
+pub const ERROR_CODE_PASSWORD_TOO_SHORT: &str = "password_too_short";
+pub const ERROR_CODE_PASSWORD_TOO_COMMON: &str = "password_too_common";
+pub const ERROR_CODE_PASSWORD_MATCHES_USERNAME: &str = "password_matches_username";
+pub const ERROR_CODE_INVALID_TWO_FACTOR_CODE: &str = "invalid_two_factor_code";
+pub const ERROR_CODE_TWO_FACTOR_CHALLENGE_INVALID: &str = "two_factor_challenge_invalid";
+pub const ERROR_CODE_TWO_FACTOR_ALREADY_ENABLED: &str = "two_factor_already_enabled";
+pub const ERROR_CODE_TWO_FACTOR_NOT_SET_UP: &str = "two_factor_not_set_up";
+pub const ERROR_CODE_TWO_FACTOR_NOT_ENABLED: &str = "two_factor_not_enabled";
+pub const ERROR_CODE_INVALID_API_TOKEN: &str = "invalid_api_token";
+pub const ERROR_CODE_API_TOKEN_READ_ONLY: &str = "api_token_read_only";
+pub const ERROR_CODE_API_TOKEN_NOT_FOUND: &str = "api_token_not_found";
+pub const ERROR_CODE_API_TOKEN_LIMIT: &str = "api_token_limit";
+// Codes taken from the tag of a `TAG: ...` message
+pub const ERROR_CODE_PERIOD_CLOSED: &str = "period_closed";
+pub const ERROR_CODE_SPLIT_RECORD_IMMUTABLE: &str = "split_record_immutable";
+pub const ERROR_CODE_SPLIT_RECORD_UNRESTORABLE: &str = "split_record_unrestorable";
+pub const ERROR_CODE_ACCOUNT_LOCKED: &str = "account_locked";
+pub const ERROR_CODE_ACCOUNT_NOT_EMPTY: &str = "account_not_empty";
+pub const ERROR_CODE_DATABASE_BUSY: &str = "database_busy";
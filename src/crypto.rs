@@ -0,0 +1,256 @@
+use std::sync::OnceLock;
+
+use aes_gcm::aead::{Aead, AeadCore, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use hkdf::Hkdf;
+use libsql::Connection;
+use sha2::Sha256;
+
+use crate::Db;
+use crate::constants::ENCRYPTED_FIELD_PREFIX;
+
+// Field-level encryption of record names and notes at rest.
+//
+// Each user with `users.records_key_version` set gets a data key derived from the server
+// master key with HKDF over their id and key version. Stored values are self-describing,
+// `enc:v<version>:<owner id>:<hex nonce + ciphertext>`, so `record_from_row` can decrypt
+// without knowing who asked. The master key is process-wide because both binaries read
+// records through plain `Db` handles that carry no `AppState`.
+
+const NONCE_LEN: usize = 12;
+
+static MASTER_KEY: OnceLock<MasterKey> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldCryptoError {
+    /// A value is encrypted but no master key is installed.
+    MissingKey,
+    /// The master key is not 64 hex characters.
+    InvalidKey,
+    /// A value has the encrypted prefix but cannot be parsed.
+    Malformed,
+    /// Authentication failed: wrong key, wrong owner or tampered ciphertext.
+    Decrypt,
+}
+
+impl std::fmt::Display for FieldCryptoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldCryptoError::MissingKey => write!(f, "record encryption key is not configured"),
+            FieldCryptoError::InvalidKey => {
+                write!(f, "record encryption key must be 64 hex characters")
+            }
+            FieldCryptoError::Malformed => write!(f, "encrypted field is malformed"),
+            FieldCryptoError::Decrypt => write!(f, "encrypted field could not be decrypted"),
+        }
+    }
+}
+
+impl std::error::Error for FieldCryptoError {}
+
+impl From<FieldCryptoError> for libsql::Error {
+    fn from(value: FieldCryptoError) -> Self {
+        libsql::Error::ToSqlConversionFailure(Box::new(value))
+    }
+}
+
+/// The 256-bit server master key. Never stored in the database.
+#[derive(Clone, PartialEq, Eq)]
+pub struct MasterKey([u8; 32]);
+
+impl std::fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MasterKey(..)")
+    }
+}
+
+impl MasterKey {
+    pub fn from_hex(value: &str) -> Result<Self, FieldCryptoError> {
+        let bytes = decode_hex(value.trim()).ok_or(FieldCryptoError::InvalidKey)?;
+        let key: [u8; 32] = bytes.try_into().map_err(|_| FieldCryptoError::InvalidKey)?;
+        Ok(Self(key))
+    }
+
+    fn data_key(&self, owner_user_id: &str, key_version: i64) -> Aes256Gcm {
+        let hkdf = Hkdf::<Sha256>::new(None, &self.0);
+        let mut okm = [0u8; 32];
+        hkdf.expand(
+            format!("kash-record-names:{owner_user_id}:v{key_version}").as_bytes(),
+            &mut okm,
+        )
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&okm))
+    }
+}
+
+/// Installs the process-wide master key. Returns `false` if one was already installed.
+pub fn install_master_key(key: MasterKey) -> bool {
+    MASTER_KEY.set(key).is_ok()
+}
+
+pub fn installed_master_key() -> Option<&'static MasterKey> {
+    MASTER_KEY.get()
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_FIELD_PREFIX)
+}
+
+pub fn encrypt_field(
+    master: &MasterKey,
+    owner_user_id: &str,
+    key_version: i64,
+    plaintext: &str,
+) -> String {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = master
+        .data_key(owner_user_id, key_version)
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext.as_bytes(),
+                aad: owner_user_id.as_bytes(),
+            },
+        )
+        .expect("AES-GCM encryption of an in-memory buffer cannot fail");
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    format!(
+        "{ENCRYPTED_FIELD_PREFIX}{key_version}:{owner_user_id}:{}",
+        encode_hex(&sealed)
+    )
+}
+
+/// Parses `enc:v<version>:<owner>:<hex>` into its parts.
+fn parse_sealed(stored: &str) -> Result<(i64, &str, Vec<u8>), FieldCryptoError> {
+    let rest = stored
+        .strip_prefix(ENCRYPTED_FIELD_PREFIX)
+        .ok_or(FieldCryptoError::Malformed)?;
+    let mut parts = rest.splitn(3, ':');
+    let (Some(version), Some(owner), Some(payload)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(FieldCryptoError::Malformed);
+    };
+    let version = version
+        .parse::<i64>()
+        .map_err(|_| FieldCryptoError::Malformed)?;
+    let sealed = decode_hex(payload).ok_or(FieldCryptoError::Malformed)?;
+    if sealed.len() < NONCE_LEN {
+        return Err(FieldCryptoError::Malformed);
+    }
+    Ok((version, owner, sealed))
+}
+
+/// The key version a stored value was sealed with; `None` for plaintext.
+pub fn sealed_key_version(stored: &str) -> Option<i64> {
+    parse_sealed(stored).ok().map(|(version, _, _)| version)
+}
+
+/// Decrypts `stored` with `master`; plaintext values pass through unchanged.
+pub fn open_field_with(
+    master: Option<&MasterKey>,
+    stored: String,
+) -> Result<String, FieldCryptoError> {
+    if !is_encrypted(&stored) {
+        return Ok(stored);
+    }
+    let master = master.ok_or(FieldCryptoError::MissingKey)?;
+    let (version, owner, sealed) = parse_sealed(&stored)?;
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let plaintext = master
+        .data_key(owner, version)
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: owner.as_bytes(),
+            },
+        )
+        .map_err(|_| FieldCryptoError::Decrypt)?;
+    String::from_utf8(plaintext).map_err(|_| FieldCryptoError::Malformed)
+}
+
+/// Decrypts `stored` with the installed master key.
+pub fn open_field(stored: String) -> Result<String, FieldCryptoError> {
+    open_field_with(installed_master_key(), stored)
+}
+
+/// `None` when the user's records are stored in plaintext.
+pub async fn records_key_version(
+    conn: &Connection,
+    user_id: &str,
+) -> Result<Option<i64>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT records_key_version FROM users WHERE id = ?",
+            [user_id],
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => row.get(0),
+        None => Ok(None),
+    }
+}
+
+/// The value to store for `owner_user_id`'s record name: encrypted when they opted in.
+pub async fn seal_record_name(
+    conn: &Connection,
+    owner_user_id: &str,
+    name: &str,
+) -> Result<String, libsql::Error> {
+    match records_key_version(conn, owner_user_id).await? {
+        Some(key_version) => {
+            let master = installed_master_key().ok_or(FieldCryptoError::MissingKey)?;
+            Ok(encrypt_field(master, owner_user_id, key_version, name))
+        }
+        None => Ok(name.to_string()),
+    }
+}
+
+/// Installs `master` at startup. Without one, refuses to start once any user has encrypted
+/// records, since their names could not be read or written.
+pub async fn init_record_encryption(db: &Db, master: Option<MasterKey>) -> Result<(), String> {
+    if let Some(master) = master {
+        install_master_key(master);
+        return Ok(());
+    }
+    let conn = db.read().await;
+    let encrypted_users = count_encrypted_users(&conn)
+        .await
+        .map_err(|e| format!("Failed to check record encryption: {}", e))?;
+    if encrypted_users > 0 {
+        return Err(format!(
+            "RECORD_ENCRYPTION_KEY is required: {} user(s) have encrypted records",
+            encrypted_users
+        ));
+    }
+    Ok(())
+}
+
+pub async fn count_encrypted_users(conn: &Connection) -> Result<i64, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM users WHERE records_key_version IS NOT NULL",
+            (),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => row.get(0),
+        None => Ok(0),
+    }
+}
+
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub(crate) fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
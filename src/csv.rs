@@ -0,0 +1,90 @@
+use std::borrow::Cow;
+
+/// One CSV field: quoted, with quotes doubled, when it holds a comma, quote or line break.
+pub fn escape_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\"")).into()
+    } else {
+        value.into()
+    }
+}
+
+/// One parsed CSV row and the line it starts on (1-based).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub line: usize,
+    pub fields: Vec<String>,
+}
+
+/// Lazily splits CSV text into rows, the inverse of `escape_field`: quoted fields may hold
+/// commas, doubled quotes and line breaks. `\r\n` and `\n` both end a row; blank lines
+/// are skipped. An unterminated quote ends the iteration with an error.
+pub struct Rows<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Rows<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            chars: text
+                .strip_prefix('\u{feff}')
+                .unwrap_or(text)
+                .chars()
+                .peekable(),
+            line: 1,
+        }
+    }
+}
+
+impl Iterator for Rows<'_> {
+    type Item = Result<Row, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Skip blank lines between rows
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                '\n' => self.line += 1,
+                '\r' => {}
+                _ => break,
+            }
+            self.chars.next();
+        }
+        self.chars.peek()?;
+
+        let line = self.line;
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        while let Some(c) = self.chars.next() {
+            match c {
+                '"' if quoted => {
+                    if self.chars.peek() == Some(&'"') {
+                        self.chars.next();
+                        field.push('"');
+                    } else {
+                        quoted = false;
+                    }
+                }
+                '"' if field.is_empty() => quoted = true,
+                '\n' if quoted => {
+                    self.line += 1;
+                    field.push(c);
+                }
+                ',' if !quoted => fields.push(std::mem::take(&mut field)),
+                '\r' if !quoted && self.chars.peek() == Some(&'\n') => {}
+                '\n' if !quoted => {
+                    self.line += 1;
+                    break;
+                }
+                _ => field.push(c),
+            }
+        }
+        if quoted {
+            self.chars = "".chars().peekable();
+            return Some(Err(format!("line {}: unterminated quoted field", line)));
+        }
+        fields.push(field);
+        Some(Ok(Row { line, fields }))
+    }
+}
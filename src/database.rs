@@ -1,16 +1,50 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use libsql::{Builder, Connection};
-use std::{path::Path, sync::Arc};
+use serde::Serialize;
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use time::OffsetDateTime;
 use tokio::sync::RwLock;
 
+use crate::config::{DatabaseLocation, DbBusyRetry};
+use crate::utils::{precise_timestamp, to_db_date};
+
 const CREATE_USERS_TABLE: &str = r#"
 CREATE TABLE IF NOT EXISTS users (
     id             TEXT    PRIMARY KEY,
     name           TEXT    UNIQUE NOT NULL,
-    password_hash  TEXT    NOT NULL
+    password_hash  TEXT    NOT NULL,
+    username_changed_at TEXT,
+    disabled_at    TEXT,
+    records_key_version INTEGER,
+    last_login_at  TEXT,
+    previous_login_at TEXT,
+    last_seen_whats_new TEXT,
+    totp_secret    TEXT,
+    totp_enabled_at TEXT,
+    totp_last_step INTEGER
+);
+"#;
+
+const CREATE_USERNAME_HISTORY_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS username_history (
+    id          TEXT    PRIMARY KEY,
+    user_id     TEXT    NOT NULL,
+    old_name    TEXT    NOT NULL,
+    new_name    TEXT    NOT NULL,
+    changed_at  TEXT    NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id)
 );
 "#;
 
+const CREATE_USERNAME_HISTORY_USER_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_username_history_user ON username_history(user_id);
+"#;
+
 const CREATE_TELEGRAM_USERS_TABLE: &str = r#"
 CREATE TABLE IF NOT EXISTS telegram_users (
     telegram_user_id TEXT PRIMARY KEY,
@@ -21,19 +55,72 @@ CREATE TABLE IF NOT EXISTS telegram_users (
 );
 "#;
 
+// `amount` is in cents (`money::Money`); `original_amount` is a display-only decimal.
 const CREATE_RECORDS_TABLE: &str = r#"
 CREATE TABLE IF NOT EXISTS records (
     id               TEXT    PRIMARY KEY,
     owner_user_id    TEXT    NOT NULL,
     name             TEXT    NOT NULL,
-    amount           REAL    NOT NULL,
+    amount           INTEGER NOT NULL,
     category_id      TEXT,
-    date             TEXT    NOT NULL,
+    date             TEXT    NOT NULL
+                     CHECK (date GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]' AND date(date) IS date),
     pending          BOOLEAN NOT NULL DEFAULT 0,
     split_id         TEXT,
     settle           BOOLEAN NOT NULL DEFAULT 0,
     debtor_user_id   TEXT,
-    creditor_user_id TEXT
+    creditor_user_id TEXT,
+    created_via      TEXT    NOT NULL DEFAULT 'api',
+    seq              INTEGER,
+    original_amount  REAL,
+    original_currency TEXT,
+    note             TEXT,
+    currency         TEXT    NOT NULL DEFAULT 'TWD',
+    split_full_total BOOLEAN NOT NULL DEFAULT 0,
+    created_at       TEXT,
+    settled_at       TEXT,
+    deleted_at       TEXT
+);
+"#;
+
+const CREATE_RECORD_PROVENANCE_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS record_provenance (
+    record_id              TEXT    PRIMARY KEY,
+    model                  TEXT    NOT NULL,
+    prompt_hash            TEXT    NOT NULL,
+    ai_category_confidence REAL,
+    ai_category_id         TEXT,
+    created_at             TEXT    NOT NULL,
+    FOREIGN KEY (record_id) REFERENCES records(id)
+);
+"#;
+
+// One row per tag on a record; tags are stored normalized (see `records::normalize_record_tags`).
+const CREATE_RECORD_TAGS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS record_tags (
+    record_id TEXT NOT NULL,
+    tag       TEXT NOT NULL,
+    PRIMARY KEY (record_id, tag),
+    FOREIGN KEY (record_id) REFERENCES records(id)
+);
+"#;
+
+const CREATE_RECATEGORIZE_BATCHES_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS recategorize_batches (
+    undo_token    TEXT    PRIMARY KEY,
+    owner_user_id TEXT    NOT NULL,
+    created_at    TEXT    NOT NULL
+);
+"#;
+
+const CREATE_RECATEGORIZE_BATCH_ITEMS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS recategorize_batch_items (
+    undo_token           TEXT    NOT NULL,
+    record_id            TEXT    NOT NULL,
+    previous_category_id TEXT,
+    previous_amount      INTEGER NOT NULL,
+    PRIMARY KEY (undo_token, record_id),
+    FOREIGN KEY (undo_token) REFERENCES recategorize_batches(undo_token)
 );
 "#;
 
@@ -43,22 +130,117 @@ CREATE TABLE IF NOT EXISTS categories (
     owner_user_id TEXT    NOT NULL,
     name          TEXT    NOT NULL,
     is_income     BOOLEAN NOT NULL DEFAULT FALSE,
+    parent_id     TEXT,
+    monthly_budget INTEGER,
+    archived      BOOLEAN NOT NULL DEFAULT FALSE,
     UNIQUE(owner_user_id, name)
 );
 "#;
 
-const CREATE_RECORDS_DATE_INDEX: &str = r#"
-CREATE INDEX IF NOT EXISTS idx_records_date ON records(date);
+// Present in the `records` DDL once the date CHECK is in place.
+const RECORDS_DATE_CHECK_MARKER: &str = "CHECK (date GLOB";
+
+// Rows whose date would fail the CHECK: wrong shape, or not a real calendar day.
+const SELECT_RECORDS_WITH_BAD_DATES: &str = r#"
+SELECT id, CAST(date AS TEXT) FROM records
+WHERE date IS NULL
+   OR NOT (date GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]' AND date(date) IS date)
+ORDER BY rowid;
+"#;
+
+// Date range filters and `ORDER BY date, id` read the index alone; supersedes `idx_records_date`.
+const CREATE_RECORDS_DATE_ID_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_records_date_id ON records(date, id);
 "#;
 
 const CREATE_RECORDS_OWNER_INDEX: &str = r#"
 CREATE INDEX IF NOT EXISTS idx_records_owner ON records(owner_user_id);
 "#;
 
+// Only trashed records are indexed, for the trash list and the purge.
+const CREATE_RECORDS_DELETED_AT_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_records_deleted_at ON records(deleted_at) WHERE deleted_at IS NOT NULL;
+"#;
+
+// A split's records are looked up together by `split_id`; ordinary records are left out.
+const CREATE_RECORDS_SPLIT_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_records_split ON records(split_id) WHERE split_id IS NOT NULL;
+"#;
+
+// `GET /splits` finds the splits a user paid for through the records credited to them.
+const CREATE_RECORDS_SPLIT_CREDITOR_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_records_split_creditor ON records(creditor_user_id, split_id) WHERE split_id IS NOT NULL;
+"#;
+
+// Friend balances sum the unsettled shares one user owes another.
+const CREATE_RECORDS_SPLIT_DEBTOR_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_records_split_debtor ON records(debtor_user_id, creditor_user_id) WHERE split_id IS NOT NULL;
+"#;
+
+const CREATE_RECORDS_SEQ_INDEX: &str = r#"
+CREATE UNIQUE INDEX IF NOT EXISTS idx_records_seq ON records(seq);
+"#;
+
+// Rows created before `seq` existed are numbered in insertion (rowid) order.
+const BACKFILL_RECORDS_SEQ: &str = r#"
+UPDATE records
+SET seq = rowid + (SELECT COALESCE(MAX(seq), 0) FROM records)
+WHERE seq IS NULL;
+"#;
+
+// Every insert path gets the next sequence number without having to set it explicitly.
+const CREATE_RECORDS_SEQ_TRIGGER: &str = r#"
+CREATE TRIGGER IF NOT EXISTS trg_records_seq AFTER INSERT ON records
+WHEN NEW.seq IS NULL
+BEGIN
+    UPDATE records
+    SET seq = (SELECT COALESCE(MAX(seq), 0) + 1 FROM records)
+    WHERE rowid = NEW.rowid;
+END;
+"#;
+
 const CREATE_CATEGORIES_OWNER_INDEX: &str = r#"
 CREATE INDEX IF NOT EXISTS idx_categories_owner ON categories(owner_user_id);
 "#;
 
+const CREATE_CATEGORIES_PARENT_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_categories_parent ON categories(parent_id);
+"#;
+
+// Names that differ only by case were once creatable by racing SELECT-then-INSERT paths.
+// Each group is folded into its oldest row (by rowid) so the NOCASE unique index can be built.
+const MERGE_CASE_DUPLICATE_CATEGORIES: &str = r#"
+BEGIN;
+CREATE TEMP TABLE category_merge AS
+SELECT dup.id AS dup_id,
+       (SELECT keep.id FROM categories keep
+        WHERE keep.owner_user_id = dup.owner_user_id AND keep.name = dup.name COLLATE NOCASE
+        ORDER BY keep.rowid LIMIT 1) AS keep_id
+FROM categories dup;
+DELETE FROM category_merge WHERE dup_id = keep_id;
+UPDATE records
+SET category_id = (SELECT keep_id FROM category_merge WHERE dup_id = records.category_id)
+WHERE category_id IN (SELECT dup_id FROM category_merge);
+UPDATE categories
+SET parent_id = (SELECT keep_id FROM category_merge WHERE dup_id = categories.parent_id)
+WHERE parent_id IN (SELECT dup_id FROM category_merge);
+UPDATE record_provenance
+SET ai_category_id = (SELECT keep_id FROM category_merge WHERE dup_id = record_provenance.ai_category_id)
+WHERE ai_category_id IN (SELECT dup_id FROM category_merge);
+UPDATE recategorize_batch_items
+SET previous_category_id = (SELECT keep_id FROM category_merge WHERE dup_id = recategorize_batch_items.previous_category_id)
+WHERE previous_category_id IN (SELECT dup_id FROM category_merge);
+DELETE FROM categories WHERE id IN (SELECT dup_id FROM category_merge);
+UPDATE categories SET parent_id = NULL WHERE parent_id = id;
+DROP TABLE category_merge;
+COMMIT;
+"#;
+
+const CREATE_CATEGORIES_OWNER_NAME_NOCASE_INDEX: &str = r#"
+CREATE UNIQUE INDEX IF NOT EXISTS idx_categories_owner_name_nocase
+ON categories(owner_user_id, name COLLATE NOCASE);
+"#;
+
 const CREATE_FRIENDSHIP_TABLE: &str = r#"
 CREATE TABLE IF NOT EXISTS friendship (
     id                TEXT    PRIMARY KEY,
@@ -67,6 +249,11 @@ CREATE TABLE IF NOT EXISTS friendship (
     pending           BOOLEAN NOT NULL DEFAULT 1,
     nickname          TEXT,
     requester_user_id TEXT    NOT NULL,
+    status            TEXT    NOT NULL DEFAULT 'active',
+    status_changed_at TEXT,
+    created_at        TEXT,
+    request_message   TEXT,
+    accepted_at       TEXT,
     UNIQUE(from_user_id, to_user_id)
 );
 "#;
@@ -79,6 +266,45 @@ const CREATE_FRIENDSHIP_TO_INDEX: &str = r#"
 CREATE INDEX IF NOT EXISTS idx_friendship_to ON friendship(to_user_id);
 "#;
 
+const CREATE_FRIENDSHIP_STATUS_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_friendship_status ON friendship(status, status_changed_at);
+"#;
+
+/// Friend search matches substrings, which no index can seek; scanning this one, which
+/// holds just the name and id, is still cheaper than scanning `users`.
+const CREATE_USERS_NAME_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_users_name_nocase ON users(name COLLATE NOCASE, id);
+"#;
+
+const CREATE_USER_SETTINGS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS user_settings (
+    user_id        TEXT    PRIMARY KEY,
+    closed_through TEXT,
+    updated_at     TEXT    NOT NULL,
+    utc_offset_minutes INTEGER,
+    alert_batch_time   TEXT,
+    currency_code      TEXT,
+    telegram_split_notifications BOOLEAN,
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+"#;
+
+const CREATE_PERIOD_REOPEN_AUDIT_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS period_reopen_audit (
+    id             TEXT    PRIMARY KEY,
+    owner_user_id  TEXT    NOT NULL,
+    record_id      TEXT,
+    action         TEXT    NOT NULL,
+    record_date    TEXT    NOT NULL,
+    closed_through TEXT    NOT NULL,
+    created_at     TEXT    NOT NULL
+);
+"#;
+
+const CREATE_PERIOD_REOPEN_AUDIT_OWNER_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_period_reopen_audit_owner ON period_reopen_audit(owner_user_id);
+"#;
+
 const CREATE_IDEMPOTENCY_KEYS_TABLE: &str = r#"
 CREATE TABLE IF NOT EXISTS idempotency_keys (
     id              TEXT    PRIMARY KEY,
@@ -88,6 +314,7 @@ CREATE TABLE IF NOT EXISTS idempotency_keys (
     payload_hash    TEXT    NOT NULL,
     response_status INTEGER NOT NULL,
     response_body   TEXT,
+    response_digest TEXT,
     created_at      TEXT    NOT NULL,
     expires_at      TEXT    NOT NULL,
     UNIQUE(user_id, endpoint, key)
@@ -98,27 +325,995 @@ const CREATE_IDEMPOTENCY_USER_INDEX: &str = r#"
 CREATE INDEX IF NOT EXISTS idx_idempotency_user ON idempotency_keys(user_id);
 "#;
 
+const CREATE_IDEMPOTENCY_LOOKUP_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_idempotency_lookup ON idempotency_keys(user_id, endpoint, key);
+"#;
+
+const CREATE_TELEGRAM_OUTBOX_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS telegram_outbox (
+    id            TEXT    PRIMARY KEY,
+    user_id       TEXT    NOT NULL,
+    kind          TEXT    NOT NULL,
+    payload       TEXT    NOT NULL,
+    urgent        BOOLEAN NOT NULL DEFAULT 0,
+    deliver_after TEXT    NOT NULL,
+    created_at    TEXT    NOT NULL,
+    delivered_at  TEXT,
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+"#;
+
+const CREATE_TELEGRAM_OUTBOX_DUE_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_telegram_outbox_due ON telegram_outbox(delivered_at, deliver_after);
+"#;
+
+const CREATE_BOT_PENDING_ACTIONS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS bot_pending_actions (
+    id               TEXT    PRIMARY KEY,
+    telegram_user_id TEXT    NOT NULL,
+    user_id          TEXT    NOT NULL,
+    summary          TEXT    NOT NULL,
+    action           TEXT    NOT NULL,
+    expires_at       INTEGER NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+"#;
+
+const CREATE_BOT_PENDING_ACTIONS_TELEGRAM_USER_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_bot_pending_actions_telegram_user ON bot_pending_actions(telegram_user_id);
+"#;
+
+// Login sessions for `session_store::LibsqlSessionStore`. `data` is the session's JSON
+// map and `expiry_date` a unix timestamp in seconds.
+const CREATE_SESSIONS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS sessions (
+    id          TEXT    PRIMARY KEY,
+    data        TEXT    NOT NULL,
+    expiry_date INTEGER NOT NULL
+);
+"#;
+
+const CREATE_SESSIONS_EXPIRY_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_sessions_expiry ON sessions(expiry_date);
+"#;
+
+// One row per login attempt, web or Telegram `/link`. `user_id` is NULL when no account
+// had the attempted `username`; `failure_reason` is NULL for successful logins.
+const CREATE_LOGIN_ATTEMPTS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS login_attempts (
+    id             TEXT    PRIMARY KEY,
+    user_id        TEXT,
+    username       TEXT    NOT NULL,
+    attempted_at   TEXT    NOT NULL,
+    success        BOOLEAN NOT NULL,
+    source         TEXT    NOT NULL,
+    failure_reason TEXT
+);
+"#;
+
+const CREATE_LOGIN_ATTEMPTS_USER_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_login_attempts_user ON login_attempts(user_id, attempted_at);
+"#;
+
+const CREATE_LOGIN_ATTEMPTS_USERNAME_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_login_attempts_username ON login_attempts(username, attempted_at);
+"#;
+
+const CREATE_LOGIN_ATTEMPTS_AT_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_login_attempts_at ON login_attempts(attempted_at);
+"#;
+
+// Per-user event feed (`notifications.rs`). `payload` is JSON holding the ids the
+// event refers to; `read_at` is NULL until the user marks it read.
+const CREATE_NOTIFICATIONS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS notifications (
+    id          TEXT    PRIMARY KEY,
+    user_id     TEXT    NOT NULL,
+    kind        TEXT    NOT NULL,
+    payload     TEXT    NOT NULL,
+    created_at  TEXT    NOT NULL,
+    read_at     TEXT
+);
+"#;
+
+const CREATE_NOTIFICATIONS_USER_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_notifications_user ON notifications(user_id, created_at);
+"#;
+
+// Participants who deleted their account while in someone else's split. Their share
+// records are gone; this row keeps the share visible to the initiator.
+const CREATE_SPLIT_DEPARTURES_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS split_departures (
+    split_id    TEXT    NOT NULL,
+    user_id     TEXT    NOT NULL,
+    username    TEXT    NOT NULL,
+    amount      INTEGER NOT NULL,
+    pending     BOOLEAN NOT NULL,
+    settled     BOOLEAN NOT NULL,
+    departed_at TEXT    NOT NULL,
+    PRIMARY KEY (split_id, user_id)
+);
+"#;
+
+// Single-use recovery codes for two-factor login, stored as SHA-256 hex of the
+// normalized code. Redeeming one deletes its row.
+const CREATE_TWO_FACTOR_RECOVERY_CODES_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS two_factor_recovery_codes (
+    user_id     TEXT    NOT NULL,
+    code_hash   TEXT    NOT NULL,
+    created_at  TEXT    NOT NULL,
+    PRIMARY KEY (user_id, code_hash)
+);
+"#;
+
+// Password-checked logins waiting for a two-factor code. `username` is the name the
+// login was attempted under, so the final attempt is recorded under the same key.
+const CREATE_TWO_FACTOR_CHALLENGES_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS two_factor_challenges (
+    token_hash  TEXT    PRIMARY KEY,
+    user_id     TEXT    NOT NULL,
+    username    TEXT    NOT NULL,
+    expires_at  TEXT    NOT NULL,
+    attempts    INTEGER NOT NULL DEFAULT 0
+);
+"#;
+
+// Personal access tokens. Only the SHA-256 of a token is kept; revoking deletes the row.
+const CREATE_API_TOKENS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id           TEXT    PRIMARY KEY,
+    user_id      TEXT    NOT NULL,
+    name         TEXT,
+    token_hash   TEXT    NOT NULL UNIQUE,
+    read_only    BOOLEAN NOT NULL DEFAULT 0,
+    created_at   TEXT    NOT NULL,
+    expires_at   TEXT,
+    last_used_at TEXT
+);
+"#;
+
+const CREATE_API_TOKENS_USER_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_api_tokens_user ON api_tokens(user_id, created_at);
+"#;
+
+const CREATE_JOBS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS jobs (
+    id           TEXT    PRIMARY KEY,
+    job_type     TEXT    NOT NULL,
+    payload_json TEXT    NOT NULL,
+    state        TEXT    NOT NULL,
+    run_at       TEXT    NOT NULL,
+    attempts     INTEGER NOT NULL DEFAULT 0,
+    lease_until  TEXT,
+    last_error   TEXT,
+    created_at   TEXT    NOT NULL,
+    finished_at  TEXT
+);
+"#;
+
+const CREATE_JOBS_DUE_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_jobs_due ON jobs(state, run_at);
+"#;
+
 pub type Db = Arc<RwLock<Connection>>;
 
+static DB_BUSY_RETRY: OnceLock<DbBusyRetry> = OnceLock::new();
+
+/// Installs the process-wide busy handling; call before opening the database. Returns
+/// `false` if one was already installed.
+pub fn install_busy_retry(params: DbBusyRetry) -> bool {
+    DB_BUSY_RETRY.set(params).is_ok()
+}
+
+fn busy_retry() -> DbBusyRetry {
+    DB_BUSY_RETRY.get().copied().unwrap_or_default()
+}
+
+/// `SQLITE_BUSY` or `SQLITE_LOCKED`, extended codes included: another connection holds
+/// the lock this statement needs.
+pub fn is_busy_error(error: &libsql::Error) -> bool {
+    match error {
+        libsql::Error::SqliteFailure(code, _) => matches!(code & 0xff, 5 | 6),
+        other => {
+            let message = other.to_string();
+            message.contains("database is locked") || message.contains("database is busy")
+        }
+    }
+}
+
+/// Runs `operation` again while it fails with a busy error, up to `max_retries` more
+/// times, sleeping `base_delay_ms * 2^n` plus up to half that again in between.
+pub async fn retry_if_busy<T, F, Fut>(mut operation: F) -> libsql::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = libsql::Result<T>>,
+{
+    let params = busy_retry();
+    let mut retry = 0;
+    loop {
+        match operation().await {
+            Err(e) if is_busy_error(&e) && retry < params.max_retries => {
+                let delay = params.base_delay_ms.saturating_mul(1 << retry.min(16));
+                // Spread out writers that were refused at the same moment
+                let jitter = (uuid::Uuid::new_v4().as_u128() % u128::from(delay / 2 + 1)) as u64;
+                tokio::time::sleep(Duration::from_millis(delay + jitter)).await;
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Every connection waits `busy_timeout_ms` for a lock before reporting busy, and the
+/// database runs in WAL mode so readers and the one writer do not block each other.
+async fn configure_connection(conn: &Connection) -> Result<()> {
+    for pragma in [
+        format!("PRAGMA busy_timeout = {}", busy_retry().busy_timeout_ms),
+        "PRAGMA journal_mode = WAL".to_string(),
+    ] {
+        // Both pragmas answer with a row
+        let mut rows = conn.query(&pragma, ()).await?;
+        while rows.next().await?.is_some() {}
+    }
+    Ok(())
+}
+
+async fn table_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut rows = conn
+        .query(&format!("PRAGMA table_info({})", table), ())
+        .await?;
+    let mut columns = Vec::new();
+    while let Some(row) = rows.next().await? {
+        columns.push(row.get::<String>(1)?);
+    }
+    Ok(columns)
+}
+
+/// Adds a column to an existing table when a DB created by an older build lacks it.
+/// `CREATE TABLE IF NOT EXISTS` never alters tables that are already present.
+async fn ensure_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    if table_columns(conn, table)
+        .await?
+        .iter()
+        .any(|name| name == column)
+    {
+        return Ok(());
+    }
+
+    conn.execute(
+        &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+        (),
+    )
+    .await?;
+    Ok(())
+}
+
+/// What `normalize_record_dates` did to stored `records.date` values.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RecordDateReport {
+    /// `(record id, stored value, rewritten value)`.
+    pub normalized: Vec<(String, String, String)>,
+    /// `(record id, stored value)` for dates that are not a calendar day at all.
+    pub unfixable: Vec<(String, String)>,
+}
+
+/// Rewrites malformed record dates that still name a day ("2025-3-2", " 2025-03-02 ")
+/// into `YYYY-MM-DD`, and reports the ones that do not.
+pub async fn normalize_record_dates(conn: &Connection) -> Result<RecordDateReport> {
+    let mut bad_dates = Vec::new();
+    let mut rows = conn.query(SELECT_RECORDS_WITH_BAD_DATES, ()).await?;
+    while let Some(row) = rows.next().await? {
+        let stored: Option<String> = row.get(1)?;
+        bad_dates.push((row.get::<String>(0)?, stored.unwrap_or_default()));
+    }
+    drop(rows);
+
+    let mut report = RecordDateReport::default();
+    for (id, stored) in bad_dates {
+        match to_db_date(&stored) {
+            Ok(date) => {
+                conn.execute(
+                    "UPDATE records SET date = ? WHERE id = ?",
+                    (date.as_str(), id.as_str()),
+                )
+                .await?;
+                report.normalized.push((id, stored, date));
+            }
+            Err(_) => report.unfixable.push((id, stored)),
+        }
+    }
+    Ok(report)
+}
+
+/// The `CREATE TABLE` statement `table` was created with, as altered since; `None` when
+/// there is no such table.
+async fn table_ddl(conn: &Connection, table: &str) -> Result<Option<String>> {
+    let mut rows = conn
+        .query(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?",
+            [table],
+        )
+        .await?;
+    Ok(match rows.next().await? {
+        Some(row) => Some(row.get::<String>(0)?),
+        None => None,
+    })
+}
+
+async fn records_have_date_check(conn: &Connection) -> Result<bool> {
+    Ok(table_ddl(conn, "records")
+        .await?
+        .is_some_and(|ddl| ddl.contains(RECORDS_DATE_CHECK_MARKER)))
+}
+
+/// SQLite cannot add a CHECK to an existing table, so `records` is copied into a table
+/// created from the current DDL. Its indexes and triggers are recreated by `init_main_db`.
+async fn rebuild_records_table(conn: &Connection) -> Result<()> {
+    conn.execute("BEGIN TRANSACTION", ()).await?;
+    let rebuilt = async {
+        conn.execute("DROP TABLE IF EXISTS records_rebuilt", ())
+            .await?;
+        conn.execute(
+            &CREATE_RECORDS_TABLE.replace("IF NOT EXISTS records", "records_rebuilt"),
+            (),
+        )
+        .await?;
+        let new_columns = table_columns(conn, "records_rebuilt").await?;
+        let columns = table_columns(conn, "records")
+            .await?
+            .into_iter()
+            .filter(|column| new_columns.contains(column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        conn.execute(
+            &format!(
+                "INSERT INTO records_rebuilt ({columns}) SELECT {columns} FROM records ORDER BY rowid"
+            ),
+            (),
+        )
+        .await?;
+        conn.execute("DROP TABLE records", ()).await?;
+        conn.execute("ALTER TABLE records_rebuilt RENAME TO records", ())
+            .await?;
+        Ok::<(), anyhow::Error>(())
+    }
+    .await;
+    match rebuilt {
+        Ok(()) => {
+            conn.execute("COMMIT", ()).await?;
+            Ok(())
+        }
+        Err(e) => {
+            let _ = conn.execute("ROLLBACK", ()).await;
+            Err(e)
+        }
+    }
+}
+
+/// Puts the `records.date` CHECK in place on databases created before it existed.
+/// Malformed dates are normalized first; while any cannot be, the CHECK is left off and
+/// the offending records are reported on every start until they are fixed by hand.
+async fn migrate_records_date_check(conn: &Connection) -> Result<()> {
+    if records_have_date_check(conn).await? {
+        return Ok(());
+    }
+
+    let report = normalize_record_dates(conn).await?;
+    for (id, stored, date) in &report.normalized {
+        tracing::info!(record_id = %id, stored = ?stored, %date, "normalized record date");
+    }
+    if !report.unfixable.is_empty() {
+        for (id, stored) in &report.unfixable {
+            tracing::warn!(record_id = %id, stored = ?stored, "record has an unfixable date");
+        }
+        tracing::warn!(
+            unfixable = report.unfixable.len(),
+            "records.date CHECK not applied; fix the records above and restart"
+        );
+        return Ok(());
+    }
+
+    rebuild_records_table(conn).await
+}
+
+/// `ddl` with each of `columns` declared `INTEGER` where it was `REAL`.
+fn declare_integer_columns(ddl: &str, columns: &[&str]) -> String {
+    ddl.split_inclusive(',')
+        .map(|definition| {
+            let body = definition.trim_start();
+            let indent = &definition[..definition.len() - body.len()];
+            for column in columns {
+                let Some(rest) = body.strip_prefix(column) else {
+                    continue;
+                };
+                let declared = rest.trim_start();
+                if declared.len() < rest.len() && declared.starts_with("REAL") {
+                    let spacing = &rest[..rest.len() - declared.len()];
+                    return format!("{indent}{column}{spacing}INTEGER{}", &declared[4..]);
+                }
+            }
+            definition.to_string()
+        })
+        .collect()
+}
+
+/// SQLite cannot change a column's type, and a `REAL` column turns every integer written
+/// to it back into a float. So `table` is copied into one declared from its own DDL with
+/// `columns` as `INTEGER`, converting their decimal amounts to cents on the way. Its
+/// indexes and triggers are recreated by `init_main_db`; a missing table is left alone.
+async fn amounts_to_cents(conn: &Connection, table: &str, columns: &[&str]) -> Result<()> {
+    let Some(ddl) = table_ddl(conn, table).await? else {
+        return Ok(());
+    };
+    let definitions = ddl
+        .find('(')
+        .map(|open| declare_integer_columns(&ddl[open..], columns))
+        .ok_or_else(|| anyhow::anyhow!("table {table} has no column definitions"))?;
+    let rebuilt = format!("{table}_rebuilt");
+    conn.execute(&format!("DROP TABLE IF EXISTS {rebuilt}"), ())
+        .await?;
+    conn.execute(&format!("CREATE TABLE {rebuilt} {definitions}"), ())
+        .await?;
+
+    let names = table_columns(conn, table).await?;
+    let values = names
+        .iter()
+        .map(|name| {
+            if columns.contains(&name.as_str()) {
+                format!("CAST(ROUND({name} * 100) AS INTEGER)")
+            } else {
+                name.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    conn.execute(
+        &format!(
+            "INSERT INTO {rebuilt} ({}) SELECT {values} FROM {table} ORDER BY rowid",
+            names.join(", ")
+        ),
+        (),
+    )
+    .await?;
+    conn.execute(&format!("DROP TABLE {table}"), ()).await?;
+    conn.execute(&format!("ALTER TABLE {rebuilt} RENAME TO {table}"), ())
+        .await?;
+    Ok(())
+}
+
+const CREATE_SCHEMA_VERSION_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS schema_version (
+    version     INTEGER PRIMARY KEY,
+    name        TEXT    NOT NULL,
+    applied_at  TEXT    NOT NULL
+);
+"#;
+
+/// Records from before currencies were in whatever the owner's `currency_code` was set to,
+/// so they take it over; the column default covers everyone else (`DEFAULT_CURRENCY_CODE`).
+const BACKFILL_RECORDS_CURRENCY: &str = r#"
+UPDATE records SET currency = (
+    SELECT s.currency_code FROM user_settings s WHERE s.user_id = records.owner_user_id
+)
+WHERE owner_user_id IN (SELECT user_id FROM user_settings WHERE currency_code IS NOT NULL)
+"#;
+
+/// One change of a migration. `AddColumn` is skipped when the column is already there,
+/// so a step also runs cleanly on databases whose tables were created with it.
+pub enum MigrationStep {
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        definition: &'static str,
+    },
+    Sql(&'static str),
+    /// See `amounts_to_cents`.
+    AmountsToCents {
+        table: &'static str,
+        columns: &'static [&'static str],
+    },
+}
+
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub steps: &'static [MigrationStep],
+}
+
+/// Ordered schema changes, applied once each by `apply_migrations` and recorded in
+/// `schema_version`. Append new entries; never renumber or edit a released one.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "records_deleted_at",
+        steps: &[
+            MigrationStep::AddColumn {
+                table: "records",
+                column: "deleted_at",
+                definition: "TEXT",
+            },
+            MigrationStep::Sql(CREATE_RECORDS_DELETED_AT_INDEX),
+        ],
+    },
+    Migration {
+        version: 2,
+        name: "records_split_columns",
+        steps: &[
+            MigrationStep::AddColumn {
+                table: "records",
+                column: "split_id",
+                definition: "TEXT",
+            },
+            MigrationStep::AddColumn {
+                table: "records",
+                column: "debtor_user_id",
+                definition: "TEXT",
+            },
+            MigrationStep::AddColumn {
+                table: "records",
+                column: "creditor_user_id",
+                definition: "TEXT",
+            },
+            MigrationStep::Sql(CREATE_RECORDS_SPLIT_INDEX),
+            MigrationStep::Sql(CREATE_RECORDS_SPLIT_CREDITOR_INDEX),
+            MigrationStep::Sql(CREATE_RECORDS_SPLIT_DEBTOR_INDEX),
+        ],
+    },
+    Migration {
+        version: 3,
+        name: "users_two_factor",
+        steps: &[
+            MigrationStep::AddColumn {
+                table: "users",
+                column: "totp_secret",
+                definition: "TEXT",
+            },
+            MigrationStep::AddColumn {
+                table: "users",
+                column: "totp_enabled_at",
+                definition: "TEXT",
+            },
+            MigrationStep::AddColumn {
+                table: "users",
+                column: "totp_last_step",
+                definition: "INTEGER",
+            },
+        ],
+    },
+    Migration {
+        version: 4,
+        name: "records_note",
+        steps: &[MigrationStep::AddColumn {
+            table: "records",
+            column: "note",
+            definition: "TEXT",
+        }],
+    },
+    Migration {
+        version: 5,
+        name: "records_currency",
+        steps: &[
+            MigrationStep::AddColumn {
+                table: "records",
+                column: "currency",
+                definition: "TEXT NOT NULL DEFAULT 'TWD'",
+            },
+            MigrationStep::Sql(BACKFILL_RECORDS_CURRENCY),
+        ],
+    },
+    Migration {
+        version: 6,
+        name: "amounts_in_cents",
+        steps: &[
+            MigrationStep::AmountsToCents {
+                table: "records",
+                columns: &["amount"],
+            },
+            MigrationStep::AmountsToCents {
+                table: "split_departures",
+                columns: &["amount"],
+            },
+            MigrationStep::AmountsToCents {
+                table: "recategorize_batch_items",
+                columns: &["previous_amount"],
+            },
+        ],
+    },
+    Migration {
+        version: 7,
+        name: "records_split_full_total",
+        steps: &[MigrationStep::AddColumn {
+            table: "records",
+            column: "split_full_total",
+            definition: "BOOLEAN NOT NULL DEFAULT 0",
+        }],
+    },
+    Migration {
+        version: 8,
+        name: "user_settings_telegram_split_notifications",
+        steps: &[MigrationStep::AddColumn {
+            table: "user_settings",
+            column: "telegram_split_notifications",
+            definition: "BOOLEAN",
+        }],
+    },
+    Migration {
+        version: 9,
+        name: "category_budgets_in_cents",
+        steps: &[MigrationStep::AmountsToCents {
+            table: "categories",
+            columns: &["monthly_budget"],
+        }],
+    },
+];
+
+/// The newest version in `MIGRATIONS`; a database past it was written by a newer build.
+pub fn latest_schema_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// Highest version recorded in `schema_version`; 0 when none is, or the table is missing.
+pub async fn applied_schema_version(conn: &Connection) -> Result<u32> {
+    let mut rows = conn
+        .query(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'",
+            (),
+        )
+        .await?;
+    if rows.next().await?.is_none() {
+        return Ok(0);
+    }
+    drop(rows);
+
+    let mut rows = conn
+        .query("SELECT COALESCE(MAX(version), 0) FROM schema_version", ())
+        .await?;
+    Ok(match rows.next().await? {
+        Some(row) => row.get::<u32>(0)?,
+        None => 0,
+    })
+}
+
+/// The applied version, or an error when it is past `latest_schema_version`.
+pub async fn ensure_schema_supported(conn: &Connection) -> Result<u32> {
+    let current = applied_schema_version(conn).await?;
+    let latest = latest_schema_version();
+    if current > latest {
+        anyhow::bail!(
+            "database schema version {current} is newer than this build supports ({latest}); \
+             upgrade kash-server"
+        );
+    }
+    Ok(current)
+}
+
+/// Runs every migration newer than the recorded version, each in its own transaction
+/// together with its `schema_version` row. Returns the versions applied. Fails without
+/// touching anything when the database is newer than this build.
+pub async fn apply_migrations(conn: &Connection) -> Result<Vec<u32>> {
+    let current = ensure_schema_supported(conn).await?;
+    conn.execute(CREATE_SCHEMA_VERSION_TABLE, ()).await?;
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        conn.execute("BEGIN TRANSACTION", ()).await?;
+        let result = async {
+            for step in migration.steps {
+                match step {
+                    MigrationStep::AddColumn {
+                        table,
+                        column,
+                        definition,
+                    } => ensure_column(conn, table, column, definition).await?,
+                    MigrationStep::Sql(sql) => {
+                        conn.execute(sql, ()).await?;
+                    }
+                    MigrationStep::AmountsToCents { table, columns } => {
+                        amounts_to_cents(conn, table, columns).await?
+                    }
+                }
+            }
+            conn.execute(
+                "INSERT INTO schema_version (version, name, applied_at) VALUES (?, ?, ?)",
+                libsql::params![
+                    migration.version,
+                    migration.name,
+                    precise_timestamp(OffsetDateTime::now_utc())
+                ],
+            )
+            .await?;
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+        match result {
+            Ok(()) => conn.execute("COMMIT", ()).await?,
+            Err(e) => {
+                let _ = conn.execute("ROLLBACK", ()).await;
+                return Err(e.context(format!(
+                    "migration {} ({}) failed",
+                    migration.version, migration.name
+                )));
+            }
+        };
+        tracing::info!(
+            version = migration.version,
+            name = migration.name,
+            "applied schema migration"
+        );
+        applied.push(migration.version);
+    }
+    Ok(applied)
+}
+
 /// Single shared DB — contains all tables (users, records, categories, friends, etc.)
+/// Tables `init_main_db` creates; `check_main_db` reports any that are missing.
+pub const MAIN_DB_TABLES: &[&str] = &[
+    "users",
+    "username_history",
+    "telegram_users",
+    "records",
+    "record_provenance",
+    "record_tags",
+    "recategorize_batches",
+    "recategorize_batch_items",
+    "categories",
+    "friendship",
+    "user_settings",
+    "period_reopen_audit",
+    "idempotency_keys",
+    "telegram_outbox",
+    "bot_pending_actions",
+    "jobs",
+    "sessions",
+    "login_attempts",
+    "split_departures",
+    "two_factor_recovery_codes",
+    "two_factor_challenges",
+    "api_tokens",
+    "notifications",
+    "schema_version",
+];
+
+/// What `check_main_db` found. The database is healthy when both lists are empty.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct DbCheckReport {
+    /// `PRAGMA integrity_check` messages other than `ok`.
+    pub integrity_errors: Vec<String>,
+    /// Entries of `MAIN_DB_TABLES` that do not exist; `init_main_db` creates them.
+    pub missing_tables: Vec<String>,
+}
+
+impl DbCheckReport {
+    pub fn is_healthy(&self) -> bool {
+        self.integrity_errors.is_empty() && self.missing_tables.is_empty()
+    }
+}
+
+pub fn main_db_path(data_dir: &str) -> PathBuf {
+    Path::new(data_dir).join("users.db")
+}
+
+/// Opens the main database without creating or migrating it. `None` when the
+/// file does not exist.
+pub async fn open_main_db(data_dir: &str) -> Result<Option<Connection>> {
+    open_main_db_at(data_dir, &DatabaseLocation::Local).await
+}
+
+/// Like `open_main_db`, at `location`; a remote database is always there to open.
+pub async fn open_main_db_at(
+    data_dir: &str,
+    location: &DatabaseLocation,
+) -> Result<Option<Connection>> {
+    if location.is_local() && !tokio::fs::try_exists(main_db_path(data_dir)).await? {
+        return Ok(None);
+    }
+    Ok(Some(connect(data_dir, location).await?))
+}
+
+/// A connection to the main database at `location`. A local file gets the busy and WAL
+/// pragmas; an embedded replica pulls the server's state before it is used and keeps
+/// syncing in the background for the life of the process.
+async fn connect(data_dir: &str, location: &DatabaseLocation) -> Result<Connection> {
+    match location {
+        DatabaseLocation::Local => {
+            tokio::fs::create_dir_all(data_dir).await?;
+            let db = Builder::new_local(main_db_path(data_dir)).build().await?;
+            let conn = db.connect()?;
+            configure_connection(&conn).await?;
+            Ok(conn)
+        }
+        DatabaseLocation::Remote { url, auth_token } => {
+            let db = Builder::new_remote(url.clone(), auth_token.clone())
+                .build()
+                .await
+                .with_context(|| format!("failed to open remote database {url}"))?;
+            Ok(db.connect()?)
+        }
+        DatabaseLocation::Replica {
+            url,
+            auth_token,
+            sync_interval_secs,
+        } => {
+            tokio::fs::create_dir_all(data_dir).await?;
+            let db = Builder::new_remote_replica(
+                main_db_path(data_dir),
+                url.clone(),
+                auth_token.clone(),
+            )
+            .build()
+            .await
+            .with_context(|| format!("failed to open replica of {url}"))?;
+            db.sync()
+                .await
+                .with_context(|| format!("failed to sync replica of {url}"))?;
+            let conn = db.connect()?;
+
+            let interval = Duration::from_secs(*sync_interval_secs);
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(interval).await;
+                    if let Err(e) = db.sync().await {
+                        tracing::warn!(error = %e, "replica sync failed");
+                    }
+                }
+            });
+            Ok(conn)
+        }
+    }
+}
+
+/// Runs SQLite's integrity check and looks for tables a migration would add.
+pub async fn check_main_db(conn: &Connection) -> Result<DbCheckReport> {
+    let mut report = DbCheckReport::default();
+
+    let mut rows = conn.query("PRAGMA integrity_check", ()).await?;
+    while let Some(row) = rows.next().await? {
+        let message: String = row.get(0)?;
+        if message != "ok" {
+            report.integrity_errors.push(message);
+        }
+    }
+    drop(rows);
+
+    for table in MAIN_DB_TABLES {
+        let mut rows = conn
+            .query(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?",
+                [*table],
+            )
+            .await?;
+        if rows.next().await?.is_none() {
+            report.missing_tables.push(table.to_string());
+        }
+    }
+    Ok(report)
+}
+
 pub async fn init_main_db(data_dir: &str) -> Result<Db> {
-    tokio::fs::create_dir_all(data_dir).await?;
-    let path = Path::new(data_dir).join("users.db");
-    let db = Builder::new_local(path).build().await?;
-    let conn = db.connect()?;
+    init_main_db_at(data_dir, &DatabaseLocation::Local).await
+}
+
+/// Opens the main database at `location`, creating and migrating its tables.
+pub async fn init_main_db_at(data_dir: &str, location: &DatabaseLocation) -> Result<Db> {
+    let conn = connect(data_dir, location).await?;
+
+    // Checked before any DDL so an older build leaves a newer database untouched
+    ensure_schema_supported(&conn).await?;
 
     conn.execute(CREATE_USERS_TABLE, ()).await?;
+    ensure_column(&conn, "users", "username_changed_at", "TEXT").await?;
+    ensure_column(&conn, "users", "disabled_at", "TEXT").await?;
+    ensure_column(&conn, "users", "records_key_version", "INTEGER").await?;
+    ensure_column(&conn, "users", "last_login_at", "TEXT").await?;
+    ensure_column(&conn, "users", "previous_login_at", "TEXT").await?;
+    ensure_column(&conn, "users", "last_seen_whats_new", "TEXT").await?;
+    conn.execute(CREATE_USERNAME_HISTORY_TABLE, ()).await?;
+    conn.execute(CREATE_USERNAME_HISTORY_USER_INDEX, ()).await?;
     conn.execute(CREATE_TELEGRAM_USERS_TABLE, ()).await?;
     conn.execute(CREATE_RECORDS_TABLE, ()).await?;
+    ensure_column(
+        &conn,
+        "records",
+        "created_via",
+        "TEXT NOT NULL DEFAULT 'api'",
+    )
+    .await?;
+    ensure_column(&conn, "records", "seq", "INTEGER").await?;
+    ensure_column(&conn, "records", "original_amount", "REAL").await?;
+    ensure_column(&conn, "records", "original_currency", "TEXT").await?;
+    ensure_column(&conn, "records", "created_at", "TEXT").await?;
+    ensure_column(&conn, "records", "settled_at", "TEXT").await?;
+    // Before the migrations: `records_currency` fills in each user's currency from it
+    conn.execute(CREATE_USER_SETTINGS_TABLE, ()).await?;
+    ensure_column(&conn, "user_settings", "utc_offset_minutes", "INTEGER").await?;
+    ensure_column(&conn, "user_settings", "alert_batch_time", "TEXT").await?;
+    ensure_column(&conn, "user_settings", "currency_code", "TEXT").await?;
+    apply_migrations(&conn).await?;
+    migrate_records_date_check(&conn).await?;
+    conn.execute(BACKFILL_RECORDS_SEQ, ()).await?;
+    conn.execute(CREATE_RECORDS_SEQ_INDEX, ()).await?;
+    conn.execute(CREATE_RECORDS_SEQ_TRIGGER, ()).await?;
+    conn.execute(CREATE_RECORD_PROVENANCE_TABLE, ()).await?;
+    ensure_column(&conn, "record_provenance", "ai_category_id", "TEXT").await?;
+    conn.execute(CREATE_RECORD_TAGS_TABLE, ()).await?;
+    conn.execute(CREATE_RECATEGORIZE_BATCHES_TABLE, ()).await?;
+    conn.execute(CREATE_RECATEGORIZE_BATCH_ITEMS_TABLE, ())
+        .await?;
     conn.execute(CREATE_CATEGORIES_TABLE, ()).await?;
-    conn.execute(CREATE_RECORDS_DATE_INDEX, ()).await?;
+    conn.execute("DROP INDEX IF EXISTS idx_records_date", ())
+        .await?;
+    conn.execute(CREATE_RECORDS_DATE_ID_INDEX, ()).await?;
     conn.execute(CREATE_RECORDS_OWNER_INDEX, ()).await?;
+    conn.execute(CREATE_RECORDS_DELETED_AT_INDEX, ()).await?;
+    conn.execute(CREATE_RECORDS_SPLIT_INDEX, ()).await?;
+    conn.execute(CREATE_RECORDS_SPLIT_CREDITOR_INDEX, ())
+        .await?;
+    conn.execute(CREATE_RECORDS_SPLIT_DEBTOR_INDEX, ()).await?;
     conn.execute(CREATE_CATEGORIES_OWNER_INDEX, ()).await?;
+    ensure_column(&conn, "categories", "parent_id", "TEXT").await?;
+    ensure_column(&conn, "categories", "monthly_budget", "INTEGER").await?;
+    ensure_column(
+        &conn,
+        "categories",
+        "archived",
+        "BOOLEAN NOT NULL DEFAULT FALSE",
+    )
+    .await?;
+    conn.execute(CREATE_CATEGORIES_PARENT_INDEX, ()).await?;
+    conn.execute_batch(MERGE_CASE_DUPLICATE_CATEGORIES).await?;
+    conn.execute(CREATE_CATEGORIES_OWNER_NAME_NOCASE_INDEX, ())
+        .await?;
     conn.execute(CREATE_FRIENDSHIP_TABLE, ()).await?;
+    ensure_column(
+        &conn,
+        "friendship",
+        "status",
+        "TEXT NOT NULL DEFAULT 'active'",
+    )
+    .await?;
+    ensure_column(&conn, "friendship", "status_changed_at", "TEXT").await?;
+    ensure_column(&conn, "friendship", "created_at", "TEXT").await?;
+    ensure_column(&conn, "friendship", "request_message", "TEXT").await?;
+    ensure_column(&conn, "friendship", "accepted_at", "TEXT").await?;
     conn.execute(CREATE_FRIENDSHIP_FROM_INDEX, ()).await?;
     conn.execute(CREATE_FRIENDSHIP_TO_INDEX, ()).await?;
+    conn.execute(CREATE_FRIENDSHIP_STATUS_INDEX, ()).await?;
+    conn.execute(CREATE_USERS_NAME_INDEX, ()).await?;
+    conn.execute(CREATE_PERIOD_REOPEN_AUDIT_TABLE, ()).await?;
+    conn.execute(CREATE_PERIOD_REOPEN_AUDIT_OWNER_INDEX, ())
+        .await?;
     conn.execute(CREATE_IDEMPOTENCY_KEYS_TABLE, ()).await?;
     conn.execute(CREATE_IDEMPOTENCY_USER_INDEX, ()).await?;
+    ensure_column(&conn, "idempotency_keys", "response_digest", "TEXT").await?;
+    conn.execute(CREATE_IDEMPOTENCY_LOOKUP_INDEX, ()).await?;
+    conn.execute(CREATE_TELEGRAM_OUTBOX_TABLE, ()).await?;
+    conn.execute(CREATE_TELEGRAM_OUTBOX_DUE_INDEX, ()).await?;
+    conn.execute(CREATE_BOT_PENDING_ACTIONS_TABLE, ()).await?;
+    conn.execute(CREATE_BOT_PENDING_ACTIONS_TELEGRAM_USER_INDEX, ())
+        .await?;
+    conn.execute(CREATE_JOBS_TABLE, ()).await?;
+    conn.execute(CREATE_JOBS_DUE_INDEX, ()).await?;
+    // Sessions moved out of process memory; older databases gain the table here
+    conn.execute(CREATE_SESSIONS_TABLE, ()).await?;
+    conn.execute(CREATE_SESSIONS_EXPIRY_INDEX, ()).await?;
+    conn.execute(CREATE_LOGIN_ATTEMPTS_TABLE, ()).await?;
+    conn.execute(CREATE_LOGIN_ATTEMPTS_USER_INDEX, ()).await?;
+    conn.execute(CREATE_LOGIN_ATTEMPTS_USERNAME_INDEX, ())
+        .await?;
+    conn.execute(CREATE_LOGIN_ATTEMPTS_AT_INDEX, ()).await?;
+    conn.execute(CREATE_SPLIT_DEPARTURES_TABLE, ()).await?;
+    conn.execute(CREATE_TWO_FACTOR_RECOVERY_CODES_TABLE, ())
+        .await?;
+    conn.execute(CREATE_TWO_FACTOR_CHALLENGES_TABLE, ()).await?;
+    conn.execute(CREATE_API_TOKENS_TABLE, ()).await?;
+    conn.execute(CREATE_API_TOKENS_USER_INDEX, ()).await?;
+    conn.execute(CREATE_NOTIFICATIONS_TABLE, ()).await?;
+    conn.execute(CREATE_NOTIFICATIONS_USER_INDEX, ()).await?;
 
     Ok(Arc::new(RwLock::new(conn)))
 }
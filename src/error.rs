@@ -0,0 +1,140 @@
+use std::borrow::Cow;
+
+use axum::{
+    Json,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::constants::*;
+
+/// A handler error sent as a JSON body, `{"code": ..., "message": ..., "details": ...}`.
+///
+/// `code` is a stable identifier clients can branch on (the `ERROR_CODE_*` constants);
+/// `message` is for people and may change. Handlers still returning `(StatusCode, String)`
+/// convert through `From`: a message led by an upper-case tag such as `PERIOD_CLOSED: ...`
+/// gets that tag as its code (`period_closed`), any other the code for its status.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: Cow<'static, str>,
+    pub message: String,
+    pub details: Option<Value>,
+    /// Seconds sent as `Retry-After`, for 429s.
+    pub retry_after_secs: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct ApiErrorBody<'a> {
+    code: &'a str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<&'a Value>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code: Cow::Borrowed(code),
+            message: message.into(),
+            details: None,
+            retry_after_secs: None,
+        }
+    }
+
+    /// A 429 telling the client when to try again.
+    pub fn too_many_requests(message: impl Into<String>, retry_after_secs: u64) -> Self {
+        Self {
+            retry_after_secs: Some(retry_after_secs),
+            ..Self::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                ERROR_CODE_RATE_LIMITED,
+                message,
+            )
+        }
+    }
+
+    /// A 400 with the generic `ERROR_CODE_BAD_REQUEST` code.
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, ERROR_CODE_BAD_REQUEST, message)
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+/// The code for an error that has no more specific one.
+pub fn status_error_code(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::UNAUTHORIZED => ERROR_CODE_UNAUTHORIZED,
+        StatusCode::FORBIDDEN => ERROR_CODE_FORBIDDEN,
+        StatusCode::NOT_FOUND => ERROR_CODE_NOT_FOUND,
+        StatusCode::CONFLICT => ERROR_CODE_CONFLICT,
+        StatusCode::PAYLOAD_TOO_LARGE => ERROR_CODE_PAYLOAD_TOO_LARGE,
+        StatusCode::URI_TOO_LONG => ERROR_CODE_URI_TOO_LONG,
+        StatusCode::UNPROCESSABLE_ENTITY => ERROR_CODE_UNPROCESSABLE,
+        StatusCode::TOO_MANY_REQUESTS => ERROR_CODE_RATE_LIMITED,
+        status if status.is_client_error() => ERROR_CODE_BAD_REQUEST,
+        _ => ERROR_CODE_INTERNAL,
+    }
+}
+
+/// The `TAG` of a `TAG: ...` message, as the repo's `*_PREFIX` and `*_MESSAGE` constants write them.
+fn message_tag(message: &str) -> Option<&str> {
+    let (tag, _) = message.split_once(": ")?;
+    let is_tag = !tag.is_empty()
+        && tag.starts_with(|c: char| c.is_ascii_uppercase())
+        && tag.chars().all(|c| c.is_ascii_uppercase() || c == '_');
+    is_tag.then_some(tag)
+}
+
+impl From<(StatusCode, String)> for ApiError {
+    fn from((status, message): (StatusCode, String)) -> Self {
+        let code = match message_tag(&message) {
+            Some(tag) => Cow::Owned(tag.to_ascii_lowercase()),
+            None => Cow::Borrowed(status_error_code(status)),
+        };
+        Self {
+            status,
+            code,
+            message,
+            details: None,
+            retry_after_secs: None,
+        }
+    }
+}
+
+/// For callers not yet moved to `ApiError`; the code and details are dropped.
+impl From<ApiError> for (StatusCode, String) {
+    fn from(error: ApiError) -> Self {
+        (error.status, error.message)
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {}", self.status, self.code, self.message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ApiErrorBody {
+            code: &self.code,
+            message: &self.message,
+            details: self.details.as_ref(),
+        };
+        let mut response = (self.status, Json(body)).into_response();
+        if let Some(secs) = self.retry_after_secs {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, secs.into());
+        }
+        response
+    }
+}
@@ -0,0 +1,860 @@
+use std::collections::{BTreeSet, HashMap};
+
+use axum::{
+    Json,
+    body::Body,
+    extract::{Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use serde_json::Value;
+use time::OffsetDateTime;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tower_sessions::Session;
+use uuid::Uuid;
+
+use crate::auth::get_current_user;
+use crate::categories::validate_category_name;
+use crate::constants::*;
+use crate::crypto;
+use crate::error::ApiError;
+use crate::models::{
+    AccountArchive, AccountFriendship, AccountProfile, Category, ExportArchive,
+    ExportCategoryTotal, ExportFriend, ExportQuery, ExportRecord, ImportAccountResponse,
+    PublicUser,
+};
+use crate::money::Money;
+use crate::record_repo::{self, NewRecord};
+use crate::records::normalize_record_currency;
+use crate::settings::user_currency_code;
+use crate::split_repo;
+use crate::splits::load_splits;
+use crate::utils::{database_busy, db_error, db_error_with_context, validate_date};
+use crate::{AppState, Db, TransactionError, with_transaction};
+
+const EXPORT_RECORD_COLUMNS: &str = "id, name, amount, category_id, date, seq, created_via, pending, settle, split_id, \
+     debtor_user_id, creditor_user_id, original_amount, original_currency, note, \
+     (SELECT group_concat(tag) FROM record_tags WHERE record_tags.record_id = records.id), currency";
+
+enum ImportAccountError {
+    Transaction(TransactionError),
+    Db(libsql::Error),
+    NotEmpty,
+}
+
+impl From<TransactionError> for ImportAccountError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+impl From<libsql::Error> for ImportAccountError {
+    fn from(value: libsql::Error) -> Self {
+        Self::Db(value)
+    }
+}
+
+impl From<ImportAccountError> for (StatusCode, String) {
+    fn from(value: ImportAccountError) -> Self {
+        match value {
+            ImportAccountError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction")
+            }
+            ImportAccountError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction")
+            }
+            ImportAccountError::Transaction(TransactionError::Busy) => database_busy(),
+            ImportAccountError::Db(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            ImportAccountError::NotEmpty => {
+                (StatusCode::CONFLICT, ACCOUNT_NOT_EMPTY_MESSAGE.to_string())
+            }
+        }
+    }
+}
+
+/// Another user that appears in an export and must be hidden by `redact=friends`.
+/// `aliases` are display strings (username, nickname) replaced on exact match.
+pub struct RedactionIdentity {
+    pub user_id: String,
+    pub aliases: Vec<String>,
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct ExportOptions {
+    pub redact_friends: bool,
+    pub redact_notes: bool,
+    pub categories_only: bool,
+}
+
+impl ExportOptions {
+    pub fn from_query(query: &ExportQuery) -> Result<Self, (StatusCode, String)> {
+        let mut options = Self {
+            categories_only: query.categories_only.unwrap_or(false),
+            ..Self::default()
+        };
+
+        for item in query
+            .redact
+            .as_deref()
+            .unwrap_or("")
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+        {
+            match item {
+                EXPORT_REDACT_FRIENDS => options.redact_friends = true,
+                EXPORT_REDACT_NOTES => options.redact_notes = true,
+                _ => {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        format!(
+                            "redact must be a comma-separated list of: {}, {}",
+                            EXPORT_REDACT_FRIENDS, EXPORT_REDACT_NOTES
+                        ),
+                    ));
+                }
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// e.g. `1+redact.friends+categories_only`; plain `1` for a full export.
+    pub fn schema_version(&self) -> String {
+        let mut version = EXPORT_SCHEMA_VERSION.to_string();
+        if self.redact_friends {
+            version.push_str("+redact.friends");
+        }
+        if self.redact_notes {
+            version.push_str("+redact.notes");
+        }
+        if self.categories_only {
+            version.push_str("+categories_only");
+        }
+        version
+    }
+}
+
+/// Replaces every reference to the given users with `friend-N` pseudonyms.
+///
+/// Numbers are handed out in order of first appearance in the archive, so the
+/// mapping is consistent within one export but carries nothing derived from
+/// the real ids. User ids are also replaced inside longer strings.
+pub fn pseudonymize_users(value: &mut Value, identities: &[RedactionIdentity]) {
+    let mut assigned: HashMap<usize, String> = HashMap::new();
+    pseudonymize_value(value, identities, &mut assigned);
+}
+
+fn pseudonymize_value(
+    value: &mut Value,
+    identities: &[RedactionIdentity],
+    assigned: &mut HashMap<usize, String>,
+) {
+    match value {
+        Value::String(text) => {
+            if let Some(replaced) = pseudonymize_string(text, identities, assigned) {
+                *text = replaced;
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                pseudonymize_value(item, identities, assigned);
+            }
+        }
+        Value::Object(map) => {
+            for (_, item) in map.iter_mut() {
+                pseudonymize_value(item, identities, assigned);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn pseudonymize_string(
+    text: &str,
+    identities: &[RedactionIdentity],
+    assigned: &mut HashMap<usize, String>,
+) -> Option<String> {
+    let mut pseudonym_for = |index: usize| {
+        let next = assigned.len() + 1;
+        assigned
+            .entry(index)
+            .or_insert_with(|| format!("{}{}", EXPORT_FRIEND_PSEUDONYM_PREFIX, next))
+            .clone()
+    };
+
+    if let Some(index) = identities.iter().position(|identity| {
+        identity.user_id == text || identity.aliases.iter().any(|alias| alias == text)
+    }) {
+        return Some(pseudonym_for(index));
+    }
+
+    let mut result: Option<String> = None;
+    for (index, identity) in identities.iter().enumerate() {
+        if identity.user_id.is_empty() {
+            continue;
+        }
+        let current = result.as_deref().unwrap_or(text);
+        if current.contains(&identity.user_id) {
+            result = Some(current.replace(&identity.user_id, &pseudonym_for(index)));
+        }
+    }
+    result
+}
+
+/// Removes every `field` key from all objects in the archive.
+pub fn drop_field(value: &mut Value, field: &str) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                drop_field(item, field);
+            }
+        }
+        Value::Object(map) => {
+            map.remove(field);
+            for (_, item) in map.iter_mut() {
+                drop_field(item, field);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Applies the requested redactions to an assembled archive and stamps its schema version.
+pub fn redact_archive(
+    archive: &mut Value,
+    options: &ExportOptions,
+    identities: &[RedactionIdentity],
+) {
+    if options.redact_friends {
+        pseudonymize_users(archive, identities);
+    }
+    if options.redact_notes {
+        drop_field(archive, "notes");
+        drop_field(archive, "note");
+    }
+    if let Some(map) = archive.as_object_mut() {
+        map.insert(
+            "schema_version".to_string(),
+            Value::String(options.schema_version()),
+        );
+    }
+}
+
+async fn load_export_categories(
+    conn: &libsql::Connection,
+    user_id: &str,
+) -> Result<Vec<Category>, (StatusCode, String)> {
+    let mut rows = conn
+        .query(
+            "SELECT id, name, is_income, parent_id, monthly_budget, archived FROM categories WHERE owner_user_id = ? ORDER BY name ASC",
+            [user_id],
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query categories"))?;
+
+    let mut categories = Vec::new();
+    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        categories.push(crate::categories::extract_category_from_row(row)?);
+    }
+    Ok(categories)
+}
+
+fn export_record_from_row(row: &libsql::Row) -> Result<ExportRecord, (StatusCode, String)> {
+    let invalid = |_| db_error_with_context("invalid record data");
+    Ok(ExportRecord {
+        id: row.get(0).map_err(invalid)?,
+        name: crypto::open_field(row.get(1).map_err(invalid)?)
+            .map_err(|_| db_error_with_context("failed to decrypt record name"))?,
+        amount: Money::from_cents(row.get(2).map_err(invalid)?),
+        currency: row.get(16).map_err(invalid)?,
+        category_id: row.get(3).map_err(invalid)?,
+        date: row.get(4).map_err(invalid)?,
+        seq: row.get(5).map_err(invalid)?,
+        created_via: row.get(6).map_err(invalid)?,
+        pending: row.get(7).map_err(invalid)?,
+        settle: row.get(8).map_err(invalid)?,
+        split_id: row.get(9).map_err(invalid)?,
+        debtor_user_id: row.get(10).map_err(invalid)?,
+        creditor_user_id: row.get(11).map_err(invalid)?,
+        original_amount: row.get(12).map_err(invalid)?,
+        original_currency: row.get(13).map_err(invalid)?,
+        note: row
+            .get::<Option<String>>(14)
+            .map_err(invalid)?
+            .map(crypto::open_field)
+            .transpose()
+            .map_err(|_| db_error_with_context("failed to decrypt record note"))?,
+        tags: record_repo::split_tags(row.get(15).map_err(invalid)?),
+    })
+}
+
+async fn load_export_records(
+    conn: &libsql::Connection,
+    user_id: &str,
+) -> Result<Vec<ExportRecord>, (StatusCode, String)> {
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT {EXPORT_RECORD_COLUMNS} \
+                 FROM records WHERE owner_user_id = ? AND deleted_at IS NULL ORDER BY date ASC, seq ASC"
+            ),
+            [user_id],
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query records"))?;
+
+    let mut records = Vec::new();
+    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        records.push(export_record_from_row(&row)?);
+    }
+    Ok(records)
+}
+
+/// Up to `limit` live records after `after` (`(date, id)` of the previous page's last record).
+async fn load_export_records_page(
+    conn: &libsql::Connection,
+    user_id: &str,
+    after: Option<(&str, &str)>,
+    limit: u32,
+) -> Result<Vec<ExportRecord>, (StatusCode, String)> {
+    let (after_date, after_id) = after.unzip();
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT {EXPORT_RECORD_COLUMNS} FROM records \
+                 WHERE owner_user_id = ? AND deleted_at IS NULL \
+                 AND (? IS NULL OR date > ? OR (date = ? AND id > ?)) \
+                 ORDER BY date ASC, id ASC LIMIT ?"
+            ),
+            (user_id, after_date, after_date, after_date, after_id, limit),
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query records"))?;
+
+    let mut records = Vec::new();
+    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        records.push(export_record_from_row(&row)?);
+    }
+    Ok(records)
+}
+
+async fn load_export_category_totals(
+    conn: &libsql::Connection,
+    user_id: &str,
+) -> Result<Vec<ExportCategoryTotal>, (StatusCode, String)> {
+    let mut rows = conn
+        .query(
+            "SELECT substr(r.date, 1, 7) AS month, r.category_id, c.name, SUM(r.amount), COUNT(*) \
+             FROM records r \
+             LEFT JOIN categories c ON c.id = r.category_id AND c.owner_user_id = r.owner_user_id \
+             WHERE r.owner_user_id = ? AND r.deleted_at IS NULL \
+             GROUP BY month, r.category_id \
+             ORDER BY month ASC, c.name ASC",
+            [user_id],
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query category totals"))?;
+
+    let mut totals = Vec::new();
+    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        let invalid = |_| db_error_with_context("invalid category total data");
+        totals.push(ExportCategoryTotal {
+            month: row.get(0).map_err(invalid)?,
+            category_id: row.get(1).map_err(invalid)?,
+            category_name: row.get(2).map_err(invalid)?,
+            total: Money::from_cents(row.get(3).map_err(invalid)?),
+            record_count: row.get(4).map_err(invalid)?,
+        });
+    }
+    Ok(totals)
+}
+
+async fn load_export_friends(
+    conn: &libsql::Connection,
+    user_id: &str,
+) -> Result<Vec<ExportFriend>, (StatusCode, String)> {
+    let mut rows = conn
+        .query(
+            "SELECT f.to_user_id, u.name, f.nickname, f.pending FROM friendship f \
+             JOIN users u ON u.id = f.to_user_id \
+             WHERE f.from_user_id = ? AND f.status = ? ORDER BY u.name ASC",
+            (user_id, FRIENDSHIP_STATUS_ACTIVE),
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query friends"))?;
+
+    let mut friends = Vec::new();
+    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        let invalid = |_| db_error_with_context("invalid friend data");
+        friends.push(ExportFriend {
+            user_id: row.get(0).map_err(invalid)?,
+            username: row.get(1).map_err(invalid)?,
+            nickname: row.get(2).map_err(invalid)?,
+            pending: row.get(3).map_err(invalid)?,
+        });
+    }
+    Ok(friends)
+}
+
+/// Every other user referenced by the export: friends plus split counterparties
+/// who may no longer be friends.
+async fn load_redaction_identities(
+    conn: &libsql::Connection,
+    user_id: &str,
+    friends: &[ExportFriend],
+) -> Result<Vec<RedactionIdentity>, (StatusCode, String)> {
+    let mut identities: Vec<RedactionIdentity> = friends
+        .iter()
+        .map(|friend| RedactionIdentity {
+            user_id: friend.user_id.clone(),
+            aliases: std::iter::once(friend.username.clone())
+                .chain(friend.nickname.clone())
+                .collect(),
+        })
+        .collect();
+
+    let mut rows = conn
+        .query(
+            "SELECT debtor_user_id FROM records WHERE owner_user_id = ? AND debtor_user_id IS NOT NULL \
+             UNION SELECT creditor_user_id FROM records WHERE owner_user_id = ? AND creditor_user_id IS NOT NULL",
+            (user_id, user_id),
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query split counterparties"))?;
+
+    let mut counterparties = BTreeSet::new();
+    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        let counterparty: String = row
+            .get(0)
+            .map_err(|_| db_error_with_context("invalid split counterparty"))?;
+        if counterparty != user_id
+            && !identities
+                .iter()
+                .any(|identity| identity.user_id == counterparty)
+        {
+            counterparties.insert(counterparty);
+        }
+    }
+    drop(rows);
+
+    for counterparty in counterparties {
+        let mut name_rows = conn
+            .query(
+                "SELECT name FROM users WHERE id = ?",
+                [counterparty.as_str()],
+            )
+            .await
+            .map_err(|_| db_error_with_context("failed to query split counterparty"))?;
+        let aliases = match name_rows.next().await.map_err(|_| db_error())? {
+            Some(row) => vec![
+                row.get(0)
+                    .map_err(|_| db_error_with_context("invalid split counterparty"))?,
+            ],
+            None => Vec::new(),
+        };
+        identities.push(RedactionIdentity {
+            user_id: counterparty,
+            aliases,
+        });
+    }
+
+    Ok(identities)
+}
+
+/// Builds the export archive for `user`, shared by `GET /export` and the operator CLI.
+pub async fn build_export(
+    conn: &libsql::Connection,
+    user: PublicUser,
+    options: &ExportOptions,
+) -> Result<Value, (StatusCode, String)> {
+    let exported_at = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let categories = load_export_categories(conn, &user.id).await?;
+    let (records, category_totals) = if options.categories_only {
+        (
+            None,
+            Some(load_export_category_totals(conn, &user.id).await?),
+        )
+    } else {
+        (Some(load_export_records(conn, &user.id).await?), None)
+    };
+    let friends = load_export_friends(conn, &user.id).await?;
+    let identities = if options.redact_friends {
+        load_redaction_identities(conn, &user.id, &friends).await?
+    } else {
+        Vec::new()
+    };
+
+    let archive = ExportArchive {
+        schema_version: options.schema_version(),
+        exported_at,
+        user,
+        categories,
+        records,
+        category_totals,
+        friends,
+    };
+
+    let mut archive = serde_json::to_value(archive)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    redact_archive(&mut archive, options, &identities);
+    Ok(archive)
+}
+
+pub async fn export_data(
+    State(app_state): State<AppState>,
+    session: Session,
+    Query(query): Query<ExportQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, String)> {
+    let user = get_current_user(&session).await?;
+    let options = ExportOptions::from_query(&query)?;
+
+    let conn = app_state.main_db.read().await;
+    let archive = build_export(&conn, user, &options).await?;
+    Ok((StatusCode::OK, Json(archive)))
+}
+
+async fn load_account_profile(
+    conn: &libsql::Connection,
+    user_id: &str,
+) -> Result<Option<AccountProfile>, (StatusCode, String)> {
+    let mut rows = conn
+        .query(
+            "SELECT id, name, username_changed_at, disabled_at, last_login_at, previous_login_at \
+             FROM users WHERE id = ?",
+            [user_id],
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query user"))?;
+    let Some(row) = rows.next().await.map_err(|_| db_error())? else {
+        return Ok(None);
+    };
+    let invalid = |_| db_error_with_context("invalid user data");
+    Ok(Some(AccountProfile {
+        id: row.get(0).map_err(invalid)?,
+        username: row.get(1).map_err(invalid)?,
+        username_changed_at: row.get(2).map_err(invalid)?,
+        disabled_at: row.get(3).map_err(invalid)?,
+        last_login_at: row.get(4).map_err(invalid)?,
+        previous_login_at: row.get(5).map_err(invalid)?,
+    }))
+}
+
+/// Every friendship row of the user, whatever its status, as they see it.
+async fn load_account_friendships(
+    conn: &libsql::Connection,
+    user_id: &str,
+) -> Result<Vec<AccountFriendship>, (StatusCode, String)> {
+    let mut rows = conn
+        .query(
+            "SELECT f.to_user_id, COALESCE(u.name, ''), f.nickname, f.status, f.pending, \
+             f.requester_user_id = f.from_user_id, f.created_at, f.accepted_at \
+             FROM friendship f LEFT JOIN users u ON u.id = f.to_user_id \
+             WHERE f.from_user_id = ? ORDER BY u.name ASC",
+            [user_id],
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query friendships"))?;
+
+    let mut friendships = Vec::new();
+    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        let invalid = |_| db_error_with_context("invalid friendship data");
+        friendships.push(AccountFriendship {
+            user_id: row.get(0).map_err(invalid)?,
+            username: row.get(1).map_err(invalid)?,
+            nickname: row.get(2).map_err(invalid)?,
+            status: row.get(3).map_err(invalid)?,
+            pending: row.get(4).map_err(invalid)?,
+            requested_by_me: row.get(5).map_err(invalid)?,
+            created_at: row.get(6).map_err(invalid)?,
+            accepted_at: row.get(7).map_err(invalid)?,
+        });
+    }
+    Ok(friendships)
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T) -> Result<String, (StatusCode, String)> {
+    serde_json::to_string(value).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// The account archive up to and including the opening bracket of `records`: schema
+/// version, profile, categories, friendships and the splits the user initiated.
+async fn account_archive_head(
+    conn: &libsql::Connection,
+    user_id: &str,
+    exported_at: &str,
+) -> Result<String, (StatusCode, String)> {
+    let profile = load_account_profile(conn, user_id)
+        .await?
+        .ok_or((StatusCode::UNAUTHORIZED, "Not logged in".to_string()))?;
+    let categories = load_export_categories(conn, user_id).await?;
+    let friendships = load_account_friendships(conn, user_id).await?;
+    let split_ids = split_repo::list_initiated_split_ids(conn, user_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to query splits"))?;
+    let splits = load_splits(conn, &split_ids).await?;
+
+    Ok(format!(
+        "{{\"schema_version\":{},\"exported_at\":{},\"profile\":{},\"categories\":{},\"friendships\":{},\"splits\":{},\"records\":[",
+        ACCOUNT_ARCHIVE_SCHEMA_VERSION,
+        to_json(exported_at)?,
+        to_json(&profile)?,
+        to_json(&categories)?,
+        to_json(&friendships)?,
+        to_json(&splits)?,
+    ))
+}
+
+/// Streams `head`, then the user's records one page per chunk, then the closing brackets.
+/// Stops early when the client disconnects; a database error aborts the body.
+async fn write_account_archive(
+    db: Db,
+    user_id: String,
+    head: String,
+    tx: mpsc::Sender<Result<String, std::io::Error>>,
+) {
+    let mut chunk = head;
+    let mut after: Option<(String, String)> = None;
+    loop {
+        let page = {
+            let conn = db.read().await;
+            load_export_records_page(
+                &conn,
+                &user_id,
+                after
+                    .as_ref()
+                    .map(|(date, id)| (date.as_str(), id.as_str())),
+                ACCOUNT_ARCHIVE_PAGE_SIZE,
+            )
+            .await
+        };
+        let page = match page {
+            Ok(page) => page,
+            Err((_, message)) => {
+                let _ = tx.send(Err(std::io::Error::other(message))).await;
+                return;
+            }
+        };
+
+        for (index, record) in page.iter().enumerate() {
+            if after.is_some() || index > 0 {
+                chunk.push(',');
+            }
+            match to_json(record) {
+                Ok(json) => chunk.push_str(&json),
+                Err((_, message)) => {
+                    let _ = tx.send(Err(std::io::Error::other(message))).await;
+                    return;
+                }
+            }
+        }
+        let last_page = page.len() < ACCOUNT_ARCHIVE_PAGE_SIZE as usize;
+        if last_page {
+            chunk.push_str("]}");
+        }
+        if tx.send(Ok(std::mem::take(&mut chunk))).await.is_err() || last_page {
+            return;
+        }
+        after = page.last().map(|last| (last.date.clone(), last.id.clone()));
+    }
+}
+
+/// `GET /auth/export`: everything about the caller as one JSON download. The head is
+/// read up front so errors still get a status; the records follow page by page from a
+/// background task, like `GET /records/export`.
+pub async fn export_account(
+    State(app_state): State<AppState>,
+    session: Session,
+) -> Result<Response, ApiError> {
+    let user = get_current_user(&session).await?;
+    let now = OffsetDateTime::now_utc();
+    let exported_at = now
+        .format(&time::format_description::well_known::Rfc3339)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let head = {
+        let conn = app_state.main_db.read().await;
+        account_archive_head(&conn, &user.id, &exported_at).await?
+    };
+
+    let (tx, rx) = mpsc::channel(2);
+    tokio::spawn(write_account_archive(
+        app_state.main_db.clone(),
+        user.id,
+        head,
+        tx,
+    ));
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"kash-export-{}.json\"", now.date()),
+            ),
+        ],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response())
+}
+
+/// Restores the categories and records of an archive into `user_id`, which must not have
+/// any records yet. Categories match existing ones by name, ignoring ASCII case; the rest
+/// are created. Everything gets new ids, so an archive can be imported next to the
+/// account it came from. Pending split shares and uncategorized records are skipped, and
+/// imported records are plain records: split and settle-up links are not restored.
+pub async fn import_account_for_user(
+    db: &Db,
+    user_id: &str,
+    mut archive: AccountArchive,
+) -> Result<ImportAccountResponse, (StatusCode, String)> {
+    if archive.schema_version != ACCOUNT_ARCHIVE_SCHEMA_VERSION {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Unsupported schema_version {}; expected {}",
+                archive.schema_version, ACCOUNT_ARCHIVE_SCHEMA_VERSION
+            ),
+        ));
+    }
+    for category in &archive.categories {
+        validate_category_name(&category.name)?;
+    }
+    for record in &mut archive.records {
+        validate_date(&record.date)?;
+        if let Some(currency) = record.currency.as_mut() {
+            *currency = normalize_record_currency(currency)?;
+        }
+    }
+    let default_currency = {
+        let conn = db.read().await;
+        user_currency_code(&conn, user_id).await?
+    };
+
+    let owner_user_id = user_id.to_string();
+    let response = with_transaction(db, |conn| {
+        Box::pin(async move {
+            let mut rows = conn
+                .query(
+                    "SELECT 1 FROM records WHERE owner_user_id = ? LIMIT 1",
+                    [owner_user_id.as_str()],
+                )
+                .await?;
+            if rows.next().await?.is_some() {
+                return Err(ImportAccountError::NotEmpty);
+            }
+            drop(rows);
+
+            let mut existing = HashMap::new();
+            let mut rows = conn
+                .query(
+                    "SELECT id, name FROM categories WHERE owner_user_id = ?",
+                    [owner_user_id.as_str()],
+                )
+                .await?;
+            while let Some(row) = rows.next().await? {
+                existing.insert(row.get::<String>(1)?.to_ascii_lowercase(), row.get::<String>(0)?);
+            }
+            drop(rows);
+
+            let mut category_ids: HashMap<&str, String> = HashMap::new();
+            let mut categories_imported = 0;
+            for category in &archive.categories {
+                let id = match existing.get(&category.name.to_ascii_lowercase()) {
+                    Some(id) => id.clone(),
+                    None => {
+                        let id = Uuid::new_v4().to_string();
+                        conn.execute(
+                            "INSERT INTO categories (id, owner_user_id, name, is_income, monthly_budget, archived) VALUES (?, ?, ?, ?, ?, ?)",
+                            libsql::params![
+                                id.as_str(),
+                                owner_user_id.as_str(),
+                                category.name.as_str(),
+                                category.is_income,
+                                category.monthly_budget,
+                                category.archived
+                            ],
+                        )
+                        .await?;
+                        existing.insert(category.name.to_ascii_lowercase(), id.clone());
+                        categories_imported += 1;
+                        id
+                    }
+                };
+                category_ids.insert(category.id.as_str(), id);
+            }
+            for category in &archive.categories {
+                if let Some(parent_id) = category
+                    .parent_id
+                    .as_deref()
+                    .and_then(|parent_id| category_ids.get(parent_id))
+                {
+                    conn.execute(
+                        "UPDATE categories SET parent_id = ? WHERE id = ? AND owner_user_id = ?",
+                        (
+                            parent_id.as_str(),
+                            category_ids[category.id.as_str()].as_str(),
+                            owner_user_id.as_str(),
+                        ),
+                    )
+                    .await?;
+                }
+            }
+
+            let (mut records_imported, mut records_skipped) = (0, 0);
+            for record in &archive.records {
+                let category_id = record
+                    .category_id
+                    .as_deref()
+                    .and_then(|category_id| category_ids.get(category_id));
+                let Some(category_id) = category_id.filter(|_| !record.pending) else {
+                    records_skipped += 1;
+                    continue;
+                };
+                record_repo::insert_record(
+                    conn,
+                    &NewRecord {
+                        id: &Uuid::new_v4().to_string(),
+                        owner_user_id: &owner_user_id,
+                        name: &record.name,
+                        amount: record.amount,
+                        currency: record.currency.as_deref().unwrap_or(&default_currency),
+                        category_id,
+                        date: &record.date,
+                        created_via: CREATED_VIA_IMPORT,
+                        original_amount: record.original_amount,
+                        original_currency: record.original_currency.as_deref(),
+                        note: record.note.as_deref(),
+                        tags: &record.tags,
+                    },
+                )
+                .await?;
+                records_imported += 1;
+            }
+
+            Ok::<_, ImportAccountError>(ImportAccountResponse {
+                categories_imported,
+                records_imported,
+                records_skipped,
+            })
+        })
+    })
+    .await?;
+
+    Ok(response)
+}
+
+/// `POST /auth/import`: a `GET /auth/export` archive, into an account without records.
+pub async fn import_account(
+    State(app_state): State<AppState>,
+    session: Session,
+    Json(archive): Json<AccountArchive>,
+) -> Result<(StatusCode, Json<ImportAccountResponse>), ApiError> {
+    let user = get_current_user(&session).await?;
+    let response = import_account_for_user(&app_state.main_db, &user.id, archive).await?;
+    Ok((StatusCode::CREATED, Json(response)))
+}
@@ -1,133 +1,247 @@
-use axum::extract::Query;
+use axum::extract::{Path, Query};
 use axum::{Json, extract::State, http::StatusCode};
 use serde::Deserialize;
 use serde_json::json;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
 use tower_sessions::Session;
 use uuid::Uuid;
 
-use crate::AppState;
 use crate::auth::{get_current_user, get_user_by_username_public};
 use crate::constants::*;
+use crate::error::ApiError;
+use crate::friendship_repo::{self, FriendDirection, FriendListKind, RelationTimeline};
+use crate::maintenance::status_timestamp;
 use crate::models::{
-    AcceptFriendPayload, FriendshipRelation, PublicUser, RemoveFriendPayload,
-    SendFriendRequestPayload, UpdateNicknamePayload,
+    AcceptFriendPayload, ActivityQuery, CancelFriendPayload, DeclineFriendPayload,
+    FriendActivityItem, FriendActivityResponse, FriendBalancesResponse, FriendProfile,
+    FriendshipRelation, PublicUser, RecentSharedSplit, RemoveFriendPayload,
+    SendFriendRequestPayload, SharedCurrencyTotals, SharedSplitHistory, UpdateNicknamePayload,
+    UserSearchResult,
 };
+use crate::money::Money;
+use crate::notifications;
+use crate::split_repo::{self, PairShareRow};
+use crate::utils::{
+    database_busy, db_error_with_context, precise_timestamp, validate_offset,
+    validate_records_limit,
+};
+use crate::{AppState, Db, TransactionError, with_transaction};
+
+enum FriendshipWriteError {
+    Transaction(TransactionError),
+    Db(libsql::Error),
+    Exists,
+    Blocked,
+}
+
+impl From<TransactionError> for FriendshipWriteError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+impl From<libsql::Error> for FriendshipWriteError {
+    fn from(value: libsql::Error) -> Self {
+        Self::Db(value)
+    }
+}
+
+impl From<FriendshipWriteError> for ApiError {
+    fn from(value: FriendshipWriteError) -> Self {
+        match value {
+            FriendshipWriteError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction").into()
+            }
+            FriendshipWriteError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction").into()
+            }
+            FriendshipWriteError::Transaction(TransactionError::Busy) => database_busy().into(),
+            FriendshipWriteError::Db(e) => internal_error(e),
+            FriendshipWriteError::Exists => ApiError::new(
+                StatusCode::CONFLICT,
+                ERROR_CODE_FRIEND_REQUEST_EXISTS,
+                "Friend request already exists",
+            ),
+            FriendshipWriteError::Blocked => ApiError::new(
+                StatusCode::FORBIDDEN,
+                ERROR_CODE_FRIEND_REQUEST_BLOCKED,
+                "Cannot send a friend request to this user",
+            ),
+        }
+    }
+}
+
+fn internal_error(e: libsql::Error) -> ApiError {
+    ApiError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ERROR_CODE_INTERNAL,
+        e.to_string(),
+    )
+}
+
+fn friend_request_not_found() -> ApiError {
+    ApiError::new(
+        StatusCode::NOT_FOUND,
+        ERROR_CODE_FRIEND_REQUEST_NOT_FOUND,
+        "Friend request not found",
+    )
+}
 
 pub async fn send_friend_request(
     State(app_state): State<AppState>,
     session: Session,
     Json(payload): Json<SendFriendRequestPayload>,
-) -> Result<(StatusCode, Json<FriendshipRelation>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<FriendshipRelation>), ApiError> {
     let current_user = get_current_user(&session).await?;
+    let relation = send_friend_request_for_user(
+        &app_state.main_db,
+        &current_user,
+        &payload.friend_username,
+        payload.message.as_deref(),
+    )
+    .await?;
 
-    if payload.friend_username.trim().is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Friend username cannot be empty".to_string(),
-        ));
+    Ok((StatusCode::CREATED, Json(relation)))
+}
+
+/// Trims a friend-request note and folds control characters and whitespace runs into
+/// single spaces. A blank note is dropped.
+fn sanitize_request_message(message: Option<&str>) -> Result<Option<String>, ApiError> {
+    let Some(message) = message else {
+        return Ok(None);
+    };
+    let sanitized = message
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if sanitized.is_empty() {
+        return Ok(None);
+    }
+    if sanitized.chars().count() > MAX_FRIEND_REQUEST_MESSAGE_LENGTH {
+        return Err(ApiError::bad_request(format!(
+            "Message cannot exceed {} characters",
+            MAX_FRIEND_REQUEST_MESSAGE_LENGTH
+        )));
     }
+    Ok(Some(sanitized))
+}
 
-    if payload.friend_username.len() > MAX_USERNAME_LENGTH {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            format!("Username cannot exceed {} characters", MAX_USERNAME_LENGTH),
-        ));
+/// Creates both pending directed rows for a request from `current_user` to `friend_username`.
+/// The optional `message` is only visible to the recipient.
+pub async fn send_friend_request_for_user(
+    db: &Db,
+    current_user: &PublicUser,
+    friend_username: &str,
+    message: Option<&str>,
+) -> Result<FriendshipRelation, ApiError> {
+    if friend_username.trim().is_empty() {
+        return Err(ApiError::bad_request("Friend username cannot be empty"));
     }
 
-    if payload.friend_username == current_user.username {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Cannot send friend request to yourself".to_string(),
-        ));
+    if friend_username.len() > MAX_USERNAME_LENGTH {
+        return Err(ApiError::bad_request(format!(
+            "Username cannot exceed {} characters",
+            MAX_USERNAME_LENGTH
+        )));
     }
 
-    let friend_user = get_user_by_username_public(&app_state.main_db, &payload.friend_username)
+    let message = sanitize_request_message(message)?;
+
+    let friend_user = get_user_by_username_public(db, friend_username)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        .ok_or_else(|| (StatusCode::NOT_FOUND, "User not found".to_string()))?;
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                ERROR_CODE_USER_NOT_FOUND,
+                "User not found",
+            )
+        })?;
+
+    // Compared by id: the session's copy of the name is stale after a rename elsewhere
+    if friend_user.id == current_user.id {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ERROR_CODE_FRIEND_REQUEST_TO_SELF,
+            "Cannot send friend request to yourself",
+        ));
+    }
 
     let a_to_b_id = Uuid::new_v4().to_string();
     let b_to_a_id = Uuid::new_v4().to_string();
 
-    let conn = app_state.main_db.write().await;
-
-    conn.execute("BEGIN TRANSACTION", ())
-        .await
-        .map_err(|e: libsql::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let tx_result: Result<(), String> = async {
-        let mut rows = conn
-            .query(
-                "SELECT COUNT(*) FROM friendship WHERE from_user_id = ? AND to_user_id = ?",
-                (current_user.id.as_str(), friend_user.id.as_str()),
-            )
-            .await
-            .map_err(|e| e.to_string())?;
-
-        if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-            let count: i64 = row.get(0).map_err(|e| e.to_string())?;
-            if count > 0 {
-                return Err("FRIENDSHIP_EXISTS".to_string());
+    with_transaction(db, |conn| {
+        let current_user_id = current_user.id.clone();
+        let friend_user_id = friend_user.id.clone();
+        let friend_username = friend_user.username.clone();
+        let a_to_b_id = a_to_b_id.clone();
+        let b_to_a_id = b_to_a_id.clone();
+        let message = message.clone();
+        Box::pin(async move {
+            // Checked first: the blocker's own blocked row would otherwise read as "exists"
+            if friendship_repo::is_blocked_pair(conn, &current_user_id, &friend_user_id).await? {
+                return Err(FriendshipWriteError::Blocked);
             }
-        }
-
-        conn.execute(
-            "INSERT INTO friendship (id, from_user_id, to_user_id, pending, nickname, requester_user_id) VALUES (?, ?, ?, ?, NULL, ?)",
-            (
-                a_to_b_id.as_str(),
-                current_user.id.as_str(),
-                friend_user.id.as_str(),
-                1i64,
-                current_user.id.as_str(),
-            ),
-        )
-        .await
-        .map_err(|e| e.to_string())?;
-
-        conn.execute(
-            "INSERT INTO friendship (id, from_user_id, to_user_id, pending, nickname, requester_user_id) VALUES (?, ?, ?, ?, NULL, ?)",
-            (
-                b_to_a_id.as_str(),
-                friend_user.id.as_str(),
-                current_user.id.as_str(),
-                1i64,
-                current_user.id.as_str(),
-            ),
-        )
-        .await
-        .map_err(|e| e.to_string())?;
-
-        Ok(())
-    }
-    .await;
-
-    match tx_result {
-        Ok(_) => {
-            conn.execute("COMMIT", ())
-                .await
-                .map_err(|e: libsql::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-        }
-        Err(e) => {
-            let _ = conn.execute("ROLLBACK", ()).await;
-            drop(conn);
-            if e == "FRIENDSHIP_EXISTS" {
-                return Err((
-                    StatusCode::CONFLICT,
-                    "Friend request already exists".to_string(),
-                ));
+            if friendship_repo::has_open_relation(conn, &current_user_id, &friend_user_id).await? {
+                return Err(FriendshipWriteError::Exists);
             }
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, e));
-        }
-    }
 
-    let relation = FriendshipRelation {
-        id: a_to_b_id,
-        user_id: friend_user.id.clone(),
-        pending: true,
-        nickname: friend_user.username.clone(),
-    };
+            // A pair that was unfriended or declined on both sides is reopened in place, so
+            // the nicknames they gave each other come back with the friendship
+            let reopened = friendship_repo::reopen_ended_pair(
+                conn,
+                &current_user_id,
+                &friend_user_id,
+                message.as_deref(),
+            )
+            .await?;
+            let relation = match reopened {
+                Some(relation) => relation,
+                None => {
+                    // A lone leftover row would collide with the new pair
+                    for status in [FRIENDSHIP_STATUS_UNFRIENDED, FRIENDSHIP_STATUS_DECLINED] {
+                        friendship_repo::delete_pair_with_status(
+                            conn,
+                            &current_user_id,
+                            &friend_user_id,
+                            status,
+                        )
+                        .await?;
+                    }
+                    friendship_repo::insert_pending_pair(
+                        conn,
+                        &current_user_id,
+                        &friend_user_id,
+                        (&a_to_b_id, &b_to_a_id),
+                        message.as_deref(),
+                    )
+                    .await?;
+                    FriendshipRelation {
+                        id: a_to_b_id,
+                        user_id: friend_user_id.clone(),
+                        username: friend_username.clone(),
+                        pending: true,
+                        nickname: friend_username,
+                        message: None,
+                        direction: Some(FRIEND_DIRECTION_OUTGOING.to_string()),
+                    }
+                }
+            };
+            notifications::notify(
+                conn,
+                &friend_user_id,
+                NOTIFICATION_KIND_FRIEND_REQUEST_RECEIVED,
+                json!({ "from_user_id": current_user_id }),
+            )
+            .await?;
 
-    Ok((StatusCode::CREATED, Json(relation)))
+            Ok(relation)
+        })
+    })
+    .await
+    .map_err(|e: FriendshipWriteError| -> ApiError { e.into() })
 }
 
 #[derive(Deserialize)]
@@ -141,62 +255,38 @@ pub async fn search_users(
     State(app_state): State<AppState>,
     session: Session,
     Query(params): Query<SearchUsersQuery>,
-) -> Result<(StatusCode, Json<Vec<PublicUser>>), (StatusCode, String)> {
-    let _current_user = get_current_user(&session).await?;
+) -> Result<(StatusCode, Json<Vec<UserSearchResult>>), ApiError> {
+    let current_user = get_current_user(&session).await?;
 
     if params.query.trim().is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "Query cannot be empty".to_string()));
+        return Err(ApiError::bad_request("Query cannot be empty"));
     }
 
     if params.query.len() < 3 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Query must be at least 3 characters long".to_string(),
+        return Err(ApiError::bad_request(
+            "Query must be at least 3 characters long",
         ));
     }
 
     if params.query.len() > MAX_SEARCH_TERM_LENGTH {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            format!("Query cannot exceed {} characters", MAX_SEARCH_TERM_LENGTH),
-        ));
+        return Err(ApiError::bad_request(format!(
+            "Query cannot exceed {} characters",
+            MAX_SEARCH_TERM_LENGTH
+        )));
     }
 
     let limit = params.limit.unwrap_or(20).min(MAX_LIMIT);
     let offset = params.offset.unwrap_or(0).min(MAX_OFFSET);
 
     if limit == 0 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Limit must be at least 1".to_string(),
-        ));
+        return Err(ApiError::bad_request("Limit must be at least 1"));
     }
 
-    let search_pattern = format!("{}%", params.query);
-
     let conn = app_state.main_db.read().await;
-    let mut rows = conn
-        .query(
-            "SELECT id, name FROM users WHERE name LIKE ? LIMIT ? OFFSET ?",
-            (search_pattern.as_str(), limit, offset),
-        )
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let mut users = Vec::new();
-    while let Some(row) = rows
-        .next()
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-    {
-        let id: String = row
-            .get(0)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-        let username: String = row
-            .get(1)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-        users.push(PublicUser { id, username });
-    }
+    let users =
+        friendship_repo::search_users(&conn, &current_user.id, &params.query, limit, offset)
+            .await
+            .map_err(internal_error)?;
 
     Ok((StatusCode::OK, Json(users)))
 }
@@ -205,108 +295,70 @@ pub async fn update_nickname(
     State(app_state): State<AppState>,
     session: Session,
     Json(payload): Json<UpdateNicknamePayload>,
-) -> Result<(StatusCode, Json<FriendshipRelation>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<FriendshipRelation>), ApiError> {
     let current_user = get_current_user(&session).await?;
     let user_id = &current_user.id;
 
     if let Some(ref nickname) = payload.nickname {
         if nickname.is_empty() {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                "Nickname cannot be empty string (use null to remove)".to_string(),
+            return Err(ApiError::bad_request(
+                "Nickname cannot be empty string (use null to remove)",
             ));
         }
 
         if nickname.len() > MAX_NICKNAME_LENGTH {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                format!("Nickname cannot exceed {} characters", MAX_NICKNAME_LENGTH),
-            ));
+            return Err(ApiError::bad_request(format!(
+                "Nickname cannot exceed {} characters",
+                MAX_NICKNAME_LENGTH
+            )));
         }
     }
 
-    let conn = app_state.main_db.read().await;
-    let mut rows = conn
-        .query(
-            "SELECT id, to_user_id as user_id, pending, nickname FROM friendship WHERE from_user_id = ? AND to_user_id = ?",
-            (user_id.as_str(), payload.friend_id.as_str()),
-        )
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let conn = app_state.main_db.write().await;
 
-    if rows
-        .next()
+    if friendship_repo::find_active_relation(&conn, user_id, &payload.friend_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(internal_error)?
         .is_none()
     {
-        return Err((
+        return Err(ApiError::new(
             StatusCode::NOT_FOUND,
-            "Friendship relation not found".to_string(),
+            ERROR_CODE_FRIEND_NOT_FOUND,
+            "Friendship relation not found",
         ));
     }
 
-    drop(rows);
-    drop(conn);
-
-    let conn = app_state.main_db.write().await;
-
-    conn.execute(
-        "UPDATE friendship SET nickname = ? WHERE from_user_id = ? AND to_user_id = ?",
-        (
-            payload.nickname.as_deref(),
-            user_id.as_str(),
-            payload.friend_id.as_str(),
-        ),
+    friendship_repo::set_nickname(
+        &conn,
+        user_id,
+        &payload.friend_id,
+        payload.nickname.as_deref(),
     )
     .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let mut rows = conn
-        .query(
-            "SELECT f.id, f.to_user_id as user_id, f.pending, COALESCE(f.nickname, u.name) as nickname FROM friendship f JOIN users u ON u.id = f.to_user_id WHERE f.from_user_id = ? AND f.to_user_id = ?",
-            (user_id.as_str(), payload.friend_id.as_str()),
-        )
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    .map_err(internal_error)?;
 
-    if let Some(row) = rows
-        .next()
+    let relation = friendship_repo::find_relation(&conn, user_id, &payload.friend_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-    {
-        let id: String = row
-            .get(0)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-        let user_id_field: String = row
-            .get(1)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-        let pending_val: i64 = row
-            .get(2)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-        let nickname: String = row
-            .get(3)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-        let relation = FriendshipRelation {
-            id,
-            user_id: user_id_field,
-            pending: pending_val != 0,
-            nickname,
-        };
-
-        return Ok((StatusCode::OK, Json(relation)));
-    }
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to retrieve updated relation".to_string(),
+            )
+        })?;
 
-    Err((
-        StatusCode::INTERNAL_SERVER_ERROR,
-        "Failed to retrieve updated relation".to_string(),
-    ))
+    Ok((StatusCode::OK, Json(relation)))
 }
 
 #[derive(Deserialize)]
 pub struct ListFriendsQuery {
     pub pending: Option<bool>,
+    /// `declined` lists requests the user declined; `pending` is ignored then.
+    pub status: Option<String>,
+    /// `incoming` or `outgoing`; both when omitted.
+    pub direction: Option<String>,
+    /// Keeps friends whose nickname or username contains it, ignoring case.
+    pub q: Option<String>,
     pub limit: Option<u32>,
     pub offset: Option<u32>,
 }
@@ -315,7 +367,7 @@ pub async fn list_friends(
     State(app_state): State<AppState>,
     session: Session,
     Query(query): Query<ListFriendsQuery>,
-) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
     let current_user = get_current_user(&session).await?;
     let user_id = &current_user.id;
 
@@ -324,91 +376,48 @@ pub async fn list_friends(
 
     let conn = app_state.main_db.read().await;
 
-    // pending=true  → incoming only (requester_user_id != current user)
+    // status=declined → incoming requests the user declined
+    // pending=true  → requests waiting on the recipient, sent or received
     // pending=false or omitted → accepted friends (pending = 0)
-    let show_pending_incoming = query.pending.unwrap_or(false);
-
-    let total_count: i64 = if show_pending_incoming {
-        let mut rows = conn
-            .query(
-                "SELECT COUNT(*) FROM friendship WHERE from_user_id = ? AND pending = 1 AND requester_user_id != ?",
-                (user_id.as_str(), user_id.as_str()),
-            )
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-        if let Some(row) = rows
-            .next()
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        {
-            row.get(0)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        } else {
-            0
+    let kind = match query.status.as_deref() {
+        Some(FRIENDSHIP_STATUS_DECLINED) => FriendListKind::Declined,
+        Some(_) => {
+            return Err(ApiError::bad_request(format!(
+                "status must be {}",
+                FRIENDSHIP_STATUS_DECLINED
+            )));
         }
-    } else {
-        let mut rows = conn
-            .query(
-                "SELECT COUNT(*) FROM friendship WHERE from_user_id = ? AND pending = 0",
-                [user_id.as_str()],
-            )
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-        if let Some(row) = rows
-            .next()
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        {
-            row.get(0)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        } else {
-            0
+        None if query.pending.unwrap_or(false) => FriendListKind::Pending,
+        None => FriendListKind::Accepted,
+    };
+    // direction=incoming → the friend sent the request; outgoing → the user did
+    let direction = match query.direction.as_deref() {
+        None => None,
+        Some(FRIEND_DIRECTION_INCOMING) => Some(FriendDirection::Incoming),
+        Some(FRIEND_DIRECTION_OUTGOING) => Some(FriendDirection::Outgoing),
+        Some(_) => {
+            return Err(ApiError::bad_request(format!(
+                "direction must be {} or {}",
+                FRIEND_DIRECTION_INCOMING, FRIEND_DIRECTION_OUTGOING
+            )));
         }
     };
 
-    let mut rows = if show_pending_incoming {
-        conn.query(
-            "SELECT f.id, f.to_user_id as user_id, f.pending, COALESCE(f.nickname, u.name) as nickname FROM friendship f JOIN users u ON u.id = f.to_user_id WHERE f.from_user_id = ? AND f.pending = 1 AND f.requester_user_id != ? ORDER BY nickname LIMIT ? OFFSET ?",
-            (user_id.as_str(), user_id.as_str(), limit, offset),
-        )
-        .await
-    } else {
-        conn.query(
-            "SELECT f.id, f.to_user_id as user_id, f.pending, COALESCE(f.nickname, u.name) as nickname FROM friendship f JOIN users u ON u.id = f.to_user_id WHERE f.from_user_id = ? AND f.pending = 0 ORDER BY nickname LIMIT ? OFFSET ?",
-            (user_id.as_str(), limit, offset),
-        )
-        .await
+    let search = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+    if search.is_some_and(|q| q.len() > MAX_SEARCH_TERM_LENGTH) {
+        return Err(ApiError::bad_request(format!(
+            "q cannot exceed {} characters",
+            MAX_SEARCH_TERM_LENGTH
+        )));
     }
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    let mut friends = Vec::new();
-    while let Some(row) = rows
-        .next()
+    let total_count = friendship_repo::count_friends(&conn, user_id, kind, direction, search)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-    {
-        let id: String = row
-            .get(0)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-        let user_id_field: String = row
-            .get(1)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-        let pending_val: i64 = row
-            .get(2)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-        let nickname: String = row
-            .get(3)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-        friends.push(FriendshipRelation {
-            id,
-            user_id: user_id_field,
-            pending: pending_val != 0,
-            nickname,
-        });
-    }
+        .map_err(internal_error)?;
+    let friends =
+        friendship_repo::list_friends(&conn, user_id, kind, direction, search, limit, offset)
+            .await
+            .map_err(internal_error)?;
 
     Ok((
         StatusCode::OK,
@@ -425,186 +434,548 @@ pub async fn accept_friend(
     State(app_state): State<AppState>,
     session: Session,
     Json(payload): Json<AcceptFriendPayload>,
-) -> Result<(StatusCode, Json<FriendshipRelation>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<FriendshipRelation>), ApiError> {
     let current_user = get_current_user(&session).await?;
-    let user_id = &current_user.id;
+    let relation =
+        accept_friend_for_user(&app_state.main_db, &current_user.id, &payload.friend_id).await?;
 
-    let conn = app_state.main_db.read().await;
+    Ok((StatusCode::OK, Json(relation)))
+}
 
-    let mut rows = conn
-        .query(
-            "SELECT f.id, f.from_user_id, f.to_user_id, f.pending, COALESCE(f.nickname, u.name) as nickname, f.requester_user_id FROM friendship f JOIN users u ON u.id = f.from_user_id WHERE f.from_user_id = ? AND f.to_user_id = ?",
-            (payload.friend_id.as_str(), user_id.as_str()),
-        )
-        .await
-        .map_err(|e: libsql::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+/// Accepts the pending request `friend_id` sent to `user_id`, activating both directed rows.
+pub async fn accept_friend_for_user(
+    db: &Db,
+    user_id: &str,
+    friend_id: &str,
+) -> Result<FriendshipRelation, ApiError> {
+    let request = {
+        let conn = db.read().await;
+        friendship_repo::find_incoming_request(&conn, friend_id, user_id)
+            .await
+            .map_err(internal_error)?
+    };
 
-    let row = rows
-        .next()
-        .await
-        .map_err(|e: libsql::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                "Friend request not found".to_string(),
+    let request = request.ok_or_else(friend_request_not_found)?;
+    if !request.relation.pending || user_id == request.requester_user_id {
+        return Err(friend_request_not_found());
+    }
+
+    with_transaction(db, |conn| {
+        let from_user_id = request.relation.user_id.clone();
+        let to_user_id = request.to_user_id.clone();
+        let requester_user_id = request.requester_user_id.clone();
+        let user_id = user_id.to_string();
+        Box::pin(async move {
+            friendship_repo::accept_pair(conn, &from_user_id, &to_user_id).await?;
+            notifications::notify(
+                conn,
+                &requester_user_id,
+                NOTIFICATION_KIND_FRIEND_ACCEPTED,
+                json!({ "friend_id": user_id }),
             )
-        })?;
+            .await?;
+            Ok(())
+        })
+    })
+    .await
+    .map_err(|e: FriendshipWriteError| -> ApiError { e.into() })?;
 
-    let relation_id: String = row
-        .get(0)
-        .map_err(|e: libsql::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    let from_user_id: String = row
-        .get(1)
-        .map_err(|e: libsql::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    let to_user_id: String = row
-        .get(2)
-        .map_err(|e: libsql::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    let pending_val: i64 = row
-        .get(3)
-        .map_err(|e: libsql::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    let nickname: String = row
-        .get(4)
-        .map_err(|e: libsql::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    let requester_user_id: String = row
-        .get(5)
-        .map_err(|e: libsql::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    drop(rows);
-    drop(conn);
-
-    if pending_val == 0 {
-        return Err((
-            StatusCode::NOT_FOUND,
-            "Friend request not found".to_string(),
-        ));
-    }
+    Ok(FriendshipRelation {
+        pending: false,
+        message: None,
+        ..request.relation
+    })
+}
 
-    if user_id == &requester_user_id {
-        return Err((
-            StatusCode::NOT_FOUND,
-            "Friend request not found".to_string(),
-        ));
+pub async fn decline_friend(
+    State(app_state): State<AppState>,
+    session: Session,
+    Json(payload): Json<DeclineFriendPayload>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    let current_user = get_current_user(&session).await?;
+    decline_friend_for_user(&app_state.main_db, &current_user.id, &payload.friend_id).await?;
+
+    Ok((StatusCode::OK, Json(json!({}))))
+}
+
+/// Declines the pending request `friend_id` sent to `user_id`: both directed rows move
+/// to `declined`. Like accepting, only the recipient may do this; anyone else gets 404.
+pub async fn decline_friend_for_user(
+    db: &Db,
+    user_id: &str,
+    friend_id: &str,
+) -> Result<(), ApiError> {
+    let request = {
+        let conn = db.read().await;
+        friendship_repo::find_incoming_request(&conn, friend_id, user_id)
+            .await
+            .map_err(internal_error)?
+    };
+
+    let request = request.ok_or_else(friend_request_not_found)?;
+    if !request.relation.pending || user_id == request.requester_user_id {
+        return Err(friend_request_not_found());
     }
 
-    let conn = app_state.main_db.write().await;
+    let status_changed_at = status_timestamp(OffsetDateTime::now_utc());
+    with_transaction(db, |conn| {
+        let from_user_id = request.relation.user_id.clone();
+        let to_user_id = request.to_user_id.clone();
+        Box::pin(async move {
+            friendship_repo::update_pair_status(
+                conn,
+                &from_user_id,
+                &to_user_id,
+                FRIENDSHIP_STATUS_DECLINED,
+                &status_changed_at,
+            )
+            .await?;
+            Ok(())
+        })
+    })
+    .await
+    .map_err(|e: FriendshipWriteError| -> ApiError { e.into() })?;
 
-    conn.execute("BEGIN TRANSACTION", ())
-        .await
-        .map_err(|e: libsql::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(())
+}
 
-    let tx_result: Result<(), libsql::Error> = async {
-        conn.execute(
-            "UPDATE friendship SET pending = 0 WHERE from_user_id = ? AND to_user_id = ?",
-            (from_user_id.as_str(), to_user_id.as_str()),
-        )
+pub async fn cancel_friend_request(
+    State(app_state): State<AppState>,
+    session: Session,
+    Json(payload): Json<CancelFriendPayload>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    let current_user = get_current_user(&session).await?;
+    cancel_friend_request_for_user(&app_state.main_db, &current_user.id, &payload.friend_id)
         .await?;
 
-        conn.execute(
-            "UPDATE friendship SET pending = 0 WHERE from_user_id = ? AND to_user_id = ?",
-            (to_user_id.as_str(), from_user_id.as_str()),
-        )
-        .await?;
+    Ok((StatusCode::OK, Json(json!({}))))
+}
 
-        Ok(())
-    }
-    .await;
+/// Withdraws the pending request `user_id` sent to `friend_id` by deleting both directed
+/// rows, so the pair can start over. Only the requester may do this; anyone else gets 404.
+pub async fn cancel_friend_request_for_user(
+    db: &Db,
+    user_id: &str,
+    friend_id: &str,
+) -> Result<(), ApiError> {
+    let request = {
+        let conn = db.read().await;
+        friendship_repo::find_incoming_request(&conn, friend_id, user_id)
+            .await
+            .map_err(internal_error)?
+    };
 
-    match tx_result {
-        Ok(_) => {
-            conn.execute("COMMIT", ())
-                .await
-                .map_err(|e: libsql::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-        }
-        Err(e) => {
-            let _ = conn.execute("ROLLBACK", ()).await;
-            drop(conn);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
-        }
+    let request = request.ok_or_else(friend_request_not_found)?;
+    if !request.relation.pending || user_id != request.requester_user_id {
+        return Err(friend_request_not_found());
     }
 
-    Ok((
-        StatusCode::OK,
-        Json(FriendshipRelation {
-            id: relation_id,
-            user_id: from_user_id,
-            pending: false,
-            nickname,
-        }),
-    ))
+    let deleted = with_transaction(db, |conn| {
+        let user_id = user_id.to_string();
+        let friend_id = friend_id.to_string();
+        Box::pin(async move {
+            // Accepted in the meantime: the pending filter leaves the friendship alone
+            Ok(friendship_repo::delete_pending_pair(conn, &user_id, &friend_id).await?)
+        })
+    })
+    .await
+    .map_err(|e: FriendshipWriteError| -> ApiError { e.into() })?;
+
+    if deleted == 0 {
+        return Err(friend_request_not_found());
+    }
+    Ok(())
 }
 
 pub async fn remove_friend(
     State(app_state): State<AppState>,
     session: Session,
     Json(payload): Json<RemoveFriendPayload>,
-) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
     let current_user = get_current_user(&session).await?;
 
-    let conn = app_state.main_db.read().await;
-    let mut rows = conn
-        .query(
-            "SELECT COUNT(*) FROM friendship WHERE (from_user_id = ? AND to_user_id = ?) OR (from_user_id = ? AND to_user_id = ?)",
-            (
-                current_user.id.as_str(),
-                payload.friend_id.as_str(),
-                payload.friend_id.as_str(),
-                current_user.id.as_str(),
-            ),
-        )
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let db = &app_state.main_db;
 
-    let count = if let Some(row) = rows
-        .next()
+    let count = {
+        let conn = db.read().await;
+        friendship_repo::count_pair_with_status(
+            &conn,
+            &current_user.id,
+            &payload.friend_id,
+            FRIENDSHIP_STATUS_ACTIVE,
+        )
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-    {
-        row.get(0)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-    } else {
-        0
+        .map_err(internal_error)?
     };
 
     if count == 0 {
-        return Err((StatusCode::NOT_FOUND, "Friendship not found".to_string()));
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            ERROR_CODE_FRIEND_NOT_FOUND,
+            "Friendship not found",
+        ));
     }
 
-    drop(rows);
-    drop(conn);
+    // Rows are kept as unfriended history until pruned by maintenance or purged by the user
+    let status_changed_at = status_timestamp(OffsetDateTime::now_utc());
+
+    with_transaction(db, |conn| {
+        let user_id = current_user.id.clone();
+        let friend_id = payload.friend_id.clone();
+        let status_changed_at = status_changed_at.clone();
+        Box::pin(async move {
+            friendship_repo::update_pair_status(
+                conn,
+                &user_id,
+                &friend_id,
+                FRIENDSHIP_STATUS_UNFRIENDED,
+                &status_changed_at,
+            )
+            .await?;
+            Ok(())
+        })
+    })
+    .await
+    .map_err(|e: FriendshipWriteError| -> ApiError { e.into() })?;
+
+    Ok((StatusCode::OK, Json(json!({}))))
+}
+
+pub async fn purge_friend_history(
+    State(app_state): State<AppState>,
+    session: Session,
+    Path(friend_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    let current_user = get_current_user(&session).await?;
 
     let conn = app_state.main_db.write().await;
-    conn.execute("BEGIN TRANSACTION", ())
+    let statuses = friendship_repo::pair_statuses(&conn, &current_user.id, &friend_id)
         .await
-        .map_err(|e: libsql::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(internal_error)?;
 
-    let tx_result: Result<(), libsql::Error> = async {
-        conn.execute(
-            "DELETE FROM friendship WHERE (from_user_id = ? AND to_user_id = ?) OR (from_user_id = ? AND to_user_id = ?)",
-            (
-                current_user.id.as_str(),
-                payload.friend_id.as_str(),
-                payload.friend_id.as_str(),
-                current_user.id.as_str(),
-            ),
-        )
-        .await?;
+    if statuses.is_empty() {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            ERROR_CODE_FRIEND_NOT_FOUND,
+            "Friendship history not found",
+        ));
+    }
 
-        Ok(())
+    if statuses
+        .iter()
+        .any(|status| status != FRIENDSHIP_STATUS_UNFRIENDED)
+    {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            ERROR_CODE_FRIENDSHIP_NOT_UNFRIENDED,
+            "Only unfriended relationships can be purged",
+        ));
     }
-    .await;
 
-    match tx_result {
-        Ok(_) => {
-            conn.execute("COMMIT", ())
-                .await
-                .map_err(|e: libsql::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-        }
-        Err(e) => {
-            let _ = conn.execute("ROLLBACK", ()).await;
-            drop(conn);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
-        }
+    friendship_repo::delete_pair_with_status(
+        &conn,
+        &current_user.id,
+        &friend_id,
+        FRIENDSHIP_STATUS_UNFRIENDED,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Friendship timestamps are stored at two precisions; the feed sorts them as `precise_timestamp`s.
+fn activity_timestamp(stored: &str) -> String {
+    OffsetDateTime::parse(stored, &Rfc3339)
+        .map(precise_timestamp)
+        .unwrap_or_else(|_| stored.to_string())
+}
+
+fn friendship_events(timeline: &RelationTimeline) -> Vec<FriendActivityItem> {
+    let status_event = (timeline.status != FRIENDSHIP_STATUS_ACTIVE)
+        .then_some(timeline.status.as_str())
+        .zip(timeline.status_changed_at.as_deref());
+    [
+        timeline
+            .created_at
+            .as_deref()
+            .map(|at| (FRIEND_ACTIVITY_REQUESTED, at)),
+        timeline
+            .accepted_at
+            .as_deref()
+            .map(|at| (FRIEND_ACTIVITY_ACCEPTED, at)),
+        status_event,
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(event, at)| FriendActivityItem::Friendship {
+        at: activity_timestamp(at),
+        event: event.to_string(),
+    })
+    .collect()
+}
+
+fn split_event(share: PairShareRow) -> FriendActivityItem {
+    FriendActivityItem::Split {
+        at: share.at,
+        split_id: share.split_id,
+        description: share.description,
+        amount: share.amount,
+        paid_by_user_id: share.creditor_user_id,
+        pending: share.pending,
     }
+}
 
-    Ok((StatusCode::OK, Json(json!({}))))
+fn settlement_event(share: PairShareRow) -> FriendActivityItem {
+    FriendActivityItem::Settlement {
+        at: share.at,
+        split_id: share.split_id,
+        description: share.description,
+        amount: share.amount,
+        paid_by_user_id: share.creditor_user_id,
+    }
+}
+
+/// Splits, settlements and friendship changes between `user_id` and `friend_id`, newest first.
+///
+/// No page can hold more than `offset + limit` entries of any one source, so each source
+/// is read newest first up to that many rows. The union is ordered by timestamp, ties by
+/// source and then id (the order each source query uses), and cut down to the page.
+pub async fn friend_activity_for_user(
+    db: &Db,
+    user_id: &str,
+    friend_id: &str,
+    limit: u32,
+    offset: u32,
+) -> Result<FriendActivityResponse, ApiError> {
+    let conn = db.read().await;
+    let timeline = friendship_repo::find_relation_timeline(&conn, user_id, friend_id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                ERROR_CODE_FRIEND_NOT_FOUND,
+                "Friend not found",
+            )
+        })?;
+
+    let window = offset.saturating_add(limit);
+    let shares = split_repo::list_pair_shares(&conn, user_id, friend_id, window)
+        .await
+        .map_err(|_| db_error_with_context("failed to query split activity"))?;
+    let settlements = split_repo::list_pair_settlements(&conn, user_id, friend_id, window)
+        .await
+        .map_err(|_| db_error_with_context("failed to query settlement activity"))?;
+    let share_count = split_repo::count_pair_shares(&conn, user_id, friend_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to count split activity"))?;
+    let settlement_count = split_repo::count_pair_settlements(&conn, user_id, friend_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to count settlement activity"))?;
+
+    let friendship = friendship_events(&timeline);
+    let total_count = u32::try_from(share_count + settlement_count + friendship.len() as i64)
+        .map_err(|_| db_error_with_context("friend activity count exceeds u32"))?;
+
+    // (source, id) breaks timestamp ties the same way within and across sources.
+    let mut entries: Vec<(u8, String, FriendActivityItem)> = Vec::new();
+    entries.extend(
+        shares
+            .into_iter()
+            .map(|share| (0, share.record_id.clone(), split_event(share))),
+    );
+    entries.extend(
+        settlements
+            .into_iter()
+            .map(|share| (1, share.record_id.clone(), settlement_event(share))),
+    );
+    entries.extend(
+        friendship
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| (2, index.to_string(), item)),
+    );
+    entries.sort_by(|(a_source, a_id, a), (b_source, b_id, b)| {
+        b.at()
+            .cmp(a.at())
+            .then(a_source.cmp(b_source))
+            .then(b_id.cmp(a_id))
+    });
+
+    Ok(FriendActivityResponse {
+        items: entries
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .map(|(_, _, item)| item)
+            .collect(),
+        total_count,
+        limit,
+        offset,
+    })
+}
+
+/// Every accepted friend with what each side owes over unsettled split shares.
+pub async fn friend_balances_for_user(
+    db: &Db,
+    user_id: &str,
+) -> Result<FriendBalancesResponse, ApiError> {
+    let conn = db.read().await;
+    let balances = friendship_repo::list_friend_balances(&conn, user_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to query friend balances"))?;
+
+    Ok(FriendBalancesResponse { balances })
+}
+
+/// The caller's accepted or pending friendship with `friend_id`; the shared split history
+/// only once accepted. 404 for anyone else, a block in either direction included.
+pub async fn friend_profile_for_user(
+    db: &Db,
+    user_id: &str,
+    friend_id: &str,
+) -> Result<FriendProfile, ApiError> {
+    let not_found = || {
+        ApiError::new(
+            StatusCode::NOT_FOUND,
+            ERROR_CODE_FRIEND_NOT_FOUND,
+            "Friend not found",
+        )
+    };
+    let conn = db.read().await;
+    let relation = friendship_repo::find_active_relation(&conn, user_id, friend_id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)?;
+    if friendship_repo::is_blocked_pair(&conn, user_id, friend_id)
+        .await
+        .map_err(internal_error)?
+    {
+        return Err(not_found());
+    }
+    let timeline = friendship_repo::find_relation_timeline(&conn, user_id, friend_id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)?;
+    let username = friendship_repo::find_username(&conn, friend_id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)?;
+
+    let status = match (relation.pending, relation.direction.as_deref()) {
+        (false, _) => FRIEND_RELATIONSHIP_ACCEPTED,
+        (true, Some(FRIEND_DIRECTION_OUTGOING)) => FRIEND_RELATIONSHIP_PENDING_OUTGOING,
+        (true, _) => FRIEND_RELATIONSHIP_PENDING_INCOMING,
+    };
+    let shared = match relation.pending {
+        true => None,
+        false => Some(shared_split_history(&conn, user_id, friend_id).await?),
+    };
+
+    Ok(FriendProfile {
+        friend_id: relation.user_id,
+        username,
+        nickname: relation.nickname,
+        status: status.to_string(),
+        requested_at: timeline.created_at,
+        shared,
+    })
+}
+
+async fn shared_split_history(
+    conn: &libsql::Connection,
+    user_id: &str,
+    friend_id: &str,
+) -> Result<SharedSplitHistory, ApiError> {
+    let totals = split_repo::pair_share_totals(conn, user_id, friend_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to total shared splits"))?;
+    let balances = friendship_repo::find_friend_balance(conn, user_id, friend_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to query friend balance"))?
+        .map(|balance| balance.currencies)
+        .unwrap_or_default();
+    let split_count = totals.iter().map(|totals| totals.split_count).sum::<i64>();
+    // Unsettled shares are live shares too, so every balance currency has totals.
+    let currencies = totals
+        .into_iter()
+        .map(|totals| {
+            let (amount_owed_to_me, amount_i_owe) = balances
+                .iter()
+                .find(|balance| balance.currency == totals.currency)
+                .map(|balance| (balance.amount_owed_to_me, balance.amount_i_owe))
+                .unwrap_or((Money::ZERO, Money::ZERO));
+            SharedCurrencyTotals {
+                currency: totals.currency,
+                paid_for_friend: totals.owed_to_viewer,
+                paid_by_friend: totals.owed_by_viewer,
+                amount_owed_to_me,
+                amount_i_owe,
+                net: amount_owed_to_me - amount_i_owe,
+            }
+        })
+        .collect();
+    let recent_splits =
+        split_repo::list_pair_shares(conn, user_id, friend_id, FRIEND_PROFILE_RECENT_SPLITS)
+            .await
+            .map_err(|_| db_error_with_context("failed to query shared splits"))?
+            .into_iter()
+            .map(|share| RecentSharedSplit {
+                split_id: share.split_id,
+                description: share.description,
+                at: share.at,
+            })
+            .collect();
+
+    Ok(SharedSplitHistory {
+        split_count: u32::try_from(split_count)
+            .map_err(|_| db_error_with_context("shared split count exceeds u32"))?,
+        currencies,
+        recent_splits,
+    })
+}
+
+pub async fn get_friend_profile(
+    State(app_state): State<AppState>,
+    session: Session,
+    Path(friend_id): Path<String>,
+) -> Result<(StatusCode, Json<FriendProfile>), ApiError> {
+    let current_user = get_current_user(&session).await?;
+    let profile = friend_profile_for_user(&app_state.main_db, &current_user.id, &friend_id).await?;
+
+    Ok((StatusCode::OK, Json(profile)))
+}
+
+pub async fn get_friend_balances(
+    State(app_state): State<AppState>,
+    session: Session,
+) -> Result<(StatusCode, Json<FriendBalancesResponse>), ApiError> {
+    let current_user = get_current_user(&session).await?;
+    let balances = friend_balances_for_user(&app_state.main_db, &current_user.id).await?;
+
+    Ok((StatusCode::OK, Json(balances)))
+}
+
+/// Activity with one friend (or former friend), newest first.
+pub async fn get_friend_activity(
+    State(app_state): State<AppState>,
+    session: Session,
+    Path(friend_id): Path<String>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<(StatusCode, Json<FriendActivityResponse>), ApiError> {
+    let current_user = get_current_user(&session).await?;
+    let limit = validate_records_limit(query.limit)?;
+    let offset = validate_offset(query.offset)?;
+
+    let activity = friend_activity_for_user(
+        &app_state.main_db,
+        &current_user.id,
+        &friend_id,
+        limit,
+        offset,
+    )
+    .await?;
+
+    Ok((StatusCode::OK, Json(activity)))
 }
@@ -0,0 +1,670 @@
+use std::collections::HashMap;
+
+use libsql::Connection;
+use libsql::params::IntoParams;
+use time::OffsetDateTime;
+
+use crate::constants::*;
+use crate::models::{CurrencyBalance, FriendBalance, FriendshipRelation, UserSearchResult};
+use crate::money::Money;
+use crate::utils::{precise_timestamp, sql_placeholders};
+
+/// Which of a user's friendships `count_friends`/`list_friends` read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FriendListKind {
+    Accepted,
+    /// Requests still waiting on the recipient, in either direction.
+    Pending,
+    /// Requests sent to the user that they declined.
+    Declined,
+}
+
+/// Who sent the request behind a friendship, seen from the listing user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FriendDirection {
+    /// Someone else asked the user.
+    Incoming,
+    /// The user sent the request.
+    Outgoing,
+}
+
+/// A pending row as seen by its recipient, with the sender's display name.
+pub struct IncomingRequest {
+    pub relation: FriendshipRelation,
+    pub to_user_id: String,
+    pub requester_user_id: String,
+}
+
+/// The timestamps of one directed row, for the friend activity feed.
+pub struct RelationTimeline {
+    pub status: String,
+    pub created_at: Option<String>,
+    pub accepted_at: Option<String>,
+    pub status_changed_at: Option<String>,
+}
+
+const RELATION_SELECT: &str = "SELECT f.id, f.to_user_id, f.pending, COALESCE(f.nickname, u.name) AS nickname, f.request_message, CASE WHEN f.requester_user_id = f.from_user_id THEN 'outgoing' ELSE 'incoming' END AS direction, u.name FROM friendship f JOIN users u ON u.id = f.to_user_id";
+
+/// Matches both directed rows of the pair; binds `(a, b, b, a)`.
+const PAIR_FILTER: &str =
+    "((from_user_id = ? AND to_user_id = ?) OR (from_user_id = ? AND to_user_id = ?))";
+
+async fn query_count(
+    conn: &Connection,
+    sql: &str,
+    params: impl IntoParams,
+) -> Result<i64, libsql::Error> {
+    let mut rows = conn.query(sql, params).await?;
+    match rows.next().await? {
+        Some(row) => row.get(0),
+        None => Ok(0),
+    }
+}
+
+fn relation_from_row(row: &libsql::Row) -> Result<FriendshipRelation, libsql::Error> {
+    let pending: i64 = row.get(2)?;
+    Ok(FriendshipRelation {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        username: row.get(6)?,
+        pending: pending != 0,
+        nickname: row.get(3)?,
+        message: row.get(4)?,
+        direction: row.get(5)?,
+    })
+}
+
+async fn find_relation_where(
+    conn: &Connection,
+    from_user_id: &str,
+    to_user_id: &str,
+    active_only: bool,
+) -> Result<Option<FriendshipRelation>, libsql::Error> {
+    let mut rows = if active_only {
+        conn.query(
+            &format!(
+                "{RELATION_SELECT} WHERE f.from_user_id = ? AND f.to_user_id = ? AND f.status = ?"
+            ),
+            (from_user_id, to_user_id, FRIENDSHIP_STATUS_ACTIVE),
+        )
+        .await?
+    } else {
+        conn.query(
+            &format!("{RELATION_SELECT} WHERE f.from_user_id = ? AND f.to_user_id = ?"),
+            (from_user_id, to_user_id),
+        )
+        .await?
+    };
+    match rows.next().await? {
+        Some(row) => relation_from_row(&row).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// True when `from_user_id` already has a pending, active or blocked row towards `to_user_id`.
+pub async fn has_open_relation(
+    conn: &Connection,
+    from_user_id: &str,
+    to_user_id: &str,
+) -> Result<bool, libsql::Error> {
+    let count = query_count(
+        conn,
+        "SELECT COUNT(*) FROM friendship WHERE from_user_id = ? AND to_user_id = ? AND status NOT IN (?, ?)",
+        (
+            from_user_id,
+            to_user_id,
+            FRIENDSHIP_STATUS_UNFRIENDED,
+            FRIENDSHIP_STATUS_DECLINED,
+        ),
+    )
+    .await?;
+    Ok(count > 0)
+}
+
+/// Inserts both pending directed rows of a request from `requester_id` to `friend_id`.
+/// `message` goes on the friend's row only; the requester's row never carries it.
+pub async fn insert_pending_pair(
+    conn: &Connection,
+    requester_id: &str,
+    friend_id: &str,
+    (requester_row_id, friend_row_id): (&str, &str),
+    message: Option<&str>,
+) -> Result<(), libsql::Error> {
+    let created_at = precise_timestamp(OffsetDateTime::now_utc());
+    for (row_id, from_user_id, to_user_id, message) in [
+        (requester_row_id, requester_id, friend_id, None),
+        (friend_row_id, friend_id, requester_id, message),
+    ] {
+        conn.execute(
+            "INSERT INTO friendship (id, from_user_id, to_user_id, pending, nickname, requester_user_id, created_at, request_message) VALUES (?, ?, ?, ?, NULL, ?, ?, ?)",
+            (row_id, from_user_id, to_user_id, 1i64, requester_id, created_at.as_str(), message),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Reopens a pair whose directed rows have both ended (unfriended or declined) as a
+/// pending request from `requester_id`, with fresh timestamps. The rows keep their ids and
+/// nicknames. Returns the requester's row, or `None` when the pair has no two ended rows.
+pub async fn reopen_ended_pair(
+    conn: &Connection,
+    requester_id: &str,
+    friend_id: &str,
+    message: Option<&str>,
+) -> Result<Option<FriendshipRelation>, libsql::Error> {
+    let ended = query_count(
+        conn,
+        &format!("SELECT COUNT(*) FROM friendship WHERE {PAIR_FILTER} AND status IN (?, ?)"),
+        (
+            requester_id,
+            friend_id,
+            friend_id,
+            requester_id,
+            FRIENDSHIP_STATUS_UNFRIENDED,
+            FRIENDSHIP_STATUS_DECLINED,
+        ),
+    )
+    .await?;
+    if ended != 2 {
+        return Ok(None);
+    }
+
+    let created_at = precise_timestamp(OffsetDateTime::now_utc());
+    for (from_user_id, to_user_id, message) in [
+        (requester_id, friend_id, None),
+        (friend_id, requester_id, message),
+    ] {
+        conn.execute(
+            "UPDATE friendship SET pending = 1, status = ?, status_changed_at = NULL, requester_user_id = ?, created_at = ?, accepted_at = NULL, request_message = ? WHERE from_user_id = ? AND to_user_id = ?",
+            (
+                FRIENDSHIP_STATUS_ACTIVE,
+                requester_id,
+                created_at.as_str(),
+                message,
+                from_user_id,
+                to_user_id,
+            ),
+        )
+        .await?;
+    }
+    find_relation_where(conn, requester_id, friend_id, true).await
+}
+
+/// The active row from `from_user_id` to `to_user_id`, named as `from_user_id` sees it.
+pub async fn find_active_relation(
+    conn: &Connection,
+    from_user_id: &str,
+    to_user_id: &str,
+) -> Result<Option<FriendshipRelation>, libsql::Error> {
+    find_relation_where(conn, from_user_id, to_user_id, true).await
+}
+
+/// Like `find_active_relation`, regardless of status.
+pub async fn find_relation(
+    conn: &Connection,
+    from_user_id: &str,
+    to_user_id: &str,
+) -> Result<Option<FriendshipRelation>, libsql::Error> {
+    find_relation_where(conn, from_user_id, to_user_id, false).await
+}
+
+pub async fn set_nickname(
+    conn: &Connection,
+    from_user_id: &str,
+    to_user_id: &str,
+    nickname: Option<&str>,
+) -> Result<u64, libsql::Error> {
+    conn.execute(
+        "UPDATE friendship SET nickname = ? WHERE from_user_id = ? AND to_user_id = ?",
+        (nickname, from_user_id, to_user_id),
+    )
+    .await
+}
+
+/// The `WHERE` clause and its parameters selecting `user_id`'s rows of `kind`. `search`
+/// keeps friends whose nickname or username contains it, ignoring case; it reads `u`, the
+/// friend's `users` row.
+fn friend_list_filter(
+    user_id: &str,
+    kind: FriendListKind,
+    direction: Option<FriendDirection>,
+    search: Option<&str>,
+) -> (String, Vec<libsql::Value>) {
+    let mut sql = String::from("f.from_user_id = ?");
+    let mut params = vec![libsql::Value::from(user_id.to_string())];
+    let status = match kind {
+        FriendListKind::Accepted => {
+            sql.push_str(" AND f.pending = 0");
+            FRIENDSHIP_STATUS_ACTIVE
+        }
+        FriendListKind::Pending => {
+            sql.push_str(" AND f.pending = 1");
+            FRIENDSHIP_STATUS_ACTIVE
+        }
+        FriendListKind::Declined => {
+            sql.push_str(" AND f.requester_user_id != ?");
+            params.push(libsql::Value::from(user_id.to_string()));
+            FRIENDSHIP_STATUS_DECLINED
+        }
+    };
+    sql.push_str(" AND f.status = ?");
+    params.push(libsql::Value::from(status.to_string()));
+    if let Some(direction) = direction {
+        sql.push_str(match direction {
+            FriendDirection::Incoming => " AND f.requester_user_id != ?",
+            FriendDirection::Outgoing => " AND f.requester_user_id = ?",
+        });
+        params.push(libsql::Value::from(user_id.to_string()));
+    }
+    if let Some(search) = search {
+        sql.push_str(
+            " AND (INSTR(LOWER(COALESCE(f.nickname, '')), LOWER(?)) > 0 OR INSTR(LOWER(u.name), LOWER(?)) > 0)",
+        );
+        params.push(libsql::Value::from(search.to_string()));
+        params.push(libsql::Value::from(search.to_string()));
+    }
+    (sql, params)
+}
+
+pub async fn count_friends(
+    conn: &Connection,
+    user_id: &str,
+    kind: FriendListKind,
+    direction: Option<FriendDirection>,
+    search: Option<&str>,
+) -> Result<i64, libsql::Error> {
+    let (filter, params) = friend_list_filter(user_id, kind, direction, search);
+    query_count(
+        conn,
+        &format!(
+            "SELECT COUNT(*) FROM friendship f JOIN users u ON u.id = f.to_user_id WHERE {filter}"
+        ),
+        params,
+    )
+    .await
+}
+
+/// One page of `user_id`'s friendships, ordered by display nickname.
+pub async fn list_friends(
+    conn: &Connection,
+    user_id: &str,
+    kind: FriendListKind,
+    direction: Option<FriendDirection>,
+    search: Option<&str>,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<FriendshipRelation>, libsql::Error> {
+    let (filter, mut params) = friend_list_filter(user_id, kind, direction, search);
+    params.push(libsql::Value::from(limit));
+    params.push(libsql::Value::from(offset));
+    let mut rows = conn
+        .query(
+            &format!("{RELATION_SELECT} WHERE {filter} ORDER BY nickname LIMIT ? OFFSET ?"),
+            params,
+        )
+        .await?;
+
+    let mut friends = Vec::new();
+    while let Some(row) = rows.next().await? {
+        friends.push(relation_from_row(&row)?);
+    }
+    Ok(friends)
+}
+
+/// Unsettled split shares, pending ones included, between `?1` and anyone else, summed
+/// per counterparty and currency: what the counterparty owes `?1`, then what `?1` owes them.
+/// Shares are owned by their debtor; trashed ones are left out.
+const UNSETTLED_SHARE_TOTALS: &str = "SELECT CASE WHEN r.debtor_user_id = ?1 THEN r.creditor_user_id ELSE r.debtor_user_id END AS counterparty, r.currency, COALESCE(SUM(CASE WHEN r.creditor_user_id = ?1 THEN ABS(r.amount) ELSE 0 END), 0), COALESCE(SUM(CASE WHEN r.debtor_user_id = ?1 THEN ABS(r.amount) ELSE 0 END), 0) FROM records r WHERE (r.debtor_user_id = ?1 OR r.creditor_user_id = ?1) AND r.debtor_user_id != r.creditor_user_id AND r.owner_user_id = r.debtor_user_id AND r.split_id IS NOT NULL AND r.deleted_at IS NULL AND r.settle = 0";
+
+async fn query_friend_balances(
+    conn: &Connection,
+    user_id: &str,
+    friend_id: Option<&str>,
+) -> Result<Vec<FriendBalance>, libsql::Error> {
+    let (mut filter, mut params) =
+        friend_list_filter(user_id, FriendListKind::Accepted, None, None);
+    let mut totals_sql = UNSETTLED_SHARE_TOTALS.to_string();
+    let mut totals_params = vec![libsql::Value::from(user_id.to_string())];
+    if let Some(friend_id) = friend_id {
+        filter.push_str(" AND f.to_user_id = ?");
+        params.push(libsql::Value::from(friend_id.to_string()));
+        totals_sql.push_str(" AND ?2 IN (r.debtor_user_id, r.creditor_user_id)");
+        totals_params.push(libsql::Value::from(friend_id.to_string()));
+    }
+    totals_sql.push_str(" GROUP BY counterparty, r.currency ORDER BY r.currency");
+
+    let mut by_friend: HashMap<String, Vec<CurrencyBalance>> = HashMap::new();
+    let mut rows = conn.query(&totals_sql, totals_params).await?;
+    while let Some(row) = rows.next().await? {
+        let amount_owed_to_me = Money::from_cents(row.get(2)?);
+        let amount_i_owe = Money::from_cents(row.get(3)?);
+        by_friend
+            .entry(row.get(0)?)
+            .or_default()
+            .push(CurrencyBalance {
+                currency: row.get(1)?,
+                amount_owed_to_me,
+                amount_i_owe,
+                net: amount_owed_to_me - amount_i_owe,
+            });
+    }
+
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT f.to_user_id, u.name, COALESCE(f.nickname, u.name) AS nickname FROM friendship f JOIN users u ON u.id = f.to_user_id WHERE {filter} ORDER BY nickname"
+            ),
+            params,
+        )
+        .await?;
+    let mut balances = Vec::new();
+    while let Some(row) = rows.next().await? {
+        let friend_id: String = row.get(0)?;
+        balances.push(FriendBalance {
+            currencies: by_friend.remove(&friend_id).unwrap_or_default(),
+            friend_id,
+            username: row.get(1)?,
+            nickname: row.get(2)?,
+        });
+    }
+    Ok(balances)
+}
+
+/// Every accepted friend of `user_id` with what each side owes, ordered by nickname.
+pub async fn list_friend_balances(
+    conn: &Connection,
+    user_id: &str,
+) -> Result<Vec<FriendBalance>, libsql::Error> {
+    query_friend_balances(conn, user_id, None).await
+}
+
+/// `list_friend_balances` for one friend; `None` unless they are an accepted friend.
+pub async fn find_friend_balance(
+    conn: &Connection,
+    user_id: &str,
+    friend_id: &str,
+) -> Result<Option<FriendBalance>, libsql::Error> {
+    Ok(query_friend_balances(conn, user_id, Some(friend_id))
+        .await?
+        .into_iter()
+        .next())
+}
+
+/// The active row `from_user_id -> to_user_id`, named after its sender, for the recipient to accept.
+pub async fn find_incoming_request(
+    conn: &Connection,
+    from_user_id: &str,
+    to_user_id: &str,
+) -> Result<Option<IncomingRequest>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT f.id, f.from_user_id, f.pending, COALESCE(f.nickname, u.name) AS nickname, f.request_message, CASE WHEN f.requester_user_id = f.to_user_id THEN 'outgoing' ELSE 'incoming' END AS direction, u.name, f.to_user_id, f.requester_user_id FROM friendship f JOIN users u ON u.id = f.from_user_id WHERE f.from_user_id = ? AND f.to_user_id = ? AND f.status = ?",
+            (from_user_id, to_user_id, FRIENDSHIP_STATUS_ACTIVE),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => Ok(Some(IncomingRequest {
+            relation: relation_from_row(&row)?,
+            to_user_id: row.get(7)?,
+            requester_user_id: row.get(8)?,
+        })),
+        None => Ok(None),
+    }
+}
+
+/// Clears `pending` and the request message on both directed rows of the pair,
+/// stamping `accepted_at`.
+pub async fn accept_pair(conn: &Connection, a: &str, b: &str) -> Result<(), libsql::Error> {
+    let accepted_at = precise_timestamp(OffsetDateTime::now_utc());
+    for (from_user_id, to_user_id) in [(a, b), (b, a)] {
+        conn.execute(
+            "UPDATE friendship SET pending = 0, request_message = NULL, accepted_at = ? WHERE from_user_id = ? AND to_user_id = ?",
+            (accepted_at.as_str(), from_user_id, to_user_id),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// `from_user_id`'s row towards `to_user_id`, whatever its status.
+pub async fn find_relation_timeline(
+    conn: &Connection,
+    from_user_id: &str,
+    to_user_id: &str,
+) -> Result<Option<RelationTimeline>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT status, created_at, accepted_at, status_changed_at FROM friendship WHERE from_user_id = ? AND to_user_id = ?",
+            (from_user_id, to_user_id),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => Ok(Some(RelationTimeline {
+            status: row.get(0)?,
+            created_at: row.get(1)?,
+            accepted_at: row.get(2)?,
+            status_changed_at: row.get(3)?,
+        })),
+        None => Ok(None),
+    }
+}
+
+/// The current username of `user_id`.
+pub async fn find_username(
+    conn: &Connection,
+    user_id: &str,
+) -> Result<Option<String>, libsql::Error> {
+    let mut rows = conn
+        .query("SELECT name FROM users WHERE id = ?", [user_id])
+        .await?;
+    match rows.next().await? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+/// Whether `user_id` has an accepted, active friendship with `friend_id`.
+pub async fn is_accepted_friend(
+    conn: &Connection,
+    user_id: &str,
+    friend_id: &str,
+) -> Result<bool, libsql::Error> {
+    let count = query_count(
+        conn,
+        "SELECT COUNT(*) FROM friendship WHERE from_user_id = ? AND to_user_id = ? AND pending = ? AND status = ?",
+        (user_id, friend_id, 0i64, FRIENDSHIP_STATUS_ACTIVE),
+    )
+    .await?;
+    Ok(count > 0)
+}
+
+/// Whether either side of the pair has blocked the other.
+pub async fn is_blocked_pair(conn: &Connection, a: &str, b: &str) -> Result<bool, libsql::Error> {
+    Ok(count_pair_with_status(conn, a, b, FRIENDSHIP_STATUS_BLOCKED).await? > 0)
+}
+
+pub async fn count_pair_with_status(
+    conn: &Connection,
+    a: &str,
+    b: &str,
+    status: &str,
+) -> Result<i64, libsql::Error> {
+    query_count(
+        conn,
+        &format!("SELECT COUNT(*) FROM friendship WHERE {PAIR_FILTER} AND status = ?"),
+        (a, b, b, a, status),
+    )
+    .await
+}
+
+/// Statuses of the pair's directed rows (zero, one or two entries).
+pub async fn pair_statuses(
+    conn: &Connection,
+    a: &str,
+    b: &str,
+) -> Result<Vec<String>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            &format!("SELECT status FROM friendship WHERE {PAIR_FILTER}"),
+            (a, b, b, a),
+        )
+        .await?;
+    let mut statuses = Vec::new();
+    while let Some(row) = rows.next().await? {
+        statuses.push(row.get(0)?);
+    }
+    Ok(statuses)
+}
+
+/// Moves both directed rows of the pair to `status`, stamping `status_changed_at`.
+/// A pending request's message is dropped with it.
+pub async fn update_pair_status(
+    conn: &Connection,
+    a: &str,
+    b: &str,
+    status: &str,
+    status_changed_at: &str,
+) -> Result<u64, libsql::Error> {
+    conn.execute(
+        &format!("UPDATE friendship SET status = ?, status_changed_at = ?, request_message = NULL WHERE {PAIR_FILTER}"),
+        (status, status_changed_at, a, b, b, a),
+    )
+    .await
+}
+
+/// Deletes both directed rows of a still-pending request, returning how many went.
+pub async fn delete_pending_pair(
+    conn: &Connection,
+    a: &str,
+    b: &str,
+) -> Result<u64, libsql::Error> {
+    conn.execute(
+        &format!("DELETE FROM friendship WHERE {PAIR_FILTER} AND pending = 1 AND status = ?"),
+        (a, b, b, a, FRIENDSHIP_STATUS_ACTIVE),
+    )
+    .await
+}
+
+pub async fn delete_pair_with_status(
+    conn: &Connection,
+    a: &str,
+    b: &str,
+    status: &str,
+) -> Result<u64, libsql::Error> {
+    conn.execute(
+        &format!("DELETE FROM friendship WHERE {PAIR_FILTER} AND status = ?"),
+        (a, b, b, a, status),
+    )
+    .await
+}
+
+/// Deletes rows in `status` whose `status_changed_at` is at or before `cutoff`.
+pub async fn delete_with_status_changed_before(
+    conn: &Connection,
+    status: &str,
+    cutoff: &str,
+) -> Result<u64, libsql::Error> {
+    conn.execute(
+        "DELETE FROM friendship WHERE status = ? AND status_changed_at IS NOT NULL AND status_changed_at <= ?",
+        (status, cutoff),
+    )
+    .await
+}
+
+/// Ids of the rows `delete_with_status_changed_before` would delete.
+pub async fn list_ids_with_status_changed_before(
+    conn: &Connection,
+    status: &str,
+    cutoff: &str,
+) -> Result<Vec<String>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT id FROM friendship WHERE status = ? AND status_changed_at IS NOT NULL AND status_changed_at <= ? ORDER BY status_changed_at, id",
+            (status, cutoff),
+        )
+        .await?;
+    let mut ids = Vec::new();
+    while let Some(row) = rows.next().await? {
+        ids.push(row.get(0)?);
+    }
+    Ok(ids)
+}
+
+pub async fn delete_by_ids(conn: &Connection, ids: &[String]) -> Result<u64, libsql::Error> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    let params: Vec<libsql::Value> = ids.iter().cloned().map(libsql::Value::from).collect();
+    conn.execute(
+        &format!(
+            "DELETE FROM friendship WHERE id IN ({})",
+            sql_placeholders(ids.len())
+        ),
+        params,
+    )
+    .await
+}
+
+/// The searcher's relationship to someone, from the searcher's own directed row
+/// (`status`, `pending`, `requester_user_id`), `None` when there is no row.
+fn search_relationship(searcher_id: &str, row: Option<(String, bool, String)>) -> &'static str {
+    match row {
+        Some((status, false, _)) if status == FRIENDSHIP_STATUS_ACTIVE => {
+            FRIEND_RELATIONSHIP_ACCEPTED
+        }
+        Some((status, true, requester)) if status == FRIENDSHIP_STATUS_ACTIVE => {
+            if requester == searcher_id {
+                FRIEND_RELATIONSHIP_PENDING_OUTGOING
+            } else {
+                FRIEND_RELATIONSHIP_PENDING_INCOMING
+            }
+        }
+        _ => FRIEND_RELATIONSHIP_NONE,
+    }
+}
+
+/// Users whose name contains `term` (ignoring case), for friend discovery, each with
+/// the searcher's relationship to them. A block in either direction hides the user, and
+/// the startup self-test user is never listed.
+pub async fn search_users(
+    conn: &Connection,
+    searcher_id: &str,
+    term: &str,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<UserSearchResult>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT u.id, u.name, f.status, f.pending, f.requester_user_id FROM users u \
+             LEFT JOIN friendship f ON f.from_user_id = ?1 AND f.to_user_id = u.id \
+             WHERE INSTR(LOWER(u.name), LOWER(?2)) > 0 AND u.name != ?3 \
+             AND NOT EXISTS (SELECT 1 FROM friendship b WHERE ((b.from_user_id = u.id AND b.to_user_id = ?1) OR (b.from_user_id = ?1 AND b.to_user_id = u.id)) AND b.status = ?4) \
+             ORDER BY u.name COLLATE NOCASE, u.id LIMIT ?5 OFFSET ?6",
+            libsql::params![
+                searcher_id,
+                term,
+                SELFTEST_USERNAME,
+                FRIENDSHIP_STATUS_BLOCKED,
+                limit,
+                offset
+            ],
+        )
+        .await?;
+    let mut users = Vec::new();
+    while let Some(row) = rows.next().await? {
+        let relation = match row.get::<Option<String>>(2)? {
+            Some(status) => {
+                let pending: i64 = row.get(3)?;
+                Some((status, pending != 0, row.get(4)?))
+            }
+            None => None,
+        };
+        users.push(UserSearchResult {
+            id: row.get(0)?,
+            username: row.get(1)?,
+            relationship: search_relationship(searcher_id, relation).to_string(),
+        });
+    }
+    Ok(users)
+}
@@ -0,0 +1,305 @@
+use std::future::Future;
+
+use axum::http::{HeaderMap, StatusCode};
+use serde::{Serialize, de::DeserializeOwned};
+use time::{Duration, OffsetDateTime, format_description::well_known::Rfc3339};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::constants::*;
+use crate::split_repo::{self, IdempotencyEntry, NewIdempotencyEntry};
+use crate::utils::{db_error_with_context, fnv1a_64_hex, validate_string_length};
+
+/// One idempotency key as stored: keys are only unique per user and endpoint.
+#[derive(Clone, Copy, Debug)]
+pub struct IdempotencyScope<'a> {
+    pub user_id: &'a str,
+    pub endpoint: &'a str,
+    pub key: &'a str,
+}
+
+/// What a request should do about the stored entry for its key.
+#[derive(Debug, PartialEq)]
+pub enum CachedOutcome {
+    /// No live entry: run the request.
+    Absent,
+    /// Another request holds the key and is still running: wait for its response.
+    InProgress,
+    /// A reservation held past `IDEMPOTENCY_IN_PROGRESS_TIMEOUT_SECS`, whose request
+    /// never finished (e.g. a crash mid-write): clear it and run as if the key were unused.
+    Stale,
+    /// Same payload as before: answer with the stored response.
+    Replay { status: i64, body: String },
+    /// The key was used with a different payload.
+    Conflict,
+    /// Same payload, but the response was too large to store.
+    ReplayUnavailable,
+}
+
+/// Decides what to do with the stored entry for a key, given this request's payload hash.
+pub fn classify_entry(
+    entry: Option<IdempotencyEntry>,
+    payload_hash: &str,
+    now: OffsetDateTime,
+) -> CachedOutcome {
+    let Some(entry) = entry else {
+        return CachedOutcome::Absent;
+    };
+    let Some(body) = entry.response_body else {
+        // An unreadable timestamp cannot be waited out, so it counts as stale
+        let reserved_at = OffsetDateTime::parse(&entry.created_at, &Rfc3339);
+        return match reserved_at {
+            Ok(at) if now - at < Duration::seconds(IDEMPOTENCY_IN_PROGRESS_TIMEOUT_SECS) => {
+                CachedOutcome::InProgress
+            }
+            _ => CachedOutcome::Stale,
+        };
+    };
+    if entry.payload_hash != payload_hash {
+        CachedOutcome::Conflict
+    } else if entry.response_digest.is_some() {
+        CachedOutcome::ReplayUnavailable
+    } else {
+        CachedOutcome::Replay {
+            status: entry.response_status,
+            body,
+        }
+    }
+}
+
+/// Hash of the JSON form of a payload, compared on replay.
+pub fn payload_hash<T: Serialize>(payload: &T) -> Result<String, (StatusCode, String)> {
+    let serialized = serde_json::to_string(payload).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to serialize payload: {}", e),
+        )
+    })?;
+
+    Ok(fnv1a_64_hex(serialized.as_bytes()))
+}
+
+pub fn validate_idempotency_key(key: &str) -> Result<(), (StatusCode, String)> {
+    validate_string_length(key, "Idempotency key", MAX_IDEMPOTENCY_KEY_LENGTH)
+}
+
+/// The optional `Idempotency-Key` request header, validated like a payload key.
+pub fn idempotency_key_header(headers: &HeaderMap) -> Result<Option<String>, (StatusCode, String)> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    let key = value
+        .to_str()
+        .map_err(|_| {
+            (
+                StatusCode::BAD_REQUEST,
+                "Idempotency key must be visible ASCII".to_string(),
+            )
+        })?
+        .to_string();
+    validate_idempotency_key(&key)?;
+    Ok(Some(key))
+}
+
+/// Runs `operation` at most once per key: the key is reserved before it runs, its
+/// response stored afterwards and replayed for a retry with the same payload.
+/// A retry with another payload is 409, as is one whose response was too large to
+/// store. If `operation` fails the reservation is dropped so the client can retry.
+/// A request arriving while another holds the key waits for that response, and is
+/// 409 `IDEMPOTENCY_IN_PROGRESS` if it does not come within `IDEMPOTENCY_WAIT_MS`.
+pub async fn run_idempotent<P, T, F>(
+    app_state: &AppState,
+    scope: IdempotencyScope<'_>,
+    payload: &P,
+    success: StatusCode,
+    operation: F,
+) -> Result<(StatusCode, T), (StatusCode, String)>
+where
+    P: Serialize,
+    T: Serialize + DeserializeOwned,
+    F: Future<Output = Result<T, (StatusCode, String)>>,
+{
+    let payload_hash = payload_hash(payload)?;
+    let deadline =
+        tokio::time::Instant::now() + std::time::Duration::from_millis(IDEMPOTENCY_WAIT_MS);
+    loop {
+        match reserve(app_state, scope, &payload_hash).await? {
+            Reservation::Reserved => break,
+            Reservation::Replay(replay) => return Ok(replay),
+            Reservation::Busy if tokio::time::Instant::now() < deadline => {
+                tokio::time::sleep(std::time::Duration::from_millis(
+                    IDEMPOTENCY_POLL_INTERVAL_MS,
+                ))
+                .await;
+            }
+            Reservation::Busy => {
+                return Err((
+                    StatusCode::CONFLICT,
+                    IDEMPOTENCY_IN_PROGRESS_MESSAGE.to_string(),
+                ));
+            }
+        }
+    }
+
+    let response = match operation.await {
+        Ok(response) => response,
+        Err(e) => {
+            let _ = release(app_state, scope).await;
+            return Err(e);
+        }
+    };
+
+    // If storing the response fails the work is already done; we still answer, the
+    // key just won't deduplicate a later retry.
+    if let Ok(response_body) = serde_json::to_string(&response) {
+        let _ = commit(
+            app_state,
+            scope,
+            i64::from(success.as_u16()),
+            &response_body,
+        )
+        .await;
+    }
+
+    Ok((success, response))
+}
+
+enum Reservation<T> {
+    /// This request now holds the key and should run.
+    Reserved,
+    Replay((StatusCode, T)),
+    /// Another request holds the key and has not answered yet.
+    Busy,
+}
+
+/// Reserves the key (`response_body = NULL`), or returns the stored response to replay.
+/// The lookup and the insert share the write lock, and the unique index on
+/// `(user_id, endpoint, key)` backs it: a lost insert race is reported as `Busy`.
+async fn reserve<T: DeserializeOwned>(
+    app_state: &AppState,
+    scope: IdempotencyScope<'_>,
+    payload_hash: &str,
+) -> Result<Reservation<T>, (StatusCode, String)> {
+    let now = OffsetDateTime::now_utc();
+    let created_at = now
+        .format(&Rfc3339)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let expires_at = (now + Duration::hours(IDEMPOTENCY_TTL_HOURS))
+        .format(&Rfc3339)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let conn = app_state.main_db.write().await;
+    let entry = split_repo::find_idempotency_entry(
+        &conn,
+        scope.key,
+        scope.user_id,
+        scope.endpoint,
+        &created_at,
+    )
+    .await
+    .map_err(|_| db_error_with_context("failed to query idempotency key"))?;
+
+    match classify_entry(entry, payload_hash, now) {
+        CachedOutcome::Absent => {}
+        CachedOutcome::InProgress => return Ok(Reservation::Busy),
+        CachedOutcome::Stale => {
+            split_repo::delete_idempotency_reservation(
+                &conn,
+                scope.key,
+                scope.user_id,
+                scope.endpoint,
+            )
+            .await
+            .map_err(|_| db_error_with_context("failed to delete idempotency reservation"))?;
+        }
+        CachedOutcome::Replay { status, body } => {
+            let response = serde_json::from_str::<T>(&body).map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to deserialize idempotency response".to_string(),
+                )
+            })?;
+            let status = StatusCode::from_u16(status as u16).map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Invalid cached response status".to_string(),
+                )
+            })?;
+            app_state.metrics.record_idempotency_hit();
+            return Ok(Reservation::Replay((status, response)));
+        }
+        CachedOutcome::Conflict => {
+            app_state.metrics.record_idempotency_conflict();
+            return Err((
+                StatusCode::CONFLICT,
+                "Idempotency key already used with different payload".to_string(),
+            ));
+        }
+        CachedOutcome::ReplayUnavailable => {
+            app_state.metrics.record_idempotency_replay_unavailable();
+            return Err((StatusCode::CONFLICT, REPLAY_UNAVAILABLE_MESSAGE.to_string()));
+        }
+    }
+
+    // Sweep the user's expired keys first: the periodic cleanup may not have run yet,
+    // and an expired row for this key would otherwise fail the insert.
+    split_repo::delete_expired_idempotency_entries(&conn, scope.user_id, &created_at)
+        .await
+        .map_err(|_| db_error_with_context("failed to delete expired idempotency keys"))?;
+    let reserved = split_repo::reserve_idempotency_entry(
+        &conn,
+        &NewIdempotencyEntry {
+            id: &Uuid::new_v4().to_string(),
+            key: scope.key,
+            user_id: scope.user_id,
+            endpoint: scope.endpoint,
+            payload_hash,
+            created_at: &created_at,
+            expires_at: &expires_at,
+        },
+    )
+    .await;
+    match reserved {
+        Ok(()) => Ok(Reservation::Reserved),
+        Err(e) if e.to_string().contains("UNIQUE constraint failed") => Ok(Reservation::Busy),
+        Err(_) => Err(db_error_with_context("failed to reserve idempotency key")),
+    }
+}
+
+async fn commit(
+    app_state: &AppState,
+    scope: IdempotencyScope<'_>,
+    response_status: i64,
+    response_body: &str,
+) -> Result<(), (StatusCode, String)> {
+    // Oversized bodies would bloat main_db and every replay lookup; keep a digest only.
+    let (stored_body, response_digest) =
+        if response_body.len() > app_state.idempotency_max_body_bytes {
+            ("", Some(fnv1a_64_hex(response_body.as_bytes())))
+        } else {
+            (response_body, None)
+        };
+
+    let conn = app_state.main_db.write().await;
+    split_repo::commit_idempotency_entry(
+        &conn,
+        scope.key,
+        scope.user_id,
+        scope.endpoint,
+        response_status,
+        stored_body,
+        response_digest.as_deref(),
+    )
+    .await
+    .map_err(|_| db_error_with_context("failed to commit idempotency entry"))
+}
+
+async fn release(
+    app_state: &AppState,
+    scope: IdempotencyScope<'_>,
+) -> Result<(), (StatusCode, String)> {
+    let conn = app_state.main_db.write().await;
+    split_repo::delete_idempotency_reservation(&conn, scope.key, scope.user_id, scope.endpoint)
+        .await
+        .map_err(|_| db_error_with_context("failed to delete idempotency reservation"))
+}
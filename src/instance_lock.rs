@@ -0,0 +1,56 @@
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+
+use crate::constants::INSTANCE_LOCK_FILE;
+
+/// Exclusive lock on a data directory, released when dropped or when the
+/// process exits. The server holds it for its whole lifetime so the operator CLI
+/// (and a second server) can tell the database has a live writer.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+}
+
+#[derive(Debug)]
+pub enum InstanceLockError {
+    /// Another process holds the lock file at this path.
+    Held(PathBuf),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for InstanceLockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstanceLockError::Held(path) => write!(
+                f,
+                "{} is locked by a running kash-server; stop it first",
+                path.display()
+            ),
+            InstanceLockError::Io(e) => write!(f, "Failed to lock data directory: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for InstanceLockError {}
+
+impl From<std::io::Error> for InstanceLockError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Takes the lock on `data_dir`, creating the directory if needed. Never waits.
+pub fn acquire(data_dir: &str) -> Result<InstanceLock, InstanceLockError> {
+    std::fs::create_dir_all(data_dir)?;
+    let path = Path::new(data_dir).join(INSTANCE_LOCK_FILE);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(InstanceLock { _file: file }),
+        Err(TryLockError::WouldBlock) => Err(InstanceLockError::Held(path)),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use libsql::Connection;
+use serde_json::Value;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::Db;
+use crate::constants::*;
+use crate::utils::{precise_timestamp, sql_placeholders};
+
+pub type JobFuture<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>>;
+
+/// Work the worker runs for one `job_type`. An `Err` is stored in `jobs.last_error`
+/// and the job is retried with backoff until `JOB_MAX_ATTEMPTS`.
+pub trait JobHandler: Send + Sync {
+    fn job_type(&self) -> &'static str;
+
+    /// Recurring jobs are scheduled once on worker start and again this long after
+    /// each run that finishes (done or failed).
+    fn interval(&self) -> Option<Duration> {
+        None
+    }
+
+    fn run<'a>(&'a self, db: &'a Db, payload: &'a Value, now: OffsetDateTime) -> JobFuture<'a>;
+}
+
+/// The handlers one worker runs. A worker only claims jobs whose type it has a
+/// handler for, so the server and the bot share the table without stealing work.
+#[derive(Clone, Default)]
+pub struct JobRegistry {
+    handlers: BTreeMap<&'static str, Arc<dyn JobHandler>>,
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(mut self, handler: impl JobHandler + 'static) -> Self {
+        self.handlers.insert(handler.job_type(), Arc::new(handler));
+        self
+    }
+
+    fn job_types(&self) -> Vec<libsql::Value> {
+        self.handlers
+            .keys()
+            .map(|job_type| libsql::Value::from(job_type.to_string()))
+            .collect()
+    }
+}
+
+/// Outcome counts of one `run_due_jobs` pass.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct JobRunReport {
+    pub succeeded: u32,
+    pub retried: u32,
+    pub failed: u32,
+}
+
+struct ClaimedJob {
+    id: String,
+    job_type: String,
+    payload_json: String,
+    attempts: u32,
+}
+
+/// Queues a job to run at or after `run_at`. Takes a connection so it can join the
+/// caller's transaction and roll back with it.
+pub async fn enqueue(
+    conn: &Connection,
+    job_type: &str,
+    payload: &Value,
+    run_at: OffsetDateTime,
+) -> Result<String, libsql::Error> {
+    let id = Uuid::new_v4().to_string();
+    let now = precise_timestamp(OffsetDateTime::now_utc());
+    conn.execute(
+        "INSERT INTO jobs (id, job_type, payload_json, state, run_at, attempts, created_at)
+         VALUES (?, ?, ?, ?, ?, 0, ?)",
+        (
+            id.as_str(),
+            job_type,
+            payload.to_string(),
+            JOB_STATE_QUEUED,
+            precise_timestamp(run_at),
+            now,
+        ),
+    )
+    .await?;
+    Ok(id)
+}
+
+/// Queues `job_type` with an empty payload unless one is already queued or running.
+/// Returns whether a job was added.
+pub async fn ensure_scheduled(
+    conn: &Connection,
+    job_type: &str,
+    run_at: OffsetDateTime,
+) -> Result<bool, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT 1 FROM jobs WHERE job_type = ? AND state IN (?, ?) LIMIT 1",
+            (job_type, JOB_STATE_QUEUED, JOB_STATE_RUNNING),
+        )
+        .await?;
+    if rows.next().await?.is_some() {
+        return Ok(false);
+    }
+    enqueue(conn, job_type, &Value::Object(Default::default()), run_at).await?;
+    Ok(true)
+}
+
+/// Delay before retrying a job that failed its `attempts`-th run: doubles from
+/// `JOB_RETRY_BASE_SECS` and is capped at `JOB_RETRY_MAX_SECS`.
+pub fn retry_delay(attempts: u32) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(30);
+    let secs = JOB_RETRY_BASE_SECS
+        .saturating_mul(1_i64 << exponent)
+        .min(JOB_RETRY_MAX_SECS);
+    Duration::seconds(secs)
+}
+
+/// Marks jobs whose lease ran out on their last allowed attempt as failed, so a job
+/// that keeps killing its worker is not reclaimed forever.
+async fn fail_exhausted_leases(
+    conn: &Connection,
+    registry: &JobRegistry,
+    now: &str,
+) -> Result<u64, libsql::Error> {
+    let mut params = vec![
+        libsql::Value::from(JOB_STATE_FAILED),
+        libsql::Value::from(now.to_string()),
+        libsql::Value::from(JOB_STATE_RUNNING),
+        libsql::Value::from(now.to_string()),
+        libsql::Value::from(JOB_MAX_ATTEMPTS),
+    ];
+    params.extend(registry.job_types());
+    conn.execute(
+        &format!(
+            "UPDATE jobs SET state = ?, lease_until = NULL, finished_at = ?,
+                 last_error = 'lease expired'
+             WHERE state = ? AND lease_until <= ? AND attempts >= ? AND job_type IN ({})",
+            sql_placeholders(registry.handlers.len())
+        ),
+        params,
+    )
+    .await
+}
+
+/// Claims the oldest due job: queued with `run_at <= now`, or running with an
+/// expired lease (its worker died). The attempt count doubles as a fencing token so
+/// a worker that lost its lease cannot record an outcome over the new claimant.
+async fn claim_next(
+    conn: &Connection,
+    registry: &JobRegistry,
+    now: OffsetDateTime,
+) -> Result<Option<ClaimedJob>, libsql::Error> {
+    let now_text = precise_timestamp(now);
+    loop {
+        let mut params = vec![
+            libsql::Value::from(JOB_STATE_QUEUED),
+            libsql::Value::from(now_text.clone()),
+            libsql::Value::from(JOB_STATE_RUNNING),
+            libsql::Value::from(now_text.clone()),
+        ];
+        params.extend(registry.job_types());
+        let mut rows = conn
+            .query(
+                &format!(
+                    "SELECT id, job_type, payload_json, state, attempts FROM jobs
+                     WHERE ((state = ? AND run_at <= ?) OR (state = ? AND lease_until <= ?))
+                       AND job_type IN ({})
+                     ORDER BY run_at, created_at, id
+                     LIMIT 1",
+                    sql_placeholders(registry.handlers.len())
+                ),
+                params,
+            )
+            .await?;
+        let Some(row) = rows.next().await? else {
+            return Ok(None);
+        };
+        let id: String = row.get(0)?;
+        let state: String = row.get(3)?;
+        let attempts: u32 = row.get(4)?;
+
+        let lease_until = precise_timestamp(now + Duration::seconds(JOB_LEASE_SECS));
+        let claimed = conn
+            .execute(
+                "UPDATE jobs SET state = ?, attempts = attempts + 1, lease_until = ?
+                 WHERE id = ? AND state = ? AND attempts = ?",
+                (
+                    JOB_STATE_RUNNING,
+                    lease_until,
+                    id.as_str(),
+                    state.as_str(),
+                    attempts,
+                ),
+            )
+            .await?;
+        if claimed == 1 {
+            return Ok(Some(ClaimedJob {
+                id,
+                job_type: row.get(1)?,
+                payload_json: row.get(2)?,
+                attempts: attempts + 1,
+            }));
+        }
+        // Another worker sharing the database file claimed it first; look again.
+    }
+}
+
+async fn record_outcome(
+    conn: &Connection,
+    job: &ClaimedJob,
+    result: &Result<(), String>,
+    now: OffsetDateTime,
+) -> Result<(), libsql::Error> {
+    let now_text = precise_timestamp(now);
+    match result {
+        Ok(()) => {
+            conn.execute(
+                "UPDATE jobs SET state = ?, lease_until = NULL, last_error = NULL, finished_at = ?
+                 WHERE id = ? AND attempts = ?",
+                (JOB_STATE_DONE, now_text, job.id.as_str(), job.attempts),
+            )
+            .await?;
+        }
+        Err(error) if job.attempts < JOB_MAX_ATTEMPTS => {
+            conn.execute(
+                "UPDATE jobs SET state = ?, lease_until = NULL, last_error = ?, run_at = ?
+                 WHERE id = ? AND attempts = ?",
+                (
+                    JOB_STATE_QUEUED,
+                    error.as_str(),
+                    precise_timestamp(now + retry_delay(job.attempts)),
+                    job.id.as_str(),
+                    job.attempts,
+                ),
+            )
+            .await?;
+        }
+        Err(error) => {
+            conn.execute(
+                "UPDATE jobs SET state = ?, lease_until = NULL, last_error = ?, finished_at = ?
+                 WHERE id = ? AND attempts = ?",
+                (
+                    JOB_STATE_FAILED,
+                    error.as_str(),
+                    now_text,
+                    job.id.as_str(),
+                    job.attempts,
+                ),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs every job of a registered type that is due at `now`, one at a time, and
+/// records each outcome. The database lock is released while a handler runs.
+pub async fn run_due_jobs(
+    db: &Db,
+    registry: &JobRegistry,
+    now: OffsetDateTime,
+) -> Result<JobRunReport, libsql::Error> {
+    let mut report = JobRunReport::default();
+    if registry.handlers.is_empty() {
+        return Ok(report);
+    }
+
+    {
+        let conn = db.write().await;
+        report.failed +=
+            fail_exhausted_leases(&conn, registry, &precise_timestamp(now)).await? as u32;
+        conn.execute(
+            "DELETE FROM jobs WHERE state = ? AND finished_at < ?",
+            (
+                JOB_STATE_DONE,
+                precise_timestamp(now - Duration::days(JOB_RETENTION_DAYS)),
+            ),
+        )
+        .await?;
+    }
+
+    loop {
+        let claimed = {
+            let conn = db.write().await;
+            claim_next(&conn, registry, now).await?
+        };
+        let Some(job) = claimed else {
+            break;
+        };
+        let Some(handler) = registry.handlers.get(job.job_type.as_str()) else {
+            continue;
+        };
+
+        let result = match serde_json::from_str::<Value>(&job.payload_json) {
+            Ok(payload) => handler.run(db, &payload, now).await,
+            Err(e) => Err(format!("invalid payload: {e}")),
+        };
+
+        let conn = db.write().await;
+        record_outcome(&conn, &job, &result, now).await?;
+        match &result {
+            Ok(()) => report.succeeded += 1,
+            Err(_) if job.attempts < JOB_MAX_ATTEMPTS => report.retried += 1,
+            Err(_) => report.failed += 1,
+        }
+        if let Err(error) = &result {
+            println!(
+                "Jobs: {} {} attempt {} failed: {}",
+                job.job_type, job.id, job.attempts, error
+            );
+        }
+        if let Some(interval) = handler.interval() {
+            ensure_scheduled(&conn, handler.job_type(), now + interval).await?;
+        }
+    }
+    Ok(report)
+}
+
+/// Schedules every recurring handler to run now, then runs due jobs every
+/// `JOB_POLL_INTERVAL_SECS` for the lifetime of the process.
+pub fn spawn_job_worker(db: Db, registry: JobRegistry) {
+    tokio::spawn(async move {
+        {
+            let conn = db.write().await;
+            for handler in registry.handlers.values() {
+                if handler.interval().is_some()
+                    && let Err(e) =
+                        ensure_scheduled(&conn, handler.job_type(), OffsetDateTime::now_utc()).await
+                {
+                    println!("Jobs: scheduling {} failed: {:?}", handler.job_type(), e);
+                }
+            }
+        }
+
+        let mut interval = tokio::time::interval(StdDuration::from_secs(JOB_POLL_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            if let Err(e) = run_due_jobs(&db, &registry, OffsetDateTime::now_utc()).await {
+                println!("Jobs: polling failed: {:?}", e);
+            }
+        }
+    });
+}
@@ -1,24 +1,77 @@
+pub mod account;
+pub mod admin;
+pub mod api_tokens;
 pub mod auth;
+pub mod backup;
+pub mod body_limit;
+pub mod bootstrap;
 pub mod categories;
+pub mod cli;
 pub mod config;
+pub mod config_sources;
 pub mod constants;
+pub mod crypto;
+pub mod csv;
 pub mod database;
+pub mod error;
+pub mod export;
 pub mod friends;
+pub mod friendship_repo;
+pub mod idempotency;
+pub mod instance_lock;
+pub mod jobs;
+pub mod login_attempts;
+pub mod maintenance;
+pub mod metrics;
 pub mod models;
+pub mod money;
+pub mod notifications;
+pub mod outbox;
+pub mod rate_limit;
+pub mod record_repo;
 pub mod records;
+pub mod request_log;
+pub mod selftest;
+pub mod session_store;
+pub mod settings;
+pub mod split_repo;
 pub mod splits;
+pub mod stats;
+pub mod tls;
+pub mod two_factor;
 pub mod utils;
+pub mod whats_new;
 
 pub use crate::database::{Db, init_main_db};
 
 use libsql::Connection;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::config::{BackupConfig, DefaultCategories, PasswordPolicy};
+use crate::metrics::Metrics;
+use crate::rate_limit::RateLimiter;
 
 /// Application state shared across all request handlers
 #[derive(Clone)]
 pub struct AppState {
     pub main_db: Db,
+    /// Bearer token for the `/admin` routes; `None` disables them.
+    pub admin_token: Option<String>,
+    /// Largest response body stored verbatim for idempotent replays.
+    pub idempotency_max_body_bytes: usize,
+    /// Most data rows one CSV import may hold.
+    pub csv_import_max_rows: usize,
+    /// Categories created for each account registered over HTTP.
+    pub default_categories: Arc<DefaultCategories>,
+    /// Checked by registration and password changes.
+    pub password_policy: PasswordPolicy,
+    pub metrics: Arc<Metrics>,
+    /// Failed logins per client and username, and registrations per client.
+    pub auth_rate_limiter: Arc<RateLimiter>,
+    /// Where `POST /admin/backup` writes snapshots; `None` when the database is remote.
+    pub backup: Option<Arc<BackupConfig>>,
 }
 
 /// Errors that can occur during transaction management
@@ -26,25 +79,42 @@ pub struct AppState {
 pub enum TransactionError {
     Begin,
     Commit,
+    /// Another process still held the database after `database::retry_if_busy` gave up.
+    Busy,
 }
 
 /// Execute a function within a database transaction, returning handler-compatible errors.
+///
+/// `BEGIN IMMEDIATE` takes the write lock before `f` runs, so a database busy with
+/// another process is reported there, where the attempt can simply be repeated.
 pub async fn with_transaction<F, T, E>(db_conn: &Db, f: F) -> Result<T, E>
 where
     F: for<'a> FnOnce(&'a Connection) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'a>>,
     E: From<TransactionError>,
 {
     let conn = db_conn.write().await;
-    conn.execute("BEGIN TRANSACTION", ())
+    database::retry_if_busy(|| conn.execute("BEGIN IMMEDIATE", ()))
         .await
-        .map_err(|_| TransactionError::Begin)?;
+        .map_err(|e| {
+            if database::is_busy_error(&e) {
+                TransactionError::Busy
+            } else {
+                TransactionError::Begin
+            }
+        })?;
     match f(&conn).await {
-        Ok(result) => {
-            conn.execute("COMMIT", ())
-                .await
-                .map_err(|_| TransactionError::Commit)?;
-            Ok(result)
-        }
+        Ok(result) => match database::retry_if_busy(|| conn.execute("COMMIT", ())).await {
+            Ok(_) => Ok(result),
+            Err(e) => {
+                let _ = conn.execute("ROLLBACK", ()).await;
+                let error = if database::is_busy_error(&e) {
+                    TransactionError::Busy
+                } else {
+                    TransactionError::Commit
+                };
+                Err(error.into())
+            }
+        },
         Err(e) => {
             let _ = conn.execute("ROLLBACK", ()).await;
             Err(e)
@@ -0,0 +1,113 @@
+use libsql::Connection;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::Db;
+use crate::constants::*;
+use crate::models::LoginAttempt;
+use crate::utils::precise_timestamp;
+
+/// Records one login attempt. `user_id` is looked up from `username` and stays NULL
+/// for names no account holds. `failure_reason` is one of the `LOGIN_FAILURE_*`
+/// constants, or `None` for a successful login.
+pub async fn record_login_attempt(
+    conn: &Connection,
+    username: &str,
+    source: &str,
+    failure_reason: Option<&str>,
+    now: OffsetDateTime,
+) -> libsql::Result<()> {
+    conn.execute(
+        "INSERT INTO login_attempts (id, user_id, username, attempted_at, success, source, failure_reason)
+         VALUES (?1, (SELECT id FROM users WHERE name = ?2), ?2, ?3, ?4, ?5, ?6)",
+        libsql::params![
+            Uuid::new_v4().to_string(),
+            username,
+            precise_timestamp(now),
+            failure_reason.is_none(),
+            source,
+            failure_reason
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+/// When logins as `username` are locked, if they are at `now`. Every
+/// `LOGIN_LOCKOUT_FAILURES` wrong passwords or two-factor codes since the last successful login
+/// lock the name for `LOGIN_LOCKOUT_MINUTES` after the last of them. Keyed by the name
+/// tried rather than the account, so names no account holds lock the same way and the
+/// answer does not reveal which accounts exist. Attempts refused while locked are not
+/// counted, so they cannot keep the name locked.
+pub async fn locked_until(
+    conn: &Connection,
+    username: &str,
+    now: OffsetDateTime,
+) -> libsql::Result<Option<OffsetDateTime>> {
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*), MAX(attempted_at) FROM login_attempts
+             WHERE username = ?1 AND failure_reason IN (?2, ?3)
+               AND attempted_at > COALESCE(
+                   (SELECT MAX(attempted_at) FROM login_attempts WHERE username = ?1 AND success = 1),
+                   '')",
+            libsql::params![
+                username,
+                LOGIN_FAILURE_INVALID_CREDENTIALS,
+                LOGIN_FAILURE_INVALID_TWO_FACTOR
+            ],
+        )
+        .await?;
+    let Some(row) = rows.next().await? else {
+        return Ok(None);
+    };
+    let failures: i64 = row.get(0)?;
+    let last_failure: Option<String> = row.get(1)?;
+    if failures == 0 || failures % LOGIN_LOCKOUT_FAILURES != 0 {
+        return Ok(None);
+    }
+
+    let until = last_failure
+        .and_then(|at| {
+            OffsetDateTime::parse(&at, &time::format_description::well_known::Rfc3339).ok()
+        })
+        .map(|at| at + Duration::minutes(LOGIN_LOCKOUT_MINUTES));
+    Ok(until.filter(|until| *until > now))
+}
+
+/// The user's most recent attempts, newest first.
+pub async fn list_login_attempts(
+    conn: &Connection,
+    user_id: &str,
+    limit: u32,
+) -> libsql::Result<Vec<LoginAttempt>> {
+    let mut rows = conn
+        .query(
+            "SELECT attempted_at, success, source, failure_reason FROM login_attempts
+             WHERE user_id = ?1 ORDER BY attempted_at DESC LIMIT ?2",
+            libsql::params![user_id, limit],
+        )
+        .await?;
+    let mut attempts = Vec::new();
+    while let Some(row) = rows.next().await? {
+        attempts.push(LoginAttempt {
+            attempted_at: row.get(0)?,
+            success: row.get::<i64>(1)? != 0,
+            source: row.get(2)?,
+            failure_reason: row.get(3)?,
+        });
+    }
+    Ok(attempts)
+}
+
+/// Deletes attempts older than `LOGIN_ATTEMPT_RETENTION_DAYS` as of `now`. Returns how
+/// many were removed.
+pub async fn purge_old_login_attempts(db: &Db, now: OffsetDateTime) -> libsql::Result<u64> {
+    let cutoff = precise_timestamp(now - Duration::days(LOGIN_ATTEMPT_RETENTION_DAYS));
+    let conn = db.write().await;
+    conn.execute(
+        "DELETE FROM login_attempts WHERE attempted_at < ?1",
+        [cutoff],
+    )
+    .await
+}
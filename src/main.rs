@@ -1,15 +1,23 @@
 use axum::{
     Router,
     response::Html,
-    routing::{get, patch, post, put},
+    routing::{delete, get, patch, post, put},
+    serve::ListenerExt,
 };
 use time::Duration;
-use tower_http::cors::CorsLayer;
-use tower_sessions::{Expiry, MemoryStore, Session, SessionManagerLayer, cookie::Key};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_sessions::{Expiry, Session, SessionManagerLayer, cookie::Key};
 
 // Import everything from the library crate (no duplicate module declarations)
 use kash_server::{
-    AppState, auth, categories, config::Config, constants::*, database, friends, records, splits,
+    AppState, admin, api_tokens, auth, body_limit, bootstrap, categories, cli,
+    config::{CliConfig, Config},
+    constants::*,
+    crypto, database, export, friends, instance_lock, jobs, maintenance, notifications,
+    rate_limit::RateLimiter,
+    records, request_log, selftest,
+    session_store::AppSessionStore,
+    settings, splits, stats, tls, two_factor, whats_new,
 };
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
@@ -19,48 +27,108 @@ async fn main() -> Result<()> {
     // Load environment variables
     dotenv::dotenv().ok();
 
-    // Load and validate configuration
-    let config = Config::from_env().map_err(|e| format!("Configuration error: {}", e))?;
+    // Operator commands (`kash-server user list`, ...) run instead of the server
+    match cli::parse_args(std::env::args_os().skip(1).collect()) {
+        Ok(Some(invocation)) => {
+            let config = CliConfig::load().map_err(|e| format!("Configuration error: {}", e))?;
+            database::install_busy_retry(config.db_busy_retry);
+            let output = cli::run(&config, &invocation).await;
+            if output.exit_code == CLI_EXIT_OK {
+                println!("{}", output.text);
+            } else {
+                eprintln!("{}", output.text);
+            }
+            std::process::exit(output.exit_code);
+        }
+        Ok(None) => {}
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, cli::USAGE);
+            std::process::exit(CLI_EXIT_USAGE);
+        }
+    }
 
-    // Initialize main database
-    let main_db = database::init_main_db(&config.data_path)
+    // Log lines for the server; CLI output above stays plain stdout/stderr
+    request_log::init_tracing();
+
+    // Load and validate configuration (environment over kash.toml)
+    let config = Config::load().map_err(|e| format!("Configuration error: {}", e))?;
+
+    // A bad certificate path fails startup here, naming the file
+    let certificates = match &config.tls {
+        Some(tls_config) => Some(
+            tls::CertificateResolver::load(tls_config.clone())
+                .map_err(|e| format!("TLS configuration error: {}", e))?,
+        ),
+        None => None,
+    };
+
+    // Held until the process exits; tells the operator CLI a server is writing
+    let _instance_lock = instance_lock::acquire(&config.data_path)?;
+
+    // Initialize main database; the bot may be writing to it too
+    database::install_busy_retry(config.db_busy_retry);
+    let main_db = database::init_main_db_at(&config.data_path, &config.database)
         .await
         .map_err(|e| format!("Failed to initialize main database: {}", e))?;
+    crypto::init_record_encryption(&main_db, config.record_encryption_key.clone()).await?;
+    auth::install_password_hash_params(config.password_hash);
+    two_factor::install_two_factor_key(two_factor::TwoFactorKey::from_session_secret(
+        &config.session_secret,
+    ));
+
+    // Exercise one full write path so a broken schema fails the deploy, not a user
+    if config.startup_self_test {
+        let report = selftest::run_startup_self_test(&main_db)
+            .await
+            .map_err(|e| format!("Startup self-test failed: {}", e))?;
+        tracing::info!("Startup self-test passed in {:?}", report.duration);
+    }
+
+    // A remote database is backed up by its provider; snapshots are of local files only
+    let backup = config.database.is_local().then(|| config.backup.clone());
+
+    // Deferred and periodic work (friendship pruning, idempotency and session cleanup,
+    // trash purge) runs as jobs
+    jobs::spawn_job_worker(
+        main_db.clone(),
+        maintenance::server_jobs(
+            config.friendship_retention.clone(),
+            Duration::seconds(config.idempotency_cleanup_interval_secs as i64),
+            backup.clone(),
+        ),
+    );
 
     // Create application state
-    let app_state = AppState { main_db };
+    let app_state = AppState {
+        main_db: main_db.clone(),
+        admin_token: config.admin_token.clone(),
+        idempotency_max_body_bytes: config.idempotency_max_body_bytes,
+        csv_import_max_rows: config.csv_import_max_rows,
+        default_categories: std::sync::Arc::new(config.default_categories.clone()),
+        password_policy: config.password_policy,
+        metrics: Default::default(),
+        auth_rate_limiter: std::sync::Arc::new(RateLimiter::new(config.login_rate_limit)),
+        backup: backup.map(std::sync::Arc::new),
+    };
 
-    // Create session store
-    let store = MemoryStore::default();
-    // TODO: Consider adding periodic session cleanup for long-running deployments
-    // to prevent memory growth with accumulated expired sessions
+    // Create session store; expired database sessions are deleted by a maintenance job
+    let store = AppSessionStore::new(config.session_store, main_db);
 
     // Create session key with proper error handling
     let session_key = Key::try_from(config.session_secret.as_bytes())
         .map_err(|e| format!("Invalid session secret: {}", e))?;
 
-    // Determine if we should use secure cookies based on environment
-    // Only use secure cookies when explicitly in production with HTTPS
-    let is_production = std::env::var("PRODUCTION")
-        .map(|val| val.to_lowercase() == "true")
-        .unwrap_or(false);
-
+    // Secure cookies whenever we serve HTTPS; PRODUCTION=true covers HTTPS terminated
+    // by a reverse proxy in front of plain HTTP
     let session_layer = SessionManagerLayer::new(store)
-        .with_secure(is_production) // Only secure in production
+        .with_secure(certificates.is_some() || config.production)
         .with_name(SESSION_NAME)
         .with_expiry(Expiry::OnInactivity(Duration::days(SESSION_EXPIRY_DAYS)))
         .with_signed(session_key);
 
     // Configure CORS to allow frontend requests
-    let frontend_origin =
-        std::env::var("FRONTEND_ORIGIN").unwrap_or_else(|_| "http://localhost:8080".to_string());
-
-    let frontend_origin_header = frontend_origin
-        .parse::<axum::http::HeaderValue>()
-        .map_err(|e| format!("Invalid FRONTEND_ORIGIN '{}': {}", frontend_origin, e))?;
-
     let cors = CorsLayer::new()
-        .allow_origin(frontend_origin_header)
+        .allow_origin(AllowOrigin::list(config.frontend_origins.clone()))
         .allow_methods([
             axum::http::Method::GET,
             axum::http::Method::POST,
@@ -73,44 +141,119 @@ async fn main() -> Result<()> {
             axum::http::header::ACCEPT,
             axum::http::header::COOKIE,
         ])
+        .expose_headers([REQUEST_ID_HEADER])
         .allow_credentials(true);
 
     // Build application router
-    let app = Router::new()
+    let routes = Router::new()
         .route("/", get(root))
         .route("/auth/register", post(auth::register))
         .route("/auth/login", post(auth::login))
+        .route("/auth/login/2fa", post(two_factor::login_two_factor))
         .route("/auth/me", get(auth::me))
         .route("/auth/logout", post(auth::logout))
+        .route("/auth/login-history", get(auth::get_login_history))
+        .route("/auth/username", patch(auth::change_username))
+        .route("/auth/change-password", post(auth::change_password))
+        .route("/auth/account", delete(auth::delete_account))
+        .route(
+            "/auth/tokens",
+            post(api_tokens::create_token).get(api_tokens::list_tokens),
+        )
+        .route("/auth/tokens/{id}", delete(api_tokens::revoke_token))
+        .route("/auth/2fa/setup", post(two_factor::setup_two_factor))
+        .route("/auth/2fa/verify", post(two_factor::verify_two_factor))
+        .route("/auth/2fa/disable", post(two_factor::disable_two_factor))
+        .route("/auth/export", get(export::export_account))
+        .route("/auth/import", post(export::import_account))
+        .route("/bootstrap", get(bootstrap::get_bootstrap))
         .route(
             "/records",
             post(records::create_record).get(records::get_records),
         )
         .route(
             "/records/{id}",
-            put(records::update_record).delete(records::delete_record),
+            get(records::get_record)
+                .put(records::update_record)
+                .delete(records::delete_record),
         )
+        .route("/records/summary", get(records::get_records_summary))
+        .route("/records/batch", post(records::create_records_batch))
+        .route("/records/export", get(records::export_records_csv))
+        .route("/records/import", post(records::import_records))
         .route("/records/{id}/settle", put(records::update_settle))
+        .route("/records/{id}/restore", post(records::restore_record))
+        .route("/records/trash", get(records::get_records_trash))
         .route(
             "/records/finalize-pending",
             post(records::finalize_pending_record),
         )
+        .route(
+            "/records/recategorize-batch",
+            post(records::recategorize_batch),
+        )
+        .route(
+            "/records/recategorize-batch/undo",
+            post(records::undo_recategorize_batch),
+        )
+        .route(
+            "/settings",
+            get(settings::get_settings).put(settings::update_settings),
+        )
+        .route("/export", get(export::export_data))
+        .route("/whats-new", get(whats_new::get_whats_new))
+        .route("/notifications", get(notifications::get_notifications))
+        .route(
+            "/notifications/{id}/read",
+            post(notifications::mark_notification_read),
+        )
+        .route("/stats/ai-accuracy", get(stats::get_ai_accuracy))
         .route(
             "/categories",
             post(categories::create_category).get(categories::get_categories),
         )
+        .route(
+            "/categories/budget-status",
+            get(categories::get_budget_status),
+        )
+        .route("/categories/merge", post(categories::merge_categories))
         .route(
             "/categories/{id}",
             put(categories::update_category).delete(categories::delete_category),
         )
+        .route(
+            "/categories/{id}/convert",
+            post(categories::convert_category),
+        )
+        .route(
+            "/categories/{id}/activity",
+            get(categories::get_category_activity),
+        )
         .route("/friends/request", post(friends::send_friend_request))
         .route("/friends/search", get(friends::search_users))
         .route("/friends/nickname", patch(friends::update_nickname))
         .route("/friends/list", get(friends::list_friends))
         .route("/friends/accept", post(friends::accept_friend))
+        .route("/friends/decline", post(friends::decline_friend))
+        .route("/friends/cancel", post(friends::cancel_friend_request))
         .route("/friends/remove", post(friends::remove_friend))
+        .route(
+            "/friends/history/{friend_id}",
+            delete(friends::purge_friend_history),
+        )
+        .route("/friends/balances", get(friends::get_friend_balances))
+        .route("/friends/{id}", get(friends::get_friend_profile))
+        .route("/friends/{id}/activity", get(friends::get_friend_activity))
+        .route(
+            "/friends/{id}/settle-up",
+            get(splits::get_settle_up).post(splits::execute_settle_up),
+        )
+        .route("/splits", get(splits::list_splits))
         .route("/splits/create", post(splits::create_split))
         .route("/splits/pending", get(splits::list_pending_splits))
+        .route("/splits/{id}", get(splits::get_split))
+        .route("/splits/{id}/cancel", post(splits::cancel_split))
+        .route("/splits/{id}/settle-all", post(splits::settle_split))
         .route(
             "/splits/unsettled",
             get(splits::list_unsettled_splits_with_friend),
@@ -119,22 +262,62 @@ async fn main() -> Result<()> {
             "/splits/unsettled/{friend_id}/settle_all",
             put(splits::settle_all_unsettled_splits_with_friend),
         )
+        .route("/admin/friendships/prune", post(admin::prune_friendships))
+        .route(
+            "/admin/idempotency-keys/cleanup",
+            post(admin::cleanup_idempotency_keys),
+        )
+        .route(
+            "/admin/users/{id}/unlink-telegram",
+            post(admin::unlink_telegram),
+        )
+        .route("/admin/users/{id}/disable", post(admin::disable_user))
+        .route(
+            "/admin/users/{id}/encrypt-records",
+            post(admin::encrypt_records),
+        )
+        .route(
+            "/admin/users/{id}/rotate-records-key",
+            post(admin::rotate_records_key),
+        )
+        .route("/admin/metrics", get(admin::get_metrics))
+        .route("/admin/backup", post(admin::create_backup));
+
+    // Size caps sit inside CORS so a browser can read the 413; API tokens are resolved
+    // before either, so a refused token costs no body read
+    let app = body_limit::layer(routes, config.body_limits);
+    let app = api_tokens::layer(app, app_state.main_db.clone())
         .layer(cors)
         .layer(session_layer)
         .with_state(app_state);
 
+    // Outermost: every request gets an id and one log line
+    let app = request_log::layer(app);
+
     // Create TCP listener with proper error handling
     let bind_address = config.bind_address();
     let listener = tokio::net::TcpListener::bind(&bind_address)
         .await
         .map_err(|e| format!("Failed to bind to {}: {}", bind_address, e))?;
 
-    println!("Server running on http://{}", bind_address);
-
-    // Start server with proper error handling
-    axum::serve(listener, app)
-        .await
-        .map_err(|e| format!("Server error: {}", e))?;
+    // Start server with proper error handling; the peer address keys the auth rate limits
+    let app = app.into_make_service_with_connect_info::<std::net::SocketAddr>();
+    match certificates {
+        Some(certificates) => {
+            tls::spawn_reloader(certificates.clone());
+            let listener =
+                tls::TlsListener::new(listener, certificates.server_config())?.tap_io(|stream| {
+                    let _ = stream.get_ref().0.set_nodelay(true);
+                });
+            tracing::info!("Server running on https://{}", bind_address);
+            axum::serve(listener, app).await
+        }
+        None => {
+            tracing::info!("Server running on http://{}", bind_address);
+            axum::serve(listener, app).await
+        }
+    }
+    .map_err(|e| format!("Server error: {}", e))?;
 
     Ok(())
 }
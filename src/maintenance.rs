@@ -0,0 +1,264 @@
+use serde_json::Value;
+use time::{Duration, OffsetDateTime};
+
+use crate::Db;
+use crate::admin::{AdminActionError, CleanupIdempotencyKeys, PruneFriendships, run_admin_action};
+use crate::backup::BackupDatabaseJob;
+use crate::config::{BackupConfig, FriendshipRetention};
+use crate::constants::*;
+use crate::jobs::{JobFuture, JobHandler, JobRegistry};
+use crate::login_attempts::purge_old_login_attempts;
+use crate::notifications::purge_read_notifications;
+use crate::record_repo;
+use crate::session_store::delete_expired_sessions;
+use crate::utils::precise_timestamp;
+
+/// Rows deleted by one maintenance pass.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    pub unfriended_rows_pruned: u64,
+    pub blocked_rows_pruned: u64,
+}
+
+/// Second-precision RFC 3339 timestamp so stored values compare correctly as text.
+pub fn status_timestamp(at: OffsetDateTime) -> String {
+    at.replace_nanosecond(0)
+        .unwrap_or(at)
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap_or_default()
+}
+
+async fn prune_friendship_status(
+    db: &Db,
+    status: &str,
+    after_days: Option<u32>,
+    now: OffsetDateTime,
+) -> Result<u64, AdminActionError> {
+    let Some(days) = after_days else {
+        return Ok(0);
+    };
+
+    let action = PruneFriendships::older_than(status, days, now);
+    let response = run_admin_action(db, action, false).await?;
+    Ok(response.rows_affected.unwrap_or(0))
+}
+
+/// Deletes unfriended (and, if configured, blocked) relationship rows whose status
+/// changed at least the configured number of days before `now`. Both directed rows
+/// of a pair share one timestamp, so pairs are always removed together.
+pub async fn prune_friendships(
+    db: &Db,
+    retention: &FriendshipRetention,
+    now: OffsetDateTime,
+) -> Result<MaintenanceReport, AdminActionError> {
+    Ok(MaintenanceReport {
+        unfriended_rows_pruned: prune_friendship_status(
+            db,
+            FRIENDSHIP_STATUS_UNFRIENDED,
+            retention.unfriended_after_days,
+            now,
+        )
+        .await?,
+        blocked_rows_pruned: prune_friendship_status(
+            db,
+            FRIENDSHIP_STATUS_BLOCKED,
+            retention.blocked_after_days,
+            now,
+        )
+        .await?,
+    })
+}
+
+/// Recurring job running `prune_friendships` every `MAINTENANCE_INTERVAL_SECS`.
+pub struct PruneFriendshipsJob {
+    pub retention: FriendshipRetention,
+}
+
+impl JobHandler for PruneFriendshipsJob {
+    fn job_type(&self) -> &'static str {
+        JOB_TYPE_PRUNE_FRIENDSHIPS
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        Some(Duration::seconds(MAINTENANCE_INTERVAL_SECS as i64))
+    }
+
+    fn run<'a>(&'a self, db: &'a Db, _payload: &'a Value, now: OffsetDateTime) -> JobFuture<'a> {
+        Box::pin(async move {
+            let report = prune_friendships(db, &self.retention, now)
+                .await
+                .map_err(|e| format!("friendship pruning failed: {:?}", e))?;
+            if report != MaintenanceReport::default() {
+                println!(
+                    "Maintenance: pruned {} unfriended and {} blocked friendship rows",
+                    report.unfriended_rows_pruned, report.blocked_rows_pruned
+                );
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Recurring job deleting expired idempotency keys every `interval`, as
+/// `POST /admin/idempotency-keys/cleanup` does.
+pub struct CleanupIdempotencyKeysJob {
+    pub interval: Duration,
+}
+
+impl JobHandler for CleanupIdempotencyKeysJob {
+    fn job_type(&self) -> &'static str {
+        JOB_TYPE_CLEANUP_IDEMPOTENCY_KEYS
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        Some(self.interval)
+    }
+
+    fn run<'a>(&'a self, db: &'a Db, _payload: &'a Value, now: OffsetDateTime) -> JobFuture<'a> {
+        Box::pin(async move {
+            let action = CleanupIdempotencyKeys {
+                now: status_timestamp(now),
+            };
+            let response = run_admin_action(db, action, false)
+                .await
+                .map_err(|e| format!("idempotency cleanup failed: {:?}", e))?;
+            let deleted = response.rows_affected.unwrap_or(0);
+            if deleted > 0 {
+                println!("Maintenance: deleted {} expired idempotency keys", deleted);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Permanently deletes records that have been in the trash for `RECORD_TRASH_RETENTION_DAYS`
+/// as of `now`. Returns how many were removed.
+pub async fn purge_deleted_records(db: &Db, now: OffsetDateTime) -> Result<u64, libsql::Error> {
+    let cutoff = precise_timestamp(now - Duration::days(RECORD_TRASH_RETENTION_DAYS));
+    let conn = db.write().await;
+    record_repo::purge_trash(&conn, &cutoff).await
+}
+
+/// Recurring job running `purge_deleted_records` every `MAINTENANCE_INTERVAL_SECS`.
+pub struct PurgeDeletedRecordsJob;
+
+impl JobHandler for PurgeDeletedRecordsJob {
+    fn job_type(&self) -> &'static str {
+        JOB_TYPE_PURGE_DELETED_RECORDS
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        Some(Duration::seconds(MAINTENANCE_INTERVAL_SECS as i64))
+    }
+
+    fn run<'a>(&'a self, db: &'a Db, _payload: &'a Value, now: OffsetDateTime) -> JobFuture<'a> {
+        Box::pin(async move {
+            let purged = purge_deleted_records(db, now)
+                .await
+                .map_err(|e| format!("record purge failed: {}", e))?;
+            if purged > 0 {
+                println!("Maintenance: purged {} deleted records", purged);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Recurring job running `delete_expired_sessions` every `MAINTENANCE_INTERVAL_SECS`.
+pub struct DeleteExpiredSessionsJob;
+
+impl JobHandler for DeleteExpiredSessionsJob {
+    fn job_type(&self) -> &'static str {
+        JOB_TYPE_DELETE_EXPIRED_SESSIONS
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        Some(Duration::seconds(MAINTENANCE_INTERVAL_SECS as i64))
+    }
+
+    fn run<'a>(&'a self, db: &'a Db, _payload: &'a Value, now: OffsetDateTime) -> JobFuture<'a> {
+        Box::pin(async move {
+            let deleted = delete_expired_sessions(db, now)
+                .await
+                .map_err(|e| format!("session cleanup failed: {}", e))?;
+            if deleted > 0 {
+                println!("Maintenance: deleted {} expired sessions", deleted);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Recurring job running `purge_old_login_attempts` every `MAINTENANCE_INTERVAL_SECS`.
+pub struct PurgeLoginAttemptsJob;
+
+impl JobHandler for PurgeLoginAttemptsJob {
+    fn job_type(&self) -> &'static str {
+        JOB_TYPE_PURGE_LOGIN_ATTEMPTS
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        Some(Duration::seconds(MAINTENANCE_INTERVAL_SECS as i64))
+    }
+
+    fn run<'a>(&'a self, db: &'a Db, _payload: &'a Value, now: OffsetDateTime) -> JobFuture<'a> {
+        Box::pin(async move {
+            let purged = purge_old_login_attempts(db, now)
+                .await
+                .map_err(|e| format!("login attempt purge failed: {}", e))?;
+            if purged > 0 {
+                tracing::info!(purged, "purged old login attempts");
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Recurring job running `purge_read_notifications` every `MAINTENANCE_INTERVAL_SECS`.
+pub struct PurgeReadNotificationsJob;
+
+impl JobHandler for PurgeReadNotificationsJob {
+    fn job_type(&self) -> &'static str {
+        JOB_TYPE_PURGE_READ_NOTIFICATIONS
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        Some(Duration::seconds(MAINTENANCE_INTERVAL_SECS as i64))
+    }
+
+    fn run<'a>(&'a self, db: &'a Db, _payload: &'a Value, now: OffsetDateTime) -> JobFuture<'a> {
+        Box::pin(async move {
+            let purged = purge_read_notifications(db, now)
+                .await
+                .map_err(|e| format!("notification purge failed: {}", e))?;
+            if purged > 0 {
+                tracing::info!(purged, "purged read notifications");
+            }
+            Ok(())
+        })
+    }
+}
+
+/// The jobs the API server's worker runs. Scheduled backups run only when `backup`
+/// sets an interval.
+pub fn server_jobs(
+    retention: FriendshipRetention,
+    idempotency_cleanup_interval: Duration,
+    backup: Option<BackupConfig>,
+) -> JobRegistry {
+    let registry = JobRegistry::new()
+        .register(PruneFriendshipsJob { retention })
+        .register(CleanupIdempotencyKeysJob {
+            interval: idempotency_cleanup_interval,
+        })
+        .register(PurgeDeletedRecordsJob)
+        .register(DeleteExpiredSessionsJob)
+        .register(PurgeLoginAttemptsJob)
+        .register(PurgeReadNotificationsJob);
+    match backup {
+        Some(config) if config.interval_hours.is_some() => {
+            registry.register(BackupDatabaseJob { config })
+        }
+        _ => registry,
+    }
+}
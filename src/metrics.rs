@@ -0,0 +1,39 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::models::MetricsSnapshot;
+
+/// In-process counters, reset on restart. Read through `GET /admin/metrics`.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    idempotency_hits: AtomicU64,
+    idempotency_conflicts: AtomicU64,
+    idempotency_replay_unavailable: AtomicU64,
+}
+
+impl Metrics {
+    /// A stored response was replayed.
+    pub fn record_idempotency_hit(&self) {
+        self.idempotency_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A key was reused with a different payload.
+    pub fn record_idempotency_conflict(&self) {
+        self.idempotency_conflicts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A key matched but only a digest of its response was stored.
+    pub fn record_idempotency_replay_unavailable(&self) {
+        self.idempotency_replay_unavailable
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            idempotency_hits: self.idempotency_hits.load(Ordering::Relaxed),
+            idempotency_conflicts: self.idempotency_conflicts.load(Ordering::Relaxed),
+            idempotency_replay_unavailable: self
+                .idempotency_replay_unavailable
+                .load(Ordering::Relaxed),
+        }
+    }
+}
@@ -1,4 +1,16 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::money::Money;
+
+/// Distinguishes an explicit `null` (`Some(None)`) from an absent field (`None`)
+/// for optional payload fields that can be cleared.
+fn deserialize_explicit_null<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct User {
@@ -6,6 +18,9 @@ pub struct User {
     pub username: String,
     #[serde(skip_serializing)]
     pub password_hash: String,
+    /// Set by the admin API; disabled users cannot log in.
+    #[serde(skip_serializing, default)]
+    pub disabled_at: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -14,12 +29,48 @@ pub struct RegisterPayload {
     pub password: String,
 }
 
-#[derive(Serialize)]
+#[derive(Deserialize)]
+pub struct ChangeUsernamePayload {
+    pub new_username: String,
+    pub current_password: String,
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordPayload {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+/// `DELETE /auth/account`: the password, re-checked before anything is removed.
+#[derive(Deserialize)]
+pub struct DeleteAccountPayload {
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PublicUser {
     pub id: String,
     pub username: String,
 }
 
+/// One `GET /friends/search` result.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UserSearchResult {
+    pub id: String,
+    pub username: String,
+    /// The searcher's side: `none`, `pending_outgoing`, `pending_incoming` or `accepted`.
+    pub relationship: String,
+}
+
+/// One row of `kash-server user list`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UserSummary {
+    pub id: String,
+    pub username: String,
+    pub disabled_at: Option<String>,
+    pub last_login_at: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct LoginPayload {
     pub username: String,
@@ -30,25 +81,111 @@ pub struct LoginPayload {
 pub struct Record {
     pub id: String,
     pub name: String,
-    pub amount: f64,
+    pub amount: Money,
+    /// ISO 4217 code of `amount`.
+    pub currency: String,
     pub category_id: Option<String>,
     pub date: String,
+    /// Creation order across all records; breaks ties between records on the same date.
+    #[serde(default)]
+    pub seq: i64,
+    /// The foreign amount an imported record was converted from; display only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_amount: Option<f64>,
+    /// ISO 4217 code of `original_amount`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_currency: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Sorted; omitted when the record has none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Set by `GET /records` when the record falls in the user's closed period.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+    /// Set by `GET /records` on the payer's record of a split.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split_progress: Option<SplitProgress>,
+    /// Set by `GET /records` on a participant's split record: whether it has been paid back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settled: Option<bool>,
 }
 
-#[derive(Deserialize)]
+/// `GET /records/{id}`: the record as `GET /records` lists it plus its split and
+/// settlement columns.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordDetail {
+    #[serde(flatten)]
+    pub record: Record,
+    pub pending: bool,
+    pub settle: bool,
+    pub split_id: Option<String>,
+    pub debtor_user_id: Option<String>,
+    pub creditor_user_id: Option<String>,
+}
+
+/// How far a split's participants have paid the payer back.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SplitProgress {
+    pub participants_total: u32,
+    pub participants_settled: u32,
+    pub amount_outstanding: Money,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct CreateRecordPayload {
     pub name: String,
-    pub amount: f64,
+    pub amount: Money,
+    /// ISO 4217 code of `amount`; defaults to the user's `currency_code` setting.
+    #[serde(default)]
+    pub currency: Option<String>,
     pub category_id: String,
     pub date: String,
+    /// Given together with `original_currency` or not at all; `amount` stays authoritative.
+    #[serde(default)]
+    pub original_amount: Option<f64>,
+    #[serde(default)]
+    pub original_currency: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Lower-cased and de-duplicated on save.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Origin metadata attached to a record at creation time.
+/// `model` and `prompt_hash` are stored in `record_provenance` for AI-created records.
+#[derive(Debug, Clone)]
+pub struct RecordProvenance {
+    pub created_via: String,
+    pub model: Option<String>,
+    pub prompt_hash: Option<String>,
+    pub ai_category_confidence: Option<f64>,
 }
 
 #[derive(Deserialize)]
 pub struct UpdateRecordPayload {
     pub name: Option<String>,
-    pub amount: Option<f64>,
+    pub amount: Option<Money>,
+    pub currency: Option<String>,
     pub category_id: Option<String>,
     pub date: Option<String>,
+    /// Absent leaves the original amount unchanged; `null` (with `original_currency: null`) clears it.
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub original_amount: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub original_currency: Option<Option<String>>,
+    /// Absent leaves the note unchanged; `null` or a blank string clears it.
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub note: Option<Option<String>>,
+    /// Replaces every tag; `[]` removes them all.
+    pub tags: Option<Vec<String>>,
+}
+
+/// `reopen=true` lets an HTTP mutation touch records in a closed period; each use is audited.
+#[derive(Deserialize, Default)]
+pub struct ReopenQuery {
+    pub reopen: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -59,12 +196,187 @@ pub struct GetRecordsQuery {
     pub offset: Option<u32>,
     pub pending: Option<bool>,
     pub settle: Option<bool>,
+    /// Case-insensitive substring of the record name.
+    pub q: Option<String>,
+    pub min_amount: Option<Money>,
+    pub max_amount: Option<Money>,
+    /// Only records carrying this tag.
+    pub tag: Option<String>,
+    /// `date` (default), `amount`, `name` or `created`.
+    pub sort_by: Option<String>,
+    /// `asc` or `desc`; defaults to `asc` for `name` and `desc` otherwise.
+    pub order: Option<String>,
+    /// `next_cursor` of the previous page, under the same sort; replaces `offset`.
+    pub cursor: Option<String>,
+    /// Whether to count every match; defaults to true without `cursor`, false with it.
+    pub include_total: Option<bool>,
 }
 
-#[derive(Serialize)]
+/// Paging of the per-category and per-friend activity feeds.
+#[derive(Deserialize)]
+pub struct ActivityQuery {
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct GetRecordsResponse {
     pub records: Vec<Record>,
+    /// Left out when the count was skipped (`include_total=false`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_count: Option<u32>,
+    /// Opaque; pass as `cursor` for the next page. Absent on the last page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// A record in the trash; `deleted_at` is a `precise_timestamp`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrashedRecord {
+    #[serde(flatten)]
+    pub record: Record,
+    pub deleted_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RecordTrashResponse {
+    pub records: Vec<TrashedRecord>,
     pub total_count: u32,
+    /// When records trashed now will be purged, in days.
+    pub retention_days: i64,
+}
+
+#[derive(Deserialize)]
+pub struct RecordsCsvQuery {
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ImportRecordsQuery {
+    pub create_missing_categories: Option<bool>,
+}
+
+/// A CSV row `POST /records/import` could not take, by the line it starts on.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImportLineError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// `created` is zero whenever `errors` is not empty: an import applies completely or not at all.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImportRecordsResponse {
+    pub created: u32,
+    pub created_categories: Vec<String>,
+    pub errors: Vec<ImportLineError>,
+}
+
+#[derive(Deserialize)]
+pub struct RecordSummaryQuery {
+    pub start_date: String,
+    pub end_date: String,
+    pub include_pending: Option<bool>,
+}
+
+/// One category's records in a `GET /records/summary` range. Uncategorized records
+/// share the entry whose `category_id` is `None`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CategorySummary {
+    pub category_id: Option<String>,
+    pub category_name: Option<String>,
+    pub is_income: bool,
+    pub currency: String,
+    pub total: Money,
+    pub record_count: u32,
+}
+
+/// One currency's totals in a `GET /records/summary` range. `income` sums positive amounts
+/// and `expense` negative ones, so `net = income + expense`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CurrencySummary {
+    pub currency: String,
+    pub income: Money,
+    pub expense: Money,
+    pub net: Money,
+}
+
+/// Amounts are never added across currencies: `categories` has one entry per category and
+/// currency, `currencies` one per currency in the range, and `income`, `expense` and `net`
+/// cover only records in `currency`, the user's `currency_code` setting.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordSummaryResponse {
+    pub start_date: String,
+    pub end_date: String,
+    pub include_pending: bool,
+    pub categories: Vec<CategorySummary>,
+    pub currency: String,
+    pub income: Money,
+    pub expense: Money,
+    pub net: Money,
+    pub currencies: Vec<CurrencySummary>,
+}
+
+#[derive(Deserialize)]
+pub struct RecategorizeBatchPayload {
+    pub name_pattern: String,
+    pub created_via: Option<String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub target_category_id: String,
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct RecategorizeBatchResponse {
+    pub dry_run: bool,
+    pub matched_count: u32,
+    pub records: Vec<Record>,
+    pub undo_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UndoRecategorizeBatchPayload {
+    pub undo_token: String,
+}
+
+#[derive(Serialize)]
+pub struct UndoRecategorizeBatchResponse {
+    pub restored_count: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct UserSettings {
+    pub closed_through: Option<String>,
+    /// Minutes east of UTC used for the user's local time; `None` uses the default.
+    pub utc_offset_minutes: Option<i32>,
+    /// Local `HH:MM` at which batched budget alerts are delivered; `None` uses the default.
+    pub alert_batch_time: Option<String>,
+    /// ISO 4217 code new records default to and the bot formats totals in; `None` uses the
+    /// default.
+    pub currency_code: Option<String>,
+    /// Whether splits created or settled for the user are sent to their linked Telegram
+    /// chats; `None` uses the default, on.
+    pub telegram_split_notifications: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateSettingsPayload {
+    /// Absent leaves the closed period unchanged; `null` reopens every period.
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub closed_through: Option<Option<String>>,
+    /// Absent leaves the offset unchanged; `null` restores the default.
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub utc_offset_minutes: Option<Option<i32>>,
+    /// Absent leaves the batch time unchanged; `null` restores the default.
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub alert_batch_time: Option<Option<String>>,
+    /// Absent leaves the currency unchanged; `null` restores the default.
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub currency_code: Option<Option<String>>,
+    /// Absent leaves Telegram split messages unchanged; `null` restores the default.
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub telegram_split_notifications: Option<Option<bool>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -72,17 +384,128 @@ pub struct Category {
     pub id: String,
     pub name: String,
     pub is_income: bool,
+    pub parent_id: Option<String>,
+    /// Spending limit per calendar month; expense categories only.
+    pub monthly_budget: Option<Money>,
+    /// Hidden from pickers and closed to new records; existing records keep it.
+    pub archived: bool,
 }
 
 #[derive(Deserialize)]
 pub struct CreateCategoryPayload {
     pub name: String,
     pub is_income: bool,
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub monthly_budget: Option<Money>,
 }
 
 #[derive(Deserialize)]
 pub struct UpdateCategoryPayload {
     pub name: Option<String>,
+    /// Absent leaves the parent unchanged; `null` promotes the category to top level.
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub parent_id: Option<Option<String>>,
+    /// Absent leaves the budget unchanged; `null` clears it.
+    #[serde(default, deserialize_with = "deserialize_explicit_null")]
+    pub monthly_budget: Option<Option<Money>>,
+    pub archived: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct MergeCategoriesPayload {
+    pub source_id: String,
+    pub target_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MergeCategoriesResponse {
+    pub category: Category,
+    /// Live records moved from the source; trashed ones move too but are not counted.
+    pub records_moved: u32,
+}
+
+#[derive(Deserialize)]
+pub struct BudgetStatusQuery {
+    /// `YYYY-MM`; defaults to the current UTC month.
+    pub month: Option<String>,
+}
+
+/// Every budgeted category's spending in one month.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BudgetStatusResponse {
+    /// `YYYY-MM`
+    pub month: String,
+    pub categories: Vec<CategoryBudgetStatus>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CategoryBudgetStatus {
+    pub category_id: String,
+    pub category_name: String,
+    pub monthly_budget: Money,
+    /// Non-pending expenses in the month, as a positive amount.
+    pub spent: Money,
+    /// `monthly_budget - spent`; negative once over budget.
+    pub remaining: Money,
+}
+
+#[derive(Deserialize)]
+pub struct ConvertCategoryPayload {
+    pub is_income: bool,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Switching a category between income and expense. Subcategories follow their parent, and
+/// every record whose sign disagrees with the new type is re-signed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CategoryConversionPlan {
+    pub category_id: String,
+    pub name: String,
+    /// The type the category is switched to.
+    pub is_income: bool,
+    /// The category followed by its subcategories.
+    pub category_ids: Vec<String>,
+    pub records_to_flip: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConvertCategoryResponse {
+    pub dry_run: bool,
+    pub plan: CategoryConversionPlan,
+    pub records_flipped: u32,
+}
+
+/// A category change held until the user confirms it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CategoryEditAction {
+    Rename {
+        category_id: String,
+        new_name: String,
+    },
+    Convert {
+        category_id: String,
+        is_income: bool,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PendingCategoryEdit {
+    /// What confirming will do, including how many records change sign.
+    pub summary: String,
+    pub action: CategoryEditAction,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteCategoryQuery {
+    pub promote_children: Option<bool>,
+    pub reassign_children_to: Option<String>,
+    /// Moves the category's records to this category of the same type.
+    pub reassign_to: Option<String>,
+    /// Leaves the category's records uncategorized.
+    pub force: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -90,9 +513,10 @@ pub struct GetCategoriesQuery {
     pub limit: Option<u32>,
     pub offset: Option<u32>,
     pub search: Option<String>,
+    pub include_archived: Option<bool>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct GetCategoriesResponse {
     pub categories: Vec<Category>,
     pub total_count: u32,
@@ -100,9 +524,172 @@ pub struct GetCategoriesResponse {
     pub offset: u32,
 }
 
+#[derive(Deserialize, Default)]
+pub struct ExportQuery {
+    /// Comma-separated redactions: `friends`, `notes`.
+    pub redact: Option<String>,
+    pub categories_only: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct ExportArchive {
+    pub schema_version: String,
+    pub exported_at: String,
+    pub user: PublicUser,
+    pub categories: Vec<Category>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub records: Option<Vec<ExportRecord>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category_totals: Option<Vec<ExportCategoryTotal>>,
+    pub friends: Vec<ExportFriend>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportRecord {
+    pub id: String,
+    pub name: String,
+    #[serde(deserialize_with = "crate::money::deserialize_rounded")]
+    pub amount: Money,
+    /// Missing from archives exported before records had a currency; those import in the
+    /// user's `currency_code`.
+    #[serde(default)]
+    pub currency: Option<String>,
+    pub category_id: Option<String>,
+    pub date: String,
+    pub seq: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_amount: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_currency: Option<String>,
+    /// Dropped by `redact=notes`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    pub created_via: String,
+    pub pending: bool,
+    pub settle: bool,
+    pub split_id: Option<String>,
+    pub debtor_user_id: Option<String>,
+    pub creditor_user_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ExportCategoryTotal {
+    /// `YYYY-MM`
+    pub month: String,
+    pub category_id: Option<String>,
+    pub category_name: Option<String>,
+    pub total: Money,
+    pub record_count: u32,
+}
+
+#[derive(Serialize)]
+pub struct ExportFriend {
+    pub user_id: String,
+    pub username: String,
+    pub nickname: Option<String>,
+    pub pending: bool,
+}
+
+/// `GET /auth/export`: the account itself, without its password hash.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountProfile {
+    pub id: String,
+    pub username: String,
+    pub username_changed_at: Option<String>,
+    pub disabled_at: Option<String>,
+    pub last_login_at: Option<String>,
+    pub previous_login_at: Option<String>,
+}
+
+/// A friendship as the exporting user sees it; `requested_by_me` tells the direction of
+/// the request.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AccountFriendship {
+    pub user_id: String,
+    pub username: String,
+    pub nickname: Option<String>,
+    pub status: String,
+    pub pending: bool,
+    pub requested_by_me: bool,
+    pub created_at: Option<String>,
+    pub accepted_at: Option<String>,
+}
+
+/// `POST /auth/import`: the parts of a `GET /auth/export` archive that are restored.
+/// Everything else in the archive is ignored.
+#[derive(Deserialize)]
+pub struct AccountArchive {
+    pub schema_version: u32,
+    #[serde(default)]
+    pub categories: Vec<Category>,
+    #[serde(default)]
+    pub records: Vec<ExportRecord>,
+}
+
+/// Pending split shares and records without a category are skipped.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImportAccountResponse {
+    pub categories_imported: u32,
+    pub records_imported: u32,
+    pub records_skipped: u32,
+}
+
+/// One capped page of a bot record search. `truncated` is set when more records match than
+/// `offset + records.len()`.
+#[derive(Debug)]
+pub struct RecordSearchPage {
+    pub total_count: u32,
+    pub limit: u32,
+    pub offset: u32,
+    pub truncated: bool,
+    pub records: Vec<Record>,
+}
+
+#[derive(Deserialize)]
+pub struct AiAccuracyQuery {
+    pub months: Option<u32>,
+}
+
+/// How often the bot's category pick survived, per month of record date.
+#[derive(Serialize, Debug)]
+pub struct AiAccuracyResponse {
+    /// Oldest month first; months without AI-created records are included with zero counts.
+    pub months: Vec<AiAccuracyMonth>,
+    pub total: u32,
+    pub corrected: u32,
+    /// `None` when there are no AI-created records in the window.
+    pub accuracy_percent: Option<f64>,
+    pub top_confusions: Vec<CategoryConfusion>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AiAccuracyMonth {
+    /// `YYYY-MM`
+    pub month: String,
+    pub total: u32,
+    pub corrected: u32,
+    pub accuracy_percent: Option<f64>,
+    pub top_confusions: Vec<CategoryConfusion>,
+}
+
+/// The AI picked `ai_category_*`; the user moved the record to `user_category_*`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CategoryConfusion {
+    pub ai_category_id: String,
+    pub ai_category_name: Option<String>,
+    pub user_category_id: Option<String>,
+    pub user_category_name: Option<String>,
+    pub count: u32,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SendFriendRequestPayload {
     pub friend_username: String,
+    /// Short note shown to the recipient with the request.
+    #[serde(default)]
+    pub message: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -110,6 +697,16 @@ pub struct AcceptFriendPayload {
     pub friend_id: String,
 }
 
+#[derive(Deserialize)]
+pub struct DeclineFriendPayload {
+    pub friend_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct CancelFriendPayload {
+    pub friend_id: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UpdateNicknamePayload {
     pub friend_id: String,
@@ -125,24 +722,66 @@ pub struct RemoveFriendPayload {
 pub struct FriendshipRelation {
     pub id: String,
     pub user_id: String,
+    /// The friend's current username; `nickname` falls back to it.
+    #[serde(default)]
+    pub username: String,
     pub pending: bool,
     pub nickname: String,
+    /// The sender's note, only on the recipient's row of a pending request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// `incoming` or `outgoing`: whether the friend or the viewer sent the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<String>,
+}
+
+/// How a split's `total_amount` is divided between the initiator and the participants.
+/// In every mode the initiator's record holds only their own share.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitMode {
+    /// Each participant's `amount` is given. The initiator may list themselves for their
+    /// own share, and the amounts must then add up to the total; otherwise their share is
+    /// what is left.
+    #[default]
+    Exact,
+    /// The total is divided evenly between the initiator and the participants.
+    Equal,
+    /// Each entry gives a `percentage`, adding up to 100. The initiator may list
+    /// themselves for their own share; without an entry it is zero.
+    Percentage,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SplitParticipant {
     pub user_id: String,
-    pub amount: f64,
+    /// Only in `exact` mode; the other modes compute it.
+    #[serde(default)]
+    pub amount: Money,
+    /// Only in `percentage` mode, with at most two decimal places.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentage: Option<f64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreateSplitPayload {
     pub idempotency_key: String,
-    pub total_amount: f64,
+    pub total_amount: Money,
     pub description: String,
     pub date: String,
     pub category_id: String,
+    /// ISO 4217 code every share record is booked in; defaults to the initiator's
+    /// `currency_code` setting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    /// Part of the idempotency payload, so a retry must repeat it.
+    #[serde(default)]
+    pub split_mode: SplitMode,
     pub splits: Vec<SplitParticipant>,
+    /// Books the initiator's record at the whole total instead of their own share, for
+    /// clients that still expect it. What the participants owe is the same either way.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub legacy_full_total: bool,
 }
 
 #[derive(Deserialize)]
@@ -164,7 +803,8 @@ pub struct SplitListItem {
     pub split_id: String,
     pub description: String,
     pub date: String,
-    pub amount: f64,
+    pub amount: Money,
+    pub currency: String,
     pub debtor_user_id: String,
     pub creditor_user_id: String,
     pub counterparty_user_id: String,
@@ -176,6 +816,52 @@ pub struct SplitListItem {
     pub direction: String,
 }
 
+/// Netting of every unsettled split record between the caller and one friend.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SettleUpPlan {
+    pub friend_id: String,
+    /// The caller's records owed to the friend; all are marked settled.
+    pub my_record_ids: Vec<String>,
+    /// The friend's records owed to the caller; all are marked settled.
+    pub friend_record_ids: Vec<String>,
+    /// One netting per currency the records are in, ordered by code. Debts in different
+    /// currencies never offset each other.
+    pub currencies: Vec<CurrencySettleUp>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CurrencySettleUp {
+    pub currency: String,
+    pub you_owe: Money,
+    pub they_owe: Money,
+    /// The one payment left in this currency after netting; `None` when the debts
+    /// cancel out.
+    pub residual: Option<SettleUpPayment>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SettleUpPayment {
+    pub from_user_id: String,
+    pub to_user_id: String,
+    pub amount: Money,
+}
+
+/// The record ids of the previewed plan, so a settle-up only runs on what the user saw.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExecuteSettleUpPayload {
+    pub my_record_ids: Vec<String>,
+    pub friend_record_ids: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SettleUpResponse {
+    pub plan: SettleUpPlan,
+    pub settled_count: u32,
+    /// The residual payment records in each user's books, the caller's first, for each
+    /// currency with a residual in plan order.
+    pub residual_record_ids: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SplitListResponse {
     pub splits: Vec<SplitListItem>,
@@ -184,6 +870,226 @@ pub struct SplitListResponse {
     pub offset: u32,
 }
 
+/// `GET /splits/{id}`: a split as rebuilt from its records.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SplitDetail {
+    pub split_id: String,
+    /// `initiated` while a participant has not accepted their share, `completed` once all
+    /// have, `settled` once all have paid back.
+    pub status: String,
+    pub total_amount: Money,
+    pub description: String,
+    pub date: String,
+    pub initiator_user_id: String,
+    pub initiator_name: String,
+    /// What the initiator spent themselves: `total_amount` less every participant's share.
+    #[serde(default)]
+    pub initiator_share: Money,
+    /// What live participants still owe the initiator: their unsettled shares, pending ones
+    /// included, as in `GET /friends/balances`.
+    #[serde(default)]
+    pub receivable: Money,
+    /// The initiator's record holds the whole total (`CreateSplitPayload.legacy_full_total`).
+    #[serde(default)]
+    pub legacy_full_total: bool,
+    pub participants: Vec<SplitDetailParticipant>,
+    /// Participants who deleted their account; their shares still count towards `total_amount`.
+    #[serde(default)]
+    pub departed_participants: Vec<SplitDepartedParticipant>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SplitDetailParticipant {
+    pub user_id: String,
+    pub username: String,
+    pub amount: Money,
+    /// Whether the participant's record is still live (not trashed).
+    pub record_exists: bool,
+    pub pending: bool,
+    pub settled: bool,
+}
+
+/// A participant's share as it stood when they deleted their account.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SplitDepartedParticipant {
+    pub user_id: String,
+    pub username: String,
+    pub amount: Money,
+    pub pending: bool,
+    pub settled: bool,
+    pub departed_at: String,
+}
+
+/// `POST /splits/{id}/cancel`: every record the split had, now deleted.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CancelSplitResponse {
+    pub split_id: String,
+    pub status: String,
+    pub removed_record_ids: Vec<String>,
+}
+
+/// `POST /splits/{id}/settle-all`: settles one participant's shares, or everyone's.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SettleSplitPayload {
+    pub friend_id: Option<String>,
+}
+
+/// `settled_record_ids` lists only the records this call settled.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SettleSplitResponse {
+    pub split_id: String,
+    pub status: String,
+    pub settled_record_ids: Vec<String>,
+}
+
+/// `GET /splits?role&status&limit&offset`.
+#[derive(Deserialize)]
+pub struct SplitsQuery {
+    /// `initiator` or `participant`; both when absent.
+    pub role: Option<String>,
+    /// `initiated`, `completed` or `settled`.
+    pub status: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SplitSummary {
+    pub split_id: String,
+    /// `initiator` or `participant`: the caller's side of the split.
+    pub role: String,
+    pub status: String,
+    pub total_amount: Money,
+    pub description: String,
+    pub date: String,
+    pub initiator_user_id: String,
+    pub initiator_name: String,
+    pub progress: SplitProgress,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SplitSummaryListResponse {
+    pub splits: Vec<SplitSummary>,
+    pub total_count: u32,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+/// What one accepted friend and the caller owe each other over unsettled split shares,
+/// pending ones included, with one entry per currency ordered by code. Empty when
+/// nothing is owed either way.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FriendBalance {
+    pub friend_id: String,
+    pub username: String,
+    pub nickname: String,
+    pub currencies: Vec<CurrencyBalance>,
+}
+
+/// One currency of a friend balance. `net` is positive when the friend owes the caller.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CurrencyBalance {
+    pub currency: String,
+    pub amount_owed_to_me: Money,
+    pub amount_i_owe: Money,
+    pub net: Money,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FriendBalancesResponse {
+    pub balances: Vec<FriendBalance>,
+}
+
+/// `GET /friends/{id}`: the caller's friendship with one person and, once accepted,
+/// their shared split history.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FriendProfile {
+    pub friend_id: String,
+    pub username: String,
+    pub nickname: String,
+    /// `accepted`, `pending_outgoing` or `pending_incoming`, as in friend search.
+    pub status: String,
+    /// When the current request was sent; `None` for rows older than the column.
+    pub requested_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared: Option<SharedSplitHistory>,
+}
+
+/// What two friends have split, with one entry per currency ordered by code.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SharedSplitHistory {
+    pub split_count: u32,
+    pub currencies: Vec<SharedCurrencyTotals>,
+    /// The newest `FRIEND_PROFILE_RECENT_SPLITS`, newest first.
+    pub recent_splits: Vec<RecentSharedSplit>,
+}
+
+/// Totals cover every live share in `currency`, settled or not; the balance only the
+/// unsettled ones, as `GET /friends/balances` does.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SharedCurrencyTotals {
+    pub currency: String,
+    /// The friend's shares of splits the caller paid.
+    pub paid_for_friend: Money,
+    /// The caller's shares of splits the friend paid.
+    pub paid_by_friend: Money,
+    pub amount_owed_to_me: Money,
+    pub amount_i_owe: Money,
+    pub net: Money,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecentSharedSplit {
+    pub split_id: String,
+    /// The name on the caller's own record of the split.
+    pub description: Option<String>,
+    pub at: String,
+}
+
+/// One entry of `GET /friends/{id}/activity`. `at` is a `precise_timestamp`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FriendActivityItem {
+    /// A split share between the two users, created at `at`.
+    Split {
+        at: String,
+        split_id: String,
+        /// The name on the viewer's own record of the split.
+        description: Option<String>,
+        amount: Money,
+        paid_by_user_id: String,
+        pending: bool,
+    },
+    /// A split share between the two users, settled at `at`.
+    Settlement {
+        at: String,
+        split_id: String,
+        description: Option<String>,
+        amount: Money,
+        paid_by_user_id: String,
+    },
+    /// `requested`, `accepted`, `unfriended` or `blocked`.
+    Friendship { at: String, event: String },
+}
+
+impl FriendActivityItem {
+    pub fn at(&self) -> &str {
+        match self {
+            FriendActivityItem::Split { at, .. }
+            | FriendActivityItem::Settlement { at, .. }
+            | FriendActivityItem::Friendship { at, .. } => at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FriendActivityResponse {
+    pub items: Vec<FriendActivityItem>,
+    pub total_count: u32,
+    pub limit: u32,
+    pub offset: u32,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FinalizePendingPayload {
     pub record_id: String,
@@ -199,10 +1105,346 @@ pub struct UpdateSettlePayload {
 pub struct SplitRecord {
     pub id: String,
     pub payer_id: String,
-    pub total_amount: f64,
+    pub total_amount: Money,
     pub description: String,
     pub date: String,
     pub status: String,
     pub created_at: String,
     pub updated_at: String,
 }
+
+#[derive(Deserialize, Default)]
+pub struct AdminDryRunQuery {
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct AdminPruneFriendshipsQuery {
+    pub status: String,
+    pub older_than_days: u32,
+    pub dry_run: Option<bool>,
+}
+
+/// Result of an admin action. `rows_affected` is `None` for a dry run, which only plans.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AdminActionResponse<P> {
+    pub action: String,
+    pub dry_run: bool,
+    pub plan: P,
+    pub rows_affected: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FriendshipPrunePlan {
+    pub status: String,
+    pub cutoff: String,
+    pub friendship_ids: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MetricsSnapshot {
+    pub idempotency_hits: u64,
+    pub idempotency_conflicts: u64,
+    pub idempotency_replay_unavailable: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IdempotencyCleanupPlan {
+    pub expired_before: String,
+    pub key_ids: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TelegramUnlinkPlan {
+    pub user_id: String,
+    pub telegram_user_ids: Vec<String>,
+}
+
+/// Record names to (re-)encrypt for one user, moving them to `to_key_version`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordEncryptionPlan {
+    pub user_id: String,
+    pub from_key_version: Option<i64>,
+    pub to_key_version: i64,
+    pub record_ids: Vec<String>,
+}
+
+/// A database snapshot written by `POST /admin/backup` or the backup job.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BackupSnapshot {
+    /// The snapshot's folder, named for the UTC time it was taken.
+    pub path: String,
+    pub created_at: String,
+    pub files: Vec<BackupFile>,
+    pub total_bytes: u64,
+    /// Older snapshot folders deleted because they fell out of the retention window.
+    pub pruned: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BackupFile {
+    pub name: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UserDisablePlan {
+    pub user_id: String,
+    pub username: String,
+    pub already_disabled: bool,
+    /// Stored sessions that are logged out.
+    pub session_count: u64,
+    /// Telegram chats that are unlinked.
+    pub telegram_link_count: u64,
+}
+
+/// One entry of `GET /auth/login-history`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LoginAttempt {
+    pub attempted_at: String,
+    pub success: bool,
+    /// `web` or `telegram`.
+    pub source: String,
+    /// `invalid_credentials`, `locked`, `disabled` or `rate_limited`; absent on success.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LoginHistoryResponse {
+    pub attempts: Vec<LoginAttempt>,
+}
+
+/// `202` from `POST /auth/login` when the account has two-factor login enabled: no
+/// session yet, only a token for `POST /auth/login/2fa`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TwoFactorChallengeResponse {
+    pub two_factor_required: bool,
+    pub challenge_token: String,
+    pub expires_in_secs: i64,
+}
+
+/// `POST /auth/login/2fa`: a TOTP code or an unused recovery code.
+#[derive(Deserialize)]
+pub struct TwoFactorLoginPayload {
+    pub challenge_token: String,
+    pub code: String,
+}
+
+/// `POST /auth/2fa/setup`: the new secret, for manual entry or as an `otpauth://` URI.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TwoFactorSetupResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Deserialize)]
+pub struct TwoFactorCodePayload {
+    pub code: String,
+}
+
+/// `POST /auth/2fa/verify`: shown once; only their hashes are stored.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TwoFactorRecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+/// `POST /auth/2fa/disable`: the password and a current code.
+#[derive(Deserialize)]
+pub struct DisableTwoFactorPayload {
+    pub password: String,
+    pub code: String,
+}
+
+/// `POST /auth/tokens`. Without `expires_in_days` the token lasts until revoked.
+#[derive(Deserialize)]
+pub struct CreateApiTokenPayload {
+    pub name: Option<String>,
+    pub expires_in_days: Option<i64>,
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiTokenInfo {
+    pub id: String,
+    pub name: Option<String>,
+    pub read_only: bool,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+}
+
+/// The only response that carries the plaintext token.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreatedApiTokenResponse {
+    pub token: String,
+    #[serde(flatten)]
+    pub info: ApiTokenInfo,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ApiTokenListResponse {
+    pub tokens: Vec<ApiTokenInfo>,
+}
+
+#[derive(Deserialize)]
+pub struct LoginHistoryQuery {
+    pub limit: Option<u32>,
+}
+
+/// `POST /auth/login` response: the user plus how many items `GET /whats-new` holds.
+#[derive(Serialize)]
+pub struct LoginResponse {
+    #[serde(flatten)]
+    pub user: PublicUser,
+    pub whats_new: WhatsNewCounts,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct WhatsNewCounts {
+    pub friend_requests: u32,
+    pub splits: u32,
+    pub auto_records: u32,
+    pub budget_alerts: u32,
+}
+
+impl WhatsNewCounts {
+    pub fn total(&self) -> u32 {
+        self.friend_requests + self.splits + self.auto_records + self.budget_alerts
+    }
+}
+
+/// Activity since the previous login. Each list is capped at `WHATS_NEW_MAX_ITEMS`;
+/// `counts` has the full totals.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WhatsNew {
+    /// The previous login; `None` on a first login or once the digest was fetched.
+    pub since: Option<String>,
+    pub counts: WhatsNewCounts,
+    pub friend_requests: Vec<WhatsNewFriendRequest>,
+    pub splits: Vec<WhatsNewSplit>,
+    pub auto_records: Vec<WhatsNewRecord>,
+    pub budget_alerts: Vec<BudgetAlert>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WhatsNewFriendRequest {
+    pub user_id: String,
+    pub username: String,
+    pub created_at: String,
+    pub message: Option<String>,
+}
+
+/// A split someone else created with the user as a participant.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WhatsNewSplit {
+    pub record_id: String,
+    pub description: String,
+    /// The user's share, as a positive amount.
+    pub amount: Money,
+    pub requested_by_name: String,
+    pub created_at: String,
+}
+
+/// A record the user did not enter through the API (bot, import, settle-up).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WhatsNewRecord {
+    pub record_id: String,
+    pub name: String,
+    pub amount: Money,
+    pub created_via: String,
+    pub created_at: String,
+}
+
+/// A category crossing a budget threshold, queued for delivery over Telegram.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BudgetAlert {
+    pub category_name: String,
+    /// Spent so far in the budget period, as a positive amount.
+    pub spent: f64,
+    pub limit: f64,
+}
+
+/// One entry of `GET /notifications`. `kind` is `split_created`, `split_settled`,
+/// `friend_request_received` or `friend_accepted`; `payload` carries the ids involved.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub id: String,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub created_at: String,
+    /// When `POST /notifications/{id}/read` was first called; `None` while unread.
+    pub read_at: Option<String>,
+}
+
+/// `GET /notifications?limit&offset`.
+#[derive(Deserialize)]
+pub struct NotificationsQuery {
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// `unread_count` and `total_count` cover every notification, not just this page.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NotificationListResponse {
+    pub notifications: Vec<Notification>,
+    pub unread_count: u32,
+    pub total_count: u32,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+/// A split event queued for a user's Telegram chats. Only ids are stored; the drainer
+/// looks up the names and amount when it sends the message.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SplitNotice {
+    pub split_id: String,
+    /// The share record the message is about.
+    pub record_id: String,
+    /// Who created or settled it.
+    pub from_user_id: String,
+}
+
+/// One part of `GET /bootstrap`. `version` is the `ETag` the matching standalone
+/// endpoint sends for the same body, for later `If-None-Match` requests.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BootstrapSection<T> {
+    pub version: String,
+    pub data: T,
+}
+
+/// Items waiting on the user.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InboxCounts {
+    /// Incoming friend requests (`GET /friends/list?pending=true`).
+    pub friend_requests: u32,
+    /// Split records awaiting the user's confirmation (`GET /splits/pending`).
+    pub pending_splits: u32,
+}
+
+/// Server features the UI may switch on.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    /// `RECORD_ENCRYPTION_KEY` is set, so record names can be encrypted at rest.
+    pub record_encryption: bool,
+    /// Largest `limit` the list endpoints accept.
+    pub max_page_size: u32,
+    /// ISO 4217 codes accepted for `currency_code` and `original_currency`.
+    pub currency_codes: Vec<String>,
+}
+
+/// `GET /bootstrap`: everything the app needs on a cold start.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BootstrapResponse {
+    /// As `GET /auth/me`.
+    pub user: BootstrapSection<PublicUser>,
+    /// As `GET /categories?limit=MAX_LIMIT`.
+    pub categories: BootstrapSection<GetCategoriesResponse>,
+    /// As `GET /settings`.
+    pub settings: BootstrapSection<UserSettings>,
+    /// As `GET /records?limit=BOOTSTRAP_RECORDS_LIMIT`.
+    pub records: BootstrapSection<GetRecordsResponse>,
+    pub inbox: BootstrapSection<InboxCounts>,
+    pub capabilities: BootstrapSection<Capabilities>,
+}
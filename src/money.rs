@@ -0,0 +1,360 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::models::Record;
+
+/// An amount in minor units (cents), as stored in `records.amount`. On the wire it stays a
+/// decimal: serialized as a JSON number, and read from a number or a string with at most
+/// two decimal places, so `0.1 + 0.2` noise from a client is refused instead of stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+/// Why a decimal could not be read as `Money`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoneyError {
+    Invalid,
+    TooPrecise,
+    TooLarge,
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MoneyError::Invalid => "amount must be a decimal number",
+            MoneyError::TooPrecise => "amount must have at most two decimal places",
+            MoneyError::TooLarge => "amount is too large",
+        })
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+    /// Largest magnitude accepted, in cents: every amount up to it is exact as a JSON number.
+    pub const MAX_CENTS: i64 = (1 << 53) - 1;
+
+    pub const fn from_cents(cents: i64) -> Money {
+        Money(cents)
+    }
+
+    pub const fn cents(self) -> i64 {
+        self.0
+    }
+
+    /// `value` as money when it has at most two decimal places. Goes through the shortest
+    /// decimal that reads back as `value`, so `19.99` is accepted and `19.999` is not.
+    pub fn from_decimal(value: f64) -> Result<Money, MoneyError> {
+        if !value.is_finite() {
+            return Err(MoneyError::Invalid);
+        }
+        value.to_string().parse()
+    }
+
+    /// The amount in major units, for JSON and for arithmetic with display-only values.
+    pub fn to_decimal(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+
+    pub const fn abs(self) -> Money {
+        Money(self.0.abs())
+    }
+
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub const fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    /// Splits `self` in proportion to `weights` by largest remainder: each part is rounded
+    /// down, then the cents left over go one each to the largest fractional parts, earlier
+    /// entries winning ties. The parts always add up to `self`. All-zero weights give all
+    /// zero parts.
+    pub fn allocate(self, weights: &[u64]) -> Vec<Money> {
+        let total_weight: u128 = weights.iter().map(|&weight| u128::from(weight)).sum();
+        if total_weight == 0 {
+            return vec![Money::ZERO; weights.len()];
+        }
+        let magnitude = u128::from(self.0.unsigned_abs());
+        let mut parts: Vec<(u128, u128)> = weights
+            .iter()
+            .map(|&weight| {
+                let share = magnitude * u128::from(weight);
+                (share / total_weight, share % total_weight)
+            })
+            .collect();
+
+        let allocated: u128 = parts.iter().map(|(part, _)| part).sum();
+        let mut order: Vec<usize> = (0..parts.len()).collect();
+        order.sort_by(|&a, &b| parts[b].1.cmp(&parts[a].1).then(a.cmp(&b)));
+        for &index in order.iter().take((magnitude - allocated) as usize) {
+            parts[index].0 += 1;
+        }
+
+        let sign = self.0.signum();
+        parts
+            .into_iter()
+            .map(|(part, _)| Money(sign * part as i64))
+            .collect()
+    }
+}
+
+/// `-12.5`, `980`, `0.05`: the shortest decimal, the way the amount was written as a number.
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let cents = self.0.unsigned_abs();
+        match cents % 100 {
+            0 => write!(f, "{sign}{}", cents / 100),
+            fraction if fraction % 10 == 0 => {
+                write!(f, "{sign}{}.{}", cents / 100, fraction / 10)
+            }
+            fraction => write!(f, "{sign}{}.{fraction:02}", cents / 100),
+        }
+    }
+}
+
+/// Plain decimals only: an optional sign, digits and up to two decimal places.
+impl FromStr for Money {
+    type Err = MoneyError;
+
+    fn from_str(value: &str) -> Result<Money, MoneyError> {
+        let value = value.trim();
+        let (negative, unsigned) = match value.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, value.strip_prefix('+').unwrap_or(value)),
+        };
+        let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+            return Err(MoneyError::Invalid);
+        }
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.len() > 2 {
+            return Err(MoneyError::TooPrecise);
+        }
+
+        let whole = whole.trim_start_matches('0');
+        let whole: i64 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().map_err(|_| MoneyError::TooLarge)?
+        };
+        let fraction: i64 = format!("{fraction:0<2}").parse().unwrap_or_default();
+        let cents = whole
+            .checked_mul(100)
+            .and_then(|cents| cents.checked_add(fraction))
+            .filter(|cents| *cents <= Money::MAX_CENTS)
+            .ok_or(MoneyError::TooLarge)?;
+        Ok(Money(if negative { -cents } else { cents }))
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.to_decimal())
+    }
+}
+
+struct MoneyVisitor;
+
+impl Visitor<'_> for MoneyVisitor {
+    type Value = Money;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an amount with at most two decimal places")
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Money, E> {
+        value
+            .checked_mul(100)
+            .filter(|cents| cents.abs() <= Money::MAX_CENTS)
+            .map(Money)
+            .ok_or_else(|| E::custom(MoneyError::TooLarge))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Money, E> {
+        let value = i64::try_from(value).map_err(|_| E::custom(MoneyError::TooLarge))?;
+        self.visit_i64(value)
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Money, E> {
+        Money::from_decimal(value).map_err(E::custom)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Money, E> {
+        value.parse().map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+/// For amounts written before they were kept in cents, which may carry float noise such as
+/// `89.99999999999999`: rounds to the nearest cent the way the `amounts_in_cents` migration
+/// did, instead of refusing the value.
+pub fn deserialize_rounded<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+    let value = f64::deserialize(deserializer)?;
+    let cents = (value * 100.0).round();
+    if !cents.is_finite() || cents.abs() > Money::MAX_CENTS as f64 {
+        return Err(de::Error::custom(MoneyError::TooLarge));
+    }
+    Ok(Money(cents as i64))
+}
+
+impl From<Money> for libsql::Value {
+    fn from(value: Money) -> Self {
+        libsql::Value::Integer(value.0)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        self.0 -= other.0;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+// Money as shown in chat. The HTTP API always returns raw signed numbers; these helpers
+// are only for text the bot sends.
+
+/// Typographic minus, so an expense does not read like a hyphenated error code.
+const MINUS_SIGN: char = '\u{2212}';
+
+pub const PENDING_CONFIRMATION_NOTE: &str = "(pending your confirmation)";
+
+/// Display symbol for an ISO 4217 code; codes without a well-known symbol are shown as-is.
+pub fn currency_symbol(currency_code: &str) -> &str {
+    match currency_code {
+        "TWD" => "NT$",
+        "USD" => "$",
+        "EUR" => "€",
+        "GBP" => "£",
+        "JPY" => "¥",
+        "CNY" => "CN¥",
+        "HKD" => "HK$",
+        "KRW" => "₩",
+        "SGD" => "S$",
+        "AUD" => "A$",
+        "CAD" => "C$",
+        "NZD" => "NZ$",
+        "INR" => "₹",
+        "THB" => "฿",
+        "VND" => "₫",
+        "PHP" => "₱",
+        other => other,
+    }
+}
+
+fn group_thousands(digits: &str) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// `−NT$180` for expenses, `+NT$85,000` for income. Whole amounts drop the decimals.
+pub fn format_amount(amount: Money, currency_code: &str) -> String {
+    let cents = amount.cents().unsigned_abs();
+    let whole = group_thousands(&(cents / 100).to_string());
+    let number = match cents % 100 {
+        0 => whole,
+        fraction => format!("{whole}.{fraction:02}"),
+    };
+
+    let symbol = currency_symbol(currency_code);
+    let sign = if amount.is_negative() {
+        MINUS_SIGN
+    } else {
+        '+'
+    };
+    if symbol == currency_code {
+        format!("{sign}{symbol} {number}")
+    } else {
+        format!("{sign}{symbol}{number}")
+    }
+}
+
+/// `old → new` when the amount changed, otherwise just the amount.
+pub fn format_amount_change(old_amount: Money, new_amount: Money, currency_code: &str) -> String {
+    let new = format_amount(new_amount, currency_code);
+    let old = format_amount(old_amount, currency_code);
+    if old == new {
+        new
+    } else {
+        format!("{old} → {new}")
+    }
+}
+
+/// One `/recent` line: `date | name | amount`, then the note if there is one, noting split
+/// records still awaiting the user.
+pub fn format_record_line(record: &Record, pending: bool) -> String {
+    let mut line = format!(
+        "{} | {} | {}",
+        record.date,
+        record.name,
+        format_amount(record.amount, &record.currency)
+    );
+    if let Some(note) = &record.note {
+        line.push_str(" | ");
+        line.push_str(&note.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+    if pending {
+        line.push(' ');
+        line.push_str(PENDING_CONFIRMATION_NOTE);
+    }
+    line
+}
@@ -0,0 +1,180 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use libsql::Connection;
+use serde_json::Value;
+use time::{Duration, OffsetDateTime};
+use tower_sessions::Session;
+use uuid::Uuid;
+
+use crate::constants::*;
+use crate::models::{Notification, NotificationListResponse, NotificationsQuery};
+use crate::utils::{
+    db_error_with_context, precise_timestamp, validate_offset, validate_records_limit,
+};
+use crate::{AppState, Db, auth::get_current_user};
+
+// Per-user events other people caused: a split naming the user, a share settled, a
+// friend request and its acceptance. Writers call `notify` inside their own transaction,
+// so a notification exists exactly when the change it describes was committed.
+
+/// Queues a `kind` notification (one of the `NOTIFICATION_KIND_*` constants) for
+/// `user_id`. `payload` holds the ids the event refers to.
+pub async fn notify(
+    conn: &Connection,
+    user_id: &str,
+    kind: &str,
+    payload: Value,
+) -> libsql::Result<()> {
+    conn.execute(
+        "INSERT INTO notifications (id, user_id, kind, payload, created_at) VALUES (?, ?, ?, ?, ?)",
+        (
+            Uuid::new_v4().to_string(),
+            user_id,
+            kind,
+            payload.to_string(),
+            precise_timestamp(OffsetDateTime::now_utc()),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+fn notification_from_row(row: &libsql::Row) -> libsql::Result<Notification> {
+    let payload: String = row.get(2)?;
+    Ok(Notification {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        // A payload that no longer parses still shows the event, without its ids
+        payload: serde_json::from_str(&payload).unwrap_or(Value::Null),
+        created_at: row.get(3)?,
+        read_at: row.get(4)?,
+    })
+}
+
+/// `(total, unread)` notifications of `user_id`.
+pub async fn count_notifications(conn: &Connection, user_id: &str) -> libsql::Result<(u32, u32)> {
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*), COUNT(*) - COUNT(read_at) FROM notifications WHERE user_id = ?",
+            [user_id],
+        )
+        .await?;
+    let Some(row) = rows.next().await? else {
+        return Ok((0, 0));
+    };
+    let total: i64 = row.get(0)?;
+    let unread: i64 = row.get(1)?;
+    Ok((
+        u32::try_from(total).unwrap_or(u32::MAX),
+        u32::try_from(unread).unwrap_or(u32::MAX),
+    ))
+}
+
+/// One page of `user_id`'s notifications, newest first.
+pub async fn list_notifications(
+    conn: &Connection,
+    user_id: &str,
+    limit: u32,
+    offset: u32,
+) -> libsql::Result<Vec<Notification>> {
+    let mut rows = conn
+        .query(
+            "SELECT id, kind, payload, created_at, read_at FROM notifications
+             WHERE user_id = ? ORDER BY created_at DESC, id DESC LIMIT ? OFFSET ?",
+            libsql::params![user_id, limit, offset],
+        )
+        .await?;
+    let mut notifications = Vec::new();
+    while let Some(row) = rows.next().await? {
+        notifications.push(notification_from_row(&row)?);
+    }
+    Ok(notifications)
+}
+
+/// Marks `id` read unless it already is, keeping the first `read_at`. `None` when
+/// `user_id` has no such notification.
+pub async fn mark_read(
+    conn: &Connection,
+    user_id: &str,
+    id: &str,
+    now: OffsetDateTime,
+) -> libsql::Result<Option<Notification>> {
+    conn.execute(
+        "UPDATE notifications SET read_at = ? WHERE id = ? AND user_id = ? AND read_at IS NULL",
+        (precise_timestamp(now), id, user_id),
+    )
+    .await?;
+    let mut rows = conn
+        .query(
+            "SELECT id, kind, payload, created_at, read_at FROM notifications WHERE id = ? AND user_id = ?",
+            (id, user_id),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => Ok(Some(notification_from_row(&row)?)),
+        None => Ok(None),
+    }
+}
+
+/// Deletes notifications read more than `NOTIFICATION_READ_RETENTION_DAYS` before `now`.
+/// Unread ones are kept however old. Returns how many were removed.
+pub async fn purge_read_notifications(db: &Db, now: OffsetDateTime) -> libsql::Result<u64> {
+    let cutoff = precise_timestamp(now - Duration::days(NOTIFICATION_READ_RETENTION_DAYS));
+    let conn = db.write().await;
+    conn.execute(
+        "DELETE FROM notifications WHERE read_at IS NOT NULL AND read_at < ?1",
+        [cutoff],
+    )
+    .await
+}
+
+pub async fn get_notifications(
+    State(app_state): State<AppState>,
+    session: Session,
+    Query(query): Query<NotificationsQuery>,
+) -> Result<(StatusCode, Json<NotificationListResponse>), (StatusCode, String)> {
+    let user = get_current_user(&session).await?;
+    let limit = validate_records_limit(query.limit)?;
+    let offset = validate_offset(query.offset)?;
+
+    let conn = app_state.main_db.read().await;
+    let (total_count, unread_count) = count_notifications(&conn, &user.id)
+        .await
+        .map_err(|_| db_error_with_context("failed to count notifications"))?;
+    let notifications = list_notifications(&conn, &user.id, limit, offset)
+        .await
+        .map_err(|_| db_error_with_context("failed to query notifications"))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(NotificationListResponse {
+            notifications,
+            unread_count,
+            total_count,
+            limit,
+            offset,
+        }),
+    ))
+}
+
+pub async fn mark_notification_read(
+    State(app_state): State<AppState>,
+    session: Session,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<Notification>), (StatusCode, String)> {
+    let user = get_current_user(&session).await?;
+
+    let conn = app_state.main_db.write().await;
+    let notification = mark_read(&conn, &user.id, &id, OffsetDateTime::now_utc())
+        .await
+        .map_err(|_| db_error_with_context("failed to update notification"))?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            NOTIFICATION_NOT_FOUND_MESSAGE.to_string(),
+        ))?;
+
+    Ok((StatusCode::OK, Json(notification)))
+}
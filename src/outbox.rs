@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use time::{Duration, OffsetDateTime, Time, UtcOffset};
+use uuid::Uuid;
+
+use crate::Db;
+use crate::constants::*;
+use crate::crypto;
+use crate::maintenance::status_timestamp;
+use crate::models::{BudgetAlert, SplitNotice};
+use crate::money::{Money, format_amount};
+use crate::settings::fetch_user_settings;
+use crate::utils::{db_error, db_error_with_context, sql_placeholders};
+
+/// Every queued alert for one user that is due, rendered as a single Telegram message.
+pub struct OutboxMessage {
+    pub user_id: String,
+    /// Chats linked to the user; empty when the user has no Telegram link.
+    pub chat_ids: Vec<String>,
+    pub entry_ids: Vec<String>,
+    pub text: String,
+}
+
+/// Parses a local `HH:MM` batch time.
+pub fn parse_batch_time(value: &str) -> Option<Time> {
+    let (hour, minute) = value.split_once(':')?;
+    if hour.len() != 2 || minute.len() != 2 {
+        return None;
+    }
+    Time::from_hms(hour.parse().ok()?, minute.parse().ok()?, 0).ok()
+}
+
+/// The first `batch_time` in the user's local time strictly after `now`, in UTC.
+pub fn next_batch_window(
+    now: OffsetDateTime,
+    utc_offset_minutes: i32,
+    batch_time: Time,
+) -> OffsetDateTime {
+    let offset = UtcOffset::from_whole_seconds(utc_offset_minutes * 60).unwrap_or(UtcOffset::UTC);
+    let local_now = now.to_offset(offset);
+    let mut window = local_now.replace_time(batch_time);
+    if window <= local_now {
+        window += Duration::days(1);
+    }
+    window.to_offset(UtcOffset::UTC)
+}
+
+/// Alerts for a category at or over its limit skip the daily batch.
+pub fn is_urgent(alert: &BudgetAlert) -> bool {
+    alert.spent >= alert.limit
+}
+
+fn percent_of_limit(alert: &BudgetAlert) -> f64 {
+    if alert.limit > 0.0 {
+        (alert.spent / alert.limit * 100.0).round()
+    } else {
+        100.0
+    }
+}
+
+pub fn format_budget_alerts(alerts: &[BudgetAlert]) -> String {
+    let heading = if alerts.len() == 1 {
+        "Budget alert:"
+    } else {
+        "Budget alerts:"
+    };
+    let lines: Vec<String> = alerts
+        .iter()
+        .map(|alert| {
+            let over = if is_urgent(alert) {
+                " (over budget)"
+            } else {
+                ""
+            };
+            format!(
+                "- {}: {:.0}% of budget ({:.2} / {:.2}){}",
+                alert.category_name,
+                percent_of_limit(alert),
+                alert.spent,
+                alert.limit,
+                over
+            )
+        })
+        .collect();
+    format!("{}\n{}", heading, lines.join("\n"))
+}
+
+/// Queues `alert` for `user_id` and returns its `deliver_after` timestamp.
+///
+/// Urgent alerts are due at `now`; the rest wait for the user's next batch window
+/// (`alert_batch_time` in their `utc_offset_minutes`, defaulting to 21:00 UTC+8).
+pub async fn enqueue_budget_alert(
+    conn: &libsql::Connection,
+    user_id: &str,
+    alert: &BudgetAlert,
+    now: OffsetDateTime,
+) -> Result<String, (StatusCode, String)> {
+    let urgent = is_urgent(alert);
+    let deliver_after = if urgent {
+        now
+    } else {
+        let settings = fetch_user_settings(conn, user_id).await?;
+        let batch_time = settings
+            .alert_batch_time
+            .as_deref()
+            .and_then(parse_batch_time)
+            .or_else(|| parse_batch_time(DEFAULT_ALERT_BATCH_TIME))
+            .unwrap_or(Time::MIDNIGHT);
+        next_batch_window(
+            now,
+            settings
+                .utc_offset_minutes
+                .unwrap_or(DEFAULT_UTC_OFFSET_MINUTES),
+            batch_time,
+        )
+    };
+    let deliver_after = status_timestamp(deliver_after);
+
+    let payload = serde_json::to_string(alert)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    conn.execute(
+        "INSERT INTO telegram_outbox (id, user_id, kind, payload, urgent, deliver_after, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        (
+            Uuid::new_v4().to_string(),
+            user_id,
+            OUTBOX_KIND_BUDGET_ALERT,
+            payload,
+            urgent,
+            deliver_after.as_str(),
+            status_timestamp(now),
+        ),
+    )
+    .await
+    .map_err(|_| db_error_with_context("failed to queue budget alert"))?;
+
+    Ok(deliver_after)
+}
+
+/// Groups every undelivered budget alert due at `now` into one message per user.
+pub async fn due_budget_alert_messages(
+    conn: &libsql::Connection,
+    now: OffsetDateTime,
+) -> Result<Vec<OutboxMessage>, (StatusCode, String)> {
+    let mut rows = conn
+        .query(
+            "SELECT id, user_id, payload FROM telegram_outbox \
+             WHERE delivered_at IS NULL AND kind = ? AND deliver_after <= ? \
+             ORDER BY user_id, created_at, id",
+            (OUTBOX_KIND_BUDGET_ALERT, status_timestamp(now)),
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query outbox"))?;
+
+    let mut grouped: Vec<(String, Vec<String>, Vec<BudgetAlert>)> = Vec::new();
+    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        let invalid = |_| db_error_with_context("invalid outbox data");
+        let id: String = row.get(0).map_err(invalid)?;
+        let user_id: String = row.get(1).map_err(invalid)?;
+        let payload: String = row.get(2).map_err(invalid)?;
+        let alert: BudgetAlert = serde_json::from_str(&payload)
+            .map_err(|_| db_error_with_context("invalid outbox payload"))?;
+
+        match grouped.last_mut() {
+            Some((last_user, ids, alerts)) if *last_user == user_id => {
+                ids.push(id);
+                alerts.push(alert);
+            }
+            _ => grouped.push((user_id, vec![id], vec![alert])),
+        }
+    }
+    drop(rows);
+
+    let chat_ids = linked_chat_ids(conn, grouped.iter().map(|(user_id, _, _)| user_id)).await?;
+    Ok(grouped
+        .into_iter()
+        .map(|(user_id, entry_ids, alerts)| OutboxMessage {
+            chat_ids: chat_ids.get(&user_id).cloned().unwrap_or_default(),
+            user_id,
+            entry_ids,
+            text: format_budget_alerts(&alerts),
+        })
+        .collect())
+}
+
+/// The Telegram text for a split notice. `yours` is whether the share belongs to the
+/// recipient rather than to `from_name`.
+pub fn format_split_notice(
+    kind: &str,
+    from_name: &str,
+    description: &str,
+    share: Money,
+    currency_code: &str,
+    yours: bool,
+) -> String {
+    let formatted = format_amount(share.abs(), currency_code);
+    let amount = formatted.trim_start_matches('+');
+    if kind == OUTBOX_KIND_SPLIT_CREATED {
+        format!(
+            "{from_name} added you to '{description}' — you owe {amount}. Reply here to categorize it."
+        )
+    } else if yours {
+        format!("{from_name} marked your share of '{description}' ({amount}) as settled.")
+    } else {
+        format!("{from_name} settled their share of '{description}' ({amount}).")
+    }
+}
+
+/// Queues a `kind` split notice (`OUTBOX_KIND_SPLIT_*`) for `user_id`, due at `now`.
+/// Nothing is queued unless the user has a linked chat and has not turned
+/// `telegram_split_notifications` off. Returns whether a row was written.
+pub async fn enqueue_split_notice(
+    conn: &libsql::Connection,
+    user_id: &str,
+    kind: &str,
+    notice: &SplitNotice,
+    now: OffsetDateTime,
+) -> Result<bool, (StatusCode, String)> {
+    let payload = serde_json::to_string(notice)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let now = status_timestamp(now);
+    let queued = conn
+        .execute(
+            "INSERT INTO telegram_outbox (id, user_id, kind, payload, urgent, deliver_after, created_at) \
+             SELECT ?1, ?2, ?3, ?4, 1, ?5, ?5 \
+             WHERE EXISTS (SELECT 1 FROM telegram_users WHERE user_id = ?2) \
+             AND COALESCE((SELECT telegram_split_notifications FROM user_settings WHERE user_id = ?2), 1) != 0",
+            libsql::params![Uuid::new_v4().to_string(), user_id, kind, payload, now],
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to queue split notice"))?;
+    Ok(queued > 0)
+}
+
+/// Queues `notices` (recipient, notice) once the split change is committed. A failure
+/// is logged and skipped: the split itself has already been saved.
+pub async fn queue_split_notices(db: &Db, kind: &str, notices: &[(String, SplitNotice)]) {
+    if notices.is_empty() {
+        return;
+    }
+    let conn = db.write().await;
+    let now = OffsetDateTime::now_utc();
+    for (user_id, notice) in notices {
+        if let Err((_, message)) = enqueue_split_notice(&conn, user_id, kind, notice, now).await {
+            tracing::warn!(
+                user_id = user_id.as_str(),
+                split_id = notice.split_id.as_str(),
+                "split notice not queued: {message}"
+            );
+        }
+    }
+}
+
+/// Every undelivered split notice due at `now`, one message each. A notice whose record
+/// is gone (the split was cancelled) comes back without chats, so it is dropped.
+pub async fn due_split_notice_messages(
+    conn: &libsql::Connection,
+    now: OffsetDateTime,
+) -> Result<Vec<OutboxMessage>, (StatusCode, String)> {
+    let mut rows = conn
+        .query(
+            "SELECT o.id, o.user_id, o.kind, r.name, r.amount, r.currency, r.owner_user_id, COALESCE(u.name, '') \
+             FROM telegram_outbox o \
+             LEFT JOIN records r ON r.id = json_extract(o.payload, '$.record_id') \
+             LEFT JOIN users u ON u.id = json_extract(o.payload, '$.from_user_id') \
+             WHERE o.delivered_at IS NULL AND o.kind IN (?, ?) AND o.deliver_after <= ? \
+             ORDER BY o.created_at, o.id",
+            (
+                OUTBOX_KIND_SPLIT_CREATED,
+                OUTBOX_KIND_SPLIT_SETTLED,
+                status_timestamp(now),
+            ),
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query outbox"))?;
+
+    let mut notices = Vec::new();
+    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        let invalid = |_| db_error_with_context("invalid outbox data");
+        let id: String = row.get(0).map_err(invalid)?;
+        let user_id: String = row.get(1).map_err(invalid)?;
+        let kind: String = row.get(2).map_err(invalid)?;
+        let text = match row.get::<Option<String>>(3).map_err(invalid)? {
+            Some(name) => {
+                let description = crypto::open_field(name)
+                    .map_err(|_| db_error_with_context("failed to decrypt record name"))?;
+                let share = Money::from_cents(row.get(4).map_err(invalid)?);
+                let currency: String = row.get(5).map_err(invalid)?;
+                let owner: String = row.get(6).map_err(invalid)?;
+                let from_name: String = row.get(7).map_err(invalid)?;
+                Some(format_split_notice(
+                    &kind,
+                    &from_name,
+                    &description,
+                    share,
+                    &currency,
+                    owner == user_id,
+                ))
+            }
+            None => None,
+        };
+        notices.push((id, user_id, text));
+    }
+    drop(rows);
+
+    let chat_ids = linked_chat_ids(conn, notices.iter().map(|(_, user_id, _)| user_id)).await?;
+    Ok(notices
+        .into_iter()
+        .map(|(id, user_id, text)| OutboxMessage {
+            chat_ids: match text {
+                Some(_) => chat_ids.get(&user_id).cloned().unwrap_or_default(),
+                None => Vec::new(),
+            },
+            user_id,
+            entry_ids: vec![id],
+            text: text.unwrap_or_default(),
+        })
+        .collect())
+}
+
+async fn linked_chat_ids(
+    conn: &libsql::Connection,
+    user_ids: impl Iterator<Item = &String>,
+) -> Result<HashMap<String, Vec<String>>, (StatusCode, String)> {
+    let params: Vec<libsql::Value> = user_ids.cloned().map(libsql::Value::from).collect();
+    let mut chat_ids: HashMap<String, Vec<String>> = HashMap::new();
+    if params.is_empty() {
+        return Ok(chat_ids);
+    }
+
+    let sql = format!(
+        "SELECT user_id, chat_id FROM telegram_users WHERE user_id IN ({}) ORDER BY chat_id",
+        sql_placeholders(params.len())
+    );
+    let mut rows = conn
+        .query(&sql, params)
+        .await
+        .map_err(|_| db_error_with_context("failed to query Telegram links"))?;
+    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        let invalid = |_| db_error_with_context("invalid Telegram link data");
+        let user_id: String = row.get(0).map_err(invalid)?;
+        let chat_id: String = row.get(1).map_err(invalid)?;
+        chat_ids.entry(user_id).or_default().push(chat_id);
+    }
+    Ok(chat_ids)
+}
+
+pub async fn mark_delivered(
+    conn: &libsql::Connection,
+    entry_ids: &[String],
+    now: OffsetDateTime,
+) -> Result<u64, (StatusCode, String)> {
+    if entry_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let mut params = vec![libsql::Value::from(status_timestamp(now))];
+    params.extend(entry_ids.iter().cloned().map(libsql::Value::from));
+    conn.execute(
+        &format!(
+            "UPDATE telegram_outbox SET delivered_at = ? WHERE delivered_at IS NULL AND id IN ({})",
+            sql_placeholders(entry_ids.len())
+        ),
+        params,
+    )
+    .await
+    .map_err(|_| db_error_with_context("failed to mark outbox entries delivered"))
+}
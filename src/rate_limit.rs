@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+
+use crate::config::LoginRateLimit;
+use crate::constants::RATE_LIMIT_MAX_TRACKED_KEYS;
+use crate::error::ApiError;
+
+/// In-memory token buckets, one per key. A bucket starts with `max_attempts`
+/// tokens, each attempt spends one, and they refill evenly over `window_secs`.
+/// Lost on restart, like `Metrics`.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: LoginRateLimit,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(LoginRateLimit::default())
+    }
+}
+
+impl RateLimiter {
+    pub fn new(limit: LoginRateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The bucket map. A panic while it was held leaves at worst one stale bucket, so a
+    /// poisoned lock is taken over rather than failing every later login.
+    fn buckets(&self) -> MutexGuard<'_, HashMap<String, Bucket>> {
+        self.buckets.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn capacity(&self) -> f64 {
+        self.limit.max_attempts as f64
+    }
+
+    /// Seconds for one spent attempt to come back.
+    fn refill_interval(&self) -> f64 {
+        self.limit.window_secs as f64 / self.capacity()
+    }
+
+    fn tokens_at(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now
+            .saturating_duration_since(bucket.updated_at)
+            .as_secs_f64();
+        (bucket.tokens + elapsed / self.refill_interval()).min(self.capacity())
+    }
+
+    /// `Err` with the wait until the next attempt when `key` has none left.
+    pub fn check(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let buckets = self.buckets();
+        match buckets.get(key) {
+            Some(bucket) => {
+                let tokens = self.tokens_at(bucket, now);
+                if tokens >= 1.0 {
+                    Ok(())
+                } else {
+                    Err(Duration::from_secs_f64(
+                        (1.0 - tokens) * self.refill_interval(),
+                    ))
+                }
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Spends one attempt of `key`, or returns the wait like `check` when none is left.
+    pub fn consume(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets();
+        if buckets.len() >= RATE_LIMIT_MAX_TRACKED_KEYS && !buckets.contains_key(key) {
+            let capacity = self.capacity();
+            buckets.retain(|_, bucket| self.tokens_at(bucket, now) < capacity);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.capacity(),
+            updated_at: now,
+        });
+        let tokens = self.tokens_at(bucket, now);
+        if tokens < 1.0 {
+            return Err(Duration::from_secs_f64(
+                (1.0 - tokens) * self.refill_interval(),
+            ));
+        }
+        *bucket = Bucket {
+            tokens: tokens - 1.0,
+            updated_at: now,
+        };
+        Ok(())
+    }
+
+    /// Gives `key` its full budget back, e.g. after a successful login.
+    pub fn reset(&self, key: &str) {
+        self.buckets().remove(key);
+    }
+}
+
+/// Whole seconds to send as `Retry-After`, never zero.
+pub fn retry_after_secs(wait: Duration) -> u64 {
+    wait.as_secs_f64().ceil().max(1.0) as u64
+}
+
+/// Key for failed logins of `username` from `client`; usernames match ignoring case.
+pub fn login_key(client: &str, username: &str) -> String {
+    format!("login:{}:{}", client, username.trim().to_lowercase())
+}
+
+/// Key for registrations from `client`.
+pub fn register_key(client: &str) -> String {
+    format!("register:{}", client)
+}
+
+/// The peer address of the connection, when the server was started with connect info.
+/// Routers driven without it (tests) all share the `unknown` client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl std::fmt::Display for ClientIp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(ip) => write!(f, "{}", ip),
+            None => write!(f, "unknown"),
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(ClientIp(
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip()),
+        ))
+    }
+}
+
+/// The 429 for a key that has no attempts left.
+pub fn too_many_attempts(wait: Duration) -> ApiError {
+    let secs = retry_after_secs(wait);
+    ApiError::too_many_requests(
+        format!("Too many attempts; try again in {} seconds", secs),
+        secs,
+    )
+}
@@ -0,0 +1,1432 @@
+use std::cmp::Ordering;
+
+use libsql::Connection;
+use libsql::params::IntoParams;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::constants::CREATED_VIA_BOT_AI;
+use crate::crypto::{self, decode_hex, encode_hex};
+use crate::models::{CategorySummary, Record, RecordDetail, TrashedRecord};
+use crate::money::Money;
+use crate::utils::{precise_timestamp, sql_placeholders, to_db_date};
+
+/// Columns read by `record_from_row`, in order. Tags come back comma-joined.
+pub const RECORD_COLUMNS: &str = "id, name, amount, category_id, date, seq, original_amount, original_currency, note, \
+     (SELECT group_concat(tag) FROM record_tags WHERE record_tags.record_id = records.id), currency";
+
+/// A plain (non-split) record to insert.
+pub struct NewRecord<'a> {
+    pub id: &'a str,
+    pub owner_user_id: &'a str,
+    pub name: &'a str,
+    pub amount: Money,
+    pub currency: &'a str,
+    pub category_id: &'a str,
+    pub date: &'a str,
+    pub created_via: &'a str,
+    pub original_amount: Option<f64>,
+    pub original_currency: Option<&'a str>,
+    pub note: Option<&'a str>,
+    pub tags: &'a [String],
+}
+
+pub struct NewProvenance<'a> {
+    pub record_id: &'a str,
+    pub model: &'a str,
+    pub prompt_hash: &'a str,
+    pub ai_category_confidence: Option<f64>,
+    /// The category the model chose, kept even if the user later recategorizes the record.
+    pub ai_category_id: &'a str,
+    pub created_at: &'a str,
+}
+
+/// `GET /records` filter. `None` matches anything.
+#[derive(Clone, Copy)]
+pub struct RecordFilter<'a> {
+    pub start_date: &'a str,
+    pub end_date: &'a str,
+    pub pending: Option<bool>,
+    pub settle: Option<bool>,
+    /// Forced by `GET /categories/{id}/activity`.
+    pub category_id: Option<&'a str>,
+    /// Case-insensitive substring of the record name (`q`).
+    pub name_contains: Option<&'a str>,
+    pub min_amount: Option<Money>,
+    pub max_amount: Option<Money>,
+    pub tag: Option<&'a str>,
+    /// Order of the page; the count ignores it.
+    pub sort: RecordSort,
+    /// Only rows after this position in `sort` order; the count ignores it.
+    pub after: Option<&'a RecordCursor>,
+}
+
+/// The column `GET /records?sort_by` orders by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordSortField {
+    Date,
+    /// The signed amount, so the largest expense sorts first ascending.
+    Amount,
+    /// Case-insensitive.
+    Name,
+    /// Creation order (`seq`).
+    Created,
+}
+
+/// Ties on the sort column break by `seq` in the same direction, which is unique, so pages
+/// never overlap. The default is the original newest-date-first order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordSort {
+    pub field: RecordSortField,
+    pub descending: bool,
+}
+
+impl Default for RecordSort {
+    fn default() -> Self {
+        Self {
+            field: RecordSortField::Date,
+            descending: true,
+        }
+    }
+}
+
+/// Where the next `GET /records` page starts: the sort value and `seq` of the last row
+/// sent, under the sort it was sent in. Clients get it as an opaque string; only `encode`
+/// and `decode` know the format (hex of JSON), so it may change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordCursor {
+    sort: RecordSort,
+    key: CursorKey,
+    seq: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CursorKey {
+    Date(String),
+    Amount(Money),
+    Name(String),
+    Created,
+}
+
+impl RecordCursor {
+    /// The position right after `record` in `sort` order.
+    pub fn after(record: &Record, sort: RecordSort) -> Self {
+        let key = match sort.field {
+            RecordSortField::Date => CursorKey::Date(record.date.clone()),
+            RecordSortField::Amount => CursorKey::Amount(record.amount),
+            RecordSortField::Name => CursorKey::Name(record.name.clone()),
+            RecordSortField::Created => CursorKey::Created,
+        };
+        Self {
+            sort,
+            key,
+            seq: record.seq,
+        }
+    }
+
+    pub fn sort(&self) -> RecordSort {
+        self.sort
+    }
+
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("a cursor always serializes");
+        encode_hex(&json)
+    }
+
+    /// `None` for anything `encode` did not produce.
+    pub fn decode(value: &str) -> Option<Self> {
+        let cursor: Self = serde_json::from_slice(&decode_hex(value)?).ok()?;
+        let matches_sort = matches!(
+            (&cursor.key, cursor.sort.field),
+            (CursorKey::Date(_), RecordSortField::Date)
+                | (CursorKey::Amount(_), RecordSortField::Amount)
+                | (CursorKey::Name(_), RecordSortField::Name)
+                | (CursorKey::Created, RecordSortField::Created)
+        );
+        matches_sort.then_some(cursor)
+    }
+
+    /// `(date, seq) < (?, ?)` and the like: rows past the cursor, matching `order_by`.
+    fn keyset(&self) -> (String, Vec<libsql::Value>) {
+        let op = if self.sort.descending { "<" } else { ">" };
+        let seq = libsql::Value::from(self.seq);
+        match &self.key {
+            CursorKey::Date(date) => (
+                format!("(date, seq) {op} (?, ?)"),
+                vec![libsql::Value::from(date.clone()), seq],
+            ),
+            CursorKey::Amount(amount) => (
+                format!("(amount, seq) {op} (?, ?)"),
+                vec![libsql::Value::from(*amount), seq],
+            ),
+            CursorKey::Name(name) => (
+                format!("(name COLLATE NOCASE, seq) {op} (?, ?)"),
+                vec![libsql::Value::from(name.clone()), seq],
+            ),
+            CursorKey::Created => (format!("seq {op} ?"), vec![seq]),
+        }
+    }
+
+    /// `keyset` for records sorted in memory (decrypted names).
+    fn is_passed_by(&self, record: &Record) -> bool {
+        let ordering = match &self.key {
+            CursorKey::Date(date) => record.date.as_str().cmp(date.as_str()),
+            CursorKey::Amount(amount) => record.amount.cmp(amount),
+            CursorKey::Name(name) => record.name.to_lowercase().cmp(&name.to_lowercase()),
+            CursorKey::Created => Ordering::Equal,
+        }
+        .then(record.seq.cmp(&self.seq));
+        if self.sort.descending {
+            ordering == Ordering::Less
+        } else {
+            ordering == Ordering::Greater
+        }
+    }
+}
+
+impl RecordSort {
+    fn order_by(self) -> String {
+        let direction = if self.descending { "DESC" } else { "ASC" };
+        match self.field {
+            RecordSortField::Date => format!("date {direction}, seq {direction}"),
+            RecordSortField::Amount => format!("amount {direction}, seq {direction}"),
+            RecordSortField::Name => format!("name COLLATE NOCASE {direction}, seq {direction}"),
+            RecordSortField::Created => format!("seq {direction}"),
+        }
+    }
+}
+
+/// Filter shared by the bot's `list_records` and `sum_records` tools. `None` matches anything.
+#[derive(Clone, Copy)]
+pub struct RecordSearch<'a> {
+    pub start_date: &'a str,
+    pub end_date: &'a str,
+    pub category_id: Option<&'a str>,
+    /// Case-insensitive substring of the record name.
+    pub name_contains: Option<&'a str>,
+    pub min_amount: Option<Money>,
+    pub max_amount: Option<Money>,
+}
+
+/// Signed totals of the records matching a `RecordSearch`.
+#[derive(Debug)]
+pub struct RecordSearchTotals {
+    pub record_count: u32,
+    pub income_count: u32,
+    pub income_total: Money,
+    pub expense_count: u32,
+    /// Sum of negative amounts, so it is zero or negative.
+    pub expense_total: Money,
+}
+
+impl RecordSearchTotals {
+    pub fn net(&self) -> Money {
+        self.income_total + self.expense_total
+    }
+}
+
+/// Batch recategorization filter. An empty `created_via` matches any source.
+pub struct RecategorizeFilter<'a> {
+    pub name_pattern: &'a str,
+    pub created_via: &'a str,
+    pub start_date: &'a str,
+    pub end_date: &'a str,
+    pub target_category_id: &'a str,
+}
+
+/// A record together with the split fields that decide who may settle it.
+pub struct SettlementRecord {
+    pub record: Record,
+    pub settle: bool,
+    pub debtor_user_id: Option<String>,
+    pub creditor_user_id: Option<String>,
+}
+
+/// An AI-created record's original category pick next to its current category.
+pub struct AiCategoryPick {
+    pub date: String,
+    pub ai_category_id: String,
+    pub category_id: Option<String>,
+}
+
+/// A record's state before a batch recategorization, plus its current date.
+pub struct RecategorizeItem {
+    pub record_id: String,
+    pub previous_category_id: Option<String>,
+    pub previous_amount: Money,
+    pub date: String,
+}
+
+/// The comma-joined tags of `RECORD_COLUMNS`, sorted.
+pub fn split_tags(joined: Option<String>) -> Vec<String> {
+    let mut tags: Vec<String> = joined
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect();
+    tags.sort();
+    tags
+}
+
+/// Reads a row selected with `RECORD_COLUMNS` first.
+pub fn record_from_row(row: &libsql::Row) -> Result<Record, libsql::Error> {
+    Ok(Record {
+        id: row.get(0)?,
+        name: crypto::open_field(row.get(1)?)?,
+        amount: Money::from_cents(row.get(2)?),
+        currency: row.get(10)?,
+        category_id: row.get(3)?,
+        date: row.get(4)?,
+        seq: row.get(5)?,
+        original_amount: row.get(6)?,
+        original_currency: row.get(7)?,
+        note: row
+            .get::<Option<String>>(8)?
+            .map(crypto::open_field)
+            .transpose()?,
+        tags: split_tags(row.get(9)?),
+        locked: false,
+        split_progress: None,
+        settled: None,
+    })
+}
+
+async fn query_records(
+    conn: &Connection,
+    sql: &str,
+    params: impl IntoParams,
+) -> Result<Vec<Record>, libsql::Error> {
+    let mut rows = conn.query(sql, params).await?;
+    let mut records = Vec::new();
+    while let Some(row) = rows.next().await? {
+        records.push(record_from_row(&row)?);
+    }
+    Ok(records)
+}
+
+async fn query_record(
+    conn: &Connection,
+    sql: &str,
+    params: impl IntoParams,
+) -> Result<Option<Record>, libsql::Error> {
+    let mut rows = conn.query(sql, params).await?;
+    match rows.next().await? {
+        Some(row) => record_from_row(&row).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// `None` when the category does not exist or belongs to someone else.
+pub async fn category_is_income(
+    conn: &Connection,
+    user_id: &str,
+    category_id: &str,
+) -> Result<Option<bool>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT is_income FROM categories WHERE id = ? AND owner_user_id = ?",
+            (category_id, user_id),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => row.get(0).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// `(is_income, archived)`, or `None` like `category_is_income`.
+pub async fn category_is_income_and_archived(
+    conn: &Connection,
+    user_id: &str,
+    category_id: &str,
+) -> Result<Option<(bool, bool)>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT is_income, archived FROM categories WHERE id = ? AND owner_user_id = ?",
+            (category_id, user_id),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => Ok(Some((row.get(0)?, row.get(1)?))),
+        None => Ok(None),
+    }
+}
+
+pub async fn insert_record(conn: &Connection, record: &NewRecord<'_>) -> Result<(), libsql::Error> {
+    let name = crypto::seal_record_name(conn, record.owner_user_id, record.name).await?;
+    let note = match record.note {
+        Some(note) => Some(crypto::seal_record_name(conn, record.owner_user_id, note).await?),
+        None => None,
+    };
+    let date = to_db_date(record.date)?;
+    conn.execute(
+        "INSERT INTO records (id, owner_user_id, name, amount, currency, category_id, date, created_via, original_amount, original_currency, note, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        libsql::params![
+            record.id,
+            record.owner_user_id,
+            name.as_str(),
+            record.amount,
+            record.currency,
+            record.category_id,
+            date,
+            record.created_via,
+            record.original_amount,
+            record.original_currency,
+            note,
+            precise_timestamp(OffsetDateTime::now_utc()),
+        ],
+    )
+    .await?;
+    replace_tags(conn, record.id, record.tags).await
+}
+
+/// Sets the record's tags to exactly `tags`.
+pub async fn replace_tags(
+    conn: &Connection,
+    record_id: &str,
+    tags: &[String],
+) -> Result<(), libsql::Error> {
+    conn.execute("DELETE FROM record_tags WHERE record_id = ?", [record_id])
+        .await?;
+    for tag in tags {
+        conn.execute(
+            "INSERT INTO record_tags (record_id, tag) VALUES (?, ?)",
+            (record_id, tag.as_str()),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+pub async fn insert_provenance(
+    conn: &Connection,
+    provenance: &NewProvenance<'_>,
+) -> Result<(), libsql::Error> {
+    conn.execute(
+        "INSERT INTO record_provenance (record_id, model, prompt_hash, ai_category_confidence, ai_category_id, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        (
+            provenance.record_id,
+            provenance.model,
+            provenance.prompt_hash,
+            provenance.ai_category_confidence,
+            provenance.ai_category_id,
+            provenance.created_at,
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// `bot_ai` records dated on or after `since` whose provenance names the AI's category pick.
+pub async fn list_ai_category_picks(
+    conn: &Connection,
+    user_id: &str,
+    since: &str,
+) -> Result<Vec<AiCategoryPick>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT r.date, p.ai_category_id, r.category_id FROM records r \
+             JOIN record_provenance p ON p.record_id = r.id \
+             WHERE r.owner_user_id = ? AND r.deleted_at IS NULL AND r.created_via = ? AND r.date >= ? \
+             AND p.ai_category_id IS NOT NULL \
+             ORDER BY r.date ASC, r.seq ASC",
+            (user_id, CREATED_VIA_BOT_AI, since),
+        )
+        .await?;
+    let mut picks = Vec::new();
+    while let Some(row) = rows.next().await? {
+        picks.push(AiCategoryPick {
+            date: row.get(0)?,
+            ai_category_id: row.get(1)?,
+            category_id: row.get(2)?,
+        });
+    }
+    Ok(picks)
+}
+
+/// The creation sequence assigned to `record_id` on insert.
+pub async fn find_seq(conn: &Connection, record_id: &str) -> Result<Option<i64>, libsql::Error> {
+    let mut rows = conn
+        .query("SELECT seq FROM records WHERE id = ?", [record_id])
+        .await?;
+    match rows.next().await? {
+        Some(row) => row.get(0).map(Some),
+        None => Ok(None),
+    }
+}
+
+pub async fn find_record(
+    conn: &Connection,
+    user_id: &str,
+    record_id: &str,
+) -> Result<Option<Record>, libsql::Error> {
+    query_record(
+        conn,
+        &format!("SELECT {RECORD_COLUMNS} FROM records WHERE id = ? AND owner_user_id = ? AND deleted_at IS NULL"),
+        (record_id, user_id),
+    )
+    .await
+}
+
+/// The record with its pending, settlement and split columns.
+pub async fn find_record_detail(
+    conn: &Connection,
+    user_id: &str,
+    record_id: &str,
+) -> Result<Option<RecordDetail>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT {RECORD_COLUMNS}, pending, settle, split_id, debtor_user_id, creditor_user_id \
+                 FROM records WHERE id = ? AND owner_user_id = ? AND deleted_at IS NULL"
+            ),
+            (record_id, user_id),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => Ok(Some(RecordDetail {
+            record: record_from_row(&row)?,
+            pending: row.get(11)?,
+            settle: row.get(12)?,
+            split_id: row.get(13)?,
+            debtor_user_id: row.get(14)?,
+            creditor_user_id: row.get(15)?,
+        })),
+        None => Ok(None),
+    }
+}
+
+/// A record as written to the CSV export.
+pub struct RecordCsvRow {
+    pub record: Record,
+    pub category_name: Option<String>,
+    pub pending: bool,
+    pub settle: bool,
+}
+
+/// Up to `limit` of `user_id`'s records dated `start_date..=end_date` in `(date, id)` order,
+/// starting after the `(date, id)` of the previous page.
+pub async fn list_csv_page(
+    conn: &Connection,
+    user_id: &str,
+    start_date: &str,
+    end_date: &str,
+    after: Option<(&str, &str)>,
+    limit: u32,
+) -> Result<Vec<RecordCsvRow>, libsql::Error> {
+    let (after_date, after_id) = after.unzip();
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT {RECORD_COLUMNS}, pending, settle, \
+                 (SELECT c.name FROM categories c WHERE c.id = records.category_id AND c.owner_user_id = records.owner_user_id) \
+                 FROM records WHERE owner_user_id = ? AND deleted_at IS NULL AND date BETWEEN ? AND ? \
+                 AND (? IS NULL OR date > ? OR (date = ? AND id > ?)) \
+                 ORDER BY date ASC, id ASC LIMIT ?"
+            ),
+            (
+                user_id, start_date, end_date, after_date, after_date, after_date, after_id, limit,
+            ),
+        )
+        .await?;
+    let mut page = Vec::new();
+    while let Some(row) = rows.next().await? {
+        page.push(RecordCsvRow {
+            record: record_from_row(&row)?,
+            pending: row.get(11)?,
+            settle: row.get(12)?,
+            category_name: row.get(13)?,
+        });
+    }
+    Ok(page)
+}
+
+/// The record and its `split_id` (`None` for records that are not part of a split).
+pub async fn find_record_with_split_id(
+    conn: &Connection,
+    user_id: &str,
+    record_id: &str,
+) -> Result<Option<(Record, Option<String>)>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT {RECORD_COLUMNS}, split_id FROM records WHERE id = ? AND owner_user_id = ? AND deleted_at IS NULL"
+            ),
+            (record_id, user_id),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => Ok(Some((record_from_row(&row)?, row.get(11)?))),
+        None => Ok(None),
+    }
+}
+
+/// `Some(split_id)` when the record exists; the inner value is `None` for non-split records.
+pub async fn find_split_id(
+    conn: &Connection,
+    user_id: &str,
+    record_id: &str,
+) -> Result<Option<Option<String>>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT split_id FROM records WHERE id = ? AND owner_user_id = ? AND deleted_at IS NULL",
+            (record_id, user_id),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => row.get(0).map(Some),
+        None => Ok(None),
+    }
+}
+
+pub async fn find_record_by_seq(
+    conn: &Connection,
+    user_id: &str,
+    seq: i64,
+) -> Result<Option<Record>, libsql::Error> {
+    query_record(
+        conn,
+        &format!("SELECT {RECORD_COLUMNS} FROM records WHERE seq = ? AND owner_user_id = ? AND deleted_at IS NULL"),
+        (seq, user_id),
+    )
+    .await
+}
+
+/// Up to `limit` records named exactly `name` (case-insensitive), newest first.
+/// Falls back to matching decrypted names for users with encrypted records.
+pub async fn find_by_exact_name(
+    conn: &Connection,
+    user_id: &str,
+    name: &str,
+    limit: u32,
+) -> Result<Vec<Record>, libsql::Error> {
+    if crypto::records_key_version(conn, user_id).await?.is_some() {
+        let name = name.to_lowercase();
+        let records = query_records(
+            conn,
+            &format!(
+                "SELECT {RECORD_COLUMNS} FROM records WHERE owner_user_id = ? AND deleted_at IS NULL ORDER BY date DESC, seq DESC"
+            ),
+            [user_id],
+        )
+        .await?;
+        return Ok(records
+            .into_iter()
+            .filter(|record| record.name.to_lowercase() == name)
+            .take(limit as usize)
+            .collect());
+    }
+    query_records(
+        conn,
+        &format!(
+            "SELECT {RECORD_COLUMNS} FROM records WHERE LOWER(name) = LOWER(?) AND owner_user_id = ? AND deleted_at IS NULL ORDER BY date DESC, seq DESC LIMIT ?"
+        ),
+        (name, user_id, limit),
+    )
+    .await
+}
+
+/// The user's most recently created records, newest first by `seq`.
+pub async fn list_recent(
+    conn: &Connection,
+    user_id: &str,
+    limit: u32,
+) -> Result<Vec<Record>, libsql::Error> {
+    query_records(
+        conn,
+        &format!(
+            "SELECT {RECORD_COLUMNS} FROM records WHERE owner_user_id = ? AND deleted_at IS NULL ORDER BY seq DESC LIMIT ?"
+        ),
+        (user_id, limit),
+    )
+    .await
+}
+
+/// The `WHERE` clause and its parameters selecting `user_id`'s live records matching `filter`.
+fn record_filter(user_id: &str, filter: &RecordFilter<'_>) -> (String, Vec<libsql::Value>) {
+    let mut sql = String::from("owner_user_id = ? AND deleted_at IS NULL AND date BETWEEN ? AND ?");
+    let mut params = vec![
+        libsql::Value::from(user_id.to_string()),
+        libsql::Value::from(filter.start_date.to_string()),
+        libsql::Value::from(filter.end_date.to_string()),
+    ];
+    if let Some(pending) = filter.pending {
+        sql.push_str(" AND pending = ?");
+        params.push(libsql::Value::from(pending));
+    }
+    if let Some(settle) = filter.settle {
+        sql.push_str(" AND settle = ?");
+        params.push(libsql::Value::from(settle));
+    }
+    if let Some(category_id) = filter.category_id {
+        sql.push_str(" AND category_id = ?");
+        params.push(libsql::Value::from(category_id.to_string()));
+    }
+    if let Some(name_contains) = filter.name_contains {
+        sql.push_str(" AND INSTR(LOWER(name), LOWER(?)) > 0");
+        params.push(libsql::Value::from(name_contains.to_string()));
+    }
+    if let Some(min_amount) = filter.min_amount {
+        sql.push_str(" AND amount >= ?");
+        params.push(libsql::Value::from(min_amount));
+    }
+    if let Some(max_amount) = filter.max_amount {
+        sql.push_str(" AND amount <= ?");
+        params.push(libsql::Value::from(max_amount));
+    }
+    if let Some(tag) = filter.tag {
+        sql.push_str(
+            " AND EXISTS (SELECT 1 FROM record_tags WHERE record_tags.record_id = records.id AND record_tags.tag = ?)",
+        );
+        params.push(libsql::Value::from(tag.to_string()));
+    }
+    (sql, params)
+}
+
+/// `search_decrypted` for `GET /records`: every other match in the range, filtered by the
+/// decrypted name and, for `sort_by=name`, sorted by it. `None` when SQL can do both.
+async fn filter_decrypted(
+    conn: &Connection,
+    user_id: &str,
+    filter: &RecordFilter<'_>,
+) -> Result<Option<Vec<Record>>, libsql::Error> {
+    let by_name = filter.sort.field == RecordSortField::Name;
+    if filter.name_contains.is_none() && !by_name {
+        return Ok(None);
+    }
+    if crypto::records_key_version(conn, user_id).await?.is_none() {
+        return Ok(None);
+    }
+    let unfiltered = RecordFilter {
+        name_contains: None,
+        after: None,
+        ..*filter
+    };
+    let mut records = list_records_sql(conn, user_id, &unfiltered, u32::MAX, 0).await?;
+    if let Some(needle) = filter.name_contains {
+        let needle = needle.to_lowercase();
+        records.retain(|record| record.name.to_lowercase().contains(&needle));
+    }
+    if by_name {
+        records.sort_by_cached_key(|record| (record.name.to_lowercase(), record.seq));
+        if filter.sort.descending {
+            records.reverse();
+        }
+    }
+    if let Some(cursor) = filter.after {
+        records.retain(|record| cursor.is_passed_by(record));
+    }
+    Ok(Some(records))
+}
+
+pub async fn count_records(
+    conn: &Connection,
+    user_id: &str,
+    filter: &RecordFilter<'_>,
+) -> Result<u32, libsql::Error> {
+    let unsorted = RecordFilter {
+        sort: RecordSort::default(),
+        after: None,
+        ..*filter
+    };
+    if let Some(records) = filter_decrypted(conn, user_id, &unsorted).await? {
+        return Ok(records.len() as u32);
+    }
+    let (where_sql, params) = record_filter(user_id, filter);
+    let mut rows = conn
+        .query(
+            &format!("SELECT COUNT(*) FROM records WHERE {where_sql}"),
+            params,
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => row.get(0),
+        None => Ok(0),
+    }
+}
+
+/// One page of records matching `filter` in `filter.sort` order.
+pub async fn list_records(
+    conn: &Connection,
+    user_id: &str,
+    filter: &RecordFilter<'_>,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<Record>, libsql::Error> {
+    if let Some(records) = filter_decrypted(conn, user_id, filter).await? {
+        return Ok(records
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect());
+    }
+    list_records_sql(conn, user_id, filter, limit, offset).await
+}
+
+async fn list_records_sql(
+    conn: &Connection,
+    user_id: &str,
+    filter: &RecordFilter<'_>,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<Record>, libsql::Error> {
+    let (mut where_sql, mut params) = record_filter(user_id, filter);
+    if let Some(cursor) = filter.after {
+        let (keyset_sql, keyset_params) = cursor.keyset();
+        where_sql.push_str(" AND ");
+        where_sql.push_str(&keyset_sql);
+        params.extend(keyset_params);
+    }
+    params.push(libsql::Value::from(limit));
+    params.push(libsql::Value::from(offset));
+    query_records(
+        conn,
+        &format!(
+            "SELECT {RECORD_COLUMNS} FROM records WHERE {where_sql} ORDER BY {} LIMIT ? OFFSET ?",
+            filter.sort.order_by()
+        ),
+        params,
+    )
+    .await
+}
+
+/// Binds `(owner, start, end, category, category, name, name, min, min, max, max)`.
+const RECORD_SEARCH_FILTER: &str = "owner_user_id = ? AND deleted_at IS NULL AND date BETWEEN ? AND ? \
+     AND (? IS NULL OR category_id = ?) \
+     AND (? IS NULL OR INSTR(LOWER(name), LOWER(?)) > 0) \
+     AND (? IS NULL OR amount >= ?) AND (? IS NULL OR amount <= ?)";
+
+/// Encrypted names cannot be matched in SQL, so a name search for a user with encrypted
+/// records loads every other match in the date range and filters the decrypted names here.
+/// `None` when the SQL filter applies. This scans the whole range, so it is slower than the
+/// plaintext path on large accounts.
+async fn search_decrypted(
+    conn: &Connection,
+    user_id: &str,
+    search: &RecordSearch<'_>,
+) -> Result<Option<Vec<Record>>, libsql::Error> {
+    let Some(needle) = search.name_contains else {
+        return Ok(None);
+    };
+    if crypto::records_key_version(conn, user_id).await?.is_none() {
+        return Ok(None);
+    }
+    let unfiltered = RecordSearch {
+        name_contains: None,
+        ..*search
+    };
+    let needle = needle.to_lowercase();
+    let records = list_search_sql(conn, user_id, &unfiltered, u32::MAX, 0).await?;
+    Ok(Some(
+        records
+            .into_iter()
+            .filter(|record| record.name.to_lowercase().contains(&needle))
+            .collect(),
+    ))
+}
+
+pub async fn count_search(
+    conn: &Connection,
+    user_id: &str,
+    search: &RecordSearch<'_>,
+) -> Result<u32, libsql::Error> {
+    if let Some(records) = search_decrypted(conn, user_id, search).await? {
+        return Ok(records.len() as u32);
+    }
+    let mut rows = conn
+        .query(
+            &format!("SELECT COUNT(*) FROM records WHERE {RECORD_SEARCH_FILTER}"),
+            (
+                user_id,
+                search.start_date,
+                search.end_date,
+                search.category_id,
+                search.category_id,
+                search.name_contains,
+                search.name_contains,
+                search.min_amount,
+                search.min_amount,
+                search.max_amount,
+                search.max_amount,
+            ),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => row.get(0),
+        None => Ok(0),
+    }
+}
+
+/// One page of records matching `search`, newest date first, then newest created.
+pub async fn list_search(
+    conn: &Connection,
+    user_id: &str,
+    search: &RecordSearch<'_>,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<Record>, libsql::Error> {
+    if let Some(records) = search_decrypted(conn, user_id, search).await? {
+        return Ok(records
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect());
+    }
+    list_search_sql(conn, user_id, search, limit, offset).await
+}
+
+async fn list_search_sql(
+    conn: &Connection,
+    user_id: &str,
+    search: &RecordSearch<'_>,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<Record>, libsql::Error> {
+    query_records(
+        conn,
+        &format!(
+            "SELECT {RECORD_COLUMNS} FROM records WHERE {RECORD_SEARCH_FILTER} ORDER BY date DESC, seq DESC LIMIT ? OFFSET ?"
+        ),
+        (
+            user_id,
+            search.start_date,
+            search.end_date,
+            search.category_id,
+            search.category_id,
+            search.name_contains,
+            search.name_contains,
+            search.min_amount,
+            search.min_amount,
+            search.max_amount,
+            search.max_amount,
+            limit,
+            offset,
+        ),
+    )
+    .await
+}
+
+pub async fn sum_search(
+    conn: &Connection,
+    user_id: &str,
+    search: &RecordSearch<'_>,
+) -> Result<RecordSearchTotals, libsql::Error> {
+    if let Some(records) = search_decrypted(conn, user_id, search).await? {
+        let mut totals = RecordSearchTotals {
+            record_count: records.len() as u32,
+            income_count: 0,
+            income_total: Money::ZERO,
+            expense_count: 0,
+            expense_total: Money::ZERO,
+        };
+        for record in &records {
+            if record.amount.is_positive() {
+                totals.income_count += 1;
+                totals.income_total += record.amount;
+            } else if record.amount.is_negative() {
+                totals.expense_count += 1;
+                totals.expense_total += record.amount;
+            }
+        }
+        return Ok(totals);
+    }
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT COUNT(*), \
+                 COUNT(CASE WHEN amount > 0 THEN 1 END), COALESCE(SUM(CASE WHEN amount > 0 THEN amount END), 0), \
+                 COUNT(CASE WHEN amount < 0 THEN 1 END), COALESCE(SUM(CASE WHEN amount < 0 THEN amount END), 0) \
+                 FROM records WHERE {RECORD_SEARCH_FILTER}"
+            ),
+            (
+                user_id,
+                search.start_date,
+                search.end_date,
+                search.category_id,
+                search.category_id,
+                search.name_contains,
+                search.name_contains,
+                search.min_amount,
+                search.min_amount,
+                search.max_amount,
+                search.max_amount,
+            ),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => Ok(RecordSearchTotals {
+            record_count: row.get(0)?,
+            income_count: row.get(1)?,
+            income_total: Money::from_cents(row.get(2)?),
+            expense_count: row.get(3)?,
+            expense_total: Money::from_cents(row.get(4)?),
+        }),
+        None => Ok(RecordSearchTotals {
+            record_count: 0,
+            income_count: 0,
+            income_total: Money::ZERO,
+            expense_count: 0,
+            expense_total: Money::ZERO,
+        }),
+    }
+}
+
+/// A category's totals in `summarize_by_category`, with its positive and negative parts.
+pub struct CategoryTotals {
+    pub summary: CategorySummary,
+    pub income: Money,
+    pub expense: Money,
+}
+
+/// Per-category sums of `user_id`'s records dated `start_date..=end_date`, one per currency the
+/// category was spent in, by currency code and then largest first.
+/// Pending split shares count only with `include_pending`. Uncategorized records group
+/// together, counted as income when their sum is positive.
+pub async fn summarize_by_category(
+    conn: &Connection,
+    user_id: &str,
+    start_date: &str,
+    end_date: &str,
+    include_pending: bool,
+) -> Result<Vec<CategoryTotals>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT r.category_id, c.name, COALESCE(c.is_income, SUM(r.amount) > 0), SUM(r.amount), COUNT(*), \
+             COALESCE(SUM(CASE WHEN r.amount > 0 THEN r.amount END), 0), \
+             COALESCE(SUM(CASE WHEN r.amount < 0 THEN r.amount END), 0), r.currency \
+             FROM records r \
+             LEFT JOIN categories c ON c.id = r.category_id AND c.owner_user_id = r.owner_user_id \
+             WHERE r.owner_user_id = ? AND r.deleted_at IS NULL AND r.date >= ? AND r.date <= ? AND (? OR r.pending = 0) \
+             GROUP BY r.category_id, r.currency \
+             ORDER BY r.currency ASC, ABS(SUM(r.amount)) DESC, c.name ASC",
+            (user_id, start_date, end_date, include_pending),
+        )
+        .await?;
+
+    let mut totals = Vec::new();
+    while let Some(row) = rows.next().await? {
+        totals.push(CategoryTotals {
+            summary: CategorySummary {
+                category_id: row.get(0)?,
+                category_name: row.get(1)?,
+                is_income: row.get(2)?,
+                currency: row.get(7)?,
+                total: Money::from_cents(row.get(3)?),
+                record_count: row.get(4)?,
+            },
+            income: Money::from_cents(row.get(5)?),
+            expense: Money::from_cents(row.get(6)?),
+        });
+    }
+    Ok(totals)
+}
+
+pub async fn update_record(
+    conn: &Connection,
+    user_id: &str,
+    record: &Record,
+) -> Result<u64, libsql::Error> {
+    let name = crypto::seal_record_name(conn, user_id, &record.name).await?;
+    let note = match record.note.as_deref() {
+        Some(note) => Some(crypto::seal_record_name(conn, user_id, note).await?),
+        None => None,
+    };
+    let date = to_db_date(&record.date)?;
+    let updated = conn
+        .execute(
+            "UPDATE records SET name = ?, amount = ?, currency = ?, category_id = ?, date = ?, original_amount = ?, original_currency = ?, note = ? WHERE id = ? AND owner_user_id = ? AND deleted_at IS NULL",
+            libsql::params![
+                name.as_str(),
+                record.amount,
+                record.currency.as_str(),
+                record.category_id.as_deref(),
+                date,
+                record.original_amount,
+                record.original_currency.as_deref(),
+                note,
+                record.id.as_str(),
+                user_id,
+            ],
+        )
+        .await?;
+    if updated > 0 {
+        replace_tags(conn, &record.id, &record.tags).await?;
+    }
+    Ok(updated)
+}
+
+/// `None` when the record does not exist for `user_id`.
+pub async fn find_pending_flag(
+    conn: &Connection,
+    user_id: &str,
+    record_id: &str,
+) -> Result<Option<bool>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT pending FROM records WHERE id = ? AND owner_user_id = ? AND deleted_at IS NULL",
+            (record_id, user_id),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => row.get(0).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// The subset of `record_ids` still awaiting the user's confirmation.
+pub async fn list_pending_ids(
+    conn: &Connection,
+    user_id: &str,
+    record_ids: &[String],
+) -> Result<Vec<String>, libsql::Error> {
+    if record_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut params = vec![libsql::Value::from(user_id.to_string())];
+    params.extend(record_ids.iter().cloned().map(libsql::Value::from));
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT id FROM records WHERE owner_user_id = ? AND deleted_at IS NULL AND pending = 1 AND id IN ({})",
+                sql_placeholders(record_ids.len())
+            ),
+            params,
+        )
+        .await?;
+    let mut ids = Vec::new();
+    while let Some(row) = rows.next().await? {
+        ids.push(row.get(0)?);
+    }
+    Ok(ids)
+}
+
+/// Clears `pending` and sets the category; affects no rows if the record is no longer pending.
+pub async fn finalize_pending(
+    conn: &Connection,
+    user_id: &str,
+    record_id: &str,
+    category_id: &str,
+) -> Result<u64, libsql::Error> {
+    conn.execute(
+        "UPDATE records SET pending = ?, category_id = ? WHERE id = ? AND owner_user_id = ? AND deleted_at IS NULL AND pending = ?",
+        (false, category_id, record_id, user_id, true),
+    )
+    .await
+}
+
+pub async fn find_date(
+    conn: &Connection,
+    user_id: &str,
+    record_id: &str,
+) -> Result<Option<String>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT date FROM records WHERE id = ? AND owner_user_id = ? AND deleted_at IS NULL",
+            (record_id, user_id),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => row.get(0).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Moves the record to the trash by stamping `deleted_at`; every other query skips it.
+pub async fn soft_delete_record(
+    conn: &Connection,
+    user_id: &str,
+    record_id: &str,
+    deleted_at: &str,
+) -> Result<u64, libsql::Error> {
+    conn.execute(
+        "UPDATE records SET deleted_at = ? WHERE id = ? AND owner_user_id = ? AND deleted_at IS NULL",
+        (deleted_at, record_id, user_id),
+    )
+    .await
+}
+
+pub async fn count_trash(conn: &Connection, user_id: &str) -> Result<u32, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM records WHERE owner_user_id = ? AND deleted_at IS NOT NULL",
+            [user_id],
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => row.get(0),
+        None => Ok(0),
+    }
+}
+
+/// The user's trashed records, most recently deleted first.
+pub async fn list_trash(
+    conn: &Connection,
+    user_id: &str,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<TrashedRecord>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT {RECORD_COLUMNS}, deleted_at FROM records \
+                 WHERE owner_user_id = ? AND deleted_at IS NOT NULL \
+                 ORDER BY deleted_at DESC, seq DESC LIMIT ? OFFSET ?"
+            ),
+            (user_id, limit, offset),
+        )
+        .await?;
+    let mut records = Vec::new();
+    while let Some(row) = rows.next().await? {
+        records.push(TrashedRecord {
+            record: record_from_row(&row)?,
+            deleted_at: row.get(11)?,
+        });
+    }
+    Ok(records)
+}
+
+/// A trashed record with its split columns and when it was deleted.
+pub async fn find_trashed_detail(
+    conn: &Connection,
+    user_id: &str,
+    record_id: &str,
+) -> Result<Option<(RecordDetail, String)>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT {RECORD_COLUMNS}, pending, settle, split_id, debtor_user_id, creditor_user_id, deleted_at \
+                 FROM records WHERE id = ? AND owner_user_id = ? AND deleted_at IS NOT NULL"
+            ),
+            (record_id, user_id),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => Ok(Some((
+            RecordDetail {
+                record: record_from_row(&row)?,
+                pending: row.get(11)?,
+                settle: row.get(12)?,
+                split_id: row.get(13)?,
+                debtor_user_id: row.get(14)?,
+                creditor_user_id: row.get(15)?,
+            },
+            row.get(16)?,
+        ))),
+        None => Ok(None),
+    }
+}
+
+/// Takes the record back out of the trash.
+pub async fn restore_record(
+    conn: &Connection,
+    user_id: &str,
+    record_id: &str,
+) -> Result<u64, libsql::Error> {
+    conn.execute(
+        "UPDATE records SET deleted_at = NULL WHERE id = ? AND owner_user_id = ? AND deleted_at IS NOT NULL",
+        (record_id, user_id),
+    )
+    .await
+}
+
+/// Permanently deletes records trashed at or before `deleted_before`, with their provenance
+/// and tags.
+pub async fn purge_trash(conn: &Connection, deleted_before: &str) -> Result<u64, libsql::Error> {
+    conn.execute(
+        "DELETE FROM record_provenance WHERE record_id IN \
+         (SELECT id FROM records WHERE deleted_at IS NOT NULL AND deleted_at <= ?)",
+        [deleted_before],
+    )
+    .await?;
+    conn.execute(
+        "DELETE FROM record_tags WHERE record_id IN \
+         (SELECT id FROM records WHERE deleted_at IS NOT NULL AND deleted_at <= ?)",
+        [deleted_before],
+    )
+    .await?;
+    conn.execute(
+        "DELETE FROM records WHERE deleted_at IS NOT NULL AND deleted_at <= ?",
+        [deleted_before],
+    )
+    .await
+}
+
+pub async fn find_settlement_record(
+    conn: &Connection,
+    user_id: &str,
+    record_id: &str,
+) -> Result<Option<SettlementRecord>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            &format!("SELECT {RECORD_COLUMNS}, settle, debtor_user_id, creditor_user_id FROM records WHERE id = ? AND owner_user_id = ? AND deleted_at IS NULL"),
+            (record_id, user_id),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => Ok(Some(SettlementRecord {
+            record: record_from_row(&row)?,
+            settle: row.get(11)?,
+            debtor_user_id: row.get(12)?,
+            creditor_user_id: row.get(13)?,
+        })),
+        None => Ok(None),
+    }
+}
+
+/// Settles the record, stamping `settled_at` for the friend activity feed.
+pub async fn mark_settled(
+    conn: &Connection,
+    user_id: &str,
+    record_id: &str,
+) -> Result<u64, libsql::Error> {
+    conn.execute(
+        "UPDATE records SET settle = ?, settled_at = ? WHERE id = ? AND owner_user_id = ? AND deleted_at IS NULL",
+        (
+            true,
+            precise_timestamp(OffsetDateTime::now_utc()),
+            record_id,
+            user_id,
+        ),
+    )
+    .await
+}
+
+/// Finalized records matching `filter` that are not already in the target category.
+pub async fn find_recategorize_matches(
+    conn: &Connection,
+    user_id: &str,
+    filter: &RecategorizeFilter<'_>,
+) -> Result<Vec<Record>, libsql::Error> {
+    query_records(
+        conn,
+        &format!(
+            "SELECT {RECORD_COLUMNS} FROM records \
+             WHERE owner_user_id = ? AND deleted_at IS NULL \
+             AND pending = 0 \
+             AND INSTR(LOWER(name), LOWER(?)) > 0 \
+             AND (? = '' OR created_via = ?) \
+             AND date BETWEEN ? AND ? \
+             AND (category_id IS NULL OR category_id != ?) \
+             ORDER BY date DESC, seq DESC"
+        ),
+        (
+            user_id,
+            filter.name_pattern,
+            filter.created_via,
+            filter.created_via,
+            filter.start_date,
+            filter.end_date,
+            filter.target_category_id,
+        ),
+    )
+    .await
+}
+
+pub async fn set_category_and_amount(
+    conn: &Connection,
+    user_id: &str,
+    record_id: &str,
+    category_id: Option<&str>,
+    amount: Money,
+) -> Result<u64, libsql::Error> {
+    conn.execute(
+        "UPDATE records SET category_id = ?, amount = ? WHERE id = ? AND owner_user_id = ? AND deleted_at IS NULL",
+        (category_id, amount, record_id, user_id),
+    )
+    .await
+}
+
+pub async fn insert_recategorize_batch(
+    conn: &Connection,
+    undo_token: &str,
+    user_id: &str,
+    created_at: &str,
+) -> Result<(), libsql::Error> {
+    conn.execute(
+        "INSERT INTO recategorize_batches (undo_token, owner_user_id, created_at) VALUES (?, ?, ?)",
+        (undo_token, user_id, created_at),
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn insert_recategorize_item(
+    conn: &Connection,
+    undo_token: &str,
+    previous: &Record,
+) -> Result<(), libsql::Error> {
+    conn.execute(
+        "INSERT INTO recategorize_batch_items (undo_token, record_id, previous_category_id, previous_amount) VALUES (?, ?, ?, ?)",
+        (
+            undo_token,
+            previous.id.as_str(),
+            previous.category_id.as_deref(),
+            previous.amount,
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn recategorize_batch_exists(
+    conn: &Connection,
+    user_id: &str,
+    undo_token: &str,
+) -> Result<bool, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT undo_token FROM recategorize_batches WHERE undo_token = ? AND owner_user_id = ?",
+            (undo_token, user_id),
+        )
+        .await?;
+    Ok(rows.next().await?.is_some())
+}
+
+/// Items of the batch whose records still belong to `user_id` and are not in the trash.
+pub async fn list_recategorize_items(
+    conn: &Connection,
+    user_id: &str,
+    undo_token: &str,
+) -> Result<Vec<RecategorizeItem>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT i.record_id, i.previous_category_id, i.previous_amount, r.date \
+             FROM recategorize_batch_items i \
+             JOIN records r ON r.id = i.record_id AND r.owner_user_id = ? AND r.deleted_at IS NULL \
+             WHERE i.undo_token = ?",
+            (user_id, undo_token),
+        )
+        .await?;
+    let mut items = Vec::new();
+    while let Some(row) = rows.next().await? {
+        items.push(RecategorizeItem {
+            record_id: row.get(0)?,
+            previous_category_id: row.get(1)?,
+            previous_amount: Money::from_cents(row.get(2)?),
+            date: row.get(3)?,
+        });
+    }
+    Ok(items)
+}
+
+/// Deletes the batch and its items; the undo token cannot be used again.
+pub async fn delete_recategorize_batch(
+    conn: &Connection,
+    undo_token: &str,
+) -> Result<(), libsql::Error> {
+    conn.execute(
+        "DELETE FROM recategorize_batch_items WHERE undo_token = ?",
+        [undo_token],
+    )
+    .await?;
+    conn.execute(
+        "DELETE FROM recategorize_batches WHERE undo_token = ?",
+        [undo_token],
+    )
+    .await?;
+    Ok(())
+}
@@ -1,23 +1,54 @@
+use std::collections::HashMap;
+
 use axum::{
     Json,
+    body::Body,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
 };
+use serde_json::json;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 use tower_sessions::Session;
 use uuid::Uuid;
 
 use crate::auth::get_current_user;
+use crate::categories::validate_category_name;
 use crate::constants::*;
+use crate::csv;
+use crate::error::ApiError;
+use crate::idempotency::{IdempotencyScope, idempotency_key_header, run_idempotent};
 use crate::models::{
-    CreateRecordPayload, FinalizePendingPayload, GetRecordsQuery, GetRecordsResponse, Record,
-    UpdateRecordPayload, UpdateSettlePayload,
+    ActivityQuery, CreateRecordPayload, CurrencySummary, FinalizePendingPayload, GetRecordsQuery,
+    GetRecordsResponse, ImportLineError, ImportRecordsQuery, ImportRecordsResponse,
+    RecategorizeBatchPayload, RecategorizeBatchResponse, Record, RecordDetail, RecordProvenance,
+    RecordSearchPage, RecordSummaryQuery, RecordSummaryResponse, RecordTrashResponse,
+    RecordsCsvQuery, ReopenQuery, SplitNotice, SplitProgress, UndoRecategorizeBatchPayload,
+    UndoRecategorizeBatchResponse, UpdateRecordPayload, UpdateSettlePayload, UserSettings,
+};
+use crate::money::{self, Money};
+use crate::notifications;
+use crate::outbox;
+use crate::record_repo::{
+    self, NewProvenance, NewRecord, RecategorizeFilter, RecordCursor, RecordFilter, RecordSearch,
+    RecordSearchTotals, RecordSort, RecordSortField, SettlementRecord,
 };
+use crate::settings::{
+    fetch_user_settings, guard_closed_period, is_in_closed_period, period_closed_message,
+    user_currency_code,
+};
+use crate::split_repo;
 use crate::utils::{
-    db_error, db_error_with_context, validate_category_exists, validate_date, validate_offset,
+    conditional_json, database_busy, db_error_with_context, precise_timestamp,
+    validate_category_exists, validate_date, validate_limit, validate_offset,
     validate_records_limit, validate_string_length,
 };
 use crate::{AppState, TransactionError, with_transaction};
 
+const RECORDS_CREATE_ENDPOINT: &str = "/records";
+const RECORDS_BATCH_ENDPOINT: &str = "/records/batch";
+
 enum FinalizePendingError {
     Transaction(TransactionError),
     Db(&'static str),
@@ -32,37 +63,150 @@ impl From<TransactionError> for FinalizePendingError {
     }
 }
 
-impl From<FinalizePendingError> for (StatusCode, String) {
+impl From<FinalizePendingError> for ApiError {
     fn from(value: FinalizePendingError) -> Self {
         match value {
             FinalizePendingError::Transaction(TransactionError::Begin) => {
-                db_error_with_context("failed to begin transaction")
+                db_error_with_context("failed to begin transaction").into()
             }
             FinalizePendingError::Transaction(TransactionError::Commit) => {
-                db_error_with_context("failed to commit transaction")
-            }
-            FinalizePendingError::Db(ctx) => db_error_with_context(ctx),
-            FinalizePendingError::NotFound => {
-                (StatusCode::NOT_FOUND, "Record not found".to_string())
+                db_error_with_context("failed to commit transaction").into()
             }
-            FinalizePendingError::CategoryNotFound => (
+            FinalizePendingError::Transaction(TransactionError::Busy) => database_busy().into(),
+            FinalizePendingError::Db(ctx) => db_error_with_context(ctx).into(),
+            FinalizePendingError::NotFound => record_not_found("Record not found"),
+            FinalizePendingError::CategoryNotFound => ApiError::new(
                 StatusCode::BAD_REQUEST,
-                "Category does not exist".to_string(),
+                ERROR_CODE_CATEGORY_NOT_FOUND,
+                "Category does not exist",
             ),
-            FinalizePendingError::Conflict => (
+            FinalizePendingError::Conflict => ApiError::new(
                 StatusCode::CONFLICT,
-                "Record already finalized or being finalized".to_string(),
+                ERROR_CODE_RECORD_ALREADY_FINALIZED,
+                "Record already finalized or being finalized",
+            ),
+        }
+    }
+}
+
+enum UpdateSettleError {
+    Transaction(TransactionError),
+    Db(&'static str),
+    NotFound,
+    Forbidden,
+}
+
+impl From<TransactionError> for UpdateSettleError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+impl From<UpdateSettleError> for ApiError {
+    fn from(value: UpdateSettleError) -> Self {
+        match value {
+            UpdateSettleError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction").into()
+            }
+            UpdateSettleError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction").into()
+            }
+            UpdateSettleError::Transaction(TransactionError::Busy) => database_busy().into(),
+            UpdateSettleError::Db(ctx) => db_error_with_context(ctx).into(),
+            UpdateSettleError::NotFound => record_not_found("Record not found"),
+            UpdateSettleError::Forbidden => (
+                StatusCode::FORBIDDEN,
+                "Only the record's owner or the split's creditor can settle it".to_string(),
+            )
+                .into(),
+        }
+    }
+}
+
+enum CreateRecordError {
+    Transaction(TransactionError),
+    Db(&'static str),
+    Rejected((StatusCode, String)),
+}
+
+impl From<TransactionError> for CreateRecordError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+impl From<CreateRecordError> for (StatusCode, String) {
+    fn from(value: CreateRecordError) -> Self {
+        match value {
+            CreateRecordError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction")
+            }
+            CreateRecordError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction")
+            }
+            CreateRecordError::Transaction(TransactionError::Busy) => database_busy(),
+            CreateRecordError::Db(ctx) => db_error_with_context(ctx),
+            CreateRecordError::Rejected(error) => error,
+        }
+    }
+}
+
+enum ImportRecordsError {
+    Transaction(TransactionError),
+    Db(&'static str),
+    Lines(Vec<ImportLineError>),
+}
+
+impl From<TransactionError> for ImportRecordsError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+enum RecategorizeBatchError {
+    Transaction(TransactionError),
+    Db(&'static str),
+    NotFound,
+    Rejected((StatusCode, String)),
+}
+
+impl From<TransactionError> for RecategorizeBatchError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+impl From<RecategorizeBatchError> for ApiError {
+    fn from(value: RecategorizeBatchError) -> Self {
+        match value {
+            RecategorizeBatchError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction").into()
+            }
+            RecategorizeBatchError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction").into()
+            }
+            RecategorizeBatchError::Transaction(TransactionError::Busy) => database_busy().into(),
+            RecategorizeBatchError::Db(ctx) => db_error_with_context(ctx).into(),
+            RecategorizeBatchError::NotFound => ApiError::new(
+                StatusCode::NOT_FOUND,
+                ERROR_CODE_UNDO_TOKEN_NOT_FOUND,
+                "Undo token not found",
             ),
+            RecategorizeBatchError::Rejected(error) => error.into(),
         }
     }
 }
 
+fn record_not_found(message: &str) -> ApiError {
+    ApiError::new(StatusCode::NOT_FOUND, ERROR_CODE_RECORD_NOT_FOUND, message)
+}
+
 pub fn validate_record_name(name: &str) -> Result<(), (StatusCode, String)> {
     validate_string_length(name, "Record name", MAX_RECORD_NAME_LENGTH)
 }
 
-pub fn validate_record_amount(amount: f64) -> Result<(), (StatusCode, String)> {
-    if amount == 0.0 {
+pub fn validate_record_amount(amount: Money) -> Result<(), (StatusCode, String)> {
+    if amount.is_zero() {
         return Err((
             StatusCode::BAD_REQUEST,
             "Record amount cannot be zero".to_string(),
@@ -75,7 +219,121 @@ pub fn validate_category_id(category_id: &str) -> Result<(), (StatusCode, String
     validate_string_length(category_id, "Category ID", MAX_CATEGORY_NAME_LENGTH)
 }
 
-fn normalize_amount_by_category(amount: f64, is_income: bool) -> f64 {
+const ORIGINAL_AMOUNT_UNPAIRED_MESSAGE: &str =
+    "original_amount and original_currency must be provided together";
+
+/// `original_amount` and `original_currency` are all-or-nothing. Returns the pair with the
+/// currency trimmed and upper-cased, or `None` when neither is given.
+pub fn validate_original_amount(
+    original_amount: Option<f64>,
+    original_currency: Option<&str>,
+) -> Result<Option<(f64, String)>, (StatusCode, String)> {
+    let (amount, currency) = match (original_amount, original_currency) {
+        (None, None) => return Ok(None),
+        (Some(amount), Some(currency)) => (amount, currency.trim().to_ascii_uppercase()),
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                ORIGINAL_AMOUNT_UNPAIRED_MESSAGE.to_string(),
+            ));
+        }
+    };
+    if !amount.is_finite() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "original_amount must be a finite number".to_string(),
+        ));
+    }
+    if !ISO_CURRENCY_CODES.contains(&currency.as_str()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "original_currency must be an ISO 4217 currency code".to_string(),
+        ));
+    }
+    Ok(Some((amount, currency)))
+}
+
+/// `currency` trimmed and upper-cased, if it is an ISO 4217 code.
+pub fn normalize_record_currency(currency: &str) -> Result<String, (StatusCode, String)> {
+    let currency = currency.trim().to_ascii_uppercase();
+    if !ISO_CURRENCY_CODES.contains(&currency.as_str()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "currency must be an ISO 4217 currency code".to_string(),
+        ));
+    }
+    Ok(currency)
+}
+
+/// The trimmed note, `None` when blank.
+pub fn normalize_record_note(note: Option<&str>) -> Result<Option<String>, (StatusCode, String)> {
+    let Some(note) = note.map(str::trim).filter(|note| !note.is_empty()) else {
+        return Ok(None);
+    };
+    if note.chars().count() > MAX_RECORD_NOTE_LENGTH {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Record note cannot be longer than {} characters",
+                MAX_RECORD_NOTE_LENGTH
+            ),
+        ));
+    }
+    Ok(Some(note.to_string()))
+}
+
+/// One tag, trimmed and lower-cased: 1 to `MAX_RECORD_TAG_LENGTH` letters, digits, `-` or `_`.
+pub fn normalize_record_tag(tag: &str) -> Result<String, (StatusCode, String)> {
+    let tag = tag.trim().to_lowercase();
+    let length = tag.chars().count();
+    if length == 0
+        || length > MAX_RECORD_TAG_LENGTH
+        || !tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Tags must be 1 to {} letters, digits, '-' or '_'",
+                MAX_RECORD_TAG_LENGTH
+            ),
+        ));
+    }
+    Ok(tag)
+}
+
+/// The record's tags normalized, de-duplicated and sorted; at most `MAX_TAGS_PER_RECORD`.
+pub fn normalize_record_tags(tags: &[String]) -> Result<Vec<String>, (StatusCode, String)> {
+    let mut normalized = tags
+        .iter()
+        .map(|tag| normalize_record_tag(tag))
+        .collect::<Result<Vec<_>, _>>()?;
+    normalized.sort();
+    normalized.dedup();
+    if normalized.len() > MAX_TAGS_PER_RECORD {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("A record can have at most {} tags", MAX_TAGS_PER_RECORD),
+        ));
+    }
+    Ok(normalized)
+}
+
+pub fn validate_created_via(created_via: &str) -> Result<(), (StatusCode, String)> {
+    if !CREATED_VIA_VALUES.contains(&created_via) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "created_via must be one of: {}",
+                CREATED_VIA_VALUES.join(", ")
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn normalize_amount_by_category(amount: Money, is_income: bool) -> Money {
     if is_income {
         amount.abs()
     } else {
@@ -88,114 +346,667 @@ async fn get_category_is_income(
     user_id: &str,
     category_id: &str,
 ) -> Result<bool, (StatusCode, String)> {
-    let mut rows = conn
-        .query(
-            "SELECT is_income FROM categories WHERE id = ? AND owner_user_id = ?",
-            (category_id, user_id),
-        )
+    record_repo::category_is_income(conn, user_id, category_id)
         .await
-        .map_err(|_| db_error_with_context("failed to query category type"))?;
+        .map_err(|_| db_error_with_context("failed to query category type"))?
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                "Category does not exist".to_string(),
+            )
+        })
+}
 
-    if let Some(row) = rows.next().await.map_err(|_| db_error())? {
-        let is_income: bool = row
-            .get(0)
-            .map_err(|_| db_error_with_context("invalid category data"))?;
-        Ok(is_income)
-    } else {
-        Err((
+/// `get_category_is_income` for a record about to be created: archived categories
+/// keep their records but take no new ones.
+pub async fn get_category_for_new_record(
+    conn: &libsql::Connection,
+    user_id: &str,
+    category_id: &str,
+) -> Result<bool, (StatusCode, String)> {
+    match record_repo::category_is_income_and_archived(conn, user_id, category_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to query category type"))?
+    {
+        Some((_, true)) => Err((
+            StatusCode::BAD_REQUEST,
+            CATEGORY_ARCHIVED_MESSAGE.to_string(),
+        )),
+        Some((is_income, false)) => Ok(is_income),
+        None => Err((
             StatusCode::BAD_REQUEST,
             "Category does not exist".to_string(),
-        ))
+        )),
     }
 }
 
 pub fn extract_record_from_row(row: libsql::Row) -> Result<Record, (StatusCode, String)> {
-    let id: String = row
-        .get(0)
-        .map_err(|_| db_error_with_context("invalid record data"))?;
-    let name: String = row
-        .get(1)
-        .map_err(|_| db_error_with_context("invalid record data"))?;
-    let amount: f64 = row
-        .get(2)
-        .map_err(|_| db_error_with_context("invalid record data"))?;
-    let category_id: Option<String> = row
-        .get(3)
-        .map_err(|_| db_error_with_context("invalid record data"))?;
-    let date: String = row
-        .get(4)
-        .map_err(|_| db_error_with_context("invalid record data"))?;
-
-    Ok(Record {
-        id,
-        name,
-        amount,
-        category_id,
-        date,
-    })
+    record_repo::record_from_row(&row).map_err(|_| db_error_with_context("invalid record data"))
 }
 
-pub async fn create_record_for_user(
-    db: &crate::Db,
+pub async fn fetch_record_split_id(
+    conn: &libsql::Connection,
+    user_id: &str,
+    record_id: &str,
+) -> Result<Option<String>, (StatusCode, String)> {
+    record_repo::find_split_id(conn, user_id, record_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to query record split"))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Record not found".to_string()))
+}
+
+/// Sign follows the category, so only the magnitude counts as a change.
+pub fn amount_differs(new_amount: Option<Money>, existing_amount: Money) -> bool {
+    new_amount.is_some_and(|amount| amount.abs() != existing_amount.abs())
+}
+
+/// Split records (the payer's own and every participant's) hold what the initiator says is
+/// owed, so only `name` and `category_id` may change; amount (currency included) or date
+/// edits are refused.
+pub fn guard_split_record_edit(
+    split_id: Option<&str>,
+    amount_changed: bool,
+    date_changed: bool,
+) -> Result<(), (StatusCode, String)> {
+    if split_id.is_some() && (amount_changed || date_changed) {
+        return Err((
+            StatusCode::CONFLICT,
+            SPLIT_RECORD_IMMUTABLE_MESSAGE.to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Re-checks, under the write lock that applies an edit, that the record and target category
+/// resolved earlier still exist. `category` is `(id, name)` of a newly chosen category.
+///
+/// The bot resolves both before taking the lock, so either may be deleted from the web UI in
+/// between; the 409 names what disappeared so the user can pick another target and retry.
+pub async fn guard_edit_references(
+    conn: &libsql::Connection,
     user_id: &str,
+    record: &Record,
+    category: Option<(&str, &str)>,
+) -> Result<(), (StatusCode, String)> {
+    let record_exists = record_repo::find_split_id(conn, user_id, &record.id)
+        .await
+        .map_err(|_| db_error_with_context("failed to query record"))?
+        .is_some();
+    if !record_exists {
+        return Err((
+            StatusCode::CONFLICT,
+            reference_deleted_message("record", &record.name),
+        ));
+    }
+
+    if let Some((category_id, category_name)) = category {
+        let category_exists = record_repo::category_is_income(conn, user_id, category_id)
+            .await
+            .map_err(|_| db_error_with_context("failed to query category"))?
+            .is_some();
+        if !category_exists {
+            return Err((
+                StatusCode::CONFLICT,
+                reference_deleted_message("category", category_name),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn reference_deleted_message(kind: &str, name: &str) -> String {
+    format!("{REFERENCE_DELETED_PREFIX}: {kind} \"{name}\" was deleted")
+}
+
+/// A create payload that passed validation, with its strings trimmed.
+struct PreparedRecord {
+    name: String,
+    amount: Money,
+    /// `None` takes the user's `currency_code` when inserted.
+    currency: Option<String>,
+    category_id: String,
+    date: String,
+    original_amount: Option<f64>,
+    original_currency: Option<String>,
+    note: Option<String>,
+    tags: Vec<String>,
+    created_via: String,
+    provenance: Option<RecordProvenance>,
+}
+
+fn prepare_record(
     payload: CreateRecordPayload,
-) -> Result<Record, (StatusCode, String)> {
+    provenance: Option<RecordProvenance>,
+) -> Result<PreparedRecord, (StatusCode, String)> {
     validate_record_name(&payload.name)?;
     validate_record_amount(payload.amount)?;
+    let currency = payload
+        .currency
+        .as_deref()
+        .map(normalize_record_currency)
+        .transpose()?;
     validate_category_id(&payload.category_id)?;
     validate_date(&payload.date)?;
+    let original = validate_original_amount(
+        payload.original_amount,
+        payload.original_currency.as_deref(),
+    )?;
+    let note = normalize_record_note(payload.note.as_deref())?;
+    let tags = normalize_record_tags(&payload.tags)?;
+    let created_via = provenance
+        .as_ref()
+        .map(|provenance| provenance.created_via.clone())
+        .unwrap_or_else(|| CREATED_VIA_API.to_string());
+    validate_created_via(&created_via)?;
+
+    Ok(PreparedRecord {
+        name: payload.name.trim().to_string(),
+        amount: payload.amount,
+        currency,
+        category_id: payload.category_id.trim().to_string(),
+        date: payload.date.trim().to_string(),
+        original_amount: original.as_ref().map(|(amount, _)| *amount),
+        original_currency: original.map(|(_, currency)| currency),
+        note,
+        tags,
+        created_via,
+        provenance,
+    })
+}
 
-    let category_id = payload.category_id.trim().to_string();
+/// Inserts already validated records, each paired with its category's `is_income`, in one
+/// transaction, and returns them in the same order.
+async fn insert_prepared_records(
+    db: &crate::Db,
+    user_id: &str,
+    records: Vec<(PreparedRecord, bool)>,
+    reopen: bool,
+) -> Result<Vec<Record>, (StatusCode, String)> {
+    let created_at = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    with_transaction(db, move |conn| {
+        let owner_user_id = user_id.to_string();
+        Box::pin(async move {
+            let default_currency = user_currency_code(conn, &owner_user_id)
+                .await
+                .map_err(CreateRecordError::Rejected)?;
+            let mut created = Vec::with_capacity(records.len());
+            for (record, is_income) in records {
+                let record_id = Uuid::new_v4().to_string();
+                let amount = normalize_amount_by_category(record.amount, is_income);
+                let currency = record.currency.unwrap_or_else(|| default_currency.clone());
+                guard_closed_period(
+                    conn,
+                    &owner_user_id,
+                    Some(&record_id),
+                    "create",
+                    &[&record.date],
+                    reopen,
+                )
+                .await
+                .map_err(CreateRecordError::Rejected)?;
+
+                record_repo::insert_record(
+                    conn,
+                    &NewRecord {
+                        id: &record_id,
+                        owner_user_id: &owner_user_id,
+                        name: &record.name,
+                        amount,
+                        currency: &currency,
+                        category_id: &record.category_id,
+                        date: &record.date,
+                        created_via: &record.created_via,
+                        original_amount: record.original_amount,
+                        original_currency: record.original_currency.as_deref(),
+                        note: record.note.as_deref(),
+                        tags: &record.tags,
+                    },
+                )
+                .await
+                .map_err(|_| CreateRecordError::Db("record creation failed"))?;
+
+                if record.created_via == CREATED_VIA_BOT_AI
+                    && let Some(ref provenance) = record.provenance
+                {
+                    record_repo::insert_provenance(
+                        conn,
+                        &NewProvenance {
+                            record_id: &record_id,
+                            model: provenance.model.as_deref().unwrap_or_default(),
+                            prompt_hash: provenance.prompt_hash.as_deref().unwrap_or_default(),
+                            ai_category_confidence: provenance.ai_category_confidence,
+                            ai_category_id: &record.category_id,
+                            created_at: &created_at,
+                        },
+                    )
+                    .await
+                    .map_err(|_| CreateRecordError::Db("record provenance creation failed"))?;
+                }
+
+                let seq = record_repo::find_seq(conn, &record_id)
+                    .await
+                    .map_err(|_| CreateRecordError::Db("failed to load record sequence"))?
+                    .ok_or(CreateRecordError::Db("failed to load record sequence"))?;
+
+                created.push(Record {
+                    id: record_id,
+                    name: record.name,
+                    amount,
+                    currency,
+                    category_id: Some(record.category_id),
+                    date: record.date,
+                    seq,
+                    original_amount: record.original_amount,
+                    original_currency: record.original_currency,
+                    note: record.note,
+                    tags: record.tags,
+                    locked: false,
+                    split_progress: None,
+                    settled: None,
+                });
+            }
+            Ok(created)
+        })
+    })
+    .await
+    .map_err(|e: CreateRecordError| -> (StatusCode, String) { e.into() })
+}
 
-    validate_category_exists(db, user_id, &category_id).await?;
+/// Creates a record owned by `user_id`.
+///
+/// `provenance` records where the record came from; `None` means the HTTP API.
+/// AI-created records (`bot_ai`) also get a `record_provenance` row.
+/// Dates in the user's closed period are rejected unless `reopen` is set.
+pub async fn create_record_for_user(
+    db: &crate::Db,
+    user_id: &str,
+    payload: CreateRecordPayload,
+    provenance: Option<RecordProvenance>,
+    reopen: bool,
+) -> Result<Record, (StatusCode, String)> {
+    let record = prepare_record(payload, provenance)?;
 
     let is_income = {
         let conn = db.read().await;
-        get_category_is_income(&conn, user_id, &category_id).await?
+        get_category_for_new_record(&conn, user_id, &record.category_id).await?
     };
-    let normalized_amount = normalize_amount_by_category(payload.amount, is_income);
 
-    let record_id = Uuid::new_v4().to_string();
+    let mut created =
+        insert_prepared_records(db, user_id, vec![(record, is_income)], reopen).await?;
+    Ok(created.remove(0))
+}
 
-    let conn = db.write().await;
-    conn.execute(
-        "INSERT INTO records (id, owner_user_id, name, amount, category_id, date) VALUES (?, ?, ?, ?, ?, ?)",
-        (
-            record_id.as_str(),
-            user_id,
-            payload.name.trim(),
-            normalized_amount,
-            category_id.as_str(),
-            payload.date.trim(),
-        ),
-    )
-    .await
-    .map_err(|_| db_error_with_context("record creation failed"))?;
+/// Creates up to `MAX_RECORDS_PER_BATCH` records at once, like `create_record_for_user`
+/// for each payload (and its own provenance) but all-or-nothing: every payload is
+/// validated before anything is written, errors name the offending index, and the
+/// inserts share one transaction. Records come back in input order.
+pub async fn create_records_for_user(
+    db: &crate::Db,
+    user_id: &str,
+    payloads: Vec<(CreateRecordPayload, Option<RecordProvenance>)>,
+    reopen: bool,
+) -> Result<Vec<Record>, (StatusCode, String)> {
+    if payloads.is_empty() || payloads.len() > MAX_RECORDS_PER_BATCH {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "A batch must contain between 1 and {} records",
+                MAX_RECORDS_PER_BATCH
+            ),
+        ));
+    }
+    let at_index =
+        |index: usize| move |(status, message)| (status, format!("records[{index}]: {message}"));
 
-    Ok(Record {
-        id: record_id,
-        name: payload.name.trim().to_string(),
-        amount: normalized_amount,
-        category_id: Some(category_id),
-        date: payload.date.trim().to_string(),
+    let mut prepared = Vec::with_capacity(payloads.len());
+    for (index, (payload, provenance)) in payloads.into_iter().enumerate() {
+        prepared.push(prepare_record(payload, provenance).map_err(at_index(index))?);
+    }
+
+    let mut is_income_by_category: HashMap<String, bool> = HashMap::new();
+    let mut records = Vec::with_capacity(prepared.len());
+    {
+        let conn = db.read().await;
+        for (index, record) in prepared.into_iter().enumerate() {
+            let is_income = match is_income_by_category.get(&record.category_id) {
+                Some(is_income) => *is_income,
+                None => {
+                    let is_income =
+                        get_category_for_new_record(&conn, user_id, &record.category_id)
+                            .await
+                            .map_err(at_index(index))?;
+                    is_income_by_category.insert(record.category_id.clone(), is_income);
+                    is_income
+                }
+            };
+            records.push((record, is_income));
+        }
+    }
+
+    insert_prepared_records(db, user_id, records, reopen).await
+}
+
+/// Returns the user's most recently created records, newest first.
+/// Ordered by `seq` alone, so records sharing a date keep their creation order.
+pub async fn list_recent_records(
+    db: &crate::Db,
+    user_id: &str,
+    limit: u32,
+) -> Result<Vec<Record>, (StatusCode, String)> {
+    let conn = db.read().await;
+    record_repo::list_recent(&conn, user_id, limit)
+        .await
+        .map_err(|_| db_error_with_context("failed to query recent records"))
+}
+
+/// `/recent` lines for the bot: formatted in each record's currency, with pending split records noted.
+pub async fn recent_record_lines(
+    db: &crate::Db,
+    user_id: &str,
+    limit: u32,
+) -> Result<Vec<String>, (StatusCode, String)> {
+    let conn = db.read().await;
+    let records = record_repo::list_recent(&conn, user_id, limit)
+        .await
+        .map_err(|_| db_error_with_context("failed to query recent records"))?;
+    let record_ids: Vec<String> = records.iter().map(|record| record.id.clone()).collect();
+    let pending_ids = record_repo::list_pending_ids(&conn, user_id, &record_ids)
+        .await
+        .map_err(|_| db_error_with_context("failed to query pending records"))?;
+
+    Ok(records
+        .iter()
+        .map(|record| money::format_record_line(record, pending_ids.contains(&record.id)))
+        .collect())
+}
+
+fn validate_record_search(search: &RecordSearch<'_>) -> Result<(), (StatusCode, String)> {
+    validate_date(search.start_date)?;
+    validate_date(search.end_date)?;
+    validate_amount_range(search.min_amount, search.max_amount)
+}
+
+fn validate_amount_range(
+    min: Option<Money>,
+    max: Option<Money>,
+) -> Result<(), (StatusCode, String)> {
+    if let (Some(min), Some(max)) = (min, max)
+        && min > max
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "min_amount cannot be greater than max_amount".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// `sort_by` and `order` of `GET /records`, checked against the fixed set of columns so no
+/// query text reaches the `ORDER BY`.
+/// A `next_cursor` from an earlier page of the same sort.
+fn parse_record_cursor(value: &str, sort: RecordSort) -> Result<RecordCursor, ApiError> {
+    let invalid = |message: &str| {
+        ApiError::new(StatusCode::BAD_REQUEST, ERROR_CODE_INVALID_CURSOR, message)
+            .with_details(json!({ "field": "cursor" }))
+    };
+    let cursor = RecordCursor::decode(value).ok_or_else(|| invalid("cursor is not valid"))?;
+    if cursor.sort() != sort {
+        return Err(invalid(
+            "cursor belongs to another sort_by/order; start again without it",
+        ));
+    }
+    Ok(cursor)
+}
+
+fn parse_record_sort(sort_by: Option<&str>, order: Option<&str>) -> Result<RecordSort, ApiError> {
+    let field = match sort_by {
+        None | Some("date") => RecordSortField::Date,
+        Some("amount") => RecordSortField::Amount,
+        Some("name") => RecordSortField::Name,
+        Some("created") => RecordSortField::Created,
+        Some(other) => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ERROR_CODE_INVALID_SORT,
+                format!("sort_by must be date, amount, name or created, not '{other}'"),
+            )
+            .with_details(json!({ "field": "sort_by" })));
+        }
+    };
+    let descending = match order {
+        None => field != RecordSortField::Name,
+        Some("asc") => false,
+        Some("desc") => true,
+        Some(other) => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ERROR_CODE_INVALID_SORT,
+                format!("order must be asc or desc, not '{other}'"),
+            )
+            .with_details(json!({ "field": "order" })));
+        }
+    };
+    Ok(RecordSort { field, descending })
+}
+
+/// The trimmed `q` of `GET /records`; blank means no name filter.
+fn validate_name_query(q: Option<&str>) -> Result<Option<&str>, (StatusCode, String)> {
+    let q = q.map(str::trim).filter(|q| !q.is_empty());
+    if let Some(q) = q {
+        validate_string_length(q, "q", MAX_RECORD_NAME_LENGTH)?;
+    }
+    Ok(q)
+}
+
+/// Records matching `search` for the bot's `list_records` tool, newest first.
+///
+/// `limit` is clamped to `BOT_LIST_RECORDS_MAX` rather than rejected, so an unfiltered query
+/// on a large account stays small; `truncated` tells the caller to narrow the search.
+pub async fn search_records_for_user(
+    db: &crate::Db,
+    user_id: &str,
+    search: &RecordSearch<'_>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<RecordSearchPage, (StatusCode, String)> {
+    validate_record_search(search)?;
+    let limit = validate_limit(
+        limit.map(|limit| limit.min(BOT_LIST_RECORDS_MAX)),
+        BOT_LIST_RECORDS_MAX,
+    )?;
+    let offset = validate_offset(offset)?;
+
+    let conn = db.read().await;
+    let total_count = record_repo::count_search(&conn, user_id, search)
+        .await
+        .map_err(|_| db_error_with_context("failed to count records"))?;
+    let records = record_repo::list_search(&conn, user_id, search, limit, offset)
+        .await
+        .map_err(|_| db_error_with_context("failed to query records"))?;
+
+    Ok(RecordSearchPage {
+        total_count,
+        limit,
+        offset,
+        truncated: offset + (records.len() as u32) < total_count,
+        records,
     })
 }
 
+/// Income and expense totals over every record matching `search`, for the bot's `sum_records` tool.
+pub async fn sum_records_for_user(
+    db: &crate::Db,
+    user_id: &str,
+    search: &RecordSearch<'_>,
+) -> Result<RecordSearchTotals, (StatusCode, String)> {
+    validate_record_search(search)?;
+
+    let conn = db.read().await;
+    record_repo::sum_search(&conn, user_id, search)
+        .await
+        .map_err(|_| db_error_with_context("failed to sum records"))
+}
+
+pub async fn fetch_record_by_seq(
+    db: &crate::Db,
+    user_id: &str,
+    seq: i64,
+) -> Result<Option<Record>, (StatusCode, String)> {
+    let conn = db.read().await;
+    record_repo::find_record_by_seq(&conn, user_id, seq)
+        .await
+        .map_err(|_| db_error_with_context("failed to query record"))
+}
+
+/// `POST /records`. With an `Idempotency-Key` header a retry replays the first response
+/// instead of creating a duplicate.
 pub async fn create_record(
     State(app_state): State<AppState>,
     session: Session,
+    headers: HeaderMap,
+    Query(reopen): Query<ReopenQuery>,
     Json(payload): Json<CreateRecordPayload>,
-) -> Result<(StatusCode, Json<Record>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<Record>), ApiError> {
+    let user = get_current_user(&session).await?;
+    let reopen = reopen.reopen.unwrap_or(false);
+    let Some(key) = idempotency_key_header(&headers)? else {
+        let record =
+            create_record_for_user(&app_state.main_db, &user.id, payload, None, reopen).await?;
+        return Ok((StatusCode::CREATED, Json(record)));
+    };
+
+    let scope = IdempotencyScope {
+        user_id: &user.id,
+        endpoint: RECORDS_CREATE_ENDPOINT,
+        key: &key,
+    };
+    let (status, record) = run_idempotent(
+        &app_state,
+        scope,
+        &payload,
+        StatusCode::CREATED,
+        create_record_for_user(&app_state.main_db, &user.id, payload.clone(), None, reopen),
+    )
+    .await?;
+    Ok((status, Json(record)))
+}
+
+/// `POST /records/batch`: a JSON array of create payloads, created all-or-nothing.
+/// Takes an `Idempotency-Key` header like `POST /records`.
+pub async fn create_records_batch(
+    State(app_state): State<AppState>,
+    session: Session,
+    headers: HeaderMap,
+    Query(reopen): Query<ReopenQuery>,
+    Json(payloads): Json<Vec<CreateRecordPayload>>,
+) -> Result<(StatusCode, Json<Vec<Record>>), ApiError> {
     let user = get_current_user(&session).await?;
-    let record = create_record_for_user(&app_state.main_db, &user.id, payload).await?;
-    Ok((StatusCode::CREATED, Json(record)))
+    let reopen = reopen.reopen.unwrap_or(false);
+    let key = idempotency_key_header(&headers)?;
+    let create = create_records_for_user(
+        &app_state.main_db,
+        &user.id,
+        payloads
+            .iter()
+            .cloned()
+            .map(|payload| (payload, None))
+            .collect(),
+        reopen,
+    );
+    let Some(key) = key else {
+        return Ok((StatusCode::CREATED, Json(create.await?)));
+    };
+
+    let scope = IdempotencyScope {
+        user_id: &user.id,
+        endpoint: RECORDS_BATCH_ENDPOINT,
+        key: &key,
+    };
+    let (status, records) =
+        run_idempotent(&app_state, scope, &payloads, StatusCode::CREATED, create).await?;
+    Ok((status, Json(records)))
+}
+
+/// Fills in split repayment state for a page of `user_id`'s records.
+///
+/// The payer's record of a split gets `split_progress`; a participant's record gets its
+/// own `settled` flag. Runs at most two queries whatever the page size (one for the
+/// page's split memberships, one grouped over the splits the user paid for) and returns
+/// how many it ran.
+pub async fn attach_split_status(
+    conn: &libsql::Connection,
+    user_id: &str,
+    records: &mut [Record],
+) -> Result<usize, (StatusCode, String)> {
+    if records.is_empty() {
+        return Ok(0);
+    }
+
+    let record_ids: Vec<String> = records.iter().map(|record| record.id.clone()).collect();
+    let memberships = split_repo::list_split_memberships(conn, user_id, &record_ids)
+        .await
+        .map_err(|_| db_error_with_context("failed to query split records"))?;
+    if memberships.is_empty() {
+        return Ok(1);
+    }
+
+    let paid_split_ids: Vec<String> = memberships
+        .iter()
+        .filter(|m| m.creditor_user_id.as_deref() == Some(user_id))
+        .map(|m| m.split_id.clone())
+        .collect();
+    let mut queries = 1;
+    let mut progress: HashMap<String, SplitProgress> = HashMap::new();
+    if !paid_split_ids.is_empty() {
+        queries += 1;
+        let rows = split_repo::list_split_progress(conn, user_id, &paid_split_ids)
+            .await
+            .map_err(|_| db_error_with_context("failed to query split progress"))?;
+        for row in rows {
+            progress.insert(
+                row.split_id,
+                SplitProgress {
+                    participants_total: row.participants_total,
+                    participants_settled: row.participants_settled,
+                    amount_outstanding: row.amount_outstanding,
+                },
+            );
+        }
+    }
+
+    let memberships: HashMap<&str, _> = memberships
+        .iter()
+        .map(|m| (m.record_id.as_str(), m))
+        .collect();
+    for record in records.iter_mut() {
+        let Some(membership) = memberships.get(record.id.as_str()) else {
+            continue;
+        };
+        if membership.creditor_user_id.as_deref() == Some(user_id) {
+            record.split_progress = Some(progress.get(&membership.split_id).cloned().unwrap_or(
+                SplitProgress {
+                    participants_total: 0,
+                    participants_settled: 0,
+                    amount_outstanding: Money::ZERO,
+                },
+            ));
+        } else {
+            record.settled = Some(membership.settle);
+        }
+    }
+
+    Ok(queries)
 }
 
 pub async fn get_records(
     State(app_state): State<AppState>,
     session: Session,
+    headers: HeaderMap,
     Query(query): Query<GetRecordsQuery>,
-) -> Result<(StatusCode, Json<GetRecordsResponse>), (StatusCode, String)> {
+) -> Result<Response, ApiError> {
     let user = get_current_user(&session).await?;
     let limit = validate_records_limit(query.limit)?;
     let offset = validate_offset(query.offset)?;
@@ -209,156 +1020,602 @@ pub async fn get_records(
         validate_date(end_date)?;
     }
 
+    let name_contains = validate_name_query(query.q.as_deref())?;
+    validate_amount_range(query.min_amount, query.max_amount)?;
+    let tag = query.tag.as_deref().map(normalize_record_tag).transpose()?;
+    let sort = parse_record_sort(query.sort_by.as_deref(), query.order.as_deref())?;
+    let after = query
+        .cursor
+        .as_deref()
+        .map(|cursor| parse_record_cursor(cursor, sort))
+        .transpose()?;
+    if after.is_some() && offset > 0 {
+        return Err(
+            ApiError::bad_request("Use either cursor or offset, not both")
+                .with_details(json!({ "field": "offset" })),
+        );
+    }
+    // The count is the slow part of a deep page; cursor paging skips it unless asked
+    let include_total = query.include_total.unwrap_or(after.is_none());
+
     let start_date = query.start_date.unwrap_or_else(|| "0000-01-01".to_string());
     let end_date = query.end_date.unwrap_or_else(|| "9999-12-31".to_string());
 
-    let pending = query.pending.map(|p| if p { 1 } else { 0 });
-    let settle = query.settle.map(|s| if s { 1 } else { 0 });
+    let filter = RecordFilter {
+        start_date: &start_date,
+        end_date: &end_date,
+        pending: query.pending,
+        settle: query.settle,
+        category_id: None,
+        name_contains,
+        min_amount: query.min_amount,
+        max_amount: query.max_amount,
+        tag: tag.as_deref(),
+        sort,
+        after: after.as_ref(),
+    };
+    let page = list_records_page(&conn, &user.id, &filter, limit, offset, include_total).await?;
 
-    let total_count: u32 = match (pending, settle) {
-        (None, None) => {
-            let mut count_rows = conn
-                .query(
-                    "SELECT COUNT(*) FROM records WHERE owner_user_id = ? AND date BETWEEN ? AND ?",
-                    (user.id.as_str(), start_date.as_str(), end_date.as_str()),
+    Ok(conditional_json(&headers, page))
+}
+
+/// A CSV row that passed field validation, waiting for its category and period checks.
+struct ImportRow {
+    line: usize,
+    name: String,
+    amount: Money,
+    /// `None` when the file has no `currency` column or leaves it blank.
+    currency: Option<String>,
+    category: String,
+    date: String,
+}
+
+fn import_row(
+    row: &csv::Row,
+    columns: &[usize; 4],
+    currency_column: Option<usize>,
+) -> Result<ImportRow, (StatusCode, String)> {
+    let field = |index: usize| row.fields.get(index).map_or("", |value| value.trim());
+    let [name, amount, category, date] = columns.map(field);
+    let currency = currency_column
+        .map(field)
+        .filter(|currency| !currency.is_empty())
+        .map(normalize_record_currency)
+        .transpose()?;
+
+    validate_record_name(name)?;
+    let amount_value = amount.parse::<Money>().map_err(|error| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid amount: {} ({})", amount, error),
+        )
+    })?;
+    validate_record_amount(amount_value)?;
+    validate_category_name(category)?;
+    validate_date(date)?;
+
+    Ok(ImportRow {
+        line: row.line,
+        name: name.to_string(),
+        amount: amount_value,
+        currency,
+        category: category.to_string(),
+        date: date.to_string(),
+    })
+}
+
+/// Splits `body` into validated rows and per-line errors. Problems with the file as a
+/// whole (no header, a missing column, a broken quote, more than `max_rows` rows) fail
+/// the request instead.
+fn parse_import_rows(
+    body: &str,
+    max_rows: usize,
+) -> Result<(Vec<ImportRow>, Vec<ImportLineError>), (StatusCode, String)> {
+    let mut rows = csv::Rows::new(body);
+    let header = rows
+        .next()
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "CSV file is empty".to_string()))?
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let mut columns = [0; 4];
+    for (column, name) in columns.iter_mut().zip(RECORDS_CSV_IMPORT_COLUMNS) {
+        *column = header
+            .fields
+            .iter()
+            .position(|field| field.trim().eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("CSV header must include the {} column", name),
                 )
+            })?;
+    }
+    let currency_column = header
+        .fields
+        .iter()
+        .position(|field| field.trim().eq_ignore_ascii_case("currency"));
+
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+    for (index, row) in rows.enumerate() {
+        if index == max_rows {
+            return Err((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("CSV import is limited to {} rows", max_rows),
+            ));
+        }
+        let row = row.map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        match import_row(&row, &columns, currency_column) {
+            Ok(row) => parsed.push(row),
+            Err((_, message)) => errors.push(ImportLineError {
+                line: row.line,
+                message,
+            }),
+        }
+    }
+    Ok((parsed, errors))
+}
+
+/// Creates one record per CSV row in a single transaction, so a file with any bad line
+/// leaves nothing behind: the response then lists every failing line and `created` is 0.
+/// Categories match by name, ignoring ASCII case like the unique index; missing ones are
+/// an error unless `create_missing_categories`, which creates them as income or expense
+/// from the sign of the first amount that needs them (negative, as exported, for expense).
+pub async fn import_records_for_user(
+    db: &crate::Db,
+    user_id: &str,
+    body: &str,
+    max_rows: usize,
+    create_missing_categories: bool,
+) -> Result<ImportRecordsResponse, (StatusCode, String)> {
+    let (rows, line_errors) = parse_import_rows(body, max_rows)?;
+    let row_count = rows.len() as u32;
+
+    let result = with_transaction(db, |conn| {
+        let owner_user_id = user_id.to_string();
+        let mut errors = line_errors;
+        Box::pin(async move {
+            let settings = fetch_user_settings(conn, &owner_user_id)
                 .await
-                .map_err(|_| db_error_with_context("failed to count records"))?;
+                .map_err(|_| ImportRecordsError::Db("failed to load settings"))?;
+            let closed_through = settings.closed_through;
+            let default_currency = settings
+                .currency_code
+                .unwrap_or_else(|| DEFAULT_CURRENCY_CODE.to_string());
 
-            if let Some(row) = count_rows.next().await.map_err(|_| db_error())? {
-                row.get(0).map_err(|_| db_error())?
-            } else {
-                0
-            }
-        }
-        (Some(p), None) => {
-            let mut count_rows = conn
+            let mut categories = HashMap::new();
+            let mut category_rows = conn
                 .query(
-                    "SELECT COUNT(*) FROM records WHERE owner_user_id = ? AND date BETWEEN ? AND ? AND pending = ?",
-                    (user.id.as_str(), start_date.as_str(), end_date.as_str(), p),
+                    "SELECT id, name, is_income, archived FROM categories WHERE owner_user_id = ?",
+                    [owner_user_id.as_str()],
                 )
                 .await
-                .map_err(|_| db_error_with_context("failed to count records"))?;
-
-            if let Some(row) = count_rows.next().await.map_err(|_| db_error())? {
-                row.get(0).map_err(|_| db_error())?
-            } else {
-                0
+                .map_err(|_| ImportRecordsError::Db("failed to query categories"))?;
+            while let Some(row) = category_rows
+                .next()
+                .await
+                .map_err(|_| ImportRecordsError::Db("failed to query categories"))?
+            {
+                let invalid = |_| ImportRecordsError::Db("invalid category data");
+                let name: String = row.get(1).map_err(invalid)?;
+                categories.insert(
+                    name.to_ascii_lowercase(),
+                    (
+                        row.get::<String>(0).map_err(invalid)?,
+                        row.get::<bool>(2).map_err(invalid)?,
+                        row.get::<bool>(3).map_err(invalid)?,
+                    ),
+                );
             }
-        }
-        (None, Some(s)) => {
-            let mut count_rows = conn
-                .query(
-                    "SELECT COUNT(*) FROM records WHERE owner_user_id = ? AND date BETWEEN ? AND ? AND settle = ?",
-                    (user.id.as_str(), start_date.as_str(), end_date.as_str(), s),
+
+            let mut created_categories = Vec::new();
+            for row in &rows {
+                if let Some(ref closed_through) = closed_through
+                    && is_in_closed_period(Some(closed_through), &row.date)
+                {
+                    errors.push(ImportLineError {
+                        line: row.line,
+                        message: period_closed_message(closed_through),
+                    });
+                    continue;
+                }
+                let key = row.category.to_ascii_lowercase();
+                let (category_id, is_income) = match categories.get(&key) {
+                    Some((_, _, true)) => {
+                        errors.push(ImportLineError {
+                            line: row.line,
+                            message: format!("{}: {}", CATEGORY_ARCHIVED_MESSAGE, row.category),
+                        });
+                        continue;
+                    }
+                    Some((id, is_income, false)) => (id.clone(), *is_income),
+                    None if create_missing_categories => {
+                        let category = (Uuid::new_v4().to_string(), row.amount.is_positive());
+                        conn.execute(
+                            "INSERT INTO categories (id, owner_user_id, name, is_income) VALUES (?, ?, ?, ?)",
+                            (
+                                category.0.as_str(),
+                                owner_user_id.as_str(),
+                                row.category.as_str(),
+                                category.1,
+                            ),
+                        )
+                        .await
+                        .map_err(|_| ImportRecordsError::Db("failed to create category"))?;
+                        created_categories.push(row.category.clone());
+                        categories.insert(key, (category.0.clone(), category.1, false));
+                        category
+                    }
+                    None => {
+                        errors.push(ImportLineError {
+                            line: row.line,
+                            message: format!("Category not found: {}", row.category),
+                        });
+                        continue;
+                    }
+                };
+                // Once a line has failed nothing will be kept; keep checking the rest
+                if !errors.is_empty() {
+                    continue;
+                }
+
+                record_repo::insert_record(
+                    conn,
+                    &NewRecord {
+                        id: &Uuid::new_v4().to_string(),
+                        owner_user_id: &owner_user_id,
+                        name: &row.name,
+                        amount: normalize_amount_by_category(row.amount, is_income),
+                        currency: row.currency.as_deref().unwrap_or(&default_currency),
+                        category_id: &category_id,
+                        date: &row.date,
+                        created_via: CREATED_VIA_IMPORT,
+                        original_amount: None,
+                        original_currency: None,
+                        note: None,
+                        tags: &[],
+                    },
                 )
                 .await
-                .map_err(|_| db_error_with_context("failed to count records"))?;
+                .map_err(|_| ImportRecordsError::Db("record creation failed"))?;
+            }
 
-            if let Some(row) = count_rows.next().await.map_err(|_| db_error())? {
-                row.get(0).map_err(|_| db_error())?
-            } else {
-                0
+            if !errors.is_empty() {
+                errors.sort_by_key(|error| error.line);
+                return Err(ImportRecordsError::Lines(errors));
             }
+            Ok(created_categories)
+        })
+    })
+    .await;
+
+    match result {
+        Ok(created_categories) => Ok(ImportRecordsResponse {
+            created: row_count,
+            created_categories,
+            errors: Vec::new(),
+        }),
+        Err(ImportRecordsError::Lines(errors)) => Ok(ImportRecordsResponse {
+            created: 0,
+            created_categories: Vec::new(),
+            errors,
+        }),
+        Err(ImportRecordsError::Transaction(TransactionError::Begin)) => {
+            Err(db_error_with_context("failed to begin transaction"))
         }
-        (Some(p), Some(s)) => {
-            let mut count_rows = conn
-                .query(
-                    "SELECT COUNT(*) FROM records WHERE owner_user_id = ? AND date BETWEEN ? AND ? AND pending = ? AND settle = ?",
-                    (user.id.as_str(), start_date.as_str(), end_date.as_str(), p, s),
-                )
-                .await
-                .map_err(|_| db_error_with_context("failed to count records"))?;
+        Err(ImportRecordsError::Transaction(TransactionError::Commit)) => {
+            Err(db_error_with_context("failed to commit transaction"))
+        }
+        Err(ImportRecordsError::Transaction(TransactionError::Busy)) => Err(database_busy()),
+        Err(ImportRecordsError::Db(ctx)) => Err(db_error_with_context(ctx)),
+    }
+}
 
-            if let Some(row) = count_rows.next().await.map_err(|_| db_error())? {
-                row.get(0).map_err(|_| db_error())?
-            } else {
-                0
+/// Per-category and per-currency totals for `GET /records/summary`, summed in SQL so clients
+/// no longer download every record to build their charts.
+pub async fn summarize_records_for_user(
+    conn: &libsql::Connection,
+    user_id: &str,
+    query: RecordSummaryQuery,
+) -> Result<RecordSummaryResponse, (StatusCode, String)> {
+    validate_date(&query.start_date)?;
+    validate_date(&query.end_date)?;
+    if query.start_date > query.end_date {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "start_date cannot be after end_date".to_string(),
+        ));
+    }
+    let include_pending = query.include_pending.unwrap_or(false);
+
+    let totals = record_repo::summarize_by_category(
+        conn,
+        user_id,
+        &query.start_date,
+        &query.end_date,
+        include_pending,
+    )
+    .await
+    .map_err(|_| db_error_with_context("failed to summarize records"))?;
+
+    let currency = user_currency_code(conn, user_id).await?;
+    // Totals arrive ordered by currency, so each currency's categories are adjacent
+    let mut currencies: Vec<CurrencySummary> = Vec::new();
+    for category in &totals {
+        match currencies.last_mut() {
+            Some(last) if last.currency == category.summary.currency => {
+                last.income += category.income;
+                last.expense += category.expense;
             }
+            _ => currencies.push(CurrencySummary {
+                currency: category.summary.currency.clone(),
+                income: category.income,
+                expense: category.expense,
+                net: Money::ZERO,
+            }),
         }
-    };
+    }
+    for summary in &mut currencies {
+        summary.net = summary.income + summary.expense;
+    }
+    let (income, expense) = currencies
+        .iter()
+        .find(|summary| summary.currency == currency)
+        .map_or((Money::ZERO, Money::ZERO), |summary| {
+            (summary.income, summary.expense)
+        });
+
+    Ok(RecordSummaryResponse {
+        start_date: query.start_date,
+        end_date: query.end_date,
+        include_pending,
+        categories: totals
+            .into_iter()
+            .map(|category| category.summary)
+            .collect(),
+        currency,
+        income,
+        expense,
+        net: income + expense,
+        currencies,
+    })
+}
 
-    let mut records = Vec::new();
-    match (pending, settle) {
-        (None, None) => {
-            let mut rows = conn
-                .query(
-                    "SELECT id, name, amount, category_id, date FROM records WHERE owner_user_id = ? AND date BETWEEN ? AND ? ORDER BY date DESC LIMIT ? OFFSET ?",
-                    (user.id.as_str(), start_date.as_str(), end_date.as_str(), limit, offset),
-                )
-                .await
-                .map_err(|_| db_error_with_context("failed to query records"))?;
+pub async fn get_records_summary(
+    State(app_state): State<AppState>,
+    session: Session,
+    Query(query): Query<RecordSummaryQuery>,
+) -> Result<(StatusCode, Json<RecordSummaryResponse>), ApiError> {
+    let user = get_current_user(&session).await?;
+    let conn = app_state.main_db.read().await;
+    let summary = summarize_records_for_user(&conn, &user.id, query).await?;
+    Ok((StatusCode::OK, Json(summary)))
+}
 
-            while let Some(row) = rows.next().await.map_err(|_| db_error())? {
-                records.push(extract_record_from_row(row)?);
-            }
+/// `records-2024-06.csv` for a range inside one month, otherwise named after the bounds given.
+fn csv_filename(start_date: Option<&str>, end_date: Option<&str>) -> String {
+    match (start_date, end_date) {
+        (Some(start), Some(end)) if start.get(..7) == end.get(..7) => {
+            format!("records-{}.csv", &start[..7])
         }
-        (Some(p), None) => {
-            let mut rows = conn
-                .query(
-                    "SELECT id, name, amount, category_id, date FROM records WHERE owner_user_id = ? AND date BETWEEN ? AND ? AND pending = ? ORDER BY date DESC LIMIT ? OFFSET ?",
-                    (user.id.as_str(), start_date.as_str(), end_date.as_str(), p, limit, offset),
-                )
-                .await
-                .map_err(|_| db_error_with_context("failed to query records"))?;
+        (Some(start), Some(end)) => format!("records-{start}_{end}.csv"),
+        (Some(start), None) => format!("records-from-{start}.csv"),
+        (None, Some(end)) => format!("records-until-{end}.csv"),
+        (None, None) => "records.csv".to_string(),
+    }
+}
 
-            while let Some(row) = rows.next().await.map_err(|_| db_error())? {
-                records.push(extract_record_from_row(row)?);
+/// Streams the user's records in the range as CSV, one page per chunk, until a short
+/// page. Stops early when the client disconnects; a database error aborts the body.
+async fn write_records_csv(
+    db: crate::Db,
+    user_id: String,
+    start_date: String,
+    end_date: String,
+    tx: mpsc::Sender<Result<String, std::io::Error>>,
+) {
+    let mut chunk = format!("{RECORDS_CSV_HEADER}\n");
+    let mut after: Option<(String, String)> = None;
+    loop {
+        let page = {
+            let conn = db.read().await;
+            record_repo::list_csv_page(
+                &conn,
+                &user_id,
+                &start_date,
+                &end_date,
+                after
+                    .as_ref()
+                    .map(|(date, id)| (date.as_str(), id.as_str())),
+                RECORDS_CSV_PAGE_SIZE,
+            )
+            .await
+        };
+        let page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                let _ = tx.send(Err(std::io::Error::other(e))).await;
+                return;
             }
+        };
+
+        for row in &page {
+            chunk.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{}\n",
+                csv::escape_field(&row.record.id),
+                csv::escape_field(&row.record.name),
+                row.record.amount,
+                row.record.currency,
+                csv::escape_field(row.category_name.as_deref().unwrap_or_default()),
+                row.record.date,
+                row.pending,
+                row.settle,
+                csv::escape_field(row.record.note.as_deref().unwrap_or_default()),
+                row.record.tags.join(" "),
+            ));
         }
-        (None, Some(s)) => {
-            let mut rows = conn
-                .query(
-                    "SELECT id, name, amount, category_id, date FROM records WHERE owner_user_id = ? AND date BETWEEN ? AND ? AND settle = ? ORDER BY date DESC LIMIT ? OFFSET ?",
-                    (user.id.as_str(), start_date.as_str(), end_date.as_str(), s, limit, offset),
-                )
-                .await
-                .map_err(|_| db_error_with_context("failed to query records"))?;
-
-            while let Some(row) = rows.next().await.map_err(|_| db_error())? {
-                records.push(extract_record_from_row(row)?);
+        if tx.send(Ok(std::mem::take(&mut chunk))).await.is_err() {
+            return;
+        }
+        match page.last() {
+            Some(last) if page.len() == RECORDS_CSV_PAGE_SIZE as usize => {
+                after = Some((last.record.date.clone(), last.record.id.clone()));
             }
+            _ => return,
         }
-        (Some(p), Some(s)) => {
-            let mut rows = conn
-                .query(
-                    "SELECT id, name, amount, category_id, date FROM records WHERE owner_user_id = ? AND date BETWEEN ? AND ? AND pending = ? AND settle = ? ORDER BY date DESC LIMIT ? OFFSET ?",
-                    (user.id.as_str(), start_date.as_str(), end_date.as_str(), p, s, limit, offset),
-                )
-                .await
-                .map_err(|_| db_error_with_context("failed to query records"))?;
+    }
+}
+
+/// `GET /records/export`: the caller's records as a CSV download, oldest first. The body
+/// is written page by page from a background task rather than built in memory.
+pub async fn export_records_csv(
+    State(app_state): State<AppState>,
+    session: Session,
+    Query(query): Query<RecordsCsvQuery>,
+) -> Result<Response, ApiError> {
+    let user = get_current_user(&session).await?;
+    if let Some(ref start_date) = query.start_date {
+        validate_date(start_date)?;
+    }
+    if let Some(ref end_date) = query.end_date {
+        validate_date(end_date)?;
+    }
+
+    let filename = csv_filename(query.start_date.as_deref(), query.end_date.as_deref());
+    let start_date = query.start_date.unwrap_or_else(|| "0000-01-01".to_string());
+    let end_date = query.end_date.unwrap_or_else(|| "9999-12-31".to_string());
+
+    let (tx, rx) = mpsc::channel(2);
+    tokio::spawn(write_records_csv(
+        app_state.main_db.clone(),
+        user.id,
+        start_date,
+        end_date,
+        tx,
+    ));
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response())
+}
+
+/// One page of `filter` with lock flags and split status attached, the total count when
+/// `include_total`, and a cursor for the next page if there is one.
+/// Backs `GET /records` and `GET /categories/{id}/activity`.
+pub async fn list_records_page(
+    conn: &libsql::Connection,
+    user_id: &str,
+    filter: &RecordFilter<'_>,
+    limit: u32,
+    offset: u32,
+    include_total: bool,
+) -> Result<GetRecordsResponse, (StatusCode, String)> {
+    let settings = fetch_user_settings(conn, user_id).await?;
+    list_records_page_with_settings(
+        conn,
+        user_id,
+        &settings,
+        filter,
+        limit,
+        offset,
+        include_total,
+    )
+    .await
+}
+
+/// `list_records_page` for a caller that already loaded the user's settings.
+pub async fn list_records_page_with_settings(
+    conn: &libsql::Connection,
+    user_id: &str,
+    settings: &UserSettings,
+    filter: &RecordFilter<'_>,
+    limit: u32,
+    offset: u32,
+    include_total: bool,
+) -> Result<GetRecordsResponse, (StatusCode, String)> {
+    let total_count = if include_total {
+        let count = record_repo::count_records(conn, user_id, filter)
+            .await
+            .map_err(|_| db_error_with_context("failed to count records"))?;
+        Some(count)
+    } else {
+        None
+    };
+    // One row past the page tells whether another page follows
+    let mut records = record_repo::list_records(conn, user_id, filter, limit + 1, offset)
+        .await
+        .map_err(|_| db_error_with_context("failed to query records"))?;
+    let next_cursor = if records.len() > limit as usize {
+        records.truncate(limit as usize);
+        records
+            .last()
+            .map(|last| RecordCursor::after(last, filter.sort).encode())
+    } else {
+        None
+    };
+
+    for record in &mut records {
+        record.locked = is_in_closed_period(settings.closed_through.as_deref(), &record.date);
+    }
+    attach_split_status(conn, user_id, &mut records).await?;
+
+    Ok(GetRecordsResponse {
+        records,
+        total_count,
+        next_cursor,
+    })
+}
+
+/// One of the user's records with the same `locked` and split status as in `GET /records`.
+pub async fn get_record(
+    State(app_state): State<AppState>,
+    session: Session,
+    Path(record_id): Path<String>,
+) -> Result<(StatusCode, Json<RecordDetail>), ApiError> {
+    let user = get_current_user(&session).await?;
+    let conn = app_state.main_db.read().await;
 
-            while let Some(row) = rows.next().await.map_err(|_| db_error())? {
-                records.push(extract_record_from_row(row)?);
-            }
-        }
-    }
+    let mut detail = record_repo::find_record_detail(&conn, &user.id, &record_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to query record"))?
+        .ok_or_else(|| record_not_found("Record not found"))?;
 
-    Ok((
-        StatusCode::OK,
-        Json(GetRecordsResponse {
-            records,
-            total_count,
-        }),
-    ))
+    let settings = fetch_user_settings(&conn, &user.id).await?;
+    detail.record.locked =
+        is_in_closed_period(settings.closed_through.as_deref(), &detail.record.date);
+    attach_split_status(&conn, &user.id, std::slice::from_mut(&mut detail.record)).await?;
+
+    Ok((StatusCode::OK, Json(detail)))
 }
 
 pub async fn update_record(
     State(app_state): State<AppState>,
     session: Session,
     Path(record_id): Path<String>,
+    Query(reopen): Query<ReopenQuery>,
     Json(payload): Json<UpdateRecordPayload>,
-) -> Result<(StatusCode, Json<Record>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<Record>), ApiError> {
     let user = get_current_user(&session).await?;
 
     if payload.name.is_none()
         && payload.amount.is_none()
+        && payload.currency.is_none()
         && payload.category_id.is_none()
         && payload.date.is_none()
+        && payload.original_amount.is_none()
+        && payload.original_currency.is_none()
+        && payload.note.is_none()
+        && payload.tags.is_none()
     {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "At least one field must be provided for update".to_string(),
+        return Err(ApiError::bad_request(
+            "At least one field must be provided for update",
         ));
     }
 
@@ -370,6 +1627,12 @@ pub async fn update_record(
         validate_record_amount(amount)?;
     }
 
+    let currency = payload
+        .currency
+        .as_deref()
+        .map(normalize_record_currency)
+        .transpose()?;
+
     if let Some(ref category_id) = payload.category_id {
         validate_category_id(category_id)?;
     }
@@ -378,6 +1641,29 @@ pub async fn update_record(
         validate_date(date)?;
     }
 
+    // Both set or both cleared; changing only one side is rejected as unpaired.
+    let original = match (&payload.original_amount, &payload.original_currency) {
+        (None, None) => None,
+        (Some(None), Some(None)) => Some(None),
+        (Some(amount), Some(currency)) => {
+            Some(validate_original_amount(*amount, currency.as_deref())?)
+        }
+        _ => {
+            return Err(ApiError::bad_request(ORIGINAL_AMOUNT_UNPAIRED_MESSAGE));
+        }
+    };
+
+    let note = payload
+        .note
+        .as_ref()
+        .map(|note| normalize_record_note(note.as_deref()))
+        .transpose()?;
+    let tags = payload
+        .tags
+        .as_deref()
+        .map(normalize_record_tags)
+        .transpose()?;
+
     let db = &app_state.main_db;
 
     if let Some(ref category_id) = payload.category_id {
@@ -386,19 +1672,23 @@ pub async fn update_record(
 
     let conn = db.write().await;
 
-    let mut existing_rows = conn
-        .query(
-            "SELECT id, name, amount, category_id, date FROM records WHERE id = ? AND owner_user_id = ?",
-            (record_id.as_str(), user.id.as_str()),
-        )
-        .await
-        .map_err(|_| db_error_with_context("failed to query existing record"))?;
-
-    let existing_record = if let Some(row) = existing_rows.next().await.map_err(|_| db_error())? {
-        extract_record_from_row(row)?
-    } else {
-        return Err((StatusCode::NOT_FOUND, "Record not found".to_string()));
-    };
+    let (existing_record, split_id) =
+        record_repo::find_record_with_split_id(&conn, &user.id, &record_id)
+            .await
+            .map_err(|_| db_error_with_context("failed to query existing record"))?
+            .ok_or_else(|| record_not_found("Record not found"))?;
+
+    guard_split_record_edit(
+        split_id.as_deref(),
+        amount_differs(payload.amount, existing_record.amount)
+            || currency
+                .as_deref()
+                .is_some_and(|currency| currency != existing_record.currency),
+        payload
+            .date
+            .as_deref()
+            .is_some_and(|date| date != existing_record.date),
+    )?;
 
     let updated_name = payload.name.as_deref().unwrap_or(&existing_record.name);
     let updated_category_id = payload
@@ -410,46 +1700,56 @@ pub async fn update_record(
             let is_income = get_category_is_income(&conn, &user.id, category_id).await?;
             normalize_amount_by_category(amount, is_income)
         } else {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                "Cannot update amount without a category".to_string(),
+            return Err(ApiError::bad_request(
+                "Cannot update amount without a category",
             ));
         }
     } else {
         existing_record.amount
     };
-    let updated_date = payload.date.unwrap_or(existing_record.date);
-
-    let affected_rows = conn
-        .execute(
-            "UPDATE records SET name = ?, amount = ?, category_id = ?, date = ? WHERE id = ? AND owner_user_id = ?",
-            (
-                updated_name,
-                updated_amount,
-                updated_category_id.as_deref(),
-                updated_date.as_str(),
-                record_id.as_str(),
-                user.id.as_str(),
-            ),
-        )
-        .await
-        .map_err(|_| db_error_with_context("failed to update record"))?;
-
-    if affected_rows == 0 {
-        return Err((
-            StatusCode::NOT_FOUND,
-            "Record not found or no changes made".to_string(),
-        ));
-    }
+    let updated_date = payload.date.unwrap_or_else(|| existing_record.date.clone());
+
+    guard_closed_period(
+        &conn,
+        &user.id,
+        Some(&record_id),
+        "update",
+        &[&existing_record.date, &updated_date],
+        reopen.reopen.unwrap_or(false),
+    )
+    .await?;
 
     let updated_record = Record {
         id: record_id,
         name: updated_name.to_string(),
         amount: updated_amount,
+        currency: currency.unwrap_or_else(|| existing_record.currency.clone()),
         category_id: updated_category_id,
         date: updated_date,
+        seq: existing_record.seq,
+        original_amount: match original {
+            Some(ref original) => original.as_ref().map(|(amount, _)| *amount),
+            None => existing_record.original_amount,
+        },
+        original_currency: match original {
+            Some(original) => original.map(|(_, currency)| currency),
+            None => existing_record.original_currency,
+        },
+        note: note.unwrap_or(existing_record.note),
+        tags: tags.unwrap_or(existing_record.tags),
+        locked: false,
+        split_progress: None,
+        settled: None,
     };
 
+    let affected_rows = record_repo::update_record(&conn, &user.id, &updated_record)
+        .await
+        .map_err(|_| db_error_with_context("failed to update record"))?;
+
+    if affected_rows == 0 {
+        return Err(record_not_found("Record not found or no changes made"));
+    }
+
     Ok((StatusCode::OK, Json(updated_record)))
 }
 
@@ -457,30 +1757,37 @@ pub async fn finalize_pending_record(
     State(app_state): State<AppState>,
     session: Session,
     Json(payload): Json<FinalizePendingPayload>,
-) -> Result<(StatusCode, Json<Record>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<Record>), ApiError> {
     let user = get_current_user(&session).await?;
     validate_category_id(&payload.category_id)?;
     validate_string_length(&payload.record_id, "Record ID", MAX_RECORD_NAME_LENGTH)?;
 
-    let db = &app_state.main_db;
-    let category_id = payload.category_id.trim().to_string();
-    let record_id = payload.record_id.trim().to_string();
+    let record = finalize_pending_for_user(
+        &app_state.main_db,
+        &user.id,
+        payload.record_id.trim(),
+        payload.category_id.trim(),
+    )
+    .await?;
 
-    let record = with_transaction(db, |conn| {
-        let category_id = category_id.clone();
-        let record_id = record_id.clone();
-        let owner_user_id = user.id.clone();
-        Box::pin(async move {
-            let mut category_rows = conn
-                .query(
-                    "SELECT id FROM categories WHERE id = ? AND owner_user_id = ?",
-                    (category_id.as_str(), owner_user_id.as_str()),
-                )
-                .await
-                .map_err(|_| FinalizePendingError::Db("failed to validate category"))?;
+    Ok((StatusCode::OK, Json(record)))
+}
 
-            if category_rows
-                .next()
+/// Confirms one of the user's pending records under `category_id`. The conditional
+/// UPDATE decides who wins: a record that is no longer pending when it runs, even if
+/// another caller finalized it a moment earlier, is 409 `record_already_finalized`.
+pub async fn finalize_pending_for_user(
+    db: &crate::Db,
+    user_id: &str,
+    record_id: &str,
+    category_id: &str,
+) -> Result<Record, ApiError> {
+    with_transaction(db, |conn| {
+        let category_id = category_id.to_string();
+        let record_id = record_id.to_string();
+        let owner_user_id = user_id.to_string();
+        Box::pin(async move {
+            if record_repo::category_is_income(conn, &owner_user_id, &category_id)
                 .await
                 .map_err(|_| FinalizePendingError::Db("failed to validate category"))?
                 .is_none()
@@ -488,204 +1795,613 @@ pub async fn finalize_pending_record(
                 return Err(FinalizePendingError::CategoryNotFound);
             }
 
-            let mut existing_rows = conn
-                .query(
-                    "SELECT pending FROM records WHERE id = ? AND owner_user_id = ?",
-                    (record_id.as_str(), owner_user_id.as_str()),
-                )
-                .await
-                .map_err(|_| FinalizePendingError::Db("failed to query pending record"))?;
-
-            let pending: bool = if let Some(row) = existing_rows
-                .next()
-                .await
-                .map_err(|_| FinalizePendingError::Db("failed to query pending record"))?
-            {
-                row.get(0)
-                    .map_err(|_| FinalizePendingError::Db("invalid pending record data"))?
-            } else {
-                return Err(FinalizePendingError::NotFound);
-            };
-
-            if !pending {
-                return Err(FinalizePendingError::Conflict);
-            }
-
-            let affected_rows = conn
-                .execute(
-                    "UPDATE records SET pending = ?, category_id = ? WHERE id = ? AND owner_user_id = ? AND pending = ?",
-                    (
-                        false,
-                        category_id.as_str(),
-                        record_id.as_str(),
-                        owner_user_id.as_str(),
-                        true,
-                    ),
-                )
-                .await
-                .map_err(|_| FinalizePendingError::Db("failed to finalize pending record"))?;
+            let affected_rows =
+                record_repo::finalize_pending(conn, &owner_user_id, &record_id, &category_id)
+                    .await
+                    .map_err(|_| FinalizePendingError::Db("failed to finalize pending record"))?;
 
+            // Nothing updated: tell a missing record apart from one already finalized
             if affected_rows == 0 {
-                return Err(FinalizePendingError::Conflict);
+                let exists = record_repo::find_pending_flag(conn, &owner_user_id, &record_id)
+                    .await
+                    .map_err(|_| FinalizePendingError::Db("failed to query pending record"))?
+                    .is_some();
+                return Err(if exists {
+                    FinalizePendingError::Conflict
+                } else {
+                    FinalizePendingError::NotFound
+                });
             }
 
-            let mut updated_rows = conn
-                .query(
-                    "SELECT id, name, amount, category_id, date FROM records WHERE id = ? AND owner_user_id = ?",
-                    (record_id.as_str(), owner_user_id.as_str()),
-                )
-                .await
-                .map_err(|_| FinalizePendingError::Db("failed to load finalized record"))?;
-
-            let row = updated_rows
-                .next()
+            record_repo::find_record(conn, &owner_user_id, &record_id)
                 .await
                 .map_err(|_| FinalizePendingError::Db("failed to load finalized record"))?
-                .ok_or(FinalizePendingError::NotFound)?;
-
-            let finalized_category_id: Option<String> = row
-                .get(3)
-                .map_err(|_| FinalizePendingError::Db("invalid finalized record data"))?;
-
-            let record = Record {
-                id: row
-                    .get(0)
-                    .map_err(|_| FinalizePendingError::Db("invalid finalized record data"))?,
-                name: row
-                    .get(1)
-                    .map_err(|_| FinalizePendingError::Db("invalid finalized record data"))?,
-                amount: row
-                    .get(2)
-                    .map_err(|_| FinalizePendingError::Db("invalid finalized record data"))?,
-                category_id: finalized_category_id,
-                date: row
-                    .get(4)
-                    .map_err(|_| FinalizePendingError::Db("invalid finalized record data"))?,
-            };
-
-            Ok(record)
+                .ok_or(FinalizePendingError::NotFound)
         })
     })
     .await
-    .map_err(|e: FinalizePendingError| -> (StatusCode, String) { e.into() })?;
-
-    Ok((StatusCode::OK, Json(record)))
+    .map_err(|e: FinalizePendingError| -> ApiError { e.into() })
 }
 
 pub async fn delete_record(
     State(app_state): State<AppState>,
     session: Session,
     Path(record_id): Path<String>,
-) -> Result<StatusCode, (StatusCode, String)> {
+    Query(reopen): Query<ReopenQuery>,
+) -> Result<StatusCode, ApiError> {
     let user = get_current_user(&session).await?;
-    let conn = app_state.main_db.write().await;
+    delete_record_for_user(
+        &app_state.main_db,
+        &user.id,
+        &record_id,
+        reopen.reopen.unwrap_or(false),
+    )
+    .await?;
 
-    let affected_rows = conn
-        .execute(
-            "DELETE FROM records WHERE id = ? AND owner_user_id = ?",
-            (record_id.as_str(), user.id.as_str()),
-        )
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Moves one of the user's records to the trash; shared by `DELETE /records/{id}` and
+/// the bot's confirmed deletes. Records in a closed period need `reopen`.
+pub async fn delete_record_for_user(
+    db: &crate::Db,
+    user_id: &str,
+    record_id: &str,
+    reopen: bool,
+) -> Result<(), ApiError> {
+    let conn = db.write().await;
+
+    let existing_date = record_repo::find_date(&conn, user_id, record_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to query existing record"))?
+        .ok_or_else(|| record_not_found("Record not found"))?;
+
+    guard_closed_period(
+        &conn,
+        user_id,
+        Some(record_id),
+        "delete",
+        &[&existing_date],
+        reopen,
+    )
+    .await?;
+
+    let deleted_at = precise_timestamp(time::OffsetDateTime::now_utc());
+    let affected_rows = record_repo::soft_delete_record(&conn, user_id, record_id, &deleted_at)
         .await
         .map_err(|_| db_error_with_context("failed to delete record"))?;
 
     if affected_rows == 0 {
-        return Err((StatusCode::NOT_FOUND, "Record not found".to_string()));
+        return Err(record_not_found("Record not found"));
     }
 
-    Ok(StatusCode::NO_CONTENT)
+    Ok(())
+}
+
+/// `GET /records/trash`: the caller's deleted records, most recently deleted first.
+/// The purge job removes them `RECORD_TRASH_RETENTION_DAYS` after deletion.
+pub async fn get_records_trash(
+    State(app_state): State<AppState>,
+    session: Session,
+    Query(query): Query<ActivityQuery>,
+) -> Result<Json<RecordTrashResponse>, ApiError> {
+    let user = get_current_user(&session).await?;
+    let limit = validate_records_limit(query.limit)?;
+    let offset = validate_offset(query.offset)?;
+
+    let conn = app_state.main_db.read().await;
+    let total_count = record_repo::count_trash(&conn, &user.id)
+        .await
+        .map_err(|_| db_error_with_context("failed to count deleted records"))?;
+    let records = record_repo::list_trash(&conn, &user.id, limit, offset)
+        .await
+        .map_err(|_| db_error_with_context("failed to query deleted records"))?;
+
+    Ok(Json(RecordTrashResponse {
+        records,
+        total_count,
+        retention_days: RECORD_TRASH_RETENTION_DAYS,
+    }))
+}
+
+/// Refuses to restore a split record whose split moved on while it was in the trash:
+/// a participant's share once the payer's own record is gone, and an unsettled share
+/// once the two users have settled up since it was deleted (the settle-up skipped it).
+async fn guard_split_restore(
+    conn: &libsql::Connection,
+    detail: &RecordDetail,
+    deleted_at: &str,
+) -> Result<(), (StatusCode, String)> {
+    let (Some(split_id), Some(debtor), Some(creditor)) = (
+        detail.split_id.as_deref(),
+        detail.debtor_user_id.as_deref(),
+        detail.creditor_user_id.as_deref(),
+    ) else {
+        return Ok(());
+    };
+    if debtor == creditor {
+        return Ok(());
+    }
+
+    let payer_live = split_repo::payer_record_live(conn, split_id, creditor)
+        .await
+        .map_err(|_| db_error_with_context("failed to query split"))?;
+    if !payer_live {
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "{SPLIT_RECORD_UNRESTORABLE_PREFIX}: the payer's record of this split was deleted"
+            ),
+        ));
+    }
+
+    if !detail.settle
+        && split_repo::settled_between_since(conn, debtor, creditor, deleted_at)
+            .await
+            .map_err(|_| db_error_with_context("failed to query settlements"))?
+    {
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "{SPLIT_RECORD_UNRESTORABLE_PREFIX}: you settled up with this friend after the record was deleted"
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// `POST /records/{id}/restore`: takes a record out of the trash. Closed periods apply
+/// as for any other change; a record whose category was deleted meanwhile, or a split
+/// record `guard_split_restore` refuses, stays in the trash with a 409.
+pub async fn restore_record(
+    State(app_state): State<AppState>,
+    session: Session,
+    Path(record_id): Path<String>,
+    Query(reopen): Query<ReopenQuery>,
+) -> Result<Json<Record>, ApiError> {
+    let user = get_current_user(&session).await?;
+    let conn = app_state.main_db.write().await;
+
+    let (detail, deleted_at) = record_repo::find_trashed_detail(&conn, &user.id, &record_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to query deleted record"))?
+        .ok_or_else(|| record_not_found("Deleted record not found"))?;
+
+    if let Some(ref category_id) = detail.record.category_id
+        && record_repo::category_is_income(&conn, &user.id, category_id)
+            .await
+            .map_err(|_| db_error_with_context("failed to query category"))?
+            .is_none()
+    {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            ERROR_CODE_CONFLICT,
+            "Cannot restore record: its category was deleted",
+        ));
+    }
+    guard_split_restore(&conn, &detail, &deleted_at).await?;
+
+    guard_closed_period(
+        &conn,
+        &user.id,
+        Some(&record_id),
+        "restore",
+        &[&detail.record.date],
+        reopen.reopen.unwrap_or(false),
+    )
+    .await?;
+
+    let affected_rows = record_repo::restore_record(&conn, &user.id, &record_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to restore record"))?;
+    if affected_rows == 0 {
+        return Err(record_not_found("Deleted record not found"));
+    }
+
+    Ok(Json(detail.record))
 }
 
+/// Settles a record owned by the caller, or a participant's share of a split the caller
+/// paid for. Other members of the split get 403; anyone else 404, so record ids of
+/// unrelated users are not confirmed. Settling twice is a no-op.
 pub async fn update_settle(
     State(app_state): State<AppState>,
     session: Session,
     Path(record_id): Path<String>,
     Json(_payload): Json<UpdateSettlePayload>,
-) -> Result<(StatusCode, Json<Record>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<Record>), ApiError> {
     let current_user = get_current_user(&session).await?;
     let user_id = current_user.id.clone();
     let db = &app_state.main_db;
 
-    let record = with_transaction(db, |conn| {
+    let (record, notice) = with_transaction(db, |conn| {
         let record_id = record_id.clone();
         let user_id = user_id.clone();
-        let owner_user_id = user_id.clone();
         Box::pin(async move {
-            let mut rows = conn
-                .query(
-                    "SELECT id, name, amount, category_id, date, settle, debtor_user_id, creditor_user_id FROM records WHERE id = ? AND owner_user_id = ?",
-                    (record_id.as_str(), owner_user_id.as_str()),
+            let owner_user_id = settlement_owner(conn, &user_id, &record_id).await?;
+            let SettlementRecord {
+                record,
+                settle,
+                creditor_user_id,
+                ..
+            } = record_repo::find_settlement_record(conn, &owner_user_id, &record_id)
+                .await
+                .map_err(|_| UpdateSettleError::Db("failed to query record"))?
+                .ok_or(UpdateSettleError::NotFound)?;
+
+            if settle {
+                return Ok((record, None));
+            }
+
+            record_repo::mark_settled(conn, &owner_user_id, &record_id)
+                .await
+                .map_err(|_| UpdateSettleError::Db("failed to update settlement status"))?;
+
+            // A settled split share tells the other side: the participant when the
+            // initiator settles it, the initiator when the participant does
+            let other_party = if owner_user_id != user_id {
+                Some(owner_user_id.as_str())
+            } else {
+                creditor_user_id
+                    .as_deref()
+                    .filter(|creditor| *creditor != user_id)
+            };
+            let split_id = match other_party {
+                Some(_) => split_repo::find_record_split_id(conn, &record_id)
+                    .await
+                    .map_err(|_| UpdateSettleError::Db("failed to query record"))?,
+                None => None,
+            };
+            let mut notice = None;
+            if let (Some(other_party), Some(split_id)) = (other_party, split_id) {
+                notifications::notify(
+                    conn,
+                    other_party,
+                    NOTIFICATION_KIND_SPLIT_SETTLED,
+                    json!({
+                        "split_id": split_id,
+                        "record_id": record_id,
+                        "settled_by_user_id": user_id,
+                    }),
                 )
                 .await
-                .map_err(|_| TransactionError::Begin)?;
+                .map_err(|_| UpdateSettleError::Db("failed to queue notification"))?;
+                notice = Some((
+                    other_party.to_string(),
+                    SplitNotice {
+                        split_id,
+                        record_id: record_id.clone(),
+                        from_user_id: user_id.clone(),
+                    },
+                ));
+            }
 
-            let row = rows
-                .next()
+            let record = record_repo::find_record(conn, &owner_user_id, &record_id)
                 .await
-                .map_err(|_| TransactionError::Begin)?
-                .ok_or(TransactionError::Begin)?;
+                .map_err(|_| UpdateSettleError::Db("failed to query record"))?
+                .ok_or(UpdateSettleError::NotFound)?;
+            Ok::<_, UpdateSettleError>((record, notice))
+        })
+    })
+    .await?;
+
+    let notices: Vec<_> = notice.into_iter().collect();
+    outbox::queue_split_notices(db, OUTBOX_KIND_SPLIT_SETTLED, &notices).await;
+    Ok((StatusCode::OK, Json(record)))
+}
+
+/// Whose record `user_id` is settling: their own, or a share of a split they are the
+/// creditor of.
+async fn settlement_owner(
+    conn: &libsql::Connection,
+    user_id: &str,
+    record_id: &str,
+) -> Result<String, UpdateSettleError> {
+    if record_repo::find_settlement_record(conn, user_id, record_id)
+        .await
+        .map_err(|_| UpdateSettleError::Db("failed to query record"))?
+        .is_some()
+    {
+        return Ok(user_id.to_string());
+    }
+
+    let split_id = split_repo::find_record_split_id(conn, record_id)
+        .await
+        .map_err(|_| UpdateSettleError::Db("failed to query record"))?
+        .ok_or(UpdateSettleError::NotFound)?;
+    let shares = split_repo::list_split_shares(conn, &[split_id])
+        .await
+        .map_err(|_| UpdateSettleError::Db("failed to query split records"))?;
+    let share = shares
+        .iter()
+        .find(|share| share.record_id == record_id)
+        .ok_or(UpdateSettleError::NotFound)?;
+
+    if share.creditor_user_id.as_deref() == Some(user_id) {
+        Ok(share.owner_user_id.clone())
+    } else if shares
+        .iter()
+        .any(|share| share.owner_user_id == user_id && !share.deleted)
+    {
+        Err(UpdateSettleError::Forbidden)
+    } else {
+        Err(UpdateSettleError::NotFound)
+    }
+}
+
+async fn query_recategorize_matches(
+    conn: &libsql::Connection,
+    user_id: &str,
+    name_pattern: &str,
+    created_via: &str,
+    start_date: &str,
+    end_date: &str,
+    target_category_id: &str,
+) -> Result<Vec<Record>, (StatusCode, String)> {
+    record_repo::find_recategorize_matches(
+        conn,
+        user_id,
+        &RecategorizeFilter {
+            name_pattern,
+            created_via,
+            start_date,
+            end_date,
+            target_category_id,
+        },
+    )
+    .await
+    .map_err(|_| db_error_with_context("failed to query matching records"))
+}
+
+/// Moves every record matching the filter into `target_category_id`.
+///
+/// Defaults to a dry run that only previews the matches. Applying returns an
+/// undo token that restores each record's previous category and amount.
+pub async fn recategorize_batch(
+    State(app_state): State<AppState>,
+    session: Session,
+    Query(reopen): Query<ReopenQuery>,
+    Json(payload): Json<RecategorizeBatchPayload>,
+) -> Result<(StatusCode, Json<RecategorizeBatchResponse>), ApiError> {
+    let user = get_current_user(&session).await?;
+    validate_string_length(
+        &payload.name_pattern,
+        "Name pattern",
+        MAX_RECORD_NAME_LENGTH,
+    )?;
+    validate_category_id(&payload.target_category_id)?;
+
+    if let Some(ref created_via) = payload.created_via {
+        validate_created_via(created_via)?;
+    }
+    if let Some(ref start_date) = payload.start_date {
+        validate_date(start_date)?;
+    }
+    if let Some(ref end_date) = payload.end_date {
+        validate_date(end_date)?;
+    }
+
+    let db = &app_state.main_db;
+    let dry_run = payload.dry_run.unwrap_or(true);
+    let name_pattern = payload.name_pattern.trim().to_string();
+    let created_via = payload.created_via.unwrap_or_default();
+    let start_date = payload
+        .start_date
+        .unwrap_or_else(|| "0000-01-01".to_string());
+    let end_date = payload.end_date.unwrap_or_else(|| "9999-12-31".to_string());
+    let target_category_id = payload.target_category_id.trim().to_string();
+
+    validate_category_exists(db, &user.id, &target_category_id).await?;
+
+    if dry_run {
+        let conn = db.read().await;
+        let mut records = query_recategorize_matches(
+            &conn,
+            &user.id,
+            &name_pattern,
+            &created_via,
+            &start_date,
+            &end_date,
+            &target_category_id,
+        )
+        .await?;
+
+        let settings = fetch_user_settings(&conn, &user.id).await?;
+        for record in &mut records {
+            record.locked = is_in_closed_period(settings.closed_through.as_deref(), &record.date);
+        }
+
+        return Ok((
+            StatusCode::OK,
+            Json(RecategorizeBatchResponse {
+                dry_run,
+                matched_count: records.len() as u32,
+                records,
+                undo_token: None,
+            }),
+        ));
+    }
 
-            let settle: bool = row.get(5).map_err(|_| TransactionError::Begin)?;
-            let debtor_user_id: Option<String> = row.get(6).map_err(|_| TransactionError::Begin)?;
-            let creditor_user_id: Option<String> = row.get(7).map_err(|_| TransactionError::Begin)?;
+    let reopen = reopen.reopen.unwrap_or(false);
+    let undo_token = Uuid::new_v4().to_string();
+    let created_at = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-            drop(rows);
+    let records = with_transaction(db, |conn| {
+        let owner_user_id = user.id.clone();
+        let name_pattern = name_pattern.clone();
+        let created_via = created_via.clone();
+        let start_date = start_date.clone();
+        let end_date = end_date.clone();
+        let target_category_id = target_category_id.clone();
+        let undo_token = undo_token.clone();
+        let created_at = created_at.clone();
+        Box::pin(async move {
+            let is_income = get_category_is_income(conn, &owner_user_id, &target_category_id)
+                .await
+                .map_err(RecategorizeBatchError::Rejected)?;
+
+            let matches = query_recategorize_matches(
+                conn,
+                &owner_user_id,
+                &name_pattern,
+                &created_via,
+                &start_date,
+                &end_date,
+                &target_category_id,
+            )
+            .await
+            .map_err(RecategorizeBatchError::Rejected)?;
+
+            let dates: Vec<&str> = matches.iter().map(|record| record.date.as_str()).collect();
+            guard_closed_period(
+                conn,
+                &owner_user_id,
+                None,
+                "recategorize_batch",
+                &dates,
+                reopen,
+            )
+            .await
+            .map_err(RecategorizeBatchError::Rejected)?;
 
-            let is_owner = owner_user_id == user_id;
-            let is_debtor = debtor_user_id.as_ref() == Some(&user_id);
-            let is_creditor = creditor_user_id.as_ref() == Some(&user_id);
+            record_repo::insert_recategorize_batch(conn, &undo_token, &owner_user_id, &created_at)
+                .await
+                .map_err(|_| RecategorizeBatchError::Db("failed to record undo batch"))?;
+
+            let mut updated = Vec::with_capacity(matches.len());
+            for record in matches {
+                record_repo::insert_recategorize_item(conn, &undo_token, &record)
+                    .await
+                    .map_err(|_| RecategorizeBatchError::Db("failed to record undo item"))?;
+
+                let amount = normalize_amount_by_category(record.amount, is_income);
+                record_repo::set_category_and_amount(
+                    conn,
+                    &owner_user_id,
+                    &record.id,
+                    Some(&target_category_id),
+                    amount,
+                )
+                .await
+                .map_err(|_| RecategorizeBatchError::Db("failed to recategorize record"))?;
 
-            if !is_owner && !is_debtor && !is_creditor {
-                return Err(TransactionError::Begin);
+                updated.push(Record {
+                    category_id: Some(target_category_id.clone()),
+                    amount,
+                    ..record
+                });
             }
 
-            if settle {
-                let record = Record {
-                    id: row.get(0).map_err(|_| TransactionError::Begin)?,
-                    name: row.get(1).map_err(|_| TransactionError::Begin)?,
-                    amount: row.get(2).map_err(|_| TransactionError::Begin)?,
-                    category_id: row.get(3).map_err(|_| TransactionError::Begin)?,
-                    date: row.get(4).map_err(|_| TransactionError::Begin)?,
-                };
-                return Ok(record);
+            Ok(updated)
+        })
+    })
+    .await
+    .map_err(|e: RecategorizeBatchError| -> ApiError { e.into() })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(RecategorizeBatchResponse {
+            dry_run,
+            matched_count: records.len() as u32,
+            records,
+            undo_token: Some(undo_token),
+        }),
+    ))
+}
+
+pub async fn undo_recategorize_batch(
+    State(app_state): State<AppState>,
+    session: Session,
+    Query(reopen): Query<ReopenQuery>,
+    Json(payload): Json<UndoRecategorizeBatchPayload>,
+) -> Result<(StatusCode, Json<UndoRecategorizeBatchResponse>), ApiError> {
+    let user = get_current_user(&session).await?;
+    validate_string_length(&payload.undo_token, "Undo token", MAX_RECORD_NAME_LENGTH)?;
+    let undo_token = payload.undo_token.trim().to_string();
+    let reopen = reopen.reopen.unwrap_or(false);
+
+    let restored_count = with_transaction(&app_state.main_db, |conn| {
+        let owner_user_id = user.id.clone();
+        let undo_token = undo_token.clone();
+        Box::pin(async move {
+            if !record_repo::recategorize_batch_exists(conn, &owner_user_id, &undo_token)
+                .await
+                .map_err(|_| RecategorizeBatchError::Db("failed to query undo batch"))?
+            {
+                return Err(RecategorizeBatchError::NotFound);
             }
 
-            conn.execute(
-                "UPDATE records SET settle = ? WHERE id = ? AND owner_user_id = ?",
-                (true, record_id.as_str(), owner_user_id.as_str()),
+            let items = record_repo::list_recategorize_items(conn, &owner_user_id, &undo_token)
+                .await
+                .map_err(|_| RecategorizeBatchError::Db("failed to query undo items"))?;
+
+            let dates: Vec<&str> = items.iter().map(|item| item.date.as_str()).collect();
+            guard_closed_period(
+                conn,
+                &owner_user_id,
+                None,
+                "undo_recategorize_batch",
+                &dates,
+                reopen,
             )
             .await
-            .map_err(|_| TransactionError::Commit)?;
-
-            let mut updated_rows = conn
-                .query(
-                    "SELECT id, name, amount, category_id, date FROM records WHERE id = ? AND owner_user_id = ?",
-                    (record_id.as_str(), owner_user_id.as_str()),
+            .map_err(RecategorizeBatchError::Rejected)?;
+
+            let mut restored: u32 = 0;
+            for item in &items {
+                let affected = record_repo::set_category_and_amount(
+                    conn,
+                    &owner_user_id,
+                    &item.record_id,
+                    item.previous_category_id.as_deref(),
+                    item.previous_amount,
                 )
                 .await
-                .map_err(|_| TransactionError::Commit)?;
+                .map_err(|_| RecategorizeBatchError::Db("failed to restore record"))?;
+                restored += affected as u32;
+            }
 
-            let updated_row = updated_rows
-                .next()
+            record_repo::delete_recategorize_batch(conn, &undo_token)
                 .await
-                .map_err(|_| TransactionError::Commit)?
-                .ok_or(TransactionError::Commit)?;
-
-            let record = Record {
-                id: updated_row.get(0).map_err(|_| TransactionError::Commit)?,
-                name: updated_row.get(1).map_err(|_| TransactionError::Commit)?,
-                amount: updated_row.get(2).map_err(|_| TransactionError::Commit)?,
-                category_id: updated_row.get(3).map_err(|_| TransactionError::Commit)?,
-                date: updated_row.get(4).map_err(|_| TransactionError::Commit)?,
-            };
+                .map_err(|_| RecategorizeBatchError::Db("failed to clear undo batch"))?;
 
-            Ok(record)
+            Ok(restored)
         })
     })
     .await
-    .map_err(|e| match e {
-        TransactionError::Begin => (StatusCode::NOT_FOUND, "Record not found".to_string()),
-        TransactionError::Commit => db_error_with_context("failed to update settlement status"),
-    })?;
+    .map_err(|e: RecategorizeBatchError| -> ApiError { e.into() })?;
 
-    Ok((StatusCode::OK, Json(record)))
+    Ok((
+        StatusCode::OK,
+        Json(UndoRecategorizeBatchResponse { restored_count }),
+    ))
+}
+
+/// `POST /records/import`: creates records from a `text/csv` body with a
+/// `name,amount,category,date` header (any column order, extra columns ignored). An
+/// optional `currency` column sets each row's currency; blank cells take the user's default.
+/// 201 when every row was imported; 400 listing the failing lines when none were.
+pub async fn import_records(
+    State(app_state): State<AppState>,
+    session: Session,
+    Query(query): Query<ImportRecordsQuery>,
+    body: String,
+) -> Result<(StatusCode, Json<ImportRecordsResponse>), ApiError> {
+    let user = get_current_user(&session).await?;
+    let response = import_records_for_user(
+        &app_state.main_db,
+        &user.id,
+        &body,
+        app_state.csv_import_max_rows,
+        query.create_missing_categories.unwrap_or(false),
+    )
+    .await?;
+    let status = if response.errors.is_empty() {
+        StatusCode::CREATED
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    Ok((status, Json(response)))
 }
@@ -0,0 +1,53 @@
+use axum::{Router, http::Request};
+use tower_http::{
+    LatencyUnit,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, RequestId, SetRequestIdLayer},
+    trace::{DefaultOnResponse, TraceLayer},
+};
+use tracing::Level;
+use tracing_subscriber::EnvFilter;
+
+use crate::constants::{DEFAULT_LOG_FILTER, REQUEST_ID_HEADER};
+
+/// Installs the global tracing subscriber. `RUST_LOG` selects the level per target and
+/// falls back to `DEFAULT_LOG_FILTER`. Without it, `tracing` macros are no-ops, which is
+/// how the test router runs.
+pub fn init_tracing() {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+    tracing_subscriber::fmt().with_env_filter(filter).init();
+}
+
+/// Gives every request an `x-request-id` (a client-sent one is kept, otherwise a UUID),
+/// logs one line per response with method, path, status, latency and that id, and
+/// echoes the id on the response so a reported failure can be found in the logs.
+pub fn layer<S>(router: Router<S>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let trace = TraceLayer::new_for_http()
+        .make_span_with(|request: &Request<_>| {
+            let request_id = request
+                .extensions()
+                .get::<RequestId>()
+                .and_then(|id| id.header_value().to_str().ok())
+                .unwrap_or_default();
+            tracing::info_span!(
+                "request",
+                method = %request.method(),
+                path = %request.uri().path(),
+                request_id = %request_id,
+            )
+        })
+        .on_response(
+            DefaultOnResponse::new()
+                .level(Level::INFO)
+                .latency_unit(LatencyUnit::Millis),
+        );
+
+    // The last layer added runs first: the id is set before the span is made
+    router
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+        .layer(trace)
+        .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
+}
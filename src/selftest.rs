@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::constants::*;
+use crate::models::{CreateCategoryPayload, CreateRecordPayload};
+use crate::money::Money;
+use crate::utils::precise_timestamp;
+use crate::{Db, auth, categories, record_repo, records};
+
+/// How long a passing self-test took.
+#[derive(Debug, Clone, Copy)]
+pub struct SelfTestReport {
+    pub duration: Duration,
+}
+
+/// The step that failed and why.
+#[derive(Debug)]
+pub struct SelfTestError {
+    pub step: &'static str,
+    pub detail: String,
+}
+
+impl std::fmt::Display for SelfTestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "self-test step '{}' failed: {}", self.step, self.detail)
+    }
+}
+
+impl std::error::Error for SelfTestError {}
+
+fn failed(step: &'static str, detail: impl ToString) -> SelfTestError {
+    SelfTestError {
+        step,
+        detail: detail.to_string(),
+    }
+}
+
+/// Runs one create-category, create-record, read-back, delete cycle as the reserved
+/// `SELFTEST_USERNAME` user through the same library functions the handlers use.
+///
+/// The user and everything it owns are deleted before returning, pass or fail, and
+/// also beforehand in case an earlier run was interrupted.
+pub async fn run_startup_self_test(db: &Db) -> Result<SelfTestReport, SelfTestError> {
+    let started = Instant::now();
+    purge_self_test_user(db)
+        .await
+        .map_err(|e| failed("remove leftovers", e))?;
+
+    let result = exercise_write_path(db).await;
+    let cleanup = purge_self_test_user(db).await;
+    result?;
+    cleanup.map_err(|e| failed("clean up", e))?;
+
+    Ok(SelfTestReport {
+        duration: started.elapsed(),
+    })
+}
+
+async fn exercise_write_path(db: &Db) -> Result<(), SelfTestError> {
+    let user = auth::create_user(db, SELFTEST_USERNAME, &Uuid::new_v4().to_string())
+        .await
+        .map_err(|e| failed("create user", e))?;
+
+    let category = categories::create_category_for_user(
+        db,
+        &user.id,
+        CreateCategoryPayload {
+            name: SELFTEST_CATEGORY_NAME.to_string(),
+            is_income: false,
+            parent_id: None,
+            monthly_budget: None,
+        },
+    )
+    .await
+    .map_err(|(_, message)| failed("create category", message))?;
+
+    let created = records::create_record_for_user(
+        db,
+        &user.id,
+        CreateRecordPayload {
+            name: SELFTEST_RECORD_NAME.to_string(),
+            amount: Money::from_cents(100),
+            currency: None,
+            category_id: category.id.clone(),
+            date: OffsetDateTime::now_utc().date().to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .map_err(|(_, message)| failed("create record", message))?;
+
+    let conn = db.write().await;
+    let stored = record_repo::find_record(&conn, &user.id, &created.id)
+        .await
+        .map_err(|e| failed("read back record", e))?
+        .ok_or_else(|| failed("read back record", "record not found"))?;
+    if stored.name != created.name
+        || stored.amount != created.amount
+        || stored.category_id != created.category_id
+        || stored.date != created.date
+    {
+        return Err(failed(
+            "read back record",
+            format!("stored record {stored:?} differs from created record {created:?}"),
+        ));
+    }
+
+    let deleted_at = precise_timestamp(OffsetDateTime::now_utc());
+    let deleted = record_repo::soft_delete_record(&conn, &user.id, &created.id, &deleted_at)
+        .await
+        .map_err(|e| failed("delete record", e))?;
+    if deleted != 1 {
+        return Err(failed(
+            "delete record",
+            format!("expected 1 deleted row, got {deleted}"),
+        ));
+    }
+    Ok(())
+}
+
+/// Deletes the reserved user and every row it may own.
+async fn purge_self_test_user(db: &Db) -> Result<(), libsql::Error> {
+    let conn = db.write().await;
+    let owned_by_self_test_user = "IN (SELECT id FROM users WHERE name = ?)";
+    for statement in [
+        format!("DELETE FROM records WHERE owner_user_id {owned_by_self_test_user}"),
+        format!("DELETE FROM categories WHERE owner_user_id {owned_by_self_test_user}"),
+        format!("DELETE FROM user_settings WHERE user_id {owned_by_self_test_user}"),
+        "DELETE FROM users WHERE name = ?".to_string(),
+    ] {
+        conn.execute(&statement, [SELFTEST_USERNAME]).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use async_trait::async_trait;
+use time::OffsetDateTime;
+use tower_sessions::{
+    MemoryStore, SessionStore,
+    session::{Id, Record},
+    session_store::{Error, Result},
+};
+
+use crate::Db;
+use crate::config::SessionStoreKind;
+use crate::database::retry_if_busy;
+
+/// Session records kept in the `sessions` table of the main database, so logins
+/// survive a restart. Every statement runs under the connection lock, and `save`
+/// is a single upsert, so concurrent saves of one session id leave the last
+/// complete record rather than a mix of two.
+#[derive(Clone)]
+pub struct LibsqlSessionStore {
+    db: Db,
+}
+
+impl std::fmt::Debug for LibsqlSessionStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LibsqlSessionStore").finish_non_exhaustive()
+    }
+}
+
+impl LibsqlSessionStore {
+    pub fn new(db: Db) -> Self {
+        Self { db }
+    }
+}
+
+fn encode_data(record: &Record) -> Result<String> {
+    serde_json::to_string(&record.data).map_err(|e| Error::Encode(e.to_string()))
+}
+
+fn backend_error(e: libsql::Error) -> Error {
+    Error::Backend(e.to_string())
+}
+
+#[async_trait]
+impl SessionStore for LibsqlSessionStore {
+    async fn create(&self, record: &mut Record) -> Result<()> {
+        let data = encode_data(record)?;
+        let conn = self.db.write().await;
+        // A colliding id is replaced with a fresh one rather than overwriting another session
+        loop {
+            let inserted = retry_if_busy(|| {
+                conn.execute(
+                    "INSERT INTO sessions (id, data, expiry_date) VALUES (?1, ?2, ?3)
+                     ON CONFLICT(id) DO NOTHING",
+                    libsql::params![
+                        record.id.to_string(),
+                        data.as_str(),
+                        record.expiry_date.unix_timestamp()
+                    ],
+                )
+            })
+            .await
+            .map_err(backend_error)?;
+            if inserted > 0 {
+                return Ok(());
+            }
+            record.id = Id::default();
+        }
+    }
+
+    async fn save(&self, record: &Record) -> Result<()> {
+        let data = encode_data(record)?;
+        let conn = self.db.write().await;
+        // Every request saves its session, so this is the write most likely to meet the bot's
+        retry_if_busy(|| {
+            conn.execute(
+                "INSERT INTO sessions (id, data, expiry_date) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(id) DO UPDATE SET data = excluded.data, expiry_date = excluded.expiry_date",
+                libsql::params![
+                    record.id.to_string(),
+                    data.as_str(),
+                    record.expiry_date.unix_timestamp()
+                ],
+            )
+        })
+        .await
+        .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> Result<Option<Record>> {
+        let conn = self.db.read().await;
+        let mut rows = conn
+            .query(
+                "SELECT data, expiry_date FROM sessions WHERE id = ?1 AND expiry_date > ?2",
+                libsql::params![
+                    session_id.to_string(),
+                    OffsetDateTime::now_utc().unix_timestamp()
+                ],
+            )
+            .await
+            .map_err(backend_error)?;
+        let Some(row) = rows.next().await.map_err(backend_error)? else {
+            return Ok(None);
+        };
+
+        let data: String = row.get(0).map_err(|e| Error::Decode(e.to_string()))?;
+        let expiry_date: i64 = row.get(1).map_err(|e| Error::Decode(e.to_string()))?;
+        Ok(Some(Record {
+            id: *session_id,
+            data: serde_json::from_str(&data).map_err(|e| Error::Decode(e.to_string()))?,
+            expiry_date: OffsetDateTime::from_unix_timestamp(expiry_date)
+                .map_err(|e| Error::Decode(e.to_string()))?,
+        }))
+    }
+
+    async fn delete(&self, session_id: &Id) -> Result<()> {
+        let conn = self.db.write().await;
+        retry_if_busy(|| {
+            conn.execute(
+                "DELETE FROM sessions WHERE id = ?1",
+                [session_id.to_string()],
+            )
+        })
+        .await
+        .map_err(backend_error)?;
+        Ok(())
+    }
+}
+
+/// Deletes sessions whose expiry has passed as of `now`. Each save moves a session's
+/// expiry to `SESSION_EXPIRY_DAYS` after its last activity. Returns how many were removed.
+pub async fn delete_expired_sessions(db: &Db, now: OffsetDateTime) -> libsql::Result<u64> {
+    let conn = db.write().await;
+    conn.execute(
+        "DELETE FROM sessions WHERE expiry_date <= ?1",
+        [now.unix_timestamp()],
+    )
+    .await
+}
+
+/// The session store chosen by `SESSION_STORE`.
+#[derive(Debug, Clone)]
+pub enum AppSessionStore {
+    Database(LibsqlSessionStore),
+    Memory(MemoryStore),
+}
+
+impl AppSessionStore {
+    pub fn new(kind: SessionStoreKind, db: Db) -> Self {
+        match kind {
+            SessionStoreKind::Database => Self::Database(LibsqlSessionStore::new(db)),
+            SessionStoreKind::Memory => Self::Memory(MemoryStore::default()),
+        }
+    }
+}
+
+#[async_trait]
+impl SessionStore for AppSessionStore {
+    async fn create(&self, record: &mut Record) -> Result<()> {
+        match self {
+            Self::Database(store) => store.create(record).await,
+            Self::Memory(store) => store.create(record).await,
+        }
+    }
+
+    async fn save(&self, record: &Record) -> Result<()> {
+        match self {
+            Self::Database(store) => store.save(record).await,
+            Self::Memory(store) => store.save(record).await,
+        }
+    }
+
+    async fn load(&self, session_id: &Id) -> Result<Option<Record>> {
+        match self {
+            Self::Database(store) => store.load(session_id).await,
+            Self::Memory(store) => store.load(session_id).await,
+        }
+    }
+
+    async fn delete(&self, session_id: &Id) -> Result<()> {
+        match self {
+            Self::Database(store) => store.delete(session_id).await,
+            Self::Memory(store) => store.delete(session_id).await,
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use tower_sessions::Session;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::auth::get_current_user;
+use crate::constants::*;
+use crate::models::{UpdateSettingsPayload, UserSettings};
+use crate::outbox::parse_batch_time;
+use crate::utils::{conditional_json, db_error, db_error_with_context, validate_date};
+
+pub async fn fetch_user_settings(
+    conn: &libsql::Connection,
+    user_id: &str,
+) -> Result<UserSettings, (StatusCode, String)> {
+    let mut rows = conn
+        .query(
+            "SELECT closed_through, utc_offset_minutes, alert_batch_time, currency_code, telegram_split_notifications FROM user_settings WHERE user_id = ?",
+            [user_id],
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query user settings"))?;
+
+    if let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        let invalid = |_| db_error_with_context("invalid user settings data");
+        Ok(UserSettings {
+            closed_through: row.get(0).map_err(invalid)?,
+            utc_offset_minutes: row.get(1).map_err(invalid)?,
+            alert_batch_time: row.get(2).map_err(invalid)?,
+            currency_code: row.get(3).map_err(invalid)?,
+            telegram_split_notifications: row
+                .get::<Option<i64>>(4)
+                .map_err(invalid)?
+                .map(|enabled| enabled != 0),
+        })
+    } else {
+        Ok(UserSettings::default())
+    }
+}
+
+fn validate_utc_offset_minutes(minutes: i32) -> Result<(), (StatusCode, String)> {
+    if (MIN_UTC_OFFSET_MINUTES..=MAX_UTC_OFFSET_MINUTES).contains(&minutes) {
+        Ok(())
+    } else {
+        Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "utc_offset_minutes must be between {} and {}",
+                MIN_UTC_OFFSET_MINUTES, MAX_UTC_OFFSET_MINUTES
+            ),
+        ))
+    }
+}
+
+fn validate_alert_batch_time(value: &str) -> Result<(), (StatusCode, String)> {
+    match parse_batch_time(value) {
+        Some(_) => Ok(()),
+        None => Err((
+            StatusCode::BAD_REQUEST,
+            "alert_batch_time must be in HH:MM format".to_string(),
+        )),
+    }
+}
+
+fn validate_currency_code(value: &str) -> Result<(), (StatusCode, String)> {
+    if ISO_CURRENCY_CODES.contains(&value) {
+        Ok(())
+    } else {
+        Err((
+            StatusCode::BAD_REQUEST,
+            "currency_code must be an ISO 4217 currency code".to_string(),
+        ))
+    }
+}
+
+/// The currency the user's amounts are shown in, falling back to the default.
+pub async fn user_currency_code(
+    conn: &libsql::Connection,
+    user_id: &str,
+) -> Result<String, (StatusCode, String)> {
+    let settings = fetch_user_settings(conn, user_id).await?;
+    Ok(settings
+        .currency_code
+        .unwrap_or_else(|| DEFAULT_CURRENCY_CODE.to_string()))
+}
+
+/// Dates are `YYYY-MM-DD`, so string comparison matches calendar order.
+pub fn is_in_closed_period(closed_through: Option<&str>, date: &str) -> bool {
+    closed_through.is_some_and(|closed_through| date.trim() <= closed_through)
+}
+
+pub fn period_closed_message(closed_through: &str) -> String {
+    format!(
+        "PERIOD_CLOSED: records dated on or before {} are locked",
+        closed_through
+    )
+}
+
+/// Rejects a mutation touching any of `dates` that falls in the user's closed period.
+///
+/// With `reopen` the mutation is allowed, and one audit row per distinct closed date is written
+/// on `conn` so it commits or rolls back with the mutation itself.
+pub async fn guard_closed_period(
+    conn: &libsql::Connection,
+    user_id: &str,
+    record_id: Option<&str>,
+    action: &str,
+    dates: &[&str],
+    reopen: bool,
+) -> Result<(), (StatusCode, String)> {
+    let settings = fetch_user_settings(conn, user_id).await?;
+    let Some(closed_through) = settings.closed_through else {
+        return Ok(());
+    };
+
+    let mut closed_dates: Vec<&str> = dates
+        .iter()
+        .map(|date| date.trim())
+        .filter(|date| is_in_closed_period(Some(&closed_through), date))
+        .collect();
+    closed_dates.sort_unstable();
+    closed_dates.dedup();
+    if closed_dates.is_empty() {
+        return Ok(());
+    }
+
+    if !reopen {
+        return Err((StatusCode::CONFLICT, period_closed_message(&closed_through)));
+    }
+
+    let created_at = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    for date in closed_dates {
+        conn.execute(
+            "INSERT INTO period_reopen_audit (id, owner_user_id, record_id, action, record_date, closed_through, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            (
+                Uuid::new_v4().to_string(),
+                user_id,
+                record_id,
+                action,
+                date,
+                closed_through.as_str(),
+                created_at.as_str(),
+            ),
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to audit period reopen"))?;
+    }
+
+    Ok(())
+}
+
+pub async fn get_settings(
+    State(app_state): State<AppState>,
+    session: Session,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let user = get_current_user(&session).await?;
+    let conn = app_state.main_db.read().await;
+    let settings = fetch_user_settings(&conn, &user.id).await?;
+    Ok(conditional_json(&headers, settings))
+}
+
+pub async fn update_settings(
+    State(app_state): State<AppState>,
+    session: Session,
+    Json(payload): Json<UpdateSettingsPayload>,
+) -> Result<(StatusCode, Json<UserSettings>), (StatusCode, String)> {
+    let user = get_current_user(&session).await?;
+
+    if payload.closed_through.is_none()
+        && payload.utc_offset_minutes.is_none()
+        && payload.alert_batch_time.is_none()
+        && payload.currency_code.is_none()
+        && payload.telegram_split_notifications.is_none()
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "At least one field must be provided for update".to_string(),
+        ));
+    }
+
+    let closed_through = payload
+        .closed_through
+        .map(|date| date.map(|date| date.trim().to_string()));
+    if let Some(Some(ref date)) = closed_through {
+        validate_date(date)?;
+    }
+    if let Some(Some(minutes)) = payload.utc_offset_minutes {
+        validate_utc_offset_minutes(minutes)?;
+    }
+    let alert_batch_time = payload
+        .alert_batch_time
+        .map(|time| time.map(|time| time.trim().to_string()));
+    if let Some(Some(ref time)) = alert_batch_time {
+        validate_alert_batch_time(time)?;
+    }
+    let currency_code = payload
+        .currency_code
+        .map(|code| code.map(|code| code.trim().to_ascii_uppercase()));
+    if let Some(Some(ref code)) = currency_code {
+        validate_currency_code(code)?;
+    }
+
+    let updated_at = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let conn = app_state.main_db.write().await;
+    let current = fetch_user_settings(&conn, &user.id).await?;
+    let settings = UserSettings {
+        closed_through: closed_through.unwrap_or(current.closed_through),
+        utc_offset_minutes: payload
+            .utc_offset_minutes
+            .unwrap_or(current.utc_offset_minutes),
+        alert_batch_time: alert_batch_time.unwrap_or(current.alert_batch_time),
+        currency_code: currency_code.unwrap_or(current.currency_code),
+        telegram_split_notifications: payload
+            .telegram_split_notifications
+            .unwrap_or(current.telegram_split_notifications),
+    };
+    conn.execute(
+        "INSERT INTO user_settings (user_id, closed_through, utc_offset_minutes, alert_batch_time, currency_code, telegram_split_notifications, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?) \
+         ON CONFLICT(user_id) DO UPDATE SET closed_through = excluded.closed_through, utc_offset_minutes = excluded.utc_offset_minutes, \
+         alert_batch_time = excluded.alert_batch_time, currency_code = excluded.currency_code, \
+         telegram_split_notifications = excluded.telegram_split_notifications, updated_at = excluded.updated_at",
+        libsql::params![
+            user.id.as_str(),
+            settings.closed_through.as_deref(),
+            settings.utc_offset_minutes,
+            settings.alert_batch_time.as_deref(),
+            settings.currency_code.as_deref(),
+            settings.telegram_split_notifications,
+            updated_at.as_str(),
+        ],
+    )
+    .await
+    .map_err(|_| db_error_with_context("failed to update user settings"))?;
+
+    Ok((StatusCode::OK, Json(settings)))
+}
@@ -0,0 +1,908 @@
+use libsql::Connection;
+use libsql::params::IntoParams;
+use time::OffsetDateTime;
+
+use crate::constants::{
+    CREATED_VIA_SETTLE_UP, SPLIT_STATUS_COMPLETED, SPLIT_STATUS_INITIATED, SPLIT_STATUS_SETTLED,
+};
+use crate::crypto;
+use crate::money::Money;
+use crate::utils::{precise_timestamp, sql_placeholders, to_db_date};
+
+/// A split record joined with its debtor's and creditor's names (empty when unknown).
+pub struct SplitRecordRow {
+    pub record_id: String,
+    pub split_id: Option<String>,
+    pub description: String,
+    pub date: String,
+    pub amount: Money,
+    pub currency: String,
+    pub debtor_user_id: Option<String>,
+    pub creditor_user_id: Option<String>,
+    pub creditor_name: String,
+    pub debtor_name: String,
+    pub pending: bool,
+    pub settle: bool,
+}
+
+/// One record of a split fan-out. The payer's record is owned, owed and credited by the
+/// payer; each participant's record is pending, uncategorized and credited to the payer.
+pub struct NewSplitRecord<'a> {
+    pub id: &'a str,
+    pub owner_user_id: &'a str,
+    pub name: &'a str,
+    pub amount: Money,
+    pub currency: &'a str,
+    pub category_id: Option<&'a str>,
+    pub date: &'a str,
+    pub pending: bool,
+    pub split_id: &'a str,
+    pub debtor_user_id: &'a str,
+    pub creditor_user_id: &'a str,
+    /// Only on an initiator's record booked at the whole total.
+    pub full_total: bool,
+}
+
+/// The split side of a record, as seen by its owner.
+pub struct SplitMembership {
+    pub record_id: String,
+    pub split_id: String,
+    pub creditor_user_id: Option<String>,
+    pub settle: bool,
+}
+
+/// Repayment state of one split, aggregated over its participants' records.
+pub struct SplitProgressRow {
+    pub split_id: String,
+    pub participants_total: u32,
+    pub participants_settled: u32,
+    pub amount_outstanding: Money,
+}
+
+/// One record of a split, trashed or not, with its owner's name (empty when unknown).
+pub struct SplitShareRow {
+    pub split_id: String,
+    pub record_id: String,
+    pub owner_user_id: String,
+    pub owner_name: String,
+    pub description: String,
+    pub date: String,
+    pub amount: Money,
+    pub creditor_user_id: Option<String>,
+    pub pending: bool,
+    pub settle: bool,
+    pub deleted: bool,
+    pub full_total: bool,
+}
+
+/// A participant's share of a split, kept after they deleted their account.
+pub struct SplitDepartureRow {
+    pub split_id: String,
+    pub user_id: String,
+    pub username: String,
+    pub amount: Money,
+    pub pending: bool,
+    pub settled: bool,
+    pub departed_at: String,
+}
+
+/// An uncategorized, non-split record for the residual payment of a settle-up.
+pub struct NewSettleUpRecord<'a> {
+    pub id: &'a str,
+    pub owner_user_id: &'a str,
+    pub name: &'a str,
+    pub amount: Money,
+    pub currency: &'a str,
+    pub date: &'a str,
+}
+
+/// A split share between two users, as the friend activity feed shows it to `viewer`.
+pub struct PairShareRow {
+    pub record_id: String,
+    pub split_id: String,
+    /// The name on the viewer's own record of the split; `None` once they deleted it.
+    pub description: Option<String>,
+    pub amount: Money,
+    pub creditor_user_id: String,
+    pub pending: bool,
+    /// `created_at` for shares, `settled_at` for settlements, as a `precise_timestamp`.
+    pub at: String,
+}
+
+/// A stored idempotency key. `response_body` is NULL while the request is in flight.
+pub struct IdempotencyEntry {
+    pub response_status: i64,
+    pub response_body: Option<String>,
+    pub payload_hash: String,
+    /// Set instead of a replayable body when the response exceeded the stored size limit.
+    pub response_digest: Option<String>,
+    /// RFC 3339; how long an in-flight reservation has been held.
+    pub created_at: String,
+}
+
+pub struct NewIdempotencyEntry<'a> {
+    pub id: &'a str,
+    pub key: &'a str,
+    pub user_id: &'a str,
+    pub endpoint: &'a str,
+    pub payload_hash: &'a str,
+    pub created_at: &'a str,
+    pub expires_at: &'a str,
+}
+
+const SPLIT_RECORD_SELECT: &str = "SELECT r.id, r.split_id, r.name, r.date, r.amount, r.debtor_user_id, r.creditor_user_id, COALESCE(creditor_user.name, ''), COALESCE(debtor_user.name, ''), r.pending, r.settle, r.currency FROM records r LEFT JOIN users creditor_user ON creditor_user.id = r.creditor_user_id LEFT JOIN users debtor_user ON debtor_user.id = r.debtor_user_id";
+
+/// Finalized, unsettled split records between two users, owned by either of them.
+/// Binds `(a, b, a, b, b, a)`.
+const UNSETTLED_BETWEEN_FILTER: &str = "owner_user_id IN (?, ?) AND deleted_at IS NULL AND pending = 0 AND settle = 0 AND split_id IS NOT NULL AND ((debtor_user_id = ? AND creditor_user_id = ?) OR (debtor_user_id = ? AND creditor_user_id = ?))";
+
+/// Participant shares between two users, each owned by its debtor. Binds `(a, b, a, b, b, a)`.
+const PAIR_SHARE_FILTER: &str = "r.owner_user_id IN (?, ?) AND r.deleted_at IS NULL AND r.split_id IS NOT NULL AND ((r.debtor_user_id = ? AND r.creditor_user_id = ?) OR (r.debtor_user_id = ? AND r.creditor_user_id = ?))";
+
+/// Shares created before `created_at` existed count from the start of their date, written
+/// like `precise_timestamp` so they sort with the rest.
+const SHARE_CREATED_AT: &str = "COALESCE(r.created_at, r.date || 'T00:00:00.000000000Z')";
+
+const SETTLED_SHARE_FILTER: &str = "r.settle = 1 AND r.settled_at IS NOT NULL";
+
+fn split_record_from_row(row: &libsql::Row) -> Result<SplitRecordRow, libsql::Error> {
+    Ok(SplitRecordRow {
+        record_id: row.get(0)?,
+        split_id: row.get(1)?,
+        description: crypto::open_field(row.get(2)?)?,
+        date: row.get(3)?,
+        amount: Money::from_cents(row.get(4)?),
+        currency: row.get(11)?,
+        debtor_user_id: row.get(5)?,
+        creditor_user_id: row.get(6)?,
+        creditor_name: row.get(7)?,
+        debtor_name: row.get(8)?,
+        pending: row.get(9)?,
+        settle: row.get(10)?,
+    })
+}
+
+async fn query_count(
+    conn: &Connection,
+    sql: &str,
+    params: impl IntoParams,
+) -> Result<i64, libsql::Error> {
+    let mut rows = conn.query(sql, params).await?;
+    match rows.next().await? {
+        Some(row) => row.get(0),
+        None => Ok(0),
+    }
+}
+
+async fn query_split_records(
+    conn: &Connection,
+    sql: &str,
+    params: impl IntoParams,
+) -> Result<Vec<SplitRecordRow>, libsql::Error> {
+    let mut rows = conn.query(sql, params).await?;
+    let mut records = Vec::new();
+    while let Some(row) = rows.next().await? {
+        records.push(split_record_from_row(&row)?);
+    }
+    Ok(records)
+}
+
+async fn query_pair_shares(
+    conn: &Connection,
+    viewer: &str,
+    friend: &str,
+    (at_column, extra_filter): (&str, &str),
+    limit: u32,
+) -> Result<Vec<PairShareRow>, libsql::Error> {
+    let sql = format!(
+        "SELECT r.id, r.split_id, (SELECT mine.name FROM records mine WHERE mine.split_id = r.split_id AND mine.owner_user_id = ? AND mine.deleted_at IS NULL ORDER BY mine.seq LIMIT 1), r.amount, r.creditor_user_id, r.pending, {at_column} AS at FROM records r WHERE {PAIR_SHARE_FILTER} AND {extra_filter} ORDER BY at DESC, r.id DESC LIMIT ?"
+    );
+    let mut rows = conn
+        .query(
+            &sql,
+            (
+                viewer, viewer, friend, viewer, friend, friend, viewer, limit,
+            ),
+        )
+        .await?;
+    let mut shares = Vec::new();
+    while let Some(row) = rows.next().await? {
+        shares.push(PairShareRow {
+            record_id: row.get(0)?,
+            split_id: row.get(1)?,
+            description: row
+                .get::<Option<String>>(2)?
+                .map(crypto::open_field)
+                .transpose()?,
+            amount: Money::from_cents(row.get(3)?).abs(),
+            creditor_user_id: row.get(4)?,
+            pending: row.get(5)?,
+            at: row.get(6)?,
+        });
+    }
+    Ok(shares)
+}
+
+/// One currency of `pair_share_totals`.
+pub struct PairShareTotals {
+    pub currency: String,
+    pub split_count: i64,
+    /// What `friend` owed `viewer` in those splits.
+    pub owed_to_viewer: Money,
+    /// What `viewer` owed `friend` in them.
+    pub owed_by_viewer: Money,
+}
+
+/// Over every live share between `viewer` and `friend`, per currency ordered by code: how
+/// many splits they share and what each side owed the other in them.
+pub async fn pair_share_totals(
+    conn: &Connection,
+    viewer: &str,
+    friend: &str,
+) -> Result<Vec<PairShareTotals>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT r.currency, COUNT(DISTINCT r.split_id), COALESCE(SUM(CASE WHEN r.creditor_user_id = ? THEN ABS(r.amount) ELSE 0 END), 0), COALESCE(SUM(CASE WHEN r.creditor_user_id = ? THEN ABS(r.amount) ELSE 0 END), 0) FROM records r WHERE {PAIR_SHARE_FILTER} GROUP BY r.currency ORDER BY r.currency"
+            ),
+            (viewer, friend, viewer, friend, viewer, friend, friend, viewer),
+        )
+        .await?;
+    let mut totals = Vec::new();
+    while let Some(row) = rows.next().await? {
+        totals.push(PairShareTotals {
+            currency: row.get(0)?,
+            split_count: row.get(1)?,
+            owed_to_viewer: Money::from_cents(row.get(2)?),
+            owed_by_viewer: Money::from_cents(row.get(3)?),
+        });
+    }
+    Ok(totals)
+}
+
+pub async fn count_pair_shares(conn: &Connection, a: &str, b: &str) -> Result<i64, libsql::Error> {
+    query_count(
+        conn,
+        &format!("SELECT COUNT(*) FROM records r WHERE {PAIR_SHARE_FILTER}"),
+        (a, b, a, b, b, a),
+    )
+    .await
+}
+
+/// The newest `limit` split shares between `viewer` and `friend`, by creation.
+pub async fn list_pair_shares(
+    conn: &Connection,
+    viewer: &str,
+    friend: &str,
+    limit: u32,
+) -> Result<Vec<PairShareRow>, libsql::Error> {
+    query_pair_shares(conn, viewer, friend, (SHARE_CREATED_AT, "1 = 1"), limit).await
+}
+
+pub async fn count_pair_settlements(
+    conn: &Connection,
+    a: &str,
+    b: &str,
+) -> Result<i64, libsql::Error> {
+    query_count(
+        conn,
+        &format!(
+            "SELECT COUNT(*) FROM records r WHERE {PAIR_SHARE_FILTER} AND {SETTLED_SHARE_FILTER}"
+        ),
+        (a, b, a, b, b, a),
+    )
+    .await
+}
+
+/// The newest `limit` settled shares between `viewer` and `friend`, by `settled_at`.
+/// Shares settled before `settled_at` existed have no settlement time and are left out.
+pub async fn list_pair_settlements(
+    conn: &Connection,
+    viewer: &str,
+    friend: &str,
+    limit: u32,
+) -> Result<Vec<PairShareRow>, libsql::Error> {
+    query_pair_shares(
+        conn,
+        viewer,
+        friend,
+        ("r.settled_at", SETTLED_SHARE_FILTER),
+        limit,
+    )
+    .await
+}
+
+pub async fn insert_split_record(
+    conn: &Connection,
+    record: &NewSplitRecord<'_>,
+) -> Result<(), libsql::Error> {
+    let name = crypto::seal_record_name(conn, record.owner_user_id, record.name).await?;
+    let date = to_db_date(record.date)?;
+    conn.execute(
+        "INSERT INTO records (id, owner_user_id, name, amount, currency, category_id, date, pending, split_id, settle, debtor_user_id, creditor_user_id, split_full_total, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        libsql::params![
+            record.id,
+            record.owner_user_id,
+            name.as_str(),
+            record.amount,
+            record.currency,
+            record.category_id,
+            date,
+            record.pending,
+            record.split_id,
+            false,
+            record.debtor_user_id,
+            record.creditor_user_id,
+            record.full_total,
+            precise_timestamp(OffsetDateTime::now_utc()),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn count_pending(conn: &Connection, user_id: &str) -> Result<i64, libsql::Error> {
+    query_count(
+        conn,
+        "SELECT COUNT(*) FROM records WHERE owner_user_id = ? AND deleted_at IS NULL AND pending = 1 AND split_id IS NOT NULL",
+        [user_id],
+    )
+    .await
+}
+
+/// `user_id`'s pending split records, newest first.
+pub async fn list_pending(
+    conn: &Connection,
+    user_id: &str,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<SplitRecordRow>, libsql::Error> {
+    query_split_records(
+        conn,
+        &format!("{SPLIT_RECORD_SELECT} WHERE r.owner_user_id = ? AND r.deleted_at IS NULL AND r.pending = 1 AND r.split_id IS NOT NULL ORDER BY r.date DESC, r.seq DESC LIMIT ? OFFSET ?"),
+        (user_id, limit, offset),
+    )
+    .await
+}
+
+pub async fn count_unsettled_between(
+    conn: &Connection,
+    a: &str,
+    b: &str,
+) -> Result<i64, libsql::Error> {
+    query_count(
+        conn,
+        &format!("SELECT COUNT(*) FROM records WHERE {UNSETTLED_BETWEEN_FILTER}"),
+        (a, b, a, b, b, a),
+    )
+    .await
+}
+
+/// Every record of the given splits that has not been purged, in creation order. Trashed
+/// records are included with `deleted` set so a split's shares can still be accounted for.
+pub async fn list_split_shares(
+    conn: &Connection,
+    split_ids: &[String],
+) -> Result<Vec<SplitShareRow>, libsql::Error> {
+    if split_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let sql = format!(
+        "SELECT r.split_id, r.id, r.owner_user_id, COALESCE(owner.name, ''), r.name, r.date, r.amount, r.creditor_user_id, r.pending, r.settle, r.deleted_at IS NOT NULL, r.split_full_total FROM records r LEFT JOIN users owner ON owner.id = r.owner_user_id WHERE r.split_id IN ({}) ORDER BY r.seq",
+        sql_placeholders(split_ids.len())
+    );
+    let params: Vec<libsql::Value> = split_ids.iter().cloned().map(libsql::Value::from).collect();
+    let mut rows = conn.query(&sql, params).await?;
+    let mut shares = Vec::new();
+    while let Some(row) = rows.next().await? {
+        shares.push(SplitShareRow {
+            split_id: row.get(0)?,
+            record_id: row.get(1)?,
+            owner_user_id: row.get(2)?,
+            owner_name: row.get(3)?,
+            description: crypto::open_field(row.get(4)?)?,
+            date: row.get(5)?,
+            amount: Money::from_cents(row.get(6)?),
+            creditor_user_id: row.get(7)?,
+            pending: row.get(8)?,
+            settle: row.get(9)?,
+            deleted: row.get(10)?,
+            full_total: row.get(11)?,
+        });
+    }
+    Ok(shares)
+}
+
+/// The split a live record belongs to, whoever owns it; `None` for unknown or non-split records.
+pub async fn find_record_split_id(
+    conn: &Connection,
+    record_id: &str,
+) -> Result<Option<String>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT split_id FROM records WHERE id = ? AND deleted_at IS NULL AND split_id IS NOT NULL",
+            [record_id],
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => row.get(0).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Departed participants of the given splits, in departure order.
+pub async fn list_split_departures(
+    conn: &Connection,
+    split_ids: &[String],
+) -> Result<Vec<SplitDepartureRow>, libsql::Error> {
+    if split_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let sql = format!(
+        "SELECT split_id, user_id, username, amount, pending, settled, departed_at FROM split_departures WHERE split_id IN ({}) ORDER BY departed_at, user_id",
+        sql_placeholders(split_ids.len())
+    );
+    let params: Vec<libsql::Value> = split_ids.iter().cloned().map(libsql::Value::from).collect();
+    let mut rows = conn.query(&sql, params).await?;
+    let mut departures = Vec::new();
+    while let Some(row) = rows.next().await? {
+        departures.push(SplitDepartureRow {
+            split_id: row.get(0)?,
+            user_id: row.get(1)?,
+            username: row.get(2)?,
+            amount: Money::from_cents(row.get(3)?),
+            pending: row.get(4)?,
+            settled: row.get(5)?,
+            departed_at: row.get(6)?,
+        });
+    }
+    Ok(departures)
+}
+
+/// Permanently deletes every record of `split_id`, trashed ones included, with their tags,
+/// and its departures. Returns the number of records removed.
+pub async fn delete_split_records(conn: &Connection, split_id: &str) -> Result<u64, libsql::Error> {
+    conn.execute(
+        "DELETE FROM split_departures WHERE split_id = ?",
+        [split_id],
+    )
+    .await?;
+    conn.execute(
+        "DELETE FROM record_tags WHERE record_id IN (SELECT id FROM records WHERE split_id = ?)",
+        [split_id],
+    )
+    .await?;
+    conn.execute("DELETE FROM records WHERE split_id = ?", [split_id])
+        .await
+}
+
+/// Settles the given participant records of `split_id`; settled and trashed ones are skipped.
+pub async fn settle_split_shares(
+    conn: &Connection,
+    split_id: &str,
+    record_ids: &[String],
+) -> Result<u64, libsql::Error> {
+    if record_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let sql = format!(
+        "UPDATE records SET settle = 1, settled_at = ? WHERE split_id = ? AND owner_user_id != creditor_user_id AND settle = 0 AND deleted_at IS NULL AND id IN ({})",
+        sql_placeholders(record_ids.len())
+    );
+    let mut params = vec![
+        libsql::Value::from(precise_timestamp(OffsetDateTime::now_utc())),
+        libsql::Value::from(split_id.to_string()),
+    ];
+    params.extend(record_ids.iter().cloned().map(libsql::Value::from));
+    conn.execute(&sql, params).await
+}
+
+/// Which of a user's splits `count_splits` and `list_split_ids` cover.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SplitRole {
+    Any,
+    /// Splits the user paid for: every record of them is credited to the user.
+    Initiator,
+    /// Splits the user owes a share on.
+    Participant,
+}
+
+/// One row per split `?1` has `role` in, with its status worked out from the live
+/// participant records the same way `splits::load_split` does, filtered to status `?2`
+/// unless it is NULL.
+fn user_splits_sql(role: SplitRole) -> String {
+    let role_filter = match role {
+        SplitRole::Any => "owner_user_id = ?1 OR creditor_user_id = ?1",
+        SplitRole::Initiator => "creditor_user_id = ?1",
+        SplitRole::Participant => "owner_user_id = ?1 AND creditor_user_id != owner_user_id",
+    };
+    let live_share = "r.owner_user_id != r.creditor_user_id AND r.deleted_at IS NULL";
+    format!(
+        "SELECT * FROM (SELECT r.split_id, CASE \
+         WHEN SUM(CASE WHEN {live_share} AND r.pending = 1 THEN 1 ELSE 0 END) > 0 THEN '{SPLIT_STATUS_INITIATED}' \
+         WHEN SUM(CASE WHEN {live_share} THEN 1 ELSE 0 END) > 0 AND SUM(CASE WHEN {live_share} AND r.settle = 0 THEN 1 ELSE 0 END) = 0 THEN '{SPLIT_STATUS_SETTLED}' \
+         ELSE '{SPLIT_STATUS_COMPLETED}' END AS status, MIN(r.date) AS date, MIN(r.seq) AS first_seq \
+         FROM records r WHERE r.split_id IN (SELECT split_id FROM records WHERE split_id IS NOT NULL AND ({role_filter})) \
+         GROUP BY r.split_id) WHERE ?2 IS NULL OR status = ?2"
+    )
+}
+
+pub async fn count_splits(
+    conn: &Connection,
+    user_id: &str,
+    role: SplitRole,
+    status: Option<&str>,
+) -> Result<i64, libsql::Error> {
+    query_count(
+        conn,
+        &format!("SELECT COUNT(*) FROM ({})", user_splits_sql(role)),
+        (user_id, status),
+    )
+    .await
+}
+
+/// Ids of the splits `count_splits` counts, newest first.
+pub async fn list_split_ids(
+    conn: &Connection,
+    user_id: &str,
+    role: SplitRole,
+    status: Option<&str>,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<String>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            &format!(
+                "{} ORDER BY date DESC, first_seq DESC LIMIT ?3 OFFSET ?4",
+                user_splits_sql(role)
+            ),
+            (user_id, status, limit, offset),
+        )
+        .await?;
+    let mut ids = Vec::new();
+    while let Some(row) = rows.next().await? {
+        ids.push(row.get(0)?);
+    }
+    Ok(ids)
+}
+
+/// Every split `user_id` initiated, oldest first.
+pub async fn list_initiated_split_ids(
+    conn: &Connection,
+    user_id: &str,
+) -> Result<Vec<String>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT split_id FROM records
+             WHERE owner_user_id = ?1 AND creditor_user_id = ?1 AND split_id IS NOT NULL
+             GROUP BY split_id ORDER BY MIN(seq)",
+            [user_id],
+        )
+        .await?;
+    let mut ids = Vec::new();
+    while let Some(row) = rows.next().await? {
+        ids.push(row.get(0)?);
+    }
+    Ok(ids)
+}
+
+/// Whether the payer's own record of `split_id` is still live (neither trashed nor purged).
+pub async fn payer_record_live(
+    conn: &Connection,
+    split_id: &str,
+    payer_user_id: &str,
+) -> Result<bool, libsql::Error> {
+    Ok(query_count(
+        conn,
+        "SELECT COUNT(*) FROM records WHERE split_id = ? AND owner_user_id = ? AND creditor_user_id = owner_user_id AND deleted_at IS NULL",
+        (split_id, payer_user_id),
+    )
+    .await?
+        > 0)
+}
+
+/// Whether any split record between `a` and `b` was settled after `since` (a `precise_timestamp`).
+pub async fn settled_between_since(
+    conn: &Connection,
+    a: &str,
+    b: &str,
+    since: &str,
+) -> Result<bool, libsql::Error> {
+    Ok(query_count(
+        conn,
+        "SELECT COUNT(*) FROM records WHERE owner_user_id IN (?, ?) AND split_id IS NOT NULL AND ((debtor_user_id = ? AND creditor_user_id = ?) OR (debtor_user_id = ? AND creditor_user_id = ?)) AND settled_at > ?",
+        (a, b, a, b, b, a, since),
+    )
+    .await?
+        > 0)
+}
+
+/// Finalized, unsettled split records between `a` and `b` in either direction, newest first.
+pub async fn list_unsettled_between(
+    conn: &Connection,
+    a: &str,
+    b: &str,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<SplitRecordRow>, libsql::Error> {
+    query_split_records(
+        conn,
+        &format!("{SPLIT_RECORD_SELECT} WHERE r.owner_user_id IN (?, ?) AND r.deleted_at IS NULL AND r.pending = 0 AND r.settle = 0 AND r.split_id IS NOT NULL AND ((r.debtor_user_id = ? AND r.creditor_user_id = ?) OR (r.debtor_user_id = ? AND r.creditor_user_id = ?)) ORDER BY r.date DESC, r.seq DESC LIMIT ? OFFSET ?"),
+        (a, b, a, b, b, a, limit, offset),
+    )
+    .await
+}
+
+/// Settles every record `list_unsettled_between` would return, on both users' sides.
+pub async fn settle_all_between(conn: &Connection, a: &str, b: &str) -> Result<u64, libsql::Error> {
+    conn.execute(
+        &format!("UPDATE records SET settle = 1, settled_at = ? WHERE {UNSETTLED_BETWEEN_FILTER}"),
+        (
+            precise_timestamp(OffsetDateTime::now_utc()),
+            a,
+            b,
+            a,
+            b,
+            b,
+            a,
+        ),
+    )
+    .await
+}
+
+/// Every record `list_unsettled_between` would return, oldest first.
+pub async fn list_all_unsettled_between(
+    conn: &Connection,
+    a: &str,
+    b: &str,
+) -> Result<Vec<SplitRecordRow>, libsql::Error> {
+    query_split_records(
+        conn,
+        &format!("{SPLIT_RECORD_SELECT} WHERE r.owner_user_id IN (?, ?) AND r.deleted_at IS NULL AND r.pending = 0 AND r.settle = 0 AND r.split_id IS NOT NULL AND ((r.debtor_user_id = ? AND r.creditor_user_id = ?) OR (r.debtor_user_id = ? AND r.creditor_user_id = ?)) ORDER BY r.date, r.seq"),
+        (a, b, a, b, b, a),
+    )
+    .await
+}
+
+/// Settles the given records, skipping any that are no longer unsettled between `a` and `b`.
+pub async fn settle_between_by_ids(
+    conn: &Connection,
+    a: &str,
+    b: &str,
+    record_ids: &[String],
+) -> Result<u64, libsql::Error> {
+    if record_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let sql = format!(
+        "UPDATE records SET settle = 1, settled_at = ? WHERE {UNSETTLED_BETWEEN_FILTER} AND id IN ({})",
+        sql_placeholders(record_ids.len())
+    );
+    let mut params = vec![libsql::Value::from(precise_timestamp(
+        OffsetDateTime::now_utc(),
+    ))];
+    params.extend(
+        [a, b, a, b, b, a]
+            .into_iter()
+            .map(|id| libsql::Value::from(id.to_string())),
+    );
+    params.extend(record_ids.iter().cloned().map(libsql::Value::from));
+    conn.execute(&sql, params).await
+}
+
+pub async fn insert_settle_up_record(
+    conn: &Connection,
+    record: &NewSettleUpRecord<'_>,
+) -> Result<(), libsql::Error> {
+    let name = crypto::seal_record_name(conn, record.owner_user_id, record.name).await?;
+    let date = to_db_date(record.date)?;
+    conn.execute(
+        "INSERT INTO records (id, owner_user_id, name, amount, currency, category_id, date, created_via, created_at) VALUES (?, ?, ?, ?, ?, NULL, ?, ?, ?)",
+        (
+            record.id,
+            record.owner_user_id,
+            name.as_str(),
+            record.amount,
+            record.currency,
+            date,
+            CREATED_VIA_SETTLE_UP,
+            precise_timestamp(OffsetDateTime::now_utc()),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Split membership of the given records owned by `owner_user_id`, in one query.
+/// Records that are not part of a split are left out.
+pub async fn list_split_memberships(
+    conn: &Connection,
+    owner_user_id: &str,
+    record_ids: &[String],
+) -> Result<Vec<SplitMembership>, libsql::Error> {
+    if record_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let sql = format!(
+        "SELECT id, split_id, creditor_user_id, settle FROM records WHERE owner_user_id = ? AND deleted_at IS NULL AND split_id IS NOT NULL AND id IN ({})",
+        sql_placeholders(record_ids.len())
+    );
+    let mut params = vec![libsql::Value::from(owner_user_id.to_string())];
+    params.extend(record_ids.iter().cloned().map(libsql::Value::from));
+    let mut rows = conn.query(&sql, params).await?;
+    let mut memberships = Vec::new();
+    while let Some(row) = rows.next().await? {
+        memberships.push(SplitMembership {
+            record_id: row.get(0)?,
+            split_id: row.get(1)?,
+            creditor_user_id: row.get(2)?,
+            settle: row.get(3)?,
+        });
+    }
+    Ok(memberships)
+}
+
+/// Participant counts and outstanding amounts for the given splits paid by
+/// `creditor_user_id`, grouped by split in one query. The payer's own record is excluded.
+pub async fn list_split_progress(
+    conn: &Connection,
+    creditor_user_id: &str,
+    split_ids: &[String],
+) -> Result<Vec<SplitProgressRow>, libsql::Error> {
+    if split_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let sql = format!(
+        "SELECT split_id, COUNT(*), SUM(CASE WHEN settle = 1 THEN 1 ELSE 0 END), COALESCE(SUM(CASE WHEN settle = 0 THEN ABS(amount) ELSE 0 END), 0) FROM records WHERE creditor_user_id = ? AND owner_user_id != creditor_user_id AND deleted_at IS NULL AND split_id IN ({}) GROUP BY split_id",
+        sql_placeholders(split_ids.len())
+    );
+    let mut params = vec![libsql::Value::from(creditor_user_id.to_string())];
+    params.extend(split_ids.iter().cloned().map(libsql::Value::from));
+    let mut rows = conn.query(&sql, params).await?;
+    let mut progress = Vec::new();
+    while let Some(row) = rows.next().await? {
+        progress.push(SplitProgressRow {
+            split_id: row.get(0)?,
+            participants_total: row.get(1)?,
+            participants_settled: row.get(2)?,
+            amount_outstanding: Money::from_cents(row.get(3)?),
+        });
+    }
+    Ok(progress)
+}
+
+/// The key's entry unless it expired by `now`; an expired key counts as never used.
+pub async fn find_idempotency_entry(
+    conn: &Connection,
+    key: &str,
+    user_id: &str,
+    endpoint: &str,
+    now: &str,
+) -> Result<Option<IdempotencyEntry>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT response_status, response_body, payload_hash, response_digest, created_at FROM idempotency_keys WHERE key = ? AND user_id = ? AND endpoint = ? AND expires_at > ?",
+            (key, user_id, endpoint, now),
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => Ok(Some(IdempotencyEntry {
+            response_status: row.get(0)?,
+            response_body: row.get(1)?,
+            payload_hash: row.get(2)?,
+            response_digest: row.get(3)?,
+            created_at: row.get(4)?,
+        })),
+        None => Ok(None),
+    }
+}
+
+/// Inserts an in-flight reservation (`response_body = NULL`).
+pub async fn reserve_idempotency_entry(
+    conn: &Connection,
+    entry: &NewIdempotencyEntry<'_>,
+) -> Result<(), libsql::Error> {
+    conn.execute(
+        "INSERT INTO idempotency_keys (id, key, user_id, endpoint, payload_hash, response_status, response_body, created_at, expires_at) VALUES (?, ?, ?, ?, ?, ?, NULL, ?, ?)",
+        (
+            entry.id,
+            entry.key,
+            entry.user_id,
+            entry.endpoint,
+            entry.payload_hash,
+            0i64,
+            entry.created_at,
+            entry.expires_at,
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Stores the response; an oversized one is stored as `response_digest` with an empty body.
+pub async fn commit_idempotency_entry(
+    conn: &Connection,
+    key: &str,
+    user_id: &str,
+    endpoint: &str,
+    response_status: i64,
+    response_body: &str,
+    response_digest: Option<&str>,
+) -> Result<(), libsql::Error> {
+    conn.execute(
+        "UPDATE idempotency_keys SET response_status = ?, response_body = ?, response_digest = ? WHERE key = ? AND user_id = ? AND endpoint = ?",
+        (response_status, response_body, response_digest, key, user_id, endpoint),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Ids of keys whose `expires_at` is at or before `now`.
+pub async fn list_expired_idempotency_ids(
+    conn: &Connection,
+    now: &str,
+) -> Result<Vec<String>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT id FROM idempotency_keys WHERE expires_at <= ? ORDER BY expires_at, id",
+            [now],
+        )
+        .await?;
+    let mut ids = Vec::new();
+    while let Some(row) = rows.next().await? {
+        ids.push(row.get(0)?);
+    }
+    Ok(ids)
+}
+
+/// Deletes the user's keys that expired by `now`, so an expired key can be reserved again.
+pub async fn delete_expired_idempotency_entries(
+    conn: &Connection,
+    user_id: &str,
+    now: &str,
+) -> Result<u64, libsql::Error> {
+    conn.execute(
+        "DELETE FROM idempotency_keys WHERE user_id = ? AND expires_at <= ?",
+        (user_id, now),
+    )
+    .await
+}
+
+pub async fn delete_idempotency_entries(
+    conn: &Connection,
+    ids: &[String],
+) -> Result<u64, libsql::Error> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    let params: Vec<libsql::Value> = ids.iter().cloned().map(libsql::Value::from).collect();
+    conn.execute(
+        &format!(
+            "DELETE FROM idempotency_keys WHERE id IN ({})",
+            sql_placeholders(ids.len())
+        ),
+        params,
+    )
+    .await
+}
+
+/// Deletes the key only while it is still an in-flight reservation.
+pub async fn delete_idempotency_reservation(
+    conn: &Connection,
+    key: &str,
+    user_id: &str,
+    endpoint: &str,
+) -> Result<(), libsql::Error> {
+    conn.execute(
+        "DELETE FROM idempotency_keys WHERE key = ? AND user_id = ? AND endpoint = ? AND response_body IS NULL",
+        (key, user_id, endpoint),
+    )
+    .await?;
+    Ok(())
+}
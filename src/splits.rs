@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use axum::{
     Json,
     extract::{Path, Query, State},
@@ -10,20 +12,31 @@ use uuid::Uuid;
 
 use crate::auth::get_current_user;
 use crate::constants::*;
+use crate::friendship_repo;
+use crate::idempotency::{IdempotencyScope, run_idempotent, validate_idempotency_key};
 use crate::models::{
-    CreateSplitPayload, PendingSplitsQuery, SplitListItem, SplitListResponse, SplitParticipant,
-    UnsettledSplitsQuery,
+    CancelSplitResponse, CreateSplitPayload, CurrencySettleUp, ExecuteSettleUpPayload,
+    PendingSplitsQuery, ReopenQuery, SettleSplitPayload, SettleSplitResponse, SettleUpPayment,
+    SettleUpPlan, SettleUpResponse, SplitDepartedParticipant, SplitDetail, SplitDetailParticipant,
+    SplitListItem, SplitListResponse, SplitNotice, SplitParticipant, SplitProgress, SplitSummary,
+    SplitSummaryListResponse, SplitsQuery, UnsettledSplitsQuery,
+};
+use crate::money::Money;
+use crate::notifications;
+use crate::outbox;
+use crate::records::{get_category_for_new_record, normalize_record_currency};
+use crate::settings::{guard_closed_period, user_currency_code};
+use crate::split_repo::{
+    self, NewSettleUpRecord, NewSplitRecord, SplitDepartureRow, SplitRecordRow, SplitRole,
+    SplitShareRow,
 };
 use crate::utils::{
-    calculate_split_amounts, db_error, db_error_with_context, validate_category_exists,
-    validate_date, validate_offset, validate_records_limit, validate_split_participants,
-    validate_string_length,
+    calculate_split_amounts, database_busy, db_error_with_context, validate_date, validate_offset,
+    validate_records_limit, validate_split_participants, validate_string_length,
 };
 use crate::{AppState, TransactionError, with_transaction};
 
 const SPLIT_CREATE_ENDPOINT: &str = "/splits/create";
-const IDEMPOTENCY_TTL_HOURS: i64 = 24;
-const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
 
 enum SplitRecordError {
     Transaction,
@@ -36,17 +49,107 @@ impl From<TransactionError> for SplitRecordError {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreateSplitResponse {
     pub split_id: String,
     pub payer_record_id: String,
     pub pending_record_ids: Vec<String>,
 }
 
-struct CachedIdempotency {
-    response_status: i64,
-    response_body: String,
-    payload_hash: String,
+enum SettleUpError {
+    Transaction,
+    Db,
+    Stale,
+}
+
+impl From<TransactionError> for SettleUpError {
+    fn from(_: TransactionError) -> Self {
+        Self::Transaction
+    }
+}
+
+enum CancelSplitError {
+    Transaction(TransactionError),
+    Db(&'static str),
+    NotFound,
+    NotInitiator,
+    Accepted,
+    Rejected((StatusCode, String)),
+}
+
+impl From<TransactionError> for CancelSplitError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+impl From<CancelSplitError> for (StatusCode, String) {
+    fn from(value: CancelSplitError) -> Self {
+        match value {
+            CancelSplitError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction")
+            }
+            CancelSplitError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction")
+            }
+            CancelSplitError::Transaction(TransactionError::Busy) => database_busy(),
+            CancelSplitError::Db(ctx) => db_error_with_context(ctx),
+            CancelSplitError::NotFound => {
+                (StatusCode::NOT_FOUND, SPLIT_NOT_FOUND_MESSAGE.to_string())
+            }
+            CancelSplitError::NotInitiator => (
+                StatusCode::FORBIDDEN,
+                "Only the split's initiator can cancel it".to_string(),
+            ),
+            CancelSplitError::Accepted => (
+                StatusCode::CONFLICT,
+                format!(
+                    "{SPLIT_NOT_CANCELLABLE_PREFIX}: a participant already accepted or settled their share"
+                ),
+            ),
+            CancelSplitError::Rejected(error) => error,
+        }
+    }
+}
+
+enum SettleSplitError {
+    Transaction(TransactionError),
+    Db(&'static str),
+    NotFound,
+    NotInitiator,
+    NotParticipant,
+}
+
+impl From<TransactionError> for SettleSplitError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+impl From<SettleSplitError> for (StatusCode, String) {
+    fn from(value: SettleSplitError) -> Self {
+        match value {
+            SettleSplitError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction")
+            }
+            SettleSplitError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction")
+            }
+            SettleSplitError::Transaction(TransactionError::Busy) => database_busy(),
+            SettleSplitError::Db(ctx) => db_error_with_context(ctx),
+            SettleSplitError::NotFound => {
+                (StatusCode::NOT_FOUND, SPLIT_NOT_FOUND_MESSAGE.to_string())
+            }
+            SettleSplitError::NotInitiator => (
+                StatusCode::FORBIDDEN,
+                "Only the split's initiator can settle it".to_string(),
+            ),
+            SettleSplitError::NotParticipant => (
+                StatusCode::BAD_REQUEST,
+                "Friend is not a participant of this split".to_string(),
+            ),
+        }
+    }
 }
 
 pub async fn create_split(
@@ -55,151 +158,359 @@ pub async fn create_split(
     Json(payload): Json<CreateSplitPayload>,
 ) -> Result<(StatusCode, Json<CreateSplitResponse>), (StatusCode, String)> {
     let current_user = get_current_user(&session).await?;
-    validate_split_create_payload(&payload, &current_user.id)?;
-    validate_all_participants_are_friends(&app_state, &current_user.id, &payload.splits).await?;
+    let (status, response) = create_split_for_user(&app_state, &current_user.id, payload).await?;
 
-    let payload_hash = compute_payload_hash(&payload)?;
-    if let Some(cached) =
-        get_existing_idempotency_response(&app_state, &current_user.id, &payload.idempotency_key)
-            .await?
-    {
-        if cached.payload_hash != payload_hash {
-            return Err((
-                StatusCode::CONFLICT,
-                "Idempotency key already used with different payload".to_string(),
-            ));
-        }
+    Ok((status, Json(response)))
+}
 
-        let response =
-            serde_json::from_str::<CreateSplitResponse>(&cached.response_body).map_err(|_| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to deserialize idempotency response".to_string(),
-                )
-            })?;
+/// Validates and fans out a split paid by `user_id`, replaying the stored response
+/// when the idempotency key was already used with the same payload.
+pub async fn create_split_for_user(
+    app_state: &AppState,
+    user_id: &str,
+    payload: CreateSplitPayload,
+) -> Result<(StatusCode, CreateSplitResponse), (StatusCode, String)> {
+    validate_split_create_payload(&payload, user_id)?;
+    validate_all_participants_are_friends(app_state, user_id, &payload.splits).await?;
+    let currency = split_currency(app_state, user_id, payload.currency.as_deref()).await?;
+
+    let scope = IdempotencyScope {
+        user_id,
+        endpoint: SPLIT_CREATE_ENDPOINT,
+        key: &payload.idempotency_key,
+    };
+    // The key is reserved before any record is written, so a retry while the records
+    // are still being written cannot start a second split.
+    run_idempotent(app_state, scope, &payload, StatusCode::CREATED, async {
+        let split_id = Uuid::new_v4().to_string();
+        let (payer_record_id, pending_record_ids) =
+            create_split_records(app_state, user_id, &split_id, &currency, &payload).await?;
+        Ok(CreateSplitResponse {
+            split_id,
+            payer_record_id,
+            pending_record_ids,
+        })
+    })
+    .await
+}
 
-        let status = StatusCode::from_u16(cached.response_status as u16).map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Invalid cached response status".to_string(),
-            )
-        })?;
+pub async fn list_pending_splits(
+    State(app_state): State<AppState>,
+    session: Session,
+    Query(query): Query<PendingSplitsQuery>,
+) -> Result<(StatusCode, Json<SplitListResponse>), (StatusCode, String)> {
+    let current_user = get_current_user(&session).await?;
+    let limit = validate_records_limit(query.limit)?;
+    let offset = validate_offset(query.offset)?;
 
-        return Ok((status, Json(response)));
-    }
+    let conn = app_state.main_db.read().await;
+
+    let raw_count = split_repo::count_pending(&conn, &current_user.id)
+        .await
+        .map_err(|_| db_error_with_context("failed to count pending splits"))?;
+    let total_count = u32::try_from(raw_count)
+        .map_err(|_| db_error_with_context("pending split count exceeds u32"))?;
 
-    let split_id = Uuid::new_v4().to_string();
-    let now = now_rfc3339()?;
-    let expires_at = (time::OffsetDateTime::now_utc()
-        + time::Duration::hours(IDEMPOTENCY_TTL_HOURS))
-    .format(&time::format_description::well_known::Rfc3339)
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    // Reserve the idempotency key before writing any records. This ensures that
-    // if the fanout partially succeeds and then fails, a client retry with the
-    // same key will see the reservation (response_body = NULL) and get a 500
-    // rather than re-running the fanout and creating duplicate records.
-    reserve_idempotency_entry(
-        &app_state,
-        &payload.idempotency_key,
+    let splits = split_repo::list_pending(&conn, &current_user.id, limit, offset)
+        .await
+        .map_err(|_| db_error_with_context("failed to query pending splits"))?
+        .into_iter()
+        .map(|row| split_list_item_from_row(row, &current_user.id))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((
+        StatusCode::OK,
+        Json(SplitListResponse {
+            splits,
+            total_count,
+            limit,
+            offset,
+        }),
+    ))
+}
+
+pub async fn get_split(
+    State(app_state): State<AppState>,
+    session: Session,
+    Path(split_id): Path<String>,
+) -> Result<(StatusCode, Json<SplitDetail>), (StatusCode, String)> {
+    let current_user = get_current_user(&session).await?;
+
+    let conn = app_state.main_db.read().await;
+    let split = load_split(&conn, &split_id)
+        .await?
+        .filter(|split| split_visible_to(split, &current_user.id))
+        .ok_or_else(|| (StatusCode::NOT_FOUND, SPLIT_NOT_FOUND_MESSAGE.to_string()))?;
+
+    Ok((StatusCode::OK, Json(split)))
+}
+
+/// Undoes a split nobody has acted on yet: the payer's record and every participant's
+/// pending record are deleted in one transaction, so a failed attempt can simply be
+/// retried. Once any participant accepted (finalized) or settled their share the split
+/// stands, and the caller gets 409.
+pub async fn cancel_split(
+    State(app_state): State<AppState>,
+    session: Session,
+    Path(split_id): Path<String>,
+    Query(reopen): Query<ReopenQuery>,
+) -> Result<(StatusCode, Json<CancelSplitResponse>), (StatusCode, String)> {
+    let current_user = get_current_user(&session).await?;
+    let response = cancel_split_for_user(
+        &app_state.main_db,
         &current_user.id,
-        &payload_hash,
-        &now,
-        &expires_at,
+        &split_id,
+        reopen.reopen.unwrap_or(false),
     )
     .await?;
 
-    let fanout_result =
-        create_split_records(&app_state, &current_user.id, &split_id, &payload).await;
-
-    let (payer_record_id, pending_record_ids) = match fanout_result {
-        Ok(ids) => ids,
-        Err(e) => {
-            // Fanout failed — delete the reservation so the client can retry
-            // cleanly with the same idempotency key.
-            let _ = delete_idempotency_reservation(
-                &app_state,
-                &payload.idempotency_key,
-                &current_user.id,
-            )
-            .await;
-            return Err(e);
-        }
-    };
+    Ok((StatusCode::OK, Json(response)))
+}
 
-    let response = CreateSplitResponse {
-        split_id,
-        payer_record_id,
-        pending_record_ids,
-    };
+pub async fn cancel_split_for_user(
+    db: &crate::Db,
+    user_id: &str,
+    split_id: &str,
+    reopen: bool,
+) -> Result<CancelSplitResponse, (StatusCode, String)> {
+    let user_id = user_id.to_string();
+    let split_id = split_id.to_string();
 
-    let response_body = serde_json::to_string(&response).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to serialize response: {}", e),
-        )
-    })?;
+    let response = with_transaction(db, move |conn| {
+        Box::pin(async move {
+            let shares = split_repo::list_split_shares(conn, std::slice::from_ref(&split_id))
+                .await
+                .map_err(|_| CancelSplitError::Db("failed to query split records"))?;
+            let split = split_detail_from_shares(&split_id, &shares.iter().collect::<Vec<_>>())
+                .filter(|split| split_visible_to(split, &user_id))
+                .ok_or(CancelSplitError::NotFound)?;
+            if split.initiator_user_id != user_id {
+                return Err(CancelSplitError::NotInitiator);
+            }
+            if split
+                .participants
+                .iter()
+                .any(|participant| !participant.pending || participant.settled)
+            {
+                return Err(CancelSplitError::Accepted);
+            }
+
+            if let Some(payer) = shares.iter().find(|share| share.owner_user_id == user_id) {
+                guard_closed_period(
+                    conn,
+                    &user_id,
+                    Some(&payer.record_id),
+                    "delete",
+                    &[&payer.date],
+                    reopen,
+                )
+                .await
+                .map_err(CancelSplitError::Rejected)?;
+            }
+
+            split_repo::delete_split_records(conn, &split_id)
+                .await
+                .map_err(|_| CancelSplitError::Db("failed to delete split records"))?;
+
+            Ok(CancelSplitResponse {
+                split_id,
+                status: SPLIT_STATUS_CANCELLED.to_string(),
+                removed_record_ids: shares.into_iter().map(|share| share.record_id).collect(),
+            })
+        })
+    })
+    .await?;
 
-    // Commit the response body into the reservation. If this fails, records are
-    // already written — we still return 201; the key just won't deduplicate a
-    // future retry (acceptable: rare, and the payer will see their records).
-    let _ = commit_idempotency_entry(
-        &app_state,
-        &payload.idempotency_key,
+    Ok(response)
+}
+
+/// Marks the participant records of a split as repaid in one go, instead of one
+/// `PUT /records/{id}/settle` per record. Only the initiator may call it; `friend_id`
+/// narrows it to that participant. Already-settled records are left alone, so repeating
+/// the call is a no-op.
+pub async fn settle_split(
+    State(app_state): State<AppState>,
+    session: Session,
+    Path(split_id): Path<String>,
+    Json(payload): Json<SettleSplitPayload>,
+) -> Result<(StatusCode, Json<SettleSplitResponse>), (StatusCode, String)> {
+    let current_user = get_current_user(&session).await?;
+    let response = settle_split_for_user(
+        &app_state.main_db,
         &current_user.id,
-        i64::from(StatusCode::CREATED.as_u16()),
-        &response_body,
+        &split_id,
+        payload.friend_id.as_deref(),
     )
-    .await;
+    .await?;
 
-    Ok((StatusCode::CREATED, Json(response)))
+    Ok((StatusCode::OK, Json(response)))
 }
 
-pub async fn list_pending_splits(
+pub async fn settle_split_for_user(
+    db: &crate::Db,
+    user_id: &str,
+    split_id: &str,
+    friend_id: Option<&str>,
+) -> Result<SettleSplitResponse, (StatusCode, String)> {
+    let user_id = user_id.to_string();
+    let split_id = split_id.to_string();
+    let friend_id = friend_id.map(str::to_string);
+
+    let (response, notices) = with_transaction(db, move |conn| {
+        Box::pin(async move {
+            let shares = split_repo::list_split_shares(conn, std::slice::from_ref(&split_id))
+                .await
+                .map_err(|_| SettleSplitError::Db("failed to query split records"))?;
+            let split = split_detail_from_shares(&split_id, &shares.iter().collect::<Vec<_>>())
+                .filter(|split| split_visible_to(split, &user_id))
+                .ok_or(SettleSplitError::NotFound)?;
+            if split.initiator_user_id != user_id {
+                return Err(SettleSplitError::NotInitiator);
+            }
+
+            let live_shares: Vec<&SplitShareRow> = shares
+                .iter()
+                .filter(|share| share.owner_user_id != user_id && !share.deleted)
+                .filter(|share| {
+                    friend_id
+                        .as_deref()
+                        .is_none_or(|friend_id| share.owner_user_id == friend_id)
+                })
+                .collect();
+            if friend_id.is_some() && live_shares.is_empty() {
+                return Err(SettleSplitError::NotParticipant);
+            }
+
+            let settled_record_ids: Vec<String> = live_shares
+                .iter()
+                .filter(|share| !share.settle)
+                .map(|share| share.record_id.clone())
+                .collect();
+            split_repo::settle_split_shares(conn, &split_id, &settled_record_ids)
+                .await
+                .map_err(|_| SettleSplitError::Db("failed to settle split records"))?;
+            let mut notices = Vec::new();
+            for share in live_shares.iter().filter(|share| !share.settle) {
+                notices.push((
+                    share.owner_user_id.clone(),
+                    SplitNotice {
+                        split_id: split_id.clone(),
+                        record_id: share.record_id.clone(),
+                        from_user_id: user_id.clone(),
+                    },
+                ));
+                notifications::notify(
+                    conn,
+                    &share.owner_user_id,
+                    NOTIFICATION_KIND_SPLIT_SETTLED,
+                    json!({
+                        "split_id": split_id,
+                        "record_id": share.record_id,
+                        "settled_by_user_id": user_id,
+                    }),
+                )
+                .await
+                .map_err(|_| SettleSplitError::Db("failed to queue notifications"))?;
+            }
+
+            let shares = split_repo::list_split_shares(conn, std::slice::from_ref(&split_id))
+                .await
+                .map_err(|_| SettleSplitError::Db("failed to query split records"))?;
+            let status = split_detail_from_shares(&split_id, &shares.iter().collect::<Vec<_>>())
+                .map_or_else(|| split.status.clone(), |split| split.status);
+
+            Ok((
+                SettleSplitResponse {
+                    split_id,
+                    status,
+                    settled_record_ids,
+                },
+                notices,
+            ))
+        })
+    })
+    .await?;
+
+    outbox::queue_split_notices(db, OUTBOX_KIND_SPLIT_SETTLED, &notices).await;
+    Ok(response)
+}
+
+/// Rebuilds `split_id` from its records; `None` when none are left.
+pub async fn load_split(
+    conn: &libsql::Connection,
+    split_id: &str,
+) -> Result<Option<SplitDetail>, (StatusCode, String)> {
+    let split_ids = [split_id.to_string()];
+    let shares = split_repo::list_split_shares(conn, &split_ids)
+        .await
+        .map_err(|_| db_error_with_context("failed to query split records"))?;
+    let departures = split_repo::list_split_departures(conn, &split_ids)
+        .await
+        .map_err(|_| db_error_with_context("failed to query split departures"))?;
+    Ok(
+        split_detail_from_shares(split_id, &shares.iter().collect::<Vec<_>>())
+            .map(|split| with_departures(split, &departures)),
+    )
+}
+
+/// Rebuilds the given splits in order, with two queries for all of them; ids with no
+/// records left are skipped.
+pub async fn load_splits(
+    conn: &libsql::Connection,
+    split_ids: &[String],
+) -> Result<Vec<SplitDetail>, (StatusCode, String)> {
+    let shares = split_repo::list_split_shares(conn, split_ids)
+        .await
+        .map_err(|_| db_error_with_context("failed to query split records"))?;
+    let departures = split_repo::list_split_departures(conn, split_ids)
+        .await
+        .map_err(|_| db_error_with_context("failed to query split departures"))?;
+
+    Ok(split_ids
+        .iter()
+        .filter_map(|split_id| {
+            let own: Vec<&SplitShareRow> = shares
+                .iter()
+                .filter(|share| &share.split_id == split_id)
+                .collect();
+            split_detail_from_shares(split_id, &own)
+        })
+        .map(|split| with_departures(split, &departures))
+        .collect())
+}
+
+pub async fn list_splits(
     State(app_state): State<AppState>,
     session: Session,
-    Query(query): Query<PendingSplitsQuery>,
-) -> Result<(StatusCode, Json<SplitListResponse>), (StatusCode, String)> {
+    Query(query): Query<SplitsQuery>,
+) -> Result<(StatusCode, Json<SplitSummaryListResponse>), (StatusCode, String)> {
     let current_user = get_current_user(&session).await?;
+    let role = parse_split_role(query.role.as_deref())?;
+    let status = parse_split_status(query.status.as_deref())?;
     let limit = validate_records_limit(query.limit)?;
     let offset = validate_offset(query.offset)?;
 
     let conn = app_state.main_db.read().await;
 
-    let mut count_rows = conn
-        .query(
-            "SELECT COUNT(*) FROM records WHERE owner_user_id = ? AND pending = 1 AND split_id IS NOT NULL",
-            [current_user.id.as_str()],
-        )
-        .await
-        .map_err(|_| db_error_with_context("failed to count pending splits"))?;
-
-    let total_count: u32 = if let Some(row) = count_rows.next().await.map_err(|_| db_error())? {
-        let raw_count: i64 = row
-            .get(0)
-            .map_err(|_| db_error_with_context("invalid pending split count"))?;
-        u32::try_from(raw_count)
-            .map_err(|_| db_error_with_context("pending split count exceeds u32"))?
-    } else {
-        0
-    };
-
-    let mut rows = conn
-        .query(
-            "SELECT r.id, r.split_id, r.name, r.date, r.amount, r.debtor_user_id, r.creditor_user_id, COALESCE(creditor_user.name, ''), COALESCE(debtor_user.name, ''), r.pending, r.settle FROM records r LEFT JOIN users creditor_user ON creditor_user.id = r.creditor_user_id LEFT JOIN users debtor_user ON debtor_user.id = r.debtor_user_id WHERE r.owner_user_id = ? AND r.pending = 1 AND r.split_id IS NOT NULL ORDER BY r.date DESC, r.id DESC LIMIT ? OFFSET ?",
-            (current_user.id.as_str(), limit, offset),
-        )
+    let raw_count = split_repo::count_splits(&conn, &current_user.id, role, status)
         .await
-        .map_err(|_| db_error_with_context("failed to query pending splits"))?;
+        .map_err(|_| db_error_with_context("failed to count splits"))?;
+    let total_count =
+        u32::try_from(raw_count).map_err(|_| db_error_with_context("split count exceeds u32"))?;
 
-    let mut splits = Vec::new();
-    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
-        splits.push(split_list_item_from_row(row, &current_user.id)?);
-    }
+    let split_ids =
+        split_repo::list_split_ids(&conn, &current_user.id, role, status, limit, offset)
+            .await
+            .map_err(|_| db_error_with_context("failed to query splits"))?;
+    let splits = load_splits(&conn, &split_ids)
+        .await?
+        .into_iter()
+        .map(|split| split_summary(split, &current_user.id))
+        .collect();
 
     Ok((
         StatusCode::OK,
-        Json(SplitListResponse {
+        Json(SplitSummaryListResponse {
             splits,
             total_count,
             limit,
@@ -208,6 +519,185 @@ pub async fn list_pending_splits(
     ))
 }
 
+fn parse_split_role(role: Option<&str>) -> Result<SplitRole, (StatusCode, String)> {
+    match role.map(str::trim) {
+        None | Some("") => Ok(SplitRole::Any),
+        Some(SPLIT_ROLE_INITIATOR) => Ok(SplitRole::Initiator),
+        Some(SPLIT_ROLE_PARTICIPANT) => Ok(SplitRole::Participant),
+        Some(_) => Err((
+            StatusCode::BAD_REQUEST,
+            format!("role must be {SPLIT_ROLE_INITIATOR} or {SPLIT_ROLE_PARTICIPANT}"),
+        )),
+    }
+}
+
+fn parse_split_status(status: Option<&str>) -> Result<Option<&'static str>, (StatusCode, String)> {
+    match status.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(status) => SPLIT_STATUSES
+            .iter()
+            .find(|known| **known == status)
+            .map(|known| Some(*known))
+            .ok_or_else(|| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("status must be one of {}", SPLIT_STATUSES.join(", ")),
+                )
+            }),
+    }
+}
+
+fn split_summary(split: SplitDetail, user_id: &str) -> SplitSummary {
+    let live: Vec<&SplitDetailParticipant> = split
+        .participants
+        .iter()
+        .filter(|participant| participant.record_exists)
+        .collect();
+    let progress = SplitProgress {
+        participants_total: live.len() as u32,
+        participants_settled: live
+            .iter()
+            .filter(|participant| participant.settled)
+            .count() as u32,
+        amount_outstanding: live
+            .iter()
+            .filter(|participant| !participant.settled)
+            .map(|participant| participant.amount)
+            .sum(),
+    };
+    let role = if split.initiator_user_id == user_id {
+        SPLIT_ROLE_INITIATOR
+    } else {
+        SPLIT_ROLE_PARTICIPANT
+    };
+    SplitSummary {
+        split_id: split.split_id,
+        role: role.to_string(),
+        status: split.status,
+        total_amount: split.total_amount,
+        description: split.description,
+        date: split.date,
+        initiator_user_id: split.initiator_user_id,
+        initiator_name: split.initiator_name,
+        progress,
+    }
+}
+
+/// Only the initiator and the participants may see a split.
+pub fn split_visible_to(split: &SplitDetail, user_id: &str) -> bool {
+    split.initiator_user_id == user_id
+        || split
+            .participants
+            .iter()
+            .any(|participant| participant.user_id == user_id)
+}
+
+/// Every split record is credited to the initiator. Their own record (owned, owed and
+/// credited by them) holds their share, description and date, or the whole total when
+/// booked with `legacy_full_total`; every other record is a participant's share.
+fn split_detail_from_shares(split_id: &str, shares: &[&SplitShareRow]) -> Option<SplitDetail> {
+    let initiator_user_id = shares
+        .iter()
+        .find_map(|share| share.creditor_user_id.clone())?;
+    let payer = shares
+        .iter()
+        .find(|share| share.owner_user_id == initiator_user_id)
+        .copied();
+    let header = payer.or(shares.first().copied())?;
+
+    let participants: Vec<SplitDetailParticipant> = shares
+        .iter()
+        .filter(|share| share.owner_user_id != initiator_user_id)
+        .map(|share| SplitDetailParticipant {
+            user_id: share.owner_user_id.clone(),
+            username: share.owner_name.clone(),
+            amount: share.amount.abs(),
+            record_exists: !share.deleted,
+            pending: share.pending,
+            settled: share.settle,
+        })
+        .collect();
+
+    let payer_amount = payer.map_or(Money::ZERO, |share| share.amount.abs());
+    let legacy_full_total = payer.is_some_and(|share| share.full_total);
+    let shares_total: Money = participants
+        .iter()
+        .map(|participant| participant.amount)
+        .sum();
+    let (initiator_share, total_amount) = if legacy_full_total {
+        (payer_amount - shares_total, payer_amount)
+    } else {
+        (payer_amount, payer_amount + shares_total)
+    };
+    let receivable = participants
+        .iter()
+        .filter(|participant| participant.record_exists && !participant.settled)
+        .map(|participant| participant.amount)
+        .sum();
+
+    let live: Vec<&SplitDetailParticipant> = participants
+        .iter()
+        .filter(|participant| participant.record_exists)
+        .collect();
+    let status = if live.iter().any(|participant| participant.pending) {
+        SPLIT_STATUS_INITIATED
+    } else if !live.is_empty() && live.iter().all(|participant| participant.settled) {
+        SPLIT_STATUS_SETTLED
+    } else {
+        SPLIT_STATUS_COMPLETED
+    };
+
+    let initiator_name = payer
+        .map(|share| share.owner_name.clone())
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| initiator_user_id.clone());
+
+    Some(SplitDetail {
+        split_id: split_id.to_string(),
+        status: status.to_string(),
+        total_amount,
+        description: header.description.clone(),
+        date: header.date.clone(),
+        initiator_user_id,
+        initiator_name,
+        initiator_share,
+        receivable,
+        legacy_full_total,
+        participants,
+        departed_participants: Vec::new(),
+    })
+}
+
+/// Adds the split's departed participants (from rows of any split) to `split`. Their
+/// shares stay in `total_amount` but not in the status, which follows live records only.
+/// A whole-total initiator record already counts them, so there they come out of
+/// `initiator_share` instead.
+fn with_departures(mut split: SplitDetail, departures: &[SplitDepartureRow]) -> SplitDetail {
+    split.departed_participants = departures
+        .iter()
+        .filter(|departure| departure.split_id == split.split_id)
+        .map(|departure| SplitDepartedParticipant {
+            user_id: departure.user_id.clone(),
+            username: departure.username.clone(),
+            amount: departure.amount,
+            pending: departure.pending,
+            settled: departure.settled,
+            departed_at: departure.departed_at.clone(),
+        })
+        .collect();
+    let departed: Money = split
+        .departed_participants
+        .iter()
+        .map(|departed| departed.amount)
+        .sum();
+    if split.legacy_full_total {
+        split.initiator_share -= departed;
+    } else {
+        split.total_amount += departed;
+    }
+    split
+}
+
 pub async fn list_unsettled_splits_with_friend(
     State(app_state): State<AppState>,
     session: Session,
@@ -229,52 +719,19 @@ pub async fn list_unsettled_splits_with_friend(
 
     let conn = app_state.main_db.read().await;
 
-    let mut count_rows = conn
-        .query(
-            "SELECT COUNT(*) FROM records WHERE owner_user_id IN (?, ?) AND pending = 0 AND settle = 0 AND split_id IS NOT NULL AND ((debtor_user_id = ? AND creditor_user_id = ?) OR (debtor_user_id = ? AND creditor_user_id = ?))",
-            (
-                current_user.id.as_str(),
-                friend_id.as_str(),
-                current_user.id.as_str(),
-                friend_id.as_str(),
-                friend_id.as_str(),
-                current_user.id.as_str(),
-            ),
-        )
+    let raw_count = split_repo::count_unsettled_between(&conn, &current_user.id, &friend_id)
         .await
         .map_err(|_| db_error_with_context("failed to count unsettled splits"))?;
+    let total_count = u32::try_from(raw_count)
+        .map_err(|_| db_error_with_context("unsettled split count exceeds u32"))?;
 
-    let total_count: u32 = if let Some(row) = count_rows.next().await.map_err(|_| db_error())? {
-        let raw_count: i64 = row
-            .get(0)
-            .map_err(|_| db_error_with_context("invalid unsettled split count"))?;
-        u32::try_from(raw_count)
-            .map_err(|_| db_error_with_context("unsettled split count exceeds u32"))?
-    } else {
-        0
-    };
-
-    let mut rows = conn
-        .query(
-            "SELECT r.id, r.split_id, r.name, r.date, r.amount, r.debtor_user_id, r.creditor_user_id, COALESCE(creditor_user.name, ''), COALESCE(debtor_user.name, ''), r.pending, r.settle FROM records r LEFT JOIN users creditor_user ON creditor_user.id = r.creditor_user_id LEFT JOIN users debtor_user ON debtor_user.id = r.debtor_user_id WHERE r.owner_user_id IN (?, ?) AND r.pending = 0 AND r.settle = 0 AND r.split_id IS NOT NULL AND ((r.debtor_user_id = ? AND r.creditor_user_id = ?) OR (r.debtor_user_id = ? AND r.creditor_user_id = ?)) ORDER BY r.date DESC, r.id DESC LIMIT ? OFFSET ?",
-            (
-                current_user.id.as_str(),
-                friend_id.as_str(),
-                current_user.id.as_str(),
-                friend_id.as_str(),
-                friend_id.as_str(),
-                current_user.id.as_str(),
-                limit,
-                offset,
-            ),
-        )
-        .await
-        .map_err(|_| db_error_with_context("failed to query unsettled splits"))?;
-
-    let mut splits = Vec::new();
-    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
-        splits.push(split_list_item_from_row(row, &current_user.id)?);
-    }
+    let splits =
+        split_repo::list_unsettled_between(&conn, &current_user.id, &friend_id, limit, offset)
+            .await
+            .map_err(|_| db_error_with_context("failed to query unsettled splits"))?
+            .into_iter()
+            .map(|row| split_list_item_from_row(row, &current_user.id))
+            .collect::<Result<Vec<_>, _>>()?;
 
     Ok((
         StatusCode::OK,
@@ -310,18 +767,7 @@ pub async fn settle_all_unsettled_splits_with_friend(
         let friend_id = friend_id.clone();
 
         Box::pin(async move {
-            let affected = conn
-                .execute(
-                    "UPDATE records SET settle = 1 WHERE owner_user_id IN (?, ?) AND pending = 0 AND settle = 0 AND split_id IS NOT NULL AND ((debtor_user_id = ? AND creditor_user_id = ?) OR (debtor_user_id = ? AND creditor_user_id = ?))",
-                    (
-                        user_id.as_str(),
-                        friend_id.as_str(),
-                        user_id.as_str(),
-                        friend_id.as_str(),
-                        friend_id.as_str(),
-                        user_id.as_str(),
-                    ),
-                )
+            let affected = split_repo::settle_all_between(conn, &user_id, &friend_id)
                 .await
                 .map_err(|_| TransactionError::Commit)?;
 
@@ -334,6 +780,7 @@ pub async fn settle_all_unsettled_splits_with_friend(
         TransactionError::Commit => {
             db_error_with_context("failed to persist settle-all split updates")
         }
+        TransactionError::Busy => database_busy(),
     })?;
 
     Ok((
@@ -342,43 +789,244 @@ pub async fn settle_all_unsettled_splits_with_friend(
     ))
 }
 
+/// Nets the unsettled split records between `user_id` and `friend_id` into one payment
+/// per currency. All of the records get settled; only the differences change hands.
+pub fn plan_settle_up(user_id: &str, friend_id: &str, rows: &[SplitRecordRow]) -> SettleUpPlan {
+    let mut plan = SettleUpPlan {
+        friend_id: friend_id.to_string(),
+        my_record_ids: Vec::new(),
+        friend_record_ids: Vec::new(),
+        currencies: Vec::new(),
+    };
+    let mut totals: BTreeMap<&str, (Money, Money)> = BTreeMap::new();
+    for row in rows {
+        let (you_owe, they_owe) = totals.entry(&row.currency).or_default();
+        if row.debtor_user_id.as_deref() == Some(user_id) {
+            plan.my_record_ids.push(row.record_id.clone());
+            *you_owe += row.amount.abs();
+        } else {
+            plan.friend_record_ids.push(row.record_id.clone());
+            *they_owe += row.amount.abs();
+        }
+    }
+
+    for (currency, (you_owe, they_owe)) in totals {
+        let net = they_owe - you_owe;
+        let residual = if net.is_positive() {
+            Some(SettleUpPayment {
+                from_user_id: friend_id.to_string(),
+                to_user_id: user_id.to_string(),
+                amount: net,
+            })
+        } else if net.is_negative() {
+            Some(SettleUpPayment {
+                from_user_id: user_id.to_string(),
+                to_user_id: friend_id.to_string(),
+                amount: -net,
+            })
+        } else {
+            None
+        };
+        plan.currencies.push(CurrencySettleUp {
+            currency: currency.to_string(),
+            you_owe,
+            they_owe,
+            residual,
+        });
+    }
+    plan
+}
+
+/// `user_id`'s display name as stored on the split rows, falling back to the id.
+fn name_on_rows(rows: &[SplitRecordRow], user_id: &str) -> String {
+    rows.iter()
+        .find_map(|row| {
+            if row.creditor_user_id.as_deref() == Some(user_id) {
+                Some(row.creditor_name.clone())
+            } else if row.debtor_user_id.as_deref() == Some(user_id) {
+                Some(row.debtor_name.clone())
+            } else {
+                None
+            }
+        })
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| user_id.to_string())
+}
+
+fn same_record_ids(planned: &[String], confirmed: &[String]) -> bool {
+    let mut planned = planned.to_vec();
+    let mut confirmed = confirmed.to_vec();
+    planned.sort();
+    confirmed.sort();
+    planned == confirmed
+}
+
+async fn validate_settle_up_friend(
+    app_state: &AppState,
+    current_user_id: &str,
+    friend_id: &str,
+) -> Result<String, (StatusCode, String)> {
+    validate_string_length(friend_id, "Friend ID", MAX_RECORD_NAME_LENGTH)?;
+    let friend_id = friend_id.trim().to_string();
+    if friend_id == current_user_id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Friend ID cannot be your own user ID".to_string(),
+        ));
+    }
+
+    let conn = app_state.main_db.read().await;
+    let is_friend = friendship_repo::is_accepted_friend(&conn, current_user_id, &friend_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to validate friendship relation"))?;
+    if !is_friend {
+        return Err((StatusCode::NOT_FOUND, "Friend not found".to_string()));
+    }
+    Ok(friend_id)
+}
+
+pub async fn get_settle_up(
+    State(app_state): State<AppState>,
+    session: Session,
+    Path(friend_id): Path<String>,
+) -> Result<(StatusCode, Json<SettleUpPlan>), (StatusCode, String)> {
+    let current_user = get_current_user(&session).await?;
+    let friend_id = validate_settle_up_friend(&app_state, &current_user.id, &friend_id).await?;
+
+    let conn = app_state.main_db.read().await;
+    let rows = split_repo::list_all_unsettled_between(&conn, &current_user.id, &friend_id)
+        .await
+        .map_err(|_| db_error_with_context("failed to query unsettled splits"))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(plan_settle_up(&current_user.id, &friend_id, &rows)),
+    ))
+}
+
+/// Executes a previewed settle-up: settles the listed records on both sides and books each
+/// currency's residual payment in each user's records, in that currency, all in one
+/// transaction on the shared DB.
+///
+/// The plan is recomputed under the write lock; if the unsettled records changed since the
+/// preview (a new split, a manual settle, a concurrent settle-up) nothing is written and the
+/// caller gets 409 so they can review the new plan.
+pub async fn execute_settle_up(
+    State(app_state): State<AppState>,
+    session: Session,
+    Path(friend_id): Path<String>,
+    Json(payload): Json<ExecuteSettleUpPayload>,
+) -> Result<(StatusCode, Json<SettleUpResponse>), (StatusCode, String)> {
+    let current_user = get_current_user(&session).await?;
+    let friend_id = validate_settle_up_friend(&app_state, &current_user.id, &friend_id).await?;
+
+    let user_id = current_user.id.clone();
+    let today = time::OffsetDateTime::now_utc().date().to_string();
+
+    let response = with_transaction(&app_state.main_db, |conn| {
+        Box::pin(async move {
+            let rows = split_repo::list_all_unsettled_between(conn, &user_id, &friend_id)
+                .await
+                .map_err(|_| SettleUpError::Db)?;
+            let plan = plan_settle_up(&user_id, &friend_id, &rows);
+            if !same_record_ids(&plan.my_record_ids, &payload.my_record_ids)
+                || !same_record_ids(&plan.friend_record_ids, &payload.friend_record_ids)
+            {
+                return Err(SettleUpError::Stale);
+            }
+
+            let record_ids: Vec<String> = plan
+                .my_record_ids
+                .iter()
+                .chain(&plan.friend_record_ids)
+                .cloned()
+                .collect();
+            let settled =
+                split_repo::settle_between_by_ids(conn, &user_id, &friend_id, &record_ids)
+                    .await
+                    .map_err(|_| SettleUpError::Db)?;
+            if settled != record_ids.len() as u64 {
+                return Err(SettleUpError::Stale);
+            }
+            let settled_count = u32::try_from(settled).map_err(|_| SettleUpError::Db)?;
+
+            let friend_name = format!(
+                "{SETTLE_UP_RECORD_NAME} {}",
+                name_on_rows(&rows, &friend_id)
+            );
+            let my_name = format!("{SETTLE_UP_RECORD_NAME} {}", name_on_rows(&rows, &user_id));
+            let mut booked_ids = Vec::new();
+            for entry in &plan.currencies {
+                let Some(residual) = &entry.residual else {
+                    continue;
+                };
+                let my_amount = if residual.from_user_id == user_id {
+                    -residual.amount
+                } else {
+                    residual.amount
+                };
+                for (owner_user_id, name, amount) in [
+                    (&user_id, &friend_name, my_amount),
+                    (&friend_id, &my_name, -my_amount),
+                ] {
+                    let record_id = Uuid::new_v4().to_string();
+                    split_repo::insert_settle_up_record(
+                        conn,
+                        &NewSettleUpRecord {
+                            id: &record_id,
+                            owner_user_id,
+                            name,
+                            amount,
+                            currency: &entry.currency,
+                            date: &today,
+                        },
+                    )
+                    .await
+                    .map_err(|_| SettleUpError::Db)?;
+                    booked_ids.push(record_id);
+                }
+            }
+
+            Ok(SettleUpResponse {
+                plan,
+                settled_count,
+                residual_record_ids: booked_ids,
+            })
+        })
+    })
+    .await
+    .map_err(|error| match error {
+        SettleUpError::Stale => (
+            StatusCode::CONFLICT,
+            "Unsettled splits changed since this settle-up was previewed; review it again"
+                .to_string(),
+        ),
+        SettleUpError::Transaction | SettleUpError::Db => {
+            db_error_with_context("failed to execute settle-up")
+        }
+    })?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
 fn split_list_item_from_row(
-    row: libsql::Row,
+    row: SplitRecordRow,
     current_user_id: &str,
 ) -> Result<SplitListItem, (StatusCode, String)> {
-    let record_id: String = row
-        .get(0)
-        .map_err(|_| db_error_with_context("invalid split list record id"))?;
-    let split_id: Option<String> = row
-        .get(1)
-        .map_err(|_| db_error_with_context("invalid split list split id"))?;
-    let description: String = row
-        .get(2)
-        .map_err(|_| db_error_with_context("invalid split list description"))?;
-    let date: String = row
-        .get(3)
-        .map_err(|_| db_error_with_context("invalid split list date"))?;
-    let amount: f64 = row
-        .get(4)
-        .map_err(|_| db_error_with_context("invalid split list amount"))?;
-    let debtor_user_id: Option<String> = row
-        .get(5)
-        .map_err(|_| db_error_with_context("invalid split list debtor"))?;
-    let creditor_user_id: Option<String> = row
-        .get(6)
-        .map_err(|_| db_error_with_context("invalid split list creditor"))?;
-    let creditor_name: String = row
-        .get(7)
-        .map_err(|_| db_error_with_context("invalid split list creditor name"))?;
-    let debtor_name: String = row
-        .get(8)
-        .map_err(|_| db_error_with_context("invalid split list debtor name"))?;
-    let pending: bool = row
-        .get(9)
-        .map_err(|_| db_error_with_context("invalid split list pending flag"))?;
-    let settle: bool = row
-        .get(10)
-        .map_err(|_| db_error_with_context("invalid split list settle flag"))?;
+    let SplitRecordRow {
+        record_id,
+        split_id,
+        description,
+        date,
+        amount,
+        currency,
+        debtor_user_id,
+        creditor_user_id,
+        creditor_name,
+        debtor_name,
+        pending,
+        settle,
+    } = row;
 
     let split_id =
         split_id.ok_or_else(|| db_error_with_context("split record missing split_id"))?;
@@ -424,6 +1072,7 @@ fn split_list_item_from_row(
         description,
         date,
         amount: amount.abs(),
+        currency,
         debtor_user_id,
         creditor_user_id: creditor_user_id.clone(),
         counterparty_user_id,
@@ -440,21 +1089,17 @@ fn validate_split_create_payload(
     payload: &CreateSplitPayload,
     initiator_user_id: &str,
 ) -> Result<(), (StatusCode, String)> {
-    validate_string_length(
-        &payload.idempotency_key,
-        "Idempotency key",
-        MAX_IDEMPOTENCY_KEY_LENGTH,
-    )?;
+    validate_idempotency_key(&payload.idempotency_key)?;
     validate_string_length(&payload.description, "Description", 255)?;
     validate_string_length(&payload.category_id, "Category ID", 100)?;
     validate_date(&payload.date)?;
-    validate_split_participants(&payload.splits, initiator_user_id)
+    validate_split_participants(&payload.splits, initiator_user_id, payload.split_mode)
         .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
 
-    if !payload.total_amount.is_finite() || payload.total_amount <= 0.0 {
+    if !payload.total_amount.is_positive() {
         return Err((
             StatusCode::BAD_REQUEST,
-            "Total amount must be a positive finite number".to_string(),
+            "Total amount must be positive".to_string(),
         ));
     }
 
@@ -468,26 +1113,22 @@ async fn validate_all_participants_are_friends(
 ) -> Result<(), (StatusCode, String)> {
     let conn = app_state.main_db.read().await;
 
-    for participant in participants {
-        let mut rows = conn
-            .query(
-                "SELECT COUNT(*) FROM friendship WHERE from_user_id = ? AND to_user_id = ? AND pending = ?",
-                (current_user_id, participant.user_id.as_str(), 0i64),
-            )
-            .await
-            .map_err(|_| db_error_with_context("failed to validate friendship relation"))?;
-
-        let count: i64 =
-            if let Some(row) = rows.next().await.map_err(|_| {
-                db_error_with_context("failed to fetch friendship validation result")
-            })? {
-                row.get(0)
-                    .map_err(|_| db_error_with_context("invalid friendship validation result"))?
-            } else {
-                0
-            };
+    // A percentage split may list the initiator for their own share
+    for participant in participants
+        .iter()
+        .filter(|participant| participant.user_id != current_user_id)
+    {
+        let validation_error = |_| db_error_with_context("failed to validate friendship relation");
+        // A block in either direction ends the friendship, whatever the other row still says
+        let is_friend =
+            friendship_repo::is_accepted_friend(&conn, current_user_id, &participant.user_id)
+                .await
+                .map_err(validation_error)?
+                && !friendship_repo::is_blocked_pair(&conn, current_user_id, &participant.user_id)
+                    .await
+                    .map_err(validation_error)?;
 
-        if count == 0 {
+        if !is_friend {
             return Err((
                 StatusCode::BAD_REQUEST,
                 format!(
@@ -501,75 +1142,42 @@ async fn validate_all_participants_are_friends(
     Ok(())
 }
 
-async fn get_existing_idempotency_response(
+/// The currency a split is booked in: the payload's `currency`, or the initiator's
+/// `currency_code` without one. Every share record carries it, whatever the participants
+/// keep their own records in; amounts owed are never converted.
+async fn split_currency(
     app_state: &AppState,
-    user_id: &str,
-    idempotency_key: &str,
-) -> Result<Option<CachedIdempotency>, (StatusCode, String)> {
-    let maybe_cached = {
-        let conn = app_state.main_db.read().await;
-        let mut rows = conn
-            .query(
-                "SELECT response_status, response_body, payload_hash FROM idempotency_keys WHERE key = ? AND user_id = ? AND endpoint = ?",
-                (idempotency_key, user_id, SPLIT_CREATE_ENDPOINT),
-            )
-            .await
-            .map_err(|_| db_error_with_context("failed to query idempotency key"))?;
-
-        if let Some(row) = rows
-            .next()
-            .await
-            .map_err(|_| db_error_with_context("failed to read idempotency key row"))?
-        {
-            let response_status: i64 = row
-                .get(0)
-                .map_err(|_| db_error_with_context("invalid idempotency status"))?;
-            let response_body: Option<String> = row
-                .get(1)
-                .map_err(|_| db_error_with_context("invalid idempotency response body"))?;
-            let payload_hash: String = row
-                .get(2)
-                .map_err(|_| db_error_with_context("invalid idempotency payload hash"))?;
-            Some((response_status, response_body, payload_hash))
-        } else {
-            None
+    initiator_user_id: &str,
+    currency: Option<&str>,
+) -> Result<String, (StatusCode, String)> {
+    match currency {
+        Some(currency) => normalize_record_currency(currency),
+        None => {
+            let conn = app_state.main_db.read().await;
+            user_currency_code(&conn, initiator_user_id).await
         }
-        // read lock dropped here
-    };
-
-    if let Some((response_status, response_body, payload_hash)) = maybe_cached {
-        // A NULL response_body means a reservation was written but the fanout
-        // never completed (e.g. the server crashed mid-write). Clear the stale
-        // reservation so the caller can retry cleanly.
-        let Some(response_body) = response_body else {
-            let _ = delete_idempotency_reservation(app_state, idempotency_key, user_id).await;
-            return Ok(None);
-        };
-
-        return Ok(Some(CachedIdempotency {
-            response_status,
-            response_body,
-            payload_hash,
-        }));
     }
-
-    Ok(None)
 }
 
 async fn create_split_records(
     app_state: &AppState,
     initiator_user_id: &str,
     split_id: &str,
+    currency: &str,
     payload: &CreateSplitPayload,
 ) -> Result<(String, Vec<String>), (StatusCode, String)> {
     let calculated = calculate_split_amounts(
         payload.total_amount,
         payload.splits.clone(),
         initiator_user_id,
+        payload.split_mode,
     )
     .map_err(|msg| (StatusCode::BAD_REQUEST, msg))?;
 
-    validate_category_exists(&app_state.main_db, initiator_user_id, &payload.category_id).await?;
+    {
+        let conn = app_state.main_db.read().await;
+        get_category_for_new_record(&conn, initiator_user_id, &payload.category_id).await?;
+    }
 
     let payer_record_id = Uuid::new_v4().to_string();
     let initiator_share = calculated
@@ -577,11 +1185,13 @@ async fn create_split_records(
         .find(|(user_id, _)| user_id == initiator_user_id)
         .map(|(_, amount)| *amount)
         .ok_or_else(|| db_error_with_context("split calculation missing initiator share"))?;
-    let payer_amount = if initiator_share == 0.0 {
-        0.0
+    // The initiator's record is their own share; what the others owe lives in their records
+    let payer_amount = if payload.legacy_full_total {
+        -payload.total_amount
     } else {
         -initiator_share.abs()
     };
+    let full_total = payload.legacy_full_total;
 
     // Pre-generate all pending record IDs before entering the transaction
     let pending_record_ids: Vec<String> = calculated
@@ -598,8 +1208,9 @@ async fn create_split_records(
         let date = payload.date.trim().to_string();
         let split_id_str = split_id.to_string();
         let initiator_id = initiator_user_id.to_string();
+        let currency = currency.to_string();
         let payer_id = payer_record_id.clone();
-        let participants: Vec<(String, f64)> = calculated
+        let participants: Vec<(String, Money)> = calculated
             .iter()
             .filter(|(uid, _)| uid != initiator_user_id)
             .map(|(uid, amt)| (uid.clone(), *amt))
@@ -612,26 +1223,28 @@ async fn create_split_records(
             let date = date.clone();
             let split_id_str = split_id_str.clone();
             let initiator_id = initiator_id.clone();
+            let currency = currency.clone();
             let participants = participants.clone();
             let pending_ids = pending_ids.clone();
 
             Box::pin(async move {
                 // Payer record
-                conn.execute(
-                    "INSERT INTO records (id, owner_user_id, name, amount, category_id, date, pending, split_id, settle, debtor_user_id, creditor_user_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-                    (
-                        payer_id.as_str(),
-                        initiator_id.as_str(),
-                        description.as_str(),
-                        payer_amount,
-                        category_id.as_str(),
-                        date.as_str(),
-                        false,
-                        split_id_str.as_str(),
-                        false,
-                        initiator_id.as_str(),
-                        initiator_id.as_str(),
-                    ),
+                split_repo::insert_split_record(
+                    conn,
+                    &NewSplitRecord {
+                        id: &payer_id,
+                        owner_user_id: &initiator_id,
+                        name: &description,
+                        amount: payer_amount,
+                        currency: &currency,
+                        category_id: Some(&category_id),
+                        date: &date,
+                        pending: false,
+                        split_id: &split_id_str,
+                        debtor_user_id: &initiator_id,
+                        creditor_user_id: &initiator_id,
+                        full_total,
+                    },
                 )
                 .await
                 .map_err(|_| SplitRecordError::Db)?;
@@ -640,22 +1253,34 @@ async fn create_split_records(
                 for ((participant_user_id, amount), pending_record_id) in
                     participants.iter().zip(pending_ids.iter())
                 {
-                    let pending_amount = -(amount.abs());
-                    conn.execute(
-                        "INSERT INTO records (id, owner_user_id, name, amount, category_id, date, pending, split_id, settle, debtor_user_id, creditor_user_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-                        (
-                            pending_record_id.as_str(),
-                            participant_user_id.as_str(),
-                            description.as_str(),
-                            pending_amount,
-                            Option::<&str>::None,
-                            date.as_str(),
-                            true,
-                            split_id_str.as_str(),
-                            false,
-                            participant_user_id.as_str(),
-                            initiator_id.as_str(),
-                        ),
+                    split_repo::insert_split_record(
+                        conn,
+                        &NewSplitRecord {
+                            id: pending_record_id,
+                            owner_user_id: participant_user_id,
+                            name: &description,
+                            amount: -(amount.abs()),
+                            currency: &currency,
+                            category_id: None,
+                            date: &date,
+                            pending: true,
+                            split_id: &split_id_str,
+                            debtor_user_id: participant_user_id,
+                            creditor_user_id: &initiator_id,
+                            full_total: false,
+                        },
+                    )
+                    .await
+                    .map_err(|_| SplitRecordError::Db)?;
+                    notifications::notify(
+                        conn,
+                        participant_user_id,
+                        NOTIFICATION_KIND_SPLIT_CREATED,
+                        json!({
+                            "split_id": split_id_str,
+                            "record_id": pending_record_id,
+                            "initiator_user_id": initiator_id,
+                        }),
                     )
                     .await
                     .map_err(|_| SplitRecordError::Db)?;
@@ -668,99 +1293,23 @@ async fn create_split_records(
         .map_err(|_| db_error_with_context("failed to create split records"))?;
     }
 
-    Ok((payer_record_id, pending_record_ids))
-}
-
-async fn reserve_idempotency_entry(
-    app_state: &AppState,
-    idempotency_key: &str,
-    user_id: &str,
-    payload_hash: &str,
-    created_at: &str,
-    expires_at: &str,
-) -> Result<(), (StatusCode, String)> {
-    let conn = app_state.main_db.write().await;
-    conn.execute(
-        "INSERT INTO idempotency_keys (id, key, user_id, endpoint, payload_hash, response_status, response_body, created_at, expires_at) VALUES (?, ?, ?, ?, ?, ?, NULL, ?, ?)",
-        (
-            Uuid::new_v4().to_string(),
-            idempotency_key,
-            user_id,
-            SPLIT_CREATE_ENDPOINT,
-            payload_hash,
-            0i64,
-            created_at,
-            expires_at,
-        ),
-    )
-    .await
-    .map_err(|_| db_error_with_context("failed to reserve idempotency key"))?;
-
-    Ok(())
-}
-
-async fn commit_idempotency_entry(
-    app_state: &AppState,
-    idempotency_key: &str,
-    user_id: &str,
-    response_status: i64,
-    response_body: &str,
-) -> Result<(), (StatusCode, String)> {
-    let conn = app_state.main_db.write().await;
-    conn.execute(
-        "UPDATE idempotency_keys SET response_status = ?, response_body = ? WHERE key = ? AND user_id = ? AND endpoint = ?",
-        (
-            response_status,
-            response_body,
-            idempotency_key,
-            user_id,
-            SPLIT_CREATE_ENDPOINT,
-        ),
-    )
-    .await
-    .map_err(|_| db_error_with_context("failed to commit idempotency entry"))?;
-
-    Ok(())
-}
-
-async fn delete_idempotency_reservation(
-    app_state: &AppState,
-    idempotency_key: &str,
-    user_id: &str,
-) -> Result<(), (StatusCode, String)> {
-    let conn = app_state.main_db.write().await;
-    conn.execute(
-        "DELETE FROM idempotency_keys WHERE key = ? AND user_id = ? AND endpoint = ? AND response_body IS NULL",
-        (idempotency_key, user_id, SPLIT_CREATE_ENDPOINT),
-    )
-    .await
-    .map_err(|_| db_error_with_context("failed to delete idempotency reservation"))?;
-
-    Ok(())
-}
-
-fn compute_payload_hash(payload: &CreateSplitPayload) -> Result<String, (StatusCode, String)> {
-    let serialized = serde_json::to_string(payload).map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to serialize payload: {}", e),
-        )
-    })?;
-
-    Ok(fnv1a_64_hex(serialized.as_bytes()))
-}
-
-fn fnv1a_64_hex(bytes: &[u8]) -> String {
-    let mut hash: u64 = 0xcbf29ce484222325;
-    for b in bytes {
-        hash ^= u64::from(*b);
-        hash = hash.wrapping_mul(0x100000001b3);
-    }
-    format!("{:016x}", hash)
-}
+    // Telegram messages go out after the records are committed and cannot fail the split
+    let notices: Vec<(String, SplitNotice)> = calculated
+        .iter()
+        .filter(|(uid, _)| uid != initiator_user_id)
+        .zip(pending_record_ids.iter())
+        .map(|((participant_user_id, _), record_id)| {
+            (
+                participant_user_id.clone(),
+                SplitNotice {
+                    split_id: split_id.to_string(),
+                    record_id: record_id.clone(),
+                    from_user_id: initiator_user_id.to_string(),
+                },
+            )
+        })
+        .collect();
+    outbox::queue_split_notices(&app_state.main_db, OUTBOX_KIND_SPLIT_CREATED, &notices).await;
 
-fn now_rfc3339() -> Result<String, (StatusCode, String)> {
-    time::OffsetDateTime::now_utc()
-        .format(&time::format_description::well_known::Rfc3339)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+    Ok((payer_record_id, pending_record_ids))
 }
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use tower_sessions::Session;
+
+use crate::AppState;
+use crate::auth::get_current_user;
+use crate::constants::*;
+use crate::models::{AiAccuracyMonth, AiAccuracyQuery, AiAccuracyResponse, CategoryConfusion};
+use crate::record_repo::{self, AiCategoryPick};
+use crate::utils::{db_error, db_error_with_context};
+
+pub fn validate_accuracy_months(months: Option<u32>) -> Result<u32, (StatusCode, String)> {
+    match months {
+        None => Ok(AI_ACCURACY_DEFAULT_MONTHS),
+        Some(months) if (1..=AI_ACCURACY_MAX_MONTHS).contains(&months) => Ok(months),
+        Some(_) => Err((
+            StatusCode::BAD_REQUEST,
+            format!("months must be between 1 and {}", AI_ACCURACY_MAX_MONTHS),
+        )),
+    }
+}
+
+/// The current UTC month as `YYYY-MM`.
+pub fn current_month() -> String {
+    let today = time::OffsetDateTime::now_utc().date();
+    format!("{:04}-{:02}", today.year(), u8::from(today.month()))
+}
+
+/// The month (`YYYY-MM`) that `now` falls in at `utc_offset_minutes` from UTC.
+pub fn local_month(now: time::OffsetDateTime, utc_offset_minutes: i32) -> String {
+    let offset = time::UtcOffset::from_whole_seconds(utc_offset_minutes * 60)
+        .unwrap_or(time::UtcOffset::UTC);
+    let today = now.to_offset(offset).date();
+    format!("{:04}-{:02}", today.year(), u8::from(today.month()))
+}
+
+/// The first and last day (`YYYY-MM-DD`) of `month` (`YYYY-MM`).
+pub fn month_date_range(month: &str) -> Result<(String, String), (StatusCode, String)> {
+    let month = month_window(month, 1)?.remove(0);
+    let invalid = || {
+        (
+            StatusCode::BAD_REQUEST,
+            "Month must be in YYYY-MM format".to_string(),
+        )
+    };
+    let (year, number) = month.split_once('-').ok_or_else(invalid)?;
+    let year: i32 = year.parse().map_err(|_| invalid())?;
+    let number = number
+        .parse::<u8>()
+        .ok()
+        .and_then(|number| time::Month::try_from(number).ok())
+        .ok_or_else(invalid)?;
+    Ok((
+        format!("{month}-01"),
+        format!("{month}-{:02}", number.length(year)),
+    ))
+}
+
+/// The `months` calendar months ending with `current_month` (`YYYY-MM`), oldest first.
+pub fn month_window(current_month: &str, months: u32) -> Result<Vec<String>, (StatusCode, String)> {
+    let invalid = || {
+        (
+            StatusCode::BAD_REQUEST,
+            "Month must be in YYYY-MM format".to_string(),
+        )
+    };
+    let (year, month) = current_month.split_once('-').ok_or_else(invalid)?;
+    let year: i32 = year.parse().map_err(|_| invalid())?;
+    let month: i32 = month.parse().map_err(|_| invalid())?;
+    if !(1..=12).contains(&month) {
+        return Err(invalid());
+    }
+
+    let last = year * 12 + (month - 1);
+    Ok((0..months as i32)
+        .rev()
+        .map(|back| {
+            let index = last - back;
+            format!(
+                "{:04}-{:02}",
+                index.div_euclid(12),
+                index.rem_euclid(12) + 1
+            )
+        })
+        .collect())
+}
+
+fn accuracy_percent(total: u32, corrected: u32) -> Option<f64> {
+    (total > 0).then(|| {
+        let percent = f64::from(total - corrected) * 100.0 / f64::from(total);
+        (percent * 10.0).round() / 10.0
+    })
+}
+
+fn is_corrected(pick: &AiCategoryPick) -> bool {
+    pick.category_id.as_deref() != Some(pick.ai_category_id.as_str())
+}
+
+/// Counts `(AI pick, user choice)` pairs among corrected picks, most frequent first.
+fn top_confusions(
+    picks: &[&AiCategoryPick],
+    category_names: &HashMap<String, String>,
+) -> Vec<CategoryConfusion> {
+    let mut counts: HashMap<(&str, Option<&str>), u32> = HashMap::new();
+    for pick in picks {
+        if is_corrected(pick) {
+            *counts
+                .entry((&pick.ai_category_id, pick.category_id.as_deref()))
+                .or_default() += 1;
+        }
+    }
+
+    let mut confusions: Vec<CategoryConfusion> = counts
+        .into_iter()
+        .map(
+            |((ai_category_id, user_category_id), count)| CategoryConfusion {
+                ai_category_id: ai_category_id.to_string(),
+                ai_category_name: category_names.get(ai_category_id).cloned(),
+                user_category_id: user_category_id.map(str::to_string),
+                user_category_name: user_category_id.and_then(|id| category_names.get(id).cloned()),
+                count,
+            },
+        )
+        .collect();
+    confusions.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.ai_category_name.cmp(&b.ai_category_name))
+            .then_with(|| a.user_category_name.cmp(&b.user_category_name))
+            .then_with(|| a.ai_category_id.cmp(&b.ai_category_id))
+            .then_with(|| a.user_category_id.cmp(&b.user_category_id))
+    });
+    confusions.truncate(AI_ACCURACY_TOP_CONFUSIONS);
+    confusions
+}
+
+async fn load_category_names(
+    conn: &libsql::Connection,
+    user_id: &str,
+) -> Result<HashMap<String, String>, (StatusCode, String)> {
+    let mut rows = conn
+        .query(
+            "SELECT id, name FROM categories WHERE owner_user_id = ?",
+            [user_id],
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to query categories"))?;
+
+    let mut names = HashMap::new();
+    while let Some(row) = rows.next().await.map_err(|_| db_error())? {
+        let id: String = row
+            .get(0)
+            .map_err(|_| db_error_with_context("invalid category data"))?;
+        let name: String = row
+            .get(1)
+            .map_err(|_| db_error_with_context("invalid category data"))?;
+        names.insert(id, name);
+    }
+    Ok(names)
+}
+
+/// AI categorization accuracy over the `months` months ending with `current_month`.
+///
+/// A `bot_ai` record counts as corrected when its current category differs from the one
+/// the model picked at creation (stored in `record_provenance.ai_category_id`).
+pub async fn ai_accuracy_for_user(
+    conn: &libsql::Connection,
+    user_id: &str,
+    months: u32,
+    current_month: &str,
+) -> Result<AiAccuracyResponse, (StatusCode, String)> {
+    let window = month_window(current_month, months)?;
+    let since = format!("{}-01", window[0]);
+
+    let picks = record_repo::list_ai_category_picks(conn, user_id, &since)
+        .await
+        .map_err(|_| db_error_with_context("failed to query AI-created records"))?;
+    let category_names = load_category_names(conn, user_id).await?;
+
+    let mut in_window = Vec::new();
+    let mut month_summaries = Vec::with_capacity(window.len());
+    for month in window {
+        let month_picks: Vec<&AiCategoryPick> = picks
+            .iter()
+            .filter(|pick| pick.date.get(..7) == Some(month.as_str()))
+            .collect();
+        let total = month_picks.len() as u32;
+        let corrected = month_picks.iter().filter(|pick| is_corrected(pick)).count() as u32;
+        month_summaries.push(AiAccuracyMonth {
+            month,
+            total,
+            corrected,
+            accuracy_percent: accuracy_percent(total, corrected),
+            top_confusions: top_confusions(&month_picks, &category_names),
+        });
+        in_window.extend(month_picks);
+    }
+
+    let total = in_window.len() as u32;
+    let corrected = in_window.iter().filter(|pick| is_corrected(pick)).count() as u32;
+    Ok(AiAccuracyResponse {
+        months: month_summaries,
+        total,
+        corrected,
+        accuracy_percent: accuracy_percent(total, corrected),
+        top_confusions: top_confusions(&in_window, &category_names),
+    })
+}
+
+pub async fn get_ai_accuracy(
+    State(app_state): State<AppState>,
+    session: Session,
+    Query(query): Query<AiAccuracyQuery>,
+) -> Result<(StatusCode, Json<AiAccuracyResponse>), (StatusCode, String)> {
+    let user = get_current_user(&session).await?;
+    let months = validate_accuracy_months(query.months)?;
+
+    let conn = app_state.main_db.read().await;
+    let report = ai_accuracy_for_user(&conn, &user.id, months, &current_month()).await?;
+
+    Ok((StatusCode::OK, Json(report)))
+}
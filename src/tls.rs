@@ -0,0 +1,236 @@
+use std::future::pending;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use rustls::ServerConfig;
+use rustls::crypto::CryptoProvider;
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
+
+use crate::config::TlsConfig;
+use crate::constants::*;
+
+/// Why a certificate or key could not be loaded. Every variant names the file.
+#[derive(Debug)]
+pub enum TlsError {
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    NoCertificate(String),
+    NoPrivateKey(String),
+    /// The key is not supported or does not belong to the certificate.
+    InvalidKey {
+        path: String,
+        source: rustls::Error,
+    },
+}
+
+impl std::fmt::Display for TlsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TlsError::Read { path, source } => write!(f, "cannot read {}: {}", path, source),
+            TlsError::NoCertificate(path) => write!(f, "{} contains no PEM certificate", path),
+            TlsError::NoPrivateKey(path) => write!(f, "{} contains no PEM private key", path),
+            TlsError::InvalidKey { path, source } => {
+                write!(
+                    f,
+                    "{} is not a usable key for the certificate: {}",
+                    path, source
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for TlsError {}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, TlsError> {
+    std::fs::read(path).map_err(|source| TlsError::Read {
+        path: path.to_string(),
+        source,
+    })
+}
+
+/// Reads the certificate chain and private key named by `tls` and checks they match.
+pub fn load_certified_key(tls: &TlsConfig) -> Result<CertifiedKey, TlsError> {
+    let cert_pem = read_file(&tls.cert_path)?;
+    let certs = rustls_pemfile::certs(&mut cert_pem.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|source| TlsError::Read {
+            path: tls.cert_path.clone(),
+            source,
+        })?;
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificate(tls.cert_path.clone()));
+    }
+
+    let key_pem = read_file(&tls.key_path)?;
+    let key = rustls_pemfile::private_key(&mut key_pem.as_slice())
+        .map_err(|source| TlsError::Read {
+            path: tls.key_path.clone(),
+            source,
+        })?
+        .ok_or_else(|| TlsError::NoPrivateKey(tls.key_path.clone()))?;
+
+    CertifiedKey::from_der(certs, key, &provider()).map_err(|source| TlsError::InvalidKey {
+        path: tls.key_path.clone(),
+        source,
+    })
+}
+
+/// Hands every handshake the certificate loaded last, so `reload` takes effect for
+/// new connections while open ones keep theirs.
+#[derive(Debug)]
+pub struct CertificateResolver {
+    tls: TlsConfig,
+    current: RwLock<Arc<CertifiedKey>>,
+}
+
+impl CertificateResolver {
+    pub fn load(tls: TlsConfig) -> Result<Arc<Self>, TlsError> {
+        let key = load_certified_key(&tls)?;
+        Ok(Arc::new(Self {
+            tls,
+            current: RwLock::new(Arc::new(key)),
+        }))
+    }
+
+    /// Reads the files again. On error the previous certificate stays in use.
+    pub fn reload(&self) -> Result<(), TlsError> {
+        let key = Arc::new(load_certified_key(&self.tls)?);
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = key;
+        Ok(())
+    }
+
+    /// Server settings resolving through this resolver; HTTP/1.1 only, like `axum::serve`.
+    pub fn server_config(self: &Arc<Self>) -> Arc<ServerConfig> {
+        let mut config = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .expect("the ring provider supports the default protocol versions")
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone());
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Arc::new(config)
+    }
+}
+
+impl ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.current
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        )
+    }
+}
+
+/// Reloads the certificate on SIGHUP and every `reload_interval_secs`, for the life
+/// of the process. A failed reload is logged and the old certificate kept.
+pub fn spawn_reloader(resolver: Arc<CertificateResolver>) {
+    tokio::spawn(async move {
+        let mut hangup =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()).ok();
+        let interval = resolver.tls.reload_interval_secs.map(Duration::from_secs);
+        loop {
+            let on_hangup = async {
+                match hangup.as_mut() {
+                    Some(signal) => {
+                        signal.recv().await;
+                    }
+                    None => pending::<()>().await,
+                }
+            };
+            let on_interval = async {
+                match interval {
+                    Some(interval) => tokio::time::sleep(interval).await,
+                    None => pending::<()>().await,
+                }
+            };
+            let trigger = tokio::select! {
+                _ = on_hangup => "SIGHUP",
+                _ = on_interval => "interval",
+            };
+            match resolver.reload() {
+                Ok(()) => tracing::info!(trigger, "TLS certificate reloaded"),
+                Err(e) => tracing::warn!(trigger, error = %e, "TLS certificate reload failed"),
+            }
+        }
+    });
+}
+
+/// Accepts TCP connections and runs their TLS handshakes in separate tasks, so a slow
+/// client cannot hold up the others. Handshakes that fail or take longer than
+/// `TLS_HANDSHAKE_TIMEOUT_SECS` are dropped before the server sees them.
+pub struct TlsListener {
+    local_addr: SocketAddr,
+    ready: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+}
+
+impl TlsListener {
+    pub fn new(tcp: TcpListener, config: Arc<ServerConfig>) -> std::io::Result<Self> {
+        let local_addr = tcp.local_addr()?;
+        let acceptor = TlsAcceptor::from(config);
+        let (sender, ready) = mpsc::channel(TLS_ACCEPT_QUEUE);
+
+        tokio::spawn(async move {
+            while !sender.is_closed() {
+                let (stream, remote_addr) = match tcp.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        // Usually out of file descriptors; give connections time to close
+                        tracing::warn!(error = %e, "accepting a connection failed");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+                let acceptor = acceptor.clone();
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let handshake = tokio::time::timeout(
+                        Duration::from_secs(TLS_HANDSHAKE_TIMEOUT_SECS),
+                        acceptor.accept(stream),
+                    );
+                    match handshake.await {
+                        Ok(Ok(stream)) => {
+                            let _ = sender.send((stream, remote_addr)).await;
+                        }
+                        Ok(Err(e)) => {
+                            tracing::debug!(%remote_addr, error = %e, "TLS handshake failed")
+                        }
+                        Err(_) => tracing::debug!(%remote_addr, "TLS handshake timed out"),
+                    }
+                });
+            }
+        });
+
+        Ok(Self { local_addr, ready })
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.ready.recv().await {
+            Some(accepted) => accepted,
+            // The accept task only stops once this listener is gone
+            None => pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
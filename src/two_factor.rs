@@ -0,0 +1,713 @@
+use std::sync::OnceLock;
+
+use aes_gcm::aead::{Aead, AeadCore, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use axum::{Json, extract::State, http::StatusCode};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use libsql::Connection;
+use password_hash::rand_core::RngCore;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use time::{Duration, OffsetDateTime};
+use tower_sessions::Session;
+
+use crate::auth::{self, get_current_user};
+use crate::constants::*;
+use crate::crypto::{decode_hex, encode_hex};
+use crate::database::Db;
+use crate::error::ApiError;
+use crate::models::{
+    DisableTwoFactorPayload, LoginResponse, PublicUser, TwoFactorChallengeResponse,
+    TwoFactorCodePayload, TwoFactorLoginPayload, TwoFactorRecoveryCodesResponse,
+    TwoFactorSetupResponse,
+};
+use crate::utils::{database_busy, db_error_with_context, precise_timestamp};
+use crate::{AppState, TransactionError, with_transaction};
+
+// Optional TOTP two-factor login (RFC 6238: HMAC-SHA-1, 30-second steps, 6 digits).
+//
+// `POST /auth/2fa/setup` stores a fresh secret in `users.totp_secret`, sealed with a key
+// derived from `SESSION_SECRET`; `verify` confirms a code, stamps `totp_enabled_at` and
+// hands out single-use recovery codes. While it is enabled a correct password only earns
+// a challenge token, which `POST /auth/login/2fa` exchanges with a code for the session.
+// The last accepted time step is kept in `totp_last_step`, so no code works twice. The
+// key is process-wide, like the record key, because the bot checks codes for `/link`.
+
+const NONCE_LEN: usize = 12;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+/// Recovery code characters; no 0/o, 1/l/i, so codes read back unambiguously.
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+static TWO_FACTOR_KEY: OnceLock<TwoFactorKey> = OnceLock::new();
+
+/// Seals TOTP secrets at rest. Derived from `SESSION_SECRET`, so changing that secret
+/// makes every enrolled authenticator unreadable.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TwoFactorKey([u8; 32]);
+
+impl std::fmt::Debug for TwoFactorKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TwoFactorKey(..)")
+    }
+}
+
+impl TwoFactorKey {
+    pub fn from_session_secret(session_secret: &str) -> Self {
+        let hkdf = Hkdf::<Sha256>::new(None, session_secret.as_bytes());
+        let mut okm = [0u8; 32];
+        hkdf.expand(b"kash-totp-secrets", &mut okm)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        Self(okm)
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.0))
+    }
+}
+
+/// Installs the process-wide key. Returns `false` if one was already installed.
+pub fn install_two_factor_key(key: TwoFactorKey) -> bool {
+    TWO_FACTOR_KEY.set(key).is_ok()
+}
+
+fn installed_key() -> Result<&'static TwoFactorKey, ApiError> {
+    TWO_FACTOR_KEY.get().ok_or_else(|| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Two-factor login is not configured".to_string(),
+        )
+            .into()
+    })
+}
+
+fn seal_secret(key: &TwoFactorKey, user_id: &str, secret: &[u8]) -> String {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = key
+        .cipher()
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: secret,
+                aad: user_id.as_bytes(),
+            },
+        )
+        .expect("AES-GCM encryption of an in-memory buffer cannot fail");
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    encode_hex(&sealed)
+}
+
+fn open_secret(key: &TwoFactorKey, user_id: &str, stored: &str) -> Result<Vec<u8>, ApiError> {
+    let unreadable = || -> ApiError {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Two-factor secret could not be read".to_string(),
+        )
+            .into()
+    };
+    let sealed = decode_hex(stored)
+        .filter(|sealed| sealed.len() > NONCE_LEN)
+        .ok_or_else(unreadable)?;
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    key.cipher()
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: user_id.as_bytes(),
+            },
+        )
+        .map_err(|_| unreadable())
+}
+
+// ---------------------------------------------------------------------------
+// TOTP codes
+// ---------------------------------------------------------------------------
+
+fn time_step(at: OffsetDateTime) -> i64 {
+    at.unix_timestamp().div_euclid(TOTP_STEP_SECS)
+}
+
+fn code_for_step(secret: &[u8], step: i64) -> String {
+    let mut mac =
+        <Hmac<Sha1> as Mac>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&(step as u64).to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    // Dynamic truncation, RFC 4226 section 5.3
+    let offset = usize::from(digest[digest.len() - 1] & 0x0f);
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    )
+}
+
+/// The code an authenticator app shows at `at` for `secret`.
+pub fn totp_code(secret: &[u8], at: OffsetDateTime) -> String {
+    code_for_step(secret, time_step(at))
+}
+
+/// The time step within `TOTP_ALLOWED_DRIFT_STEPS` of `now` whose code is `code`.
+fn matching_step(secret: &[u8], code: &str, now: OffsetDateTime) -> Option<i64> {
+    let current = time_step(now);
+    (current - TOTP_ALLOWED_DRIFT_STEPS..=current + TOTP_ALLOWED_DRIFT_STEPS)
+        .find(|step| constant_time_eq(code_for_step(secret, *step).as_bytes(), code.as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn is_totp_code(code: &str) -> bool {
+    code.len() == TOTP_DIGITS as usize && code.chars().all(|c| c.is_ascii_digit())
+}
+
+/// RFC 4648 base32 without padding, the form authenticator apps take secrets in.
+pub fn encode_base32(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for &byte in bytes {
+        buffer = (buffer << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    encoded
+}
+
+/// Reads base32 in either case, ignoring padding and spaces.
+pub fn decode_base32(value: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for c in value
+        .trim_end_matches('=')
+        .chars()
+        .filter(|c| !c.is_whitespace())
+    {
+        let index = BASE32_ALPHABET
+            .iter()
+            .position(|&a| char::from(a) == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | index as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+fn otpauth_uri(username: &str, secret: &str) -> String {
+    // Usernames are limited to letters, digits, `_` and `-`, so nothing needs escaping
+    format!(
+        "otpauth://totp/{TOTP_ISSUER}:{username}?secret={secret}&issuer={TOTP_ISSUER}\
+         &algorithm=SHA1&digits={TOTP_DIGITS}&period={TOTP_STEP_SECS}"
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Recovery codes and challenge tokens
+// ---------------------------------------------------------------------------
+
+fn hash_token(value: &str) -> String {
+    encode_hex(&Sha256::digest(value.as_bytes()))
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    encode_hex(&bytes)
+}
+
+/// `TWO_FACTOR_RECOVERY_CODE_LENGTH` characters, shown as two dash-separated halves.
+fn generate_recovery_code() -> String {
+    let mut code = String::with_capacity(TWO_FACTOR_RECOVERY_CODE_LENGTH);
+    while code.len() < TWO_FACTOR_RECOVERY_CODE_LENGTH {
+        let byte = (OsRng.next_u32() & 0xff) as usize;
+        // Rejection sampling keeps every character equally likely.
+        if byte < 256 - 256 % RECOVERY_CODE_ALPHABET.len() {
+            code.push(RECOVERY_CODE_ALPHABET[byte % RECOVERY_CODE_ALPHABET.len()] as char);
+        }
+    }
+    let (first, second) = code.split_at(TWO_FACTOR_RECOVERY_CODE_LENGTH / 2);
+    format!("{first}-{second}")
+}
+
+/// Case, dashes and spaces do not matter when a recovery code is typed back.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Stored state
+// ---------------------------------------------------------------------------
+
+enum TwoFactorError {
+    Transaction(TransactionError),
+    Db(libsql::Error),
+}
+
+impl From<TransactionError> for TwoFactorError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+impl From<libsql::Error> for TwoFactorError {
+    fn from(value: libsql::Error) -> Self {
+        Self::Db(value)
+    }
+}
+
+impl From<TwoFactorError> for ApiError {
+    fn from(value: TwoFactorError) -> Self {
+        match value {
+            TwoFactorError::Transaction(TransactionError::Begin) => {
+                db_error_with_context("failed to begin transaction")
+            }
+            TwoFactorError::Transaction(TransactionError::Commit) => {
+                db_error_with_context("failed to commit transaction")
+            }
+            TwoFactorError::Transaction(TransactionError::Busy) => database_busy(),
+            TwoFactorError::Db(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        }
+        .into()
+    }
+}
+
+/// `users.totp_secret` (sealed) and whether it was confirmed.
+struct StoredSecret {
+    sealed: String,
+    enabled: bool,
+}
+
+async fn stored_secret(conn: &Connection, user_id: &str) -> Result<Option<StoredSecret>, ApiError> {
+    let load_error =
+        |_| -> ApiError { db_error_with_context("failed to load two-factor login").into() };
+    let mut rows = conn
+        .query(
+            "SELECT totp_secret, totp_enabled_at IS NOT NULL FROM users
+             WHERE id = ? AND totp_secret IS NOT NULL",
+            [user_id],
+        )
+        .await
+        .map_err(load_error)?;
+    let Some(row) = rows.next().await.map_err(load_error)? else {
+        return Ok(None);
+    };
+    Ok(Some(StoredSecret {
+        sealed: row.get(0).map_err(load_error)?,
+        enabled: row.get::<i64>(1).map_err(load_error)? != 0,
+    }))
+}
+
+/// Whether logins as `user_id` need a second factor.
+pub async fn is_enabled(db: &Db, user_id: &str) -> Result<bool, ApiError> {
+    let conn = db.read().await;
+    Ok(stored_secret(&conn, user_id)
+        .await?
+        .is_some_and(|stored| stored.enabled))
+}
+
+/// Uses up `code` if it is a current TOTP code, or an unused recovery code, of
+/// `user_id`'s enabled two-factor login.
+async fn redeem_code(
+    conn: &Connection,
+    user_id: &str,
+    code: &str,
+    now: OffsetDateTime,
+) -> Result<bool, ApiError> {
+    let Some(stored) = stored_secret(conn, user_id)
+        .await?
+        .filter(|stored| stored.enabled)
+    else {
+        return Ok(false);
+    };
+    let code = code.trim();
+    let redeemed = if is_totp_code(code) {
+        let secret = open_secret(installed_key()?, user_id, &stored.sealed)?;
+        let Some(step) = matching_step(&secret, code, now) else {
+            return Ok(false);
+        };
+        // Only a step newer than the last accepted one, so a seen code cannot be replayed
+        conn.execute(
+            "UPDATE users SET totp_last_step = ?1
+             WHERE id = ?2 AND (totp_last_step IS NULL OR totp_last_step < ?1)",
+            libsql::params![step, user_id],
+        )
+        .await
+    } else {
+        conn.execute(
+            "DELETE FROM two_factor_recovery_codes WHERE user_id = ? AND code_hash = ?",
+            [user_id, &hash_token(&normalize_recovery_code(code))],
+        )
+        .await
+    };
+    Ok(redeemed.map_err(|_| db_error_with_context("failed to check two-factor code"))? > 0)
+}
+
+/// Checks the second factor of a login whose password was already checked and records
+/// the attempt under `username`, so wrong codes count towards the lockout.
+pub async fn verify_login_code(
+    db: &Db,
+    user_id: &str,
+    username: &str,
+    source: &str,
+    code: &str,
+) -> Result<bool, ApiError> {
+    let accepted = {
+        let conn = db.write().await;
+        redeem_code(&conn, user_id, code, OffsetDateTime::now_utc()).await?
+    };
+    let failure_reason = (!accepted).then_some(LOGIN_FAILURE_INVALID_TWO_FACTOR);
+    auth::record_attempt(db, username, source, failure_reason).await?;
+    Ok(accepted)
+}
+
+/// Starts the second step of a login attempted as `username`.
+pub async fn create_challenge(
+    db: &Db,
+    user_id: &str,
+    username: &str,
+) -> Result<TwoFactorChallengeResponse, ApiError> {
+    let token = random_token();
+    let now = OffsetDateTime::now_utc();
+    let expires_at = now + Duration::seconds(TWO_FACTOR_CHALLENGE_TTL_SECS);
+    let conn = db.write().await;
+    // Expired challenges are swept here rather than by a job
+    conn.execute(
+        "DELETE FROM two_factor_challenges WHERE expires_at <= ?",
+        [precise_timestamp(now)],
+    )
+    .await
+    .map_err(|_| db_error_with_context("failed to clear login challenges"))?;
+    conn.execute(
+        "INSERT INTO two_factor_challenges (token_hash, user_id, username, expires_at)
+         VALUES (?, ?, ?, ?)",
+        [
+            hash_token(&token),
+            user_id.to_string(),
+            username.to_string(),
+            precise_timestamp(expires_at),
+        ],
+    )
+    .await
+    .map_err(|_| db_error_with_context("failed to create login challenge"))?;
+
+    Ok(TwoFactorChallengeResponse {
+        two_factor_required: true,
+        challenge_token: token,
+        expires_in_secs: TWO_FACTOR_CHALLENGE_TTL_SECS,
+    })
+}
+
+/// Spends one attempt of a live challenge; its user id and attempted name, or `None`
+/// when the token is unknown, expired or out of attempts.
+async fn spend_challenge_attempt(
+    db: &Db,
+    token_hash: &str,
+    now: OffsetDateTime,
+) -> Result<Option<(String, String)>, ApiError> {
+    let conn = db.write().await;
+    let spend_error =
+        |_| -> ApiError { db_error_with_context("failed to check login challenge").into() };
+    let mut rows = conn
+        .query(
+            "UPDATE two_factor_challenges SET attempts = attempts + 1
+             WHERE token_hash = ? AND expires_at > ? AND attempts < ?
+             RETURNING user_id, username",
+            libsql::params![
+                token_hash,
+                precise_timestamp(now),
+                TWO_FACTOR_CHALLENGE_MAX_ATTEMPTS
+            ],
+        )
+        .await
+        .map_err(spend_error)?;
+    let Some(row) = rows.next().await.map_err(spend_error)? else {
+        return Ok(None);
+    };
+    Ok(Some((
+        row.get(0).map_err(spend_error)?,
+        row.get(1).map_err(spend_error)?,
+    )))
+}
+
+fn invalid_code() -> ApiError {
+    ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        ERROR_CODE_INVALID_TWO_FACTOR_CODE,
+        "Invalid two-factor code",
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Handlers
+// ---------------------------------------------------------------------------
+
+/// `POST /auth/login/2fa`: exchanges a challenge from `POST /auth/login` and a TOTP or
+/// recovery code for the session. A challenge takes `TWO_FACTOR_CHALLENGE_MAX_ATTEMPTS`
+/// codes within `TWO_FACTOR_CHALLENGE_TTL_SECS`.
+pub async fn login_two_factor(
+    State(app_state): State<AppState>,
+    session: Session,
+    Json(payload): Json<TwoFactorLoginPayload>,
+) -> Result<(StatusCode, Json<LoginResponse>), ApiError> {
+    let db = &app_state.main_db;
+    let token_hash = hash_token(payload.challenge_token.trim());
+    let Some((user_id, username)) =
+        spend_challenge_attempt(db, &token_hash, OffsetDateTime::now_utc()).await?
+    else {
+        return Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            ERROR_CODE_TWO_FACTOR_CHALLENGE_INVALID,
+            "Login challenge is invalid or expired; log in again",
+        ));
+    };
+
+    if !verify_login_code(db, &user_id, &username, LOGIN_SOURCE_WEB, &payload.code).await? {
+        return Err(invalid_code());
+    }
+    {
+        let conn = db.write().await;
+        conn.execute(
+            "DELETE FROM two_factor_challenges WHERE token_hash = ?",
+            [token_hash],
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to close login challenge"))?;
+    }
+
+    let user = auth::get_user_by_id(db, &user_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| {
+            ApiError::from((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()))
+        })?;
+    if user.disabled_at.is_some() {
+        return Err((StatusCode::FORBIDDEN, "Account disabled".to_string()).into());
+    }
+    let response = auth::start_session(
+        &app_state,
+        &session,
+        PublicUser {
+            id: user.id,
+            username: user.username,
+        },
+    )
+    .await?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// `POST /auth/2fa/setup`: a new secret, replacing any from an unfinished setup. Logins
+/// are unaffected until `POST /auth/2fa/verify` confirms it.
+pub async fn setup_two_factor(
+    State(app_state): State<AppState>,
+    session: Session,
+) -> Result<(StatusCode, Json<TwoFactorSetupResponse>), ApiError> {
+    let current_user = get_current_user(&session).await?;
+    let key = installed_key()?;
+    let mut secret = [0u8; TOTP_SECRET_BYTES];
+    OsRng.fill_bytes(&mut secret);
+
+    let conn = app_state.main_db.write().await;
+    let user = auth::refresh_user(&conn, current_user).await?;
+    let updated = conn
+        .execute(
+            "UPDATE users SET totp_secret = ?, totp_last_step = NULL
+             WHERE id = ? AND totp_enabled_at IS NULL",
+            [seal_secret(key, &user.id, &secret), user.id.clone()],
+        )
+        .await
+        .map_err(|_| db_error_with_context("failed to store two-factor secret"))?;
+    if updated == 0 {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            ERROR_CODE_TWO_FACTOR_ALREADY_ENABLED,
+            "Two-factor login is already enabled",
+        ));
+    }
+
+    let secret = encode_base32(&secret);
+    Ok((
+        StatusCode::OK,
+        Json(TwoFactorSetupResponse {
+            otpauth_uri: otpauth_uri(&user.username, &secret),
+            secret,
+        }),
+    ))
+}
+
+/// `POST /auth/2fa/verify`: confirms the pending secret with a code from the app,
+/// enables two-factor login and returns `TWO_FACTOR_RECOVERY_CODE_COUNT` recovery codes.
+pub async fn verify_two_factor(
+    State(app_state): State<AppState>,
+    session: Session,
+    Json(payload): Json<TwoFactorCodePayload>,
+) -> Result<(StatusCode, Json<TwoFactorRecoveryCodesResponse>), ApiError> {
+    let user = get_current_user(&session).await?;
+    let stored = {
+        let conn = app_state.main_db.read().await;
+        stored_secret(&conn, &user.id).await?
+    };
+    let stored = match stored {
+        Some(stored) if stored.enabled => {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                ERROR_CODE_TWO_FACTOR_ALREADY_ENABLED,
+                "Two-factor login is already enabled",
+            ));
+        }
+        Some(stored) => stored,
+        None => {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                ERROR_CODE_TWO_FACTOR_NOT_SET_UP,
+                "Start with POST /auth/2fa/setup",
+            ));
+        }
+    };
+
+    let secret = open_secret(installed_key()?, &user.id, &stored.sealed)?;
+    let code = payload.code.trim();
+    let step = is_totp_code(code)
+        .then(|| matching_step(&secret, code, OffsetDateTime::now_utc()))
+        .flatten()
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ERROR_CODE_INVALID_TWO_FACTOR_CODE,
+                "Invalid two-factor code",
+            )
+        })?;
+
+    let recovery_codes: Vec<String> = (0..TWO_FACTOR_RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+    let hashes: Vec<String> = recovery_codes
+        .iter()
+        .map(|code| hash_token(&normalize_recovery_code(code)))
+        .collect();
+    let now = precise_timestamp(OffsetDateTime::now_utc());
+    let user_id = user.id.clone();
+    let enabled = with_transaction(&app_state.main_db, |conn| {
+        Box::pin(async move {
+            // The secret that was checked, in case another setup replaced it meanwhile
+            let enabled = conn
+                .execute(
+                    "UPDATE users SET totp_enabled_at = ?, totp_last_step = ?
+                     WHERE id = ? AND totp_secret = ? AND totp_enabled_at IS NULL",
+                    libsql::params![now.as_str(), step, user_id.as_str(), stored.sealed],
+                )
+                .await?;
+            if enabled == 0 {
+                return Ok::<bool, TwoFactorError>(false);
+            }
+            conn.execute(
+                "DELETE FROM two_factor_recovery_codes WHERE user_id = ?",
+                [user_id.as_str()],
+            )
+            .await?;
+            for hash in hashes {
+                conn.execute(
+                    "INSERT INTO two_factor_recovery_codes (user_id, code_hash, created_at)
+                     VALUES (?, ?, ?)",
+                    [user_id.as_str(), hash.as_str(), now.as_str()],
+                )
+                .await?;
+            }
+            Ok(true)
+        })
+    })
+    .await?;
+    if !enabled {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            ERROR_CODE_TWO_FACTOR_NOT_SET_UP,
+            "The two-factor secret changed; start again with POST /auth/2fa/setup",
+        ));
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(TwoFactorRecoveryCodesResponse { recovery_codes }),
+    ))
+}
+
+/// `POST /auth/2fa/disable`: turns two-factor login off after re-checking the password
+/// and a current TOTP or recovery code. The secret and recovery codes are deleted.
+pub async fn disable_two_factor(
+    State(app_state): State<AppState>,
+    session: Session,
+    Json(payload): Json<DisableTwoFactorPayload>,
+) -> Result<StatusCode, ApiError> {
+    let current_user = get_current_user(&session).await?;
+    if payload.password.is_empty() {
+        return Err(ApiError::bad_request("Password cannot be empty"));
+    }
+    let user = auth::get_user_by_id(&app_state.main_db, &current_user.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| ApiError::from((StatusCode::UNAUTHORIZED, ERR_UNAUTHORIZED.to_string())))?;
+    let is_valid = auth::verify_password(&payload.password, &user.password_hash)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !is_valid {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()).into());
+    }
+    if !is_enabled(&app_state.main_db, &user.id).await? {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            ERROR_CODE_TWO_FACTOR_NOT_ENABLED,
+            "Two-factor login is not enabled",
+        ));
+    }
+    let redeemed = {
+        let conn = app_state.main_db.write().await;
+        redeem_code(&conn, &user.id, &payload.code, OffsetDateTime::now_utc()).await?
+    };
+    if !redeemed {
+        return Err(invalid_code());
+    }
+
+    with_transaction(&app_state.main_db, |conn| {
+        Box::pin(async move {
+            conn.execute(
+                "UPDATE users SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_step = NULL
+                 WHERE id = ?",
+                [user.id.as_str()],
+            )
+            .await?;
+            delete_two_factor_rows(conn, &user.id).await?;
+            Ok::<(), TwoFactorError>(())
+        })
+    })
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Deletes `user_id`'s recovery codes and open login challenges.
+pub async fn delete_two_factor_rows(conn: &Connection, user_id: &str) -> libsql::Result<()> {
+    for statement in [
+        "DELETE FROM two_factor_recovery_codes WHERE user_id = ?",
+        "DELETE FROM two_factor_challenges WHERE user_id = ?",
+    ] {
+        conn.execute(statement, [user_id]).await?;
+    }
+    Ok(())
+}
@@ -1,6 +1,13 @@
-use axum::http::StatusCode;
+use axum::{
+    Json,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
 
 use crate::constants::*;
+use crate::models::{SplitMode, SplitParticipant};
+use crate::money::Money;
 
 pub fn db_error() -> (StatusCode, String) {
     (
@@ -16,6 +23,73 @@ pub fn db_error_with_context(context: &str) -> (StatusCode, String) {
     )
 }
 
+/// 503 for a write that found the database locked by another process on every retry.
+pub fn database_busy() -> (StatusCode, String) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        DATABASE_BUSY_MESSAGE.to_string(),
+    )
+}
+
+/// Non-cryptographic 64-bit FNV-1a hash, hex encoded. Used for payload fingerprints.
+pub fn fnv1a_64_hex(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// Version of a JSON body: the FNV-1a hash of its serialization, so equal bodies
+/// share a version. Sent as the `ETag` of conditional GETs and in `GET /bootstrap`.
+pub fn json_version<T: Serialize>(value: &T) -> String {
+    fnv1a_64_hex(&serde_json::to_vec(value).unwrap_or_default())
+}
+
+fn if_none_match_names(headers: &HeaderMap, version: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/").trim_matches('"'))
+        .any(|tag| tag == "*" || tag == version)
+}
+
+/// `200` with `value` and its `ETag`, or an empty `304` when `If-None-Match` already
+/// names that version.
+pub fn conditional_json<T: Serialize>(headers: &HeaderMap, value: T) -> Response {
+    let version = json_version(&value);
+    let etag = HeaderValue::from_str(&format!("\"{version}\""))
+        .expect("hex digest is a valid header value");
+    if if_none_match_names(headers, &version) {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+    (StatusCode::OK, [(header::ETAG, etag)], Json(value)).into_response()
+}
+
+/// UTC `YYYY-MM-DDTHH:MM:SS.nnnnnnnnnZ` with a fixed nine-digit fraction, so values sort
+/// as strings even for events microseconds apart (activity right after a login).
+pub fn precise_timestamp(at: time::OffsetDateTime) -> String {
+    let at = at.to_offset(time::UtcOffset::UTC);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+        at.year(),
+        u8::from(at.month()),
+        at.day(),
+        at.hour(),
+        at.minute(),
+        at.second(),
+        at.nanosecond()
+    )
+}
+
+/// `?, ?, ...` with `count` placeholders for an `IN (...)` list.
+pub fn sql_placeholders(count: usize) -> String {
+    vec!["?"; count].join(", ")
+}
+
 pub fn validate_string_length(
     value: &str,
     field_name: &str,
@@ -50,6 +124,61 @@ pub fn validate_date(value: &str) -> Result<(), (StatusCode, String)> {
     Ok(())
 }
 
+/// A record date the DB layer refuses to store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidDbDate(pub String);
+
+impl std::fmt::Display for InvalidDbDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid record date: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidDbDate {}
+
+impl From<InvalidDbDate> for libsql::Error {
+    fn from(value: InvalidDbDate) -> Self {
+        libsql::Error::ToSqlConversionFailure(Box::new(value))
+    }
+}
+
+/// The `YYYY-MM-DD` form every write to `records.date` goes through. Trims, zero-pads
+/// one-digit months and days, and rejects anything that is not a calendar date.
+pub fn to_db_date(value: &str) -> Result<String, InvalidDbDate> {
+    let invalid = || InvalidDbDate(value.to_string());
+    let digits = |part: &str, min_len: usize, max_len: usize| {
+        (min_len..=max_len).contains(&part.len()) && part.bytes().all(|b| b.is_ascii_digit())
+    };
+
+    let mut parts = value.trim().split('-');
+    let (Some(year), Some(month), Some(day), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid());
+    };
+    if !digits(year, 4, 4) || !digits(month, 1, 2) || !digits(day, 1, 2) {
+        return Err(invalid());
+    }
+
+    let month = month
+        .parse::<u8>()
+        .ok()
+        .and_then(|month| time::Month::try_from(month).ok())
+        .ok_or_else(invalid)?;
+    let date = time::Date::from_calendar_date(
+        year.parse().map_err(|_| invalid())?,
+        month,
+        day.parse().map_err(|_| invalid())?,
+    )
+    .map_err(|_| invalid())?;
+    Ok(format!(
+        "{:04}-{:02}-{:02}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    ))
+}
+
 pub async fn validate_category_exists(
     db: &crate::Db,
     user_id: &str,
@@ -121,88 +250,180 @@ pub fn validate_offset(offset: Option<u32>) -> Result<u32, (StatusCode, String)>
 /// Validates split participants for consistency and validity.
 ///
 /// Checks:
-/// - No duplicate user_ids (including initiator appearing in splits)
-/// - All amounts are strictly positive (> 0.0)
-/// - Amounts are finite (no NaN or infinity)
+/// - No duplicate user_ids (including initiator appearing in splits, except once for
+///   their own share in `exact` and `percentage` mode)
+/// - Each entry carries what `mode` needs: a positive `amount` in `exact` mode (the
+///   initiator's own may be zero), a percentage above 0 and at most 100 in `percentage`
+///   mode, neither in `equal` mode
 ///
 /// # Errors
-/// Returns descriptive error messages for validation failures.
+/// Returns descriptive error messages for validation failures, naming the participant.
 pub fn validate_split_participants(
-    splits: &[crate::models::SplitParticipant],
+    splits: &[SplitParticipant],
     initiator_id: &str,
+    mode: SplitMode,
 ) -> Result<(), String> {
-    // Check for duplicate user_ids and ensure initiator doesn't appear in splits
+    if splits.len() > MAX_SPLIT_PARTICIPANTS {
+        return Err(format!(
+            "A split can have at most {} participants besides the initiator",
+            MAX_SPLIT_PARTICIPANTS
+        ));
+    }
+
+    // Check for duplicate user_ids; only equal splits leave the initiator out entirely
     let mut seen_ids = std::collections::HashSet::new();
-    seen_ids.insert(initiator_id.to_string());
+    if mode == SplitMode::Equal {
+        seen_ids.insert(initiator_id.to_string());
+    }
 
     for split in splits {
         if !seen_ids.insert(split.user_id.clone()) {
             return Err(format!("Duplicate participant: {}", split.user_id));
         }
 
-        // Check amount is positive
-        if split.amount <= 0.0 {
-            return Err("Amount must be positive".to_string());
-        }
-
-        // Check for NaN or infinity
-        if !split.amount.is_finite() {
-            return Err("Amount must be a valid finite number".to_string());
+        let problem = match mode {
+            SplitMode::Exact if split.percentage.is_some() => {
+                Some("percentage is only used in percentage mode")
+            }
+            SplitMode::Exact if split.user_id == initiator_id && split.amount.is_negative() => {
+                Some("amount must not be negative")
+            }
+            SplitMode::Exact if split.user_id != initiator_id && !split.amount.is_positive() => {
+                Some("amount must be positive")
+            }
+            SplitMode::Equal if split.percentage.is_some() || !split.amount.is_zero() => {
+                Some("equal splits take no amount or percentage")
+            }
+            SplitMode::Percentage if !split.amount.is_zero() => {
+                Some("percentage splits take no amount")
+            }
+            SplitMode::Percentage => match split.percentage {
+                None => Some("percentage is required in percentage mode"),
+                Some(percentage) if percentage_basis_points(percentage).is_none() => Some(
+                    "percentage must be above 0 and at most 100, with at most two decimal places",
+                ),
+                Some(_) => None,
+            },
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            return Err(format!("Participant {}: {}", split.user_id, problem));
         }
     }
 
     Ok(())
 }
 
-/// Calculates final split amounts with deterministic remainder assignment.
+/// A percentage in hundredths of a percent, if it is above 0, at most 100 and has at
+/// most two decimal places.
+fn percentage_basis_points(percentage: f64) -> Option<u64> {
+    let basis_points = (percentage * 100.0).round();
+    let exact = (percentage * 100.0 - basis_points).abs() < 1e-6;
+    (percentage.is_finite() && exact && (1.0..=10_000.0).contains(&basis_points))
+        .then_some(basis_points as u64)
+}
+
+/// Calculates final split amounts, the initiator first.
 ///
-/// If the sum of split amounts is less than the total, the remainder is
-/// assigned to the initiator. Returns all participants (including initiator)
-/// with their final amounts, rounded to 2 decimals.
+/// Amounts are whole cents, so nothing is rounded in `exact` mode: each participant owes
+/// exactly their amount and the initiator's share is their own entry, or what is left of
+/// the total without one. `equal`
+/// and `percentage` divide the total with `Money::allocate`, the initiator first, so a
+/// leftover cent falls to the initiator before anyone else. Either way the shares add up
+/// to `total` to the cent. Expects participants already checked by
+/// `validate_split_participants`.
 ///
 /// # Errors
 /// Returns error if:
-/// - Split sum exceeds total_amount
-/// - Any amount is not finite
+/// - total_amount is not positive
+/// - Split sum exceeds total_amount, or misses it with the initiator's entry (`exact`)
+/// - Percentages do not add up to 100 (`percentage`)
+/// - A participant's share comes to less than a cent
 pub fn calculate_split_amounts(
-    total: f64,
-    splits: Vec<crate::models::SplitParticipant>,
+    total: Money,
+    splits: Vec<SplitParticipant>,
     initiator_id: &str,
-) -> Result<Vec<(String, f64)>, String> {
-    // Ensure total is valid
-    if !total.is_finite() || total <= 0.0 {
-        return Err("Total amount must be a positive finite number".to_string());
-    }
-
-    // Sum participant splits with rounding to 2 decimals
-    let mut total_split = 0.0;
-    for split in &splits {
-        // Round each split to 2 decimals to avoid floating-point drift
-        let rounded = (split.amount * 100.0).round() / 100.0;
-        total_split += rounded;
-    }
-
-    // Round total_split to 2 decimals for comparison
-    total_split = (total_split * 100.0).round() / 100.0;
-
-    // Check that sum doesn't exceed total
-    if total_split > total {
-        return Err("Split sum exceeds total".to_string());
+    mode: SplitMode,
+) -> Result<Vec<(String, Money)>, String> {
+    if !total.is_positive() {
+        return Err("Total amount must be positive".to_string());
     }
 
-    // Build result with all participants
-    let mut result = Vec::new();
+    let result = match mode {
+        SplitMode::Exact => {
+            let (own, others): (Vec<_>, Vec<_>) = splits
+                .into_iter()
+                .partition(|split| split.user_id == initiator_id);
+            let total_split: Money = others.iter().map(|split| split.amount).sum();
+            if total_split > total {
+                return Err("Split sum exceeds total".to_string());
+            }
+            let initiator_share = match own.first() {
+                Some(own) if own.amount + total_split != total => {
+                    return Err(format!(
+                        "Split amounts must add up to the total of {} with the initiator's share, not {}",
+                        total,
+                        own.amount + total_split
+                    ));
+                }
+                Some(own) => own.amount,
+                None => total - total_split,
+            };
 
-    // Add initiator's share (total minus all other splits, plus remainder)
-    let initiator_amount = total - total_split;
-    let initiator_amount = (initiator_amount * 100.0).round() / 100.0;
-    result.push((initiator_id.to_string(), initiator_amount));
+            let mut result = Vec::with_capacity(others.len() + 1);
+            result.push((initiator_id.to_string(), initiator_share));
+            result.extend(
+                others
+                    .into_iter()
+                    .map(|split| (split.user_id, split.amount)),
+            );
+            result
+        }
+        SplitMode::Equal => {
+            let user_ids: Vec<String> = std::iter::once(initiator_id.to_string())
+                .chain(splits.into_iter().map(|split| split.user_id))
+                .collect();
+            let shares = total.allocate(&vec![1; user_ids.len()]);
+            user_ids.into_iter().zip(shares).collect()
+        }
+        SplitMode::Percentage => {
+            let weight = |split: &SplitParticipant| {
+                split
+                    .percentage
+                    .and_then(percentage_basis_points)
+                    .unwrap_or(0)
+            };
+            let initiator_weight = splits
+                .iter()
+                .find(|split| split.user_id == initiator_id)
+                .map_or(0, weight);
+            let mut user_ids = vec![initiator_id.to_string()];
+            let mut weights = vec![initiator_weight];
+            for split in splits.iter().filter(|split| split.user_id != initiator_id) {
+                user_ids.push(split.user_id.clone());
+                weights.push(weight(split));
+            }
+            let sum: u64 = weights.iter().sum();
+            if sum != 10_000 {
+                return Err(format!(
+                    "Percentages must add up to 100, not {}",
+                    sum as f64 / 100.0
+                ));
+            }
+            let shares = total.allocate(&weights);
+            user_ids.into_iter().zip(shares).collect()
+        }
+    };
 
-    // Add all other participants with rounded amounts
-    for split in splits {
-        let rounded = (split.amount * 100.0).round() / 100.0;
-        result.push((split.user_id, rounded));
+    if let Some((user_id, _)) = result
+        .iter()
+        .skip(1)
+        .find(|(_, amount)| !amount.is_positive())
+    {
+        return Err(format!(
+            "Participant {}: share comes to less than a cent",
+            user_id
+        ));
     }
-
     Ok(result)
 }
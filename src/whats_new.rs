@@ -0,0 +1,257 @@
+use axum::{Json, extract::State, http::StatusCode};
+use libsql::Connection;
+use time::OffsetDateTime;
+use tower_sessions::Session;
+
+use crate::AppState;
+use crate::auth::get_current_user;
+use crate::constants::*;
+use crate::crypto;
+use crate::models::{
+    BudgetAlert, WhatsNew, WhatsNewCounts, WhatsNewFriendRequest, WhatsNewRecord, WhatsNewSplit,
+};
+use crate::money::Money;
+use crate::utils::{db_error_with_context, precise_timestamp};
+
+// "While you were away": what changed between the previous login and this one.
+//
+// Login moves `last_login_at` into `previous_login_at`; the digest covers everything
+// created since then. Fetching it stamps `last_seen_whats_new`, which hides the digest
+// until the next login. Every step is a fixed number of queries whatever the user's size.
+
+/// Budget alerts store whole-second `created_at`s (`status_timestamp`), so they are compared
+/// against the second the window starts in.
+fn since_second(since: &str) -> String {
+    match since.get(..19) {
+        Some(second) => format!("{second}Z"),
+        None => since.to_string(),
+    }
+}
+
+const INCOMING_REQUEST_FILTER: &str = "f.from_user_id = ? AND f.pending = 1 AND f.requester_user_id != ? AND f.status = ? AND f.created_at >= ?";
+const SPLIT_FILTER: &str = "r.owner_user_id = ? AND r.deleted_at IS NULL AND r.split_id IS NOT NULL AND r.creditor_user_id != ? AND r.created_at >= ?";
+const AUTO_RECORD_FILTER: &str = "r.owner_user_id = ? AND r.deleted_at IS NULL AND r.split_id IS NULL AND r.created_via != ? AND r.created_at >= ?";
+
+/// Starts a new login window for `user_id`.
+pub async fn record_login(conn: &Connection, user_id: &str) -> Result<(), libsql::Error> {
+    conn.execute(
+        "UPDATE users SET previous_login_at = last_login_at, last_login_at = ? WHERE id = ?",
+        (precise_timestamp(OffsetDateTime::now_utc()), user_id),
+    )
+    .await?;
+    Ok(())
+}
+
+/// The start of the unseen digest window: `None` on a first login or once it was fetched.
+async fn unseen_since(conn: &Connection, user_id: &str) -> Result<Option<String>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT previous_login_at FROM users WHERE id = ? AND (last_seen_whats_new IS NULL OR last_seen_whats_new < last_login_at)",
+            [user_id],
+        )
+        .await?;
+    match rows.next().await? {
+        Some(row) => row.get(0),
+        None => Ok(None),
+    }
+}
+
+/// Item counts of the unseen digest. Zero once it was fetched this login.
+pub async fn whats_new_counts(
+    conn: &Connection,
+    user_id: &str,
+) -> Result<WhatsNewCounts, libsql::Error> {
+    match unseen_since(conn, user_id).await? {
+        Some(since) => counts_since(conn, user_id, &since).await,
+        None => Ok(WhatsNewCounts::default()),
+    }
+}
+
+async fn counts_since(
+    conn: &Connection,
+    user_id: &str,
+    since: &str,
+) -> Result<WhatsNewCounts, libsql::Error> {
+    let sql = format!(
+        "SELECT \
+         (SELECT COUNT(*) FROM friendship f WHERE {INCOMING_REQUEST_FILTER}), \
+         (SELECT COUNT(*) FROM records r WHERE {SPLIT_FILTER}), \
+         (SELECT COUNT(*) FROM records r WHERE {AUTO_RECORD_FILTER}), \
+         (SELECT COUNT(*) FROM telegram_outbox o WHERE o.user_id = ? AND o.kind = ? AND o.created_at >= ?)"
+    );
+    let mut rows = conn
+        .query(
+            &sql,
+            (
+                user_id,
+                user_id,
+                FRIENDSHIP_STATUS_ACTIVE,
+                since,
+                user_id,
+                user_id,
+                since,
+                user_id,
+                CREATED_VIA_API,
+                since,
+                user_id,
+                OUTBOX_KIND_BUDGET_ALERT,
+                since_second(since),
+            ),
+        )
+        .await?;
+    let Some(row) = rows.next().await? else {
+        return Ok(WhatsNewCounts::default());
+    };
+    Ok(WhatsNewCounts {
+        friend_requests: row.get(0)?,
+        splits: row.get(1)?,
+        auto_records: row.get(2)?,
+        budget_alerts: row.get(3)?,
+    })
+}
+
+async fn list_friend_requests(
+    conn: &Connection,
+    user_id: &str,
+    since: &str,
+) -> Result<Vec<WhatsNewFriendRequest>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT f.to_user_id, COALESCE(u.name, ''), f.created_at, f.request_message FROM friendship f LEFT JOIN users u ON u.id = f.to_user_id WHERE {INCOMING_REQUEST_FILTER} ORDER BY f.created_at DESC LIMIT ?"
+            ),
+            (
+                user_id,
+                user_id,
+                FRIENDSHIP_STATUS_ACTIVE,
+                since,
+                WHATS_NEW_MAX_ITEMS,
+            ),
+        )
+        .await?;
+    let mut requests = Vec::new();
+    while let Some(row) = rows.next().await? {
+        requests.push(WhatsNewFriendRequest {
+            user_id: row.get(0)?,
+            username: row.get(1)?,
+            created_at: row.get(2)?,
+            message: row.get(3)?,
+        });
+    }
+    Ok(requests)
+}
+
+async fn list_splits(
+    conn: &Connection,
+    user_id: &str,
+    since: &str,
+) -> Result<Vec<WhatsNewSplit>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT r.id, r.name, r.amount, COALESCE(u.name, ''), r.created_at FROM records r LEFT JOIN users u ON u.id = r.creditor_user_id WHERE {SPLIT_FILTER} ORDER BY r.created_at DESC LIMIT ?"
+            ),
+            (user_id, user_id, since, WHATS_NEW_MAX_ITEMS),
+        )
+        .await?;
+    let mut splits = Vec::new();
+    while let Some(row) = rows.next().await? {
+        splits.push(WhatsNewSplit {
+            record_id: row.get(0)?,
+            description: crypto::open_field(row.get(1)?)?,
+            amount: Money::from_cents(row.get(2)?).abs(),
+            requested_by_name: row.get(3)?,
+            created_at: row.get(4)?,
+        });
+    }
+    Ok(splits)
+}
+
+async fn list_auto_records(
+    conn: &Connection,
+    user_id: &str,
+    since: &str,
+) -> Result<Vec<WhatsNewRecord>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            &format!(
+                "SELECT r.id, r.name, r.amount, r.created_via, r.created_at FROM records r WHERE {AUTO_RECORD_FILTER} ORDER BY r.created_at DESC LIMIT ?"
+            ),
+            (user_id, CREATED_VIA_API, since, WHATS_NEW_MAX_ITEMS),
+        )
+        .await?;
+    let mut records = Vec::new();
+    while let Some(row) = rows.next().await? {
+        records.push(WhatsNewRecord {
+            record_id: row.get(0)?,
+            name: crypto::open_field(row.get(1)?)?,
+            amount: Money::from_cents(row.get(2)?),
+            created_via: row.get(3)?,
+            created_at: row.get(4)?,
+        });
+    }
+    Ok(records)
+}
+
+async fn list_budget_alerts(
+    conn: &Connection,
+    user_id: &str,
+    since: &str,
+) -> Result<Vec<BudgetAlert>, libsql::Error> {
+    let mut rows = conn
+        .query(
+            "SELECT payload FROM telegram_outbox WHERE user_id = ? AND kind = ? AND created_at >= ? ORDER BY created_at DESC LIMIT ?",
+            (
+                user_id,
+                OUTBOX_KIND_BUDGET_ALERT,
+                since_second(since),
+                WHATS_NEW_MAX_ITEMS,
+            ),
+        )
+        .await?;
+    let mut alerts = Vec::new();
+    while let Some(row) = rows.next().await? {
+        // Skip payloads written by a different alert format rather than failing the digest.
+        if let Ok(alert) = serde_json::from_str(&row.get::<String>(0)?) {
+            alerts.push(alert);
+        }
+    }
+    Ok(alerts)
+}
+
+/// The unseen digest for `user_id`, marking it seen. Empty when there is nothing unseen.
+pub async fn take_whats_new(conn: &Connection, user_id: &str) -> Result<WhatsNew, libsql::Error> {
+    let Some(since) = unseen_since(conn, user_id).await? else {
+        return Ok(WhatsNew::default());
+    };
+
+    let digest = WhatsNew {
+        counts: counts_since(conn, user_id, &since).await?,
+        friend_requests: list_friend_requests(conn, user_id, &since).await?,
+        splits: list_splits(conn, user_id, &since).await?,
+        auto_records: list_auto_records(conn, user_id, &since).await?,
+        budget_alerts: list_budget_alerts(conn, user_id, &since).await?,
+        since: Some(since),
+    };
+
+    conn.execute(
+        "UPDATE users SET last_seen_whats_new = ? WHERE id = ?",
+        (precise_timestamp(OffsetDateTime::now_utc()), user_id),
+    )
+    .await?;
+    Ok(digest)
+}
+
+pub async fn get_whats_new(
+    State(app_state): State<AppState>,
+    session: Session,
+) -> Result<(StatusCode, Json<WhatsNew>), (StatusCode, String)> {
+    let user = get_current_user(&session).await?;
+
+    let conn = app_state.main_db.write().await;
+    let digest = take_whats_new(&conn, &user.id)
+        .await
+        .map_err(|_| db_error_with_context("failed to load what's new"))?;
+
+    Ok((StatusCode::OK, Json(digest)))
+}
@@ -0,0 +1,327 @@
+/// Tests Z281-Z283: Account archive export and import
+///
+/// `GET /auth/export` streams one JSON download with `schema_version`, the profile
+/// (no password hash), categories, friendships as the user sees them, the splits they
+/// initiated and every live record, read a page at a time. `POST /auth/import` restores
+/// the categories and records of such an archive into an account that has no records
+/// yet, under new ids; pending split shares and uncategorized records are skipped.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::categories;
+use kash_server::constants::*;
+use kash_server::models::{CreateCategoryPayload, CreateRecordPayload, ImportAccountResponse};
+use kash_server::records;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+/// Status, `Content-Disposition` and parsed body of `GET /auth/export`.
+async fn export(app: &common::TestApp, cookie: &str) -> (StatusCode, Option<String>, Value) {
+    let request = Request::builder()
+        .uri("/auth/export")
+        .header("cookie", cookie)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let disposition = response
+        .headers()
+        .get(header::CONTENT_DISPOSITION)
+        .map(|value| value.to_str().unwrap().to_string());
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(!text.contains("password"), "{text}");
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    (status, disposition, body)
+}
+
+async fn import(app: &common::TestApp, cookie: &str, archive: &Value) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/auth/import")
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(Body::from(archive.to_string()))
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn count(app: &common::TestApp, sql: &str, user_id: &str) -> i64 {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn.query(sql, [user_id]).await.unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+async fn split_scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    let carol = format!("carol_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob, &carol])
+        .category(&alice, "Dining")
+        .category(&bob, "Dining")
+        .friend(&alice, &bob)
+        .friend_request(&carol, &alice)
+        .split(&alice, "Dining", 90.0, &[(&bob, 30.0)])
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z281: The archive holds every section, with records across several pages
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z281_export_contains_every_section() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = split_scenario(&app, "z281").await;
+    let alice = scenario.id("alice_z281");
+    let bulk = ACCOUNT_ARCHIVE_PAGE_SIZE as i64 * 2 + 7;
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < ?3)
+             INSERT INTO records (id, owner_user_id, name, amount, category_id, date, seq)
+             SELECT 'z281-' || i, ?1, 'Item ' || i, -1.0, ?2, '2024-02-01', 1000000 + i FROM n",
+            libsql::params![alice, scenario.category_id("alice_z281", "Dining"), bulk],
+        )
+        .await
+        .unwrap();
+    }
+
+    let (status, disposition, archive) = export(&app, scenario.cookie("alice_z281")).await;
+    assert_eq!(status, StatusCode::OK, "{archive}");
+    assert!(
+        disposition
+            .expect("Content-Disposition")
+            .starts_with("attachment; filename=\"kash-export-")
+    );
+    assert_eq!(archive["schema_version"], ACCOUNT_ARCHIVE_SCHEMA_VERSION);
+    assert_eq!(archive["profile"]["id"], alice);
+    assert_eq!(archive["profile"]["username"], "alice_z281");
+    assert_eq!(archive["categories"][0]["name"], "Dining");
+
+    let friendships: Vec<(&str, bool, bool)> = archive["friendships"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|friendship| {
+            (
+                friendship["username"].as_str().unwrap(),
+                friendship["pending"].as_bool().unwrap(),
+                friendship["requested_by_me"].as_bool().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        friendships,
+        vec![("bob_z281", false, true), ("carol_z281", true, false)]
+    );
+
+    assert_eq!(archive["splits"].as_array().unwrap().len(), 1);
+    assert_eq!(
+        archive["splits"][0]["split_id"],
+        scenario.splits[0].split_id.as_str()
+    );
+    assert_eq!(
+        archive["splits"][0]["participants"][0]["username"],
+        "bob_z281"
+    );
+
+    let records = archive["records"].as_array().unwrap();
+    assert_eq!(records.len() as i64, bulk + 1);
+    let mut ids: Vec<&str> = records
+        .iter()
+        .map(|record| record["id"].as_str().unwrap())
+        .collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), records.len(), "no record repeated across pages");
+    let split_record = records
+        .iter()
+        .find(|record| !record["split_id"].is_null())
+        .expect("split record");
+    assert_eq!(
+        split_record["split_id"],
+        scenario.splits[0].split_id.as_str()
+    );
+    assert_eq!(split_record["creditor_user_id"], alice);
+
+    // A participant sees their pending share, and no split they initiated
+    let (status, _, archive) = export(&app, scenario.cookie("bob_z281")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(archive["splits"], json!([]));
+    assert_eq!(archive["records"][0]["pending"], true);
+    assert_eq!(archive["records"][0]["settle"], false);
+}
+
+// ---------------------------------------------------------------------------
+// Z282: An archive imports into an empty account under new ids
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z282_import_restores_categories_and_records() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = split_scenario(&app, "z282").await;
+    let alice = scenario.id("alice_z282");
+    let dining = scenario.category_id("alice_z282", "Dining");
+    let takeaway = categories::create_category_for_user(
+        &app.state.main_db,
+        alice,
+        CreateCategoryPayload {
+            name: "Takeaway".to_string(),
+            is_income: false,
+            parent_id: Some(dining.to_string()),
+            monthly_budget: Some(money(50.0)),
+        },
+    )
+    .await
+    .unwrap();
+    records::create_record_for_user(
+        &app.state.main_db,
+        alice,
+        CreateRecordPayload {
+            name: "Noodles".to_string(),
+            amount: money(12.5),
+            currency: None,
+            category_id: takeaway.id.clone(),
+            date: "2024-03-02".to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .unwrap();
+    let (_, _, archive) = export(&app, scenario.cookie("alice_z282")).await;
+
+    let dave = common::create_test_user(&app.state, "dave_z282", "password123")
+        .await
+        .unwrap();
+    let cookie = common::login_user(&app.router, "dave_z282", "password123")
+        .await
+        .unwrap();
+    categories::create_category_for_user(
+        &app.state.main_db,
+        &dave,
+        CreateCategoryPayload {
+            name: "dining".to_string(),
+            is_income: false,
+            parent_id: None,
+            monthly_budget: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let mut future = archive.clone();
+    future["schema_version"] = json!(ACCOUNT_ARCHIVE_SCHEMA_VERSION + 1);
+    let (status, _) = import(&app, &cookie, &future).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = import(&app, &cookie, &archive).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let response: ImportAccountResponse = serde_json::from_value(body).unwrap();
+    assert_eq!(
+        response,
+        ImportAccountResponse {
+            categories_imported: 1,
+            records_imported: 2,
+            records_skipped: 0,
+        }
+    );
+
+    // "Dining" matched dave's own "dining"; "Takeaway" is new and still under it
+    assert_eq!(
+        count(
+            &app,
+            "SELECT COUNT(*) FROM categories c JOIN categories p ON p.id = c.parent_id
+             WHERE c.owner_user_id = ?1 AND c.name = 'Takeaway' AND p.name = 'dining'
+               AND c.monthly_budget = 5000",
+            &dave
+        )
+        .await,
+        1
+    );
+    assert_eq!(
+        count(
+            &app,
+            "SELECT COUNT(*) FROM records WHERE owner_user_id = ? AND created_via = 'import'
+               AND split_id IS NULL AND creditor_user_id IS NULL",
+            &dave
+        )
+        .await,
+        2
+    );
+    assert_eq!(
+        count(
+            &app,
+            "SELECT COUNT(*) FROM records WHERE owner_user_id = ?",
+            alice
+        )
+        .await,
+        2,
+        "the source account is untouched"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z283: Import needs an account without records; pending shares are skipped
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z283_import_requires_an_empty_account() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = split_scenario(&app, "z283").await;
+    let (_, _, alice_archive) = export(&app, scenario.cookie("alice_z283")).await;
+    let (_, _, bob_archive) = export(&app, scenario.cookie("bob_z283")).await;
+
+    // Bob holds a pending share, so the account is not empty
+    let (status, body) = import(&app, scenario.cookie("bob_z283"), &alice_archive).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], ERROR_CODE_ACCOUNT_NOT_EMPTY, "{body}");
+    assert_eq!(
+        count(
+            &app,
+            "SELECT COUNT(*) FROM records WHERE owner_user_id = ?",
+            scenario.id("bob_z283")
+        )
+        .await,
+        1
+    );
+
+    let carol = scenario.id("carol_z283");
+    let (status, body) = import(&app, scenario.cookie("carol_z283"), &bob_archive).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let response: ImportAccountResponse = serde_json::from_value(body).unwrap();
+    assert_eq!(response.records_imported, 0);
+    assert_eq!(response.records_skipped, 1);
+    assert_eq!(
+        count(
+            &app,
+            "SELECT COUNT(*) FROM records WHERE owner_user_id = ?",
+            carol
+        )
+        .await,
+        0
+    );
+}
@@ -0,0 +1,433 @@
+/// Tests Z271-Z275: Account deletion
+///
+/// `DELETE /auth/account` re-checks the password, then `account::delete_account` removes
+/// the user in one transaction: both directions of their friendships, the Telegram link,
+/// idempotency keys, participants' pending shares of splits they initiated, and everything
+/// they own. Accepted shares of their splits stay with their owners as plain records; in
+/// other people's splits they are listed under `departed_participants`. Each step is tested
+/// on its own, then through the endpoint.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::account;
+use kash_server::constants::SPLIT_STATUS_INITIATED;
+use kash_server::models::{SplitDepartedParticipant, SplitDetail};
+use serde_json::{Value, json};
+use time::OffsetDateTime;
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> (StatusCode, Value) {
+    let body = payload.map_or_else(Body::empty, |payload| Body::from(payload.to_string()));
+    let request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    (status, body)
+}
+
+async fn delete_account(app: &common::TestApp, cookie: &str, password: &str) -> StatusCode {
+    send(
+        app,
+        "DELETE",
+        "/auth/account",
+        cookie,
+        Some(json!({ "password": password })),
+    )
+    .await
+    .0
+}
+
+async fn count(app: &common::TestApp, sql: &str, user_id: &str) -> i64 {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn.query(sql, [user_id]).await.unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+async fn finalize(app: &common::TestApp, scenario: &Scenario, user: &str, record_id: &str) {
+    let (status, body) = send(
+        app,
+        "POST",
+        "/records/finalize-pending",
+        scenario.cookie(user),
+        Some(json!({
+            "record_id": record_id,
+            "category_id": scenario.category_id(user, "Dining"),
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+}
+
+async fn three_way_split(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    let carol = format!("carol_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob, &carol])
+        .category(&alice, "Dining")
+        .category(&bob, "Dining")
+        .friend(&alice, &bob)
+        .friend(&alice, &carol)
+        .split(&alice, "Dining", 90.0, &[(&bob, 30.0), (&carol, 20.0)])
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z271: The endpoint needs the password, then the account and its session are gone
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z271_delete_account_requires_password_and_ends_session() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let user_id = common::create_test_user(&app.state, "alice_z271", "password123")
+        .await
+        .unwrap();
+    common::create_test_user(&app.state, "bob_z271", "password123")
+        .await
+        .unwrap();
+    let cookie = common::login_user(&app.router, "alice_z271", "password123")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        delete_account(&app, &cookie, "wrong-password").await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        delete_account(&app, &cookie, "").await,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        count(&app, "SELECT COUNT(*) FROM users WHERE id = ?", &user_id).await,
+        1
+    );
+
+    assert_eq!(
+        delete_account(&app, &cookie, "password123").await,
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(
+        count(&app, "SELECT COUNT(*) FROM users WHERE id = ?", &user_id).await,
+        0
+    );
+    let (status, _) = common::auth_request(&app.router, "GET", "/auth/me", &cookie)
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(
+        common::login_user(&app.router, "alice_z271", "password123")
+            .await
+            .is_err()
+    );
+
+    // Other accounts are untouched
+    assert!(
+        common::login_user(&app.router, "bob_z271", "password123")
+            .await
+            .is_ok()
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z272: Friendships in both directions, the Telegram link and idempotency keys
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z272_friendships_telegram_links_and_idempotency_keys_are_removed() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z272", "bob_z272", "carol_z272"])
+        .friend("alice_z272", "bob_z272")
+        .friend_request("carol_z272", "alice_z272")
+        .friend("bob_z272", "carol_z272")
+        .build(&app)
+        .await;
+    let alice = scenario.id("alice_z272");
+    {
+        let conn = app.state.main_db.write().await;
+        for user_id in [alice, scenario.id("bob_z272")] {
+            conn.execute(
+                "INSERT INTO telegram_users (telegram_user_id, user_id, chat_id, created_at)
+                 VALUES ('tg-' || ?1, ?1, 'chat', 0)",
+                [user_id],
+            )
+            .await
+            .unwrap();
+            conn.execute(
+                "INSERT INTO idempotency_keys (id, key, user_id, endpoint, payload_hash, response_status, created_at, expires_at)
+                 VALUES ('key-' || ?1, 'k', ?1, '/splits/create', 'h', 201, '', '')",
+                [user_id],
+            )
+            .await
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO bot_pending_actions (id, telegram_user_id, user_id, summary, action, expires_at)
+             VALUES ('action-z272', 'tg-' || ?1, ?1, 'summary', '{}', 0)",
+            [alice],
+        )
+        .await
+        .unwrap();
+    }
+
+    let friendship_sql =
+        "SELECT COUNT(*) FROM friendship WHERE from_user_id = ?1 OR to_user_id = ?1";
+    let others_before = count(&app, friendship_sql, scenario.id("bob_z272")).await;
+    {
+        let conn = app.state.main_db.write().await;
+        assert!(account::delete_friendships(&conn, alice).await.unwrap() >= 3);
+        assert_eq!(
+            account::delete_telegram_links(&conn, alice).await.unwrap(),
+            1
+        );
+        assert_eq!(
+            account::delete_idempotency_keys(&conn, alice)
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    assert_eq!(count(&app, friendship_sql, alice).await, 0);
+    assert_eq!(
+        count(&app, friendship_sql, scenario.id("bob_z272")).await,
+        others_before - 2,
+        "bob keeps the friendship with carol"
+    );
+    assert_eq!(
+        count(
+            &app,
+            "SELECT COUNT(*) FROM bot_pending_actions WHERE user_id = ?",
+            alice
+        )
+        .await,
+        0
+    );
+    for table in ["telegram_users", "idempotency_keys"] {
+        let sql = format!("SELECT COUNT(*) FROM {table} WHERE user_id = ?");
+        assert_eq!(count(&app, &sql, alice).await, 0, "{table}");
+        assert_eq!(
+            count(&app, &sql, scenario.id("bob_z272")).await,
+            1,
+            "{table}"
+        );
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Z273: Splits the user initiated: pending shares voided, accepted ones detached
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z273_initiated_splits_void_pending_and_detach_accepted_shares() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z273").await;
+    let split = &scenario.splits[0];
+    let (bob_record, carol_record) = (&split.pending_record_ids[0], &split.pending_record_ids[1]);
+    finalize(&app, &scenario, "bob_z273", bob_record).await;
+
+    let alice = scenario.id("alice_z273");
+    {
+        let conn = app.state.main_db.write().await;
+        assert_eq!(
+            account::void_initiated_split_shares(&conn, alice)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            account::detach_shared_records(&conn, alice).await.unwrap(),
+            1
+        );
+    }
+
+    let record_sql = "SELECT COUNT(*) FROM records WHERE id = ?";
+    assert_eq!(count(&app, record_sql, carol_record).await, 0);
+    assert_eq!(count(&app, record_sql, bob_record).await, 1);
+    assert_eq!(
+        count(
+            &app,
+            "SELECT COUNT(*) FROM records WHERE id = ? AND split_id IS NULL
+               AND debtor_user_id IS NULL AND creditor_user_id IS NULL",
+            bob_record
+        )
+        .await,
+        1,
+        "bob's accepted share is now a plain record"
+    );
+    assert_eq!(
+        count(
+            &app,
+            "SELECT COUNT(*) FROM records WHERE owner_user_id = ?",
+            alice
+        )
+        .await,
+        1,
+        "alice's own share goes with the rest of alice's records"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z274: In someone else's split the user is listed as departed
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z274_participant_is_listed_as_departed() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z274").await;
+    let uri = format!("/splits/{}", scenario.splits[0].split_id);
+    let bob = scenario.id("bob_z274").to_string();
+
+    {
+        let conn = app.state.main_db.write().await;
+        let now = OffsetDateTime::now_utc();
+        assert_eq!(
+            account::leave_participant_splits(&conn, &bob, "bob_z274", now)
+                .await
+                .unwrap(),
+            1
+        );
+        // The initiator's and carol's splits are not bob's to leave
+        assert_eq!(
+            account::leave_participant_splits(&conn, scenario.id("alice_z274"), "alice_z274", now)
+                .await
+                .unwrap(),
+            0
+        );
+        conn.execute("DELETE FROM split_departures", ())
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(
+        delete_account(&app, scenario.cookie("bob_z274"), "password123").await,
+        StatusCode::NO_CONTENT
+    );
+
+    let (status, body) = send(&app, "GET", &uri, scenario.cookie("alice_z274"), None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let detail: SplitDetail = serde_json::from_value(body).unwrap();
+    assert_eq!(detail.total_amount, money(90.0));
+    assert_eq!(detail.status, SPLIT_STATUS_INITIATED);
+    assert_eq!(
+        detail
+            .participants
+            .iter()
+            .map(|participant| participant.username.as_str())
+            .collect::<Vec<_>>(),
+        vec!["carol_z274"]
+    );
+    let departed: Vec<SplitDepartedParticipant> = detail
+        .departed_participants
+        .into_iter()
+        .map(|departed| SplitDepartedParticipant {
+            departed_at: String::new(),
+            ..departed
+        })
+        .collect();
+    assert_eq!(
+        departed,
+        vec![SplitDepartedParticipant {
+            user_id: bob,
+            username: "bob_z274".to_string(),
+            amount: money(30.0),
+            pending: true,
+            settled: false,
+            departed_at: String::new(),
+        }]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z275: Everything the user owns is removed, nobody else's data
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z275_owned_data_is_removed() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z275").await;
+    let alice = scenario.id("alice_z275");
+    let bob = scenario.id("bob_z275");
+    {
+        let conn = app.state.main_db.write().await;
+        for user_id in [alice, bob] {
+            conn.execute(
+                "INSERT INTO sessions (id, data, expiry_date)
+                 VALUES ('session-' || ?1, json_object('user_id', ?1), 4102444800)",
+                [user_id],
+            )
+            .await
+            .unwrap();
+        }
+        // Attempts that matched no account: under alice's name in another case, under a
+        // name she used before, and under bob's
+        conn.execute(
+            "INSERT INTO username_history (id, user_id, old_name, new_name, changed_at)
+             VALUES ('history-z275', ?1, 'alicia_z275', 'alice_z275', '2025-01-01T00:00:00Z')",
+            [alice],
+        )
+        .await
+        .unwrap();
+        for username in ["ALICE_z275", "alicia_z275", "bob_z275"] {
+            conn.execute(
+                "INSERT INTO login_attempts (id, user_id, username, attempted_at, success, source)
+                 VALUES ('attempt-' || ?1, NULL, ?1, '2025-01-01T00:00:00Z', 0, 'web')",
+                [username],
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    let owned = [
+        "SELECT COUNT(*) FROM records WHERE owner_user_id = ?",
+        "SELECT COUNT(*) FROM categories WHERE owner_user_id = ?",
+        "SELECT COUNT(*) FROM login_attempts WHERE user_id = ?",
+        "SELECT COUNT(*) FROM sessions WHERE json_extract(data, '$.user_id') = ?",
+    ];
+    let mut bob_before = Vec::new();
+    for sql in owned {
+        assert!(count(&app, sql, alice).await > 0, "{sql}");
+        bob_before.push(count(&app, sql, bob).await);
+    }
+
+    {
+        let conn = app.state.main_db.write().await;
+        assert_eq!(account::delete_owned_data(&conn, alice).await.unwrap(), 1);
+        assert!(account::delete_user_row(&conn, alice).await.unwrap());
+        assert!(!account::delete_user_row(&conn, alice).await.unwrap());
+    }
+
+    for (sql, before) in owned.into_iter().zip(bob_before) {
+        assert_eq!(count(&app, sql, alice).await, 0, "{sql}");
+        assert_eq!(count(&app, sql, bob).await, before, "{sql}");
+    }
+    let unlinked = "SELECT COUNT(*) FROM login_attempts WHERE user_id IS NULL AND username = ?";
+    for username in ["ALICE_z275", "alicia_z275"] {
+        assert_eq!(count(&app, unlinked, username).await, 0, "{username}");
+    }
+    assert_eq!(count(&app, unlinked, "bob_z275").await, 1);
+}
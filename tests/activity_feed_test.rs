@@ -0,0 +1,340 @@
+/// Tests Y1-Y4: Per-category and per-friend activity feeds
+///
+/// `GET /categories/{id}/activity` is `GET /records` with the category forced.
+/// `GET /friends/{id}/activity` merges split shares (by creation), settlements
+/// (by `settled_at`) and friendship changes into one newest-first list that is
+/// paginated as a whole.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::models::{CreateRecordPayload, FriendActivityItem, FriendActivityResponse};
+use kash_server::records;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn get_json(app: &common::TestApp, uri: &str, cookie: &str) -> (StatusCode, Value) {
+    let (status, body) = common::auth_request(&app.router, "GET", uri, cookie)
+        .await
+        .expect("request");
+    let body = serde_json::from_str(&body).unwrap_or(Value::String(body));
+    (status, body)
+}
+
+async fn friend_activity_page(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    viewer: &str,
+    friend: &str,
+    limit: u32,
+    offset: u32,
+) -> FriendActivityResponse {
+    let (status, body) = get_json(
+        app,
+        &format!(
+            "/friends/{}/activity?limit={limit}&offset={offset}",
+            scenario.id(friend)
+        ),
+        scenario.cookie(viewer),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    serde_json::from_value(body).expect("activity response")
+}
+
+async fn create_record(app: &common::TestApp, scenario: &Scenario, category: &str, name: &str) {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id("alice_y1"),
+        CreateRecordPayload {
+            name: name.to_string(),
+            amount: money(12.0),
+            currency: None,
+            category_id: scenario.category_id("alice_y1", category).to_string(),
+            date: "2025-03-10".to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record");
+}
+
+async fn execute(app: &common::TestApp, sql: &str, params: (&str, &str)) {
+    let conn = app.state.main_db.write().await;
+    conn.execute(sql, params).await.expect("seed timestamp");
+}
+
+fn at(second: u32) -> String {
+    format!("2025-01-01T00:00:{second:02}.000000000Z")
+}
+
+/// `(kind, at)` of each item, for order assertions.
+fn shape(items: &[FriendActivityItem]) -> Vec<(&'static str, String)> {
+    items
+        .iter()
+        .map(|item| {
+            let kind = match item {
+                FriendActivityItem::Split { .. } => "split",
+                FriendActivityItem::Settlement { .. } => "settlement",
+                FriendActivityItem::Friendship { .. } => "friendship",
+            };
+            (kind, item.at().to_string())
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Y1: Category activity lists only that category, newest first, paginated
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn y1_category_activity_is_scoped_and_paginated() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_y1", "bob_y1"])
+        .category("alice_y1", "Food")
+        .category("alice_y1", "Travel")
+        .category("bob_y1", "Food")
+        .build(&app)
+        .await;
+    for name in ["Lunch", "Dinner", "Snack"] {
+        create_record(&app, &scenario, "Food", name).await;
+    }
+    create_record(&app, &scenario, "Travel", "Train").await;
+    let food = scenario.category_id("alice_y1", "Food");
+
+    let uri = format!("/categories/{food}/activity?limit=2");
+    let (status, body) = get_json(&app, &uri, scenario.cookie("alice_y1")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["total_count"], 3);
+    let names: Vec<&str> = body["records"]
+        .as_array()
+        .expect("records")
+        .iter()
+        .map(|record| record["name"].as_str().expect("name"))
+        .collect();
+    assert_eq!(names, vec!["Snack", "Dinner"]);
+
+    let uri = format!("/categories/{food}/activity?limit=2&offset=2");
+    let (_, body) = get_json(&app, &uri, scenario.cookie("alice_y1")).await;
+    assert_eq!(body["records"][0]["name"], "Lunch");
+    assert_eq!(body["records"].as_array().expect("records").len(), 1);
+
+    let uri = format!("/categories/{food}/activity");
+    let (status, _) = get_json(&app, &uri, scenario.cookie("bob_y1")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "another user's category");
+    let uri = format!("/categories/{food}/activity?limit=0");
+    let (status, _) = get_json(&app, &uri, scenario.cookie("alice_y1")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+// ---------------------------------------------------------------------------
+// Y2: Interleaved splits, settlements and friendship events keep one global order
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn y2_friend_activity_merges_sources_across_pages() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_y2", "bob_y2"])
+        .category("alice_y2", "Dining")
+        .category("bob_y2", "Dining")
+        .friend("alice_y2", "bob_y2")
+        .split("alice_y2", "Dining", 20.0, &[("bob_y2", 10.0)])
+        .split("bob_y2", "Dining", 40.0, &[("alice_y2", 20.0)])
+        .split("alice_y2", "Dining", 60.0, &[("bob_y2", 30.0)])
+        .build(&app)
+        .await;
+    let shares: Vec<&str> = scenario
+        .splits
+        .iter()
+        .map(|split| split.pending_record_ids[0].as_str())
+        .collect();
+
+    // Friendship timestamps mix whole-second (status) and precise formats.
+    execute(
+        &app,
+        "UPDATE friendship SET created_at = ?, accepted_at = ?",
+        ("2025-01-01T00:00:01Z", &at(3)),
+    )
+    .await;
+    for (share, second) in [(shares[0], 2), (shares[1], 4), (shares[2], 6)] {
+        execute(
+            &app,
+            "UPDATE records SET created_at = ? WHERE id = ?",
+            (&at(second), share),
+        )
+        .await;
+    }
+    for (share, second) in [(shares[0], 5), (shares[1], 7)] {
+        execute(
+            &app,
+            "UPDATE records SET settle = 1, settled_at = ? WHERE id = ?",
+            (&at(second), share),
+        )
+        .await;
+    }
+
+    let expected = vec![
+        ("settlement", at(7)),
+        ("split", at(6)),
+        ("settlement", at(5)),
+        ("split", at(4)),
+        ("friendship", at(3)),
+        ("split", at(2)),
+        ("friendship", at(1)),
+    ];
+    let full = friend_activity_page(&app, &scenario, "alice_y2", "bob_y2", 50, 0).await;
+    assert_eq!(full.total_count, 7);
+    assert_eq!(shape(&full.items), expected);
+
+    let mut paged = Vec::new();
+    for offset in [0, 3, 6] {
+        let page = friend_activity_page(&app, &scenario, "alice_y2", "bob_y2", 3, offset).await;
+        assert_eq!(page.total_count, 7);
+        assert_eq!(page.offset, offset);
+        paged.extend(page.items);
+    }
+    assert_eq!(paged, full.items, "pages concatenate to the full feed");
+
+    let FriendActivityItem::Split {
+        description,
+        amount,
+        paid_by_user_id,
+        pending,
+        ..
+    } = &full.items[3]
+    else {
+        panic!("expected a split: {:?}", full.items[3]);
+    };
+    assert_eq!(description.as_deref(), Some("bob_y2 split"));
+    assert_eq!(*amount, money(20.0));
+    assert_eq!(paid_by_user_id, scenario.id("bob_y2"));
+    assert!(*pending);
+    assert_eq!(
+        full.items[4],
+        FriendActivityItem::Friendship {
+            at: at(3),
+            event: "accepted".to_string(),
+        }
+    );
+
+    let bob_view = friend_activity_page(&app, &scenario, "bob_y2", "alice_y2", 50, 0).await;
+    assert_eq!(
+        shape(&bob_view.items),
+        expected,
+        "both sides see one timeline"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Y3: Strangers are refused; former friends keep their history
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn y3_friend_activity_requires_a_relationship() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_y3", "bob_y3", "carol_y3"])
+        .category("alice_y3", "Dining")
+        .friend("alice_y3", "bob_y3")
+        .split("alice_y3", "Dining", 20.0, &[("bob_y3", 10.0)])
+        .build(&app)
+        .await;
+
+    let (status, _) = get_json(
+        &app,
+        &format!("/friends/{}/activity", scenario.id("carol_y3")),
+        scenario.cookie("alice_y3"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/friends/remove")
+        .header("cookie", scenario.cookie("alice_y3"))
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({ "friend_id": scenario.id("bob_y3") }).to_string(),
+        ))
+        .expect("build request");
+    let response = app.router.clone().oneshot(request).await.expect("remove");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let page = friend_activity_page(&app, &scenario, "alice_y3", "bob_y3", 50, 0).await;
+    let events: Vec<&str> = page
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            FriendActivityItem::Friendship { event, .. } => Some(event.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert!(events.contains(&"unfriended"), "{events:?}");
+    assert!(
+        page.items
+            .iter()
+            .any(|item| matches!(item, FriendActivityItem::Split { .. })),
+        "the split stays in the history"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Y4: Settling through the API stamps the settlement into the feed
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn y4_settle_all_appears_as_settlement() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_y4", "bob_y4"])
+        .category("alice_y4", "Dining")
+        .friend("alice_y4", "bob_y4")
+        .split("alice_y4", "Dining", 20.0, &[("bob_y4", 10.0)])
+        .build(&app)
+        .await;
+    execute(
+        &app,
+        "UPDATE records SET pending = 0 WHERE id = ? AND split_id = ?",
+        (
+            &scenario.splits[0].pending_record_ids[0],
+            &scenario.splits[0].split_id,
+        ),
+    )
+    .await;
+
+    let (status, body) = common::auth_request(
+        &app.router,
+        "PUT",
+        &format!("/splits/unsettled/{}/settle_all", scenario.id("bob_y4")),
+        scenario.cookie("alice_y4"),
+    )
+    .await
+    .expect("settle all");
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let page = friend_activity_page(&app, &scenario, "alice_y4", "bob_y4", 1, 0).await;
+    let FriendActivityItem::Settlement {
+        description,
+        amount,
+        paid_by_user_id,
+        ..
+    } = &page.items[0]
+    else {
+        panic!("newest entry is the settlement: {:?}", page.items);
+    };
+    assert_eq!(description.as_deref(), Some("alice_y4 split"));
+    assert_eq!(*amount, money(10.0));
+    assert_eq!(paid_by_user_id, scenario.id("alice_y4"));
+}
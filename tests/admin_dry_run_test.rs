@@ -0,0 +1,390 @@
+/// Tests D1-D6: Admin actions and dry runs
+///
+/// Every `/admin` endpoint runs an `AdminAction`: `dry_run=true` returns the plan
+/// without writing, and the real run applies the plan it computes in the same
+/// transaction. These tests check that the dry-run plan equals the applied plan
+/// and that exactly the planned rows change.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::TEST_ADMIN_TOKEN;
+use common::fixtures::{FIXTURE_PASSWORD, ScenarioBuilder};
+use common::send_request;
+use kash_server::AppState;
+use kash_server::admin::{self, PruneFriendships};
+use kash_server::constants::FRIENDSHIP_STATUS_UNFRIENDED;
+use serde_json::{Value, json};
+use time::OffsetDateTime;
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn admin_post(app: &common::TestApp, uri: &str, token: Option<&str>) -> (StatusCode, Value) {
+    let mut request = Request::builder().uri(uri).method("POST");
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = app
+        .router
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn count(app: &common::TestApp, sql: &str) -> i64 {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn.query(sql, ()).await.unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+/// Dry-runs `uri`, checks nothing changed, then applies it and checks the applied
+/// plan matches the preview and `expected_rows` rows changed. Returns the plan.
+async fn assert_plan_matches_apply(
+    app: &common::TestApp,
+    uri: &str,
+    state_sql: &str,
+    expected_rows: u64,
+) -> Value {
+    let before = count(app, state_sql).await;
+
+    let separator = if uri.contains('?') { '&' } else { '?' };
+    let (status, preview) = admin_post(
+        app,
+        &format!("{uri}{separator}dry_run=true"),
+        Some(TEST_ADMIN_TOKEN),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{preview}");
+    assert_eq!(preview["dry_run"], true);
+    assert!(preview["rows_affected"].is_null());
+    assert_eq!(
+        count(app, state_sql).await,
+        before,
+        "dry run must not write"
+    );
+
+    let (status, applied) = admin_post(app, uri, Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(status, StatusCode::OK, "{applied}");
+    assert_eq!(applied["dry_run"], false);
+    assert_eq!(applied["action"], preview["action"]);
+    assert_eq!(
+        applied["plan"], preview["plan"],
+        "preview diverged from action"
+    );
+    assert_eq!(applied["rows_affected"], expected_rows);
+    assert_eq!(
+        count(app, state_sql).await,
+        before - expected_rows as i64,
+        "exactly the planned rows change"
+    );
+
+    preview["plan"].clone()
+}
+
+// ---------------------------------------------------------------------------
+// D1: Admin routes require the configured bearer token
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn d1_admin_routes_require_token() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let uri = "/admin/idempotency-keys/cleanup?dry_run=true";
+
+    let (status, _) = admin_post(&app, uri, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = admin_post(&app, uri, Some("wrong_token_wrong_token_wrong_token")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = admin_post(&app, uri, Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let disabled = AppState {
+        admin_token: None,
+        ..app.state.clone()
+    };
+    let mut headers = axum::http::HeaderMap::new();
+    headers.insert(
+        "authorization",
+        format!("Bearer {TEST_ADMIN_TOKEN}").parse().unwrap(),
+    );
+    let (status, _) = admin::require_admin(&disabled, &headers).expect_err("no token configured");
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+// ---------------------------------------------------------------------------
+// D2: Friendship prune plan matches what is deleted
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn d2_prune_friendships_plan_matches_apply() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_d2", "bob_d2", "carol_d2"])
+        .friend("alice_d2", "bob_d2")
+        .friend("alice_d2", "carol_d2")
+        .build(&app)
+        .await;
+
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/remove",
+        scenario.cookie("alice_d2"),
+        Some(json!({ "friend_id": scenario.id("bob_d2") })),
+    )
+    .await;
+    assert!(response.status().is_success());
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "UPDATE friendship SET status_changed_at = '2020-01-01T00:00:00Z' WHERE status = 'unfriended'",
+            (),
+        )
+        .await
+        .unwrap();
+    }
+
+    let plan = assert_plan_matches_apply(
+        &app,
+        "/admin/friendships/prune?status=unfriended&older_than_days=30",
+        "SELECT COUNT(*) FROM friendship",
+        2,
+    )
+    .await;
+    assert_eq!(plan["status"], FRIENDSHIP_STATUS_UNFRIENDED);
+    assert_eq!(plan["friendship_ids"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        count(
+            &app,
+            "SELECT COUNT(*) FROM friendship WHERE status = 'active'"
+        )
+        .await,
+        2,
+        "Alice and Carol stay friends"
+    );
+
+    let (status, _) = admin_post(
+        &app,
+        "/admin/friendships/prune?status=active&older_than_days=30",
+        Some(TEST_ADMIN_TOKEN),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+// ---------------------------------------------------------------------------
+// D3: Idempotency cleanup only removes expired keys
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn d3_idempotency_cleanup_plan_matches_apply() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let _scenario = ScenarioBuilder::new()
+        .users(&["alice_d3", "bob_d3"])
+        .category("alice_d3", "Dining")
+        .friend("alice_d3", "bob_d3")
+        .split("alice_d3", "Dining", 30.0, &[("bob_d3", 10.0)])
+        .split("alice_d3", "Dining", 40.0, &[("bob_d3", 10.0)])
+        .split("alice_d3", "Dining", 50.0, &[("bob_d3", 10.0)])
+        .build(&app)
+        .await;
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "UPDATE idempotency_keys SET expires_at = '2020-01-01T00:00:00Z' WHERE key != 'fixture-split-3'",
+            (),
+        )
+        .await
+        .unwrap();
+    }
+
+    let plan = assert_plan_matches_apply(
+        &app,
+        "/admin/idempotency-keys/cleanup",
+        "SELECT COUNT(*) FROM idempotency_keys",
+        2,
+    )
+    .await;
+    assert_eq!(plan["key_ids"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        count(&app, "SELECT COUNT(*) FROM idempotency_keys").await,
+        1
+    );
+}
+
+// ---------------------------------------------------------------------------
+// D4: Force-unlinking Telegram removes exactly the user's links
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn d4_unlink_telegram_plan_matches_apply() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_d4", "bob_d4"])
+        .build(&app)
+        .await;
+    let alice = scenario.id("alice_d4");
+    {
+        let conn = app.state.main_db.write().await;
+        for (telegram_id, user_id) in [
+            ("101", alice),
+            ("102", alice),
+            ("201", scenario.id("bob_d4")),
+        ] {
+            conn.execute(
+                "INSERT INTO telegram_users (telegram_user_id, user_id, chat_id, created_at) VALUES (?, ?, ?, 0)",
+                (telegram_id, user_id, telegram_id),
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    let plan = assert_plan_matches_apply(
+        &app,
+        &format!("/admin/users/{alice}/unlink-telegram"),
+        "SELECT COUNT(*) FROM telegram_users",
+        2,
+    )
+    .await;
+    assert_eq!(plan["telegram_user_ids"], json!(["101", "102"]));
+    assert_eq!(
+        count(
+            &app,
+            "SELECT COUNT(*) FROM telegram_users WHERE telegram_user_id = '201'"
+        )
+        .await,
+        1
+    );
+
+    let (status, _) = admin_post(
+        &app,
+        "/admin/users/no-such-user/unlink-telegram?dry_run=true",
+        Some(TEST_ADMIN_TOKEN),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+// ---------------------------------------------------------------------------
+// D5: Disabling a user blocks login and ends their sessions; the dry run does not
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn d5_disable_user_plan_matches_apply() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["mallory_d5"])
+        .build(&app)
+        .await;
+    let mallory = scenario.id("mallory_d5");
+    let cookie = scenario.cookie("mallory_d5");
+    let uri = format!("/admin/users/{mallory}/disable");
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "INSERT INTO telegram_users (telegram_user_id, user_id, chat_id, created_at) VALUES ('5', ?, '5', 0)",
+            [mallory],
+        )
+        .await
+        .expect("link telegram");
+    }
+
+    let (status, preview) =
+        admin_post(&app, &format!("{uri}?dry_run=true"), Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(preview["plan"]["username"], "mallory_d5");
+    assert_eq!(preview["plan"]["already_disabled"], false);
+    assert_eq!(preview["plan"]["session_count"], 1);
+    assert_eq!(preview["plan"]["telegram_link_count"], 1);
+    let response = send_request(&app, "GET", "/auth/me", cookie, None).await;
+    assert_eq!(
+        response.status(),
+        StatusCode::OK,
+        "dry run leaves the account usable"
+    );
+
+    let (status, applied) = admin_post(&app, &uri, Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(applied["plan"], preview["plan"]);
+    assert_eq!(applied["rows_affected"], 3, "user, session and link");
+
+    // The cookie from before is logged out and the bot no longer knows the chat
+    let response = send_request(&app, "GET", "/auth/me", cookie, None).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        count(
+            &app,
+            "SELECT COUNT(*) FROM telegram_users WHERE chat_id = '5'"
+        )
+        .await,
+        0
+    );
+
+    let response = send_request(
+        &app,
+        "POST",
+        "/auth/login",
+        "",
+        Some(json!({ "username": "mallory_d5", "password": FIXTURE_PASSWORD })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let (status, again) = admin_post(&app, &uri, Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(again["plan"]["already_disabled"], true);
+    assert_eq!(again["plan"]["session_count"], 0);
+    assert_eq!(again["rows_affected"], 0);
+}
+
+// ---------------------------------------------------------------------------
+// D6: Library callers get the same plan/apply split
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn d6_run_admin_action_dry_run_matches_apply() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_d6", "bob_d6"])
+        .friend("alice_d6", "bob_d6")
+        .build(&app)
+        .await;
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/remove",
+        scenario.cookie("alice_d6"),
+        Some(json!({ "friend_id": scenario.id("bob_d6") })),
+    )
+    .await;
+    assert!(response.status().is_success());
+
+    let db = &app.state.main_db;
+    let later = OffsetDateTime::now_utc() + time::Duration::days(400);
+    let action = PruneFriendships::older_than(FRIENDSHIP_STATUS_UNFRIENDED, 365, later);
+
+    let preview = admin::run_admin_action(db, action.clone(), true)
+        .await
+        .expect("dry run");
+    assert_eq!(preview.plan.friendship_ids.len(), 2);
+    assert_eq!(preview.rows_affected, None);
+
+    let applied = admin::run_admin_action(db, action, false)
+        .await
+        .expect("apply");
+    assert_eq!(applied.plan, preview.plan);
+    assert_eq!(applied.rows_affected, Some(2));
+    assert_eq!(count(&app, "SELECT COUNT(*) FROM friendship").await, 0);
+}
@@ -0,0 +1,356 @@
+/// Tests M1-M4: AI categorization accuracy report
+///
+/// `GET /stats/ai-accuracy?months=N` compares the category the bot picked for
+/// each `bot_ai` record (kept in `record_provenance.ai_category_id`) with the
+/// record's current category, per month of record date, and lists the most
+/// frequent (AI pick, user choice) pairs.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use common::send_json;
+use kash_server::constants::CREATED_VIA_BOT_AI;
+use kash_server::models::{CreateRecordPayload, RecordProvenance};
+use kash_server::{records, stats};
+use serde_json::{Value, json};
+
+// ---- Helpers ----
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob])
+        .category(&alice, "Food")
+        .category(&alice, "Groceries")
+        .category(&alice, "Transport")
+        .category(&alice, "Taxi")
+        .category(&bob, "Food")
+        .build(app)
+        .await
+}
+
+/// `(month before last, last month, this month)` as `YYYY-MM`.
+fn recent_months() -> (String, String, String) {
+    let window = stats::month_window(&stats::current_month(), 3).expect("month window");
+    (window[0].clone(), window[1].clone(), window[2].clone())
+}
+
+async fn create_bot_record(
+    app: &common::TestApp,
+    user_id: &str,
+    category_id: &str,
+    date: &str,
+) -> String {
+    let payload = CreateRecordPayload {
+        name: "Receipt".to_string(),
+        amount: money(12.0),
+        currency: None,
+        category_id: category_id.to_string(),
+        date: date.to_string(),
+        original_amount: None,
+        original_currency: None,
+        note: None,
+        tags: Vec::new(),
+    };
+    let provenance = RecordProvenance {
+        created_via: CREATED_VIA_BOT_AI.to_string(),
+        model: Some("gpt-test".to_string()),
+        prompt_hash: Some("abc123".to_string()),
+        ai_category_confidence: Some(0.8),
+    };
+    records::create_record_for_user(
+        &app.state.main_db,
+        user_id,
+        payload,
+        Some(provenance),
+        false,
+    )
+    .await
+    .expect("create bot record")
+    .id
+}
+
+async fn recategorize(app: &common::TestApp, cookie: &str, record_id: &str, category_id: &str) {
+    let (status, body) = send_json(
+        app,
+        "PUT",
+        &format!("/records/{record_id}"),
+        cookie,
+        Some(json!({ "category_id": category_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+}
+
+fn confusion(ai: &str, user: &str, count: u32) -> Value {
+    json!({ "ai": ai, "user": user, "count": count })
+}
+
+fn confusions(value: &Value) -> Vec<Value> {
+    value
+        .as_array()
+        .expect("confusions array")
+        .iter()
+        .map(|item| {
+            confusion(
+                item["ai_category_name"].as_str().expect("ai name"),
+                item["user_category_name"].as_str().expect("user name"),
+                item["count"].as_u64().expect("count") as u32,
+            )
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// M1: Corrections are counted per month with their confusion pairs
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn m1_accuracy_and_confusion_matrix_per_month() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "m1").await;
+    let alice = scenario.id("alice_m1");
+    let cookie = scenario.cookie("alice_m1");
+    let food = scenario.category_id("alice_m1", "Food");
+    let groceries = scenario.category_id("alice_m1", "Groceries");
+    let transport = scenario.category_id("alice_m1", "Transport");
+    let taxi = scenario.category_id("alice_m1", "Taxi");
+    let (_, last_month, this_month) = recent_months();
+
+    let mut this_month_food = Vec::new();
+    for _ in 0..4 {
+        this_month_food
+            .push(create_bot_record(&app, alice, food, &format!("{this_month}-01")).await);
+    }
+    let this_month_transport =
+        create_bot_record(&app, alice, transport, &format!("{this_month}-01")).await;
+    recategorize(&app, cookie, &this_month_food[0], groceries).await;
+    recategorize(&app, cookie, &this_month_food[1], groceries).await;
+    recategorize(&app, cookie, &this_month_transport, taxi).await;
+
+    let last_month_food = create_bot_record(&app, alice, food, &format!("{last_month}-15")).await;
+    create_bot_record(&app, alice, food, &format!("{last_month}-20")).await;
+    recategorize(&app, cookie, &last_month_food, groceries).await;
+
+    // Neither an HTTP-created record nor another user's bot record counts.
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/records",
+        cookie,
+        Some(json!({ "name": "Bus", "amount": 2.0, "category_id": transport, "date": format!("{this_month}-01") })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    create_bot_record(
+        &app,
+        scenario.id("bob_m1"),
+        scenario.category_id("bob_m1", "Food"),
+        &format!("{this_month}-01"),
+    )
+    .await;
+
+    let (status, report) =
+        send_json(&app, "GET", "/stats/ai-accuracy?months=2", cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+
+    let months = report["months"].as_array().expect("months");
+    assert_eq!(months.len(), 2);
+    assert_eq!(months[0]["month"], json!(last_month));
+    assert_eq!(months[0]["total"], 2);
+    assert_eq!(months[0]["corrected"], 1);
+    assert_eq!(months[0]["accuracy_percent"], 50.0);
+    assert_eq!(
+        confusions(&months[0]["top_confusions"]),
+        vec![confusion("Food", "Groceries", 1)]
+    );
+
+    assert_eq!(months[1]["month"], json!(this_month));
+    assert_eq!(months[1]["total"], 5);
+    assert_eq!(months[1]["corrected"], 3);
+    assert_eq!(months[1]["accuracy_percent"], 40.0);
+    assert_eq!(
+        confusions(&months[1]["top_confusions"]),
+        vec![
+            confusion("Food", "Groceries", 2),
+            confusion("Transport", "Taxi", 1)
+        ]
+    );
+
+    assert_eq!(report["total"], 7);
+    assert_eq!(report["corrected"], 4);
+    assert_eq!(report["accuracy_percent"], 42.9);
+    assert_eq!(
+        confusions(&report["top_confusions"]),
+        vec![
+            confusion("Food", "Groceries", 3),
+            confusion("Transport", "Taxi", 1)
+        ]
+    );
+    assert_eq!(report["top_confusions"][0]["ai_category_id"], json!(food));
+    assert_eq!(
+        report["top_confusions"][0]["user_category_id"],
+        json!(groceries)
+    );
+}
+
+// ---------------------------------------------------------------------------
+// M2: Months default to 3, are bounded, and empty months report no accuracy
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn m2_window_defaults_bounds_and_empty_months() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "m2").await;
+    let alice = scenario.id("alice_m2");
+    let cookie = scenario.cookie("alice_m2");
+    let food = scenario.category_id("alice_m2", "Food");
+    let (two_months_ago, _, this_month) = recent_months();
+    let outside = stats::month_window(&two_months_ago, 2).expect("month window")[0].clone();
+
+    create_bot_record(&app, alice, food, &format!("{this_month}-01")).await;
+    create_bot_record(&app, alice, food, &format!("{outside}-28")).await;
+
+    let (status, report) = send_json(&app, "GET", "/stats/ai-accuracy", cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    let months = report["months"].as_array().expect("months");
+    assert_eq!(months.len(), 3);
+    assert_eq!(months[0]["month"], json!(two_months_ago));
+    assert_eq!(months[0]["total"], 0);
+    assert_eq!(months[0]["accuracy_percent"], Value::Null);
+    assert_eq!(months[2]["total"], 1);
+    assert_eq!(months[2]["accuracy_percent"], 100.0);
+    assert_eq!(report["total"], 1, "records before the window are excluded");
+    assert_eq!(report["top_confusions"], json!([]));
+
+    for months in [0, 25] {
+        let (status, _) = send_json(
+            &app,
+            "GET",
+            &format!("/stats/ai-accuracy?months={months}"),
+            cookie,
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "months={months}");
+    }
+
+    let bob_cookie = scenario.cookie("bob_m2");
+    let (status, report) =
+        send_json(&app, "GET", "/stats/ai-accuracy?months=1", bob_cookie, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["total"], 0);
+    assert_eq!(report["accuracy_percent"], Value::Null);
+}
+
+// ---------------------------------------------------------------------------
+// M3: A record moved back to the AI's pick counts as accurate
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn m3_reverted_and_batch_corrections() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "m3").await;
+    let alice = scenario.id("alice_m3");
+    let cookie = scenario.cookie("alice_m3");
+    let food = scenario.category_id("alice_m3", "Food");
+    let groceries = scenario.category_id("alice_m3", "Groceries");
+    let (_, _, this_month) = recent_months();
+    let date = format!("{this_month}-01");
+
+    let reverted = create_bot_record(&app, alice, food, &date).await;
+    recategorize(&app, cookie, &reverted, groceries).await;
+    recategorize(&app, cookie, &reverted, food).await;
+
+    create_bot_record(&app, alice, groceries, &date).await;
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/records/recategorize-batch",
+        cookie,
+        Some(json!({
+            "name_pattern": "Receipt",
+            "created_via": CREATED_VIA_BOT_AI,
+            "start_date": date,
+            "end_date": date,
+            "target_category_id": food,
+            "dry_run": false
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let (status, report) =
+        send_json(&app, "GET", "/stats/ai-accuracy?months=1", cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert_eq!(report["total"], 2);
+    assert_eq!(report["corrected"], 1, "only the batch-moved record");
+    assert_eq!(
+        confusions(&report["top_confusions"]),
+        vec![confusion("Groceries", "Food", 1)]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// M4: Month window arithmetic and legacy provenance rows
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn m4_month_window_and_legacy_provenance() {
+    assert_eq!(
+        stats::month_window("2026-02", 3).expect("window"),
+        vec!["2025-12", "2026-01", "2026-02"]
+    );
+    assert_eq!(
+        stats::month_window("2026-12", 1).expect("window"),
+        vec!["2026-12"]
+    );
+    for invalid in ["2026", "2026-13", "2026-00", "abcd-01"] {
+        assert!(stats::month_window(invalid, 1).is_err(), "{invalid}");
+    }
+    assert_eq!(stats::validate_accuracy_months(None).expect("default"), 3);
+
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "m4").await;
+    let alice = scenario.id("alice_m4");
+    let food = scenario.category_id("alice_m4", "Food");
+    let (_, _, this_month) = recent_months();
+
+    let record_id = create_bot_record(&app, alice, food, &format!("{this_month}-01")).await;
+    {
+        let conn = app.state.main_db.read().await;
+        let mut rows = conn
+            .query(
+                "SELECT ai_category_id FROM record_provenance WHERE record_id = ?",
+                [record_id.as_str()],
+            )
+            .await
+            .expect("query provenance");
+        let row = rows
+            .next()
+            .await
+            .expect("read row")
+            .expect("provenance row");
+        let ai_category_id: String = row.get(0).expect("ai category id");
+        assert_eq!(ai_category_id, food);
+    }
+
+    let legacy_id = create_bot_record(&app, alice, food, &format!("{this_month}-01")).await;
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "UPDATE record_provenance SET ai_category_id = NULL WHERE record_id = ?",
+            [legacy_id.as_str()],
+        )
+        .await
+        .expect("clear ai category");
+    }
+
+    let conn = app.state.main_db.read().await;
+    let report = stats::ai_accuracy_for_user(&conn, alice, 1, &this_month)
+        .await
+        .expect("report");
+    assert_eq!(report.total, 1, "provenance without an AI pick is skipped");
+    assert_eq!(report.accuracy_percent, Some(100.0));
+}
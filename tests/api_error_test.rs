@@ -0,0 +1,178 @@
+/// Tests Z231-Z233: JSON error bodies
+///
+/// `error::ApiError` renders as `{"code", "message", "details"?}` with the handler's
+/// status. Errors still built as `(StatusCode, String)` convert with the code for their
+/// status, or the tag of a `TAG: ...` message. The records and friends routes answer
+/// with it.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    response::IntoResponse,
+};
+use kash_server::constants::*;
+use kash_server::error::ApiError;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn render(error: ApiError) -> (StatusCode, Value) {
+    let response = error.into_response();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&bytes).expect("json body"))
+}
+
+// ---------------------------------------------------------------------------
+// Z231: The body carries code, message and optional details
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z231_error_renders_as_json() {
+    let (status, body) = render(ApiError::new(
+        StatusCode::NOT_FOUND,
+        ERROR_CODE_RECORD_NOT_FOUND,
+        "Record not found",
+    ))
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        body,
+        json!({ "code": ERROR_CODE_RECORD_NOT_FOUND, "message": "Record not found" })
+    );
+
+    let (status, body) =
+        render(ApiError::bad_request("bad sort").with_details(json!({ "field": "sort_by" }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], ERROR_CODE_BAD_REQUEST);
+    assert_eq!(body["details"], json!({ "field": "sort_by" }));
+}
+
+// ---------------------------------------------------------------------------
+// Z232: Plain (StatusCode, String) errors keep their status and get a code
+// ---------------------------------------------------------------------------
+
+#[test]
+fn z232_tuple_errors_convert() {
+    for (status, message, code) in [
+        (
+            StatusCode::BAD_REQUEST,
+            "Invalid date",
+            ERROR_CODE_BAD_REQUEST,
+        ),
+        (
+            StatusCode::UNAUTHORIZED,
+            ERR_UNAUTHORIZED,
+            ERROR_CODE_UNAUTHORIZED,
+        ),
+        (
+            StatusCode::NOT_FOUND,
+            "Split not found",
+            ERROR_CODE_NOT_FOUND,
+        ),
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error: failed to begin transaction",
+            ERROR_CODE_INTERNAL,
+        ),
+        (
+            StatusCode::CONFLICT,
+            "PERIOD_CLOSED: records dated on or before 2025-03-31 are locked",
+            ERROR_CODE_PERIOD_CLOSED,
+        ),
+        (
+            StatusCode::CONFLICT,
+            SPLIT_RECORD_IMMUTABLE_MESSAGE,
+            ERROR_CODE_SPLIT_RECORD_IMMUTABLE,
+        ),
+        (
+            StatusCode::BAD_REQUEST,
+            "records[2]: Invalid date",
+            ERROR_CODE_BAD_REQUEST,
+        ),
+    ] {
+        let error = ApiError::from((status, message.to_string()));
+        assert_eq!(error.status, status, "{message}");
+        assert_eq!(error.code, code, "{message}");
+        assert_eq!(error.message, message);
+        assert_eq!(
+            <(StatusCode, String)>::from(error),
+            (status, message.to_string())
+        );
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Z233: Records and friends routes answer with JSON errors
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z233_routes_answer_with_json_errors() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    common::create_test_user(&app.state, "alice_z233", "password123")
+        .await
+        .unwrap();
+    let cookie = common::login_user(&app.router, "alice_z233", "password123")
+        .await
+        .unwrap();
+
+    for (method, uri, body, status, code) in [
+        (
+            "GET",
+            "/records/missing",
+            Value::Null,
+            StatusCode::NOT_FOUND,
+            ERROR_CODE_RECORD_NOT_FOUND,
+        ),
+        (
+            "DELETE",
+            "/records/missing",
+            Value::Null,
+            StatusCode::NOT_FOUND,
+            ERROR_CODE_RECORD_NOT_FOUND,
+        ),
+        (
+            "POST",
+            "/friends/request",
+            json!({ "friend_username": "nobody_z233" }),
+            StatusCode::NOT_FOUND,
+            ERROR_CODE_USER_NOT_FOUND,
+        ),
+        (
+            "GET",
+            "/records",
+            Value::Null,
+            StatusCode::UNAUTHORIZED,
+            ERROR_CODE_UNAUTHORIZED,
+        ),
+    ] {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json");
+        if status != StatusCode::UNAUTHORIZED {
+            request = request.header("cookie", &cookie);
+        }
+        let body = match body {
+            Value::Null => Body::empty(),
+            body => Body::from(body.to_string()),
+        };
+        let response = app
+            .router
+            .clone()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), status, "{method} {uri}");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&bytes).expect("json error body");
+        assert_eq!(body["code"], code, "{method} {uri}: {body}");
+        assert!(body["message"].is_string(), "{body}");
+    }
+}
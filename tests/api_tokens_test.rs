@@ -0,0 +1,276 @@
+/// Tests Z381-Z384: Personal access tokens
+///
+/// `POST /auth/tokens` returns a `kash_pat_` token once; requests carrying it as
+/// `Authorization: Bearer` need no cookie. Read-only tokens are refused on writes,
+/// revoked or expired tokens on everything, and tokens are managed from a session only.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder};
+use kash_server::constants::*;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+enum Auth<'a> {
+    Cookie(&'a str),
+    Bearer(&'a str),
+}
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    auth: Auth<'_>,
+    payload: Option<Value>,
+) -> (StatusCode, Value) {
+    let builder = Request::builder().uri(uri).method(method);
+    let builder = match auth {
+        Auth::Cookie(cookie) => builder.header("cookie", cookie),
+        Auth::Bearer(token) => builder.header("authorization", format!("Bearer {token}")),
+    };
+    let request = match payload {
+        Some(payload) => builder
+            .header("content-type", "application/json")
+            .body(Body::from(payload.to_string())),
+        None => builder.body(Body::empty()),
+    }
+    .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn scenario(app: &common::TestApp, user: &str) -> Scenario {
+    ScenarioBuilder::new()
+        .user(user)
+        .category(user, "Dining")
+        .build(app)
+        .await
+}
+
+/// Creates a token from `user`'s session; the whole response.
+async fn create_token(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    payload: Value,
+) -> Value {
+    let (status, body) = send(
+        app,
+        "POST",
+        "/auth/tokens",
+        Auth::Cookie(scenario.cookie(user)),
+        Some(payload),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    body
+}
+
+fn lunch(scenario: &Scenario, user: &str) -> Value {
+    json!({
+        "name": "Lunch",
+        "amount": -12.5,
+        "category_id": scenario.category_id(user, "Dining"),
+        "date": "2026-03-02",
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Z381: A bearer token reads and writes /records without a cookie
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z381_bearer_token_uses_records_without_cookie() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "alice_z381").await;
+    let created = create_token(
+        &app,
+        &scenario,
+        "alice_z381",
+        json!({ "name": "bank import cron" }),
+    )
+    .await;
+    let token = created["token"].as_str().unwrap();
+    assert!(token.starts_with(API_TOKEN_PREFIX));
+    assert_eq!(created["read_only"], false);
+    assert_eq!(created["expires_at"], Value::Null);
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/records",
+        Auth::Bearer(token),
+        Some(lunch(&scenario, "alice_z381")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+
+    let (status, body) = send(&app, "GET", "/records", Auth::Bearer(token), None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let records = body["records"].as_array().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0]["name"], "Lunch");
+
+    // Listed with its use, never with the token itself
+    let (status, body) = send(
+        &app,
+        "GET",
+        "/auth/tokens",
+        Auth::Cookie(scenario.cookie("alice_z381")),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let tokens = body["tokens"].as_array().unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0]["name"], "bank import cron");
+    assert!(tokens[0]["last_used_at"].is_string());
+    assert!(tokens[0].get("token").is_none());
+    assert!(!body.to_string().contains(token));
+}
+
+// ---------------------------------------------------------------------------
+// Z382: Read-only tokens are refused on writes
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z382_read_only_token_cannot_write() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "bob_z382").await;
+    let created = create_token(&app, &scenario, "bob_z382", json!({ "read_only": true })).await;
+    let token = created["token"].as_str().unwrap();
+
+    let (status, _) = send(&app, "GET", "/records", Auth::Bearer(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/records",
+        Auth::Bearer(token),
+        Some(lunch(&scenario, "bob_z382")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], ERROR_CODE_API_TOKEN_READ_ONLY);
+
+    let (_, body) = send(&app, "GET", "/records", Auth::Bearer(token), None).await;
+    assert_eq!(body["records"].as_array().unwrap().len(), 0);
+}
+
+// ---------------------------------------------------------------------------
+// Z383: Revoked, expired and unknown tokens are refused at once
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z383_revoked_and_expired_tokens_are_refused() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "carol_z383").await;
+    let cookie = scenario.cookie("carol_z383");
+    let created = create_token(&app, &scenario, "carol_z383", json!({})).await;
+    let token = created["token"].as_str().unwrap();
+    let (status, _) = send(&app, "GET", "/records", Auth::Bearer(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let uri = format!("/auth/tokens/{}", created["id"].as_str().unwrap());
+    let (status, _) = send(&app, "DELETE", &uri, Auth::Cookie(cookie), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, body) = send(&app, "GET", "/records", Auth::Bearer(token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], ERROR_CODE_INVALID_API_TOKEN);
+    let (status, _) = send(&app, "DELETE", &uri, Auth::Cookie(cookie), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let created = create_token(
+        &app,
+        &scenario,
+        "carol_z383",
+        json!({ "expires_in_days": 30 }),
+    )
+    .await;
+    assert!(created["expires_at"].is_string());
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "UPDATE api_tokens SET expires_at = '2020-01-01T00:00:00.000000000Z' WHERE id = ?",
+            [created["id"].as_str().unwrap()],
+        )
+        .await
+        .unwrap();
+    }
+    let (status, _) = send(
+        &app,
+        "GET",
+        "/records",
+        Auth::Bearer(created["token"].as_str().unwrap()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let unknown = format!("{API_TOKEN_PREFIX}{}", "0".repeat(64));
+    let (status, _) = send(&app, "GET", "/records", Auth::Bearer(&unknown), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/auth/tokens",
+        Auth::Cookie(cookie),
+        Some(json!({ "expires_in_days": 0 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+// ---------------------------------------------------------------------------
+// Z384: Tokens are managed from a session, and only the owner's
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z384_tokens_are_managed_from_the_owners_session() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["dave_z384", "erin_z384"])
+        .build(&app)
+        .await;
+    let created = create_token(&app, &scenario, "dave_z384", json!({})).await;
+    let token = created["token"].as_str().unwrap();
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/auth/tokens",
+        Auth::Bearer(token),
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(&app, "GET", "/auth/tokens", Auth::Bearer(token), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let uri = format!("/auth/tokens/{}", created["id"].as_str().unwrap());
+    let (status, _) = send(
+        &app,
+        "DELETE",
+        &uri,
+        Auth::Cookie(scenario.cookie("erin_z384")),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, body) = send(&app, "GET", "/auth/me", Auth::Bearer(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["username"], "dave_z384");
+}
@@ -0,0 +1,215 @@
+/// Tests Z251-Z253: Login and registration rate limits
+///
+/// `rate_limit::RateLimiter` gives each client and username `max_attempts` failed logins
+/// per `window_secs`. Once they are spent `/auth/login` answers 429 with `Retry-After`,
+/// even for the right password, until attempts refill; a successful login restores the
+/// full budget. Registrations count per client. Test routers have no connect info, so
+/// every request comes from the one `unknown` client.
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+    routing::post,
+};
+use kash_server::config::LoginRateLimit;
+use kash_server::constants::*;
+use kash_server::rate_limit::{RateLimiter, login_key};
+use kash_server::{AppState, auth};
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+use tower_sessions::{MemoryStore, SessionManagerLayer};
+
+// ---- Helpers ----
+
+/// Status, `Retry-After` and JSON body of a POST.
+async fn post_json(router: &Router, uri: &str, body: Value) -> (StatusCode, Option<String>, Value) {
+    let response = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .map(|value| value.to_str().unwrap().to_string());
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        retry_after,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn login(router: &Router, username: &str, password: &str) -> (StatusCode, Option<String>) {
+    let (status, retry_after, _) = post_json(
+        router,
+        "/auth/login",
+        json!({ "username": username, "password": password }),
+    )
+    .await;
+    (status, retry_after)
+}
+
+/// The auth routes over `state`, for limits other than the default.
+fn auth_router(state: AppState) -> Router {
+    Router::new()
+        .route("/auth/login", post(auth::login))
+        .route("/auth/register", post(auth::register))
+        .layer(SessionManagerLayer::new(MemoryStore::default()).with_secure(false))
+        .with_state(state)
+}
+
+// ---------------------------------------------------------------------------
+// Z251: The 6th bad attempt is refused with 429, even with the right password
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z251_sixth_failed_login_is_rate_limited() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    common::create_test_user(&app.state, "alice_z251", "password123")
+        .await
+        .unwrap();
+    common::create_test_user(&app.state, "bob_z251", "password123")
+        .await
+        .unwrap();
+
+    for _ in 0..DEFAULT_LOGIN_RATE_LIMIT_MAX_ATTEMPTS {
+        let (status, _) = login(&app.router, "alice_z251", "wrong-password").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    let (status, retry_after, body) = post_json(
+        &app.router,
+        "/auth/login",
+        json!({ "username": "alice_z251", "password": "wrong-password" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["code"], ERROR_CODE_RATE_LIMITED);
+    let retry_after: u64 = retry_after.expect("Retry-After").parse().unwrap();
+    let refill =
+        DEFAULT_LOGIN_RATE_LIMIT_WINDOW_SECS / DEFAULT_LOGIN_RATE_LIMIT_MAX_ATTEMPTS as u64;
+    assert!((1..=refill).contains(&retry_after), "{retry_after}");
+
+    // The right password waits too, and the username matches ignoring case
+    let (status, _) = login(&app.router, "alice_z251", "password123").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    let (status, _) = login(&app.router, "ALICE_Z251", "password123").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // Other usernames keep their own budget
+    let (status, _) = login(&app.router, "bob_z251", "password123").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+// ---------------------------------------------------------------------------
+// Z252: After the window a correct login succeeds and resets the count
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z252_login_succeeds_after_the_window() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    common::create_test_user(&app.state, "alice_z252", "password123")
+        .await
+        .unwrap();
+    let limit = LoginRateLimit {
+        max_attempts: 2,
+        window_secs: 1,
+    };
+    let router = auth_router(AppState {
+        auth_rate_limiter: Arc::new(RateLimiter::new(limit)),
+        ..app.state.clone()
+    });
+
+    for expected in [
+        StatusCode::UNAUTHORIZED,
+        StatusCode::UNAUTHORIZED,
+        StatusCode::TOO_MANY_REQUESTS,
+    ] {
+        let (status, _) = login(&router, "alice_z252", "wrong-password").await;
+        assert_eq!(status, expected);
+    }
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let (status, _) = login(&router, "alice_z252", "password123").await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The success gave back the whole budget, not just what had refilled
+    for expected in [
+        StatusCode::UNAUTHORIZED,
+        StatusCode::UNAUTHORIZED,
+        StatusCode::TOO_MANY_REQUESTS,
+    ] {
+        let (status, _) = login(&router, "alice_z252", "wrong-password").await;
+        assert_eq!(status, expected);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Z253: Registrations count per client; buckets refill evenly over the window
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z253_registrations_and_refill() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let router = auth_router(AppState {
+        auth_rate_limiter: Arc::new(RateLimiter::new(LoginRateLimit {
+            max_attempts: 2,
+            window_secs: 3600,
+        })),
+        ..app.state.clone()
+    });
+
+    for (username, expected) in [
+        ("carol_z253", StatusCode::CREATED),
+        ("carol_z253", StatusCode::CONFLICT),
+        ("dave_z253", StatusCode::TOO_MANY_REQUESTS),
+    ] {
+        let (status, _, _) = post_json(
+            &router,
+            "/auth/register",
+            json!({ "username": username, "password": "correct horse battery" }),
+        )
+        .await;
+        assert_eq!(status, expected, "{username}");
+    }
+
+    let limiter = RateLimiter::new(LoginRateLimit {
+        max_attempts: 4,
+        window_secs: 60,
+    });
+    let key = login_key("192.0.2.1", "alice");
+    let start = Instant::now();
+    for _ in 0..4 {
+        limiter.consume(&key, start).unwrap();
+    }
+    assert_eq!(limiter.check(&key, start), Err(Duration::from_secs(15)));
+    assert!(
+        limiter
+            .check(&key, start + Duration::from_secs(14))
+            .is_err()
+    );
+    assert_eq!(limiter.check(&key, start + Duration::from_secs(15)), Ok(()));
+    assert!(
+        limiter
+            .check(&login_key("192.0.2.2", "alice"), start)
+            .is_ok()
+    );
+    limiter.reset(&key);
+    assert_eq!(limiter.check(&key, start), Ok(()));
+}
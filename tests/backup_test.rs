@@ -0,0 +1,186 @@
+/// Tests Z321-Z323: Database snapshots
+///
+/// `POST /admin/backup` and the scheduled backup job copy `users.db` with `VACUUM INTO`
+/// into a folder named for the UTC time, under `BACKUP_PATH`, and delete snapshot
+/// folders older than the retention window. The database stays live meanwhile.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::TEST_ADMIN_TOKEN;
+use common::fixtures::ScenarioBuilder;
+use kash_server::AppState;
+use kash_server::backup::{self, BackupDatabaseJob, BackupError};
+use kash_server::config::BackupConfig;
+use kash_server::jobs::JobHandler;
+use kash_server::models::BackupSnapshot;
+use libsql::Builder;
+use serde_json::Value;
+use std::path::Path;
+use time::{Duration, OffsetDateTime};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn post_backup(app: &common::TestApp, token: Option<&str>) -> (StatusCode, Value) {
+    let mut request = Request::builder().uri("/admin/backup").method("POST");
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = app
+        .router
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes)
+            .unwrap_or(Value::String(String::from_utf8_lossy(&bytes).to_string())),
+    )
+}
+
+async fn snapshot_usernames(snapshot: &BackupSnapshot) -> Vec<String> {
+    let db = Builder::new_local(Path::new(&snapshot.path).join("users.db"))
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    let mut rows = conn
+        .query("SELECT name FROM users ORDER BY name", ())
+        .await
+        .unwrap();
+    let mut names = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        names.push(row.get::<String>(0).unwrap());
+    }
+    names
+}
+
+fn backup_config(app: &common::TestApp) -> BackupConfig {
+    app.state
+        .backup
+        .as_deref()
+        .expect("backups enabled")
+        .clone()
+}
+
+// ---------------------------------------------------------------------------
+// Z321: An admin snapshot is a readable copy of the live database
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z321_admin_backup_writes_snapshot() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    ScenarioBuilder::new()
+        .users(&["alice_z321", "bob_z321"])
+        .build(&app)
+        .await;
+
+    let (status, _) = post_backup(&app, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body) = post_backup(&app, Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let snapshot: BackupSnapshot = serde_json::from_value(body).unwrap();
+    assert!(snapshot.path.starts_with(&backup_config(&app).path));
+    assert_eq!(snapshot.files.len(), 1);
+    assert_eq!(snapshot.files[0].name, "users.db");
+    let on_disk = std::fs::metadata(Path::new(&snapshot.path).join("users.db"))
+        .unwrap()
+        .len();
+    assert_eq!(snapshot.files[0].size_bytes, on_disk);
+    assert_eq!(snapshot.total_bytes, on_disk);
+    assert!(snapshot.pruned.is_empty());
+
+    assert_eq!(
+        snapshot_usernames(&snapshot).await,
+        vec!["alice_z321".to_string(), "bob_z321".to_string()]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z322: Snapshots past the retention window are pruned; other folders stay
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z322_old_snapshots_are_pruned() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let config = backup_config(&app);
+    let now = OffsetDateTime::now_utc();
+
+    let retention = Duration::days(i64::from(config.retention_days));
+    let expired = backup::snapshot_name(now - retention - Duration::days(1));
+    let kept = backup::snapshot_name(now - Duration::days(1));
+    for name in [expired.as_str(), kept.as_str(), "manual-copy"] {
+        std::fs::create_dir_all(Path::new(&config.path).join(name)).unwrap();
+    }
+    assert!(backup::snapshot_taken_at("manual-copy").is_none());
+
+    let snapshot = backup::create_snapshot(&app.state.main_db, &config, now)
+        .await
+        .expect("snapshot");
+    assert_eq!(snapshot.pruned, vec![expired.clone()]);
+    assert!(!Path::new(&config.path).join(&expired).exists());
+    assert!(Path::new(&config.path).join(&kept).exists());
+    assert!(Path::new(&config.path).join("manual-copy").exists());
+}
+
+// ---------------------------------------------------------------------------
+// Z323: One snapshot per second, the job writes one too, remote databases refuse
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z323_repeat_job_and_disabled_backups() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let config = backup_config(&app);
+    let now = OffsetDateTime::now_utc() - Duration::hours(1);
+
+    backup::create_snapshot(&app.state.main_db, &config, now)
+        .await
+        .expect("first snapshot");
+    let Err(BackupError::Exists(path)) =
+        backup::create_snapshot(&app.state.main_db, &config, now).await
+    else {
+        panic!("a second snapshot in the same second must not overwrite the first");
+    };
+    assert!(path.join("users.db").exists(), "the first one is kept");
+
+    let job = BackupDatabaseJob {
+        config: BackupConfig {
+            interval_hours: Some(6),
+            ..config.clone()
+        },
+    };
+    assert_eq!(job.interval(), Some(Duration::hours(6)));
+    let later = now + Duration::minutes(5);
+    job.run(&app.state.main_db, &Value::Null, later)
+        .await
+        .expect("job run");
+    assert!(
+        Path::new(&config.path)
+            .join(backup::snapshot_name(later))
+            .join("users.db")
+            .exists()
+    );
+
+    let remote = AppState {
+        backup: None,
+        ..app.state.clone()
+    };
+    let mut headers = axum::http::HeaderMap::new();
+    headers.insert(
+        "authorization",
+        format!("Bearer {TEST_ADMIN_TOKEN}").parse().unwrap(),
+    );
+    let (status, _) = kash_server::admin::create_backup(axum::extract::State(remote), headers)
+        .await
+        .expect_err("no local database to copy");
+    assert_eq!(status, StatusCode::CONFLICT);
+}
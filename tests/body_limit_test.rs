@@ -0,0 +1,213 @@
+/// Tests Z351-Z353: Request size limits
+///
+/// Bodies over `REQUEST_BODY_MAX_BYTES` (64 KB; `IMPORT_BODY_MAX_BYTES` on the import
+/// routes) and query strings over `MAX_QUERY_STRING_LENGTH` are refused with a JSON
+/// error before any handler runs. A split names at most `MAX_SPLIT_PARTICIPANTS` others.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{ScenarioBuilder, money};
+use kash_server::constants::*;
+use kash_server::models::{SplitMode, SplitParticipant};
+use kash_server::utils::validate_split_participants;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    uri: &str,
+    cookie: &str,
+    body: Body,
+    content_length: Option<usize>,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header("cookie", cookie)
+        .header("content-type", "application/json");
+    if let Some(length) = content_length {
+        request = request.header("content-length", length);
+    }
+    let response = app
+        .router
+        .clone()
+        .oneshot(request.body(body).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8(bytes.to_vec()).unwrap();
+    (
+        status,
+        serde_json::from_str(&body).unwrap_or(Value::String(body)),
+    )
+}
+
+/// A record payload whose name alone is `name_len` bytes.
+fn record_payload(name_len: usize) -> String {
+    json!({
+        "name": "x".repeat(name_len),
+        "amount": 1.0,
+        "category_id": "missing",
+        "date": "2025-03-01",
+    })
+    .to_string()
+}
+
+// ---------------------------------------------------------------------------
+// Z351: An oversized body is a JSON 413, declared or streamed
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z351_oversized_body_is_json_413() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new().user("alice_z351").build(&app).await;
+    let cookie = scenario.cookie("alice_z351");
+    let payload = record_payload(DEFAULT_REQUEST_BODY_MAX_BYTES);
+
+    let (status, body) = send(
+        &app,
+        "/records",
+        cookie,
+        Body::from(payload.clone()),
+        Some(payload.len()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE, "{body}");
+    assert_eq!(body["code"], ERROR_CODE_PAYLOAD_TOO_LARGE);
+
+    // No Content-Length: reading stops at the cap
+    let chunks = payload
+        .into_bytes()
+        .chunks(8 * 1024)
+        .map(|chunk| Ok::<_, std::io::Error>(chunk.to_vec()))
+        .collect::<Vec<_>>();
+    let (status, body) = send(
+        &app,
+        "/records",
+        cookie,
+        Body::from_stream(tokio_stream::iter(chunks)),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE, "{body}");
+    assert_eq!(body["code"], ERROR_CODE_PAYLOAD_TOO_LARGE);
+
+    // Under the cap the handler runs and judges the payload itself
+    let (status, body) = send(
+        &app,
+        "/records",
+        cookie,
+        Body::from(record_payload(300)),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+}
+
+// ---------------------------------------------------------------------------
+// Z352: Imports get the larger cap; long query strings are a JSON 414
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z352_import_cap_and_query_length() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .user("alice_z352")
+        .category("alice_z352", "Dining")
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z352");
+
+    // About 100 KB of CSV: over the JSON cap, well under the import cap
+    let mut csv = String::from("name,amount,category,date\n");
+    for i in 0..2000 {
+        csv.push_str(&format!(
+            "Lunch number {i} at the noodle place,12.5,Dining,2025-03-02\n"
+        ));
+    }
+    assert!(csv.len() > DEFAULT_REQUEST_BODY_MAX_BYTES);
+    let (status, body) = send(&app, "/records/import", cookie, Body::from(csv), None).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    assert_eq!(body["created"], 2000);
+
+    let uri = format!("/records?q={}", "a".repeat(MAX_QUERY_STRING_LENGTH));
+    let (status, body) = common::auth_request(&app.router, "GET", &uri, cookie)
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::URI_TOO_LONG, "{body}");
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["code"], ERROR_CODE_URI_TOO_LONG);
+}
+
+// ---------------------------------------------------------------------------
+// Z353: A split one participant over the cap is refused
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z353_split_participant_cap() {
+    let participants = |count: usize| {
+        (0..count)
+            .map(|i| SplitParticipant {
+                user_id: format!("friend-{i}"),
+                amount: money(1.0),
+                percentage: None,
+            })
+            .collect::<Vec<_>>()
+    };
+    assert!(
+        validate_split_participants(
+            &participants(MAX_SPLIT_PARTICIPANTS),
+            "me",
+            SplitMode::Exact
+        )
+        .is_ok()
+    );
+    assert!(
+        validate_split_participants(
+            &participants(MAX_SPLIT_PARTICIPANTS + 1),
+            "me",
+            SplitMode::Exact
+        )
+        .is_err()
+    );
+
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .user("alice_z353")
+        .category("alice_z353", "Dining")
+        .build(&app)
+        .await;
+    let payload = json!({
+        "idempotency_key": "z353-over-cap",
+        "total_amount": 100.0,
+        "description": "Team dinner",
+        "date": "2025-03-01",
+        "category_id": scenario.category_id("alice_z353", "Dining"),
+        "splits": participants(MAX_SPLIT_PARTICIPANTS + 1)
+            .iter()
+            .map(|p| json!({"user_id": p.user_id, "amount": p.amount}))
+            .collect::<Vec<_>>(),
+    });
+    let (status, body) = send(
+        &app,
+        "/splits/create",
+        scenario.cookie("alice_z353"),
+        Body::from(payload.to_string()),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert!(
+        body.to_string()
+            .contains(&format!("at most {MAX_SPLIT_PARTICIPANTS} participants")),
+        "{body}"
+    );
+}
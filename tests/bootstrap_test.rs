@@ -0,0 +1,273 @@
+/// Tests Q1-Q4: Cold-start bootstrap
+///
+/// `GET /bootstrap` returns the user, categories, settings, the first records
+/// page, inbox counts and capabilities in one response. Each section equals the
+/// body of its standalone endpoint and carries that endpoint's `ETag` as its
+/// version, so the client can follow up with `If-None-Match`. Assembling it stays
+/// within `BOOTSTRAP_QUERY_BUDGET` queries, counted with a SQLite authorizer.
+mod common;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::bootstrap::bootstrap_for_user;
+use kash_server::constants::*;
+use kash_server::models::{BootstrapResponse, CreateRecordPayload, PublicUser};
+use kash_server::records;
+use libsql::{AuthAction, AuthContext, Authorization};
+use serde_json::Value;
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+/// `(status, ETag without quotes, JSON body)` of a GET.
+async fn get(
+    app: &common::TestApp,
+    uri: &str,
+    cookie: &str,
+    if_none_match: Option<&str>,
+) -> (StatusCode, Option<String>, Value) {
+    let mut request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .header("cookie", cookie);
+    if let Some(version) = if_none_match {
+        request = request.header(header::IF_NONE_MATCH, format!("\"{version}\""));
+    }
+    let response = app
+        .router
+        .clone()
+        .oneshot(request.body(Body::empty()).expect("build request"))
+        .await
+        .expect("execute request");
+    let status = response.status();
+    let etag = response
+        .headers()
+        .get(header::ETAG)
+        .map(|value| value.to_str().expect("etag").trim_matches('"').to_string());
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("read body");
+    let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, etag, body)
+}
+
+async fn bootstrap(app: &common::TestApp, cookie: &str) -> (BootstrapResponse, Value) {
+    let (status, _, body) = get(app, "/bootstrap", cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let response = serde_json::from_value(body.clone()).expect("bootstrap response");
+    (response, body)
+}
+
+async fn create_record(app: &common::TestApp, scenario: &Scenario, user: &str, name: &str) {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id(user),
+        CreateRecordPayload {
+            name: name.to_string(),
+            amount: money(12.0),
+            currency: None,
+            category_id: scenario.category_id(user, "Dining").to_string(),
+            date: "2025-03-10".to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record");
+}
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    let carol = format!("carol_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob, &carol])
+        .category(&alice, "Dining")
+        .category(&alice, "Travel")
+        .category(&bob, "Dining")
+        .friend(&alice, &bob)
+        .friend_request(&carol, &bob)
+        .split(&alice, "Dining", 60.0, &[(&bob, 30.0)])
+        .build(app)
+        .await
+}
+
+/// SELECT statements (subqueries included) prepared on the shared connection while
+/// `bootstrap_for_user` runs.
+async fn count_bootstrap_queries(app: &common::TestApp, user: PublicUser) -> u32 {
+    let conn = app.state.main_db.write().await;
+    let selects = Arc::new(AtomicU32::new(0));
+    let counter = selects.clone();
+    conn.authorizer(Some(Arc::new(move |ctx: &AuthContext| {
+        if matches!(ctx.action, AuthAction::Select) {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+        Authorization::Allow
+    })))
+    .expect("install authorizer");
+    let result = bootstrap_for_user(&conn, user).await;
+    conn.authorizer(None).expect("remove authorizer");
+    result.expect("bootstrap");
+    selects.load(Ordering::SeqCst)
+}
+
+// ---------------------------------------------------------------------------
+// Q1: Every section matches its standalone endpoint, version included
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn q1_sections_match_standalone_endpoints() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "q1").await;
+
+    for user in ["alice_q1", "bob_q1"] {
+        let cookie = scenario.cookie(user);
+        let (_, body) = bootstrap(&app, cookie).await;
+
+        for (section, uri) in [
+            ("user", "/auth/me".to_string()),
+            ("categories", format!("/categories?limit={MAX_LIMIT}")),
+            ("settings", "/settings".to_string()),
+            (
+                "records",
+                format!("/records?limit={BOOTSTRAP_RECORDS_LIMIT}"),
+            ),
+        ] {
+            let (status, etag, standalone) = get(&app, &uri, cookie, None).await;
+            assert_eq!(status, StatusCode::OK, "{uri}");
+            assert_eq!(body[section]["data"], standalone, "{user} {section}");
+            assert_eq!(
+                body[section]["version"].as_str(),
+                etag.as_deref(),
+                "{user} {section} version is the ETag"
+            );
+        }
+
+        let (_, _, friends) = get(
+            &app,
+            "/friends/list?pending=true&direction=incoming",
+            cookie,
+            None,
+        )
+        .await;
+        let (_, _, splits) = get(&app, "/splits/pending", cookie, None).await;
+        assert_eq!(
+            body["inbox"]["data"]["friend_requests"],
+            friends["total_count"]
+        );
+        assert_eq!(
+            body["inbox"]["data"]["pending_splits"],
+            splits["total_count"]
+        );
+    }
+
+    let (bob, _) = bootstrap(&app, scenario.cookie("bob_q1")).await;
+    assert_eq!(bob.user.data.id, scenario.id("bob_q1"));
+    assert_eq!(bob.inbox.data.friend_requests, 1);
+    assert_eq!(bob.inbox.data.pending_splits, 1);
+    assert_eq!(bob.records.data.records.len(), 1);
+    assert!(bob.capabilities.data.record_encryption);
+    assert_eq!(bob.capabilities.data.max_page_size, MAX_LIMIT);
+    assert!(
+        bob.capabilities
+            .data
+            .currency_codes
+            .contains(&"TWD".to_string())
+    );
+
+    let (alice, _) = bootstrap(&app, scenario.cookie("alice_q1")).await;
+    let names: Vec<&str> = alice
+        .categories
+        .data
+        .categories
+        .iter()
+        .map(|category| category.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["Dining", "Travel"]);
+    assert_eq!(alice.categories.data.total_count, 2);
+    assert_eq!(alice.inbox.data.friend_requests, 0);
+}
+
+// ---------------------------------------------------------------------------
+// Q2: The query budget holds, and does not grow with the account
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn q2_query_budget_is_constant() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "q2").await;
+    let alice = PublicUser {
+        id: scenario.id("alice_q2").to_string(),
+        username: "alice_q2".to_string(),
+    };
+
+    // Alice paid a split on her first page, so split progress is looked up too.
+    let queries = count_bootstrap_queries(&app, alice.clone()).await;
+    assert!(queries > 0, "the authorizer saw the queries");
+    assert!(
+        queries <= BOOTSTRAP_QUERY_BUDGET,
+        "{queries} queries, budget {BOOTSTRAP_QUERY_BUDGET}"
+    );
+
+    for i in 0..30 {
+        create_record(&app, &scenario, "alice_q2", &format!("Lunch {i}")).await;
+    }
+    assert_eq!(count_bootstrap_queries(&app, alice).await, queries);
+}
+
+// ---------------------------------------------------------------------------
+// Q3: A section version works as If-None-Match on its endpoint
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn q3_versions_drive_conditional_requests() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "q3").await;
+    let cookie = scenario.cookie("alice_q3");
+    let (boot, _) = bootstrap(&app, cookie).await;
+
+    let categories_uri = format!("/categories?limit={MAX_LIMIT}");
+    let (status, etag, _) = get(
+        &app,
+        &categories_uri,
+        cookie,
+        Some(&boot.categories.version),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert_eq!(etag.as_deref(), Some(boot.categories.version.as_str()));
+    let (status, _, _) = get(&app, "/settings", cookie, Some(&boot.settings.version)).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+
+    create_record(&app, &scenario, "alice_q3", "Dinner").await;
+    let records_uri = format!("/records?limit={BOOTSTRAP_RECORDS_LIMIT}");
+    let (status, etag, body) = get(&app, &records_uri, cookie, Some(&boot.records.version)).await;
+    assert_eq!(status, StatusCode::OK, "records changed since bootstrap");
+    assert_ne!(etag.as_deref(), Some(boot.records.version.as_str()));
+    assert_eq!(body["total_count"], 2);
+
+    // A version from a different page of the same endpoint does not match.
+    let (status, _, _) = get(&app, "/records?limit=1", cookie, etag.as_deref()).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+// ---------------------------------------------------------------------------
+// Q4: Bootstrap requires a session
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn q4_bootstrap_requires_login() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (status, _, _) = get(&app, "/bootstrap", "", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
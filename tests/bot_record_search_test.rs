@@ -0,0 +1,307 @@
+/// Tests T1-T3: Bot record search cap and totals
+///
+/// The Telegram bot's `list_records` tool reads through
+/// `records::search_records_for_user`, which caps every page at
+/// `BOT_LIST_RECORDS_MAX` and reports `truncated` when more records match.
+/// Its `sum_records` tool reads `records::sum_records_for_user`, which returns
+/// only counts and signed totals for the same filters.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::constants::{BOT_LIST_RECORDS_MAX, CREATED_VIA_API, DEFAULT_CURRENCY_CODE};
+use kash_server::record_repo::{self, NewRecord, RecordSearch};
+use kash_server::records;
+
+// ---- Helpers ----
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob])
+        .category(&alice, "Food")
+        .category(&alice, "Transport")
+        .income_category(&alice, "Salary")
+        .category(&bob, "Food")
+        .build(app)
+        .await
+}
+
+async fn insert_records(
+    app: &common::TestApp,
+    user_id: &str,
+    category_id: &str,
+    records: &[(&str, f64, &str)],
+) {
+    let conn = app.state.main_db.write().await;
+    for (name, amount, date) in records {
+        let id = uuid::Uuid::new_v4().to_string();
+        record_repo::insert_record(
+            &conn,
+            &NewRecord {
+                id: &id,
+                owner_user_id: user_id,
+                name,
+                amount: money(*amount),
+                currency: DEFAULT_CURRENCY_CODE,
+                category_id,
+                date,
+                created_via: CREATED_VIA_API,
+                original_amount: None,
+                original_currency: None,
+                note: None,
+                tags: &[],
+            },
+        )
+        .await
+        .expect("insert record");
+    }
+}
+
+fn everything<'a>() -> RecordSearch<'a> {
+    RecordSearch {
+        start_date: "0000-01-01",
+        end_date: "9999-12-31",
+        category_id: None,
+        name_contains: None,
+        min_amount: None,
+        max_amount: None,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// T1: Pages are capped at BOT_LIST_RECORDS_MAX and flag truncation
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn t1_list_is_capped_and_flags_truncation() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "t1").await;
+    let alice = scenario.id("alice_t1");
+    let food = scenario.category_id("alice_t1", "Food");
+
+    let seeded: Vec<(String, String)> = (0..60)
+        .map(|i| (format!("Lunch {i}"), format!("2026-01-{:02}", i % 28 + 1)))
+        .collect();
+    let seeded: Vec<(&str, f64, &str)> = seeded
+        .iter()
+        .map(|(name, date)| (name.as_str(), -10.0, date.as_str()))
+        .collect();
+    insert_records(&app, alice, food, &seeded).await;
+
+    let db = &app.state.main_db;
+    for requested in [None, Some(BOT_LIST_RECORDS_MAX), Some(500), Some(5000)] {
+        let page = records::search_records_for_user(db, alice, &everything(), requested, None)
+            .await
+            .expect("search");
+        assert_eq!(page.total_count, 60, "limit={requested:?}");
+        assert_eq!(
+            page.records.len() as u32,
+            BOT_LIST_RECORDS_MAX,
+            "limit={requested:?}"
+        );
+        assert_eq!(page.limit, BOT_LIST_RECORDS_MAX);
+        assert!(page.truncated, "limit={requested:?}");
+    }
+
+    let page = records::search_records_for_user(db, alice, &everything(), Some(10), Some(10))
+        .await
+        .expect("search");
+    assert_eq!(page.records.len(), 10);
+    assert!(page.truncated, "records after offset 20 remain");
+
+    let page = records::search_records_for_user(db, alice, &everything(), None, Some(50))
+        .await
+        .expect("search");
+    assert_eq!(page.records.len(), 10);
+    assert!(!page.truncated, "last page");
+
+    let page = records::search_records_for_user(db, alice, &everything(), Some(5), Some(55))
+        .await
+        .expect("search");
+    assert_eq!(page.records.len(), 5);
+    assert!(!page.truncated);
+
+    let (status, _) = records::search_records_for_user(db, alice, &everything(), Some(0), None)
+        .await
+        .expect_err("zero limit");
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+// ---------------------------------------------------------------------------
+// T2: Filters narrow both the count and the page, and stay owner-scoped
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn t2_filters_apply_to_count_and_page() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "t2").await;
+    let alice = scenario.id("alice_t2");
+    let bob = scenario.id("bob_t2");
+    let food = scenario.category_id("alice_t2", "Food");
+    let transport = scenario.category_id("alice_t2", "Transport");
+
+    insert_records(
+        &app,
+        alice,
+        food,
+        &[
+            ("Lunch", -12.0, "2026-02-01"),
+            ("Team LUNCH", -40.0, "2026-02-15"),
+            ("Dinner", -25.0, "2026-03-01"),
+        ],
+    )
+    .await;
+    insert_records(&app, alice, transport, &[("Taxi", -18.0, "2026-02-10")]).await;
+    insert_records(
+        &app,
+        bob,
+        scenario.category_id("bob_t2", "Food"),
+        &[("Lunch", -99.0, "2026-02-01")],
+    )
+    .await;
+
+    let db = &app.state.main_db;
+    let search = RecordSearch {
+        start_date: "2026-02-01",
+        end_date: "2026-02-28",
+        category_id: Some(food),
+        name_contains: Some("lunch"),
+        min_amount: None,
+        max_amount: None,
+    };
+    let page = records::search_records_for_user(db, alice, &search, None, None)
+        .await
+        .expect("search");
+    assert_eq!(page.total_count, 2);
+    assert!(!page.truncated);
+    let names: Vec<&str> = page.records.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(names, vec!["Team LUNCH", "Lunch"]);
+
+    let search = RecordSearch {
+        min_amount: Some(money(-30.0)),
+        max_amount: Some(money(-15.0)),
+        ..everything()
+    };
+    let page = records::search_records_for_user(db, alice, &search, None, None)
+        .await
+        .expect("search");
+    let names: Vec<&str> = page.records.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(names, vec!["Dinner", "Taxi"]);
+
+    let search = RecordSearch {
+        min_amount: Some(money(10.0)),
+        max_amount: Some(money(5.0)),
+        ..everything()
+    };
+    let (status, _) = records::search_records_for_user(db, alice, &search, None, None)
+        .await
+        .expect_err("min above max");
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = records::sum_records_for_user(db, alice, &search)
+        .await
+        .expect_err("min above max");
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let search = RecordSearch {
+        start_date: "2026-02-30",
+        ..everything()
+    };
+    assert!(
+        records::search_records_for_user(db, alice, &search, None, None)
+            .await
+            .is_err()
+    );
+}
+
+// ---------------------------------------------------------------------------
+// T3: sum_records totals match the seeded amounts
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn t3_sum_totals_match_seeded_data() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "t3").await;
+    let alice = scenario.id("alice_t3");
+    let food = scenario.category_id("alice_t3", "Food");
+    let transport = scenario.category_id("alice_t3", "Transport");
+    let salary = scenario.category_id("alice_t3", "Salary");
+
+    insert_records(
+        &app,
+        alice,
+        food,
+        &[
+            ("Lunch", -12.5, "2026-02-01"),
+            ("Groceries", -80.25, "2026-02-14"),
+            ("Dinner", -30.0, "2026-03-02"),
+        ],
+    )
+    .await;
+    insert_records(&app, alice, transport, &[("Bus", -2.25, "2026-02-20")]).await;
+    insert_records(
+        &app,
+        alice,
+        salary,
+        &[
+            ("Salary", 3000.0, "2026-02-28"),
+            ("Bonus", 500.0, "2026-03-05"),
+        ],
+    )
+    .await;
+    insert_records(
+        &app,
+        scenario.id("bob_t3"),
+        scenario.category_id("bob_t3", "Food"),
+        &[("Lunch", -1000.0, "2026-02-01")],
+    )
+    .await;
+
+    let db = &app.state.main_db;
+    let totals = records::sum_records_for_user(db, alice, &everything())
+        .await
+        .expect("sum");
+    assert_eq!(totals.record_count, 6);
+    assert_eq!(totals.income_count, 2);
+    assert_eq!(totals.income_total, money(3500.0));
+    assert_eq!(totals.expense_count, 4);
+    assert_eq!(totals.expense_total, money(-125.0));
+    assert_eq!(totals.net(), money(3375.0));
+
+    let february = RecordSearch {
+        start_date: "2026-02-01",
+        end_date: "2026-02-28",
+        ..everything()
+    };
+    let totals = records::sum_records_for_user(db, alice, &february)
+        .await
+        .expect("sum");
+    assert_eq!(totals.record_count, 4);
+    assert_eq!(totals.income_total, money(3000.0));
+    assert_eq!(totals.expense_total, money(-95.0));
+
+    let february_food = RecordSearch {
+        category_id: Some(food),
+        ..february
+    };
+    let totals = records::sum_records_for_user(db, alice, &february_food)
+        .await
+        .expect("sum");
+    assert_eq!(totals.record_count, 2);
+    assert_eq!(totals.income_count, 0);
+    assert_eq!(totals.expense_total, money(-92.75));
+
+    let nothing = RecordSearch {
+        start_date: "2020-01-01",
+        end_date: "2020-12-31",
+        ..everything()
+    };
+    let totals = records::sum_records_for_user(db, alice, &nothing)
+        .await
+        .expect("sum");
+    assert_eq!(totals.record_count, 0);
+    assert_eq!(totals.income_total, money(0.0));
+    assert_eq!(totals.expense_total, money(0.0));
+    assert_eq!(totals.net(), money(0.0));
+}
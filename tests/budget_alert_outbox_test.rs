@@ -0,0 +1,275 @@
+/// Tests O1-O4: Budget alert outbox
+///
+/// Budget alerts are queued in `telegram_outbox` instead of being sent inline.
+/// Non-urgent alerts wait for the user's daily batch window (`alert_batch_time`
+/// in their `utc_offset_minutes`) and are combined into one message; alerts for
+/// a category at or over its limit are due immediately. The clock is injected,
+/// so every test drives `now` explicitly.
+mod common;
+
+use axum::{http::StatusCode, response::Response};
+use common::fixtures::ScenarioBuilder;
+use common::send_request;
+use kash_server::models::BudgetAlert;
+use kash_server::outbox::{
+    due_budget_alert_messages, enqueue_budget_alert, mark_delivered, next_batch_window,
+    parse_batch_time,
+};
+use serde_json::{Value, json};
+use time::format_description::well_known::Rfc3339;
+use time::{OffsetDateTime, Time};
+
+// ---- Helpers ----
+
+async fn body_json(response: Response) -> Value {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).unwrap_or(Value::Null)
+}
+
+fn at(timestamp: &str) -> OffsetDateTime {
+    OffsetDateTime::parse(timestamp, &Rfc3339).unwrap()
+}
+
+fn alert(category_name: &str, spent: f64, limit: f64) -> BudgetAlert {
+    BudgetAlert {
+        category_name: category_name.to_string(),
+        spent,
+        limit,
+    }
+}
+
+async fn link_telegram(app: &common::TestApp, user_id: &str, chat_id: &str) {
+    let conn = app.state.main_db.write().await;
+    conn.execute(
+        "INSERT INTO telegram_users (telegram_user_id, user_id, chat_id, created_at) VALUES (?, ?, ?, 0)",
+        (chat_id, user_id, chat_id),
+    )
+    .await
+    .unwrap();
+}
+
+async fn due_count(app: &common::TestApp, now: OffsetDateTime) -> usize {
+    let conn = app.state.main_db.read().await;
+    due_budget_alert_messages(&conn, now)
+        .await
+        .expect("due messages")
+        .len()
+}
+
+// ---------------------------------------------------------------------------
+// O1: The batch window is computed in the user's local time
+// ---------------------------------------------------------------------------
+
+#[test]
+fn o1_next_batch_window_uses_local_time() {
+    let nine_pm = Time::from_hms(21, 0, 0).unwrap();
+
+    // 10:00 UTC is 18:00 in UTC+8, so the window is 21:00 local the same day.
+    assert_eq!(
+        next_batch_window(at("2025-03-10T10:00:00Z"), 480, nine_pm),
+        at("2025-03-10T13:00:00Z")
+    );
+    // Exactly at the window, the next one is a day later.
+    assert_eq!(
+        next_batch_window(at("2025-03-10T13:00:00Z"), 480, nine_pm),
+        at("2025-03-11T13:00:00Z")
+    );
+    // 23:30 UTC is already the next local day in UTC+8.
+    assert_eq!(
+        next_batch_window(at("2025-03-10T23:30:00Z"), 480, nine_pm),
+        at("2025-03-11T13:00:00Z")
+    );
+    // 01:00 UTC is still the previous local evening in UTC-5.
+    assert_eq!(
+        next_batch_window(at("2025-03-11T01:00:00Z"), -300, nine_pm),
+        at("2025-03-11T02:00:00Z")
+    );
+    assert_eq!(
+        next_batch_window(at("2025-03-11T02:30:00Z"), -300, nine_pm),
+        at("2025-03-12T02:00:00Z")
+    );
+
+    assert_eq!(
+        parse_batch_time("07:05"),
+        Some(Time::from_hms(7, 5, 0).unwrap())
+    );
+    for invalid in ["7:05", "24:00", "12:60", "noon", "12:00:00", ""] {
+        assert_eq!(parse_batch_time(invalid), None, "{invalid:?}");
+    }
+}
+
+// ---------------------------------------------------------------------------
+// O2: Same-day alerts are combined into one message at the window
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn o2_same_day_alerts_are_batched() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_o2"])
+        .build(&app)
+        .await;
+    let alice = scenario.id("alice_o2");
+    link_telegram(&app, alice, "9002").await;
+
+    // Default settings: 21:00 in UTC+8, i.e. 13:00 UTC.
+    {
+        let conn = app.state.main_db.write().await;
+        for (hour, category) in [(2, "Dining"), (5, "Groceries"), (9, "Transport")] {
+            let now = at("2025-03-10T00:00:00Z") + time::Duration::hours(hour);
+            let deliver_after =
+                enqueue_budget_alert(&conn, alice, &alert(category, 80.0, 100.0), now)
+                    .await
+                    .expect("enqueue");
+            assert_eq!(deliver_after, "2025-03-10T13:00:00Z");
+        }
+    }
+
+    assert_eq!(due_count(&app, at("2025-03-10T12:59:59Z")).await, 0);
+
+    let window = at("2025-03-10T13:00:00Z");
+    let messages = {
+        let conn = app.state.main_db.read().await;
+        due_budget_alert_messages(&conn, window).await.expect("due")
+    };
+    assert_eq!(messages.len(), 1, "one combined message per user");
+    let message = &messages[0];
+    assert_eq!(message.user_id, alice);
+    assert_eq!(message.chat_ids, vec!["9002".to_string()]);
+    assert_eq!(message.entry_ids.len(), 3);
+    for category in ["Dining", "Groceries", "Transport"] {
+        assert!(message.text.contains(category), "{}", message.text);
+    }
+
+    {
+        let conn = app.state.main_db.write().await;
+        let delivered = mark_delivered(&conn, &message.entry_ids, window)
+            .await
+            .expect("mark delivered");
+        assert_eq!(delivered, 3);
+    }
+    assert_eq!(due_count(&app, at("2025-03-11T13:00:00Z")).await, 0);
+}
+
+// ---------------------------------------------------------------------------
+// O3: Alerts at or over the limit bypass the batch
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn o3_urgent_alert_is_due_immediately() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new().users(&["bob_o3"]).build(&app).await;
+    let bob = scenario.id("bob_o3");
+    let now = at("2025-03-10T02:00:00Z");
+
+    {
+        let conn = app.state.main_db.write().await;
+        let deliver_after = enqueue_budget_alert(&conn, bob, &alert("Dining", 120.0, 100.0), now)
+            .await
+            .expect("enqueue urgent");
+        assert_eq!(deliver_after, "2025-03-10T02:00:00Z");
+        enqueue_budget_alert(&conn, bob, &alert("Groceries", 85.0, 100.0), now)
+            .await
+            .expect("enqueue batched");
+    }
+
+    let messages = {
+        let conn = app.state.main_db.read().await;
+        due_budget_alert_messages(&conn, now).await.expect("due")
+    };
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].entry_ids.len(), 1);
+    assert!(messages[0].text.contains("Dining"));
+    assert!(messages[0].text.contains("over budget"));
+    assert!(!messages[0].text.contains("Groceries"));
+    assert!(
+        messages[0].chat_ids.is_empty(),
+        "unlinked users still get an entry so it can be dropped"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// O4: The offset and batch time settings move the window
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn o4_settings_control_the_batch_window() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["carol_o4"])
+        .build(&app)
+        .await;
+    let carol = scenario.id("carol_o4");
+    let cookie = scenario.cookie("carol_o4");
+
+    for payload in [
+        json!({ "utc_offset_minutes": 900 }),
+        json!({ "utc_offset_minutes": -721 }),
+        json!({ "alert_batch_time": "9pm" }),
+        json!({ "alert_batch_time": "25:00" }),
+        json!({}),
+    ] {
+        let response = send_request(&app, "PUT", "/settings", cookie, Some(payload.clone())).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{payload}");
+    }
+
+    let response = send_request(
+        &app,
+        "PUT",
+        "/settings",
+        cookie,
+        Some(json!({ "utc_offset_minutes": -300, "alert_batch_time": "08:30" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let settings = body_json(response).await;
+    assert_eq!(settings["utc_offset_minutes"], -300);
+    assert_eq!(settings["alert_batch_time"], "08:30");
+    assert_eq!(settings["closed_through"], Value::Null);
+
+    // Updating one field leaves the others alone.
+    let response = send_request(
+        &app,
+        "PUT",
+        "/settings",
+        cookie,
+        Some(json!({ "closed_through": "2025-01-31" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = send_request(&app, "GET", "/settings", cookie, None).await;
+    let settings = body_json(response).await;
+    assert_eq!(settings["utc_offset_minutes"], -300);
+    assert_eq!(settings["alert_batch_time"], "08:30");
+    assert_eq!(settings["closed_through"], "2025-01-31");
+
+    // 12:00 UTC is 07:00 in UTC-5, so the window is 08:30 local = 13:30 UTC.
+    {
+        let conn = app.state.main_db.write().await;
+        let deliver_after = enqueue_budget_alert(
+            &conn,
+            carol,
+            &alert("Dining", 50.0, 100.0),
+            at("2025-03-10T12:00:00Z"),
+        )
+        .await
+        .expect("enqueue");
+        assert_eq!(deliver_after, "2025-03-10T13:30:00Z");
+    }
+
+    let response = send_request(
+        &app,
+        "PUT",
+        "/settings",
+        cookie,
+        Some(json!({ "utc_offset_minutes": null, "alert_batch_time": null })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let settings = body_json(response).await;
+    assert_eq!(settings["utc_offset_minutes"], Value::Null);
+    assert_eq!(settings["alert_batch_time"], Value::Null);
+    assert_eq!(settings["closed_through"], "2025-01-31");
+}
@@ -0,0 +1,273 @@
+/// Tests Z111-Z113: Archiving categories
+///
+/// `PUT /categories/{id} {"archived": true}` hides a category from
+/// `GET /categories` (unless `include_archived=true`) and from the bootstrap
+/// list. New records, imports and splits against it are 400 "Category is
+/// archived"; its existing records still list and count in summaries.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::constants::{CATEGORY_ARCHIVED_MESSAGE, DEFAULT_CSV_IMPORT_MAX_ROWS};
+use kash_server::models::{
+    Category, CreateRecordPayload, CreateSplitPayload, GetCategoriesResponse,
+    RecordSummaryResponse, SplitMode, SplitParticipant,
+};
+use kash_server::{records, splits};
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> (StatusCode, Value) {
+    let body = payload.map_or_else(Body::empty, |payload| Body::from(payload.to_string()));
+    let request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    (status, body)
+}
+
+async fn set_archived(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    category: &str,
+    archived: bool,
+) -> Category {
+    let (status, body) = send(
+        app,
+        "PUT",
+        &format!("/categories/{}", scenario.category_id(user, category)),
+        scenario.cookie(user),
+        Some(json!({ "archived": archived })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    serde_json::from_value(body).expect("category")
+}
+
+async fn category_names(app: &common::TestApp, cookie: &str, query: &str) -> (Vec<String>, u32) {
+    let (status, body) = send(app, "GET", &format!("/categories{query}"), cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let response: GetCategoriesResponse = serde_json::from_value(body).expect("categories");
+    let names = response
+        .categories
+        .into_iter()
+        .map(|category| category.name)
+        .collect();
+    (names, response.total_count)
+}
+
+async fn create(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    category: &str,
+) -> Result<String, (StatusCode, String)> {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id(user),
+        CreateRecordPayload {
+            name: format!("{category} lunch"),
+            amount: money(12.5),
+            currency: None,
+            category_id: scenario.category_id(user, category).to_string(),
+            date: "2025-03-10".to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .map(|record| record.id)
+}
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob])
+        .category(&alice, "Dining")
+        .category(&alice, "Groceries")
+        .category(&bob, "Dining")
+        .friend(&alice, &bob)
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z111: Archived categories leave the list unless include_archived=true
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z111_archived_categories_are_hidden() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z111").await;
+    let cookie = scenario.cookie("alice_z111");
+
+    let archived = set_archived(&app, &scenario, "alice_z111", "Dining", true).await;
+    assert!(archived.archived);
+    assert_eq!(archived.name, "Dining");
+
+    assert_eq!(
+        category_names(&app, cookie, "").await,
+        (vec!["Groceries".to_string()], 1)
+    );
+    assert_eq!(
+        category_names(&app, cookie, "?search=din").await,
+        (vec![], 0)
+    );
+    assert_eq!(
+        category_names(&app, cookie, "?include_archived=true").await,
+        (vec!["Dining".to_string(), "Groceries".to_string()], 2)
+    );
+    let (status, body) = send(&app, "GET", "/bootstrap", cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["categories"]["data"]["total_count"], 1, "{body}");
+
+    let restored = set_archived(&app, &scenario, "alice_z111", "Dining", false).await;
+    assert!(!restored.archived);
+    assert_eq!(category_names(&app, cookie, "").await.1, 2);
+}
+
+// ---------------------------------------------------------------------------
+// Z112: No new records in an archived category; old ones still count
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z112_archived_category_keeps_history() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z112").await;
+    let cookie = scenario.cookie("alice_z112");
+    let old = create(&app, &scenario, "alice_z112", "Dining")
+        .await
+        .expect("create before archiving");
+
+    set_archived(&app, &scenario, "alice_z112", "Dining", true).await;
+    let error = create(&app, &scenario, "alice_z112", "Dining")
+        .await
+        .expect_err("archived");
+    assert_eq!(
+        error,
+        (
+            StatusCode::BAD_REQUEST,
+            CATEGORY_ARCHIVED_MESSAGE.to_string()
+        )
+    );
+
+    let (status, body) = send(&app, "GET", "/records", cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let ids: Vec<&str> = body["records"]
+        .as_array()
+        .expect("records")
+        .iter()
+        .filter_map(|record| record["id"].as_str())
+        .collect();
+    assert_eq!(ids, vec![old.as_str()]);
+
+    let (status, body) = send(
+        &app,
+        "GET",
+        "/records/summary?start_date=2025-03-01&end_date=2025-03-31",
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let summary: RecordSummaryResponse = serde_json::from_value(body).expect("summary");
+    assert_eq!(summary.categories.len(), 1);
+    assert_eq!(
+        summary.categories[0].category_name.as_deref(),
+        Some("Dining")
+    );
+    assert_eq!(summary.expense, money(-12.5));
+}
+
+// ---------------------------------------------------------------------------
+// Z113: Imports and splits are refused too; other users are unaffected
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z113_archived_category_refuses_imports_and_splits() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z113").await;
+    let alice = scenario.id("alice_z113");
+    set_archived(&app, &scenario, "alice_z113", "Dining", true).await;
+
+    let import = records::import_records_for_user(
+        &app.state.main_db,
+        alice,
+        "name,amount,category,date\nRamen,12.5,Groceries,2025-03-10\nSushi,20,dining,2025-03-11\n",
+        DEFAULT_CSV_IMPORT_MAX_ROWS,
+        true,
+    )
+    .await
+    .expect("import");
+    assert_eq!(import.created, 0, "the whole file is rolled back");
+    assert!(import.created_categories.is_empty(), "not recreated");
+    let lines: Vec<(usize, &str)> = import
+        .errors
+        .iter()
+        .map(|error| (error.line, error.message.as_str()))
+        .collect();
+    assert_eq!(lines, vec![(3, "Category is archived: dining")]);
+
+    let error = splits::create_split_for_user(
+        &app.state,
+        alice,
+        CreateSplitPayload {
+            idempotency_key: "key-z113".to_string(),
+            total_amount: money(40.0),
+            description: "Dinner".to_string(),
+            date: "2025-03-10".to_string(),
+            category_id: scenario.category_id("alice_z113", "Dining").to_string(),
+            currency: None,
+            split_mode: SplitMode::Exact,
+            splits: vec![SplitParticipant {
+                user_id: scenario.id("bob_z113").to_string(),
+                amount: money(20.0),
+                percentage: None,
+            }],
+            legacy_full_total: false,
+        },
+    )
+    .await
+    .expect_err("archived split category");
+    assert_eq!(
+        error,
+        (
+            StatusCode::BAD_REQUEST,
+            CATEGORY_ARCHIVED_MESSAGE.to_string()
+        )
+    );
+
+    create(&app, &scenario, "alice_z113", "Groceries")
+        .await
+        .expect("other categories still work");
+    create(&app, &scenario, "bob_z113", "Dining")
+        .await
+        .expect("bob's Dining is his own");
+}
@@ -0,0 +1,362 @@
+/// Tests Z71-Z73: Monthly category budgets
+///
+/// Expense categories take an optional `monthly_budget` on create and update
+/// (`null` clears it). `GET /categories/budget-status?month=YYYY-MM` reports, for
+/// each budgeted category, the budget, the non-pending expenses dated in that
+/// month and what remains.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{FIXTURE_SPLIT_DATE, Scenario, ScenarioBuilder, money};
+use kash_server::models::{BudgetStatusResponse, Category, CreateRecordPayload};
+use kash_server::money::Money;
+use kash_server::records;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> (StatusCode, Value) {
+    let body = payload.map_or_else(Body::empty, |payload| Body::from(payload.to_string()));
+    let request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    (status, body)
+}
+
+async fn budget_status(app: &common::TestApp, cookie: &str, month: &str) -> BudgetStatusResponse {
+    let (status, body) = send(
+        app,
+        "GET",
+        &format!("/categories/budget-status?month={month}"),
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    serde_json::from_value(body).expect("budget status")
+}
+
+async fn set_budget(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    name: &str,
+    budget: Value,
+) {
+    let (status, body) = send(
+        app,
+        "PUT",
+        &format!("/categories/{}", scenario.category_id(user, name)),
+        scenario.cookie(user),
+        Some(json!({ "monthly_budget": budget })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+}
+
+async fn create(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    category: &str,
+    amount: f64,
+    date: &str,
+) -> String {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id(user),
+        CreateRecordPayload {
+            name: format!("{category} on {date}"),
+            amount: money(amount),
+            currency: None,
+            category_id: scenario.category_id(user, category).to_string(),
+            date: date.to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record")
+    .id
+}
+
+// ---------------------------------------------------------------------------
+// Z71: Budgets are set on create, changed and cleared on update
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z71_set_and_clear_budget() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z71"])
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z71");
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/categories",
+        cookie,
+        Some(json!({ "name": "Dining", "is_income": false, "monthly_budget": 6000.0 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let created: Category = serde_json::from_value(body).expect("category");
+    assert_eq!(created.monthly_budget, Some(money(6000.0)));
+    let uri = format!("/categories/{}", created.id);
+
+    let (status, body) = send(
+        &app,
+        "PUT",
+        &uri,
+        cookie,
+        Some(json!({ "name": "Eating out" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["monthly_budget"], 6000.0, "untouched by a rename");
+
+    let (_, body) = send(
+        &app,
+        "PUT",
+        &uri,
+        cookie,
+        Some(json!({ "monthly_budget": 4500.5 })),
+    )
+    .await;
+    assert_eq!(body["monthly_budget"], 4500.5);
+    let (_, listed) = send(&app, "GET", "/categories", cookie, None).await;
+    assert_eq!(listed["categories"][0]["monthly_budget"], 4500.5);
+
+    let (status, body) = send(
+        &app,
+        "PUT",
+        &uri,
+        cookie,
+        Some(json!({ "monthly_budget": null })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["monthly_budget"].is_null());
+    let (_, listed) = send(&app, "GET", "/categories", cookie, None).await;
+    assert!(listed["categories"][0]["monthly_budget"].is_null());
+}
+
+// ---------------------------------------------------------------------------
+// Z72: Spending counts live, non-pending expenses dated in the month
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z72_budget_status_sums_month_expenses() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z72", "bob_z72"])
+        .category("alice_z72", "Dining")
+        .category("bob_z72", "Dining")
+        .category("bob_z72", "Groceries")
+        .category("bob_z72", "Travel")
+        .friend("alice_z72", "bob_z72")
+        .split("alice_z72", "Dining", 60.0, &[("bob_z72", 30.0)])
+        .build(&app)
+        .await;
+    let month = &FIXTURE_SPLIT_DATE[..7];
+    let in_month = |day: &str| format!("{month}-{day}");
+    set_budget(&app, &scenario, "bob_z72", "Dining", json!(100.0)).await;
+    set_budget(&app, &scenario, "bob_z72", "Groceries", json!(50.0)).await;
+    set_budget(&app, &scenario, "bob_z72", "Travel", json!(500.3)).await;
+    create(&app, &scenario, "bob_z72", "Dining", 40.0, &in_month("01")).await;
+    create(&app, &scenario, "bob_z72", "Dining", 25.5, &in_month("28")).await;
+    create(&app, &scenario, "bob_z72", "Dining", 99.0, "2026-03-01").await;
+    create(
+        &app,
+        &scenario,
+        "bob_z72",
+        "Groceries",
+        70.0,
+        &in_month("10"),
+    )
+    .await;
+    create(&app, &scenario, "bob_z72", "Travel", 500.0, &in_month("10")).await;
+    let trashed = create(
+        &app,
+        &scenario,
+        "bob_z72",
+        "Groceries",
+        10.0,
+        &in_month("11"),
+    )
+    .await;
+    let (status, _) = send(
+        &app,
+        "DELETE",
+        &format!("/records/{trashed}"),
+        scenario.cookie("bob_z72"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // Bob's pending share of alice's split is in his Dining category but not yet spent
+    let status = budget_status(&app, scenario.cookie("bob_z72"), month).await;
+    assert_eq!(status.month, month);
+    let rows: Vec<(&str, Money, Money, Money)> = status
+        .categories
+        .iter()
+        .map(|row| {
+            (
+                row.category_name.as_str(),
+                row.monthly_budget,
+                row.spent,
+                row.remaining,
+            )
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            ("Dining", money(100.0), money(65.5), money(34.5)),
+            ("Groceries", money(50.0), money(70.0), money(-20.0)),
+            ("Travel", money(500.3), money(500.0), money(0.3)),
+        ]
+    );
+    let (_, body) = send(
+        &app,
+        "GET",
+        &format!("/categories/budget-status?month={month}"),
+        scenario.cookie("bob_z72"),
+        None,
+    )
+    .await;
+    assert_eq!(
+        body["categories"][2]["remaining"],
+        json!(0.3),
+        "exact cents"
+    );
+
+    let empty = budget_status(&app, scenario.cookie("bob_z72"), "2025-01").await;
+    assert_eq!(empty.categories[0].spent, Money::ZERO);
+    assert_eq!(empty.categories[0].remaining, money(100.0));
+    assert!(
+        budget_status(&app, scenario.cookie("alice_z72"), month)
+            .await
+            .categories
+            .is_empty()
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z73: Invalid budgets, income budgets and bad months are 400
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z73_budget_validation() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z73"])
+        .category("alice_z73", "Dining")
+        .income_category("alice_z73", "Salary")
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z73");
+    let dining = format!(
+        "/categories/{}",
+        scenario.category_id("alice_z73", "Dining")
+    );
+
+    for budget in [
+        json!(0.0),
+        json!(-10.0),
+        json!(-0.01),
+        json!(10.001),
+        json!("lots"),
+    ] {
+        let (status, _) = send(
+            &app,
+            "PUT",
+            &dining,
+            cookie,
+            Some(json!({ "monthly_budget": budget })),
+        )
+        .await;
+        assert!(status.is_client_error(), "{budget}: {status}");
+    }
+    let (status, _) = send(
+        &app,
+        "PUT",
+        &format!(
+            "/categories/{}",
+            scenario.category_id("alice_z73", "Salary")
+        ),
+        cookie,
+        Some(json!({ "monthly_budget": 100.0 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "income budget on update");
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/categories",
+        cookie,
+        Some(json!({ "name": "Bonus", "is_income": true, "monthly_budget": 100.0 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "income budget on create");
+
+    // Switching a budgeted category to income drops its budget
+    set_budget(&app, &scenario, "alice_z73", "Dining", json!(200.0)).await;
+    let (status, body) = send(
+        &app,
+        "POST",
+        &format!("{dining}/convert"),
+        cookie,
+        Some(json!({ "is_income": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(
+        budget_status(&app, cookie, "2026-02")
+            .await
+            .categories
+            .is_empty()
+    );
+
+    for month in ["2026-13", "2026-02-01", "Feb"] {
+        let (status, _) = send(
+            &app,
+            "GET",
+            &format!("/categories/budget-status?month={month}"),
+            cookie,
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{month}");
+    }
+    let (status, _) = send(&app, "GET", "/categories/budget-status", "", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
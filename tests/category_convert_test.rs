@@ -0,0 +1,287 @@
+/// Tests V1-V6: Switching a category between income and expense
+///
+/// `POST /categories/{id}/convert` re-signs every record of the category (and its
+/// subcategories) whose sign disagrees with the new type. The bot reaches the same
+/// logic through `build_pending_category_edit`, which dry-runs the conversion so
+/// the confirmation names how many records flip, and `execute_category_edit`.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use common::send_json;
+use kash_server::categories;
+use kash_server::constants::CATEGORY_EDIT_ONE_CHANGE_MESSAGE;
+use kash_server::models::{CategoryEditAction, CreateRecordPayload};
+use kash_server::money::Money;
+use kash_server::records;
+use serde_json::json;
+
+// ---- Helpers ----
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    ScenarioBuilder::new()
+        .user(&alice)
+        .category(&alice, "Freelance")
+        .category(&alice, "Groceries")
+        .build(app)
+        .await
+}
+
+async fn create_record(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    category: &str,
+    amount: f64,
+) {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id(user),
+        CreateRecordPayload {
+            name: "Invoice".to_string(),
+            amount: money(amount),
+            currency: None,
+            category_id: scenario.category_id(user, category).to_string(),
+            date: "2025-03-10".to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record");
+}
+
+async fn category_amounts(app: &common::TestApp, user_id: &str, category_id: &str) -> Vec<f64> {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT amount FROM records WHERE owner_user_id = ? AND category_id = ? ORDER BY amount",
+            (user_id, category_id),
+        )
+        .await
+        .expect("query amounts");
+    let mut amounts = Vec::new();
+    while let Some(row) = rows.next().await.expect("read row") {
+        amounts.push(Money::from_cents(row.get(0).expect("amount")).to_decimal());
+    }
+    amounts
+}
+
+// ---------------------------------------------------------------------------
+// V1: A dry run counts the records to flip and changes nothing
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn v1_dry_run_counts_without_changing() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "v1").await;
+    let freelance = scenario.category_id("alice_v1", "Freelance").to_string();
+    create_record(&app, &scenario, "alice_v1", "Freelance", 100.0).await;
+    create_record(&app, &scenario, "alice_v1", "Freelance", 250.0).await;
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        &format!("/categories/{freelance}/convert"),
+        scenario.cookie("alice_v1"),
+        Some(json!({ "is_income": true, "dry_run": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["dry_run"], true);
+    assert_eq!(body["plan"]["records_to_flip"], 2);
+    assert_eq!(body["records_flipped"], 0);
+
+    let amounts = category_amounts(&app, scenario.id("alice_v1"), &freelance).await;
+    assert_eq!(amounts, vec![-250.0, -100.0]);
+}
+
+// ---------------------------------------------------------------------------
+// V2: Converting re-signs the records and switches the category type
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn v2_convert_flips_record_signs() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "v2").await;
+    let freelance = scenario.category_id("alice_v2", "Freelance").to_string();
+    create_record(&app, &scenario, "alice_v2", "Freelance", 100.0).await;
+    create_record(&app, &scenario, "alice_v2", "Groceries", 30.0).await;
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        &format!("/categories/{freelance}/convert"),
+        scenario.cookie("alice_v2"),
+        Some(json!({ "is_income": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["records_flipped"], 1);
+
+    let amounts = category_amounts(&app, scenario.id("alice_v2"), &freelance).await;
+    assert_eq!(amounts, vec![100.0]);
+    let groceries = scenario.category_id("alice_v2", "Groceries");
+    let amounts = category_amounts(&app, scenario.id("alice_v2"), groceries).await;
+    assert_eq!(amounts, vec![-30.0], "other categories are untouched");
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &format!("/categories/{freelance}/convert"),
+        scenario.cookie("alice_v2"),
+        Some(json!({ "is_income": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "already income");
+}
+
+// ---------------------------------------------------------------------------
+// V3: The bot's pending edit warns how many records change sign
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn v3_pending_conversion_summary_counts_records() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "v3").await;
+    let alice = scenario.id("alice_v3");
+    let freelance = scenario.category_id("alice_v3", "Freelance").to_string();
+    for amount in [100.0, 200.0, 300.0] {
+        create_record(&app, &scenario, "alice_v3", "Freelance", amount).await;
+    }
+
+    let pending = categories::build_pending_category_edit(
+        &app.state.main_db,
+        alice,
+        &freelance,
+        None,
+        Some(true),
+    )
+    .await
+    .expect("pending edit");
+    assert_eq!(
+        pending.summary,
+        "Switch \"Freelance\" from expense to income. This will flip the sign of 3 records."
+    );
+    assert_eq!(
+        pending.action,
+        CategoryEditAction::Convert {
+            category_id: freelance.clone(),
+            is_income: true,
+        }
+    );
+
+    let amounts = category_amounts(&app, alice, &freelance).await;
+    assert!(
+        amounts.iter().all(|amount| *amount < 0.0),
+        "nothing applied yet"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// V4: A rename combined with a type switch is refused
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn v4_rename_with_type_switch_is_rejected() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "v4").await;
+    let freelance = scenario.category_id("alice_v4", "Freelance");
+
+    let (status, message) = categories::build_pending_category_edit(
+        &app.state.main_db,
+        scenario.id("alice_v4"),
+        freelance,
+        Some("Side gigs"),
+        Some(true),
+    )
+    .await
+    .expect_err("combined change must be refused");
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(message, CATEGORY_EDIT_ONE_CHANGE_MESSAGE);
+
+    let pending = categories::build_pending_category_edit(
+        &app.state.main_db,
+        scenario.id("alice_v4"),
+        freelance,
+        Some("Side gigs"),
+        Some(false),
+    )
+    .await
+    .expect("type unchanged, so only the rename remains");
+    assert_eq!(
+        pending.summary,
+        "Rename category \"Freelance\" to \"Side gigs\"."
+    );
+}
+
+// ---------------------------------------------------------------------------
+// V5: Executing a confirmed conversion applies it and reports the count
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn v5_confirmed_conversion_is_executed() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "v5").await;
+    let alice = scenario.id("alice_v5");
+    let freelance = scenario.category_id("alice_v5", "Freelance").to_string();
+    create_record(&app, &scenario, "alice_v5", "Freelance", 100.0).await;
+    create_record(&app, &scenario, "alice_v5", "Freelance", 40.0).await;
+
+    let pending = categories::build_pending_category_edit(
+        &app.state.main_db,
+        alice,
+        &freelance,
+        None,
+        Some(true),
+    )
+    .await
+    .expect("pending edit");
+    let outcome = categories::execute_category_edit(&app.state.main_db, alice, &pending.action)
+        .await
+        .expect("execute edit");
+    assert_eq!(
+        outcome,
+        "\"Freelance\" is now an income category; 2 records changed sign."
+    );
+
+    let amounts = category_amounts(&app, alice, &freelance).await;
+    assert_eq!(amounts, vec![40.0, 100.0]);
+
+    let (status, _) = categories::execute_category_edit(&app.state.main_db, alice, &pending.action)
+        .await
+        .expect_err("replaying the confirmation is refused");
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+// ---------------------------------------------------------------------------
+// V6: Split records cannot change sign, so the conversion is refused
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn v6_split_records_block_conversion() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_v6", "bob_v6"])
+        .category("alice_v6", "Dining")
+        .friend("alice_v6", "bob_v6")
+        .split("alice_v6", "Dining", 60.0, &[("bob_v6", 30.0)])
+        .build(&app)
+        .await;
+    let dining = scenario.category_id("alice_v6", "Dining").to_string();
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &format!("/categories/{dining}/convert"),
+        scenario.cookie("alice_v6"),
+        Some(json!({ "is_income": true, "dry_run": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
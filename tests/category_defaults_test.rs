@@ -0,0 +1,166 @@
+/// Tests Z101-Z103: Default categories for new accounts
+///
+/// `POST /auth/register` creates the user and the configured default
+/// categories in one transaction, so a first record has somewhere to go.
+/// Empty lists seed nothing, and users created any other way (fixtures, the
+/// operator CLI) start without categories as before.
+mod common;
+
+use std::sync::Arc;
+
+use axum::{Json, extract::State, http::StatusCode};
+use kash_server::auth;
+use kash_server::config::DefaultCategories;
+use kash_server::constants::{DEFAULT_EXPENSE_CATEGORIES, DEFAULT_INCOME_CATEGORIES};
+use kash_server::models::{GetCategoriesResponse, RegisterPayload};
+use kash_server::rate_limit::ClientIp;
+use kash_server::{AppState, categories};
+
+// ---- Helpers ----
+
+async fn register(state: &AppState, username: &str) -> StatusCode {
+    let payload = RegisterPayload {
+        username: username.to_string(),
+        password: "correct horse battery".to_string(),
+    };
+    match auth::register(State(state.clone()), ClientIp(None), Json(payload)).await {
+        Ok((status, _)) => status,
+        Err(error) => error.status,
+    }
+}
+
+/// The user's categories as `(name, is_income)`, sorted by name.
+async fn categories_of(app: &common::TestApp, username: &str) -> Vec<(String, bool)> {
+    let cookie = common::login_user(&app.router, username, "correct horse battery")
+        .await
+        .expect("login");
+    let (status, body) = common::auth_request(&app.router, "GET", "/categories", &cookie)
+        .await
+        .expect("list categories");
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let response: GetCategoriesResponse = serde_json::from_str(&body).expect("categories");
+    let mut categories: Vec<(String, bool)> = response
+        .categories
+        .into_iter()
+        .map(|category| (category.name, category.is_income))
+        .collect();
+    categories.sort();
+    categories
+}
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+// ---------------------------------------------------------------------------
+// Z101: Registration seeds the default expense and income categories
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z101_register_seeds_default_categories() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    common::create_test_user(&app.state, "existing_z101", "correct horse battery")
+        .await
+        .expect("create user");
+
+    assert_eq!(
+        register(&app.state, "alice_z101").await,
+        StatusCode::CREATED
+    );
+
+    let mut expected: Vec<(String, bool)> = DEFAULT_EXPENSE_CATEGORIES
+        .iter()
+        .map(|name| (name.to_string(), false))
+        .chain(
+            DEFAULT_INCOME_CATEGORIES
+                .iter()
+                .map(|name| (name.to_string(), true)),
+        )
+        .collect();
+    expected.sort();
+    assert_eq!(categories_of(&app, "alice_z101").await, expected);
+    assert!(
+        categories_of(&app, "existing_z101").await.is_empty(),
+        "users created outside registration are untouched"
+    );
+
+    // The seeded "Other" is the one record creation falls back to.
+    let alice = auth::get_user_by_username(&app.state.main_db, "alice_z101")
+        .await
+        .expect("lookup")
+        .expect("alice");
+    let other = categories::get_or_create_category(&app.state.main_db, &alice.id, "other", false)
+        .await
+        .expect("get or create");
+    assert_eq!(other.name, "Other");
+    assert_eq!(
+        categories_of(&app, "alice_z101").await.len(),
+        expected.len()
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z102: The lists come from config; empty lists seed nothing
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z102_default_categories_are_configurable() {
+    let app = common::setup_test_app().await.expect("setup failed");
+
+    let custom = AppState {
+        default_categories: Arc::new(
+            DefaultCategories::new(names(&["Rent", "Coffee"]), names(&["Wages"]))
+                .expect("valid lists"),
+        ),
+        ..app.state.clone()
+    };
+    assert_eq!(register(&custom, "bob_z102").await, StatusCode::CREATED);
+    assert_eq!(
+        categories_of(&app, "bob_z102").await,
+        vec![
+            ("Coffee".to_string(), false),
+            ("Rent".to_string(), false),
+            ("Wages".to_string(), true),
+        ]
+    );
+
+    let empty = AppState {
+        default_categories: Arc::new(DefaultCategories::new(vec![], vec![]).expect("empty")),
+        ..app.state.clone()
+    };
+    assert!(empty.default_categories.is_empty());
+    assert_eq!(register(&empty, "carol_z102").await, StatusCode::CREATED);
+    assert!(categories_of(&app, "carol_z102").await.is_empty());
+}
+
+// ---------------------------------------------------------------------------
+// Z103: A failed registration leaves nothing behind; bad lists are rejected
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z103_failed_registration_seeds_nothing() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    assert_eq!(register(&app.state, "dave_z103").await, StatusCode::CREATED);
+    assert_eq!(
+        register(&app.state, "dave_z103").await,
+        StatusCode::CONFLICT
+    );
+    assert_eq!(
+        categories_of(&app, "dave_z103").await.len(),
+        DEFAULT_EXPENSE_CATEGORIES.len() + DEFAULT_INCOME_CATEGORIES.len(),
+        "the rejected duplicate added no categories"
+    );
+
+    for (expense, income) in [
+        (names(&["Food", "food"]), vec![]),
+        (names(&["Other"]), names(&["other"])),
+        (names(&[" "]), vec![]),
+        (vec![], names(&[" Salary"])),
+        (vec!["x".repeat(101)], vec![]),
+    ] {
+        assert!(
+            DefaultCategories::new(expense.clone(), income.clone()).is_err(),
+            "{expense:?} / {income:?}"
+        );
+    }
+}
@@ -0,0 +1,272 @@
+/// Tests Z81-Z83: Deleting categories that still have records
+///
+/// `DELETE /categories/{id}` refuses (409, with the record count) while live
+/// records use the category, unless `reassign_to` names a category of the same
+/// type to move them to, or `force=true` leaves them uncategorized. Either way
+/// the records move in the same transaction as the delete.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::models::{CreateRecordPayload, Record, RecordDetail};
+use kash_server::records;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> (StatusCode, Value) {
+    let body = payload.map_or_else(Body::empty, |payload| Body::from(payload.to_string()));
+    let request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    (status, body)
+}
+
+async fn create(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    category: &str,
+    amount: f64,
+) -> Record {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id(user),
+        CreateRecordPayload {
+            name: format!("{category} {amount}"),
+            amount: money(amount),
+            currency: None,
+            category_id: scenario.category_id(user, category).to_string(),
+            date: "2025-03-10".to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record")
+}
+
+async fn fetch(app: &common::TestApp, cookie: &str, id: &str) -> RecordDetail {
+    let (status, body) = send(app, "GET", &format!("/records/{id}"), cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    serde_json::from_value(body).expect("record detail")
+}
+
+async fn scenario(app: &common::TestApp, user: &str) -> Scenario {
+    ScenarioBuilder::new()
+        .users(&[user])
+        .category(user, "Food")
+        .category(user, "Dining")
+        .income_category(user, "Salary")
+        .income_category(user, "Wages")
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z81: reassign_to moves every record, trashed ones included, keeping signs
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z81_reassign_records_keeps_amounts() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "alice_z81").await;
+    let cookie = scenario.cookie("alice_z81");
+    let dining = scenario.category_id("alice_z81", "Dining");
+    let lunch = create(&app, &scenario, "alice_z81", "Food", 12.5).await;
+    let dinner = create(&app, &scenario, "alice_z81", "Food", 30.0).await;
+    let trashed = create(&app, &scenario, "alice_z81", "Food", 7.0).await;
+    let pay = create(&app, &scenario, "alice_z81", "Salary", 1000.0).await;
+    let (status, _) = send(
+        &app,
+        "DELETE",
+        &format!("/records/{}", trashed.id),
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, body) = send(
+        &app,
+        "DELETE",
+        &format!(
+            "/categories/{}?reassign_to={dining}",
+            scenario.category_id("alice_z81", "Food")
+        ),
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT, "{body}");
+    for original in [&lunch, &dinner] {
+        let moved = fetch(&app, cookie, &original.id).await;
+        assert_eq!(moved.record.category_id.as_deref(), Some(dining));
+        assert_eq!(moved.record.amount, original.amount);
+        assert!(moved.record.amount.is_negative(), "expenses stay negative");
+    }
+    let (status, body) = send(
+        &app,
+        "POST",
+        &format!("/records/{}/restore", trashed.id),
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["category_id"], dining);
+
+    let (status, _) = send(
+        &app,
+        "DELETE",
+        &format!(
+            "/categories/{}?reassign_to={}",
+            scenario.category_id("alice_z81", "Salary"),
+            scenario.category_id("alice_z81", "Wages")
+        ),
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let moved = fetch(&app, cookie, &pay.id).await;
+    assert_eq!(moved.record.amount, money(1000.0), "income stays positive");
+}
+
+// ---------------------------------------------------------------------------
+// Z82: Without an option the delete is 409; force leaves records uncategorized
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z82_force_uncategorizes_records() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "alice_z82").await;
+    let cookie = scenario.cookie("alice_z82");
+    let food = format!("/categories/{}", scenario.category_id("alice_z82", "Food"));
+    let lunch = create(&app, &scenario, "alice_z82", "Food", 12.5).await;
+    create(&app, &scenario, "alice_z82", "Food", 30.0).await;
+
+    let (status, body) = send(&app, "DELETE", &food, cookie, None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(
+        body.as_str()
+            .is_some_and(|message| message.contains("2 records")),
+        "{body}"
+    );
+    assert_eq!(
+        fetch(&app, cookie, &lunch.id)
+            .await
+            .record
+            .category_id
+            .as_deref(),
+        Some(scenario.category_id("alice_z82", "Food"))
+    );
+
+    let (status, body) = send(&app, "DELETE", &format!("{food}?force=true"), cookie, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT, "{body}");
+    let orphan = fetch(&app, cookie, &lunch.id).await;
+    assert_eq!(orphan.record.category_id, None);
+    assert_eq!(orphan.record.amount, money(-12.5));
+
+    let dining = scenario.category_id("alice_z82", "Dining");
+    let (status, body) = send(
+        &app,
+        "PUT",
+        &format!("/records/{}", lunch.id),
+        cookie,
+        Some(json!({ "name": "Lunch" })),
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::OK,
+        "uncategorized records stay editable: {body}"
+    );
+    let (status, body) = send(
+        &app,
+        "PUT",
+        &format!("/records/{}", lunch.id),
+        cookie,
+        Some(json!({ "category_id": dining, "amount": 14.0 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["amount"], -14.0);
+
+    let (status, _) = send(
+        &app,
+        "DELETE",
+        &format!(
+            "/categories/{}",
+            scenario.category_id("alice_z82", "Salary")
+        ),
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::NO_CONTENT,
+        "an unused category needs no option"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z83: Invalid reassign targets are 400 and delete nothing
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z83_reassign_validation() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "alice_z83").await;
+    let cookie = scenario.cookie("alice_z83");
+    let food_id = scenario.category_id("alice_z83", "Food");
+    let food = format!("/categories/{food_id}");
+    create(&app, &scenario, "alice_z83", "Food", 12.5).await;
+
+    for query in [
+        format!(
+            "reassign_to={}&force=true",
+            scenario.category_id("alice_z83", "Dining")
+        ),
+        format!("reassign_to={food_id}"),
+        "reassign_to=no-such-category".to_string(),
+        format!(
+            "reassign_to={}",
+            scenario.category_id("alice_z83", "Salary")
+        ),
+    ] {
+        let (status, body) = send(&app, "DELETE", &format!("{food}?{query}"), cookie, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}: {body}");
+    }
+    let (_, listed) = send(&app, "GET", "/categories", cookie, None).await;
+    assert_eq!(listed["total_count"], 4, "nothing was deleted");
+
+    let (status, _) = send(&app, "DELETE", &format!("{food}?force=true"), "", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
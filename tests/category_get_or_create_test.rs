@@ -0,0 +1,178 @@
+/// Tests G1-G4: Shared get-or-create for categories
+///
+/// `categories::get_or_create_category` is the one find-or-insert path used by
+/// the bot. It inserts with `ON CONFLICT DO NOTHING` against the case-insensitive
+/// unique index on `(owner_user_id, name)`, so racing callers converge on a
+/// single row. `init_main_db` folds existing case-only duplicates into their
+/// oldest row before building that index.
+mod common;
+
+use common::fixtures::ScenarioBuilder;
+use kash_server::categories::get_or_create_category;
+use kash_server::constants::DEFAULT_CATEGORY_NAME;
+use kash_server::database;
+
+// ---- Helpers ----
+
+async fn count_categories(conn: &libsql::Connection, user_id: &str, name: &str) -> i64 {
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM categories WHERE owner_user_id = ? AND LOWER(name) = LOWER(?)",
+            (user_id, name),
+        )
+        .await
+        .unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+// ---------------------------------------------------------------------------
+// G1: Ten simultaneous calls create exactly one row
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn g1_concurrent_calls_create_one_category() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_g1"])
+        .build(&app)
+        .await;
+    let alice = scenario.id("alice_g1").to_string();
+
+    let handles: Vec<_> = (0..10)
+        .map(|i| {
+            let db = app.state.main_db.clone();
+            let alice = alice.clone();
+            let name = if i % 2 == 0 { "Other" } else { "other" };
+            tokio::spawn(async move { get_or_create_category(&db, &alice, name, false).await })
+        })
+        .collect();
+
+    let mut ids = Vec::new();
+    for handle in handles {
+        let category = handle.await.unwrap().expect("get or create");
+        assert_eq!(category.name.to_lowercase(), "other");
+        ids.push(category.id);
+    }
+    ids.dedup();
+    assert_eq!(ids.len(), 1, "every caller gets the same category");
+
+    let conn = app.state.main_db.read().await;
+    assert_eq!(count_categories(&conn, &alice, "Other").await, 1);
+}
+
+// ---------------------------------------------------------------------------
+// G2: An existing category with different casing is reused as-is
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn g2_existing_category_is_reused_case_insensitively() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_g2", "bob_g2"])
+        .category("alice_g2", "Dining")
+        .income_category("alice_g2", "Salary")
+        .build(&app)
+        .await;
+    let alice = scenario.id("alice_g2");
+
+    let dining = get_or_create_category(&app.state.main_db, alice, "  DINING ", false)
+        .await
+        .expect("reuse");
+    assert_eq!(dining.id, scenario.category_id("alice_g2", "Dining"));
+    assert_eq!(dining.name, "Dining");
+
+    // `is_income` comes from the existing row, not the caller.
+    let salary = get_or_create_category(&app.state.main_db, alice, "salary", false)
+        .await
+        .expect("reuse");
+    assert_eq!(salary.id, scenario.category_id("alice_g2", "Salary"));
+    assert!(salary.is_income);
+
+    // Other users' categories are never matched.
+    let bobs = get_or_create_category(&app.state.main_db, scenario.id("bob_g2"), "dining", false)
+        .await
+        .expect("create for bob");
+    assert_ne!(bobs.id, dining.id);
+    assert_eq!(bobs.name, "dining");
+
+    let conn = app.state.main_db.read().await;
+    assert_eq!(count_categories(&conn, alice, "dining").await, 1);
+    let inserted = conn
+        .execute(
+            "INSERT INTO categories (id, owner_user_id, name, is_income) VALUES ('dup-g2', ?, 'dINING', FALSE)",
+            [alice],
+        )
+        .await;
+    assert!(
+        inserted.is_err(),
+        "the NOCASE unique index rejects duplicates"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// G3: A blank name falls back to the default category
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn g3_blank_name_uses_default_category() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_g3"])
+        .build(&app)
+        .await;
+    let alice = scenario.id("alice_g3");
+
+    let first = get_or_create_category(&app.state.main_db, alice, "   ", false)
+        .await
+        .expect("default");
+    assert_eq!(first.name, DEFAULT_CATEGORY_NAME);
+    let second = get_or_create_category(&app.state.main_db, alice, "", false)
+        .await
+        .expect("default again");
+    assert_eq!(second.id, first.id);
+}
+
+// ---------------------------------------------------------------------------
+// G4: Startup folds pre-existing case duplicates into the oldest row
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn g4_init_merges_case_duplicate_categories() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let data_path = temp_dir.path().to_string_lossy().to_string();
+
+    {
+        let db = database::init_main_db(&data_path).await.expect("init");
+        let conn = db.write().await;
+        conn.execute_batch(
+            "DROP INDEX idx_categories_owner_name_nocase;
+             INSERT INTO categories (id, owner_user_id, name, is_income) VALUES ('cat-a', 'user-g4', 'Other', FALSE);
+             INSERT INTO categories (id, owner_user_id, name, is_income) VALUES ('cat-b', 'user-g4', 'other', FALSE);
+             INSERT INTO categories (id, owner_user_id, name, is_income, parent_id) VALUES ('cat-c', 'user-g4', 'Snacks', FALSE, 'cat-b');
+             INSERT INTO categories (id, owner_user_id, name, is_income) VALUES ('cat-d', 'user-h4', 'OTHER', FALSE);
+             INSERT INTO records (id, owner_user_id, name, amount, category_id, date) VALUES ('rec-1', 'user-g4', 'Gum', -1.0, 'cat-b', '2025-03-10');",
+        )
+        .await
+        .unwrap();
+    }
+
+    let db = database::init_main_db(&data_path).await.expect("re-init");
+    let conn = db.read().await;
+    assert_eq!(count_categories(&conn, "user-g4", "other").await, 1);
+    assert_eq!(
+        count_categories(&conn, "user-h4", "other").await,
+        1,
+        "other users' categories are left alone"
+    );
+
+    let mut rows = conn
+        .query(
+            "SELECT r.category_id, c.parent_id FROM records r, categories c WHERE r.id = 'rec-1' AND c.id = 'cat-c'",
+            (),
+        )
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<String>(0).unwrap(), "cat-a");
+    assert_eq!(row.get::<String>(1).unwrap(), "cat-a");
+}
@@ -0,0 +1,316 @@
+/// Tests H1-H6: Category hierarchy (parent/subcategories)
+///
+/// Categories may be nested one level deep under a parent of the same
+/// is_income type. Deleting a parent requires explicit handling of children.
+mod common;
+
+use axum::http::StatusCode;
+use common::send_json;
+use serde_json::{Value, json};
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+async fn create_category(
+    app: &common::TestApp,
+    cookie: &str,
+    name: &str,
+    is_income: bool,
+    parent_id: Option<&str>,
+) -> (StatusCode, Value) {
+    send_json(
+        app,
+        "POST",
+        "/categories",
+        cookie,
+        Some(json!({ "name": name, "is_income": is_income, "parent_id": parent_id })),
+    )
+    .await
+}
+
+async fn create_category_id(
+    app: &common::TestApp,
+    cookie: &str,
+    name: &str,
+    parent_id: Option<&str>,
+) -> String {
+    let (status, body) = create_category(app, cookie, name, false, parent_id).await;
+    assert_eq!(
+        status,
+        StatusCode::CREATED,
+        "create category {name}: {body}"
+    );
+    body["id"].as_str().expect("category id").to_string()
+}
+
+async fn setup_user(app: &common::TestApp, username: &str) -> String {
+    common::create_test_user(&app.state, username, "pw")
+        .await
+        .expect("create user");
+    common::login_user(&app.router, username, "pw")
+        .await
+        .expect("login user")
+}
+
+async fn list_categories(app: &common::TestApp, cookie: &str) -> Vec<Value> {
+    let (status, body) = send_json(app, "GET", "/categories", cookie, None).await;
+    assert_eq!(status, StatusCode::OK);
+    body["categories"]
+        .as_array()
+        .expect("categories array")
+        .clone()
+}
+
+fn parent_of(categories: &[Value], id: &str) -> Value {
+    categories
+        .iter()
+        .find(|category| category["id"] == id)
+        .map(|category| category["parent_id"].clone())
+        .expect("category present in list")
+}
+
+// ---------------------------------------------------------------------------
+// H1: Child categories are created and listed flat with parent_id
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn h1_child_category_is_listed_with_parent_id() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let cookie = setup_user(&app, "alice_h1").await;
+
+    let transport_id = create_category_id(&app, &cookie, "Transport", None).await;
+    let fuel_id = create_category_id(&app, &cookie, "Fuel", Some(&transport_id)).await;
+    let taxi_id = create_category_id(&app, &cookie, "Taxi", Some(&transport_id)).await;
+
+    let categories = list_categories(&app, &cookie).await;
+    assert_eq!(categories.len(), 3);
+    assert_eq!(parent_of(&categories, &transport_id), Value::Null);
+    assert_eq!(parent_of(&categories, &fuel_id), json!(transport_id));
+    assert_eq!(parent_of(&categories, &taxi_id), json!(transport_id));
+}
+
+// ---------------------------------------------------------------------------
+// H2: Parent must share is_income type and belong to the same user
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn h2_parent_must_match_type_and_owner() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let alice_cookie = setup_user(&app, "alice_h2").await;
+    let bob_cookie = setup_user(&app, "bob_h2").await;
+
+    let (status, salary) = create_category(&app, &alice_cookie, "Salary", true, None).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let salary_id = salary["id"].as_str().expect("salary id");
+
+    let (status, _) = create_category(&app, &alice_cookie, "Fuel", false, Some(salary_id)).await;
+    assert_eq!(
+        status,
+        StatusCode::BAD_REQUEST,
+        "expense child under income parent must be rejected"
+    );
+
+    let (status, _) = create_category(&app, &bob_cookie, "Bonus", true, Some(salary_id)).await;
+    assert_eq!(
+        status,
+        StatusCode::BAD_REQUEST,
+        "Bob must not nest under Alice's category"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// H3: Depth is limited to two levels and cycles are rejected
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn h3_depth_limit_and_cycle_prevention() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let cookie = setup_user(&app, "alice_h3").await;
+
+    let transport_id = create_category_id(&app, &cookie, "Transport", None).await;
+    let fuel_id = create_category_id(&app, &cookie, "Fuel", Some(&transport_id)).await;
+
+    let (status, _) = create_category(&app, &cookie, "Diesel", false, Some(&fuel_id)).await;
+    assert_eq!(
+        status,
+        StatusCode::BAD_REQUEST,
+        "third level must be rejected"
+    );
+
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/categories/{transport_id}"),
+        &cookie,
+        Some(json!({ "parent_id": fuel_id })),
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::BAD_REQUEST,
+        "parent moving under its own child must be rejected"
+    );
+
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/categories/{transport_id}"),
+        &cookie,
+        Some(json!({ "parent_id": transport_id })),
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::BAD_REQUEST,
+        "self-parent must be rejected"
+    );
+
+    let categories = list_categories(&app, &cookie).await;
+    assert_eq!(parent_of(&categories, &transport_id), Value::Null);
+    assert_eq!(parent_of(&categories, &fuel_id), json!(transport_id));
+}
+
+// ---------------------------------------------------------------------------
+// H4: Update can move a category under a parent and promote it back
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn h4_update_moves_and_promotes_category() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let cookie = setup_user(&app, "alice_h4").await;
+
+    let transport_id = create_category_id(&app, &cookie, "Transport", None).await;
+    let taxi_id = create_category_id(&app, &cookie, "Taxi", None).await;
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/categories/{taxi_id}"),
+        &cookie,
+        Some(json!({ "parent_id": transport_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["parent_id"], json!(transport_id));
+    assert_eq!(
+        body["name"], "Taxi",
+        "name is kept when only parent changes"
+    );
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/categories/{taxi_id}"),
+        &cookie,
+        Some(json!({ "name": "Cabs" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["parent_id"],
+        json!(transport_id),
+        "parent is kept when field is absent"
+    );
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/categories/{taxi_id}"),
+        &cookie,
+        Some(json!({ "parent_id": null })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["parent_id"], Value::Null);
+}
+
+// ---------------------------------------------------------------------------
+// H5: Deleting a parent requires promote_children or reassign_children_to
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn h5_delete_parent_requires_explicit_child_handling() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let cookie = setup_user(&app, "alice_h5").await;
+
+    let transport_id = create_category_id(&app, &cookie, "Transport", None).await;
+    let fuel_id = create_category_id(&app, &cookie, "Fuel", Some(&transport_id)).await;
+
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("/categories/{transport_id}"),
+        &cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("/categories/{transport_id}?promote_children=true&reassign_children_to={fuel_id}"),
+        &cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "both options is ambiguous");
+
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("/categories/{transport_id}?promote_children=true"),
+        &cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let categories = list_categories(&app, &cookie).await;
+    assert_eq!(categories.len(), 1);
+    assert_eq!(parent_of(&categories, &fuel_id), Value::Null);
+}
+
+// ---------------------------------------------------------------------------
+// H6: Reassigning children moves them under a valid top-level category
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn h6_delete_parent_reassigns_children() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let cookie = setup_user(&app, "alice_h6").await;
+
+    let transport_id = create_category_id(&app, &cookie, "Transport", None).await;
+    let travel_id = create_category_id(&app, &cookie, "Travel", None).await;
+    let taxi_id = create_category_id(&app, &cookie, "Taxi", Some(&transport_id)).await;
+    let fuel_id = create_category_id(&app, &cookie, "Fuel", Some(&transport_id)).await;
+
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("/categories/{transport_id}?reassign_children_to={taxi_id}"),
+        &cookie,
+        None,
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::BAD_REQUEST,
+        "children cannot be reassigned under a sibling subcategory"
+    );
+
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("/categories/{transport_id}?reassign_children_to={travel_id}"),
+        &cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let categories = list_categories(&app, &cookie).await;
+    assert_eq!(parent_of(&categories, &taxi_id), json!(travel_id));
+    assert_eq!(parent_of(&categories, &fuel_id), json!(travel_id));
+}
@@ -0,0 +1,270 @@
+/// Tests Z91-Z93: Merging categories
+///
+/// `POST /categories/merge {source_id, target_id}` moves every record of the
+/// source to the target and deletes the source in one transaction, returning the
+/// target and how many records moved. Amounts keep their sign, so merging across
+/// income and expense is 400.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::models::{CreateRecordPayload, MergeCategoriesResponse, Record};
+use kash_server::records;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> (StatusCode, Value) {
+    let body = payload.map_or_else(Body::empty, |payload| Body::from(payload.to_string()));
+    let request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    (status, body)
+}
+
+async fn merge(
+    app: &common::TestApp,
+    cookie: &str,
+    source_id: &str,
+    target_id: &str,
+) -> (StatusCode, Value) {
+    send(
+        app,
+        "POST",
+        "/categories/merge",
+        cookie,
+        Some(json!({ "source_id": source_id, "target_id": target_id })),
+    )
+    .await
+}
+
+async fn create(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    category: &str,
+    amount: f64,
+) -> Record {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id(user),
+        CreateRecordPayload {
+            name: format!("{category} {amount}"),
+            amount: money(amount),
+            currency: None,
+            category_id: scenario.category_id(user, category).to_string(),
+            date: "2025-03-10".to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record")
+}
+
+async fn category_names(app: &common::TestApp, cookie: &str) -> Vec<String> {
+    let (_, body) = send(app, "GET", "/categories", cookie, None).await;
+    body["categories"]
+        .as_array()
+        .expect("categories")
+        .iter()
+        .map(|category| category["name"].as_str().expect("name").to_string())
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Z91: Records move to the target with their amounts and the source is gone
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z91_merge_moves_records() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z91", "bob_z91"])
+        .category("alice_z91", "Food")
+        .category("alice_z91", "Foods")
+        .category("alice_z91", "Dining")
+        .category("bob_z91", "Foods")
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z91");
+    let dining = scenario.category_id("alice_z91", "Dining");
+    let moved = [
+        create(&app, &scenario, "alice_z91", "Food", 12.5).await,
+        create(&app, &scenario, "alice_z91", "Food", 30.0).await,
+    ];
+    create(&app, &scenario, "alice_z91", "Foods", 4.0).await;
+    create(&app, &scenario, "alice_z91", "Dining", 8.0).await;
+    let bobs = create(&app, &scenario, "bob_z91", "Foods", 9.0).await;
+
+    let (status, body) = merge(
+        &app,
+        cookie,
+        scenario.category_id("alice_z91", "Food"),
+        dining,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let response: MergeCategoriesResponse = serde_json::from_value(body).expect("merge response");
+    assert_eq!(response.category.id, dining);
+    assert_eq!(response.category.name, "Dining");
+    assert_eq!(response.records_moved, 2);
+    for record in &moved {
+        let (_, body) = send(
+            &app,
+            "GET",
+            &format!("/records/{}", record.id),
+            cookie,
+            None,
+        )
+        .await;
+        assert_eq!(body["category_id"], dining);
+        assert_eq!(body["amount"], record.amount.to_decimal(), "sign unchanged");
+    }
+
+    let (_, body) = merge(
+        &app,
+        cookie,
+        scenario.category_id("alice_z91", "Foods"),
+        dining,
+    )
+    .await;
+    assert_eq!(body["records_moved"], 1);
+    assert_eq!(category_names(&app, cookie).await, vec!["Dining"]);
+    let (_, page) = send(
+        &app,
+        "GET",
+        &format!("/categories/{dining}/activity"),
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(page["total_count"], 4);
+
+    let (_, body) = send(
+        &app,
+        "GET",
+        &format!("/records/{}", bobs.id),
+        scenario.cookie("bob_z91"),
+        None,
+    )
+    .await;
+    assert_eq!(
+        body["category_id"],
+        scenario.category_id("bob_z91", "Foods"),
+        "other users untouched"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z92: Income and expense categories cannot be merged into each other
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z92_merge_rejects_type_mismatch() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z92"])
+        .category("alice_z92", "Food")
+        .income_category("alice_z92", "Salary")
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z92");
+    let food = scenario.category_id("alice_z92", "Food");
+    let salary = scenario.category_id("alice_z92", "Salary");
+    let record = create(&app, &scenario, "alice_z92", "Food", 12.5).await;
+
+    for (source, target) in [(food, salary), (salary, food)] {
+        let (status, body) = merge(&app, cookie, source, target).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+        assert!(
+            body.as_str()
+                .is_some_and(|message| message.contains("income")),
+            "{body}"
+        );
+    }
+    assert_eq!(category_names(&app, cookie).await, vec!["Food", "Salary"]);
+    let (_, body) = send(
+        &app,
+        "GET",
+        &format!("/records/{}", record.id),
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(body["category_id"], food);
+}
+
+// ---------------------------------------------------------------------------
+// Z93: Self-merges, unknown ids, parents and other users' categories fail
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z93_merge_validation() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z93", "bob_z93"])
+        .category("alice_z93", "Food")
+        .category("alice_z93", "Transport")
+        .category("bob_z93", "Food")
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z93");
+    let food = scenario.category_id("alice_z93", "Food");
+    let transport = scenario.category_id("alice_z93", "Transport");
+
+    let (status, _) = merge(&app, cookie, food, food).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    for (source, target) in [
+        ("no-such-category", food),
+        (food, "no-such-category"),
+        (scenario.category_id("bob_z93", "Food"), food),
+    ] {
+        let (status, _) = merge(&app, cookie, source, target).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{source} -> {target}");
+    }
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/categories",
+        cookie,
+        Some(json!({ "name": "Fuel", "is_income": false, "parent_id": transport })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let (status, _) = merge(&app, cookie, transport, food).await;
+    assert_eq!(status, StatusCode::CONFLICT, "source has subcategories");
+    assert_eq!(
+        category_names(&app, cookie).await,
+        vec!["Food", "Fuel", "Transport"]
+    );
+
+    let (status, _) = merge(&app, "", food, transport).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
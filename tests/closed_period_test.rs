@@ -0,0 +1,319 @@
+/// Tests C1-C6: Closed accounting period
+///
+/// Records dated on or before the user's `closed_through` setting are locked.
+/// Every mutation path answers 409 PERIOD_CLOSED unless the HTTP request
+/// carries `reopen=true`, which is allowed and written to the audit table.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::money;
+use common::send_json;
+use kash_server::constants::ERROR_CODE_PERIOD_CLOSED;
+use kash_server::models::CreateRecordPayload;
+use kash_server::records;
+use serde_json::{Value, json};
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+async fn setup_user(app: &common::TestApp, username: &str) -> (String, String) {
+    let user_id = common::create_test_user(&app.state, username, "pw")
+        .await
+        .expect("create user");
+    let cookie = common::login_user(&app.router, username, "pw")
+        .await
+        .expect("login user");
+    (user_id, cookie)
+}
+
+async fn create_category(app: &common::TestApp, cookie: &str, name: &str) -> String {
+    let (status, body) = send_json(
+        app,
+        "POST",
+        "/categories",
+        cookie,
+        Some(json!({ "name": name, "is_income": false })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create category {name}");
+    body["id"].as_str().expect("category id").to_string()
+}
+
+async fn create_record(
+    app: &common::TestApp,
+    cookie: &str,
+    name: &str,
+    category_id: &str,
+    date: &str,
+) -> String {
+    let (status, body) = send_json(
+        app,
+        "POST",
+        "/records",
+        cookie,
+        Some(json!({ "name": name, "amount": 10.0, "category_id": category_id, "date": date })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create record {name}: {body}");
+    body["id"].as_str().expect("record id").to_string()
+}
+
+async fn close_through(app: &common::TestApp, cookie: &str, date: &str) {
+    let (status, body) = send_json(
+        app,
+        "PUT",
+        "/settings",
+        cookie,
+        Some(json!({ "closed_through": date })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "close period: {body}");
+    assert_eq!(body["closed_through"], date);
+}
+
+async fn audit_count(app: &common::TestApp, user_id: &str) -> i64 {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM period_reopen_audit WHERE owner_user_id = ?",
+            [user_id],
+        )
+        .await
+        .expect("query audit");
+    let row = rows.next().await.expect("read row").expect("count row");
+    row.get(0).expect("count")
+}
+
+fn assert_period_closed(status: StatusCode, body: &Value) {
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    assert_eq!(
+        body["code"], ERROR_CODE_PERIOD_CLOSED,
+        "unexpected body: {body}"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// C1: Settings round-trip and GET /records marks locked records
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn c1_settings_round_trip_and_locked_flag() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (_, cookie) = setup_user(&app, "alice_c1").await;
+    let food_id = create_category(&app, &cookie, "Food").await;
+    let march = create_record(&app, &cookie, "March", &food_id, "2024-03-31").await;
+    let april = create_record(&app, &cookie, "April", &food_id, "2024-04-01").await;
+
+    let (status, body) = send_json(&app, "GET", "/settings", &cookie, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["closed_through"], Value::Null);
+
+    close_through(&app, &cookie, "2024-03-31").await;
+
+    let (status, body) = send_json(&app, "GET", "/records", &cookie, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let records = body["records"].as_array().expect("records array");
+    let locked = |id: &str| {
+        records
+            .iter()
+            .find(|record| record["id"] == id)
+            .map(|record| record["locked"] == true)
+            .expect("record listed")
+    };
+    assert!(locked(&march), "record on closed_through is locked");
+    assert!(!locked(&april), "record after closed_through is open");
+
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        "/settings",
+        &cookie,
+        Some(json!({ "closed_through": "2024-13-01" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        "/settings",
+        &cookie,
+        Some(json!({ "closed_through": null })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["closed_through"], Value::Null);
+}
+
+// ---------------------------------------------------------------------------
+// C2: Creating a record in the closed period is guarded
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn c2_create_in_closed_period_requires_reopen() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (user_id, cookie) = setup_user(&app, "alice_c2").await;
+    let food_id = create_category(&app, &cookie, "Food").await;
+    close_through(&app, &cookie, "2024-03-31").await;
+
+    let payload =
+        json!({ "name": "Late", "amount": 5.0, "category_id": food_id, "date": "2024-03-15" });
+    let (status, body) = send_json(&app, "POST", "/records", &cookie, Some(payload.clone())).await;
+    assert_period_closed(status, &body);
+    assert_eq!(audit_count(&app, &user_id).await, 0);
+
+    let (status, _) = send_json(&app, "POST", "/records?reopen=true", &cookie, Some(payload)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(audit_count(&app, &user_id).await, 1, "reopen is audited");
+
+    create_record(&app, &cookie, "Open", &food_id, "2024-04-01").await;
+    assert_eq!(audit_count(&app, &user_id).await, 1);
+}
+
+// ---------------------------------------------------------------------------
+// C3: Updates cannot touch or move records into the closed period
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn c3_update_in_closed_period_requires_reopen() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (user_id, cookie) = setup_user(&app, "alice_c3").await;
+    let food_id = create_category(&app, &cookie, "Food").await;
+    let closed = create_record(&app, &cookie, "Closed", &food_id, "2024-03-10").await;
+    let open = create_record(&app, &cookie, "Open", &food_id, "2024-04-10").await;
+    close_through(&app, &cookie, "2024-03-31").await;
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/records/{closed}"),
+        &cookie,
+        Some(json!({ "name": "Edited" })),
+    )
+    .await;
+    assert_period_closed(status, &body);
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/records/{open}"),
+        &cookie,
+        Some(json!({ "date": "2024-03-01" })),
+    )
+    .await;
+    assert_period_closed(status, &body);
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/records/{closed}?reopen=true"),
+        &cookie,
+        Some(json!({ "name": "Edited" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["name"], "Edited");
+    assert_eq!(audit_count(&app, &user_id).await, 1);
+}
+
+// ---------------------------------------------------------------------------
+// C4: Deletes in the closed period are guarded
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn c4_delete_in_closed_period_requires_reopen() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (user_id, cookie) = setup_user(&app, "alice_c4").await;
+    let food_id = create_category(&app, &cookie, "Food").await;
+    let closed = create_record(&app, &cookie, "Closed", &food_id, "2024-03-10").await;
+    close_through(&app, &cookie, "2024-03-31").await;
+
+    let (status, body) =
+        send_json(&app, "DELETE", &format!("/records/{closed}"), &cookie, None).await;
+    assert_period_closed(status, &body);
+
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("/records/{closed}?reopen=true"),
+        &cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(audit_count(&app, &user_id).await, 1);
+}
+
+// ---------------------------------------------------------------------------
+// C5: Batch recategorization refuses to move closed records
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn c5_batch_recategorize_in_closed_period_requires_reopen() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (user_id, cookie) = setup_user(&app, "alice_c5").await;
+    let food_id = create_category(&app, &cookie, "Food").await;
+    let groceries_id = create_category(&app, &cookie, "Groceries").await;
+    create_record(&app, &cookie, "7-11", &food_id, "2024-03-10").await;
+    create_record(&app, &cookie, "7-11", &food_id, "2024-04-10").await;
+    close_through(&app, &cookie, "2024-03-31").await;
+
+    let batch =
+        json!({ "name_pattern": "7-11", "target_category_id": groceries_id, "dry_run": false });
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/records/recategorize-batch",
+        &cookie,
+        Some(batch.clone()),
+    )
+    .await;
+    assert_period_closed(status, &body);
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/records/recategorize-batch?reopen=true",
+        &cookie,
+        Some(batch),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["matched_count"], 2);
+    assert_eq!(audit_count(&app, &user_id).await, 1);
+}
+
+// ---------------------------------------------------------------------------
+// C6: The bot's create path cannot reopen a closed period
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn c6_bot_create_path_refuses_closed_period() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (user_id, cookie) = setup_user(&app, "alice_c6").await;
+    let food_id = create_category(&app, &cookie, "Food").await;
+    close_through(&app, &cookie, "2024-03-31").await;
+
+    let payload = CreateRecordPayload {
+        name: "Lunch".to_string(),
+        amount: money(8.0),
+        currency: None,
+        category_id: food_id,
+        date: "2024-03-02".to_string(),
+        original_amount: None,
+        original_currency: None,
+        note: None,
+        tags: Vec::new(),
+    };
+    let (status, message) =
+        records::create_record_for_user(&app.state.main_db, &user_id, payload, None, false)
+            .await
+            .expect_err("closed period must be refused");
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        message,
+        "PERIOD_CLOSED: records dated on or before 2024-03-31 are locked"
+    );
+    assert_eq!(audit_count(&app, &user_id).await, 0);
+}
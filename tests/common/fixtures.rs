@@ -0,0 +1,283 @@
+//! Scenario builder for seeding multi-user test data.
+//!
+//! Users, categories, friendships and splits are written through the library
+//! functions (the same code paths the HTTP handlers use) instead of one HTTP
+//! round-trip per step. Every user is logged in once so tests get a cookie for
+//! free. Password hashing is cheap because the test build enables the
+//! `test-fast-hash` feature.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use kash_server::models::PublicUser;
+use kash_server::models::{CreateCategoryPayload, CreateSplitPayload, SplitMode, SplitParticipant};
+use kash_server::money::Money;
+use kash_server::splits::CreateSplitResponse;
+use kash_server::{auth, categories, friends, splits};
+
+use super::TestApp;
+
+/// Fixture amounts are written as decimals; each must be whole cents.
+pub fn money(amount: f64) -> Money {
+    Money::from_decimal(amount).unwrap_or_else(|e| panic!("fixture amount {amount}: {e}"))
+}
+
+pub const FIXTURE_PASSWORD: &str = "password123";
+pub const FIXTURE_SPLIT_DATE: &str = "2026-02-20";
+
+enum Step {
+    User(String),
+    Category {
+        owner: String,
+        name: String,
+        is_income: bool,
+    },
+    FriendRequest {
+        from: String,
+        to: String,
+        accept: bool,
+    },
+    Split {
+        payer: String,
+        category: String,
+        currency: Option<String>,
+        total: Money,
+        participants: Vec<(String, Money)>,
+    },
+}
+
+/// Fluent description of a scenario; nothing is written until `build`.
+///
+/// ```ignore
+/// let scenario = ScenarioBuilder::new()
+///     .users(&["alice", "bob"])
+///     .category("alice", "Dining")
+///     .friend("alice", "bob")
+///     .split("alice", "Dining", 90.0, &[("bob", 30.0)])
+///     .build(&app)
+///     .await;
+/// ```
+#[derive(Default)]
+pub struct ScenarioBuilder {
+    steps: Vec<Step>,
+}
+
+impl ScenarioBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn user(mut self, username: &str) -> Self {
+        self.steps.push(Step::User(username.to_string()));
+        self
+    }
+
+    pub fn users(self, usernames: &[&str]) -> Self {
+        usernames
+            .iter()
+            .fold(self, |builder, username| builder.user(username))
+    }
+
+    /// Expense category owned by `owner`.
+    pub fn category(mut self, owner: &str, name: &str) -> Self {
+        self.steps.push(Step::Category {
+            owner: owner.to_string(),
+            name: name.to_string(),
+            is_income: false,
+        });
+        self
+    }
+
+    pub fn income_category(mut self, owner: &str, name: &str) -> Self {
+        self.steps.push(Step::Category {
+            owner: owner.to_string(),
+            name: name.to_string(),
+            is_income: true,
+        });
+        self
+    }
+
+    /// Accepted friendship: `from` sends the request and `to` accepts it.
+    pub fn friend(mut self, from: &str, to: &str) -> Self {
+        self.steps.push(Step::FriendRequest {
+            from: from.to_string(),
+            to: to.to_string(),
+            accept: true,
+        });
+        self
+    }
+
+    /// Friend request from `from` to `to` that is left pending.
+    pub fn friend_request(mut self, from: &str, to: &str) -> Self {
+        self.steps.push(Step::FriendRequest {
+            from: from.to_string(),
+            to: to.to_string(),
+            accept: false,
+        });
+        self
+    }
+
+    /// Split paid by `payer` in the payer's `category`, with `(username, amount)` shares.
+    pub fn split(self, payer: &str, category: &str, total: f64, shares: &[(&str, f64)]) -> Self {
+        self.push_split(payer, category, None, total, shares)
+    }
+
+    /// `split`, booked in `currency` instead of the payer's setting.
+    pub fn split_in(
+        self,
+        currency: &str,
+        payer: &str,
+        category: &str,
+        total: f64,
+        shares: &[(&str, f64)],
+    ) -> Self {
+        self.push_split(payer, category, Some(currency), total, shares)
+    }
+
+    fn push_split(
+        mut self,
+        payer: &str,
+        category: &str,
+        currency: Option<&str>,
+        total: f64,
+        shares: &[(&str, f64)],
+    ) -> Self {
+        self.steps.push(Step::Split {
+            payer: payer.to_string(),
+            category: category.to_string(),
+            currency: currency.map(str::to_string),
+            total: money(total),
+            participants: shares
+                .iter()
+                .map(|(username, amount)| (username.to_string(), money(*amount)))
+                .collect(),
+        });
+        self
+    }
+
+    pub async fn build(self, app: &TestApp) -> Scenario {
+        let mut scenario = Scenario::default();
+        let db = &app.state.main_db;
+
+        for step in self.steps {
+            match step {
+                Step::User(username) => {
+                    let user = auth::create_user(db, &username, FIXTURE_PASSWORD)
+                        .await
+                        .unwrap_or_else(|e| panic!("create user {username}: {e}"));
+                    let cookie = super::login_user(&app.router, &username, FIXTURE_PASSWORD)
+                        .await
+                        .unwrap_or_else(|e| panic!("login {username}: {e}"));
+                    scenario.users.insert(
+                        username,
+                        ScenarioUser {
+                            id: user.id,
+                            cookie,
+                        },
+                    );
+                }
+                Step::Category {
+                    owner,
+                    name,
+                    is_income,
+                } => {
+                    let payload = CreateCategoryPayload {
+                        name: name.clone(),
+                        is_income,
+                        parent_id: None,
+                        monthly_budget: None,
+                    };
+                    let category =
+                        categories::create_category_for_user(db, scenario.id(&owner), payload)
+                            .await
+                            .unwrap_or_else(|e| panic!("create category {name}: {e:?}"));
+                    scenario.categories.insert((owner, name), category.id);
+                }
+                Step::FriendRequest { from, to, accept } => {
+                    let sender = PublicUser {
+                        id: scenario.id(&from).to_string(),
+                        username: from.clone(),
+                    };
+                    friends::send_friend_request_for_user(db, &sender, &to, None)
+                        .await
+                        .unwrap_or_else(|e| panic!("friend request {from} -> {to}: {e:?}"));
+                    if accept {
+                        friends::accept_friend_for_user(db, scenario.id(&to), scenario.id(&from))
+                            .await
+                            .unwrap_or_else(|e| panic!("accept {from} -> {to}: {e:?}"));
+                    }
+                }
+                Step::Split {
+                    payer,
+                    category,
+                    currency,
+                    total,
+                    participants,
+                } => {
+                    let payload = CreateSplitPayload {
+                        idempotency_key: format!("fixture-split-{}", scenario.splits.len() + 1),
+                        total_amount: total,
+                        description: format!("{payer} split"),
+                        date: FIXTURE_SPLIT_DATE.to_string(),
+                        category_id: scenario.category_id(&payer, &category).to_string(),
+                        currency,
+                        split_mode: SplitMode::Exact,
+                        splits: participants
+                            .iter()
+                            .map(|(username, amount)| SplitParticipant {
+                                user_id: scenario.id(username).to_string(),
+                                amount: *amount,
+                                percentage: None,
+                            })
+                            .collect(),
+                        legacy_full_total: false,
+                    };
+                    let (status, response) =
+                        splits::create_split_for_user(&app.state, scenario.id(&payer), payload)
+                            .await
+                            .unwrap_or_else(|e| panic!("split paid by {payer}: {e:?}"));
+                    assert_eq!(status, StatusCode::CREATED, "split paid by {payer}");
+                    scenario.splits.push(response);
+                }
+            }
+        }
+
+        scenario
+    }
+}
+
+pub struct ScenarioUser {
+    pub id: String,
+    pub cookie: String,
+}
+
+/// Ids and session cookies produced by `ScenarioBuilder::build`, keyed by username.
+#[derive(Default)]
+pub struct Scenario {
+    pub users: HashMap<String, ScenarioUser>,
+    pub categories: HashMap<(String, String), String>,
+    pub splits: Vec<CreateSplitResponse>,
+}
+
+impl Scenario {
+    pub fn user(&self, username: &str) -> &ScenarioUser {
+        self.users
+            .get(username)
+            .unwrap_or_else(|| panic!("scenario has no user {username}"))
+    }
+
+    pub fn id(&self, username: &str) -> &str {
+        &self.user(username).id
+    }
+
+    pub fn cookie(&self, username: &str) -> &str {
+        &self.user(username).cookie
+    }
+
+    pub fn category_id(&self, owner: &str, name: &str) -> &str {
+        self.categories
+            .get(&(owner.to_string(), name.to_string()))
+            .unwrap_or_else(|| panic!("scenario has no category {name} for {owner}"))
+    }
+}
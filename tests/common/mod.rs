@@ -2,12 +2,22 @@ use axum::{
     Router,
     body::Body,
     http::{Request, StatusCode},
+    response::Response,
 };
-use kash_server::{AppState, auth, constants::*, database};
+use kash_server::{
+    AppState, auth,
+    config::{BackupConfig, BodyLimits, SessionStoreKind},
+    config_sources::ConfigSources,
+    constants::*,
+    crypto, database,
+    session_store::AppSessionStore,
+};
+use serde_json::Value;
 use time::Duration;
 use tower::util::ServiceExt;
-use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer, cookie::Key};
-use uuid::Uuid;
+use tower_sessions::{Expiry, SessionManagerLayer, cookie::Key};
+
+pub mod fixtures;
 
 #[derive(Clone)]
 pub struct TestConfig {
@@ -30,8 +40,19 @@ impl TestConfig {
 pub struct TestApp {
     pub router: Router,
     pub state: AppState,
+    /// Data directory holding `users.db`, for tests that open it outside the router.
+    #[allow(dead_code)]
+    pub data_path: String,
 }
 
+#[allow(dead_code)]
+pub const TEST_ADMIN_TOKEN: &str = "test_admin_token_at_least_32_characters";
+
+/// Installed process-wide by `setup_test_app`, like `RECORD_ENCRYPTION_KEY` in production.
+#[allow(dead_code)]
+pub const TEST_RECORD_ENCRYPTION_KEY: &str =
+    "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
 pub async fn setup_test_app() -> anyhow::Result<TestApp> {
     let test_config = TestConfig::new()?;
 
@@ -42,11 +63,31 @@ pub async fn setup_test_app() -> anyhow::Result<TestApp> {
         .await
         .map_err(|e| anyhow::anyhow!("Failed to initialize main database: {}", e))?;
 
-    let app_state = AppState { main_db };
+    // Every test app in the process shares the one key; a repeat install is a no-op.
+    crypto::install_master_key(crypto::MasterKey::from_hex(TEST_RECORD_ENCRYPTION_KEY)?);
+
+    let app_state = AppState {
+        main_db: main_db.clone(),
+        admin_token: Some(TEST_ADMIN_TOKEN.to_string()),
+        idempotency_max_body_bytes: DEFAULT_IDEMPOTENCY_MAX_BODY_BYTES,
+        csv_import_max_rows: DEFAULT_CSV_IMPORT_MAX_ROWS,
+        default_categories: Default::default(),
+        password_policy: Default::default(),
+        metrics: Default::default(),
+        auth_rate_limiter: Default::default(),
+        backup: Some(std::sync::Arc::new(BackupConfig::new(&data_path))),
+    };
 
-    let store = MemoryStore::default();
+    // `SESSION_STORE=memory` runs the suite against the in-process store instead
+    let store = AppSessionStore::new(
+        SessionStoreKind::from_sources(&ConfigSources::from_env()?)?,
+        main_db,
+    );
 
     let session_secret = "test_secret_key_at_least_64_chars_long_test_secret_key_at_least_64_";
+    kash_server::two_factor::install_two_factor_key(
+        kash_server::two_factor::TwoFactorKey::from_session_secret(session_secret),
+    );
     let session_key = Key::try_from(session_secret.as_bytes())
         .map_err(|e| anyhow::anyhow!("Invalid session secret: {}", e))?;
 
@@ -58,10 +99,92 @@ pub async fn setup_test_app() -> anyhow::Result<TestApp> {
 
     let router = Router::new()
         .route("/", axum::routing::get(root_handler))
+        .route(
+            "/admin/friendships/prune",
+            axum::routing::post(kash_server::admin::prune_friendships),
+        )
+        .route(
+            "/admin/idempotency-keys/cleanup",
+            axum::routing::post(kash_server::admin::cleanup_idempotency_keys),
+        )
+        .route(
+            "/admin/users/{id}/unlink-telegram",
+            axum::routing::post(kash_server::admin::unlink_telegram),
+        )
+        .route(
+            "/admin/users/{id}/disable",
+            axum::routing::post(kash_server::admin::disable_user),
+        )
+        .route(
+            "/admin/users/{id}/encrypt-records",
+            axum::routing::post(kash_server::admin::encrypt_records),
+        )
+        .route(
+            "/admin/users/{id}/rotate-records-key",
+            axum::routing::post(kash_server::admin::rotate_records_key),
+        )
+        .route(
+            "/admin/metrics",
+            axum::routing::get(kash_server::admin::get_metrics),
+        )
+        .route(
+            "/admin/backup",
+            axum::routing::post(kash_server::admin::create_backup),
+        )
         .route("/auth/register", axum::routing::post(auth::register))
         .route("/auth/login", axum::routing::post(auth::login))
+        .route(
+            "/auth/login/2fa",
+            axum::routing::post(kash_server::two_factor::login_two_factor),
+        )
         .route("/auth/me", axum::routing::get(auth::me))
         .route("/auth/logout", axum::routing::post(auth::logout))
+        .route(
+            "/auth/login-history",
+            axum::routing::get(auth::get_login_history),
+        )
+        .route(
+            "/auth/username",
+            axum::routing::patch(auth::change_username),
+        )
+        .route(
+            "/auth/change-password",
+            axum::routing::post(auth::change_password),
+        )
+        .route("/auth/account", axum::routing::delete(auth::delete_account))
+        .route(
+            "/auth/tokens",
+            axum::routing::post(kash_server::api_tokens::create_token)
+                .get(kash_server::api_tokens::list_tokens),
+        )
+        .route(
+            "/auth/tokens/{id}",
+            axum::routing::delete(kash_server::api_tokens::revoke_token),
+        )
+        .route(
+            "/auth/2fa/setup",
+            axum::routing::post(kash_server::two_factor::setup_two_factor),
+        )
+        .route(
+            "/auth/2fa/verify",
+            axum::routing::post(kash_server::two_factor::verify_two_factor),
+        )
+        .route(
+            "/auth/2fa/disable",
+            axum::routing::post(kash_server::two_factor::disable_two_factor),
+        )
+        .route(
+            "/auth/export",
+            axum::routing::get(kash_server::export::export_account),
+        )
+        .route(
+            "/auth/import",
+            axum::routing::post(kash_server::export::import_account),
+        )
+        .route(
+            "/bootstrap",
+            axum::routing::get(kash_server::bootstrap::get_bootstrap),
+        )
         .route(
             "/records",
             axum::routing::post(kash_server::records::create_record)
@@ -69,27 +192,89 @@ pub async fn setup_test_app() -> anyhow::Result<TestApp> {
         )
         .route(
             "/records/{id}",
-            axum::routing::put(kash_server::records::update_record)
+            axum::routing::get(kash_server::records::get_record)
+                .put(kash_server::records::update_record)
                 .delete(kash_server::records::delete_record),
         )
+        .route(
+            "/records/batch",
+            axum::routing::post(kash_server::records::create_records_batch),
+        )
+        .route(
+            "/records/export",
+            axum::routing::get(kash_server::records::export_records_csv),
+        )
+        .route(
+            "/records/import",
+            axum::routing::post(kash_server::records::import_records),
+        )
+        .route(
+            "/records/summary",
+            axum::routing::get(kash_server::records::get_records_summary),
+        )
         .route(
             "/records/{id}/settle",
             axum::routing::put(kash_server::records::update_settle),
         )
+        .route(
+            "/records/{id}/restore",
+            axum::routing::post(kash_server::records::restore_record),
+        )
+        .route(
+            "/records/trash",
+            axum::routing::get(kash_server::records::get_records_trash),
+        )
         .route(
             "/records/finalize-pending",
             axum::routing::post(kash_server::records::finalize_pending_record),
         )
+        .route(
+            "/records/recategorize-batch",
+            axum::routing::post(kash_server::records::recategorize_batch),
+        )
+        .route(
+            "/records/recategorize-batch/undo",
+            axum::routing::post(kash_server::records::undo_recategorize_batch),
+        )
+        .route(
+            "/settings",
+            axum::routing::get(kash_server::settings::get_settings)
+                .put(kash_server::settings::update_settings),
+        )
+        .route(
+            "/export",
+            axum::routing::get(kash_server::export::export_data),
+        )
+        .route(
+            "/stats/ai-accuracy",
+            axum::routing::get(kash_server::stats::get_ai_accuracy),
+        )
         .route(
             "/categories",
             axum::routing::post(kash_server::categories::create_category)
                 .get(kash_server::categories::get_categories),
         )
+        .route(
+            "/categories/budget-status",
+            axum::routing::get(kash_server::categories::get_budget_status),
+        )
+        .route(
+            "/categories/merge",
+            axum::routing::post(kash_server::categories::merge_categories),
+        )
         .route(
             "/categories/{id}",
             axum::routing::put(kash_server::categories::update_category)
                 .delete(kash_server::categories::delete_category),
         )
+        .route(
+            "/categories/{id}/convert",
+            axum::routing::post(kash_server::categories::convert_category),
+        )
+        .route(
+            "/categories/{id}/activity",
+            axum::routing::get(kash_server::categories::get_category_activity),
+        )
         .route(
             "/friends/request",
             axum::routing::post(kash_server::friends::send_friend_request),
@@ -110,10 +295,55 @@ pub async fn setup_test_app() -> anyhow::Result<TestApp> {
             "/friends/accept",
             axum::routing::post(kash_server::friends::accept_friend),
         )
+        .route(
+            "/friends/decline",
+            axum::routing::post(kash_server::friends::decline_friend),
+        )
+        .route(
+            "/friends/cancel",
+            axum::routing::post(kash_server::friends::cancel_friend_request),
+        )
         .route(
             "/friends/remove",
             axum::routing::post(kash_server::friends::remove_friend),
         )
+        .route(
+            "/friends/history/{friend_id}",
+            axum::routing::delete(kash_server::friends::purge_friend_history),
+        )
+        .route(
+            "/whats-new",
+            axum::routing::get(kash_server::whats_new::get_whats_new),
+        )
+        .route(
+            "/notifications",
+            axum::routing::get(kash_server::notifications::get_notifications),
+        )
+        .route(
+            "/notifications/{id}/read",
+            axum::routing::post(kash_server::notifications::mark_notification_read),
+        )
+        .route(
+            "/friends/balances",
+            axum::routing::get(kash_server::friends::get_friend_balances),
+        )
+        .route(
+            "/friends/{id}",
+            axum::routing::get(kash_server::friends::get_friend_profile),
+        )
+        .route(
+            "/friends/{id}/activity",
+            axum::routing::get(kash_server::friends::get_friend_activity),
+        )
+        .route(
+            "/friends/{id}/settle-up",
+            axum::routing::get(kash_server::splits::get_settle_up)
+                .post(kash_server::splits::execute_settle_up),
+        )
+        .route(
+            "/splits",
+            axum::routing::get(kash_server::splits::list_splits),
+        )
         .route(
             "/splits/create",
             axum::routing::post(kash_server::splits::create_split),
@@ -122,6 +352,18 @@ pub async fn setup_test_app() -> anyhow::Result<TestApp> {
             "/splits/pending",
             axum::routing::get(kash_server::splits::list_pending_splits),
         )
+        .route(
+            "/splits/{id}",
+            axum::routing::get(kash_server::splits::get_split),
+        )
+        .route(
+            "/splits/{id}/cancel",
+            axum::routing::post(kash_server::splits::cancel_split),
+        )
+        .route(
+            "/splits/{id}/settle-all",
+            axum::routing::post(kash_server::splits::settle_split),
+        )
         .route(
             "/splits/unsettled",
             axum::routing::get(kash_server::splits::list_unsettled_splits_with_friend),
@@ -129,13 +371,17 @@ pub async fn setup_test_app() -> anyhow::Result<TestApp> {
         .route(
             "/splits/unsettled/{friend_id}/settle_all",
             axum::routing::put(kash_server::splits::settle_all_unsettled_splits_with_friend),
-        )
+        );
+    let router = kash_server::body_limit::layer(router, BodyLimits::default());
+    let router = kash_server::api_tokens::layer(router, app_state.main_db.clone())
         .layer(session_layer)
         .with_state(app_state.clone());
+    let router = kash_server::request_log::layer(router);
 
     Ok(TestApp {
         router,
         state: app_state,
+        data_path,
     })
 }
 
@@ -143,34 +389,17 @@ async fn root_handler(_session: tower_sessions::Session) -> axum::response::Html
     axum::response::Html("<h1>Test Server</h1>".to_string())
 }
 
+#[allow(dead_code)]
 pub async fn create_test_user(
     app_state: &AppState,
     username: &str,
     password: &str,
 ) -> anyhow::Result<String> {
-    use argon2::{
-        Argon2,
-        password_hash::{PasswordHasher, SaltString},
-    };
-    use password_hash::rand_core::OsRng;
-
-    let salt = SaltString::generate(&mut OsRng);
-    let hash = Argon2::default()
-        .hash_password(password.as_bytes(), &salt)
-        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?
-        .to_string();
-
-    let user_id = Uuid::new_v4().to_string();
-
-    let conn = app_state.main_db.write().await;
-    conn.execute(
-        "INSERT INTO users (id, name, password_hash) VALUES (?, ?, ?)",
-        (user_id.as_str(), username, hash.as_str()),
-    )
-    .await
-    .map_err(|e| anyhow::anyhow!("Failed to create test user: {}", e))?;
+    let user = auth::create_user(&app_state.main_db, username, password)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to create test user: {}", e))?;
 
-    Ok(user_id)
+    Ok(user.id)
 }
 
 pub async fn login_user(app: &Router, username: &str, password: &str) -> anyhow::Result<String> {
@@ -230,3 +459,50 @@ pub async fn auth_request(
 
     Ok((status, body_str))
 }
+
+/// Sends `method uri` with the session `cookie` and, if given, `payload` as JSON.
+#[allow(dead_code)]
+pub async fn send_request(
+    app: &TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> Response {
+    let builder = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("cookie", cookie);
+    let request = match payload {
+        Some(payload) => builder
+            .header("content-type", "application/json")
+            .body(Body::from(payload.to_string())),
+        None => builder.body(Body::empty()),
+    }
+    .expect("build request");
+    app.router
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("execute request")
+}
+
+/// Like `send_request`, returning the status and the body as JSON; a body that is not
+/// JSON comes back as a string.
+#[allow(dead_code)]
+pub async fn send_json(
+    app: &TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> (StatusCode, Value) {
+    let response = send_request(app, method, uri, cookie, payload).await;
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("read body");
+    let body = serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+    (status, body)
+}
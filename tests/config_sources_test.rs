@@ -0,0 +1,192 @@
+/// Tests Z341-Z343: Config file and secret files
+///
+/// Settings come from the environment, then `kash.toml` (or the file in `KASH_CONFIG`),
+/// then defaults. File keys are the variable names lowercased; lists become the
+/// comma-separated form. Secrets may be read from a `_FILE` path, and a bad value is
+/// reported with the key and the source it came from.
+use kash_server::config::{CliConfig, Config, ConfigError};
+use kash_server::config_sources::ConfigSources;
+use std::path::Path;
+
+// ---- Helpers ----
+
+const SECRET: &str = "a_session_secret_long_enough_for_the_sixty_four_character_minimum__";
+
+fn env(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn load(pairs: &[(&str, &str)], file: Option<&str>) -> Result<Config, ConfigError> {
+    let sources = ConfigSources::new(env(pairs), file.map(|f| (Path::new("kash.toml"), f)))?;
+    Config::from_sources(&sources)
+}
+
+// ---------------------------------------------------------------------------
+// Z341: The environment overrides the file, which overrides the defaults
+// ---------------------------------------------------------------------------
+
+#[test]
+fn z341_environment_over_file_over_defaults() {
+    let file = r#"
+        server_port = 9000
+        database_path = "/srv/kash"
+        session_secret = "a_session_secret_long_enough_for_the_sixty_four_character_minimum__"
+        frontend_origins = [
+            "https://kash.example",
+            "https://admin.kash.example",
+        ]
+        default_expense_categories = ["Food", "Rent"]
+        startup_self_test = false
+        backup_retention_days = 30
+    "#;
+    let config = load(
+        &[("SERVER_PORT", "9100"), ("BACKUP_RETENTION_DAYS", "7")],
+        Some(file),
+    )
+    .unwrap();
+
+    assert_eq!(config.port, "9100", "environment wins");
+    assert_eq!(config.backup.retention_days, 7);
+    assert_eq!(config.data_path, "/srv/kash", "file beats the default");
+    assert_eq!(config.session_secret, SECRET);
+    assert_eq!(
+        config.frontend_origins,
+        vec!["https://kash.example", "https://admin.kash.example"]
+    );
+    assert_eq!(config.default_categories.expense, vec!["Food", "Rent"]);
+    assert!(!config.startup_self_test);
+    assert_eq!(config.host, "0.0.0.0", "neither sets it");
+    assert!(
+        config
+            .default_categories
+            .income
+            .contains(&"Salary".to_string())
+    );
+
+    // The operator CLI reads the same file
+    let sources = ConfigSources::new(env(&[]), Some((Path::new("kash.toml"), file))).unwrap();
+    assert_eq!(
+        CliConfig::from_sources(&sources).unwrap().data_path,
+        "/srv/kash"
+    );
+
+    // No file at all keeps the old environment-only behavior
+    let config = load(&[("SESSION_SECRET", SECRET)], None).unwrap();
+    assert_eq!(config.port, "3000");
+    assert_eq!(config.data_path, "data");
+}
+
+// ---------------------------------------------------------------------------
+// Z342: Secrets can be read from `_FILE` paths in either source
+// ---------------------------------------------------------------------------
+
+#[test]
+fn z342_secrets_from_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let secret_path = dir.path().join("session_secret");
+    std::fs::write(&secret_path, format!("{SECRET}\n")).unwrap();
+    let token_path = dir.path().join("admin_token");
+    std::fs::write(&token_path, "an_admin_token_of_at_least_32_characters").unwrap();
+    let secret_path = secret_path.to_string_lossy().to_string();
+    let token_path = token_path.to_string_lossy().to_string();
+
+    let file = format!("admin_token_file = {:?}\n", token_path);
+    let config = load(&[("SESSION_SECRET_FILE", &secret_path)], Some(&file)).unwrap();
+    assert_eq!(config.session_secret, SECRET, "trailing newline dropped");
+    assert_eq!(
+        config.admin_token.as_deref(),
+        Some("an_admin_token_of_at_least_32_characters")
+    );
+
+    // The environment still overrides a secret file named in the config file
+    let config = load(
+        &[
+            ("SESSION_SECRET", SECRET),
+            ("ADMIN_TOKEN", "another_admin_token_of_at_least_32_chars"),
+        ],
+        Some(&file),
+    )
+    .unwrap();
+    assert_eq!(
+        config.admin_token.as_deref(),
+        Some("another_admin_token_of_at_least_32_chars")
+    );
+
+    let err = load(
+        &[
+            ("SESSION_SECRET", SECRET),
+            ("SESSION_SECRET_FILE", &secret_path),
+        ],
+        None,
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string().contains("SESSION_SECRET is set both"),
+        "{err}"
+    );
+
+    let missing = dir.path().join("missing").to_string_lossy().to_string();
+    let err = load(&[("SESSION_SECRET_FILE", &missing)], None).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains(&missing), "{message}");
+    assert!(message.contains("SESSION_SECRET_FILE"), "{message}");
+}
+
+// ---------------------------------------------------------------------------
+// Z343: Errors name the key and the source that supplied it
+// ---------------------------------------------------------------------------
+
+#[test]
+fn z343_errors_name_key_and_source() {
+    let err = load(
+        &[("SESSION_SECRET", SECRET)],
+        Some("backup_retention_days = 0"),
+    )
+    .unwrap_err();
+    let message = err.to_string();
+    assert!(
+        message.contains("key `backup_retention_days` in kash.toml"),
+        "{message}"
+    );
+    assert!(message.contains("BACKUP_RETENTION_DAYS"), "{message}");
+
+    let err = load(
+        &[("SESSION_SECRET", SECRET), ("SERVER_PORT", "http")],
+        Some("server_port = 8080"),
+    )
+    .unwrap_err();
+    let ConfigError::InSource { sources, error } = err else {
+        panic!("expected the source to be named");
+    };
+    assert_eq!(sources, vec!["environment variable SERVER_PORT"]);
+    assert!(matches!(*error, ConfigError::InvalidPort(_)));
+
+    let err = load(&[], Some("session_secret = \"short\"")).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("key `session_secret` in kash.toml"),
+        "{err}"
+    );
+
+    let err = load(&[("SESSION_SECRET", SECRET)], Some("sever_port = 8080")).unwrap_err();
+    assert!(
+        matches!(&err, ConfigError::InvalidConfigFile { message, .. } if message.contains("sever_port")),
+        "{err}"
+    );
+
+    let err = load(&[("SESSION_SECRET", SECRET)], Some("server_port = ")).unwrap_err();
+    assert!(
+        matches!(err, ConfigError::InvalidConfigFile { .. }),
+        "{err}"
+    );
+
+    let err = load(
+        &[("SESSION_SECRET", SECRET)],
+        Some("[database]\nurl = \"libsql://x\""),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("unknown key `database`"), "{err}");
+}
@@ -0,0 +1,109 @@
+/// Tests Z211-Z213: Allowed CORS origins
+///
+/// `FRONTEND_ORIGINS` is a comma-separated list parsed by
+/// `config::parse_frontend_origins`; the old single `FRONTEND_ORIGIN` adds to it.
+/// An empty list keeps the localhost default, and a bad entry fails startup with
+/// the offending origin in the error.
+use axum::{
+    Router,
+    body::Body,
+    http::{HeaderValue, Request, header},
+    routing::get,
+};
+use kash_server::config::{ConfigError, parse_frontend_origins};
+use kash_server::constants::DEFAULT_FRONTEND_ORIGIN;
+use tower::util::ServiceExt;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+// ---- Helpers ----
+
+async fn allowed_origin(origins: Vec<HeaderValue>, origin: &str) -> Option<HeaderValue> {
+    let app = Router::new().route("/", get(|| async { "ok" })).layer(
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_credentials(true),
+    );
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/")
+                .header(header::ORIGIN, origin)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    response
+        .headers()
+        .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        .cloned()
+}
+
+// ---------------------------------------------------------------------------
+// Z211: Every listed origin is allowed; others are not
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z211_each_listed_origin_is_allowed() {
+    let origins = parse_frontend_origins(
+        " https://kash.example.com, https://staging.kash.example.com ,,http://localhost:8080,https://kash.example.com",
+    )
+    .expect("valid list");
+    assert_eq!(
+        origins,
+        vec![
+            HeaderValue::from_static("https://kash.example.com"),
+            HeaderValue::from_static("https://staging.kash.example.com"),
+            HeaderValue::from_static("http://localhost:8080"),
+        ]
+    );
+
+    for origin in ["https://staging.kash.example.com", "http://localhost:8080"] {
+        assert_eq!(
+            allowed_origin(origins.clone(), origin).await,
+            Some(HeaderValue::from_str(origin).unwrap()),
+            "{origin}"
+        );
+    }
+    assert_eq!(
+        allowed_origin(origins, "https://evil.example.com").await,
+        None
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z212: An empty list keeps the localhost default
+// ---------------------------------------------------------------------------
+
+#[test]
+fn z212_empty_list_uses_default() {
+    for value in ["", " , ,"] {
+        assert_eq!(
+            parse_frontend_origins(value).expect("empty list"),
+            vec![HeaderValue::from_static(DEFAULT_FRONTEND_ORIGIN)],
+            "{value:?}"
+        );
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Z213: A bad origin is named in the error; `*` is refused
+// ---------------------------------------------------------------------------
+
+#[test]
+fn z213_invalid_origin_is_named() {
+    for (value, offending) in [
+        (
+            "https://kash.example.com,https://bad\u{7f}.example.com",
+            "https://bad\u{7f}.example.com",
+        ),
+        ("http://localhost:8080, *", "*"),
+    ] {
+        match parse_frontend_origins(value) {
+            Err(error @ ConfigError::InvalidFrontendOrigin(_)) => {
+                assert!(error.to_string().contains(offending), "{error}");
+            }
+            other => panic!("expected InvalidFrontendOrigin for {value:?}, got {other:?}"),
+        }
+    }
+}
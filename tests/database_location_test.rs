@@ -0,0 +1,112 @@
+/// Tests Z311-Z313: Choosing a local file or a remote libsql database
+///
+/// `DATABASE_URL` moves the single shared database to a libsql server such as Turso;
+/// it needs `DATABASE_AUTH_TOKEN`, and `DATABASE_REPLICA_SYNC_SECS` keeps an embedded
+/// replica under `DATABASE_PATH`. Without a URL everything stays in `users.db`.
+use kash_server::config::{ConfigError, DatabaseLocation};
+use kash_server::database;
+
+// ---------------------------------------------------------------------------
+// Z311: The URL, token and sync interval pick the mode; a URL needs its token
+// ---------------------------------------------------------------------------
+
+#[test]
+fn z311_location_from_settings() {
+    assert_eq!(
+        DatabaseLocation::new(None, Some("ignored"), None).unwrap(),
+        DatabaseLocation::Local
+    );
+    assert_eq!(
+        DatabaseLocation::new(Some("  "), None, None).unwrap(),
+        DatabaseLocation::Local
+    );
+    assert_eq!(
+        DatabaseLocation::new(Some("libsql://kash.turso.io"), Some("token"), None).unwrap(),
+        DatabaseLocation::Remote {
+            url: "libsql://kash.turso.io".to_string(),
+            auth_token: "token".to_string(),
+        }
+    );
+    assert_eq!(
+        DatabaseLocation::new(Some("libsql://kash.turso.io"), Some("token"), Some("30")).unwrap(),
+        DatabaseLocation::Replica {
+            url: "libsql://kash.turso.io".to_string(),
+            auth_token: "token".to_string(),
+            sync_interval_secs: 30,
+        }
+    );
+
+    for token in [None, Some(""), Some("  ")] {
+        let error = DatabaseLocation::new(Some("libsql://kash.turso.io"), token, None)
+            .expect_err("a URL without a token");
+        assert!(
+            matches!(error, ConfigError::MissingDatabaseAuthToken),
+            "{error:?}"
+        );
+        assert!(error.to_string().contains("DATABASE_AUTH_TOKEN"), "{error}");
+    }
+
+    let error = DatabaseLocation::new(Some("postgres://kash"), Some("token"), None)
+        .expect_err("not a libsql URL");
+    assert!(
+        matches!(error, ConfigError::InvalidDatabaseUrl(_)),
+        "{error:?}"
+    );
+
+    for sync in ["0", "soon"] {
+        let error =
+            DatabaseLocation::new(Some("libsql://kash.turso.io"), Some("token"), Some(sync))
+                .expect_err("bad sync interval");
+        assert!(
+            matches!(error, ConfigError::InvalidDatabaseReplicaSync(_)),
+            "{error:?}"
+        );
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Z312: A local location is the data directory's users.db
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z312_local_location_uses_data_dir() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let data_path = temp_dir.path().join("data").to_string_lossy().to_string();
+
+    assert!(
+        database::open_main_db_at(&data_path, &DatabaseLocation::Local)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let db = database::init_main_db_at(&data_path, &DatabaseLocation::Local)
+        .await
+        .expect("init");
+    assert!(database::main_db_path(&data_path).exists());
+    let conn = db.read().await;
+    assert_eq!(
+        database::applied_schema_version(&conn).await.unwrap(),
+        database::latest_schema_version()
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z313: An unreachable remote database fails startup and leaves no local file
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z313_unreachable_remote_fails_startup() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let data_path = temp_dir.path().to_string_lossy().to_string();
+    let location = DatabaseLocation::Remote {
+        url: "http://127.0.0.1:1".to_string(),
+        auth_token: "token".to_string(),
+    };
+
+    assert!(
+        database::init_main_db_at(&data_path, &location)
+            .await
+            .is_err()
+    );
+    assert!(!database::main_db_path(&data_path).exists());
+}
@@ -0,0 +1,166 @@
+/// Tests Z301-Z302: Writes while another process holds the database
+///
+/// The bot and the operator CLI open `users.db` on their own connections. Every
+/// connection waits `busy_timeout` for the lock, and `with_transaction` retries a busy
+/// `BEGIN IMMEDIATE` with backoff; only when that budget runs out does a write answer
+/// 503 `database_busy` instead of a 500.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{ScenarioBuilder, money};
+use kash_server::constants::*;
+use kash_server::database;
+use kash_server::error::ApiError;
+use kash_server::models::CreateRecordPayload;
+use kash_server::records;
+use libsql::Connection;
+use serde_json::json;
+use std::time::Duration;
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+/// A second connection to the test app's database, as the bot would open it.
+async fn other_process(app: &common::TestApp) -> Connection {
+    database::open_main_db(&app.data_path)
+        .await
+        .unwrap()
+        .expect("database exists")
+}
+
+fn lunch(category_id: &str) -> CreateRecordPayload {
+    CreateRecordPayload {
+        name: "Lunch".to_string(),
+        amount: money(-8.0),
+        currency: None,
+        category_id: category_id.to_string(),
+        date: "2026-03-02".to_string(),
+        original_amount: None,
+        original_currency: None,
+        note: None,
+        tags: Vec::new(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Z301: Concurrent creates next to another writer never answer 500
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z301_concurrent_creates_survive_another_writer() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .user("alice_z301")
+        .category("alice_z301", "Dining")
+        .build(&app)
+        .await;
+    let alice = scenario.id("alice_z301").to_string();
+    let category = scenario.category_id("alice_z301", "Dining").to_string();
+
+    let other = other_process(&app).await;
+    let writer = {
+        let alice = alice.clone();
+        tokio::spawn(async move {
+            for i in 0..30 {
+                other.execute("BEGIN IMMEDIATE", ()).await.unwrap();
+                other
+                    .execute(
+                        "UPDATE users SET last_seen_whats_new = ? WHERE id = ?",
+                        libsql::params![format!("z301-{i}"), alice.as_str()],
+                    )
+                    .await
+                    .unwrap();
+                tokio::time::sleep(Duration::from_millis(3)).await;
+                other.execute("COMMIT", ()).await.unwrap();
+            }
+        })
+    };
+
+    let mut requests = Vec::new();
+    for i in 0..40 {
+        let router = app.router.clone();
+        let cookie = scenario.cookie("alice_z301").to_string();
+        let payload = json!({
+            "name": format!("Lunch {i}"),
+            "amount": -8.0,
+            "category_id": category,
+            "date": "2026-03-02",
+        });
+        requests.push(tokio::spawn(async move {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/records")
+                .header("cookie", cookie)
+                .header("content-type", "application/json")
+                .body(Body::from(payload.to_string()))
+                .unwrap();
+            router.oneshot(request).await.unwrap().status()
+        }));
+    }
+
+    let mut statuses = Vec::new();
+    for request in requests {
+        statuses.push(request.await.unwrap());
+    }
+    writer.await.unwrap();
+    assert!(
+        statuses.iter().all(|status| *status == StatusCode::CREATED),
+        "{statuses:?}"
+    );
+
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM records WHERE owner_user_id = ?",
+            [alice.as_str()],
+        )
+        .await
+        .unwrap();
+    let count: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+    assert_eq!(count, 40);
+}
+
+// ---------------------------------------------------------------------------
+// Z302: A lock held past every retry is 503 database_busy, then writes resume
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z302_exhausted_retries_are_503() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .user("alice_z302")
+        .category("alice_z302", "Dining")
+        .build(&app)
+        .await;
+    let alice = scenario.id("alice_z302");
+    let category = scenario.category_id("alice_z302", "Dining");
+
+    let other = other_process(&app).await;
+    other.execute("BEGIN IMMEDIATE", ()).await.unwrap();
+    {
+        // Keep the test short: the app's own wait before each retry
+        let conn = app.state.main_db.read().await;
+        let mut rows = conn.query("PRAGMA busy_timeout = 10", ()).await.unwrap();
+        while rows.next().await.unwrap().is_some() {}
+    }
+
+    let error =
+        records::create_record_for_user(&app.state.main_db, alice, lunch(category), None, false)
+            .await
+            .expect_err("the database is held by another connection");
+    let error = ApiError::from(error);
+    assert_eq!(error.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(error.code, ERROR_CODE_DATABASE_BUSY);
+
+    other.execute("COMMIT", ()).await.unwrap();
+    records::create_record_for_user(&app.state.main_db, alice, lunch(category), None, false)
+        .await
+        .expect("the lock is released");
+    assert!(database::is_busy_error(&libsql::Error::SqliteFailure(
+        517,
+        "database is locked".to_string()
+    )));
+}
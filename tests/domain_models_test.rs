@@ -1,5 +1,6 @@
 use kash_server::constants::*;
 use kash_server::models::*;
+use kash_server::money::Money;
 #[test]
 fn serde_send_friend_request_payload() {
     let json = r#"{"friend_username":"alice"}"#;
@@ -46,8 +47,11 @@ fn serde_friendship_relation_roundtrip() {
     let relation = FriendshipRelation {
         id: "rel-001".to_string(),
         user_id: "user-123".to_string(),
+        username: "bestie".to_string(),
         pending: false,
         nickname: "Best Friend".to_string(),
+        message: None,
+        direction: None,
     };
     let json = serde_json::to_string(&relation).unwrap();
     let deserialized: FriendshipRelation = serde_json::from_str(&json).unwrap();
@@ -71,7 +75,7 @@ fn serde_split_participant() {
     let json = r#"{"user_id":"user-123","amount":50.0}"#;
     let participant: SplitParticipant = serde_json::from_str(json).unwrap();
     assert_eq!(participant.user_id, "user-123");
-    assert_eq!(participant.amount, 50.0);
+    assert_eq!(participant.amount, Money::from_cents(5000));
 }
 
 #[test]
@@ -90,12 +94,12 @@ fn serde_create_split_payload() {
     }"#;
     let payload: CreateSplitPayload = serde_json::from_str(json).unwrap();
     assert_eq!(payload.idempotency_key, "idempotency-123");
-    assert_eq!(payload.total_amount, 120.0);
+    assert_eq!(payload.total_amount, Money::from_cents(12000));
     assert_eq!(payload.description, "Dinner with friends");
     assert_eq!(payload.date, "2025-02-16");
     assert_eq!(payload.category_id, "cat-dining");
     assert_eq!(payload.splits.len(), 3);
-    assert_eq!(payload.splits[0].amount, 40.0);
+    assert_eq!(payload.splits[0].amount, Money::from_cents(4000));
 }
 
 #[test]
@@ -118,7 +122,7 @@ fn serde_split_record_roundtrip() {
     let record = SplitRecord {
         id: "split-001".to_string(),
         payer_id: "user-123".to_string(),
-        total_amount: 150.0,
+        total_amount: Money::from_cents(15000),
         description: "Lunch split".to_string(),
         date: "2025-02-16".to_string(),
         status: SPLIT_STATUS_INITIATED.to_string(),
@@ -129,6 +133,6 @@ fn serde_split_record_roundtrip() {
     let deserialized: SplitRecord = serde_json::from_str(&json).unwrap();
     assert_eq!(deserialized.id, "split-001");
     assert_eq!(deserialized.payer_id, "user-123");
-    assert_eq!(deserialized.total_amount, 150.0);
+    assert_eq!(deserialized.total_amount, Money::from_cents(15000));
     assert_eq!(deserialized.status, SPLIT_STATUS_INITIATED);
 }
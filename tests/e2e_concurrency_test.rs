@@ -7,6 +7,7 @@ use axum::{
     body::{Body, to_bytes},
     http::{Request, StatusCode},
 };
+use kash_server::constants::ERROR_CODE_RECORD_ALREADY_FINALIZED;
 use serde_json::{Value, json};
 use tower::util::ServiceExt;
 
@@ -172,13 +173,13 @@ async fn test_concurrent_split_creation_idempotency_e2e_concurrency() {
         let pending_count: i64 = row.get(0).expect("pending count");
         let min_pending: Option<bool> = row.get(1).expect("min pending");
         let max_pending: Option<bool> = row.get(2).expect("max pending");
-        let min_amount: Option<f64> = row.get(3).expect("min amount");
-        let max_amount: Option<f64> = row.get(4).expect("max amount");
+        let min_amount: Option<i64> = row.get(3).expect("min amount");
+        let max_amount: Option<i64> = row.get(4).expect("max amount");
         assert_eq!(pending_count, 1);
         assert_eq!(min_pending, Some(true));
         assert_eq!(max_pending, Some(true));
-        assert_eq!(min_amount, Some(-40.0));
-        assert_eq!(max_amount, Some(-40.0));
+        assert_eq!(min_amount, Some(-4000));
+        assert_eq!(max_amount, Some(-4000));
     }
 
     if !created_bodies.is_empty() {
@@ -303,11 +304,9 @@ async fn test_concurrent_finalization_race_safety_e2e_concurrency() {
     )
     .await;
     assert_eq!(post_race_status, StatusCode::CONFLICT);
-    assert!(
-        post_race_body
-            .as_str()
-            .expect("post-race conflict body string")
-            .contains("finalized")
+    assert_eq!(
+        post_race_body["code"], ERROR_CODE_RECORD_ALREADY_FINALIZED,
+        "{post_race_body}"
     );
 
     {
@@ -326,12 +325,12 @@ async fn test_concurrent_finalization_race_safety_e2e_concurrency() {
             .expect("finalized row exists");
         let pending: bool = row.get(0).expect("pending");
         let category_id: Option<String> = row.get(1).expect("category id");
-        let amount: f64 = row.get(2).expect("amount");
+        let amount: i64 = row.get(2).expect("amount");
         let debtor_user_id: Option<String> = row.get(3).expect("debtor id");
         let creditor_user_id: Option<String> = row.get(4).expect("creditor id");
         assert!(!pending);
         assert_eq!(category_id, Some(bob_category_id));
-        assert_eq!(amount, -35.0);
+        assert_eq!(amount, -3500);
         assert_eq!(debtor_user_id, Some(bob_id));
         assert_eq!(creditor_user_id, Some(alice_id));
     }
@@ -0,0 +1,161 @@
+/// Tests E1-E3: Edit targets deleted before the edit applies
+///
+/// The bot's `edit_record` tool resolves the record and the new category, then
+/// takes the write lock to apply the change. `records::guard_edit_references`
+/// re-checks both under that lock, so a record or category deleted from the web
+/// UI in between yields a 409 naming what disappeared instead of a generic error
+/// or an edit pointing at a missing category.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::constants::REFERENCE_DELETED_PREFIX;
+use kash_server::models::{CreateRecordPayload, Record};
+use kash_server::records;
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send_delete(app: &common::TestApp, uri: &str, cookie: &str) -> StatusCode {
+    let request = Request::builder()
+        .uri(uri)
+        .method("DELETE")
+        .header("cookie", cookie)
+        .body(Body::empty())
+        .unwrap();
+    app.router.clone().oneshot(request).await.unwrap().status()
+}
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice])
+        .category(&alice, "Groceries")
+        .category(&alice, "Dining")
+        .build(app)
+        .await
+}
+
+async fn create_lunch(app: &common::TestApp, scenario: &Scenario, user: &str) -> Record {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id(user),
+        CreateRecordPayload {
+            name: "Lunch".to_string(),
+            amount: money(12.0),
+            currency: None,
+            category_id: scenario.category_id(user, "Groceries").to_string(),
+            date: "2025-03-10".to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record")
+}
+
+// ---------------------------------------------------------------------------
+// E1: The new category is deleted between resolving it and applying the edit
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn e1_deleted_target_category_is_named() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "e1").await;
+    let alice = scenario.id("alice_e1");
+    let record = create_lunch(&app, &scenario, "alice_e1").await;
+    let dining = scenario.category_id("alice_e1", "Dining").to_string();
+
+    let status = send_delete(
+        &app,
+        &format!("/categories/{dining}"),
+        scenario.cookie("alice_e1"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let conn = app.state.main_db.write().await;
+    let (status, message) =
+        records::guard_edit_references(&conn, alice, &record, Some((&dining, "Dining")))
+            .await
+            .expect_err("category is gone");
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        message,
+        format!("{REFERENCE_DELETED_PREFIX}: category \"Dining\" was deleted")
+    );
+
+    // Retrying with a category that still exists goes through.
+    let groceries = scenario.category_id("alice_e1", "Groceries");
+    records::guard_edit_references(&conn, alice, &record, Some((groceries, "Groceries")))
+        .await
+        .expect("category exists");
+}
+
+// ---------------------------------------------------------------------------
+// E2: The record itself is deleted before the edit applies
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn e2_deleted_record_is_named() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "e2").await;
+    let alice = scenario.id("alice_e2");
+    let record = create_lunch(&app, &scenario, "alice_e2").await;
+
+    let status = send_delete(
+        &app,
+        &format!("/records/{}", record.id),
+        scenario.cookie("alice_e2"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let conn = app.state.main_db.write().await;
+    let (status, message) = records::guard_edit_references(&conn, alice, &record, None)
+        .await
+        .expect_err("record is gone");
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        message,
+        format!("{REFERENCE_DELETED_PREFIX}: record \"Lunch\" was deleted")
+    );
+}
+
+// ---------------------------------------------------------------------------
+// E3: Another user's category never counts as existing
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn e3_foreign_category_is_treated_as_missing() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_e3", "bob_e3"])
+        .category("alice_e3", "Groceries")
+        .category("bob_e3", "Dining")
+        .build(&app)
+        .await;
+    let record = create_lunch(&app, &scenario, "alice_e3").await;
+    let bobs_dining = scenario.category_id("bob_e3", "Dining");
+
+    let conn = app.state.main_db.write().await;
+    records::guard_edit_references(&conn, scenario.id("alice_e3"), &record, None)
+        .await
+        .expect("record and current category exist");
+    let (status, _) = records::guard_edit_references(
+        &conn,
+        scenario.id("alice_e3"),
+        &record,
+        Some((bobs_dining, "Dining")),
+    )
+    .await
+    .expect_err("not alice's category");
+    assert_eq!(status, StatusCode::CONFLICT);
+}
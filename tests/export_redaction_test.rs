@@ -0,0 +1,268 @@
+/// Tests E1-E4: Privacy redaction in data exports
+///
+/// `GET /export?redact=friends` replaces every friend and split counterparty
+/// with a `friend-N` pseudonym, `redact=notes` drops free-text notes, and
+/// `categories_only=true` swaps individual records for monthly category totals.
+/// The applied options are recorded in `schema_version`.
+mod common;
+
+use axum::http::StatusCode;
+use common::send_json;
+use kash_server::export::{self, RedactionIdentity};
+use serde_json::{Value, json};
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+async fn setup_user(app: &common::TestApp, username: &str) -> (String, String) {
+    let user_id = common::create_test_user(&app.state, username, "pw")
+        .await
+        .expect("create user");
+    let cookie = common::login_user(&app.router, username, "pw")
+        .await
+        .expect("login user");
+    (user_id, cookie)
+}
+
+async fn create_category(app: &common::TestApp, cookie: &str, name: &str) -> String {
+    let (status, body) = send_json(
+        app,
+        "POST",
+        "/categories",
+        cookie,
+        Some(json!({ "name": name, "is_income": false })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create category {name}");
+    body["id"].as_str().expect("category id").to_string()
+}
+
+async fn create_record(
+    app: &common::TestApp,
+    cookie: &str,
+    name: &str,
+    amount: f64,
+    category_id: &str,
+    date: &str,
+) {
+    let (status, body) = send_json(
+        app,
+        "POST",
+        "/records",
+        cookie,
+        Some(json!({ "name": name, "amount": amount, "category_id": category_id, "date": date })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create record {name}: {body}");
+}
+
+async fn make_friends(
+    app: &common::TestApp,
+    cookie: &str,
+    friend_cookie: &str,
+    user_id: &str,
+    friend_username: &str,
+) {
+    let (status, _) = send_json(
+        app,
+        "POST",
+        "/friends/request",
+        cookie,
+        Some(json!({ "friend_username": friend_username })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = send_json(
+        app,
+        "POST",
+        "/friends/accept",
+        friend_cookie,
+        Some(json!({ "friend_id": user_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+fn collect_strings<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+    match value {
+        Value::String(text) => out.push(text),
+        Value::Array(items) => items.iter().for_each(|item| collect_strings(item, out)),
+        Value::Object(map) => map.iter().for_each(|(key, item)| {
+            out.push(key);
+            collect_strings(item, out);
+        }),
+        _ => {}
+    }
+}
+
+fn assert_not_leaked(archive: &Value, needle: &str) {
+    let mut strings = Vec::new();
+    collect_strings(archive, &mut strings);
+    assert!(
+        strings.iter().all(|text| !text.contains(needle)),
+        "{needle} leaked into redacted export: {archive}"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// E1: Pseudonyms are consistent within one archive and ids are scrubbed from text
+// ---------------------------------------------------------------------------
+
+#[test]
+fn e1_pseudonyms_are_consistent_and_scrub_embedded_ids() {
+    let identities = vec![
+        RedactionIdentity {
+            user_id: "user-bob".to_string(),
+            aliases: vec!["bob".to_string(), "Bobby".to_string()],
+        },
+        RedactionIdentity {
+            user_id: "user-carol".to_string(),
+            aliases: vec!["carol".to_string()],
+        },
+    ];
+    let mut archive = json!({
+        "friends": [
+            { "user_id": "user-carol", "username": "carol", "nickname": null },
+            { "user_id": "user-bob", "username": "bob", "nickname": "Bobby" },
+        ],
+        "records": [
+            { "debtor_user_id": "user-bob", "name": "split with user-bob" },
+        ],
+        "notes": "kept unless notes are redacted",
+    });
+
+    export::pseudonymize_users(&mut archive, &identities);
+
+    assert_eq!(archive["friends"][0]["user_id"], "friend-1");
+    assert_eq!(archive["friends"][0]["username"], "friend-1");
+    assert_eq!(archive["friends"][1]["user_id"], "friend-2");
+    assert_eq!(archive["friends"][1]["nickname"], "friend-2");
+    assert_eq!(archive["records"][0]["debtor_user_id"], "friend-2");
+    assert_eq!(archive["records"][0]["name"], "split with friend-2");
+    assert_eq!(archive["notes"], "kept unless notes are redacted");
+
+    export::drop_field(&mut archive, "notes");
+    assert!(archive.get("notes").is_none());
+}
+
+// ---------------------------------------------------------------------------
+// E2: redact=friends hides friends and split counterparties everywhere
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn e2_redact_friends_hides_every_counterparty() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (alice_id, alice_cookie) = setup_user(&app, "alice_e2").await;
+    let (bob_id, bob_cookie) = setup_user(&app, "bob_e2").await;
+    make_friends(&app, &alice_cookie, &bob_cookie, &alice_id, "bob_e2").await;
+    let (status, _) = send_json(
+        &app,
+        "PATCH",
+        "/friends/nickname",
+        &bob_cookie,
+        Some(json!({ "friend_id": alice_id, "nickname": "Ally" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let food_id = create_category(&app, &alice_cookie, "Food").await;
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/splits/create",
+        &alice_cookie,
+        Some(json!({
+            "idempotency_key": "export-e2",
+            "total_amount": 60.0,
+            "description": "Dinner",
+            "date": "2024-05-01",
+            "category_id": food_id,
+            "splits": [{ "user_id": bob_id, "amount": 20.0 }],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+
+    let (status, plain) = send_json(&app, "GET", "/export", &bob_cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{plain}");
+    assert_eq!(plain["schema_version"], "1");
+    assert_eq!(plain["friends"][0]["user_id"], json!(alice_id));
+    assert_eq!(plain["records"][0]["creditor_user_id"], json!(alice_id));
+
+    let (status, redacted) =
+        send_json(&app, "GET", "/export?redact=friends", &bob_cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{redacted}");
+    assert_eq!(redacted["schema_version"], "1+redact.friends");
+    assert_eq!(redacted["user"]["id"], json!(bob_id));
+    assert_eq!(redacted["friends"][0]["user_id"], "friend-1");
+    assert_eq!(redacted["friends"][0]["username"], "friend-1");
+    assert_eq!(redacted["friends"][0]["nickname"], "friend-1");
+    assert_eq!(redacted["records"][0]["creditor_user_id"], "friend-1");
+    assert_not_leaked(&redacted, &alice_id);
+    assert_not_leaked(&redacted, "alice_e2");
+    assert_not_leaked(&redacted, "Ally");
+}
+
+// ---------------------------------------------------------------------------
+// E3: categories_only exports monthly per-category totals instead of records
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn e3_categories_only_exports_monthly_totals() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (_, cookie) = setup_user(&app, "alice_e3").await;
+    let food_id = create_category(&app, &cookie, "Food").await;
+    let rent_id = create_category(&app, &cookie, "Rent").await;
+    create_record(&app, &cookie, "Lunch", -10.0, &food_id, "2024-05-01").await;
+    create_record(&app, &cookie, "Dinner", -15.5, &food_id, "2024-05-20").await;
+    create_record(&app, &cookie, "Lunch", -8.0, &food_id, "2024-06-02").await;
+    create_record(&app, &cookie, "May rent", -900.0, &rent_id, "2024-05-01").await;
+
+    let (status, body) = send_json(
+        &app,
+        "GET",
+        "/export?categories_only=true&redact=notes",
+        &cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["schema_version"], "1+redact.notes+categories_only");
+    assert!(body.get("records").is_none(), "no individual records");
+    assert_not_leaked(&body, "Lunch");
+
+    let totals = body["category_totals"].as_array().expect("totals array");
+    let summary: Vec<(&str, &str, f64, u64)> = totals
+        .iter()
+        .map(|total| {
+            (
+                total["month"].as_str().expect("month"),
+                total["category_name"].as_str().expect("category name"),
+                total["total"].as_f64().expect("total"),
+                total["record_count"].as_u64().expect("count"),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("2024-05", "Food", -25.5, 2),
+            ("2024-05", "Rent", -900.0, 1),
+            ("2024-06", "Food", -8.0, 1),
+        ]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// E4: Unknown redaction options are rejected
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn e4_unknown_redaction_is_rejected() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (_, cookie) = setup_user(&app, "alice_e4").await;
+
+    let (status, _) = send_json(&app, "GET", "/export?redact=friends,emails", &cookie, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,29 @@
+/// Tests B1-B2: Cargo feature layout
+///
+/// The default feature set builds only the API server; the `telegram-bot`
+/// feature additionally builds the `tg` binary. Cargo only exposes
+/// `CARGO_BIN_EXE_<name>` for binaries it built, so referencing it is a
+/// compile-time check that the binary is part of the build.
+
+// ---------------------------------------------------------------------------
+// B1: Default features build the server binary
+// ---------------------------------------------------------------------------
+
+#[test]
+fn b1_default_features_build_server_binary() {
+    let server = std::path::Path::new(env!("CARGO_BIN_EXE_kash-server"));
+    assert!(server.exists(), "missing {}", server.display());
+}
+
+// ---------------------------------------------------------------------------
+// B2: The telegram-bot feature builds both binaries
+// ---------------------------------------------------------------------------
+
+#[cfg(feature = "telegram-bot")]
+#[test]
+fn b2_telegram_bot_feature_builds_both_binaries() {
+    let server = std::path::Path::new(env!("CARGO_BIN_EXE_kash-server"));
+    let bot = std::path::Path::new(env!("CARGO_BIN_EXE_tg"));
+    assert!(server.exists(), "missing {}", server.display());
+    assert!(bot.exists(), "missing {}", bot.display());
+}
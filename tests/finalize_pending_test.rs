@@ -4,7 +4,10 @@ use axum::{
     body::Body,
     http::{Request, StatusCode},
 };
+use common::fixtures::ScenarioBuilder;
+use kash_server::constants::ERROR_CODE_RECORD_ALREADY_FINALIZED;
 use kash_server::models::Category;
+use kash_server::records;
 use serde_json::{Value, json};
 use tower::util::ServiceExt;
 
@@ -287,3 +290,42 @@ async fn finalize_pending_concurrent_requests_allow_only_one_success() {
     assert_eq!(success_count, 1);
     assert_eq!(conflict_count, 1);
 }
+
+#[tokio::test]
+async fn finalize_pending_for_user_conflicts_on_already_finalized_record() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_fin", "bob_fin"])
+        .friend("alice_fin", "bob_fin")
+        .category("alice_fin", "Dining")
+        .category("bob_fin", "Shared")
+        .split("alice_fin", "Dining", 60.0, &[("bob_fin", 30.0)])
+        .build(&app)
+        .await;
+    let bob = scenario.id("bob_fin");
+    let category = scenario.category_id("bob_fin", "Shared");
+    let record_id = &scenario.splits[0].pending_record_ids[0];
+
+    // Finalized behind the handler's back, as a concurrent caller would have
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "UPDATE records SET pending = 0, category_id = ? WHERE id = ?",
+            [category, record_id.as_str()],
+        )
+        .await
+        .expect("finalize directly");
+    }
+
+    let error = records::finalize_pending_for_user(&app.state.main_db, bob, record_id, category)
+        .await
+        .expect_err("an already finalized record must not finalize again");
+    assert_eq!(error.status, StatusCode::CONFLICT);
+    assert_eq!(error.code, ERROR_CODE_RECORD_ALREADY_FINALIZED);
+
+    let error =
+        records::finalize_pending_for_user(&app.state.main_db, bob, "missing-record", category)
+            .await
+            .expect_err("a missing record is not found");
+    assert_eq!(error.status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,251 @@
+/// Tests Z151-Z154: Net balances with friends
+///
+/// `GET /friends/balances` lists every accepted friend with what they owe the caller
+/// and what the caller owes them over unsettled split shares, pending ones included,
+/// and the `net` of the two, per currency. Settled and trashed shares no longer count.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder};
+use kash_server::constants::DEFAULT_CURRENCY_CODE;
+use kash_server::models::{FriendBalance, FriendBalancesResponse};
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> (StatusCode, Value) {
+    let body = payload.map_or_else(Body::empty, |payload| Body::from(payload.to_string()));
+    let request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    (status, body)
+}
+
+async fn balances(app: &common::TestApp, scenario: &Scenario, user: &str) -> Vec<FriendBalance> {
+    let (status, body) = send(app, "GET", "/friends/balances", scenario.cookie(user), None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let response: FriendBalancesResponse = serde_json::from_value(body).expect("balances");
+    response.balances
+}
+
+/// `(username, currency, owed to me, I owe, net)` per friend and currency.
+fn amounts(balances: &[FriendBalance]) -> Vec<(&str, &str, f64, f64, f64)> {
+    balances
+        .iter()
+        .flat_map(|balance| {
+            balance.currencies.iter().map(|currency| {
+                (
+                    balance.username.as_str(),
+                    currency.currency.as_str(),
+                    currency.amount_owed_to_me.to_decimal(),
+                    currency.amount_i_owe.to_decimal(),
+                    currency.net.to_decimal(),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Alice pays 90 with Bob (30) and Carol (20.10); Bob pays 40 with Alice (15).
+/// Dave is a friend without splits; Erin only has a pending request.
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    let carol = format!("carol_{suffix}");
+    let dave = format!("dave_{suffix}");
+    let erin = format!("erin_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob, &carol, &dave, &erin])
+        .category(&alice, "Dining")
+        .category(&bob, "Dining")
+        .friend(&alice, &bob)
+        .friend(&alice, &carol)
+        .friend(&alice, &dave)
+        .friend_request(&erin, &alice)
+        .split(&alice, "Dining", 90.0, &[(&bob, 30.0), (&carol, 20.1)])
+        .split(&bob, "Dining", 40.0, &[(&alice, 15.0)])
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z151: Both directions per accepted friend, pending shares included
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z151_balances_per_friend() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z151").await;
+
+    let alice = balances(&app, &scenario, "alice_z151").await;
+    assert_eq!(
+        amounts(&alice),
+        vec![
+            ("bob_z151", DEFAULT_CURRENCY_CODE, 30.0, 15.0, 15.0),
+            ("carol_z151", DEFAULT_CURRENCY_CODE, 20.1, 0.0, 20.1),
+        ]
+    );
+    assert_eq!(alice[0].friend_id, scenario.id("bob_z151"));
+    assert_eq!(alice[0].nickname, "bob_z151");
+    assert_eq!(alice[2].username, "dave_z151");
+    assert!(alice[2].currencies.is_empty(), "nothing owed either way");
+
+    assert_eq!(
+        amounts(&balances(&app, &scenario, "bob_z151").await),
+        vec![("alice_z151", DEFAULT_CURRENCY_CODE, 15.0, 30.0, -15.0)]
+    );
+    assert!(
+        balances(&app, &scenario, "erin_z151").await.is_empty(),
+        "a pending request is not a friend yet"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z152: Settled and trashed shares drop out; finalized ones still count
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z152_settled_shares_leave_the_balance() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z152").await;
+    let dinner = &scenario.splits[0];
+    let (bob_share, carol_share) = (&dinner.pending_record_ids[0], &dinner.pending_record_ids[1]);
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/records/finalize-pending",
+        scenario.cookie("bob_z152"),
+        Some(json!({
+            "record_id": bob_share,
+            "category_id": scenario.category_id("bob_z152", "Dining"),
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        amounts(&balances(&app, &scenario, "alice_z152").await)[0],
+        ("bob_z152", DEFAULT_CURRENCY_CODE, 30.0, 15.0, 15.0)
+    );
+
+    let (status, body) = send(
+        &app,
+        "PUT",
+        &format!("/records/{bob_share}/settle"),
+        scenario.cookie("bob_z152"),
+        Some(json!({ "split_id": dinner.split_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, body) = send(
+        &app,
+        "DELETE",
+        &format!("/records/{carol_share}"),
+        scenario.cookie("carol_z152"),
+        None,
+    )
+    .await;
+    assert!(status.is_success(), "{status} {body}");
+
+    let alice = balances(&app, &scenario, "alice_z152").await;
+    assert_eq!(
+        amounts(&alice),
+        vec![("bob_z152", DEFAULT_CURRENCY_CODE, 0.0, 15.0, -15.0)]
+    );
+    assert_eq!(alice.len(), 3, "friends without a balance are still listed");
+}
+
+// ---------------------------------------------------------------------------
+// Z153: Nicknames order the list; removed friends leave it
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z153_balances_follow_the_friend_list() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z153").await;
+    let cookie = scenario.cookie("alice_z153");
+
+    let (status, body) = send(
+        &app,
+        "PATCH",
+        "/friends/nickname",
+        cookie,
+        Some(json!({ "friend_id": scenario.id("dave_z153"), "nickname": "Able Dave" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/friends/remove",
+        cookie,
+        Some(json!({ "friend_id": scenario.id("carol_z153") })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let alice = balances(&app, &scenario, "alice_z153").await;
+    let listed: Vec<(&str, &str)> = alice
+        .iter()
+        .map(|balance| (balance.nickname.as_str(), balance.username.as_str()))
+        .collect();
+    assert_eq!(
+        listed,
+        vec![("Able Dave", "dave_z153"), ("bob_z153", "bob_z153")]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z154: Each currency is its own balance
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z154_balances_per_currency() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z154", "bob_z154"])
+        .category("alice_z154", "Dining")
+        .category("bob_z154", "Dining")
+        .friend("alice_z154", "bob_z154")
+        .split("alice_z154", "Dining", 90.0, &[("bob_z154", 30.0)])
+        .split("bob_z154", "Dining", 40.0, &[("alice_z154", 15.0)])
+        .split_in(
+            "JPY",
+            "bob_z154",
+            "Dining",
+            6000.0,
+            &[("alice_z154", 3000.0)],
+        )
+        .build(&app)
+        .await;
+
+    assert_eq!(
+        amounts(&balances(&app, &scenario, "alice_z154").await),
+        vec![
+            ("bob_z154", "JPY", 0.0, 3000.0, -3000.0),
+            ("bob_z154", DEFAULT_CURRENCY_CODE, 30.0, 15.0, 15.0),
+        ],
+        "yen are not netted against the default currency"
+    );
+}
@@ -0,0 +1,169 @@
+/// Tests Z471-Z473: What a block prevents
+///
+/// Blocking flips only the blocker's directed row to `blocked`, but the block holds both
+/// ways: neither side may send a new friend request (403 `friend_request_blocked`), a
+/// split may not name someone on the other side of a block even while the initiator's
+/// own row still reads accepted, and friend search hides each from the other.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder};
+use common::send_json;
+use kash_server::constants::*;
+use serde_json::{Value, json};
+
+// ---- Helpers ----
+
+/// `blocker` blocks `blocked`: only the blocker's own row changes.
+async fn block(app: &common::TestApp, scenario: &Scenario, blocker: &str, blocked: &str) {
+    let conn = app.state.main_db.write().await;
+    conn.execute(
+        "UPDATE friendship SET status = ?, status_changed_at = '2025-10-01T00:00:00Z' WHERE from_user_id = ? AND to_user_id = ?",
+        (
+            FRIENDSHIP_STATUS_BLOCKED,
+            scenario.id(blocker),
+            scenario.id(blocked),
+        ),
+    )
+    .await
+    .unwrap();
+}
+
+async fn request_friend(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    from: &str,
+    to: &str,
+) -> (StatusCode, Value) {
+    send_json(
+        app,
+        "POST",
+        "/friends/request",
+        scenario.cookie(from),
+        Some(json!({ "friend_username": to })),
+    )
+    .await
+}
+
+// ---------------------------------------------------------------------------
+// Z471: Neither side of a block can send a new friend request
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z471_blocked_user_cannot_request_again() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z471", "bob_z471"])
+        .friend("alice_z471", "bob_z471")
+        .build(&app)
+        .await;
+    block(&app, &scenario, "alice_z471", "bob_z471").await;
+
+    for (from, to) in [("bob_z471", "alice_z471"), ("alice_z471", "bob_z471")] {
+        let (status, body) = request_friend(&app, &scenario, from, to).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{from}: {body}");
+        assert_eq!(body["code"], ERROR_CODE_FRIEND_REQUEST_BLOCKED);
+    }
+
+    // Once the pair's rows are pruned, either side may ask again
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute("DELETE FROM friendship", ()).await.unwrap();
+    }
+    let (status, body) = request_friend(&app, &scenario, "bob_z471", "alice_z471").await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+}
+
+// ---------------------------------------------------------------------------
+// Z472: A split cannot name someone who blocked the initiator
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z472_split_with_blocker_is_refused() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z472", "bob_z472", "carol_z472"])
+        .category("alice_z472", "Dining")
+        .friend("alice_z472", "bob_z472")
+        .friend("alice_z472", "carol_z472")
+        .build(&app)
+        .await;
+    // Bob blocks Alice; Alice's own row still says they are friends
+    block(&app, &scenario, "bob_z472", "alice_z472").await;
+
+    let split = |key: &str, participant: &str| {
+        json!({
+            "idempotency_key": key,
+            "total_amount": 60.0,
+            "description": "Lunch",
+            "date": "2025-10-05",
+            "category_id": scenario.category_id("alice_z472", "Dining"),
+            "splits": [{ "user_id": scenario.id(participant), "amount": 30.0 }],
+        })
+    };
+    let cookie = scenario.cookie("alice_z472");
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/splits/create",
+        cookie,
+        Some(split("z472-bob", "bob_z472")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body,
+        format!(
+            "Participant {} is not an accepted friend",
+            scenario.id("bob_z472")
+        )
+    );
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/splits/create",
+        cookie,
+        Some(split("z472-carol", "carol_z472")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+}
+
+// ---------------------------------------------------------------------------
+// Z473: Search hides a blocked pair from each other
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z473_search_hides_blocks_both_ways() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z473", "bob_z473", "carol_z473"])
+        .friend("alice_z473", "bob_z473")
+        .build(&app)
+        .await;
+    block(&app, &scenario, "alice_z473", "bob_z473").await;
+
+    for (searcher, expected) in [
+        ("alice_z473", vec!["alice_z473", "carol_z473"]),
+        ("bob_z473", vec!["bob_z473", "carol_z473"]),
+    ] {
+        let (status, body) = send_json(
+            &app,
+            "GET",
+            "/friends/search?query=_z473",
+            scenario.cookie(searcher),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        let names: Vec<&str> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|user| user["username"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, expected, "{searcher}");
+    }
+}
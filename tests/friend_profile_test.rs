@@ -0,0 +1,225 @@
+/// Tests Z481-Z483: Friend profile
+///
+/// `GET /friends/{id}` returns the caller's friendship row (`status`, `nickname`,
+/// `requested_at`) and, once accepted, `shared`: how many splits the two share, what each
+/// paid for the other, the unsettled balance as `GET /friends/balances` computes it, and
+/// the five newest shared splits. A pending request comes without `shared`; anyone else,
+/// including a former or blocked friend, is 404.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::ScenarioBuilder;
+use common::send_json;
+use kash_server::constants::*;
+use kash_server::models::{
+    CurrencyBalance, FriendBalancesResponse, FriendProfile, SharedCurrencyTotals,
+};
+use kash_server::money::Money;
+use serde_json::json;
+
+// ---- Helpers ----
+
+async fn profile(app: &common::TestApp, cookie: &str, friend_id: &str) -> FriendProfile {
+    let (status, body) =
+        send_json(app, "GET", &format!("/friends/{friend_id}"), cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    serde_json::from_value(body).expect("friend profile")
+}
+
+// ---------------------------------------------------------------------------
+// Z481: An accepted friend's profile carries the shared split history
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z481_accepted_friend_has_shared_history() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z481", "bob_z481", "carol_z481"])
+        .category("alice_z481", "Dining")
+        .category("bob_z481", "Dining")
+        .friend("alice_z481", "bob_z481")
+        .friend("alice_z481", "carol_z481")
+        .split(
+            "alice_z481",
+            "Dining",
+            90.0,
+            &[("bob_z481", 30.0), ("carol_z481", 25.0)],
+        )
+        .split("bob_z481", "Dining", 50.0, &[("alice_z481", 20.0)])
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z481");
+    let bob = scenario.id("bob_z481");
+
+    // Settling Bob's share leaves what he was paid for in the totals only
+    let alice_split = &scenario.splits[0];
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/records/{}/settle", alice_split.pending_record_ids[0]),
+        cookie,
+        Some(json!({ "split_id": alice_split.split_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let response = profile(&app, cookie, bob).await;
+    assert_eq!(response.friend_id, bob);
+    assert_eq!(response.username, "bob_z481");
+    assert_eq!(response.nickname, "bob_z481");
+    assert_eq!(response.status, FRIEND_RELATIONSHIP_ACCEPTED);
+    assert!(response.requested_at.is_some());
+
+    let shared = response.shared.expect("shared history");
+    assert_eq!(shared.split_count, 2);
+    assert_eq!(
+        shared.currencies,
+        vec![SharedCurrencyTotals {
+            currency: DEFAULT_CURRENCY_CODE.to_string(),
+            paid_for_friend: Money::from_cents(3000),
+            paid_by_friend: Money::from_cents(2000),
+            amount_owed_to_me: Money::ZERO,
+            amount_i_owe: Money::from_cents(2000),
+            net: Money::from_cents(-2000),
+        }]
+    );
+    assert_eq!(
+        shared
+            .recent_splits
+            .iter()
+            .map(|split| split.split_id.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            scenario.splits[1].split_id.as_str(),
+            alice_split.split_id.as_str()
+        ],
+        "newest first"
+    );
+    assert_eq!(
+        shared.recent_splits[1].description.as_deref(),
+        Some("alice_z481 split")
+    );
+
+    // The balance is the one /friends/balances reports
+    let (status, body) = send_json(&app, "GET", "/friends/balances", cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let balances: FriendBalancesResponse = serde_json::from_value(body).expect("balances");
+    let balance = balances
+        .balances
+        .iter()
+        .find(|balance| balance.friend_id == bob)
+        .expect("bob's balance");
+    let totals = &shared.currencies[0];
+    assert_eq!(
+        balance.currencies,
+        vec![CurrencyBalance {
+            currency: totals.currency.clone(),
+            amount_owed_to_me: totals.amount_owed_to_me,
+            amount_i_owe: totals.amount_i_owe,
+            net: totals.net,
+        }]
+    );
+
+    // Carol shares one split with Alice and none with Bob
+    let carol = profile(
+        &app,
+        scenario.cookie("carol_z481"),
+        scenario.id("alice_z481"),
+    )
+    .await;
+    let shared = carol.shared.expect("shared history");
+    assert_eq!(shared.split_count, 1);
+    assert_eq!(shared.currencies[0].paid_by_friend, Money::from_cents(2500));
+    assert_eq!(shared.currencies[0].net, Money::from_cents(-2500));
+}
+
+// ---------------------------------------------------------------------------
+// Z482: A pending request shows its direction without the history
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z482_pending_profile_omits_history() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z482", "bob_z482"])
+        .friend_request("alice_z482", "bob_z482")
+        .build(&app)
+        .await;
+
+    let outgoing = profile(&app, scenario.cookie("alice_z482"), scenario.id("bob_z482")).await;
+    assert_eq!(outgoing.status, FRIEND_RELATIONSHIP_PENDING_OUTGOING);
+    assert!(outgoing.requested_at.is_some());
+    assert_eq!(outgoing.shared, None);
+
+    let uri = format!("/friends/{}", scenario.id("alice_z482"));
+    let (status, body) = send_json(&app, "GET", &uri, scenario.cookie("bob_z482"), None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["status"], FRIEND_RELATIONSHIP_PENDING_INCOMING);
+    assert_eq!(body["requested_at"], json!(outgoing.requested_at));
+    assert!(body.get("shared").is_none());
+}
+
+// ---------------------------------------------------------------------------
+// Z483: Strangers, former friends and blocked friends are not found
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z483_profile_needs_a_current_friendship() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z483", "bob_z483", "carol_z483", "dave_z483"])
+        .friend("alice_z483", "bob_z483")
+        .friend("alice_z483", "carol_z483")
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z483");
+
+    let (status, body) = send_json(
+        &app,
+        "PATCH",
+        "/friends/nickname",
+        cookie,
+        Some(json!({ "friend_id": scenario.id("carol_z483"), "nickname": "Neighbour" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        profile(&app, cookie, scenario.id("carol_z483"))
+            .await
+            .nickname,
+        "Neighbour"
+    );
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/friends/remove",
+        cookie,
+        Some(json!({ "friend_id": scenario.id("bob_z483") })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    {
+        // Carol blocks Alice; Alice's row still reads accepted
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "UPDATE friendship SET status = ? WHERE from_user_id = ? AND to_user_id = ?",
+            (
+                FRIENDSHIP_STATUS_BLOCKED,
+                scenario.id("carol_z483"),
+                scenario.id("alice_z483"),
+            ),
+        )
+        .await
+        .unwrap();
+    }
+
+    for friend in ["bob_z483", "carol_z483", "dave_z483"] {
+        let uri = format!("/friends/{}", scenario.id(friend));
+        let (status, body) = send_json(&app, "GET", &uri, cookie, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{friend}");
+        assert_eq!(body["code"], ERROR_CODE_FRIEND_NOT_FOUND);
+    }
+    let (status, _) = send_json(&app, "GET", "/friends/unknown-id", cookie, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
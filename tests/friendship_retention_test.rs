@@ -0,0 +1,288 @@
+/// Tests R1-R4: Retention of removed friendship rows
+///
+/// Removing a friend keeps both directed rows as `unfriended` history. The
+/// maintenance task prunes them once they are older than the configured number
+/// of days (blocked rows only when explicitly configured), and a user can purge
+/// an unfriended pair immediately via `DELETE /friends/history/{friend_id}`.
+mod common;
+
+use axum::http::StatusCode;
+use common::send_json;
+use kash_server::config::FriendshipRetention;
+use kash_server::maintenance;
+use serde_json::json;
+use time::{Duration, OffsetDateTime, format_description::well_known::Rfc3339};
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+async fn setup_user(app: &common::TestApp, username: &str) -> (String, String) {
+    let user_id = common::create_test_user(&app.state, username, "pw")
+        .await
+        .expect("create user");
+    let cookie = common::login_user(&app.router, username, "pw")
+        .await
+        .expect("login user");
+    (user_id, cookie)
+}
+
+async fn make_friends(
+    app: &common::TestApp,
+    (user_id, cookie): (&str, &str),
+    (friend_username, friend_cookie): (&str, &str),
+) {
+    let (status, _) = send_json(
+        app,
+        "POST",
+        "/friends/request",
+        cookie,
+        Some(json!({ "friend_username": friend_username })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = send_json(
+        app,
+        "POST",
+        "/friends/accept",
+        friend_cookie,
+        Some(json!({ "friend_id": user_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+async fn unfriend(app: &common::TestApp, cookie: &str, friend_id: &str) {
+    let (status, _) = send_json(
+        app,
+        "POST",
+        "/friends/remove",
+        cookie,
+        Some(json!({ "friend_id": friend_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+async fn pair_statuses(app: &common::TestApp, a: &str, b: &str) -> Vec<String> {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT status FROM friendship WHERE (from_user_id = ? AND to_user_id = ?) OR (from_user_id = ? AND to_user_id = ?) ORDER BY from_user_id",
+            (a, b, b, a),
+        )
+        .await
+        .expect("query friendship");
+    let mut statuses = Vec::new();
+    while let Some(row) = rows.next().await.expect("read row") {
+        statuses.push(row.get(0).expect("status"));
+    }
+    statuses
+}
+
+async fn status_changed_at(app: &common::TestApp, from: &str, to: &str) -> OffsetDateTime {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT status_changed_at FROM friendship WHERE from_user_id = ? AND to_user_id = ?",
+            (from, to),
+        )
+        .await
+        .expect("query friendship");
+    let row = rows.next().await.expect("read row").expect("row exists");
+    let value: String = row.get(0).expect("status_changed_at");
+    OffsetDateTime::parse(&value, &Rfc3339).expect("rfc3339 timestamp")
+}
+
+// ---------------------------------------------------------------------------
+// R1: Unfriended rows are pruned exactly at the configured age
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn r1_unfriended_rows_pruned_at_age_boundary() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (alice_id, alice_cookie) = setup_user(&app, "alice_r1").await;
+    let (bob_id, bob_cookie) = setup_user(&app, "bob_r1").await;
+    let (_, carol_cookie) = setup_user(&app, "carol_r1").await;
+    make_friends(&app, (&alice_id, &alice_cookie), ("bob_r1", &bob_cookie)).await;
+    make_friends(
+        &app,
+        (&alice_id, &alice_cookie),
+        ("carol_r1", &carol_cookie),
+    )
+    .await;
+    unfriend(&app, &alice_cookie, &bob_id).await;
+    assert_eq!(
+        pair_statuses(&app, &alice_id, &bob_id).await,
+        ["unfriended"; 2]
+    );
+
+    let changed_at = status_changed_at(&app, &alice_id, &bob_id).await;
+    let retention = FriendshipRetention {
+        unfriended_after_days: Some(30),
+        blocked_after_days: None,
+    };
+
+    let just_before = changed_at + Duration::days(30) - Duration::seconds(1);
+    let report = maintenance::prune_friendships(&app.state.main_db, &retention, just_before)
+        .await
+        .expect("prune");
+    assert_eq!(report.unfriended_rows_pruned, 0);
+    assert_eq!(pair_statuses(&app, &alice_id, &bob_id).await.len(), 2);
+
+    let at_boundary = changed_at + Duration::days(30);
+    let report = maintenance::prune_friendships(&app.state.main_db, &retention, at_boundary)
+        .await
+        .expect("prune");
+    assert_eq!(report.unfriended_rows_pruned, 2, "both directed rows");
+    assert!(pair_statuses(&app, &alice_id, &bob_id).await.is_empty());
+
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM friendship WHERE status = 'active'",
+            (),
+        )
+        .await
+        .expect("count active");
+    let count: i64 = rows
+        .next()
+        .await
+        .expect("read row")
+        .expect("count row")
+        .get(0)
+        .expect("count");
+    assert_eq!(count, 2, "active friendship with carol is untouched");
+}
+
+// ---------------------------------------------------------------------------
+// R2: Blocked rows are only pruned when blocked retention is configured
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn r2_blocked_rows_pruned_only_when_configured() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (alice_id, alice_cookie) = setup_user(&app, "alice_r2").await;
+    let (bob_id, bob_cookie) = setup_user(&app, "bob_r2").await;
+    make_friends(&app, (&alice_id, &alice_cookie), ("bob_r2", &bob_cookie)).await;
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "UPDATE friendship SET status = 'blocked', status_changed_at = '2020-01-01T00:00:00Z'",
+            (),
+        )
+        .await
+        .expect("block pair");
+    }
+
+    let now = OffsetDateTime::now_utc();
+    let unfriended_only = FriendshipRetention {
+        unfriended_after_days: Some(1),
+        blocked_after_days: None,
+    };
+    let report = maintenance::prune_friendships(&app.state.main_db, &unfriended_only, now)
+        .await
+        .expect("prune");
+    assert_eq!(report.blocked_rows_pruned, 0);
+    assert_eq!(
+        pair_statuses(&app, &alice_id, &bob_id).await,
+        ["blocked"; 2]
+    );
+
+    let with_blocked = FriendshipRetention {
+        unfriended_after_days: Some(1),
+        blocked_after_days: Some(365),
+    };
+    let report = maintenance::prune_friendships(&app.state.main_db, &with_blocked, now)
+        .await
+        .expect("prune");
+    assert_eq!(report.blocked_rows_pruned, 2);
+    assert!(pair_statuses(&app, &alice_id, &bob_id).await.is_empty());
+}
+
+// ---------------------------------------------------------------------------
+// R3: Re-friending works both with unfriended history and after pruning
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn r3_refriending_after_unfriend_and_after_prune() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (alice_id, alice_cookie) = setup_user(&app, "alice_r3").await;
+    let (bob_id, bob_cookie) = setup_user(&app, "bob_r3").await;
+    make_friends(&app, (&alice_id, &alice_cookie), ("bob_r3", &bob_cookie)).await;
+
+    unfriend(&app, &bob_cookie, &alice_id).await;
+    make_friends(&app, (&alice_id, &alice_cookie), ("bob_r3", &bob_cookie)).await;
+    assert_eq!(pair_statuses(&app, &alice_id, &bob_id).await, ["active"; 2]);
+
+    unfriend(&app, &alice_cookie, &bob_id).await;
+    let retention = FriendshipRetention {
+        unfriended_after_days: Some(1),
+        blocked_after_days: None,
+    };
+    let later = OffsetDateTime::now_utc() + Duration::days(2);
+    maintenance::prune_friendships(&app.state.main_db, &retention, later)
+        .await
+        .expect("prune");
+    assert!(pair_statuses(&app, &alice_id, &bob_id).await.is_empty());
+
+    make_friends(&app, (&alice_id, &alice_cookie), ("bob_r3", &bob_cookie)).await;
+    assert_eq!(pair_statuses(&app, &alice_id, &bob_id).await, ["active"; 2]);
+}
+
+// ---------------------------------------------------------------------------
+// R4: Manual purge is limited to the pair's members and to unfriended rows
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn r4_manual_purge_authorization() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (alice_id, alice_cookie) = setup_user(&app, "alice_r4").await;
+    let (bob_id, bob_cookie) = setup_user(&app, "bob_r4").await;
+    let (_, mallory_cookie) = setup_user(&app, "mallory_r4").await;
+    make_friends(&app, (&alice_id, &alice_cookie), ("bob_r4", &bob_cookie)).await;
+
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("/friends/history/{bob_id}"),
+        &alice_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT, "active friendship");
+
+    unfriend(&app, &alice_cookie, &bob_id).await;
+
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("/friends/history/{bob_id}"),
+        &mallory_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "outsider cannot purge");
+    assert_eq!(pair_statuses(&app, &alice_id, &bob_id).await.len(), 2);
+
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("/friends/history/{alice_id}"),
+        &bob_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(pair_statuses(&app, &alice_id, &bob_id).await.is_empty());
+
+    let (status, _) = send_json(
+        &app,
+        "DELETE",
+        &format!("/friends/history/{alice_id}"),
+        &bob_cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
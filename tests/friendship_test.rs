@@ -1,51 +1,66 @@
 mod common;
 
-use axum::{
-    body::Body,
-    http::{Request, StatusCode},
+use axum::{http::StatusCode, response::Response};
+use common::fixtures::ScenarioBuilder;
+use common::send_request;
+use kash_server::constants::{
+    ERROR_CODE_BAD_REQUEST, ERROR_CODE_FRIEND_REQUEST_EXISTS, ERROR_CODE_FRIEND_REQUEST_TO_SELF,
+    ERROR_CODE_USER_NOT_FOUND, FRIEND_RELATIONSHIP_ACCEPTED, FRIEND_RELATIONSHIP_NONE,
+    FRIEND_RELATIONSHIP_PENDING_INCOMING, FRIEND_RELATIONSHIP_PENDING_OUTGOING,
 };
-use kash_server::models::FriendshipRelation;
-use serde_json::json;
-use tower::util::ServiceExt;
+use kash_server::models::{FriendshipRelation, UserSearchResult};
+use serde_json::{Value, json};
 
-#[tokio::test]
-async fn test_send_friend_request_happy_path() {
-    let app = common::setup_test_app().await.expect("setup failed");
+// ---- Helpers ----
 
-    // Create two users
-    let _user_a_id = common::create_test_user(&app.state, "alice", "password123")
+async fn body_bytes(response: Response) -> Vec<u8> {
+    axum::body::to_bytes(response.into_body(), usize::MAX)
         .await
-        .expect("create alice failed");
-    let user_b_id = common::create_test_user(&app.state, "bob", "password123")
-        .await
-        .expect("create bob failed");
+        .unwrap()
+        .to_vec()
+}
 
-    // Login as alice
-    let cookie_a = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
+/// The `code` of a JSON error body.
+async fn error_code(response: Response) -> String {
+    let body: Value = serde_json::from_slice(&body_bytes(response).await).unwrap();
+    body["code"].as_str().unwrap_or_default().to_string()
+}
 
-    // Alice sends friend request to Bob
-    let payload = json!({"friend_username": "bob"});
-    let request = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_a)
-        .header("content-type", "application/json")
-        .body(Body::from(payload.to_string()))
-        .unwrap();
+async fn search(app: &common::TestApp, uri: &str, cookie: &str) -> Vec<UserSearchResult> {
+    let response = send_request(app, "GET", uri, cookie, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    serde_json::from_slice(&body_bytes(response).await).unwrap()
+}
 
-    let response = app.router.clone().oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::CREATED);
+async fn list_friends(app: &common::TestApp, uri: &str, cookie: &str) -> Value {
+    let response = send_request(app, "GET", uri, cookie, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    serde_json::from_slice(&body_bytes(response).await).unwrap()
+}
 
-    // Parse response
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let relation: FriendshipRelation = serde_json::from_slice(&body).unwrap();
+#[tokio::test]
+async fn test_send_friend_request_happy_path() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob"])
+        .build(&app)
+        .await;
+    let user_a_id = scenario.id("alice");
+
+    // Alice sends friend request to Bob
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/request",
+        scenario.cookie("alice"),
+        Some(json!({"friend_username": "bob"})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
 
     // Verify response structure
-    assert_eq!(relation.user_id, user_b_id);
+    let relation: FriendshipRelation = serde_json::from_slice(&body_bytes(response).await).unwrap();
+    assert_eq!(relation.user_id, scenario.id("bob"));
     assert!(relation.pending);
     assert_eq!(
         relation.nickname, "bob",
@@ -57,7 +72,7 @@ async fn test_send_friend_request_happy_path() {
     let mut rows = conn
         .query(
             "SELECT COUNT(*) FROM friendship WHERE from_user_id = ? OR to_user_id = ?",
-            (_user_a_id.as_str(), _user_a_id.as_str()),
+            (user_a_id, user_a_id),
         )
         .await
         .unwrap();
@@ -71,112 +86,65 @@ async fn test_send_friend_request_happy_path() {
 #[tokio::test]
 async fn test_send_friend_request_duplicate_error() {
     let app = common::setup_test_app().await.expect("setup failed");
-
-    let _user_a_id = common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
-    let _user_b_id = common::create_test_user(&app.state, "bob", "password123")
-        .await
-        .expect("create bob failed");
-
-    let cookie_a = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
-
-    // Send first request
-    let payload = json!({"friend_username": "bob"});
-    let request1 = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_a.clone())
-        .header("content-type", "application/json")
-        .body(Body::from(payload.to_string()))
-        .unwrap();
-
-    let response1 = app.router.clone().oneshot(request1).await.unwrap();
-    assert_eq!(response1.status(), StatusCode::CREATED);
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob"])
+        .friend_request("alice", "bob")
+        .build(&app)
+        .await;
 
     // Send duplicate request
-    let request2 = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_a)
-        .header("content-type", "application/json")
-        .body(Body::from(payload.to_string()))
-        .unwrap();
-
-    let response2 = app.router.clone().oneshot(request2).await.unwrap();
-    assert_eq!(response2.status(), StatusCode::CONFLICT);
-
-    let body = axum::body::to_bytes(response2.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let error_msg = String::from_utf8(body.to_vec()).unwrap();
-    assert!(error_msg.contains("already exists") || error_msg.contains("duplicate"));
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/request",
+        scenario.cookie("alice"),
+        Some(json!({"friend_username": "bob"})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    assert_eq!(error_code(response).await, ERROR_CODE_FRIEND_REQUEST_EXISTS);
 }
 
 #[tokio::test]
 async fn test_send_friend_request_self_error() {
     let app = common::setup_test_app().await.expect("setup failed");
-
-    let _user_a_id = common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
-
-    let cookie_a = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
+    let scenario = ScenarioBuilder::new().user("alice").build(&app).await;
 
     // Try to send friend request to self
-    let payload = json!({"friend_username": "alice"});
-    let request = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_a)
-        .header("content-type", "application/json")
-        .body(Body::from(payload.to_string()))
-        .unwrap();
-
-    let response = app.router.clone().oneshot(request).await.unwrap();
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/request",
+        scenario.cookie("alice"),
+        Some(json!({"friend_username": "alice"})),
+    )
+    .await;
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let error_msg = String::from_utf8(body.to_vec()).unwrap();
-    assert!(error_msg.contains("self") || error_msg.contains("yourself"));
+    assert_eq!(
+        error_code(response).await,
+        ERROR_CODE_FRIEND_REQUEST_TO_SELF
+    );
 }
 
 #[tokio::test]
 async fn test_send_friend_request_user_not_found() {
     let app = common::setup_test_app().await.expect("setup failed");
-
-    let _user_a_id = common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
-
-    let cookie_a = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
+    let scenario = ScenarioBuilder::new().user("alice").build(&app).await;
 
     // Try to send friend request to non-existent user
-    let payload = json!({"friend_username": "nonexistent"});
-    let request = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_a)
-        .header("content-type", "application/json")
-        .body(Body::from(payload.to_string()))
-        .unwrap();
-
-    let response = app.router.clone().oneshot(request).await.unwrap();
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/request",
+        scenario.cookie("alice"),
+        Some(json!({"friend_username": "nonexistent"})),
+    )
+    .await;
     assert_eq!(response.status(), StatusCode::NOT_FOUND);
 
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let error_msg = String::from_utf8(body.to_vec()).unwrap();
-    assert!(error_msg.contains("not found") || error_msg.contains("does not exist"));
+    assert_eq!(error_code(response).await, ERROR_CODE_USER_NOT_FOUND);
 }
 
 #[tokio::test]
@@ -184,38 +152,23 @@ async fn test_search_users_happy_path() {
     let app = common::setup_test_app().await.expect("setup failed");
 
     // Create multiple users with various usernames
-    common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
-    common::create_test_user(&app.state, "alice_smith", "password123")
-        .await
-        .expect("create alice_smith failed");
-    common::create_test_user(&app.state, "bob", "password123")
-        .await
-        .expect("create bob failed");
-    common::create_test_user(&app.state, "charlie", "password123")
-        .await
-        .expect("create charlie failed");
-
-    let cookie = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
-
-    // Search for users starting with "ali"
-    let request = Request::builder()
-        .uri("/friends/search?query=ali")
-        .method("GET")
-        .header("cookie", cookie)
-        .body(Body::empty())
-        .unwrap();
-
-    let response = app.router.clone().oneshot(request).await.unwrap();
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "alice_smith", "bob", "charlie"])
+        .build(&app)
+        .await;
+
+    // Search for users whose name contains "ali"
+    let response = send_request(
+        &app,
+        "GET",
+        "/friends/search?query=ali",
+        scenario.cookie("alice"),
+        None,
+    )
+    .await;
     assert_eq!(response.status(), StatusCode::OK);
 
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let results: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    let results: Vec<Value> = serde_json::from_slice(&body_bytes(response).await).unwrap();
 
     // Should find both alice and alice_smith
     assert_eq!(results.len(), 2);
@@ -224,38 +177,111 @@ async fn test_search_users_happy_path() {
     for user in results {
         assert!(user.get("id").is_some());
         assert!(user.get("username").is_some());
+        assert_eq!(user["relationship"], FRIEND_RELATIONSHIP_NONE);
         assert!(user.get("password_hash").is_none());
     }
 }
 
 #[tokio::test]
-async fn test_search_users_query_too_short() {
+async fn test_search_users_matches_substrings_ignoring_case() {
     let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "Big_Bob", "bobby", "carol"])
+        .build(&app)
+        .await;
 
-    common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
+    let results = search(&app, "/friends/search?query=BOB", scenario.cookie("alice")).await;
+    assert_eq!(
+        results
+            .iter()
+            .map(|user| user.username.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Big_Bob", "bobby"]
+    );
+}
 
-    let cookie = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
+#[tokio::test]
+async fn test_search_users_annotates_relationships() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&[
+            "me_search",
+            "friend_search",
+            "asked_search",
+            "asking_search",
+            "stranger_search",
+            "shunned_search",
+            "blocker_search",
+        ])
+        .friend("me_search", "friend_search")
+        .friend_request("me_search", "asked_search")
+        .friend_request("asking_search", "me_search")
+        .friend("me_search", "shunned_search")
+        .friend("me_search", "blocker_search")
+        .build(&app)
+        .await;
+    {
+        let conn = app.state.main_db.write().await;
+        for (from, to) in [
+            ("me_search", "shunned_search"),
+            ("blocker_search", "me_search"),
+        ] {
+            conn.execute(
+                "UPDATE friendship SET status = 'blocked' WHERE from_user_id = ? AND to_user_id = ?",
+                (scenario.id(from), scenario.id(to)),
+            )
+            .await
+            .unwrap();
+        }
+    }
 
-    // Try to search with query shorter than 3 characters
-    let request = Request::builder()
-        .uri("/friends/search?query=ab")
-        .method("GET")
-        .header("cookie", cookie)
-        .body(Body::empty())
-        .unwrap();
+    let results = search(
+        &app,
+        "/friends/search?query=_search",
+        scenario.cookie("me_search"),
+    )
+    .await;
+    assert_eq!(
+        results
+            .iter()
+            .map(|user| (user.username.as_str(), user.relationship.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("asked_search", FRIEND_RELATIONSHIP_PENDING_OUTGOING),
+            ("asking_search", FRIEND_RELATIONSHIP_PENDING_INCOMING),
+            ("friend_search", FRIEND_RELATIONSHIP_ACCEPTED),
+            ("me_search", FRIEND_RELATIONSHIP_NONE),
+            ("stranger_search", FRIEND_RELATIONSHIP_NONE),
+        ],
+        "a block in either direction hides the user"
+    );
+
+    let results = search(
+        &app,
+        "/friends/search?query=me_search",
+        scenario.cookie("blocker_search"),
+    )
+    .await;
+    assert!(results.is_empty());
+}
+
+#[tokio::test]
+async fn test_search_users_query_too_short() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new().user("alice").build(&app).await;
 
-    let response = app.router.clone().oneshot(request).await.unwrap();
+    // Try to search with query shorter than 3 characters
+    let response = send_request(
+        &app,
+        "GET",
+        "/friends/search?query=ab",
+        scenario.cookie("alice"),
+        None,
+    )
+    .await;
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let error_msg = String::from_utf8(body.to_vec()).unwrap();
-    assert!(error_msg.contains("at least") || error_msg.contains("minimum"));
+    assert_eq!(error_code(response).await, ERROR_CODE_BAD_REQUEST);
 }
 
 #[tokio::test]
@@ -263,31 +289,23 @@ async fn test_search_users_pagination() {
     let app = common::setup_test_app().await.expect("setup failed");
 
     // Create users with similar prefix
-    for i in 1..=5 {
-        common::create_test_user(&app.state, &format!("user{}", i), "password123")
-            .await
-            .expect("create user failed");
-    }
-
-    let cookie = common::login_user(&app.router, "user1", "password123")
-        .await
-        .expect("user1 login failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["user1", "user2", "user3", "user4", "user5"])
+        .build(&app)
+        .await;
 
     // Search with limit
-    let request = Request::builder()
-        .uri("/friends/search?query=user&limit=3&offset=0")
-        .method("GET")
-        .header("cookie", cookie)
-        .body(Body::empty())
-        .unwrap();
-
-    let response = app.router.clone().oneshot(request).await.unwrap();
+    let response = send_request(
+        &app,
+        "GET",
+        "/friends/search?query=user&limit=3&offset=0",
+        scenario.cookie("user1"),
+        None,
+    )
+    .await;
     assert_eq!(response.status(), StatusCode::OK);
 
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let results: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    let results: Vec<Value> = serde_json::from_slice(&body_bytes(response).await).unwrap();
 
     // Should return exactly 3 results
     assert_eq!(results.len(), 3);
@@ -296,62 +314,29 @@ async fn test_search_users_pagination() {
 #[tokio::test]
 async fn test_nickname_isolation_happy_path() {
     let app = common::setup_test_app().await.expect("setup failed");
-
-    let user_a_id = common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
-    let user_b_id = common::create_test_user(&app.state, "bob", "password123")
-        .await
-        .expect("create bob failed");
-
-    let cookie_a = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
-    let cookie_b = common::login_user(&app.router, "bob", "password123")
-        .await
-        .expect("bob login failed");
-
-    let payload = json!({"friend_username": "bob"});
-    let request = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_a.clone())
-        .header("content-type", "application/json")
-        .body(Body::from(payload.to_string()))
-        .unwrap();
-    let response = app.router.clone().oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::CREATED);
-
-    let accept_request = Request::builder()
-        .uri("/friends/accept")
-        .method("POST")
-        .header("cookie", cookie_b.clone())
-        .header("content-type", "application/json")
-        .body(Body::from(json!({"friend_id": user_a_id}).to_string()))
-        .unwrap();
-    let accept_response = app.router.clone().oneshot(accept_request).await.unwrap();
-    assert_eq!(accept_response.status(), StatusCode::OK);
-
-    let nickname_payload = json!({
-        "friend_id": user_b_id,
-        "nickname": "Gym buddy"
-    });
-    let request = Request::builder()
-        .uri("/friends/nickname")
-        .method("PATCH")
-        .header("cookie", cookie_a.clone())
-        .header("content-type", "application/json")
-        .body(Body::from(nickname_payload.to_string()))
-        .unwrap();
-
-    let response = app.router.clone().oneshot(request).await.unwrap();
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob"])
+        .friend("alice", "bob")
+        .build(&app)
+        .await;
+    let cookie_a = scenario.cookie("alice");
+
+    let response = send_request(
+        &app,
+        "PATCH",
+        "/friends/nickname",
+        cookie_a,
+        Some(json!({
+            "friend_id": scenario.id("bob"),
+            "nickname": "Gym buddy"
+        })),
+    )
+    .await;
     let status = response.status();
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
+    let body = body_bytes(response).await;
 
     if status != StatusCode::OK {
-        let error_msg = String::from_utf8(body.to_vec()).unwrap();
+        let error_msg = String::from_utf8(body).unwrap();
         panic!(
             "Failed to update nickname: status={}, error={}",
             status, error_msg
@@ -361,40 +346,15 @@ async fn test_nickname_isolation_happy_path() {
     let relation: FriendshipRelation = serde_json::from_slice(&body).unwrap();
     assert_eq!(relation.nickname, "Gym buddy", "Alice should see nickname");
 
-    let request = Request::builder()
-        .uri("/friends/list?pending=false")
-        .method("GET")
-        .header("cookie", cookie_a.clone())
-        .body(Body::empty())
-        .unwrap();
-
-    let response = app.router.clone().oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::OK);
-
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let list_response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let list_response = list_friends(&app, "/friends/list?pending=false", cookie_a).await;
     let friends = list_response["friends"]
         .as_array()
         .expect("friends should be array");
     assert_eq!(friends.len(), 1);
     assert_eq!(friends[0]["nickname"], "Gym buddy");
 
-    let request = Request::builder()
-        .uri("/friends/list?pending=false")
-        .method("GET")
-        .header("cookie", cookie_b)
-        .body(Body::empty())
-        .unwrap();
-
-    let response = app.router.clone().oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::OK);
-
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let list_response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let list_response =
+        list_friends(&app, "/friends/list?pending=false", scenario.cookie("bob")).await;
     let friends = list_response["friends"]
         .as_array()
         .expect("friends should be array");
@@ -408,44 +368,25 @@ async fn test_nickname_isolation_happy_path() {
 #[tokio::test]
 async fn test_nickname_oversize_error() {
     let app = common::setup_test_app().await.expect("setup failed");
-
-    let _user_a_id = common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
-    let user_b_id = common::create_test_user(&app.state, "bob", "password123")
-        .await
-        .expect("create bob failed");
-
-    let cookie_a = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
-
-    // Send friend request
-    let payload = json!({"friend_username": "bob"});
-    let request = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_a.clone())
-        .header("content-type", "application/json")
-        .body(Body::from(payload.to_string()))
-        .unwrap();
-    let _ = app.router.clone().oneshot(request).await.unwrap();
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob"])
+        .friend_request("alice", "bob")
+        .build(&app)
+        .await;
 
     // Try to set nickname > 100 chars
     let long_nickname = "a".repeat(101);
-    let nickname_payload = json!({
-        "friend_id": user_b_id,
-        "nickname": long_nickname
-    });
-    let request = Request::builder()
-        .uri("/friends/nickname")
-        .method("PATCH")
-        .header("cookie", cookie_a)
-        .header("content-type", "application/json")
-        .body(Body::from(nickname_payload.to_string()))
-        .unwrap();
-
-    let response = app.router.clone().oneshot(request).await.unwrap();
+    let response = send_request(
+        &app,
+        "PATCH",
+        "/friends/nickname",
+        scenario.cookie("alice"),
+        Some(json!({
+            "friend_id": scenario.id("bob"),
+            "nickname": long_nickname
+        })),
+    )
+    .await;
     assert_eq!(
         response.status(),
         StatusCode::BAD_REQUEST,
@@ -457,89 +398,25 @@ async fn test_nickname_oversize_error() {
 async fn test_list_friends_with_status_filter() {
     let app = common::setup_test_app().await.expect("setup failed");
 
-    let user_a_id = common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
-    let user_b_id = common::create_test_user(&app.state, "bob", "password123")
-        .await
-        .expect("create bob failed");
-    let _user_c_id = common::create_test_user(&app.state, "charlie", "password123")
-        .await
-        .expect("create charlie failed");
-
-    let cookie_a = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
-    let cookie_b = common::login_user(&app.router, "bob", "password123")
-        .await
-        .expect("bob login failed");
-    let cookie_c = common::login_user(&app.router, "charlie", "password123")
-        .await
-        .expect("charlie login failed");
-
-    // Alice sends request to Bob
-    let request = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_a.clone())
-        .header("content-type", "application/json")
-        .body(Body::from(json!({"friend_username": "bob"}).to_string()))
-        .unwrap();
-    let _ = app.router.clone().oneshot(request).await.unwrap();
-
-    // Charlie sends request to Alice
-    let request = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_c.clone())
-        .header("content-type", "application/json")
-        .body(Body::from(json!({"friend_username": "alice"}).to_string()))
-        .unwrap();
-    let _ = app.router.clone().oneshot(request).await.unwrap();
-
-    // Bob accepts Alice's request
-    let accept_request = Request::builder()
-        .uri("/friends/accept")
-        .method("POST")
-        .header("cookie", cookie_b.clone())
-        .header("content-type", "application/json")
-        .body(Body::from(json!({"friend_id": user_a_id}).to_string()))
-        .unwrap();
-    let accept_response = app.router.clone().oneshot(accept_request).await.unwrap();
-    assert_eq!(accept_response.status(), StatusCode::OK);
+    // Alice and Bob are friends; Charlie's request to Alice is still pending
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob", "charlie"])
+        .friend("alice", "bob")
+        .friend_request("charlie", "alice")
+        .build(&app)
+        .await;
+    let cookie_a = scenario.cookie("alice");
 
     // Alice: pending=false (default) → 1 accepted friend (Bob)
-    let request = Request::builder()
-        .uri("/friends/list?pending=false")
-        .method("GET")
-        .header("cookie", cookie_a.clone())
-        .body(Body::empty())
-        .unwrap();
-    let response = app.router.clone().oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::OK);
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let list_response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let list_response = list_friends(&app, "/friends/list?pending=false", cookie_a).await;
     let friends = list_response["friends"]
         .as_array()
         .expect("friends should be array");
     assert_eq!(friends.len(), 1, "Alice should have 1 accepted friend");
-    assert_eq!(friends[0]["user_id"], user_b_id);
+    assert_eq!(friends[0]["user_id"], scenario.id("bob"));
 
     // Alice: pending=true → only incoming requests (Charlie sent to Alice) → 1
-    let request = Request::builder()
-        .uri("/friends/list?pending=true")
-        .method("GET")
-        .header("cookie", cookie_a.clone())
-        .body(Body::empty())
-        .unwrap();
-    let response = app.router.clone().oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::OK);
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let list_response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let list_response = list_friends(&app, "/friends/list?pending=true", cookie_a).await;
     let friends = list_response["friends"]
         .as_array()
         .expect("friends should be array");
@@ -548,21 +425,11 @@ async fn test_list_friends_with_status_filter() {
         1,
         "Alice should see 1 incoming request (from Charlie)"
     );
-    assert_eq!(friends[0]["user_id"], _user_c_id);
+    assert_eq!(friends[0]["user_id"], scenario.id("charlie"));
 
     // Bob: pending=true → Bob sent no requests, and Alice's request to Bob is accepted, so 0 incoming
-    let request = Request::builder()
-        .uri("/friends/list?pending=true")
-        .method("GET")
-        .header("cookie", cookie_b)
-        .body(Body::empty())
-        .unwrap();
-    let response = app.router.clone().oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::OK);
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let list_response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let list_response =
+        list_friends(&app, "/friends/list?pending=true", scenario.cookie("bob")).await;
     let friends = list_response["friends"]
         .as_array()
         .expect("friends should be array");
@@ -570,71 +437,85 @@ async fn test_list_friends_with_status_filter() {
 }
 
 #[tokio::test]
-async fn test_list_friends_pagination() {
+async fn test_list_friends_direction() {
     let app = common::setup_test_app().await.expect("setup failed");
-
-    let user_a_id = common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
-
-    for i in 0..10 {
-        let username = format!("friend{}", i);
-        common::create_test_user(&app.state, &username, "password123")
-            .await
-            .expect("create friend failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob", "charlie"])
+        .friend_request("alice", "bob")
+        .friend_request("charlie", "alice")
+        .build(&app)
+        .await;
+    let cookie_a = scenario.cookie("alice");
+
+    // Alice sent one request and received one; both are pending
+    let list_response = list_friends(&app, "/friends/list?pending=true", cookie_a).await;
+    assert_eq!(list_response["total_count"], 2);
+    let friends = list_response["friends"].as_array().unwrap();
+    let direction_of = |user: &str| {
+        friends
+            .iter()
+            .find(|friend| friend["user_id"] == scenario.id(user))
+            .map(|friend| friend["direction"].clone())
+    };
+    assert_eq!(direction_of("bob"), Some(json!("outgoing")));
+    assert_eq!(direction_of("charlie"), Some(json!("incoming")));
+
+    let list_response =
+        list_friends(&app, "/friends/list?pending=true", scenario.cookie("bob")).await;
+    assert_eq!(list_response["friends"][0]["user_id"], scenario.id("alice"));
+    assert_eq!(list_response["friends"][0]["direction"], "incoming");
+
+    for (direction, user) in [("outgoing", "bob"), ("incoming", "charlie")] {
+        let uri = format!("/friends/list?pending=true&direction={direction}");
+        let list_response = list_friends(&app, &uri, cookie_a).await;
+        assert_eq!(list_response["total_count"], 1, "{direction}");
+        let friends = list_response["friends"].as_array().unwrap();
+        assert_eq!(friends.len(), 1, "{direction}");
+        assert_eq!(friends[0]["user_id"], scenario.id(user));
+        assert_eq!(friends[0]["direction"], direction);
     }
 
-    let cookie_a = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
+    // Accepted friends keep the direction of the original request
+    let accept_response = send_request(
+        &app,
+        "POST",
+        "/friends/accept",
+        scenario.cookie("bob"),
+        Some(json!({"friend_id": scenario.id("alice")})),
+    )
+    .await;
+    assert_eq!(accept_response.status(), StatusCode::OK);
+    let list_response = list_friends(&app, "/friends/list?direction=incoming", cookie_a).await;
+    assert_eq!(list_response["total_count"], 0);
+    let list_response = list_friends(&app, "/friends/list", scenario.cookie("bob")).await;
+    assert_eq!(list_response["friends"][0]["direction"], "incoming");
+
+    let response = send_request(
+        &app,
+        "GET",
+        "/friends/list?direction=sideways",
+        cookie_a,
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
 
-    // Alice sends requests to all 10 friends
-    for i in 0..10 {
-        let username = format!("friend{}", i);
-        let payload = json!({"friend_username": username});
-        let request = Request::builder()
-            .uri("/friends/request")
-            .method("POST")
-            .header("cookie", cookie_a.clone())
-            .header("content-type", "application/json")
-            .body(Body::from(payload.to_string()))
-            .unwrap();
-        let _ = app.router.clone().oneshot(request).await.unwrap();
-    }
+#[tokio::test]
+async fn test_list_friends_pagination() {
+    let app = common::setup_test_app().await.expect("setup failed");
 
-    // All 10 friends accept Alice's request
+    // Alice is friends with 10 users
+    let mut builder = ScenarioBuilder::new().user("alice");
     for i in 0..10 {
         let username = format!("friend{}", i);
-        let cookie = common::login_user(&app.router, &username, "password123")
-            .await
-            .expect("friend login failed");
-        let accept_payload = json!({"friend_id": user_a_id});
-        let request = Request::builder()
-            .uri("/friends/accept")
-            .method("POST")
-            .header("cookie", cookie)
-            .header("content-type", "application/json")
-            .body(Body::from(accept_payload.to_string()))
-            .unwrap();
-        let response = app.router.clone().oneshot(request).await.unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
+        builder = builder.user(&username).friend("alice", &username);
     }
+    let scenario = builder.build(&app).await;
+    let cookie_a = scenario.cookie("alice");
 
     // Page 1: 5 of 10 accepted friends
-    let request = Request::builder()
-        .uri("/friends/list?limit=5&offset=0")
-        .method("GET")
-        .header("cookie", cookie_a.clone())
-        .body(Body::empty())
-        .unwrap();
-
-    let response = app.router.clone().oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::OK);
-
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let list_response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let list_response = list_friends(&app, "/friends/list?limit=5&offset=0", cookie_a).await;
     let friends = list_response["friends"]
         .as_array()
         .expect("friends should be array");
@@ -646,20 +527,7 @@ async fn test_list_friends_pagination() {
     assert_eq!(total_count, 10, "Total count should be 10");
 
     // Page 2: next 5
-    let request = Request::builder()
-        .uri("/friends/list?limit=5&offset=5")
-        .method("GET")
-        .header("cookie", cookie_a)
-        .body(Body::empty())
-        .unwrap();
-
-    let response = app.router.clone().oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::OK);
-
-    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let list_response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let list_response = list_friends(&app, "/friends/list?limit=5&offset=5", cookie_a).await;
     let friends = list_response["friends"]
         .as_array()
         .expect("friends should be array");
@@ -668,52 +536,79 @@ async fn test_list_friends_pagination() {
 }
 
 #[tokio::test]
-async fn test_accept_friend_happy_path() {
+async fn test_list_friends_filter_by_name() {
     let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob", "Bobby", "carol", "dave", "robert_bob"])
+        .friend("alice", "bob")
+        .friend("alice", "Bobby")
+        .friend("alice", "carol")
+        .friend("alice", "dave")
+        .friend_request("robert_bob", "alice")
+        .build(&app)
+        .await;
+    let cookie_a = scenario.cookie("alice");
+
+    let response = send_request(
+        &app,
+        "PATCH",
+        "/friends/nickname",
+        cookie_a,
+        Some(json!({"friend_id": scenario.id("carol"), "nickname": "Bob's sister"})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
 
-    let user_a_id = common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
-    let user_b_id = common::create_test_user(&app.state, "bob", "password123")
-        .await
-        .expect("create bob failed");
+    // Nickname or username, ignoring case; pending requests stay out of the accepted list
+    let list_response = list_friends(&app, "/friends/list?q=BOB&limit=2", cookie_a).await;
+    assert_eq!(list_response["total_count"], 3);
+    let friends = list_response["friends"].as_array().unwrap();
+    assert_eq!(friends.len(), 2);
+    let list_response = list_friends(&app, "/friends/list?q=BOB&limit=2&offset=2", cookie_a).await;
+    let friends: Vec<FriendshipRelation> =
+        serde_json::from_value(list_response["friends"].clone()).unwrap();
+    assert_eq!(friends.len(), 1);
 
-    let cookie_a = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
-
-    let payload = json!({"friend_username": "bob"});
-    let request = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_a)
-        .header("content-type", "application/json")
-        .body(Body::from(payload.to_string()))
-        .unwrap();
+    let list_response = list_friends(&app, "/friends/list?q=sister", cookie_a).await;
+    let friends: Vec<FriendshipRelation> =
+        serde_json::from_value(list_response["friends"].clone()).unwrap();
+    assert_eq!(friends.len(), 1);
+    assert_eq!(friends[0].user_id, scenario.id("carol"));
+    assert_eq!(friends[0].username, "carol");
+    assert_eq!(friends[0].nickname, "Bob's sister");
 
-    let response = app.router.clone().oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::CREATED);
+    let list_response = list_friends(&app, "/friends/list?pending=true&q=bob", cookie_a).await;
+    assert_eq!(list_response["total_count"], 1);
+    assert_eq!(list_response["friends"][0]["username"], "robert_bob");
 
-    let cookie_b = common::login_user(&app.router, "bob", "password123")
-        .await
-        .expect("bob login failed");
-
-    let accept_payload = json!({"friend_id": user_a_id});
-    let accept_request = Request::builder()
-        .uri("/friends/accept")
-        .method("POST")
-        .header("cookie", cookie_b)
-        .header("content-type", "application/json")
-        .body(Body::from(accept_payload.to_string()))
-        .unwrap();
+    // A blank q lists everyone
+    let list_response = list_friends(&app, "/friends/list?q=%20", cookie_a).await;
+    assert_eq!(list_response["total_count"], 4);
+}
 
-    let accept_response = app.router.clone().oneshot(accept_request).await.unwrap();
+#[tokio::test]
+async fn test_accept_friend_happy_path() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob"])
+        .friend_request("alice", "bob")
+        .build(&app)
+        .await;
+    let user_a_id = scenario.id("alice");
+    let user_b_id = scenario.id("bob");
+
+    let accept_response = send_request(
+        &app,
+        "POST",
+        "/friends/accept",
+        scenario.cookie("bob"),
+        Some(json!({"friend_id": user_a_id})),
+    )
+    .await;
     assert_eq!(accept_response.status(), StatusCode::OK);
 
-    let body = axum::body::to_bytes(accept_response.into_body(), usize::MAX)
-        .await
-        .unwrap();
-    let relation: FriendshipRelation = serde_json::from_slice(&body).unwrap();
+    let relation: FriendshipRelation =
+        serde_json::from_slice(&body_bytes(accept_response).await).unwrap();
 
     assert_eq!(relation.user_id, user_a_id);
     assert!(!relation.pending);
@@ -723,7 +618,7 @@ async fn test_accept_friend_happy_path() {
     let mut rows_ab = conn
         .query(
             "SELECT pending FROM friendship WHERE from_user_id = ? AND to_user_id = ?",
-            (user_a_id.as_str(), user_b_id.as_str()),
+            (user_a_id, user_b_id),
         )
         .await
         .unwrap();
@@ -738,7 +633,7 @@ async fn test_accept_friend_happy_path() {
     let mut rows_ba = conn
         .query(
             "SELECT pending FROM friendship WHERE from_user_id = ? AND to_user_id = ?",
-            (user_b_id.as_str(), user_a_id.as_str()),
+            (user_b_id, user_a_id),
         )
         .await
         .unwrap();
@@ -754,282 +649,573 @@ async fn test_accept_friend_happy_path() {
 #[tokio::test]
 async fn test_accept_friend_unauthorized() {
     let app = common::setup_test_app().await.expect("setup failed");
-
-    let user_a_id = common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
-    common::create_test_user(&app.state, "bob", "password123")
-        .await
-        .expect("create bob failed");
-    common::create_test_user(&app.state, "charlie", "password123")
-        .await
-        .expect("create charlie failed");
-
-    let cookie_a = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
-
-    let payload = json!({"friend_username": "bob"});
-    let request = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_a)
-        .header("content-type", "application/json")
-        .body(Body::from(payload.to_string()))
-        .unwrap();
-
-    let response = app.router.clone().oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::CREATED);
-
-    let cookie_c = common::login_user(&app.router, "charlie", "password123")
-        .await
-        .expect("charlie login failed");
-
-    let accept_payload = json!({"friend_id": user_a_id});
-    let accept_request = Request::builder()
-        .uri("/friends/accept")
-        .method("POST")
-        .header("cookie", cookie_c)
-        .header("content-type", "application/json")
-        .body(Body::from(accept_payload.to_string()))
-        .unwrap();
-
-    let accept_response = app.router.clone().oneshot(accept_request).await.unwrap();
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob", "charlie"])
+        .friend_request("alice", "bob")
+        .build(&app)
+        .await;
+
+    let accept_response = send_request(
+        &app,
+        "POST",
+        "/friends/accept",
+        scenario.cookie("charlie"),
+        Some(json!({"friend_id": scenario.id("alice")})),
+    )
+    .await;
     assert_eq!(accept_response.status(), StatusCode::NOT_FOUND);
 }
 
 #[tokio::test]
 async fn test_accept_friend_requester_cannot_accept() {
     let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob"])
+        .friend_request("alice", "bob")
+        .build(&app)
+        .await;
+
+    let accept_response = send_request(
+        &app,
+        "POST",
+        "/friends/accept",
+        scenario.cookie("alice"),
+        Some(json!({"friend_id": scenario.id("bob")})),
+    )
+    .await;
+    assert_eq!(accept_response.status(), StatusCode::NOT_FOUND);
+}
 
-    common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
-    let user_b_id = common::create_test_user(&app.state, "bob", "password123")
-        .await
-        .expect("create bob failed");
+#[tokio::test]
+async fn test_decline_friend_happy_path() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob"])
+        .friend_request("alice", "bob")
+        .build(&app)
+        .await;
+    let cookie_b = scenario.cookie("bob");
+
+    let decline_response = send_request(
+        &app,
+        "POST",
+        "/friends/decline",
+        cookie_b,
+        Some(json!({"friend_id": scenario.id("alice")})),
+    )
+    .await;
+    assert_eq!(decline_response.status(), StatusCode::OK);
+
+    let pending = list_friends(&app, "/friends/list?pending=true", cookie_b).await;
+    assert_eq!(pending["friends"].as_array().unwrap().len(), 0);
+    let declined = list_friends(&app, "/friends/list?status=declined", cookie_b).await;
+    let declined = declined["friends"].as_array().unwrap();
+    assert_eq!(declined.len(), 1, "Bob should see Alice's declined request");
+    assert_eq!(declined[0]["user_id"], scenario.id("alice"));
+    let declined = list_friends(
+        &app,
+        "/friends/list?status=declined",
+        scenario.cookie("alice"),
+    )
+    .await;
+    assert_eq!(
+        declined["friends"].as_array().unwrap().len(),
+        0,
+        "Alice declined nothing"
+    );
 
-    let cookie_a = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
-
-    let payload = json!({"friend_username": "bob"});
-    let request = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_a.clone())
-        .header("content-type", "application/json")
-        .body(Body::from(payload.to_string()))
-        .unwrap();
+    // Declining again, or accepting afterwards, finds no pending request
+    for uri in ["/friends/decline", "/friends/accept"] {
+        let response = send_request(
+            &app,
+            "POST",
+            uri,
+            cookie_b,
+            Some(json!({"friend_id": scenario.id("alice")})),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{uri}");
+    }
 
-    let response = app.router.clone().oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::CREATED);
+    let response = send_request(&app, "GET", "/friends/list?status=blocked", cookie_b, None).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
 
-    let accept_payload = json!({"friend_id": user_b_id});
-    let accept_request = Request::builder()
-        .uri("/friends/accept")
-        .method("POST")
-        .header("cookie", cookie_a)
-        .header("content-type", "application/json")
-        .body(Body::from(accept_payload.to_string()))
-        .unwrap();
+#[tokio::test]
+async fn test_decline_friend_only_recipient_can_decline() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob", "charlie"])
+        .friend_request("alice", "bob")
+        .build(&app)
+        .await;
+
+    for (user, friend) in [("alice", "bob"), ("charlie", "alice"), ("charlie", "bob")] {
+        let response = send_request(
+            &app,
+            "POST",
+            "/friends/decline",
+            scenario.cookie(user),
+            Some(json!({"friend_id": scenario.id(friend)})),
+        )
+        .await;
+        assert_eq!(
+            response.status(),
+            StatusCode::NOT_FOUND,
+            "{user} -> {friend}"
+        );
+    }
 
-    let accept_response = app.router.clone().oneshot(accept_request).await.unwrap();
-    assert_eq!(accept_response.status(), StatusCode::NOT_FOUND);
+    let pending = list_friends(&app, "/friends/list?pending=true", scenario.cookie("bob")).await;
+    assert_eq!(pending["friends"].as_array().unwrap().len(), 1);
 }
 
 #[tokio::test]
-async fn test_remove_friend_happy_path() {
+async fn test_declined_request_can_be_sent_again() {
     let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob"])
+        .friend_request("alice", "bob")
+        .build(&app)
+        .await;
+
+    let decline_response = send_request(
+        &app,
+        "POST",
+        "/friends/decline",
+        scenario.cookie("bob"),
+        Some(json!({"friend_id": scenario.id("alice")})),
+    )
+    .await;
+    assert_eq!(decline_response.status(), StatusCode::OK);
+
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/request",
+        scenario.cookie("alice"),
+        Some(json!({"friend_username": "bob"})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
 
-    let user_a_id = common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
-    let user_b_id = common::create_test_user(&app.state, "bob", "password123")
-        .await
-        .expect("create bob failed");
-
-    let cookie_a = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
-
-    let payload = json!({"friend_username": "bob"});
-    let request = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_a.clone())
-        .header("content-type", "application/json")
-        .body(Body::from(payload.to_string()))
-        .unwrap();
+    let pending = list_friends(&app, "/friends/list?pending=true", scenario.cookie("bob")).await;
+    assert_eq!(pending["friends"].as_array().unwrap().len(), 1);
+    let declined = list_friends(
+        &app,
+        "/friends/list?status=declined",
+        scenario.cookie("bob"),
+    )
+    .await;
+    assert_eq!(declined["friends"].as_array().unwrap().len(), 0);
+}
 
-    let response = app.router.clone().oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::CREATED);
+#[tokio::test]
+async fn test_cancel_friend_request_happy_path() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob"])
+        .friend_request("alice", "bob")
+        .build(&app)
+        .await;
+
+    let cancel_response = send_request(
+        &app,
+        "POST",
+        "/friends/cancel",
+        scenario.cookie("alice"),
+        Some(json!({"friend_id": scenario.id("bob")})),
+    )
+    .await;
+    assert_eq!(cancel_response.status(), StatusCode::OK);
 
-    let cookie_b = common::login_user(&app.router, "bob", "password123")
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM friendship WHERE from_user_id IN (?, ?)",
+            (scenario.id("alice"), scenario.id("bob")),
+        )
         .await
-        .expect("bob login failed");
-
-    let accept_payload = json!({"friend_id": user_a_id});
-    let accept_request = Request::builder()
-        .uri("/friends/accept")
-        .method("POST")
-        .header("cookie", cookie_b)
-        .header("content-type", "application/json")
-        .body(Body::from(accept_payload.to_string()))
         .unwrap();
+    let count: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+    assert_eq!(count, 0, "Both directed rows should be deleted");
+    drop(rows);
+    drop(conn);
+
+    let pending = list_friends(&app, "/friends/list?pending=true", scenario.cookie("bob")).await;
+    assert_eq!(pending["friends"].as_array().unwrap().len(), 0);
+
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/request",
+        scenario.cookie("alice"),
+        Some(json!({"friend_username": "bob"})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
 
-    let accept_response = app.router.clone().oneshot(accept_request).await.unwrap();
-    assert_eq!(accept_response.status(), StatusCode::OK);
+#[tokio::test]
+async fn test_cancel_friend_request_only_requester_can_cancel() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob", "charlie"])
+        .friend_request("alice", "bob")
+        .friend("alice", "charlie")
+        .build(&app)
+        .await;
+
+    // The recipient, a stranger, and an accepted friendship are all not found
+    for (user, friend) in [("bob", "alice"), ("charlie", "bob"), ("alice", "charlie")] {
+        let response = send_request(
+            &app,
+            "POST",
+            "/friends/cancel",
+            scenario.cookie(user),
+            Some(json!({"friend_id": scenario.id(friend)})),
+        )
+        .await;
+        assert_eq!(
+            response.status(),
+            StatusCode::NOT_FOUND,
+            "{user} -> {friend}"
+        );
+    }
 
-    let remove_payload = json!({"friend_id": user_b_id});
-    let remove_request = Request::builder()
-        .uri("/friends/remove")
-        .method("POST")
-        .header("cookie", cookie_a)
-        .header("content-type", "application/json")
-        .body(Body::from(remove_payload.to_string()))
-        .unwrap();
+    let pending = list_friends(&app, "/friends/list?pending=true", scenario.cookie("bob")).await;
+    assert_eq!(pending["friends"].as_array().unwrap().len(), 1);
+    let friends = list_friends(&app, "/friends/list", scenario.cookie("charlie")).await;
+    assert_eq!(friends["friends"].as_array().unwrap().len(), 1);
+}
 
-    let remove_response = app.router.clone().oneshot(remove_request).await.unwrap();
+#[tokio::test]
+async fn test_remove_friend_happy_path() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob"])
+        .friend("alice", "bob")
+        .build(&app)
+        .await;
+    let user_a_id = scenario.id("alice");
+    let user_b_id = scenario.id("bob");
+
+    let remove_response = send_request(
+        &app,
+        "POST",
+        "/friends/remove",
+        scenario.cookie("alice"),
+        Some(json!({"friend_id": user_b_id})),
+    )
+    .await;
     assert_eq!(remove_response.status(), StatusCode::OK);
 
     let conn = app.state.main_db.read().await;
     let mut rows = conn
         .query(
-            "SELECT COUNT(*) FROM friendship WHERE (from_user_id = ? AND to_user_id = ?) OR (from_user_id = ? AND to_user_id = ?)",
-            (
-                user_a_id.as_str(),
-                user_b_id.as_str(),
-                user_b_id.as_str(),
-                user_a_id.as_str(),
-            ),
+            "SELECT COUNT(*) FROM friendship WHERE ((from_user_id = ? AND to_user_id = ?) OR (from_user_id = ? AND to_user_id = ?)) AND status = 'active'",
+            (user_a_id, user_b_id, user_b_id, user_a_id),
         )
         .await
         .unwrap();
 
     if let Some(row) = rows.next().await.unwrap() {
         let count: i64 = row.get(0).unwrap();
-        assert_eq!(count, 0, "Both directed rows should be deleted");
+        assert_eq!(count, 0, "Both directed rows should be unfriended");
     }
 }
 
 #[tokio::test]
 async fn test_remove_friend_either_party_can_initiate() {
     let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob"])
+        .friend("alice", "bob")
+        .build(&app)
+        .await;
+
+    let remove_response = send_request(
+        &app,
+        "POST",
+        "/friends/remove",
+        scenario.cookie("bob"),
+        Some(json!({"friend_id": scenario.id("alice")})),
+    )
+    .await;
+    assert_eq!(remove_response.status(), StatusCode::OK);
+}
 
-    let user_a_id = common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
-    common::create_test_user(&app.state, "bob", "password123")
-        .await
-        .expect("create bob failed");
-
-    let cookie_a = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
-
-    let payload = json!({"friend_username": "bob"});
-    let request = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_a)
-        .header("content-type", "application/json")
-        .body(Body::from(payload.to_string()))
-        .unwrap();
+#[tokio::test]
+async fn test_remove_then_accept_returns_not_found() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob"])
+        .friend("alice", "bob")
+        .build(&app)
+        .await;
+    let cookie_b = scenario.cookie("bob");
+    let alice_payload = json!({"friend_id": scenario.id("alice")});
+
+    let remove_response = send_request(
+        &app,
+        "POST",
+        "/friends/remove",
+        cookie_b,
+        Some(alice_payload.clone()),
+    )
+    .await;
+    assert_eq!(remove_response.status(), StatusCode::OK);
 
-    let response = app.router.clone().oneshot(request).await.unwrap();
-    assert_eq!(response.status(), StatusCode::CREATED);
+    let reaccept_response = send_request(
+        &app,
+        "POST",
+        "/friends/accept",
+        cookie_b,
+        Some(alice_payload),
+    )
+    .await;
+    assert_eq!(reaccept_response.status(), StatusCode::NOT_FOUND);
+}
 
-    let cookie_b = common::login_user(&app.router, "bob", "password123")
-        .await
-        .expect("bob login failed");
-
-    let accept_payload = json!({"friend_id": user_a_id});
-    let accept_request = Request::builder()
-        .uri("/friends/accept")
-        .method("POST")
-        .header("cookie", cookie_b.clone())
-        .header("content-type", "application/json")
-        .body(Body::from(accept_payload.to_string()))
-        .unwrap();
+async fn stored_request_messages(
+    app: &common::TestApp,
+    a: &str,
+    b: &str,
+) -> (Option<String>, Option<String>) {
+    let conn = app.state.main_db.read().await;
+    let mut messages = Vec::new();
+    for (from_user_id, to_user_id) in [(a, b), (b, a)] {
+        let mut rows = conn
+            .query(
+                "SELECT request_message FROM friendship WHERE from_user_id = ? AND to_user_id = ?",
+                (from_user_id, to_user_id),
+            )
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().expect("relation row");
+        messages.push(row.get::<Option<String>>(0).unwrap());
+    }
+    (messages[0].clone(), messages[1].clone())
+}
 
-    let accept_response = app.router.clone().oneshot(accept_request).await.unwrap();
-    assert_eq!(accept_response.status(), StatusCode::OK);
+#[tokio::test]
+async fn test_friend_request_message_only_reaches_recipient() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob"])
+        .build(&app)
+        .await;
+
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/request",
+        scenario.cookie("alice"),
+        Some(json!({"friend_username": "bob", "message": "it's Alice from climbing"})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let relation: Value = serde_json::from_slice(&body_bytes(response).await).unwrap();
+    assert!(
+        relation.get("message").is_none(),
+        "The sender's view never carries the note"
+    );
 
-    let remove_payload = json!({"friend_id": user_a_id});
-    let remove_request = Request::builder()
-        .uri("/friends/remove")
-        .method("POST")
-        .header("cookie", cookie_b)
-        .header("content-type", "application/json")
-        .body(Body::from(remove_payload.to_string()))
-        .unwrap();
+    let list_response =
+        list_friends(&app, "/friends/list?pending=true", scenario.cookie("bob")).await;
+    let requests = list_response["friends"].as_array().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["message"], "it's Alice from climbing");
 
-    let remove_response = app.router.clone().oneshot(remove_request).await.unwrap();
-    assert_eq!(remove_response.status(), StatusCode::OK);
+    let (alice_row, bob_row) =
+        stored_request_messages(&app, scenario.id("alice"), scenario.id("bob")).await;
+    assert_eq!(alice_row, None, "Sender's row never stores the note");
+    assert_eq!(bob_row.as_deref(), Some("it's Alice from climbing"));
 }
 
 #[tokio::test]
-async fn test_remove_then_accept_returns_not_found() {
+async fn test_friend_request_message_cleared_on_accept_and_reject() {
     let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob", "carol"])
+        .build(&app)
+        .await;
+    for (from, to) in [("alice", "bob"), ("carol", "bob")] {
+        let response = send_request(
+            &app,
+            "POST",
+            "/friends/request",
+            scenario.cookie(from),
+            Some(json!({"friend_username": to, "message": format!("hi from {from}")})),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
 
-    let user_a_id = common::create_test_user(&app.state, "alice", "password123")
-        .await
-        .expect("create alice failed");
-    common::create_test_user(&app.state, "bob", "password123")
-        .await
-        .expect("create bob failed");
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/accept",
+        scenario.cookie("bob"),
+        Some(json!({"friend_id": scenario.id("alice")})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let relation: Value = serde_json::from_slice(&body_bytes(response).await).unwrap();
+    assert!(relation.get("message").is_none());
+    let list_response =
+        list_friends(&app, "/friends/list?pending=false", scenario.cookie("bob")).await;
+    assert!(list_response["friends"][0].get("message").is_none());
+    assert_eq!(
+        stored_request_messages(&app, scenario.id("alice"), scenario.id("bob")).await,
+        (None, None)
+    );
 
-    let cookie_a = common::login_user(&app.router, "alice", "password123")
-        .await
-        .expect("alice login failed");
-
-    let payload = json!({"friend_username": "bob"});
-    let request = Request::builder()
-        .uri("/friends/request")
-        .method("POST")
-        .header("cookie", cookie_a.clone())
-        .header("content-type", "application/json")
-        .body(Body::from(payload.to_string()))
-        .unwrap();
+    // Bob turns Carol's request down.
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/remove",
+        scenario.cookie("bob"),
+        Some(json!({"friend_id": scenario.id("carol")})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        stored_request_messages(&app, scenario.id("carol"), scenario.id("bob")).await,
+        (None, None)
+    );
+}
 
-    app.router.clone().oneshot(request).await.unwrap();
+#[tokio::test]
+async fn test_friend_request_message_validation() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob", "carol"])
+        .build(&app)
+        .await;
+
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/request",
+        scenario.cookie("alice"),
+        Some(json!({"friend_username": "bob", "message": "é".repeat(201)})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(error_code(response).await, ERROR_CODE_BAD_REQUEST);
+
+    // 200 characters is fine even when it is more than 200 bytes.
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/request",
+        scenario.cookie("alice"),
+        Some(json!({"friend_username": "bob", "message": "é".repeat(200)})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
 
-    let cookie_b = common::login_user(&app.router, "bob", "password123")
-        .await
-        .expect("bob login failed");
-
-    let accept_payload = json!({"friend_id": user_a_id});
-    let accept_request = Request::builder()
-        .uri("/friends/accept")
-        .method("POST")
-        .header("cookie", cookie_b.clone())
-        .header("content-type", "application/json")
-        .body(Body::from(accept_payload.to_string()))
-        .unwrap();
+    let response = send_request(&app, "POST", "/friends/request", scenario.cookie("carol"), Some(json!({"friend_username": "bob", "message": "\n  it's\tCarol \u{7}\u{1b}[31m from   work \r\n"})))
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let (_, bob_row) =
+        stored_request_messages(&app, scenario.id("carol"), scenario.id("bob")).await;
+    assert_eq!(bob_row.as_deref(), Some("it's Carol [31m from work"));
+
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/request",
+        scenario.cookie("carol"),
+        Some(json!({"friend_username": "alice", "message": " \t\n "})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let (_, alice_row) =
+        stored_request_messages(&app, scenario.id("carol"), scenario.id("alice")).await;
+    assert_eq!(alice_row, None, "A blank note is dropped");
+}
 
-    app.router.clone().oneshot(accept_request).await.unwrap();
+#[tokio::test]
+async fn test_unfriended_pair_can_befriend_again() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice", "bob"])
+        .friend("alice", "bob")
+        .build(&app)
+        .await;
+
+    let response = send_request(
+        &app,
+        "PATCH",
+        "/friends/nickname",
+        scenario.cookie("alice"),
+        Some(json!({"friend_id": scenario.id("bob"), "nickname": "Gym buddy"})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let remove_response = send_request(
+        &app,
+        "POST",
+        "/friends/remove",
+        scenario.cookie("alice"),
+        Some(json!({"friend_id": scenario.id("bob")})),
+    )
+    .await;
+    assert_eq!(remove_response.status(), StatusCode::OK);
 
-    let remove_payload = json!({"friend_id": user_a_id});
-    let remove_request = Request::builder()
-        .uri("/friends/remove")
-        .method("POST")
-        .header("cookie", cookie_b.clone())
-        .header("content-type", "application/json")
-        .body(Body::from(remove_payload.to_string()))
-        .unwrap();
+    // Either side may ask again; the request reopens the old rows as pending
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/request",
+        scenario.cookie("bob"),
+        Some(json!({"friend_username": "alice", "message": "Sorry!"})),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let relation: FriendshipRelation = serde_json::from_slice(&body_bytes(response).await).unwrap();
+    assert!(relation.pending);
+    assert_eq!(relation.direction.as_deref(), Some("outgoing"));
+
+    let pending = list_friends(&app, "/friends/list?pending=true", scenario.cookie("alice")).await;
+    let pending = pending["friends"].as_array().unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0]["direction"], "incoming", "Bob asked this time");
+    assert_eq!(pending[0]["message"], "Sorry!");
+
+    let accept_response = send_request(
+        &app,
+        "POST",
+        "/friends/accept",
+        scenario.cookie("alice"),
+        Some(json!({"friend_id": scenario.id("bob")})),
+    )
+    .await;
+    assert_eq!(accept_response.status(), StatusCode::OK);
 
-    let remove_response = app.router.clone().oneshot(remove_request).await.unwrap();
-    assert_eq!(remove_response.status(), StatusCode::OK);
+    // Nicknames from the earlier friendship are kept
+    let friends = list_friends(
+        &app,
+        "/friends/list?pending=false",
+        scenario.cookie("alice"),
+    )
+    .await;
+    let friends = friends["friends"].as_array().unwrap();
+    assert_eq!(friends.len(), 1);
+    assert_eq!(friends[0]["nickname"], "Gym buddy");
+    assert!(friends[0].get("message").is_none());
 
-    let reaccept_request = Request::builder()
-        .uri("/friends/accept")
-        .method("POST")
-        .header("cookie", cookie_b)
-        .header("content-type", "application/json")
-        .body(Body::from(accept_payload.to_string()))
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM friendship WHERE status = 'active' AND pending = 0 AND accepted_at IS NOT NULL AND status_changed_at IS NULL",
+            (),
+        )
+        .await
         .unwrap();
-
-    let reaccept_response = app.router.clone().oneshot(reaccept_request).await.unwrap();
-    assert_eq!(reaccept_response.status(), StatusCode::NOT_FOUND);
+    let count: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+    assert_eq!(count, 2, "Both directed rows are friends again");
 }
@@ -0,0 +1,163 @@
+/// Tests Z181-Z183: Expired idempotency keys
+///
+/// Split idempotency keys expire `IDEMPOTENCY_TTL_HOURS` after creation. An expired
+/// key is no longer replayed or checked against its payload, reserving a key sweeps
+/// the user's expired rows, and `CleanupIdempotencyKeysJob` deletes the rest every
+/// configured interval.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{FIXTURE_SPLIT_DATE, Scenario, ScenarioBuilder, money};
+use kash_server::constants::JOB_TYPE_CLEANUP_IDEMPOTENCY_KEYS;
+use kash_server::jobs::JobHandler;
+use kash_server::maintenance::CleanupIdempotencyKeysJob;
+use kash_server::models::{CreateSplitPayload, SplitMode, SplitParticipant};
+use kash_server::splits;
+use serde_json::Value;
+use time::{Duration, OffsetDateTime};
+
+// ---- Helpers ----
+
+const EXPIRED: &str = "2020-01-01T00:00:00Z";
+
+async fn expire_keys(app: &common::TestApp, user_id: &str) {
+    let conn = app.state.main_db.write().await;
+    conn.execute(
+        "UPDATE idempotency_keys SET expires_at = ? WHERE user_id = ?",
+        (EXPIRED, user_id),
+    )
+    .await
+    .expect("expire keys");
+}
+
+async fn keys_of(app: &common::TestApp, user_id: &str) -> Vec<String> {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT key FROM idempotency_keys WHERE user_id = ? ORDER BY key",
+            [user_id],
+        )
+        .await
+        .unwrap();
+    let mut keys = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        keys.push(row.get(0).unwrap());
+    }
+    keys
+}
+
+fn payload(
+    scenario: &Scenario,
+    payer: &str,
+    friend: &str,
+    key: &str,
+    total: f64,
+) -> CreateSplitPayload {
+    CreateSplitPayload {
+        idempotency_key: key.to_string(),
+        total_amount: money(total),
+        description: format!("{payer} split"),
+        date: FIXTURE_SPLIT_DATE.to_string(),
+        category_id: scenario.category_id(payer, "Dining").to_string(),
+        currency: None,
+        split_mode: SplitMode::Exact,
+        splits: vec![SplitParticipant {
+            user_id: scenario.id(friend).to_string(),
+            amount: money(total / 2.0),
+            percentage: None,
+        }],
+        legacy_full_total: false,
+    }
+}
+
+/// Alice and Bob each pay one split with the other (`fixture-split-1` and `-2`).
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob])
+        .category(&alice, "Dining")
+        .category(&bob, "Dining")
+        .friend(&alice, &bob)
+        .split(&alice, "Dining", 40.0, &[(&bob, 20.0)])
+        .split(&bob, "Dining", 30.0, &[(&alice, 15.0)])
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z181: An expired key is treated as unused, even with a different payload
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z181_expired_key_is_reusable() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z181").await;
+    let alice = scenario.id("alice_z181");
+    let first = &scenario.splits[0];
+
+    let reuse = payload(&scenario, "alice_z181", "bob_z181", "fixture-split-1", 90.0);
+    let error = splits::create_split_for_user(&app.state, alice, reuse)
+        .await
+        .expect_err("live key with another payload");
+    assert_eq!(error.0, StatusCode::CONFLICT);
+
+    expire_keys(&app, alice).await;
+    let reuse = payload(&scenario, "alice_z181", "bob_z181", "fixture-split-1", 90.0);
+    let (status, response) = splits::create_split_for_user(&app.state, alice, reuse)
+        .await
+        .expect("expired key starts a new split");
+    assert_eq!(status, StatusCode::CREATED);
+    assert_ne!(response.split_id, first.split_id);
+    assert_eq!(keys_of(&app, alice).await, vec!["fixture-split-1"]);
+}
+
+// ---------------------------------------------------------------------------
+// Z182: Reserving a key sweeps only the caller's expired keys
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z182_reserve_sweeps_own_expired_keys() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z182").await;
+    let (alice, bob) = (scenario.id("alice_z182"), scenario.id("bob_z182"));
+
+    expire_keys(&app, alice).await;
+    expire_keys(&app, bob).await;
+    let fresh = payload(&scenario, "alice_z182", "bob_z182", "z182-fresh", 10.0);
+    let (status, _) = splits::create_split_for_user(&app.state, alice, fresh)
+        .await
+        .expect("fresh split");
+    assert_eq!(status, StatusCode::CREATED);
+
+    assert_eq!(keys_of(&app, alice).await, vec!["z182-fresh"]);
+    assert_eq!(
+        keys_of(&app, bob).await,
+        vec!["fixture-split-2"],
+        "left for the periodic cleanup"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z183: The cleanup job deletes expired keys on its configured interval
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z183_cleanup_job_uses_configured_interval() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z183").await;
+    let (alice, bob) = (scenario.id("alice_z183"), scenario.id("bob_z183"));
+
+    let job = CleanupIdempotencyKeysJob {
+        interval: Duration::minutes(5),
+    };
+    assert_eq!(job.job_type(), JOB_TYPE_CLEANUP_IDEMPOTENCY_KEYS);
+    assert_eq!(job.interval(), Some(Duration::minutes(5)));
+
+    expire_keys(&app, bob).await;
+    job.run(&app.state.main_db, &Value::Null, OffsetDateTime::now_utc())
+        .await
+        .expect("cleanup");
+    assert_eq!(keys_of(&app, alice).await, vec!["fixture-split-1"]);
+    assert!(keys_of(&app, bob).await.is_empty());
+}
@@ -0,0 +1,209 @@
+/// Tests I1-I3: Idempotency body limit, replay metrics and lookup index
+///
+/// Split create responses up to `idempotency_max_body_bytes` are stored and
+/// replayed verbatim. Larger ones are stored as a digest only, so a retry with
+/// the same key gets 409 `REPLAY_UNAVAILABLE` and must use a new key. Hits,
+/// conflicts and unavailable replays are counted and exposed on
+/// `GET /admin/metrics`.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::TEST_ADMIN_TOKEN;
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::AppState;
+use kash_server::constants::REPLAY_UNAVAILABLE_MESSAGE;
+use kash_server::models::{CreateSplitPayload, MetricsSnapshot, SplitMode, SplitParticipant};
+use kash_server::splits;
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob])
+        .category(&alice, "Dining")
+        .friend(&alice, &bob)
+        .build(app)
+        .await
+}
+
+fn split_payload(scenario: &Scenario, suffix: &str, key: &str, total: f64) -> CreateSplitPayload {
+    let alice = format!("alice_{suffix}");
+    CreateSplitPayload {
+        idempotency_key: key.to_string(),
+        total_amount: money(total),
+        description: "Dinner".to_string(),
+        date: "2025-03-10".to_string(),
+        category_id: scenario.category_id(&alice, "Dining").to_string(),
+        currency: None,
+        split_mode: SplitMode::Exact,
+        splits: vec![SplitParticipant {
+            user_id: scenario.id(&format!("bob_{suffix}")).to_string(),
+            amount: money(total / 2.0),
+            percentage: None,
+        }],
+        legacy_full_total: false,
+    }
+}
+
+async fn admin_metrics(app: &common::TestApp) -> MetricsSnapshot {
+    let request = Request::builder()
+        .uri("/admin/metrics")
+        .method("GET")
+        .header("authorization", format!("Bearer {TEST_ADMIN_TOKEN}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+// ---------------------------------------------------------------------------
+// I1: The (user_id, endpoint, key) lookup is indexed
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn i1_lookup_index_exists() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let conn = app.state.main_db.read().await;
+
+    let mut rows = conn
+        .query("PRAGMA index_list(idempotency_keys)", ())
+        .await
+        .unwrap();
+    let mut names = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        names.push(row.get::<String>(1).unwrap());
+    }
+    assert!(
+        names.iter().any(|name| name == "idx_idempotency_lookup"),
+        "{names:?}"
+    );
+
+    let mut rows = conn
+        .query("PRAGMA index_info(idx_idempotency_lookup)", ())
+        .await
+        .unwrap();
+    let mut columns = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        columns.push(row.get::<String>(2).unwrap());
+    }
+    assert_eq!(columns, vec!["user_id", "endpoint", "key"]);
+}
+
+// ---------------------------------------------------------------------------
+// I2: Replays and conflicts are counted
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn i2_replays_and_conflicts_are_counted() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "i2").await;
+    let alice = scenario.id("alice_i2");
+
+    let payload = split_payload(&scenario, "i2", "key-i2", 100.0);
+    let (status, first) = splits::create_split_for_user(&app.state, alice, payload.clone())
+        .await
+        .expect("create");
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, replayed) = splits::create_split_for_user(&app.state, alice, payload)
+        .await
+        .expect("replay");
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(replayed.split_id, first.split_id);
+
+    let (status, _) = splits::create_split_for_user(
+        &app.state,
+        alice,
+        split_payload(&scenario, "i2", "key-i2", 80.0),
+    )
+    .await
+    .expect_err("different payload");
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    assert_eq!(
+        admin_metrics(&app).await,
+        MetricsSnapshot {
+            idempotency_hits: 1,
+            idempotency_conflicts: 1,
+            idempotency_replay_unavailable: 0,
+        }
+    );
+}
+
+// ---------------------------------------------------------------------------
+// I3: Oversized responses store a digest and cannot be replayed
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn i3_oversized_response_is_not_replayable() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "i3").await;
+    let alice = scenario.id("alice_i3");
+    let small_limit = AppState {
+        idempotency_max_body_bytes: 16,
+        ..app.state.clone()
+    };
+
+    let payload = split_payload(&scenario, "i3", "key-i3", 100.0);
+    let (status, _) = splits::create_split_for_user(&small_limit, alice, payload.clone())
+        .await
+        .expect("create");
+    assert_eq!(status, StatusCode::CREATED);
+
+    {
+        let conn = app.state.main_db.read().await;
+        let mut rows = conn
+            .query(
+                "SELECT response_body, response_digest FROM idempotency_keys WHERE key = 'key-i3'",
+                (),
+            )
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        assert_eq!(row.get::<String>(0).unwrap(), "");
+        assert_eq!(
+            row.get::<String>(1).unwrap().len(),
+            16,
+            "fnv1a-64 hex digest"
+        );
+    }
+
+    let (status, message) = splits::create_split_for_user(&small_limit, alice, payload)
+        .await
+        .expect_err("replay unavailable");
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(message, REPLAY_UNAVAILABLE_MESSAGE);
+
+    // No duplicate fanout happened, and a fresh key goes through.
+    {
+        let conn = app.state.main_db.read().await;
+        let mut rows = conn
+            .query(
+                "SELECT COUNT(DISTINCT split_id) FROM records WHERE split_id IS NOT NULL",
+                (),
+            )
+            .await
+            .unwrap();
+        let count: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+        assert_eq!(count, 1);
+    }
+    let (status, _) = splits::create_split_for_user(
+        &small_limit,
+        alice,
+        split_payload(&scenario, "i3", "key-i3-retry", 100.0),
+    )
+    .await
+    .expect("new key");
+    assert_eq!(status, StatusCode::CREATED);
+
+    assert_eq!(admin_metrics(&app).await.idempotency_replay_unavailable, 1);
+}
@@ -0,0 +1,305 @@
+/// Tests J1-J5: Job queue
+///
+/// Jobs live in the `jobs` table and are run by `run_due_jobs` at an injected
+/// `now`. Failures are retried with doubling backoff until `JOB_MAX_ATTEMPTS`,
+/// claims carry a lease so a dead worker's job is picked up again, and a worker
+/// only claims the job types it has handlers for.
+mod common;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use kash_server::admin::AdminActionError;
+use kash_server::config::FriendshipRetention;
+use kash_server::constants::*;
+use kash_server::jobs::{self, JobFuture, JobHandler, JobRegistry, JobRunReport};
+use kash_server::utils::precise_timestamp;
+use kash_server::{Db, maintenance, with_transaction};
+use serde_json::{Value, json};
+use time::{Duration, OffsetDateTime, format_description::well_known::Rfc3339};
+
+// ---- Helpers ----
+
+fn t0() -> OffsetDateTime {
+    OffsetDateTime::parse("2025-03-01T12:00:00Z", &Rfc3339).expect("rfc3339 timestamp")
+}
+
+/// Fails its first `failures` runs, then succeeds; counts every run.
+struct FlakyJob {
+    job_type: &'static str,
+    failures: u32,
+    runs: Arc<AtomicU32>,
+}
+
+impl JobHandler for FlakyJob {
+    fn job_type(&self) -> &'static str {
+        self.job_type
+    }
+
+    fn run<'a>(&'a self, _db: &'a Db, payload: &'a Value, _now: OffsetDateTime) -> JobFuture<'a> {
+        Box::pin(async move {
+            let run = self.runs.fetch_add(1, Ordering::SeqCst) + 1;
+            if run <= self.failures {
+                return Err(format!("boom {run} for {}", payload["target"]));
+            }
+            Ok(())
+        })
+    }
+}
+
+fn flaky(job_type: &'static str, failures: u32) -> (JobRegistry, Arc<AtomicU32>) {
+    let runs = Arc::new(AtomicU32::new(0));
+    let registry = JobRegistry::new().register(FlakyJob {
+        job_type,
+        failures,
+        runs: runs.clone(),
+    });
+    (registry, runs)
+}
+
+async fn enqueue(app: &common::TestApp, job_type: &str, run_at: OffsetDateTime) -> String {
+    let conn = app.state.main_db.write().await;
+    jobs::enqueue(&conn, job_type, &json!({ "target": "t" }), run_at)
+        .await
+        .expect("enqueue job")
+}
+
+#[derive(Debug)]
+struct JobRow {
+    state: String,
+    attempts: u32,
+    run_at: String,
+    last_error: Option<String>,
+}
+
+async fn job_row(app: &common::TestApp, id: &str) -> JobRow {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT state, attempts, run_at, last_error FROM jobs WHERE id = ?",
+            [id],
+        )
+        .await
+        .expect("query job");
+    let row = rows.next().await.expect("read row").expect("job row");
+    JobRow {
+        state: row.get(0).expect("state"),
+        attempts: row.get(1).expect("attempts"),
+        run_at: row.get(2).expect("run_at"),
+        last_error: row.get(3).expect("last_error"),
+    }
+}
+
+async fn queued_run_at(app: &common::TestApp, job_type: &str) -> Vec<String> {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT run_at FROM jobs WHERE job_type = ? AND state = ? ORDER BY run_at",
+            (job_type, JOB_STATE_QUEUED),
+        )
+        .await
+        .expect("query jobs");
+    let mut run_at = Vec::new();
+    while let Some(row) = rows.next().await.expect("read row") {
+        run_at.push(row.get::<String>(0).expect("run_at"));
+    }
+    run_at
+}
+
+async fn run(app: &common::TestApp, registry: &JobRegistry, now: OffsetDateTime) -> JobRunReport {
+    jobs::run_due_jobs(&app.state.main_db, registry, now)
+        .await
+        .expect("run due jobs")
+}
+
+// ---------------------------------------------------------------------------
+// J1: A failing job is retried with doubling backoff and succeeds later
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn j1_failures_retry_with_backoff() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (registry, runs) = flaky("flaky_j1", 2);
+    let id = enqueue(&app, "flaky_j1", t0()).await;
+
+    assert_eq!(
+        run(&app, &registry, t0() - Duration::seconds(1)).await,
+        JobRunReport::default(),
+        "not due yet"
+    );
+
+    let report = run(&app, &registry, t0()).await;
+    assert_eq!(report.retried, 1);
+    let row = job_row(&app, &id).await;
+    assert_eq!(row.state, JOB_STATE_QUEUED);
+    assert_eq!(row.attempts, 1);
+    assert_eq!(row.last_error.as_deref(), Some("boom 1 for \"t\""));
+    let first_retry = t0() + Duration::seconds(JOB_RETRY_BASE_SECS);
+    assert_eq!(row.run_at, precise_timestamp(first_retry));
+
+    // Just before the backoff runs out nothing happens.
+    run(&app, &registry, first_retry - Duration::seconds(1)).await;
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    run(&app, &registry, first_retry).await;
+    let row = job_row(&app, &id).await;
+    let second_retry = first_retry + Duration::seconds(2 * JOB_RETRY_BASE_SECS);
+    assert_eq!(row.run_at, precise_timestamp(second_retry), "delay doubles");
+
+    let report = run(&app, &registry, second_retry).await;
+    assert_eq!(report.succeeded, 1);
+    let row = job_row(&app, &id).await;
+    assert_eq!(row.state, JOB_STATE_DONE);
+    assert_eq!(row.attempts, 3);
+    assert_eq!(row.last_error, None);
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+    assert_eq!(
+        jobs::retry_delay(30),
+        Duration::seconds(JOB_RETRY_MAX_SECS),
+        "backoff is capped"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// J2: A job that keeps failing stops at the maximum number of attempts
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn j2_max_attempts_marks_job_failed() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (registry, runs) = flaky("flaky_j2", u32::MAX);
+    let id = enqueue(&app, "flaky_j2", t0()).await;
+
+    let mut now = t0();
+    for _ in 0..JOB_MAX_ATTEMPTS {
+        run(&app, &registry, now).await;
+        now += Duration::seconds(JOB_RETRY_MAX_SECS);
+    }
+    let row = job_row(&app, &id).await;
+    assert_eq!(row.state, JOB_STATE_FAILED);
+    assert_eq!(row.attempts, JOB_MAX_ATTEMPTS);
+    assert!(
+        row.last_error
+            .is_some_and(|error| error.starts_with("boom"))
+    );
+
+    run(&app, &registry, now + Duration::days(1)).await;
+    assert_eq!(runs.load(Ordering::SeqCst), JOB_MAX_ATTEMPTS, "never again");
+}
+
+// ---------------------------------------------------------------------------
+// J3: A claim whose worker died is retried once its lease expires
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn j3_expired_lease_is_reclaimed() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (registry, runs) = flaky("flaky_j3", 0);
+    let crashed = enqueue(&app, "flaky_j3", t0()).await;
+    let exhausted = enqueue(&app, "flaky_j3", t0()).await;
+    let lease_until = t0() + Duration::seconds(JOB_LEASE_SECS);
+    {
+        // As left behind by workers that died mid-run; one was on its last attempt.
+        let conn = app.state.main_db.write().await;
+        for (id, attempts) in [(&crashed, 1), (&exhausted, JOB_MAX_ATTEMPTS)] {
+            conn.execute(
+                "UPDATE jobs SET state = ?, attempts = ?, lease_until = ? WHERE id = ?",
+                (
+                    JOB_STATE_RUNNING,
+                    attempts,
+                    precise_timestamp(lease_until),
+                    id.as_str(),
+                ),
+            )
+            .await
+            .expect("simulate claim");
+        }
+    }
+
+    let report = run(&app, &registry, lease_until - Duration::seconds(1)).await;
+    assert_eq!(report, JobRunReport::default(), "lease still held");
+    assert_eq!(job_row(&app, &crashed).await.state, JOB_STATE_RUNNING);
+
+    let report = run(&app, &registry, lease_until).await;
+    assert_eq!(report.succeeded, 1);
+    assert_eq!(report.failed, 1);
+    let row = job_row(&app, &crashed).await;
+    assert_eq!(row.state, JOB_STATE_DONE);
+    assert_eq!(row.attempts, 2);
+    let row = job_row(&app, &exhausted).await;
+    assert_eq!(row.state, JOB_STATE_FAILED);
+    assert_eq!(row.last_error.as_deref(), Some("lease expired"));
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+}
+
+// ---------------------------------------------------------------------------
+// J4: Workers claim only registered types; enqueues roll back with their transaction
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn j4_unregistered_and_rolled_back_jobs_are_not_run() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (registry, runs) = flaky("flaky_j4", 0);
+    let foreign = enqueue(&app, "someone_elses_job", t0()).await;
+
+    let result: Result<(), AdminActionError> = with_transaction(&app.state.main_db, |conn| {
+        Box::pin(async move {
+            jobs::enqueue(conn, "flaky_j4", &json!({}), t0()).await?;
+            Err(AdminActionError::Conflict("domain write failed"))
+        })
+    })
+    .await;
+    assert!(result.is_err());
+
+    assert_eq!(run(&app, &registry, t0()).await, JobRunReport::default());
+    assert_eq!(runs.load(Ordering::SeqCst), 0);
+    assert_eq!(job_row(&app, &foreign).await.state, JOB_STATE_QUEUED);
+    assert!(queued_run_at(&app, "flaky_j4").await.is_empty());
+}
+
+// ---------------------------------------------------------------------------
+// J5: The server's maintenance jobs run and reschedule themselves
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn j5_server_jobs_recur() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let registry = maintenance::server_jobs(
+        FriendshipRetention {
+            unfriended_after_days: Some(DEFAULT_PRUNE_UNFRIENDED_AFTER_DAYS),
+            blocked_after_days: None,
+        },
+        Duration::seconds(MAINTENANCE_INTERVAL_SECS as i64),
+        None,
+    );
+    {
+        let conn = app.state.main_db.write().await;
+        for job_type in [
+            JOB_TYPE_PRUNE_FRIENDSHIPS,
+            JOB_TYPE_CLEANUP_IDEMPOTENCY_KEYS,
+        ] {
+            assert!(
+                jobs::ensure_scheduled(&conn, job_type, t0())
+                    .await
+                    .expect("schedule")
+            );
+            assert!(
+                !jobs::ensure_scheduled(&conn, job_type, t0())
+                    .await
+                    .expect("schedule"),
+                "one pending run per recurring job"
+            );
+        }
+    }
+
+    let report = run(&app, &registry, t0()).await;
+    assert_eq!(report.succeeded, 2);
+    let next = precise_timestamp(t0() + Duration::seconds(MAINTENANCE_INTERVAL_SECS as i64));
+    for job_type in [
+        JOB_TYPE_PRUNE_FRIENDSHIPS,
+        JOB_TYPE_CLEANUP_IDEMPOTENCY_KEYS,
+    ] {
+        assert_eq!(queued_run_at(&app, job_type).await, vec![next.clone()]);
+    }
+}
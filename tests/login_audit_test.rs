@@ -0,0 +1,265 @@
+/// Tests Z261-Z264: Login audit trail and account lockout
+///
+/// `auth::authenticate_user` records every attempt in `login_attempts` with its source,
+/// and `GET /auth/login-history` lists the caller's own, newest first. Ten wrong passwords
+/// in a row lock the account for 30 minutes: it is refused with `ACCOUNT_LOCKED` before
+/// the password is checked. Names no account holds lock the same way, so the lockout
+/// does not reveal which accounts exist. Attempts older than 90 days are purged.
+mod common;
+
+use axum::http::StatusCode;
+use kash_server::auth;
+use kash_server::constants::*;
+use kash_server::login_attempts::{locked_until, purge_old_login_attempts, record_login_attempt};
+use kash_server::models::LoginHistoryResponse;
+use time::{Duration, OffsetDateTime};
+
+// ---- Helpers ----
+
+async fn attempt(app: &common::TestApp, username: &str, password: &str) -> Option<StatusCode> {
+    auth::authenticate_user(
+        &app.state.main_db,
+        username,
+        password,
+        LOGIN_SOURCE_TELEGRAM,
+    )
+    .await
+    .err()
+    .map(|(status, _)| status)
+}
+
+async fn count_attempts(app: &common::TestApp, filter: &str) -> i64 {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            &format!("SELECT COUNT(*) FROM login_attempts WHERE {}", filter),
+            (),
+        )
+        .await
+        .unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+// ---------------------------------------------------------------------------
+// Z261: Attempts are recorded and listed for their own user, newest first
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z261_login_history_lists_own_attempts() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    common::create_test_user(&app.state, "alice_z261", "password123")
+        .await
+        .unwrap();
+    common::create_test_user(&app.state, "bob_z261", "password123")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        attempt(&app, "alice_z261", "wrong-password").await,
+        Some(StatusCode::UNAUTHORIZED)
+    );
+    assert_eq!(
+        attempt(&app, "nobody_z261", "password123").await,
+        Some(StatusCode::UNAUTHORIZED)
+    );
+    common::login_user(&app.router, "bob_z261", "password123")
+        .await
+        .unwrap();
+    let cookie = common::login_user(&app.router, "alice_z261", "password123")
+        .await
+        .unwrap();
+
+    let (status, body) = common::auth_request(&app.router, "GET", "/auth/login-history", &cookie)
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::OK);
+    let history: LoginHistoryResponse = serde_json::from_str(&body).unwrap();
+    let summary: Vec<_> = history
+        .attempts
+        .iter()
+        .map(|a| (a.success, a.source.as_str(), a.failure_reason.as_deref()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (true, LOGIN_SOURCE_WEB, None),
+            (
+                false,
+                LOGIN_SOURCE_TELEGRAM,
+                Some(LOGIN_FAILURE_INVALID_CREDENTIALS)
+            ),
+        ]
+    );
+
+    // Unknown names are kept without a user
+    assert_eq!(
+        count_attempts(&app, "username = 'nobody_z261' AND user_id IS NULL").await,
+        1
+    );
+
+    let (status, body) =
+        common::auth_request(&app.router, "GET", "/auth/login-history?limit=1", &cookie)
+            .await
+            .unwrap();
+    assert_eq!(status, StatusCode::OK);
+    let history: LoginHistoryResponse = serde_json::from_str(&body).unwrap();
+    assert_eq!(history.attempts.len(), 1);
+}
+
+// ---------------------------------------------------------------------------
+// Z262: Ten wrong passwords in a row lock the account for 30 minutes
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z262_consecutive_failures_lock_the_account() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    common::create_test_user(&app.state, "alice_z262", "password123")
+        .await
+        .unwrap();
+
+    // A success in between starts the count over
+    for _ in 0..LOGIN_LOCKOUT_FAILURES - 1 {
+        attempt(&app, "alice_z262", "wrong-password").await;
+    }
+    assert_eq!(attempt(&app, "alice_z262", "password123").await, None);
+    for _ in 0..LOGIN_LOCKOUT_FAILURES {
+        assert_eq!(
+            attempt(&app, "alice_z262", "wrong-password").await,
+            Some(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    // Right or wrong, the answer is the same and says nothing about the password
+    let right = auth::authenticate_user(
+        &app.state.main_db,
+        "alice_z262",
+        "password123",
+        LOGIN_SOURCE_WEB,
+    )
+    .await
+    .unwrap_err();
+    let wrong = auth::authenticate_user(
+        &app.state.main_db,
+        "alice_z262",
+        "wrong-password",
+        LOGIN_SOURCE_WEB,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        right,
+        (StatusCode::LOCKED, ACCOUNT_LOCKED_MESSAGE.to_string())
+    );
+    assert_eq!(right, wrong);
+    assert_eq!(
+        count_attempts(
+            &app,
+            &format!("failure_reason = '{}'", LOGIN_FAILURE_LOCKED)
+        )
+        .await,
+        2
+    );
+
+    // The lock ends 30 minutes after the last counted failure; refused attempts do not extend it
+    let now = OffsetDateTime::now_utc();
+    let conn = app.state.main_db.read().await;
+    let until = locked_until(&conn, "alice_z262", now)
+        .await
+        .unwrap()
+        .expect("locked");
+    assert!(until > now + Duration::minutes(LOGIN_LOCKOUT_MINUTES - 1));
+    assert!(until <= now + Duration::minutes(LOGIN_LOCKOUT_MINUTES));
+    assert_eq!(
+        locked_until(&conn, "alice_z262", until).await.unwrap(),
+        None,
+        "unlocked once the lockout has passed"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z263: Attempts older than the retention period are purged
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z263_old_attempts_are_purged() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    common::create_test_user(&app.state, "alice_z263", "password123")
+        .await
+        .unwrap();
+    let now = OffsetDateTime::now_utc();
+    {
+        let conn = app.state.main_db.write().await;
+        for days_ago in [
+            LOGIN_ATTEMPT_RETENTION_DAYS + 1,
+            LOGIN_ATTEMPT_RETENTION_DAYS - 1,
+        ] {
+            record_login_attempt(
+                &conn,
+                "alice_z263",
+                LOGIN_SOURCE_WEB,
+                None,
+                now - Duration::days(days_ago),
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    assert_eq!(
+        purge_old_login_attempts(&app.state.main_db, now)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(count_attempts(&app, "username = 'alice_z263'").await, 1);
+}
+
+// ---------------------------------------------------------------------------
+// Z264: Unknown names lock like real accounts
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z264_unknown_names_lock_like_accounts() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    common::create_test_user(&app.state, "bob_z264", "password123")
+        .await
+        .unwrap();
+
+    for username in ["bob_z264", "ghost_z264"] {
+        for _ in 0..LOGIN_LOCKOUT_FAILURES {
+            assert_eq!(
+                attempt(&app, username, "wrong-password").await,
+                Some(StatusCode::UNAUTHORIZED),
+                "{username}"
+            );
+        }
+    }
+
+    let real = auth::authenticate_user(
+        &app.state.main_db,
+        "bob_z264",
+        "wrong-password",
+        LOGIN_SOURCE_WEB,
+    )
+    .await
+    .unwrap_err();
+    let unknown = auth::authenticate_user(
+        &app.state.main_db,
+        "ghost_z264",
+        "wrong-password",
+        LOGIN_SOURCE_WEB,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        real,
+        (StatusCode::LOCKED, ACCOUNT_LOCKED_MESSAGE.to_string())
+    );
+    assert_eq!(real, unknown);
+
+    // Another name is unaffected
+    assert_eq!(
+        attempt(&app, "carol_z264", "wrong-password").await,
+        Some(StatusCode::UNAUTHORIZED)
+    );
+}
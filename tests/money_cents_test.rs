@@ -0,0 +1,208 @@
+/// Tests Z421-Z423: Amounts in integer cents
+///
+/// Amounts are stored and added up as whole cents behind `money::Money`. The JSON
+/// shape is unchanged: amounts are still plain numbers, and requests may send a
+/// number or a decimal string, but never more than two decimal places. Split
+/// shares are exact, so a split's parts always add back up to its total.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder};
+use common::send_json;
+use kash_server::models::{RecordSummaryResponse, SplitDetail};
+use kash_server::money::{Money, MoneyError};
+use serde_json::{Value, json};
+
+// ---- Helpers ----
+
+async fn create(app: &common::TestApp, scenario: &Scenario, user: &str, amount: Value) -> Value {
+    let (status, body) = send_json(
+        app,
+        "POST",
+        "/records",
+        scenario.cookie(user),
+        Some(json!({
+            "name": "Snack",
+            "amount": amount,
+            "category_id": scenario.category_id(user, "Dining"),
+            "date": "2025-10-01",
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    body
+}
+
+// ---------------------------------------------------------------------------
+// Z421: Money parses, prints and allocates in whole cents
+// ---------------------------------------------------------------------------
+
+#[test]
+fn z421_money_parses_prints_and_allocates() {
+    for (input, cents) in [
+        ("12.34", 1234),
+        ("-9.5", -950),
+        ("+980", 98000),
+        ("0.05", 5),
+        ("7.10", 710),
+        ("3.000", 300),
+    ] {
+        assert_eq!(
+            input.parse::<Money>(),
+            Ok(Money::from_cents(cents)),
+            "{input}"
+        );
+    }
+    for (input, error) in [
+        ("12.345", MoneyError::TooPrecise),
+        ("", MoneyError::Invalid),
+        ("1e3", MoneyError::Invalid),
+        ("NaN", MoneyError::Invalid),
+        ("99999999999999999", MoneyError::TooLarge),
+    ] {
+        assert_eq!(input.parse::<Money>(), Err(error), "{input}");
+    }
+    assert_eq!(Money::from_decimal(0.1 + 0.2), Err(MoneyError::TooPrecise));
+    assert_eq!(Money::from_decimal(-13.6), Ok(Money::from_cents(-1360)));
+
+    for (cents, shown) in [(-1250, "-12.5"), (98000, "980"), (5, "0.05"), (0, "0")] {
+        assert_eq!(Money::from_cents(cents).to_string(), shown);
+    }
+
+    let thirds = Money::from_cents(10000).allocate(&[1, 1, 1]);
+    assert_eq!(
+        thirds,
+        vec![
+            Money::from_cents(3334),
+            Money::from_cents(3333),
+            Money::from_cents(3333)
+        ]
+    );
+    assert_eq!(thirds.into_iter().sum::<Money>(), Money::from_cents(10000));
+    let weighted = Money::from_cents(-1001).allocate(&[50, 25, 25]);
+    assert_eq!(
+        weighted.iter().copied().sum::<Money>(),
+        Money::from_cents(-1001)
+    );
+    assert!(weighted.iter().all(|share| share.is_negative()));
+}
+
+// ---------------------------------------------------------------------------
+// Z422: Amounts keep their JSON shape and add up exactly
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z422_amounts_round_trip_and_sum_exactly() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .user("alice_z422")
+        .category("alice_z422", "Dining")
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z422");
+
+    let created = create(&app, &scenario, "alice_z422", json!(0.1)).await;
+    assert_eq!(created["amount"], json!(-0.1));
+    let uri = format!("/records/{}", created["id"].as_str().unwrap());
+    let (_, fetched) = send_json(&app, "GET", &uri, cookie, None).await;
+    assert_eq!(fetched["amount"], json!(-0.1));
+
+    let created = create(&app, &scenario, "alice_z422", json!("0.20")).await;
+    assert_eq!(
+        created["amount"],
+        json!(-0.2),
+        "strings come back as numbers"
+    );
+
+    for amount in [json!(12.345), json!("1.999"), json!("lots")] {
+        let (status, _) = send_json(
+            &app,
+            "POST",
+            "/records",
+            cookie,
+            Some(json!({
+                "name": "Too precise",
+                "amount": amount,
+                "category_id": scenario.category_id("alice_z422", "Dining"),
+                "date": "2025-10-01",
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{amount}");
+    }
+    let (status, _) = send_json(&app, "PUT", &uri, cookie, Some(json!({ "amount": 0.001 }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, body) = send_json(
+        &app,
+        "GET",
+        "/records/summary?start_date=2025-10-01&end_date=2025-10-31",
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["expense"], json!(-0.3), "0.1 + 0.2 is exactly 0.3");
+    let summary: RecordSummaryResponse = serde_json::from_value(body).expect("summary");
+    assert_eq!(summary.expense, Money::from_cents(-30));
+}
+
+// ---------------------------------------------------------------------------
+// Z423: A split's shares add back up to its total
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z423_split_shares_sum_to_total() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z423", "bob_z423", "carol_z423"])
+        .category("alice_z423", "Dining")
+        .friend("alice_z423", "bob_z423")
+        .friend("alice_z423", "carol_z423")
+        .split(
+            "alice_z423",
+            "Dining",
+            100.0,
+            &[("bob_z423", 33.33), ("carol_z423", 33.33)],
+        )
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z423");
+    let split = &scenario.splits[0];
+
+    let (_, payer) = send_json(
+        &app,
+        "GET",
+        &format!("/records/{}", split.payer_record_id),
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(
+        payer["amount"],
+        json!(-33.34),
+        "the payer keeps the odd cent"
+    );
+
+    let (status, body) = send_json(
+        &app,
+        "GET",
+        &format!("/splits/{}", split.split_id),
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let detail: SplitDetail = serde_json::from_value(body).expect("split detail");
+    let shares: Money = detail
+        .participants
+        .iter()
+        .map(|participant| participant.amount)
+        .sum();
+    assert_eq!(shares, Money::from_cents(6666));
+    assert_eq!(
+        shares + Money::from_cents(3334),
+        detail.total_amount,
+        "shares and the payer's part make up the total"
+    );
+}
@@ -0,0 +1,247 @@
+/// Tests M1-M4: Money formatting in bot replies
+///
+/// The bot shows amounts as signed, symbol-prefixed strings (`−NT$180`,
+/// `+NT$85,000`) in the record's currency, which new records take from the
+/// user's `currency_code` setting, defaulting to TWD.
+/// Edits show `old → new`, and `/recent` notes split records still pending
+/// the user's confirmation. The HTTP API keeps returning raw numbers.
+mod common;
+
+use axum::{http::StatusCode, response::Response};
+use common::fixtures::{ScenarioBuilder, money};
+use common::send_request;
+use kash_server::models::Record;
+use kash_server::money::{
+    PENDING_CONFIRMATION_NOTE, format_amount, format_amount_change, format_record_line,
+};
+use kash_server::records;
+use serde_json::{Value, json};
+
+// ---- Helpers ----
+
+async fn body_json(response: Response) -> Value {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).unwrap_or(Value::Null)
+}
+
+fn record(name: &str, amount: f64) -> Record {
+    Record {
+        id: "rec-m".to_string(),
+        name: name.to_string(),
+        amount: money(amount),
+        currency: "TWD".to_string(),
+        category_id: None,
+        date: "2025-03-10".to_string(),
+        seq: 1,
+        original_amount: None,
+        original_currency: None,
+        note: None,
+        tags: Vec::new(),
+        locked: false,
+        split_progress: None,
+        settled: None,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// M1: Amounts are signed and symbol-prefixed per currency
+// ---------------------------------------------------------------------------
+
+#[test]
+fn m1_format_amount_snapshots() {
+    let cases = [
+        (-180.0, "TWD", "\u{2212}NT$180"),
+        (85000.0, "TWD", "+NT$85,000"),
+        (-1234567.5, "TWD", "\u{2212}NT$1,234,567.50"),
+        (-12.3, "USD", "\u{2212}$12.30"),
+        (0.5, "EUR", "+€0.50"),
+        (-999.99, "GBP", "\u{2212}£999.99"),
+        (-1500.0, "JPY", "\u{2212}¥1,500"),
+        (-180.0, "CHF", "\u{2212}CHF 180"),
+        (0.0, "TWD", "+NT$0"),
+    ];
+    for (amount, code, expected) in cases {
+        assert_eq!(
+            format_amount(money(amount), code),
+            expected,
+            "{amount} {code}"
+        );
+    }
+}
+
+// ---------------------------------------------------------------------------
+// M2: Edit and /recent lines
+// ---------------------------------------------------------------------------
+
+#[test]
+fn m2_change_and_record_line_snapshots() {
+    assert_eq!(
+        format_amount_change(money(-180.0), money(-220.0), "TWD"),
+        "\u{2212}NT$180 → \u{2212}NT$220"
+    );
+    assert_eq!(
+        format_amount_change(money(-180.0), money(-180.0), "TWD"),
+        "\u{2212}NT$180",
+        "unchanged amounts show once"
+    );
+
+    let lunch = record("Lunch", -180.0);
+    assert_eq!(
+        format_record_line(&lunch, false),
+        "2025-03-10 | Lunch | \u{2212}NT$180"
+    );
+    assert_eq!(
+        format_record_line(&lunch, true),
+        format!("2025-03-10 | Lunch | \u{2212}NT$180 {PENDING_CONFIRMATION_NOTE}")
+    );
+}
+
+// ---------------------------------------------------------------------------
+// M3: The currency setting validates and round-trips
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn m3_currency_code_setting() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_m3"])
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_m3");
+
+    for payload in [
+        json!({ "currency_code": "XYZ" }),
+        json!({ "currency_code": "dollars" }),
+    ] {
+        let response = send_request(&app, "PUT", "/settings", cookie, Some(payload.clone())).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{payload}");
+    }
+
+    let response = send_request(
+        &app,
+        "PUT",
+        "/settings",
+        cookie,
+        Some(json!({ "currency_code": " usd " })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_json(response).await["currency_code"], "USD");
+
+    let response = send_request(&app, "GET", "/settings", cookie, None).await;
+    assert_eq!(body_json(response).await["currency_code"], "USD");
+
+    let response = send_request(
+        &app,
+        "PUT",
+        "/settings",
+        cookie,
+        Some(json!({ "currency_code": null })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_json(response).await["currency_code"], Value::Null);
+}
+
+// ---------------------------------------------------------------------------
+// M4: /recent lines use each record's currency and mark pending records
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn m4_recent_lines_use_currency_and_pending() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_m4"])
+        .category("alice_m4", "Dining")
+        .income_category("alice_m4", "Salary")
+        .build(&app)
+        .await;
+    let alice = scenario.id("alice_m4");
+    let cookie = scenario.cookie("alice_m4");
+
+    for (name, amount, category) in [("Lunch", 180.0, "Dining"), ("Pay", 85000.0, "Salary")] {
+        let response = send_request(
+            &app,
+            "POST",
+            "/records",
+            cookie,
+            Some(json!({
+                "name": name,
+                "amount": amount,
+                "category_id": scenario.category_id("alice_m4", category),
+                "date": "2025-03-10",
+            })),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    // Mark the lunch as a split record still awaiting confirmation.
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "UPDATE records SET pending = 1 WHERE owner_user_id = ? AND name = 'Lunch'",
+            [alice],
+        )
+        .await
+        .unwrap();
+    }
+
+    let lines = records::recent_record_lines(&app.state.main_db, alice, 10)
+        .await
+        .expect("recent lines");
+    assert_eq!(
+        lines,
+        vec![
+            "2025-03-10 | Pay | +NT$85,000".to_string(),
+            format!("2025-03-10 | Lunch | \u{2212}NT$180 {PENDING_CONFIRMATION_NOTE}"),
+        ]
+    );
+
+    let response = send_request(
+        &app,
+        "PUT",
+        "/settings",
+        cookie,
+        Some(json!({ "currency_code": "EUR" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = send_request(
+        &app,
+        "POST",
+        "/records",
+        cookie,
+        Some(json!({
+            "name": "Coffee",
+            "amount": 4.5,
+            "category_id": scenario.category_id("alice_m4", "Dining"),
+            "date": "2025-03-11",
+        })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let lines = records::recent_record_lines(&app.state.main_db, alice, 2)
+        .await
+        .expect("recent lines");
+    assert_eq!(
+        lines,
+        vec![
+            "2025-03-11 | Coffee | \u{2212}€4.50".to_string(),
+            "2025-03-10 | Pay | +NT$85,000".to_string(),
+        ]
+    );
+
+    // The API still returns raw signed numbers.
+    let response = send_request(&app, "GET", "/records", cookie, None).await;
+    let listed = body_json(response).await;
+    let amounts: Vec<f64> = listed["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|record| record["amount"].as_f64().unwrap())
+        .collect();
+    assert!(amounts.contains(&-180.0) && amounts.contains(&85000.0));
+}
@@ -0,0 +1,253 @@
+/// Tests Z451-Z453: Notification feed
+///
+/// `GET /notifications?limit&offset` lists what other people did that concerns the
+/// user, newest first, with `unread_count` and `total_count` over the whole feed:
+/// `split_created` for each participant of a new split, `split_settled` for the other
+/// side of a settled share, `friend_request_received` and `friend_accepted`. Each is
+/// written in the same transaction as its change. `POST /notifications/{id}/read`
+/// marks one read; read notifications are purged 60 days later.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::ScenarioBuilder;
+use common::send_json;
+use kash_server::constants::*;
+use kash_server::models::{Notification, NotificationListResponse};
+use kash_server::notifications;
+use serde_json::json;
+use time::{Duration, OffsetDateTime};
+
+// ---- Helpers ----
+
+async fn feed(app: &common::TestApp, cookie: &str, query: &str) -> NotificationListResponse {
+    let uri = format!("/notifications{query}");
+    let (status, body) = send_json(app, "GET", &uri, cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    serde_json::from_value(body).expect("notifications")
+}
+
+fn kinds(response: &NotificationListResponse) -> Vec<&str> {
+    response
+        .notifications
+        .iter()
+        .map(|notification| notification.kind.as_str())
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Z451: Splits notify their participants, settling notifies the other side
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z451_split_events_reach_the_other_side() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z451", "bob_z451", "carol_z451"])
+        .category("alice_z451", "Dining")
+        .friend("alice_z451", "bob_z451")
+        .friend("alice_z451", "carol_z451")
+        .split(
+            "alice_z451",
+            "Dining",
+            90.0,
+            &[("bob_z451", 30.0), ("carol_z451", 30.0)],
+        )
+        .build(&app)
+        .await;
+    let split = &scenario.splits[0];
+    let alice = scenario.id("alice_z451");
+
+    let bob = feed(&app, scenario.cookie("bob_z451"), "").await;
+    assert_eq!(
+        kinds(&bob),
+        vec![
+            NOTIFICATION_KIND_SPLIT_CREATED,
+            NOTIFICATION_KIND_FRIEND_REQUEST_RECEIVED
+        ]
+    );
+    assert_eq!(
+        bob.notifications[0].payload,
+        json!({
+            "split_id": split.split_id,
+            "record_id": split.pending_record_ids[0],
+            "initiator_user_id": alice,
+        })
+    );
+    assert_eq!(
+        kinds(&feed(&app, scenario.cookie("alice_z451"), "").await),
+        vec![
+            NOTIFICATION_KIND_FRIEND_ACCEPTED,
+            NOTIFICATION_KIND_FRIEND_ACCEPTED,
+        ]
+    );
+
+    // The initiator settling Bob's share tells Bob
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/records/{}/settle", split.pending_record_ids[0]),
+        scenario.cookie("alice_z451"),
+        Some(json!({ "split_id": split.split_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let bob = feed(&app, scenario.cookie("bob_z451"), "").await;
+    assert_eq!(
+        kinds(&bob),
+        vec![
+            NOTIFICATION_KIND_SPLIT_SETTLED,
+            NOTIFICATION_KIND_SPLIT_CREATED,
+            NOTIFICATION_KIND_FRIEND_REQUEST_RECEIVED
+        ]
+    );
+    assert_eq!(bob.notifications[0].payload["settled_by_user_id"], alice);
+
+    // Settling the rest of the split tells only those it settled
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        &format!("/splits/{}/settle-all", split.split_id),
+        scenario.cookie("alice_z451"),
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        feed(&app, scenario.cookie("bob_z451"), "")
+            .await
+            .total_count,
+        3
+    );
+    let carol = feed(&app, scenario.cookie("carol_z451"), "").await;
+    assert_eq!(
+        kinds(&carol),
+        vec![
+            NOTIFICATION_KIND_SPLIT_SETTLED,
+            NOTIFICATION_KIND_SPLIT_CREATED,
+            NOTIFICATION_KIND_FRIEND_REQUEST_RECEIVED
+        ]
+    );
+    assert_eq!(
+        carol.notifications[0].payload["record_id"],
+        split.pending_record_ids[1]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z452: Friend events, paging, unread count and marking read
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z452_friend_events_page_and_mark_read() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z452", "bob_z452", "carol_z452"])
+        .friend_request("bob_z452", "alice_z452")
+        .friend_request("carol_z452", "alice_z452")
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z452");
+
+    let all = feed(&app, cookie, "").await;
+    assert_eq!(all.total_count, 2);
+    assert_eq!(all.unread_count, 2);
+    assert_eq!(
+        all.notifications
+            .iter()
+            .map(|notification| notification.payload["from_user_id"].clone())
+            .collect::<Vec<_>>(),
+        vec![
+            json!(scenario.id("carol_z452")),
+            json!(scenario.id("bob_z452"))
+        ],
+        "newest first"
+    );
+
+    let page = feed(&app, cookie, "?limit=1&offset=1").await;
+    assert_eq!(page.notifications, all.notifications[1..].to_vec());
+    assert_eq!((page.total_count, page.limit, page.offset), (2, 1, 1));
+
+    let uri = format!("/notifications/{}/read", all.notifications[0].id);
+    let (status, body) = send_json(&app, "POST", &uri, cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let read: Notification = serde_json::from_value(body).expect("notification");
+    assert!(read.read_at.is_some());
+    let (_, again) = send_json(&app, "POST", &uri, cookie, None).await;
+    assert_eq!(
+        again["read_at"],
+        json!(read.read_at),
+        "the first read sticks"
+    );
+    assert_eq!(feed(&app, cookie, "").await.unread_count, 1);
+
+    // Someone else's notification is not found
+    let (status, _) = send_json(&app, "POST", &uri, scenario.cookie("bob_z452"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/friends/accept",
+        cookie,
+        Some(json!({ "friend_id": scenario.id("bob_z452") })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let bob = feed(&app, scenario.cookie("bob_z452"), "").await;
+    assert_eq!(kinds(&bob), vec![NOTIFICATION_KIND_FRIEND_ACCEPTED]);
+    assert_eq!(
+        bob.notifications[0].payload,
+        json!({ "friend_id": scenario.id("alice_z452") })
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z453: Read notifications are purged after 60 days, unread ones are kept
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z453_purge_removes_only_old_read_notifications() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z453", "bob_z453", "carol_z453", "dave_z453"])
+        .friend_request("bob_z453", "alice_z453")
+        .friend_request("carol_z453", "alice_z453")
+        .friend_request("dave_z453", "alice_z453")
+        .build(&app)
+        .await;
+    let alice = scenario.id("alice_z453");
+    let ids: Vec<String> = feed(&app, scenario.cookie("alice_z453"), "")
+        .await
+        .notifications
+        .into_iter()
+        .map(|notification| notification.id)
+        .collect();
+
+    let now = OffsetDateTime::now_utc();
+    {
+        let conn = app.state.main_db.write().await;
+        let long_ago = now - Duration::days(NOTIFICATION_READ_RETENTION_DAYS + 1);
+        notifications::mark_read(&conn, alice, &ids[0], long_ago)
+            .await
+            .expect("mark read")
+            .expect("notification");
+        notifications::mark_read(&conn, alice, &ids[1], now - Duration::days(1))
+            .await
+            .expect("mark read")
+            .expect("notification");
+    }
+
+    let purged = notifications::purge_read_notifications(&app.state.main_db, now)
+        .await
+        .expect("purge");
+    assert_eq!(purged, 1);
+    let left = feed(&app, scenario.cookie("alice_z453"), "").await;
+    assert_eq!(
+        left.notifications
+            .iter()
+            .map(|notification| notification.id.as_str())
+            .collect::<Vec<_>>(),
+        vec![ids[1].as_str(), ids[2].as_str()]
+    );
+    assert_eq!(left.unread_count, 1);
+}
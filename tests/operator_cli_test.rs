@@ -0,0 +1,325 @@
+/// Tests L1-L5: Operator CLI
+///
+/// `kash-server user|db|export ...` runs `cli::run` against the data directory
+/// instead of starting the server. Each command reports text or `--json` and an
+/// exit code, and refuses to run while a server holds the instance lock.
+mod common;
+
+use std::ffi::OsString;
+
+use common::fixtures::{FIXTURE_PASSWORD, Scenario, ScenarioBuilder, money};
+use kash_server::cli::{self, CliOutput, Command, Invocation};
+use kash_server::config::{CliConfig, DatabaseLocation, DbBusyRetry, PasswordHashParams};
+use kash_server::constants::*;
+use kash_server::instance_lock;
+use kash_server::models::{CreateRecordPayload, UserSummary};
+use kash_server::records;
+use serde_json::Value;
+
+// ---- Helpers ----
+
+fn args(line: &str) -> Vec<OsString> {
+    line.split_whitespace().map(OsString::from).collect()
+}
+
+fn cli_config(data_path: &str) -> CliConfig {
+    CliConfig {
+        data_path: data_path.to_string(),
+        record_encryption_key: None,
+        password_hash: PasswordHashParams::new(
+            MIN_ARGON2_MEMORY_KIB,
+            MIN_ARGON2_ITERATIONS,
+            MIN_ARGON2_PARALLELISM,
+        )
+        .expect("minimum params"),
+        db_busy_retry: DbBusyRetry::default(),
+        database: DatabaseLocation::Local,
+    }
+}
+
+async fn run(data_path: &str, line: &str) -> CliOutput {
+    let invocation = cli::parse_args(args(line))
+        .expect("valid arguments")
+        .expect("a command");
+    cli::run(&cli_config(data_path), &invocation).await
+}
+
+/// Runs `line --json` and parses the output.
+async fn run_json(data_path: &str, line: &str) -> (i32, Value) {
+    let output = run(data_path, &format!("{line} --json")).await;
+    let body = serde_json::from_str(&output.text).expect("json output");
+    (output.exit_code, body)
+}
+
+async fn execute(app: &common::TestApp, sql: &str) {
+    let conn = app.state.main_db.write().await;
+    conn.execute(sql, ()).await.expect("execute");
+}
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob])
+        .category(&alice, "Dining")
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// L1: Arguments select a command, or none to start the server
+// ---------------------------------------------------------------------------
+
+#[test]
+fn l1_parse_args() {
+    assert_eq!(
+        cli::parse_args(args("")),
+        Ok(None),
+        "no command runs the server"
+    );
+    assert_eq!(
+        cli::parse_args(args("user reset-password alice --json")),
+        Ok(Some(Invocation {
+            command: Command::UserResetPassword {
+                username: "alice".to_string()
+            },
+            json: true,
+        }))
+    );
+    assert_eq!(
+        cli::parse_args(args("export --out a.json --user alice")),
+        Ok(Some(Invocation {
+            command: Command::Export {
+                username: "alice".to_string(),
+                out: "a.json".into(),
+            },
+            json: false,
+        }))
+    );
+    assert_eq!(
+        cli::parse_args(args("db check")).map(|i| i.map(|i| i.command)),
+        Ok(Some(Command::DbCheck))
+    );
+
+    for invalid in [
+        "user",
+        "user unlock",
+        "user delete alice",
+        "db vacuum",
+        "export --user alice",
+        "db check extra",
+        "--json",
+        "serve",
+    ] {
+        assert!(cli::parse_args(args(invalid)).is_err(), "{invalid}");
+    }
+}
+
+// ---------------------------------------------------------------------------
+// L2: user list, unlock and reset-password act on the accounts
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn l2_user_commands() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "l2").await;
+    execute(
+        &app,
+        "UPDATE users SET disabled_at = '2025-01-01T00:00:00Z' WHERE name = 'bob_l2'",
+    )
+    .await;
+
+    let (code, body) = run_json(&app.data_path, "user list").await;
+    assert_eq!(code, CLI_EXIT_OK);
+    let users: Vec<UserSummary> = serde_json::from_value(body["users"].clone()).expect("users");
+    let names: Vec<&str> = users.iter().map(|user| user.username.as_str()).collect();
+    assert_eq!(names, vec!["alice_l2", "bob_l2"]);
+    assert_eq!(users[0].id, scenario.id("alice_l2"));
+    assert!(users[0].last_login_at.is_some(), "fixtures log in");
+    assert!(users[1].disabled_at.is_some());
+    let text = run(&app.data_path, "user list").await.text;
+    assert!(
+        text.lines()
+            .any(|line| line.starts_with("bob_l2") && line.contains("disabled"))
+    );
+
+    assert!(
+        common::login_user(&app.router, "bob_l2", FIXTURE_PASSWORD)
+            .await
+            .is_err()
+    );
+    let (code, body) = run_json(&app.data_path, "user unlock bob_l2").await;
+    assert_eq!(code, CLI_EXIT_OK);
+    assert_eq!(body["unlocked"], true);
+    assert!(
+        common::login_user(&app.router, "bob_l2", FIXTURE_PASSWORD)
+            .await
+            .is_ok()
+    );
+    let (_, body) = run_json(&app.data_path, "user unlock bob_l2").await;
+    assert_eq!(body["unlocked"], false, "already enabled");
+
+    let (code, body) = run_json(&app.data_path, "user reset-password alice_l2").await;
+    assert_eq!(code, CLI_EXIT_OK);
+    let password = body["password"].as_str().expect("new password");
+    assert_eq!(password.len(), CLI_RESET_PASSWORD_LENGTH);
+    assert!(
+        common::login_user(&app.router, "alice_l2", FIXTURE_PASSWORD)
+            .await
+            .is_err()
+    );
+    assert!(
+        common::login_user(&app.router, "alice_l2", password)
+            .await
+            .is_ok()
+    );
+
+    let (code, body) = run_json(&app.data_path, "user unlock nobody_l2").await;
+    assert_eq!(code, CLI_EXIT_FAILURE);
+    assert_eq!(body["error"], "no user named 'nobody_l2'");
+    let output = run(&app.data_path, "user reset-password nobody_l2").await;
+    assert_eq!(output.exit_code, CLI_EXIT_FAILURE);
+    assert!(output.text.starts_with("error: "), "{}", output.text);
+}
+
+// ---------------------------------------------------------------------------
+// L3: db check reports problems that db migrate repairs
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn l3_db_check_and_migrate() {
+    let app = common::setup_test_app().await.expect("setup failed");
+
+    let (code, body) = run_json(&app.data_path, "db check").await;
+    assert_eq!(code, CLI_EXIT_OK);
+    assert_eq!(body["healthy"], true);
+    let (_, body) = run_json(&app.data_path, "db migrate").await;
+    assert_eq!(body["created_tables"], serde_json::json!([]));
+
+    execute(&app, "DROP TABLE jobs").await;
+    let (code, body) = run_json(&app.data_path, "db check").await;
+    assert_eq!(code, CLI_EXIT_FAILURE);
+    assert_eq!(
+        body["report"]["missing_tables"],
+        serde_json::json!(["jobs"])
+    );
+    assert!(
+        run(&app.data_path, "db check")
+            .await
+            .text
+            .contains("missing tables (run `db migrate`): jobs")
+    );
+
+    let (code, body) = run_json(&app.data_path, "db migrate").await;
+    assert_eq!(code, CLI_EXIT_OK);
+    assert_eq!(body["created_tables"], serde_json::json!(["jobs"]));
+    assert_eq!(run(&app.data_path, "db check").await.exit_code, CLI_EXIT_OK);
+
+    // Checking never creates a database.
+    let empty = tempfile::tempdir().expect("tempdir");
+    let empty = empty.path().to_string_lossy().to_string();
+    let output = run(&empty, "db check").await;
+    assert_eq!(output.exit_code, CLI_EXIT_FAILURE);
+    assert!(output.text.contains("no database at"), "{}", output.text);
+    assert!(!kash_server::database::main_db_path(&empty).exists());
+}
+
+// ---------------------------------------------------------------------------
+// L4: export writes the same archive as GET /export
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn l4_export_writes_archive() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "l4").await;
+    for name in ["Lunch", "Dinner"] {
+        records::create_record_for_user(
+            &app.state.main_db,
+            scenario.id("alice_l4"),
+            CreateRecordPayload {
+                name: name.to_string(),
+                amount: money(12.0),
+                currency: None,
+                category_id: scenario.category_id("alice_l4", "Dining").to_string(),
+                date: "2025-03-10".to_string(),
+                original_amount: None,
+                original_currency: None,
+                note: None,
+                tags: Vec::new(),
+            },
+            None,
+            false,
+        )
+        .await
+        .expect("create record");
+    }
+    let out = std::path::Path::new(&app.data_path).join("alice.json");
+
+    let line = format!("export --user alice_l4 --out {}", out.display());
+    let (code, body) = run_json(&app.data_path, &line).await;
+    assert_eq!(code, CLI_EXIT_OK);
+    assert_eq!(body["records"], 2);
+
+    let archive: Value =
+        serde_json::from_slice(&std::fs::read(&out).expect("archive written")).expect("json");
+    let (status, api) =
+        common::auth_request(&app.router, "GET", "/export", scenario.cookie("alice_l4"))
+            .await
+            .expect("export");
+    assert_eq!(status, axum::http::StatusCode::OK);
+    let mut api: Value = serde_json::from_str(&api).expect("json");
+    api["exported_at"] = archive["exported_at"].clone();
+    assert_eq!(archive, api);
+
+    let output = run(&app.data_path, &line.replace("alice_l4", "nobody_l4")).await;
+    assert_eq!(output.exit_code, CLI_EXIT_FAILURE);
+}
+
+// ---------------------------------------------------------------------------
+// L5: Commands refuse to run while a server holds the data directory
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn l5_live_server_lock_is_respected() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    scenario(&app, "l5").await;
+
+    let lock = instance_lock::acquire(&app.data_path).expect("server lock");
+    assert!(
+        instance_lock::acquire(&app.data_path).is_err(),
+        "a second server cannot start either"
+    );
+    for line in [
+        "user list",
+        "user unlock bob_l5",
+        "user reset-password alice_l5",
+        "db check",
+        "db migrate",
+    ] {
+        let (code, body) = run_json(&app.data_path, line).await;
+        assert_eq!(code, CLI_EXIT_LOCKED, "{line}");
+        assert!(
+            body["error"]
+                .as_str()
+                .is_some_and(|error| error.contains("locked by a running kash-server")),
+            "{body}"
+        );
+    }
+    assert!(
+        common::login_user(&app.router, "alice_l5", FIXTURE_PASSWORD)
+            .await
+            .is_ok(),
+        "reset-password did not run"
+    );
+
+    drop(lock);
+    assert_eq!(
+        run(&app.data_path, "user list").await.exit_code,
+        CLI_EXIT_OK
+    );
+    assert_eq!(
+        run(&app.data_path, "--help").await.text,
+        cli::USAGE,
+        "help never needs the lock"
+    );
+}
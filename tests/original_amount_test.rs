@@ -0,0 +1,244 @@
+/// Tests F1-F4: Original (pre-conversion) amounts
+///
+/// Records can carry the foreign `original_amount` and `original_currency` they
+/// were converted from. The pair is display-only: `amount` stays authoritative
+/// and keeps its category sign. Both fields are given together or not at all,
+/// the currency must be an ISO 4217 code and the amount finite. The pair shows
+/// up in record responses and in `GET /export`.
+mod common;
+
+use axum::{http::StatusCode, response::Response};
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use common::send_request;
+use kash_server::models::{CreateRecordPayload, Record};
+use kash_server::records;
+use serde_json::{Value, json};
+
+// ---- Helpers ----
+
+async fn body_json(response: Response) -> Value {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).unwrap_or(Value::Null)
+}
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice])
+        .category(&alice, "Travel")
+        .build(app)
+        .await
+}
+
+fn record_payload(scenario: &Scenario, user: &str, extra: Value) -> Value {
+    let mut payload = json!({
+        "name": "Museum",
+        "amount": 13.6,
+        "category_id": scenario.category_id(user, "Travel"),
+        "date": "2025-03-10",
+    });
+    for (key, value) in extra.as_object().unwrap() {
+        payload[key] = value.clone();
+    }
+    payload
+}
+
+async fn create(app: &common::TestApp, scenario: &Scenario, user: &str, extra: Value) -> Response {
+    send_request(
+        app,
+        "POST",
+        "/records",
+        scenario.cookie(user),
+        Some(record_payload(scenario, user, extra)),
+    )
+    .await
+}
+
+// ---------------------------------------------------------------------------
+// F1: The pair round-trips through create, list and export
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn f1_original_amount_round_trips() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "f1").await;
+    let cookie = scenario.cookie("alice_f1");
+
+    let response = create(
+        &app,
+        &scenario,
+        "alice_f1",
+        json!({ "original_amount": 12.5, "original_currency": " eur " }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created: Record = serde_json::from_value(body_json(response).await).unwrap();
+    assert_eq!(
+        created.amount,
+        money(-13.6),
+        "amount keeps the expense sign"
+    );
+    assert_eq!(created.original_amount, Some(12.5));
+    assert_eq!(created.original_currency.as_deref(), Some("EUR"));
+
+    let response = send_request(&app, "GET", "/records", cookie, None).await;
+    let listed = body_json(response).await;
+    assert_eq!(listed["records"][0]["original_amount"], 12.5);
+    assert_eq!(listed["records"][0]["original_currency"], "EUR");
+
+    let response = send_request(&app, "GET", "/export", cookie, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let export = body_json(response).await;
+    let exported = &export["records"][0];
+    assert_eq!(exported["id"], created.id.as_str());
+    assert_eq!(exported["original_amount"], 12.5);
+    assert_eq!(exported["original_currency"], "EUR");
+
+    // Records without the pair omit both fields.
+    let response = create(&app, &scenario, "alice_f1", json!({})).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let plain = body_json(response).await;
+    assert!(plain.get("original_amount").is_none());
+    assert!(plain.get("original_currency").is_none());
+}
+
+// ---------------------------------------------------------------------------
+// F2: Both fields or neither, with a known currency
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn f2_create_validates_the_pair() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "f2").await;
+
+    for extra in [
+        json!({ "original_amount": 12.5 }),
+        json!({ "original_currency": "EUR" }),
+        json!({ "original_amount": 12.5, "original_currency": "XYZ" }),
+        json!({ "original_amount": 12.5, "original_currency": "EURO" }),
+    ] {
+        let response = create(&app, &scenario, "alice_f2", extra.clone()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{extra}");
+    }
+
+    // JSON cannot carry NaN or infinity, but library callers can.
+    let (status, _) = records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id("alice_f2"),
+        CreateRecordPayload {
+            name: "Museum".to_string(),
+            amount: money(13.6),
+            currency: None,
+            category_id: scenario.category_id("alice_f2", "Travel").to_string(),
+            date: "2025-03-10".to_string(),
+            original_amount: Some(f64::INFINITY),
+            original_currency: Some("EUR".to_string()),
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect_err("non-finite original amount");
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let response = send_request(&app, "GET", "/records", scenario.cookie("alice_f2"), None).await;
+    assert_eq!(body_json(response).await["total_count"], 0);
+}
+
+// ---------------------------------------------------------------------------
+// F3: Updates set, keep and clear the pair together
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn f3_update_sets_keeps_and_clears_the_pair() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "f3").await;
+    let cookie = scenario.cookie("alice_f3");
+
+    let response = create(&app, &scenario, "alice_f3", json!({})).await;
+    let record_id = body_json(response).await["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let uri = format!("/records/{record_id}");
+
+    let response = send_request(
+        &app,
+        "PUT",
+        &uri,
+        cookie,
+        Some(json!({ "original_amount": 2000, "original_currency": "JPY" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let updated = body_json(response).await;
+    assert_eq!(updated["original_amount"], 2000.0);
+    assert_eq!(updated["original_currency"], "JPY");
+
+    let response = send_request(&app, "PUT", &uri, cookie, Some(json!({ "name": "Temple" }))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let updated = body_json(response).await;
+    assert_eq!(
+        updated["original_currency"], "JPY",
+        "untouched by other edits"
+    );
+
+    for payload in [
+        json!({ "original_currency": "USD" }),
+        json!({ "original_amount": null }),
+        json!({ "original_amount": 15, "original_currency": null }),
+    ] {
+        let response = send_request(&app, "PUT", &uri, cookie, Some(payload.clone())).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{payload}");
+    }
+
+    let response = send_request(
+        &app,
+        "PUT",
+        &uri,
+        cookie,
+        Some(json!({ "original_amount": null, "original_currency": null })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let updated = body_json(response).await;
+    assert!(updated.get("original_amount").is_none());
+    assert!(updated.get("original_currency").is_none());
+}
+
+// ---------------------------------------------------------------------------
+// F4: Totals only use the home-currency amount
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn f4_totals_ignore_original_amounts() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "f4").await;
+
+    for (amount, original) in [(13.6, 12.5), (10.9, 10.0)] {
+        let response = create(
+            &app,
+            &scenario,
+            "alice_f4",
+            json!({ "amount": amount, "original_amount": original, "original_currency": "EUR" }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    let response = send_request(
+        &app,
+        "GET",
+        "/export?categories_only=true",
+        scenario.cookie("alice_f4"),
+        None,
+    )
+    .await;
+    let export = body_json(response).await;
+    let total = export["category_totals"][0]["total"].as_f64().unwrap();
+    assert!((total - -24.5).abs() < 1e-9, "total {total}");
+}
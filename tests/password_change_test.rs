@@ -0,0 +1,203 @@
+/// Tests X1-X3: Password changes
+///
+/// `POST /auth/change-password` replaces the logged-in user's password after
+/// re-checking the current one, applies the registration length rule to the new
+/// one and rotates the session id so the old cookie stops working. Wrong current
+/// passwords spend failed-login attempts, and API tokens cannot make the change.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    response::Response,
+};
+use common::fixtures::{FIXTURE_PASSWORD, ScenarioBuilder};
+use kash_server::constants::{DEFAULT_LOGIN_RATE_LIMIT_MAX_ATTEMPTS, DEFAULT_MIN_PASSWORD_LENGTH};
+use serde_json::json;
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    body: Body,
+) -> Response {
+    let request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap();
+    app.router.clone().oneshot(request).await.unwrap()
+}
+
+async fn change_password(
+    app: &common::TestApp,
+    cookie: &str,
+    current_password: &str,
+    new_password: &str,
+) -> Response {
+    let payload = json!({ "current_password": current_password, "new_password": new_password });
+    send(
+        app,
+        "POST",
+        "/auth/change-password",
+        cookie,
+        Body::from(payload.to_string()),
+    )
+    .await
+}
+
+async fn me_status(app: &common::TestApp, cookie: &str) -> StatusCode {
+    send(app, "GET", "/auth/me", cookie, Body::empty())
+        .await
+        .status()
+}
+
+async fn can_login(app: &common::TestApp, username: &str, password: &str) -> bool {
+    common::login_user(&app.router, username, password)
+        .await
+        .is_ok()
+}
+
+// ---------------------------------------------------------------------------
+// X1: The new password replaces the old one and the session is rotated
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn x1_change_password_rotates_session() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_x1"])
+        .build(&app)
+        .await;
+    let old_cookie = scenario.cookie("alice_x1");
+
+    let response = change_password(&app, old_cookie, FIXTURE_PASSWORD, "brand-new-secret").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let new_cookie = response
+        .headers()
+        .get("set-cookie")
+        .and_then(|v| v.to_str().ok())
+        .expect("rotated session cookie")
+        .to_string();
+
+    assert_eq!(me_status(&app, &new_cookie).await, StatusCode::OK);
+    assert_eq!(
+        me_status(&app, old_cookie).await,
+        StatusCode::UNAUTHORIZED,
+        "the pre-change session id must no longer be valid"
+    );
+    assert!(!can_login(&app, "alice_x1", FIXTURE_PASSWORD).await);
+    assert!(can_login(&app, "alice_x1", "brand-new-secret").await);
+}
+
+// ---------------------------------------------------------------------------
+// X2: A wrong current password is rejected and changes nothing
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn x2_wrong_current_password_is_unauthorized() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new().users(&["bob_x2"]).build(&app).await;
+    let cookie = scenario.cookie("bob_x2");
+
+    let response = change_password(&app, cookie, "not-my-password", "brand-new-secret").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        me_status(&app, cookie).await,
+        StatusCode::OK,
+        "still logged in"
+    );
+    assert!(can_login(&app, "bob_x2", FIXTURE_PASSWORD).await);
+    assert!(!can_login(&app, "bob_x2", "brand-new-secret").await);
+}
+
+// ---------------------------------------------------------------------------
+// X3: Weak or unchanged new passwords are 400; login is required
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn x3_weak_new_password_is_rejected() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["carol_x3"])
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("carol_x3");
+
+    let too_short = "x".repeat(DEFAULT_MIN_PASSWORD_LENGTH - 1);
+    for (current, new) in [
+        (FIXTURE_PASSWORD, too_short.as_str()),
+        (FIXTURE_PASSWORD, ""),
+        (FIXTURE_PASSWORD, FIXTURE_PASSWORD),
+        ("", "brand-new-secret"),
+    ] {
+        let response = change_password(&app, cookie, current, new).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::BAD_REQUEST,
+            "{current:?} -> {new:?}"
+        );
+    }
+    assert!(can_login(&app, "carol_x3", FIXTURE_PASSWORD).await);
+
+    let response = change_password(&app, "", FIXTURE_PASSWORD, "brand-new-secret").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+// ---------------------------------------------------------------------------
+// X4: Re-checks share the failed-login budget and need a session, not a token
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn x4_password_recheck_is_rate_limited_and_session_only() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new().users(&["dave_x4"]).build(&app).await;
+    let cookie = scenario.cookie("dave_x4");
+
+    let response = send(
+        &app,
+        "POST",
+        "/auth/tokens",
+        cookie,
+        Body::from(json!({ "name": "cron" }).to_string()),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("token body");
+    let created: serde_json::Value = serde_json::from_slice(&bytes).expect("token json");
+    let request = Request::builder()
+        .uri("/auth/change-password")
+        .method("POST")
+        .header(
+            "authorization",
+            format!("Bearer {}", created["token"].as_str().expect("token")),
+        )
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({ "current_password": FIXTURE_PASSWORD, "new_password": "brand-new-secret" })
+                .to_string(),
+        ))
+        .expect("request");
+    let response = app.router.clone().oneshot(request).await.expect("response");
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "tokens cannot");
+
+    for _ in 0..DEFAULT_LOGIN_RATE_LIMIT_MAX_ATTEMPTS {
+        let response = change_password(&app, cookie, "not-my-password", "brand-new-secret").await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let response = change_password(&app, cookie, FIXTURE_PASSWORD, "brand-new-secret").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key("retry-after"));
+    assert!(
+        !can_login(&app, "dave_x4", FIXTURE_PASSWORD).await,
+        "the login budget is spent too"
+    );
+}
@@ -0,0 +1,163 @@
+/// Tests Z361-Z363: Password policy
+///
+/// New passwords, at registration or through `POST /auth/change-password`, must be
+/// at least `PASSWORD_MIN_LENGTH` characters (default 10), not on the bundled
+/// common-password list and not the username. Each rule has its own error code.
+/// Stored passwords are not rechecked, so older accounts still log in.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{FIXTURE_PASSWORD, ScenarioBuilder};
+use kash_server::AppState;
+use kash_server::config::PasswordPolicy;
+use kash_server::config_sources::ConfigSources;
+use kash_server::constants::*;
+use kash_server::rate_limit::ClientIp;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn post(
+    app: &common::TestApp,
+    uri: &str,
+    cookie: &str,
+    payload: Value,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(Body::from(payload.to_string()))
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+async fn register(app: &common::TestApp, username: &str, password: &str) -> (StatusCode, Value) {
+    post(
+        app,
+        "/auth/register",
+        "",
+        json!({ "username": username, "password": password }),
+    )
+    .await
+}
+
+// ---------------------------------------------------------------------------
+// Z361: Registration names the rule a password breaks
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z361_registration_rejects_weak_passwords_with_codes() {
+    let app = common::setup_test_app().await.expect("setup failed");
+
+    for (password, code) in [
+        ("short-pw1", ERROR_CODE_PASSWORD_TOO_SHORT),
+        ("password123", ERROR_CODE_PASSWORD_TOO_COMMON),
+        ("QWERTYUIOP", ERROR_CODE_PASSWORD_TOO_COMMON),
+        ("Alice_Z361", ERROR_CODE_PASSWORD_MATCHES_USERNAME),
+    ] {
+        let (status, body) = register(&app, "alice_z361", password).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{password:?}");
+        assert_eq!(body["code"], code, "{password:?}");
+    }
+
+    let (status, _) = register(&app, "alice_z361", "correct horse battery").await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+// ---------------------------------------------------------------------------
+// Z362: Password changes follow the policy; weak stored passwords still log in
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z362_change_password_is_validated_but_old_passwords_still_work() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    // The fixture password is on the common list; it predates the policy.
+    let scenario = ScenarioBuilder::new()
+        .users(&["robert_z362"])
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("robert_z362");
+
+    for (new_password, code) in [
+        ("letmein", ERROR_CODE_PASSWORD_TOO_SHORT),
+        ("iloveyou123", ERROR_CODE_PASSWORD_TOO_COMMON),
+        ("ROBERT_Z362", ERROR_CODE_PASSWORD_MATCHES_USERNAME),
+    ] {
+        let (status, body) = post(
+            &app,
+            "/auth/change-password",
+            cookie,
+            json!({ "current_password": FIXTURE_PASSWORD, "new_password": new_password }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{new_password:?}");
+        assert_eq!(body["code"], code, "{new_password:?}");
+    }
+
+    common::login_user(&app.router, "robert_z362", FIXTURE_PASSWORD)
+        .await
+        .expect("weak stored password still logs in");
+}
+
+// ---------------------------------------------------------------------------
+// Z363: PASSWORD_MIN_LENGTH is configurable, never below the floor
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z363_min_length_is_configurable() {
+    let sources = |value: &str| {
+        ConfigSources::new(
+            vec![("PASSWORD_MIN_LENGTH".to_string(), value.to_string())],
+            None,
+        )
+        .expect("sources")
+    };
+    assert_eq!(
+        PasswordPolicy::from_sources(&sources("14"))
+            .expect("valid length")
+            .min_length,
+        14
+    );
+    let too_low = (MIN_PASSWORD_LENGTH_FLOOR - 1).to_string();
+    for value in [too_low.as_str(), "ten"] {
+        assert!(
+            PasswordPolicy::from_sources(&sources(value)).is_err(),
+            "{value:?}"
+        );
+    }
+    assert_eq!(
+        PasswordPolicy::from_sources(&ConfigSources::new(vec![], None).expect("sources"))
+            .expect("default")
+            .min_length,
+        DEFAULT_MIN_PASSWORD_LENGTH
+    );
+
+    let app = common::setup_test_app().await.expect("setup failed");
+    let strict = AppState {
+        password_policy: PasswordPolicy { min_length: 24 },
+        ..app.state.clone()
+    };
+    let payload = json!({ "username": "carol_z363", "password": "correct horse battery" });
+    let result = kash_server::auth::register(
+        axum::extract::State(strict),
+        ClientIp(None),
+        axum::Json(serde_json::from_value(payload).expect("payload")),
+    )
+    .await;
+    let error = result.expect_err("shorter than the configured minimum");
+    assert_eq!(error.code, ERROR_CODE_PASSWORD_TOO_SHORT);
+}
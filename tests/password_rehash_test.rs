@@ -0,0 +1,191 @@
+/// Tests K1-K4: Configurable password hashing and rehash-on-login
+///
+/// New hashes use the installed `PasswordHashParams`. A successful login whose
+/// stored hash was produced with weaker parameters rehashes the password with
+/// the current ones; current hashes and failed logins leave the row alone.
+mod common;
+
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHash, PasswordHasher, SaltString, rand_core::OsRng},
+};
+use axum::http::StatusCode;
+use kash_server::auth;
+use kash_server::config::{ConfigError, PasswordHashParams};
+use kash_server::constants::*;
+
+// ---- Helpers ----
+
+/// Every test in this binary installs the same parameters; only the first install sticks.
+fn install_params() -> PasswordHashParams {
+    let params = PasswordHashParams::new(MIN_ARGON2_MEMORY_KIB, 2, 1).expect("valid params");
+    auth::install_password_hash_params(params);
+    params
+}
+
+async fn stored_hash(app: &common::TestApp, user_id: &str) -> String {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query("SELECT password_hash FROM users WHERE id = ?", [user_id])
+        .await
+        .expect("query hash");
+    let row = rows.next().await.expect("read row").expect("user row");
+    row.get(0).expect("password_hash")
+}
+
+fn stored_costs(hash: &str) -> (u32, u32, u32) {
+    let parsed = PasswordHash::new(hash).expect("PHC string");
+    let params = argon2::Params::try_from(&parsed).expect("argon2 params");
+    (params.m_cost(), params.t_cost(), params.p_cost())
+}
+
+/// Overwrites the user's hash with one made at the minimum Argon2 cost.
+async fn seed_weak_hash(app: &common::TestApp, user_id: &str, password: &str) -> String {
+    let params = argon2::Params::new(
+        argon2::Params::MIN_M_COST,
+        argon2::Params::MIN_T_COST,
+        argon2::Params::MIN_P_COST,
+        None,
+    )
+    .expect("minimum params");
+    let hash = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+        .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))
+        .expect("hash password")
+        .to_string();
+    let conn = app.state.main_db.write().await;
+    conn.execute(
+        "UPDATE users SET password_hash = ? WHERE id = ?",
+        (hash.as_str(), user_id),
+    )
+    .await
+    .expect("seed hash");
+    hash
+}
+
+// ---------------------------------------------------------------------------
+// K1: Parameters outside their ranges are rejected
+// ---------------------------------------------------------------------------
+
+#[test]
+fn k1_params_are_range_checked() {
+    assert_eq!(
+        PasswordHashParams::default(),
+        PasswordHashParams::new(
+            DEFAULT_ARGON2_MEMORY_KIB,
+            DEFAULT_ARGON2_ITERATIONS,
+            DEFAULT_ARGON2_PARALLELISM
+        )
+        .expect("defaults are valid")
+    );
+
+    for (memory_kib, iterations, parallelism, name) in [
+        (MIN_ARGON2_MEMORY_KIB - 1, 2, 1, "ARGON2_MEMORY_KIB"),
+        (MAX_ARGON2_MEMORY_KIB + 1, 2, 1, "ARGON2_MEMORY_KIB"),
+        (MIN_ARGON2_MEMORY_KIB, 0, 1, "ARGON2_ITERATIONS"),
+        (
+            MIN_ARGON2_MEMORY_KIB,
+            MAX_ARGON2_ITERATIONS + 1,
+            1,
+            "ARGON2_ITERATIONS",
+        ),
+        (MIN_ARGON2_MEMORY_KIB, 2, 0, "ARGON2_PARALLELISM"),
+        (
+            MIN_ARGON2_MEMORY_KIB,
+            2,
+            MAX_ARGON2_PARALLELISM + 1,
+            "ARGON2_PARALLELISM",
+        ),
+    ] {
+        let error =
+            PasswordHashParams::new(memory_kib, iterations, parallelism).expect_err("out of range");
+        assert!(
+            matches!(error, ConfigError::InvalidPasswordHashParams(_)),
+            "{error:?}"
+        );
+        assert!(error.to_string().contains(name), "{error}");
+    }
+}
+
+// ---------------------------------------------------------------------------
+// K2: Registration hashes with the installed parameters
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn k2_new_hashes_use_installed_params() {
+    let params = install_params();
+    let app = common::setup_test_app().await.expect("setup failed");
+
+    let user_id = common::create_test_user(&app.state, "hasher_k2", "password123")
+        .await
+        .expect("create user");
+
+    let hash = stored_hash(&app, &user_id).await;
+    assert!(hash.starts_with("$argon2id$v=19$"), "{hash}");
+    assert_eq!(
+        stored_costs(&hash),
+        (params.memory_kib, params.iterations, params.parallelism)
+    );
+}
+
+// ---------------------------------------------------------------------------
+// K3: A successful login upgrades a weak hash once
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn k3_login_rehashes_weak_hash() {
+    let params = install_params();
+    let app = common::setup_test_app().await.expect("setup failed");
+    let user_id = common::create_test_user(&app.state, "weakling_k3", "password123")
+        .await
+        .expect("create user");
+    let weak = seed_weak_hash(&app, &user_id, "password123").await;
+
+    common::login_user(&app.router, "weakling_k3", "password123")
+        .await
+        .expect("login with weak hash");
+
+    let upgraded = stored_hash(&app, &user_id).await;
+    assert_ne!(upgraded, weak);
+    assert_eq!(
+        stored_costs(&upgraded),
+        (params.memory_kib, params.iterations, params.parallelism)
+    );
+
+    // The upgraded hash still verifies and is current, so it is kept as is.
+    auth::authenticate_user(
+        &app.state.main_db,
+        "weakling_k3",
+        "password123",
+        LOGIN_SOURCE_WEB,
+    )
+    .await
+    .expect("login with upgraded hash");
+    assert_eq!(stored_hash(&app, &user_id).await, upgraded);
+}
+
+// ---------------------------------------------------------------------------
+// K4: A failed login never rehashes
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn k4_failed_login_keeps_weak_hash() {
+    install_params();
+    let app = common::setup_test_app().await.expect("setup failed");
+    let user_id = common::create_test_user(&app.state, "guarded_k4", "password123")
+        .await
+        .expect("create user");
+    let weak = seed_weak_hash(&app, &user_id, "password123").await;
+
+    let result = auth::authenticate_user(
+        &app.state.main_db,
+        "guarded_k4",
+        "wrong-password",
+        LOGIN_SOURCE_WEB,
+    )
+    .await;
+    assert!(
+        matches!(result, Err((StatusCode::UNAUTHORIZED, _))),
+        "wrong password is refused"
+    );
+    assert_eq!(stored_hash(&app, &user_id).await, weak);
+}
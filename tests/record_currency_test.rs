@@ -0,0 +1,350 @@
+/// Tests Z411-Z413: Record currencies
+///
+/// Every record carries an ISO 4217 `currency`, defaulting to the owner's
+/// `currency_code` setting. `GET /records/summary` totals each currency on its
+/// own, and every record of a split carries the split's `currency`, the payer's
+/// setting unless the payload names one.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use common::send_json;
+use kash_server::constants::*;
+use kash_server::models::{CreateSplitPayload, RecordSummaryResponse, SplitMode, SplitParticipant};
+use kash_server::splits;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> (StatusCode, String) {
+    let builder = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("cookie", cookie);
+    let request = match payload {
+        Some(payload) => builder
+            .header("content-type", "application/json")
+            .body(Body::from(payload.to_string())),
+        None => builder.body(Body::empty()),
+    }
+    .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+async fn set_currency(app: &common::TestApp, cookie: &str, code: &str) {
+    let (status, body) = send_json(
+        app,
+        "PUT",
+        "/settings",
+        cookie,
+        Some(json!({ "currency_code": code })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+}
+
+async fn create(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    category: &str,
+    amount: f64,
+    currency: Option<&str>,
+) -> Value {
+    let mut payload = json!({
+        "name": format!("{category} {amount}"),
+        "amount": amount,
+        "category_id": scenario.category_id(user, category),
+        "date": "2025-09-10",
+    });
+    if let Some(currency) = currency {
+        payload["currency"] = json!(currency);
+    }
+    let (status, body) = send_json(
+        app,
+        "POST",
+        "/records",
+        scenario.cookie(user),
+        Some(payload),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    body
+}
+
+fn split_payload(scenario: &Scenario, key: &str, currency: Option<&str>) -> CreateSplitPayload {
+    CreateSplitPayload {
+        idempotency_key: key.to_string(),
+        total_amount: money(3000.0),
+        description: "Izakaya".to_string(),
+        date: "2025-09-12".to_string(),
+        category_id: scenario.category_id("alice_z413", "Dining").to_string(),
+        currency: currency.map(str::to_string),
+        split_mode: SplitMode::Exact,
+        splits: vec![SplitParticipant {
+            user_id: scenario.id("bob_z413").to_string(),
+            amount: money(1500.0),
+            percentage: None,
+        }],
+        legacy_full_total: false,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Z411: Records default to the user's currency and accept another one
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z411_record_currency_defaults_and_validates() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .user("alice_z411")
+        .category("alice_z411", "Dining")
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z411");
+
+    let default = create(&app, &scenario, "alice_z411", "Dining", 120.0, None).await;
+    assert_eq!(default["currency"], DEFAULT_CURRENCY_CODE);
+    let yen = create(
+        &app,
+        &scenario,
+        "alice_z411",
+        "Dining",
+        980.0,
+        Some(" jpy "),
+    )
+    .await;
+    assert_eq!(yen["currency"], "JPY");
+
+    set_currency(&app, cookie, "EUR").await;
+    let euro = create(&app, &scenario, "alice_z411", "Dining", 9.5, None).await;
+    assert_eq!(euro["currency"], "EUR");
+    // Existing records keep the currency they were saved in
+    let uri = format!("/records/{}", default["id"].as_str().unwrap());
+    let (_, detail) = send_json(&app, "GET", &uri, cookie, None).await;
+    assert_eq!(detail["currency"], DEFAULT_CURRENCY_CODE);
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &uri,
+        cookie,
+        Some(json!({ "currency": "usd" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["currency"], "USD");
+    assert_eq!(body["amount"], -120.0);
+
+    for payload in [
+        json!({ "name": "Bad", "amount": 1.0, "currency": "XYZ",
+                "category_id": scenario.category_id("alice_z411", "Dining"), "date": "2025-09-10" }),
+        json!({ "name": "Bad", "amount": 1.0, "currency": "",
+                "category_id": scenario.category_id("alice_z411", "Dining"), "date": "2025-09-10" }),
+    ] {
+        let (status, _) = send_json(&app, "POST", "/records", cookie, Some(payload)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &uri,
+        cookie,
+        Some(json!({ "currency": "yen" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // The CSV export names each row's currency, and an import honours the column
+    let (_, csv) = send(&app, "GET", "/records/export", cookie, None).await;
+    assert!(csv.starts_with(&format!("{RECORDS_CSV_HEADER}\n")), "{csv}");
+    assert!(csv.contains(",Dining 980,-980,JPY,Dining,"), "{csv}");
+    let import = "name,amount,currency,category,date\nTrain,-30,chf,Dining,2025-09-11\nBus,-2,,Dining,2025-09-11\n";
+    let request = Request::builder()
+        .uri("/records/import")
+        .method("POST")
+        .header("cookie", cookie)
+        .header("content-type", "text/csv")
+        .body(Body::from(import))
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let (_, body) = send_json(
+        &app,
+        "GET",
+        "/records?start_date=2025-09-11&end_date=2025-09-11",
+        cookie,
+        None,
+    )
+    .await;
+    let mut imported: Vec<(&str, &str)> = body["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|record| {
+            (
+                record["name"].as_str().unwrap(),
+                record["currency"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    imported.sort();
+    assert_eq!(imported, vec![("Bus", "EUR"), ("Train", "CHF")]);
+}
+
+// ---------------------------------------------------------------------------
+// Z412: The summary totals each currency separately
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z412_summary_groups_by_currency() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .user("bob_z412")
+        .category("bob_z412", "Dining")
+        .income_category("bob_z412", "Salary")
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("bob_z412");
+    set_currency(&app, cookie, "EUR").await;
+
+    create(&app, &scenario, "bob_z412", "Salary", 2000.0, None).await;
+    create(&app, &scenario, "bob_z412", "Dining", 40.0, None).await;
+    create(&app, &scenario, "bob_z412", "Dining", 1200.0, Some("JPY")).await;
+    create(&app, &scenario, "bob_z412", "Dining", 800.0, Some("JPY")).await;
+
+    let (status, body) = send_json(
+        &app,
+        "GET",
+        "/records/summary?start_date=2025-09-01&end_date=2025-09-30",
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let summary: RecordSummaryResponse = serde_json::from_value(body).expect("summary");
+    let categories: Vec<(&str, &str, f64, u32)> = summary
+        .categories
+        .iter()
+        .map(|category| {
+            (
+                category.category_name.as_deref().unwrap_or_default(),
+                category.currency.as_str(),
+                category.total.to_decimal(),
+                category.record_count,
+            )
+        })
+        .collect();
+    assert_eq!(
+        categories,
+        vec![
+            ("Salary", "EUR", 2000.0, 1),
+            ("Dining", "EUR", -40.0, 1),
+            ("Dining", "JPY", -2000.0, 2),
+        ]
+    );
+    assert_eq!(summary.currency, "EUR");
+    assert_eq!(
+        (summary.income, summary.expense, summary.net),
+        (money(2000.0), money(-40.0), money(1960.0))
+    );
+    let currencies: Vec<(&str, f64, f64, f64)> = summary
+        .currencies
+        .iter()
+        .map(|total| {
+            (
+                total.currency.as_str(),
+                total.income.to_decimal(),
+                total.expense.to_decimal(),
+                total.net.to_decimal(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        currencies,
+        vec![
+            ("EUR", 2000.0, -40.0, 1960.0),
+            ("JPY", 0.0, -2000.0, -2000.0)
+        ]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z413: Split records share the split's currency, whatever the participants keep
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z413_split_records_share_its_currency() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z413", "bob_z413"])
+        .category("alice_z413", "Dining")
+        .friend("alice_z413", "bob_z413")
+        .build(&app)
+        .await;
+    set_currency(&app, scenario.cookie("alice_z413"), "JPY").await;
+
+    let error = splits::create_split_for_user(
+        &app.state,
+        scenario.id("alice_z413"),
+        split_payload(&scenario, "z413-invalid", Some("yen")),
+    )
+    .await
+    .expect_err("not an ISO 4217 code");
+    assert_eq!(error.0, StatusCode::BAD_REQUEST);
+
+    // Bob keeps his records in the default currency; the split is in Alice's
+    let (status, created) = splits::create_split_for_user(
+        &app.state,
+        scenario.id("alice_z413"),
+        split_payload(&scenario, "z413-default", None),
+    )
+    .await
+    .expect("split");
+    assert_eq!(status, StatusCode::CREATED);
+    let (_, explicit) = splits::create_split_for_user(
+        &app.state,
+        scenario.id("alice_z413"),
+        split_payload(&scenario, "z413-explicit", Some("eur")),
+    )
+    .await
+    .expect("split in EUR");
+
+    for (created, currency) in [(&created, "JPY"), (&explicit, "EUR")] {
+        for (user, record_id) in [
+            ("alice_z413", &created.payer_record_id),
+            ("bob_z413", &created.pending_record_ids[0]),
+        ] {
+            let uri = format!("/records/{record_id}");
+            let (_, record) = send_json(&app, "GET", &uri, scenario.cookie(user), None).await;
+            assert_eq!(record["currency"], currency, "{user}");
+        }
+    }
+
+    // Like its amount, a split record's currency is fixed
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/records/{}", created.payer_record_id),
+        scenario.cookie("alice_z413"),
+        Some(json!({ "currency": "EUR" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
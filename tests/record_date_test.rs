@@ -0,0 +1,301 @@
+/// Tests V1-V5: Record dates validated at the DB layer
+///
+/// `records.date` carries a CHECK that only admits real `YYYY-MM-DD` days, and
+/// every write path stores dates through `utils::to_db_date`. On databases
+/// created before the CHECK, `init_main_db` first normalizes malformed dates
+/// that still name a day, then rebuilds the table; while any date cannot be
+/// fixed the CHECK is held back and the records are reported.
+mod common;
+
+use common::fixtures::{ScenarioBuilder, money};
+use kash_server::constants::DEFAULT_CURRENCY_CODE;
+use kash_server::database::{self, RecordDateReport};
+use kash_server::record_repo::{self, NewRecord};
+use kash_server::utils::to_db_date;
+use libsql::Builder;
+use std::path::Path;
+
+// ---- Helpers ----
+
+// `records` as created by builds before the date CHECK.
+const CREATE_LEGACY_RECORDS_TABLE: &str = r#"
+CREATE TABLE records (
+    id               TEXT    PRIMARY KEY,
+    owner_user_id    TEXT    NOT NULL,
+    name             TEXT    NOT NULL,
+    amount           REAL    NOT NULL,
+    category_id      TEXT,
+    date             TEXT    NOT NULL
+);
+"#;
+
+/// Creates `users.db` in `data_path` with a legacy `records` table holding `rows`.
+async fn seed_legacy_records(data_path: &str, rows: &[(&str, &str)]) {
+    let db = Builder::new_local(Path::new(data_path).join("users.db"))
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute(CREATE_LEGACY_RECORDS_TABLE, ()).await.unwrap();
+    for (id, date) in rows {
+        conn.execute(
+            "INSERT INTO records (id, owner_user_id, name, amount, category_id, date) VALUES (?, 'user-v', 'Lunch', -120.0, NULL, ?)",
+            (*id, *date),
+        )
+        .await
+        .unwrap();
+    }
+}
+
+async fn stored_date(conn: &libsql::Connection, id: &str) -> String {
+    let mut rows = conn
+        .query("SELECT date FROM records WHERE id = ?", [id])
+        .await
+        .unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+async fn insert_raw_date(conn: &libsql::Connection, id: &str, date: &str) -> bool {
+    conn.execute(
+        "INSERT INTO records (id, owner_user_id, name, amount, category_id, date) VALUES (?, 'user-v', 'Raw', -1.0, NULL, ?)",
+        (id, date),
+    )
+    .await
+    .is_ok()
+}
+
+// ---------------------------------------------------------------------------
+// V1: to_db_date pads and rejects
+// ---------------------------------------------------------------------------
+
+#[test]
+fn v1_to_db_date_normalizes_or_rejects() {
+    assert_eq!(to_db_date("2025-03-02").unwrap(), "2025-03-02");
+    assert_eq!(to_db_date("2025-3-2").unwrap(), "2025-03-02");
+    assert_eq!(to_db_date(" 2025-12-31 ").unwrap(), "2025-12-31");
+    assert_eq!(to_db_date("2024-2-29").unwrap(), "2024-02-29");
+
+    for garbage in [
+        "",
+        "yesterday",
+        "2025/03/02",
+        "25-03-02",
+        "2025-003-02",
+        "2025-13-01",
+        "2025-02-29",
+        "2025-03-02T10:00:00",
+        "+2025-03-02",
+    ] {
+        assert!(
+            to_db_date(garbage).is_err(),
+            "{garbage:?} should be rejected"
+        );
+    }
+}
+
+// ---------------------------------------------------------------------------
+// V2: A fresh database refuses malformed dates from any writer
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn v2_check_rejects_malformed_direct_writes() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let db = database::init_main_db(&temp_dir.path().to_string_lossy())
+        .await
+        .expect("init");
+    let conn = db.write().await;
+
+    assert!(insert_raw_date(&conn, "ok", "2025-03-02").await);
+    for (id, date) in [
+        ("short", "2025-3-2"),
+        ("spaced", " 2025-03-02"),
+        ("no-day", "2025-02-30"),
+        ("words", "soon"),
+    ] {
+        assert!(
+            !insert_raw_date(&conn, id, date).await,
+            "{date:?} should violate the CHECK"
+        );
+    }
+    assert!(
+        conn.execute("UPDATE records SET date = '2025-3-2' WHERE id = 'ok'", ())
+            .await
+            .is_err()
+    );
+    assert_eq!(stored_date(&conn, "ok").await, "2025-03-02");
+}
+
+// ---------------------------------------------------------------------------
+// V3: Startup normalizes legacy dates, then adds the CHECK
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn v3_migration_normalizes_then_constrains() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let data_path = temp_dir.path().to_string_lossy().to_string();
+    seed_legacy_records(
+        &data_path,
+        &[
+            ("rec-ok", "2025-03-10"),
+            ("rec-short", "2025-3-2"),
+            ("rec-spaced", " 2025-03-05 "),
+        ],
+    )
+    .await;
+
+    let db = database::init_main_db(&data_path).await.expect("init");
+    let conn = db.write().await;
+    assert_eq!(stored_date(&conn, "rec-ok").await, "2025-03-10");
+    assert_eq!(stored_date(&conn, "rec-short").await, "2025-03-02");
+    assert_eq!(stored_date(&conn, "rec-spaced").await, "2025-03-05");
+
+    // Ordering and range filters now agree on the normalized values.
+    let mut rows = conn
+        .query(
+            "SELECT id FROM records WHERE date BETWEEN '2025-03-01' AND '2025-03-31' ORDER BY date, id",
+            (),
+        )
+        .await
+        .unwrap();
+    let mut ids = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        ids.push(row.get::<String>(0).unwrap());
+    }
+    assert_eq!(ids, ["rec-short", "rec-spaced", "rec-ok"]);
+
+    // The rebuilt table kept its sequence numbers and has the CHECK.
+    let mut rows = conn
+        .query("SELECT COUNT(*) FROM records WHERE seq IS NULL", ())
+        .await
+        .unwrap();
+    assert_eq!(
+        rows.next().await.unwrap().unwrap().get::<i64>(0).unwrap(),
+        0
+    );
+    assert!(!insert_raw_date(&conn, "rec-new", "2025-3-9").await);
+    assert!(insert_raw_date(&conn, "rec-new", "2025-03-09").await);
+}
+
+// ---------------------------------------------------------------------------
+// V4: Unfixable dates are reported and hold the CHECK back
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn v4_unfixable_dates_are_reported() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let data_path = temp_dir.path().to_string_lossy().to_string();
+    seed_legacy_records(
+        &data_path,
+        &[
+            ("rec-short", "2025-1-7"),
+            ("rec-garbage", "last tuesday"),
+            ("rec-no-day", "2025-02-30"),
+        ],
+    )
+    .await;
+
+    {
+        let db = database::init_main_db(&data_path).await.expect("init");
+        let conn = db.write().await;
+        assert_eq!(stored_date(&conn, "rec-short").await, "2025-01-07");
+        assert_eq!(stored_date(&conn, "rec-garbage").await, "last tuesday");
+        assert_eq!(
+            database::normalize_record_dates(&conn).await.unwrap(),
+            RecordDateReport {
+                normalized: vec![],
+                unfixable: vec![
+                    ("rec-garbage".to_string(), "last tuesday".to_string()),
+                    ("rec-no-day".to_string(), "2025-02-30".to_string()),
+                ],
+            }
+        );
+        // Without the CHECK the table still accepts bad rows, but the repos do not.
+        assert!(insert_raw_date(&conn, "rec-raw", "2025-4-1").await);
+        let refused = record_repo::insert_record(
+            &conn,
+            &NewRecord {
+                id: "rec-repo",
+                owner_user_id: "user-v",
+                name: "Dinner",
+                amount: money(-1.0),
+                currency: DEFAULT_CURRENCY_CODE,
+                category_id: "uncategorized",
+                date: "someday",
+                created_via: "api",
+                original_amount: None,
+                original_currency: None,
+                note: None,
+                tags: &[],
+            },
+        )
+        .await;
+        assert!(refused.is_err());
+
+        conn.execute(
+            "UPDATE records SET date = '2025-03-01' WHERE id IN ('rec-garbage', 'rec-no-day')",
+            (),
+        )
+        .await
+        .unwrap();
+    }
+
+    // Once the operator fixed them, the next start normalizes the rest and constrains.
+    let db = database::init_main_db(&data_path).await.expect("re-init");
+    let conn = db.write().await;
+    assert_eq!(stored_date(&conn, "rec-raw").await, "2025-04-01");
+    assert!(!insert_raw_date(&conn, "rec-late", "2025-4-2").await);
+}
+
+// ---------------------------------------------------------------------------
+// V5: Repository writes store the canonical form
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn v5_repository_writes_store_canonical_dates() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_v5"])
+        .build(&app)
+        .await;
+    let alice = scenario.id("alice_v5");
+
+    let conn = app.state.main_db.write().await;
+    record_repo::insert_record(
+        &conn,
+        &NewRecord {
+            id: "rec-v5",
+            owner_user_id: alice,
+            name: "Groceries",
+            amount: money(-300.0),
+            currency: DEFAULT_CURRENCY_CODE,
+            category_id: "uncategorized",
+            date: " 2025-6-1",
+            created_via: "api",
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: &[],
+        },
+    )
+    .await
+    .expect("insert");
+    assert_eq!(stored_date(&conn, "rec-v5").await, "2025-06-01");
+
+    let mut record = record_repo::find_record(&conn, alice, "rec-v5")
+        .await
+        .unwrap()
+        .expect("record");
+    record.date = "2025-7-4".to_string();
+    record_repo::update_record(&conn, alice, &record)
+        .await
+        .expect("update");
+    assert_eq!(stored_date(&conn, "rec-v5").await, "2025-07-04");
+
+    record.date = "2025-07-32".to_string();
+    assert!(
+        record_repo::update_record(&conn, alice, &record)
+            .await
+            .is_err()
+    );
+    assert_eq!(stored_date(&conn, "rec-v5").await, "2025-07-04");
+}
@@ -0,0 +1,137 @@
+/// Tests Z201-Z203: Deleting a record outside the HTTP handler
+///
+/// `records::delete_record_for_user` backs both `DELETE /records/{id}` and the bot's
+/// confirmed deletes. It trashes only the caller's live records, answers 404 for
+/// anything else, and refuses records in a closed period unless asked to reopen.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::constants::ERROR_CODE_RECORD_NOT_FOUND;
+use kash_server::models::CreateRecordPayload;
+use kash_server::records;
+
+// ---- Helpers ----
+
+async fn create(app: &common::TestApp, scenario: &Scenario, user: &str, name: &str) -> String {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id(user),
+        CreateRecordPayload {
+            name: name.to_string(),
+            amount: money(-12.5),
+            currency: None,
+            category_id: scenario.category_id(user, "Dining").to_string(),
+            date: "2025-03-10".to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record")
+    .id
+}
+
+async fn is_trashed(app: &common::TestApp, record_id: &str) -> bool {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT deleted_at IS NOT NULL FROM records WHERE id = ?",
+            [record_id],
+        )
+        .await
+        .unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob])
+        .category(&alice, "Dining")
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z201: The owner's record goes to the trash; a second delete is 404
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z201_delete_trashes_own_record() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z201").await;
+    let alice = scenario.id("alice_z201");
+    let record = create(&app, &scenario, "alice_z201", "Taxi").await;
+
+    records::delete_record_for_user(&app.state.main_db, alice, &record, false)
+        .await
+        .expect("delete");
+    assert!(is_trashed(&app, &record).await);
+
+    let error = records::delete_record_for_user(&app.state.main_db, alice, &record, false)
+        .await
+        .expect_err("already trashed");
+    assert_eq!(error.status, StatusCode::NOT_FOUND);
+    assert_eq!(error.code, ERROR_CODE_RECORD_NOT_FOUND);
+}
+
+// ---------------------------------------------------------------------------
+// Z202: Another user's record is 404 and stays live
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z202_other_users_record_is_not_found() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z202").await;
+    let record = create(&app, &scenario, "alice_z202", "Taxi").await;
+
+    let error = records::delete_record_for_user(
+        &app.state.main_db,
+        scenario.id("bob_z202"),
+        &record,
+        false,
+    )
+    .await
+    .expect_err("not bob's record");
+    assert_eq!(error.status, StatusCode::NOT_FOUND);
+    assert_eq!(error.code, ERROR_CODE_RECORD_NOT_FOUND);
+    assert!(!is_trashed(&app, &record).await);
+}
+
+// ---------------------------------------------------------------------------
+// Z203: Closed periods refuse the delete unless `reopen` is set
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z203_closed_period_needs_reopen() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z203").await;
+    let alice = scenario.id("alice_z203");
+    let record = create(&app, &scenario, "alice_z203", "Taxi").await;
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "INSERT INTO user_settings (user_id, closed_through, updated_at) VALUES (?, ?, '2025-04-01T00:00:00Z')",
+            (alice, "2025-03-31"),
+        )
+        .await
+        .expect("close period");
+    }
+
+    let error = records::delete_record_for_user(&app.state.main_db, alice, &record, false)
+        .await
+        .expect_err("closed period");
+    assert_eq!(error.status, StatusCode::CONFLICT);
+    assert!(!is_trashed(&app, &record).await);
+
+    records::delete_record_for_user(&app.state.main_db, alice, &record, true)
+        .await
+        .expect("reopened delete");
+    assert!(is_trashed(&app, &record).await);
+}
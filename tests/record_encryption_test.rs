@@ -0,0 +1,404 @@
+/// Tests E1-E5: Record name encryption at rest
+///
+/// `POST /admin/users/{id}/encrypt-records` turns on encryption for one user and
+/// seals their existing record names with AES-256-GCM under a key derived from
+/// `RECORD_ENCRYPTION_KEY`. The database then only holds `enc:v<n>:...` values,
+/// while the API, export and bot tools keep seeing plaintext. Name searches fall
+/// back to filtering in Rust. `rotate-records-key` moves the user to the next
+/// derived key and re-seals every name.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    response::Response,
+};
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use common::send_request;
+use common::{TEST_ADMIN_TOKEN, TEST_RECORD_ENCRYPTION_KEY};
+use kash_server::constants::ENCRYPTED_FIELD_PREFIX;
+use kash_server::crypto::{self, FieldCryptoError, MasterKey};
+use kash_server::models::{AdminActionResponse, RecordEncryptionPlan};
+use kash_server::record_repo::RecordSearch;
+use kash_server::records;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn body_json(response: Response) -> Value {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).unwrap_or(Value::Null)
+}
+
+async fn admin_post(app: &common::TestApp, uri: &str) -> Response {
+    let request = Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header("authorization", format!("Bearer {TEST_ADMIN_TOKEN}"))
+        .body(Body::empty())
+        .unwrap();
+    app.router.clone().oneshot(request).await.unwrap()
+}
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice])
+        .category(&alice, "Dining")
+        .build(app)
+        .await
+}
+
+async fn create_records(app: &common::TestApp, scenario: &Scenario, user: &str, names: &[&str]) {
+    for (i, name) in names.iter().enumerate() {
+        let response = send_request(
+            app,
+            "POST",
+            "/records",
+            scenario.cookie(user),
+            Some(json!({
+                "name": name,
+                "amount": 100.0 + i as f64,
+                "category_id": scenario.category_id(user, "Dining"),
+                "date": "2025-03-10",
+            })),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+}
+
+async fn stored_names(app: &common::TestApp, user_id: &str) -> Vec<String> {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT name FROM records WHERE owner_user_id = ? ORDER BY seq",
+            [user_id],
+        )
+        .await
+        .unwrap();
+    let mut names = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        names.push(row.get::<String>(0).unwrap());
+    }
+    names
+}
+
+async fn encrypt(app: &common::TestApp, user_id: &str) -> RecordEncryptionPlan {
+    let response = admin_post(app, &format!("/admin/users/{user_id}/encrypt-records")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    plan_of(response).await
+}
+
+async fn plan_of(response: Response) -> RecordEncryptionPlan {
+    let response: AdminActionResponse<RecordEncryptionPlan> =
+        serde_json::from_value(body_json(response).await).unwrap();
+    response.plan
+}
+
+fn search(name_contains: Option<&str>) -> RecordSearch<'_> {
+    RecordSearch {
+        start_date: "2025-01-01",
+        end_date: "2025-12-31",
+        category_id: None,
+        name_contains,
+        min_amount: None,
+        max_amount: None,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// E1: Encrypted names are opaque in the database and plaintext everywhere else
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn e1_encrypt_records_round_trip() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "e1").await;
+    let alice = scenario.id("alice_e1");
+    let cookie = scenario.cookie("alice_e1");
+    create_records(&app, &scenario, "alice_e1", &["Ramen", "Coffee"]).await;
+
+    let plan = encrypt(&app, alice).await;
+    assert_eq!(plan.from_key_version, None);
+    assert_eq!(plan.to_key_version, 1);
+    assert_eq!(plan.record_ids.len(), 2);
+
+    // Records created after opting in are sealed on write.
+    create_records(&app, &scenario, "alice_e1", &["Tea"]).await;
+    for stored in stored_names(&app, alice).await {
+        assert!(stored.starts_with(ENCRYPTED_FIELD_PREFIX), "{stored}");
+        assert!(!stored.contains("Ramen") && !stored.contains("Tea"));
+    }
+
+    let response = send_request(&app, "GET", "/records", cookie, None).await;
+    let listed = body_json(response).await;
+    let mut names: Vec<&str> = listed["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|record| record["name"].as_str().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["Coffee", "Ramen", "Tea"]);
+
+    let response = send_request(&app, "GET", "/export", cookie, None).await;
+    let export = body_json(response).await;
+    assert!(
+        export["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|record| record["name"] == "Ramen")
+    );
+
+    // Renames keep the value sealed.
+    let record_id = listed["records"][0]["id"].as_str().unwrap().to_string();
+    let response = send_request(
+        &app,
+        "PUT",
+        &format!("/records/{record_id}"),
+        cookie,
+        Some(json!({ "name": "Udon" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_json(response).await["name"], "Udon");
+    assert!(
+        stored_names(&app, alice)
+            .await
+            .iter()
+            .all(|stored| crypto::is_encrypted(stored))
+    );
+}
+
+// ---------------------------------------------------------------------------
+// E2: Wrong or missing keys and tampered values fail loudly
+// ---------------------------------------------------------------------------
+
+#[test]
+fn e2_wrong_key_failure_modes() {
+    let master = MasterKey::from_hex(TEST_RECORD_ENCRYPTION_KEY).unwrap();
+    let other = MasterKey::from_hex(&"ab".repeat(32)).unwrap();
+    let sealed = crypto::encrypt_field(&master, "user-1", 1, "Ramen");
+
+    assert_eq!(
+        crypto::open_field_with(Some(&master), sealed.clone()).as_deref(),
+        Ok("Ramen")
+    );
+    assert_eq!(
+        crypto::open_field_with(Some(&other), sealed.clone()),
+        Err(FieldCryptoError::Decrypt)
+    );
+    assert_eq!(
+        crypto::open_field_with(None, sealed.clone()),
+        Err(FieldCryptoError::MissingKey)
+    );
+
+    // The owner id is bound as associated data, so moving a value to another user fails.
+    let moved = sealed.replacen(":user-1:", ":user-2:", 1);
+    assert_eq!(
+        crypto::open_field_with(Some(&master), moved),
+        Err(FieldCryptoError::Decrypt)
+    );
+
+    let mut tampered = sealed.clone();
+    let last = tampered.pop().unwrap();
+    tampered.push(if last == '0' { '1' } else { '0' });
+    assert_eq!(
+        crypto::open_field_with(Some(&master), tampered),
+        Err(FieldCryptoError::Decrypt)
+    );
+    assert_eq!(
+        crypto::open_field_with(
+            Some(&master),
+            format!("{ENCRYPTED_FIELD_PREFIX}1:user-1:zz")
+        ),
+        Err(FieldCryptoError::Malformed)
+    );
+
+    // Plaintext passes through even without a key.
+    assert_eq!(
+        crypto::open_field_with(None, "Ramen".to_string()).as_deref(),
+        Ok("Ramen")
+    );
+    assert_eq!(
+        MasterKey::from_hex("too-short"),
+        Err(FieldCryptoError::InvalidKey)
+    );
+}
+
+// ---------------------------------------------------------------------------
+// E3: Name searches still work for encrypted users
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn e3_name_search_falls_back_for_encrypted_users() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "e3").await;
+    let alice = scenario.id("alice_e3");
+    create_records(
+        &app,
+        &scenario,
+        "alice_e3",
+        &["Ramen", "Ramen night", "Coffee"],
+    )
+    .await;
+    encrypt(&app, alice).await;
+
+    let page = records::search_records_for_user(
+        &app.state.main_db,
+        alice,
+        &search(Some("ramen")),
+        None,
+        None,
+    )
+    .await
+    .expect("search");
+    assert_eq!(page.total_count, 2);
+    let mut names: Vec<&str> = page.records.iter().map(|r| r.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["Ramen", "Ramen night"]);
+
+    let page = records::search_records_for_user(
+        &app.state.main_db,
+        alice,
+        &search(Some("ramen")),
+        Some(1),
+        Some(1),
+    )
+    .await
+    .expect("paged search");
+    assert_eq!(page.records.len(), 1);
+    assert!(!page.truncated);
+
+    let totals = records::sum_records_for_user(&app.state.main_db, alice, &search(Some("RAMEN")))
+        .await
+        .expect("sum");
+    assert_eq!(totals.record_count, 2);
+    assert_eq!(totals.expense_total, money(-201.0));
+
+    let all = records::sum_records_for_user(&app.state.main_db, alice, &search(None))
+        .await
+        .expect("sum all");
+    assert_eq!(all.record_count, 3);
+
+    let response = send_request(
+        &app,
+        "GET",
+        "/records?q=RAMEN&limit=1",
+        scenario.cookie("alice_e3"),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let listed = body_json(response).await;
+    assert_eq!(listed["total_count"], 2);
+    assert_eq!(listed["records"].as_array().map(Vec::len), Some(1));
+
+    let response = send_request(
+        &app,
+        "GET",
+        "/records?sort_by=name",
+        scenario.cookie("alice_e3"),
+        None,
+    )
+    .await;
+    let sorted: Vec<Value> = body_json(response).await["records"]
+        .as_array()
+        .expect("records")
+        .iter()
+        .map(|record| record["name"].clone())
+        .collect();
+    assert_eq!(
+        sorted,
+        vec!["Coffee", "Ramen", "Ramen night"],
+        "sorted by plaintext"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// E4: Rotation re-seals every name under the next key version
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn e4_rotate_records_key() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "e4").await;
+    let alice = scenario.id("alice_e4");
+    create_records(&app, &scenario, "alice_e4", &["Ramen", "Coffee"]).await;
+
+    // Rotating before encrypting is a conflict.
+    let rotate_uri = format!("/admin/users/{alice}/rotate-records-key");
+    let response = admin_post(&app, &rotate_uri).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    encrypt(&app, alice).await;
+    let before = stored_names(&app, alice).await;
+
+    let response = admin_post(&app, &rotate_uri).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let plan = plan_of(response).await;
+    assert_eq!(plan.from_key_version, Some(1));
+    assert_eq!(plan.to_key_version, 2);
+    assert_eq!(plan.record_ids.len(), 2);
+
+    let after = stored_names(&app, alice).await;
+    for (old, new) in before.iter().zip(&after) {
+        assert_ne!(old, new);
+        assert_eq!(crypto::sealed_key_version(new), Some(2));
+    }
+    {
+        let conn = app.state.main_db.read().await;
+        assert_eq!(
+            crypto::records_key_version(&conn, alice).await.unwrap(),
+            Some(2)
+        );
+    }
+
+    let response = send_request(&app, "GET", "/records", scenario.cookie("alice_e4"), None).await;
+    let listed = body_json(response).await;
+    let mut names: Vec<&str> = listed["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|record| record["name"].as_str().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["Coffee", "Ramen"]);
+}
+
+// ---------------------------------------------------------------------------
+// E5: Dry runs change nothing and unknown users are 404
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn e5_dry_run_and_unknown_user() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "e5").await;
+    let alice = scenario.id("alice_e5");
+    create_records(&app, &scenario, "alice_e5", &["Ramen"]).await;
+
+    let response = admin_post(
+        &app,
+        &format!("/admin/users/{alice}/encrypt-records?dry_run=true"),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let plan = plan_of(response).await;
+    assert_eq!(plan.record_ids.len(), 1);
+    assert_eq!(stored_names(&app, alice).await, vec!["Ramen".to_string()]);
+    {
+        let conn = app.state.main_db.read().await;
+        assert_eq!(
+            crypto::records_key_version(&conn, alice).await.unwrap(),
+            None
+        );
+    }
+
+    let response = admin_post(&app, "/admin/users/no-such-user/encrypt-records").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
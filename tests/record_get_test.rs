@@ -0,0 +1,167 @@
+/// Tests N1-N3: Fetching a single record
+///
+/// `GET /records/{id}` returns one of the caller's records with the columns the
+/// list leaves out (`pending`, `settle`, `split_id`, `debtor_user_id`,
+/// `creditor_user_id`) and 404 for ids that are missing or not theirs.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::models::{CreateRecordPayload, RecordDetail};
+use kash_server::records;
+use serde_json::Value;
+
+// ---- Helpers ----
+
+async fn get_record(app: &common::TestApp, cookie: &str, id: &str) -> (StatusCode, Value) {
+    let (status, body) =
+        common::auth_request(&app.router, "GET", &format!("/records/{id}"), cookie)
+            .await
+            .expect("request");
+    let body = serde_json::from_str(&body).unwrap_or(Value::String(body));
+    (status, body)
+}
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob])
+        .category(&alice, "Dining")
+        .category(&bob, "Dining")
+        .friend(&alice, &bob)
+        .split(&alice, "Dining", 60.0, &[(&bob, 30.0)])
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// N1: A plain record comes back with every column
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn n1_get_plain_record() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "n1").await;
+    let created = records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id("alice_n1"),
+        CreateRecordPayload {
+            name: "Ramen".to_string(),
+            amount: money(12.5),
+            currency: None,
+            category_id: scenario.category_id("alice_n1", "Dining").to_string(),
+            date: "2025-03-10".to_string(),
+            original_amount: Some(400.0),
+            original_currency: Some("JPY".to_string()),
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record");
+
+    let (status, body) = get_record(&app, scenario.cookie("alice_n1"), &created.id).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    for field in [
+        "pending",
+        "settle",
+        "split_id",
+        "debtor_user_id",
+        "creditor_user_id",
+    ] {
+        assert!(body.get(field).is_some(), "{field} is present: {body}");
+    }
+    let detail: RecordDetail = serde_json::from_value(body).expect("record detail");
+    assert_eq!(detail.record.id, created.id);
+    assert_eq!(detail.record.name, "Ramen");
+    assert_eq!(detail.record.amount, created.amount);
+    assert_eq!(detail.record.seq, created.seq);
+    assert_eq!(detail.record.original_amount, Some(400.0));
+    assert_eq!(detail.record.original_currency.as_deref(), Some("JPY"));
+    assert!(!detail.pending);
+    assert!(!detail.settle);
+    assert_eq!(detail.split_id, None);
+    assert_eq!(detail.debtor_user_id, None);
+    assert_eq!(detail.creditor_user_id, None);
+}
+
+// ---------------------------------------------------------------------------
+// N2: Split records carry their split columns and status, as in the list
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn n2_get_split_records() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "n2").await;
+    let split = &scenario.splits[0];
+
+    let (status, body) = get_record(
+        &app,
+        scenario.cookie("bob_n2"),
+        &split.pending_record_ids[0],
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let share: RecordDetail = serde_json::from_value(body).expect("record detail");
+    assert!(share.pending);
+    assert!(!share.settle);
+    assert_eq!(share.record.settled, Some(false));
+    assert_eq!(share.split_id.as_deref(), Some(split.split_id.as_str()));
+    assert_eq!(share.debtor_user_id.as_deref(), Some(scenario.id("bob_n2")));
+    assert_eq!(
+        share.creditor_user_id.as_deref(),
+        Some(scenario.id("alice_n2"))
+    );
+
+    let (status, body) =
+        get_record(&app, scenario.cookie("alice_n2"), &split.payer_record_id).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let payer: RecordDetail = serde_json::from_value(body).expect("record detail");
+    assert_eq!(payer.split_id.as_deref(), Some(split.split_id.as_str()));
+    let progress = payer.record.split_progress.clone().expect("split progress");
+    assert_eq!(progress.participants_total, 1);
+    assert_eq!(progress.participants_settled, 0);
+
+    let (_, list) =
+        common::auth_request(&app.router, "GET", "/records", scenario.cookie("alice_n2"))
+            .await
+            .expect("list records");
+    let list: Value = serde_json::from_str(&list).expect("json");
+    assert_eq!(
+        list["records"][0],
+        serde_json::to_value(&payer.record).expect("record json"),
+        "same record as the list"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// N3: Other users' records and unknown ids are 404; login is required
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn n3_get_record_is_scoped_to_owner() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "n3").await;
+    let split = &scenario.splits[0];
+
+    let (status, _) = get_record(&app, scenario.cookie("bob_n3"), &split.payer_record_id).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "alice's record");
+    let (status, _) = get_record(
+        &app,
+        scenario.cookie("alice_n3"),
+        &split.pending_record_ids[0],
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::NOT_FOUND,
+        "bob's share of alice's split"
+    );
+    let (status, _) = get_record(&app, scenario.cookie("alice_n3"), "no-such-record").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = get_record(&app, "", &split.payer_record_id).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
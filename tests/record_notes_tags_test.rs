@@ -0,0 +1,296 @@
+/// Tests Z401-Z403: Record notes and tags
+///
+/// Records carry an optional free-text `note` and up to ten lowercase `tags`.
+/// `GET /records?tag=` narrows the list, the CSV export gains `note` and `tags`
+/// columns, and notes are sealed at rest like names for encrypted users.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::TEST_ADMIN_TOKEN;
+use common::fixtures::{Scenario, ScenarioBuilder};
+use common::send_json;
+use kash_server::constants::*;
+use kash_server::crypto;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> (StatusCode, String) {
+    let builder = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("cookie", cookie);
+    let request = match payload {
+        Some(payload) => builder
+            .header("content-type", "application/json")
+            .body(Body::from(payload.to_string())),
+        None => builder.body(Body::empty()),
+    }
+    .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+async fn scenario(app: &common::TestApp, user: &str) -> Scenario {
+    ScenarioBuilder::new()
+        .user(user)
+        .category(user, "Travel")
+        .build(app)
+        .await
+}
+
+fn record(scenario: &Scenario, user: &str, name: &str, extra: Value) -> Value {
+    let mut payload = json!({
+        "name": name,
+        "amount": -120.0,
+        "category_id": scenario.category_id(user, "Travel"),
+        "date": "2025-07-14",
+    });
+    payload
+        .as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    payload
+}
+
+async fn create(app: &common::TestApp, scenario: &Scenario, user: &str, payload: Value) -> Value {
+    let (status, body) = send_json(
+        app,
+        "POST",
+        "/records",
+        scenario.cookie(user),
+        Some(payload),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    body
+}
+
+fn names(body: &Value) -> Vec<&str> {
+    body["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|record| record["name"].as_str().unwrap())
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Z401: Notes and tags are saved, listed, filtered on and edited
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z401_notes_and_tags_round_trip() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "alice_z401").await;
+    let cookie = scenario.cookie("alice_z401");
+
+    let hotel = create(
+        &app,
+        &scenario,
+        "alice_z401",
+        record(
+            &scenario,
+            "alice_z401",
+            "Hotel",
+            json!({
+                "note": "  split with landlord, waiting on receipt ",
+                "tags": ["Vacation2024", " lodging", "vacation2024"],
+            }),
+        ),
+    )
+    .await;
+    assert_eq!(hotel["note"], "split with landlord, waiting on receipt");
+    assert_eq!(hotel["tags"], json!(["lodging", "vacation2024"]));
+    let plain = create(
+        &app,
+        &scenario,
+        "alice_z401",
+        record(&scenario, "alice_z401", "Bus", json!({})),
+    )
+    .await;
+    assert!(plain.get("note").is_none());
+    assert!(plain.get("tags").is_none());
+
+    let hotel_uri = format!("/records/{}", hotel["id"].as_str().unwrap());
+    let (status, detail) = send_json(&app, "GET", &hotel_uri, cookie, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(detail["note"], hotel["note"]);
+    assert_eq!(detail["tags"], hotel["tags"]);
+
+    let (status, body) = send_json(&app, "GET", "/records?tag=VACATION2024", cookie, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(names(&body), vec!["Hotel"]);
+    assert_eq!(body["total_count"], 1);
+    let (_, body) = send_json(&app, "GET", "/records?tag=food", cookie, None).await;
+    assert_eq!(names(&body), Vec::<&str>::new());
+
+    // Tags alone replace the set and keep the note
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &hotel_uri,
+        cookie,
+        Some(json!({ "tags": ["vacation2024"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["tags"], json!(["vacation2024"]));
+    assert_eq!(body["note"], hotel["note"]);
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &hotel_uri,
+        cookie,
+        Some(json!({ "note": null, "tags": [] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (_, detail) = send_json(&app, "GET", &hotel_uri, cookie, None).await;
+    assert!(detail.get("note").is_none());
+    assert!(detail.get("tags").is_none());
+    let (_, body) = send_json(&app, "GET", "/records?tag=vacation2024", cookie, None).await;
+    assert_eq!(body["total_count"], 0);
+}
+
+// ---------------------------------------------------------------------------
+// Z402: Long notes and malformed or too many tags are rejected
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z402_note_and_tag_limits() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "bob_z402").await;
+    let cookie = scenario.cookie("bob_z402");
+
+    let longest_note = "é".repeat(MAX_RECORD_NOTE_LENGTH);
+    let longest_tag = "t".repeat(MAX_RECORD_TAG_LENGTH);
+    let most_tags: Vec<String> = (0..MAX_TAGS_PER_RECORD).map(|i| format!("t{i}")).collect();
+    let created = create(
+        &app,
+        &scenario,
+        "bob_z402",
+        record(
+            &scenario,
+            "bob_z402",
+            "Flight",
+            json!({ "note": longest_note, "tags": most_tags }),
+        ),
+    )
+    .await;
+    assert_eq!(
+        created["tags"].as_array().unwrap().len(),
+        MAX_TAGS_PER_RECORD
+    );
+
+    let too_many: Vec<String> = (0..=MAX_TAGS_PER_RECORD).map(|i| format!("t{i}")).collect();
+    for extra in [
+        json!({ "note": "é".repeat(MAX_RECORD_NOTE_LENGTH + 1) }),
+        json!({ "tags": too_many }),
+        json!({ "tags": [format!("{longest_tag}x")] }),
+        json!({ "tags": [""] }),
+        json!({ "tags": ["two words"] }),
+        json!({ "tags": ["a,b"] }),
+    ] {
+        let (status, body) = send_json(
+            &app,
+            "POST",
+            "/records",
+            cookie,
+            Some(record(&scenario, "bob_z402", "Taxi", extra.clone())),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{extra}: {body}");
+    }
+
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &format!("/records/{}", created["id"].as_str().unwrap()),
+        cookie,
+        Some(json!({ "tags": ["bad tag"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send_json(&app, "GET", "/records?tag=bad%20tag", cookie, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+// ---------------------------------------------------------------------------
+// Z403: CSV export carries notes and tags; encrypted users' notes are sealed
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z403_csv_export_and_encrypted_notes() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "carol_z403").await;
+    let cookie = scenario.cookie("carol_z403");
+    let user_id = scenario.id("carol_z403");
+
+    let request = Request::builder()
+        .uri(format!("/admin/users/{user_id}/encrypt-records"))
+        .method("POST")
+        .header("authorization", format!("Bearer {TEST_ADMIN_TOKEN}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let created = create(
+        &app,
+        &scenario,
+        "carol_z403",
+        record(
+            &scenario,
+            "carol_z403",
+            "Ferry",
+            json!({ "note": "deck seats, \"sunny\"", "tags": ["islands", "boat"] }),
+        ),
+    )
+    .await;
+    {
+        let conn = app.state.main_db.read().await;
+        let mut rows = conn
+            .query(
+                "SELECT note FROM records WHERE id = ?",
+                [created["id"].as_str().unwrap()],
+            )
+            .await
+            .unwrap();
+        let stored: String = rows.next().await.unwrap().unwrap().get(0).unwrap();
+        assert!(crypto::is_encrypted(&stored), "{stored}");
+        assert!(!stored.contains("deck"));
+    }
+
+    let (status, csv) = send(&app, "GET", "/records/export", cookie, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        csv,
+        format!(
+            "{RECORDS_CSV_HEADER}\n\
+             {},Ferry,-120,TWD,Travel,2025-07-14,false,false,\"deck seats, \"\"sunny\"\"\",boat islands\n",
+            created["id"].as_str().unwrap()
+        )
+    );
+
+    let (_, archive) = send_json(&app, "GET", "/export", cookie, None).await;
+    assert_eq!(archive["records"][0]["note"], "deck seats, \"sunny\"");
+    assert_eq!(archive["records"][0]["tags"], json!(["boat", "islands"]));
+    let (_, archive) = send_json(&app, "GET", "/export?redact=notes", cookie, None).await;
+    assert!(archive["records"][0].get("note").is_none());
+}
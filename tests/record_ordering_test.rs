@@ -0,0 +1,149 @@
+/// Tests S1-S3: Deterministic record ordering by creation sequence
+///
+/// Every record gets a monotonically increasing `seq`. It breaks ties between
+/// records sharing a date and identifies the most recently created record for
+/// the bot's /recent command and follow-up corrections.
+mod common;
+
+use axum::http::StatusCode;
+use common::send_json;
+use kash_server::records;
+use serde_json::{Value, json};
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+async fn setup_user(app: &common::TestApp, username: &str) -> (String, String) {
+    let user_id = common::create_test_user(&app.state, username, "pw")
+        .await
+        .expect("create user");
+    let cookie = common::login_user(&app.router, username, "pw")
+        .await
+        .expect("login user");
+    (user_id, cookie)
+}
+
+async fn create_category(app: &common::TestApp, cookie: &str, name: &str) -> String {
+    let (status, body) = send_json(
+        app,
+        "POST",
+        "/categories",
+        cookie,
+        Some(json!({ "name": name, "is_income": false })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create category {name}");
+    body["id"].as_str().expect("category id").to_string()
+}
+
+async fn create_record(
+    app: &common::TestApp,
+    cookie: &str,
+    name: &str,
+    category_id: &str,
+    date: &str,
+) -> Value {
+    let (status, body) = send_json(
+        app,
+        "POST",
+        "/records",
+        cookie,
+        Some(json!({ "name": name, "amount": 10.0, "category_id": category_id, "date": date })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create record {name}: {body}");
+    body
+}
+
+// ---------------------------------------------------------------------------
+// S1: Same-day records list newest-created first
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn s1_same_day_records_are_ordered_by_seq() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (_, cookie) = setup_user(&app, "alice_s1").await;
+    let food_id = create_category(&app, &cookie, "Food").await;
+
+    let first = create_record(&app, &cookie, "Breakfast", &food_id, "2024-05-01").await;
+    let second = create_record(&app, &cookie, "Lunch", &food_id, "2024-05-01").await;
+    let third = create_record(&app, &cookie, "Dinner", &food_id, "2024-05-01").await;
+
+    let first_seq = first["seq"].as_i64().expect("seq");
+    let second_seq = second["seq"].as_i64().expect("seq");
+    let third_seq = third["seq"].as_i64().expect("seq");
+    assert!(first_seq < second_seq && second_seq < third_seq);
+
+    let (status, body) = send_json(&app, "GET", "/records", &cookie, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let names: Vec<&str> = body["records"]
+        .as_array()
+        .expect("records array")
+        .iter()
+        .map(|record| record["name"].as_str().expect("name"))
+        .collect();
+    assert_eq!(names, vec!["Dinner", "Lunch", "Breakfast"]);
+}
+
+// ---------------------------------------------------------------------------
+// S2: Recent records and correction target follow creation order, not date
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn s2_recent_and_correction_target_use_seq() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (user_id, cookie) = setup_user(&app, "alice_s2").await;
+    let food_id = create_category(&app, &cookie, "Food").await;
+
+    create_record(&app, &cookie, "Coffee", &food_id, "2024-05-02").await;
+    create_record(&app, &cookie, "Bagel", &food_id, "2024-05-02").await;
+    let backdated = create_record(&app, &cookie, "Forgotten taxi", &food_id, "2024-04-30").await;
+
+    let recent = records::list_recent_records(&app.state.main_db, &user_id, 5)
+        .await
+        .expect("list recent");
+    let names: Vec<&str> = recent.iter().map(|record| record.name.as_str()).collect();
+    assert_eq!(names, vec!["Forgotten taxi", "Bagel", "Coffee"]);
+
+    let latest_seq = backdated["seq"].as_i64().expect("seq");
+    let target = records::fetch_record_by_seq(&app.state.main_db, &user_id, latest_seq)
+        .await
+        .expect("fetch by seq")
+        .expect("record exists");
+    assert_eq!(target.id, backdated["id"].as_str().expect("id"));
+}
+
+// ---------------------------------------------------------------------------
+// S3: Inserts that do not set seq still get the next number, owner-scoped reads
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn s3_raw_inserts_get_seq_and_lookup_is_owner_scoped() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (alice_id, alice_cookie) = setup_user(&app, "alice_s3").await;
+    let (bob_id, _) = setup_user(&app, "bob_s3").await;
+    let food_id = create_category(&app, &alice_cookie, "Food").await;
+
+    let before = create_record(&app, &alice_cookie, "Before", &food_id, "2024-05-03").await;
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "INSERT INTO records (id, owner_user_id, name, amount, category_id, date) VALUES (?, ?, ?, ?, ?, ?)",
+            ("raw-s3", alice_id.as_str(), "Raw", -5.0, food_id.as_str(), "2024-05-03"),
+        )
+        .await
+        .expect("raw insert");
+    }
+
+    let recent = records::list_recent_records(&app.state.main_db, &alice_id, 1)
+        .await
+        .expect("list recent");
+    assert_eq!(recent[0].id, "raw-s3");
+    assert!(recent[0].seq > before["seq"].as_i64().expect("seq"));
+
+    let foreign = records::fetch_record_by_seq(&app.state.main_db, &bob_id, recent[0].seq)
+        .await
+        .expect("fetch by seq");
+    assert!(foreign.is_none(), "Bob must not resolve Alice's record");
+}
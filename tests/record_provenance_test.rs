@@ -0,0 +1,314 @@
+/// Tests P1-P4: Record provenance and batch recategorization
+///
+/// Records remember how they were created (`created_via`); AI-created records
+/// also keep a `record_provenance` row. Batch recategorization previews by
+/// default, applies transactionally, and can be undone with the returned token.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::money;
+use kash_server::constants::CREATED_VIA_BOT_AI;
+use kash_server::models::{CreateRecordPayload, RecordProvenance};
+use kash_server::records;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+async fn json_request(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    body: Value,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("build request");
+    let response = app
+        .router
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("execute request");
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("read body");
+    let value = serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8(bytes.to_vec()).expect("utf8")));
+    (status, value)
+}
+
+async fn setup_user(app: &common::TestApp, username: &str) -> (String, String) {
+    let user_id = common::create_test_user(&app.state, username, "pw")
+        .await
+        .expect("create user");
+    let cookie = common::login_user(&app.router, username, "pw")
+        .await
+        .expect("login user");
+    (user_id, cookie)
+}
+
+async fn create_category(app: &common::TestApp, cookie: &str, name: &str) -> String {
+    let (status, body) = json_request(
+        app,
+        "POST",
+        "/categories",
+        cookie,
+        json!({ "name": name, "is_income": false }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create category {name}");
+    body["id"].as_str().expect("category id").to_string()
+}
+
+async fn create_bot_record(
+    app: &common::TestApp,
+    user_id: &str,
+    name: &str,
+    category_id: &str,
+    date: &str,
+) -> String {
+    let payload = CreateRecordPayload {
+        name: name.to_string(),
+        amount: money(4.5),
+        currency: None,
+        category_id: category_id.to_string(),
+        date: date.to_string(),
+        original_amount: None,
+        original_currency: None,
+        note: None,
+        tags: Vec::new(),
+    };
+    let provenance = RecordProvenance {
+        created_via: CREATED_VIA_BOT_AI.to_string(),
+        model: Some("gpt-test".to_string()),
+        prompt_hash: Some("abc123".to_string()),
+        ai_category_confidence: Some(0.42),
+    };
+    records::create_record_for_user(
+        &app.state.main_db,
+        user_id,
+        payload,
+        Some(provenance),
+        false,
+    )
+    .await
+    .expect("create bot record")
+    .id
+}
+
+async fn record_category(app: &common::TestApp, record_id: &str) -> String {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query("SELECT category_id FROM records WHERE id = ?", [record_id])
+        .await
+        .expect("query record");
+    let row = rows.next().await.expect("read row").expect("record exists");
+    row.get(0).expect("category id")
+}
+
+// ---------------------------------------------------------------------------
+// P1: HTTP-created records are tagged 'api' without a provenance row
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn p1_http_record_has_api_origin_and_no_provenance() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (_, cookie) = setup_user(&app, "alice_p1").await;
+    let food_id = create_category(&app, &cookie, "Food").await;
+
+    let (status, body) = json_request(
+        &app,
+        "POST",
+        "/records",
+        &cookie,
+        json!({ "name": "Lunch", "amount": 12.0, "category_id": food_id, "date": "2024-03-01" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let record_id = body["id"].as_str().expect("record id");
+
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query("SELECT created_via FROM records WHERE id = ?", [record_id])
+        .await
+        .expect("query record");
+    let row = rows.next().await.expect("read row").expect("record exists");
+    let created_via: String = row.get(0).expect("created_via");
+    assert_eq!(created_via, "api");
+
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM record_provenance WHERE record_id = ?",
+            [record_id],
+        )
+        .await
+        .expect("query provenance");
+    let row = rows.next().await.expect("read row").expect("count row");
+    let count: i64 = row.get(0).expect("count");
+    assert_eq!(count, 0, "HTTP records must not get a provenance row");
+}
+
+// ---------------------------------------------------------------------------
+// P2: AI-created records store model, prompt hash and confidence
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn p2_bot_ai_record_stores_provenance() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (user_id, cookie) = setup_user(&app, "alice_p2").await;
+    let food_id = create_category(&app, &cookie, "Food").await;
+
+    let record_id = create_bot_record(&app, &user_id, "7-11", &food_id, "2024-03-01").await;
+
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT r.created_via, p.model, p.prompt_hash, p.ai_category_confidence \
+             FROM records r JOIN record_provenance p ON p.record_id = r.id WHERE r.id = ?",
+            [record_id.as_str()],
+        )
+        .await
+        .expect("query provenance");
+    let row = rows
+        .next()
+        .await
+        .expect("read row")
+        .expect("provenance row exists");
+    let created_via: String = row.get(0).expect("created_via");
+    let model: String = row.get(1).expect("model");
+    let prompt_hash: String = row.get(2).expect("prompt_hash");
+    let confidence: f64 = row.get(3).expect("confidence");
+    assert_eq!(created_via, "bot_ai");
+    assert_eq!(model, "gpt-test");
+    assert_eq!(prompt_hash, "abc123");
+    assert!((confidence - 0.42).abs() < f64::EPSILON);
+}
+
+// ---------------------------------------------------------------------------
+// P3: Preview, apply and undo a batch recategorization
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn p3_recategorize_batch_preview_apply_undo() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (user_id, cookie) = setup_user(&app, "alice_p3").await;
+    let food_id = create_category(&app, &cookie, "Food").await;
+    let groceries_id = create_category(&app, &cookie, "Groceries").await;
+
+    let in_range = create_bot_record(&app, &user_id, "7-11 snacks", &food_id, "2024-03-05").await;
+    let out_of_range = create_bot_record(&app, &user_id, "7-11", &food_id, "2024-05-01").await;
+    let (status, body) = json_request(
+        &app,
+        "POST",
+        "/records",
+        &cookie,
+        json!({ "name": "7-11 manual", "amount": 3.0, "category_id": food_id, "date": "2024-03-06" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let manual = body["id"].as_str().expect("record id").to_string();
+
+    let filter = json!({
+        "name_pattern": "7-11",
+        "created_via": "bot_ai",
+        "start_date": "2024-03-01",
+        "end_date": "2024-03-31",
+        "target_category_id": groceries_id,
+    });
+
+    let (status, body) = json_request(
+        &app,
+        "POST",
+        "/records/recategorize-batch",
+        &cookie,
+        filter.clone(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["dry_run"], true, "dry run is the default");
+    assert_eq!(body["matched_count"], 1);
+    assert_eq!(body["records"][0]["id"], json!(in_range));
+    assert_eq!(body["undo_token"], Value::Null);
+    assert_eq!(record_category(&app, &in_range).await, food_id);
+
+    let mut apply = filter.clone();
+    apply["dry_run"] = json!(false);
+    let (status, body) =
+        json_request(&app, "POST", "/records/recategorize-batch", &cookie, apply).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["matched_count"], 1);
+    let undo_token = body["undo_token"].as_str().expect("undo token").to_string();
+    assert_eq!(record_category(&app, &in_range).await, groceries_id);
+    assert_eq!(record_category(&app, &out_of_range).await, food_id);
+    assert_eq!(record_category(&app, &manual).await, food_id);
+
+    let (status, body) = json_request(
+        &app,
+        "POST",
+        "/records/recategorize-batch/undo",
+        &cookie,
+        json!({ "undo_token": undo_token }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["restored_count"], 1);
+    assert_eq!(record_category(&app, &in_range).await, food_id);
+
+    let (status, _) = json_request(
+        &app,
+        "POST",
+        "/records/recategorize-batch/undo",
+        &cookie,
+        json!({ "undo_token": undo_token }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "undo token is single-use");
+}
+
+// ---------------------------------------------------------------------------
+// P4: Undo tokens are scoped to the user who applied the batch
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn p4_undo_token_is_owner_scoped() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let (alice_id, alice_cookie) = setup_user(&app, "alice_p4").await;
+    let (_, bob_cookie) = setup_user(&app, "bob_p4").await;
+    let food_id = create_category(&app, &alice_cookie, "Food").await;
+    let groceries_id = create_category(&app, &alice_cookie, "Groceries").await;
+    let record_id = create_bot_record(&app, &alice_id, "7-11", &food_id, "2024-03-05").await;
+
+    let (status, body) = json_request(
+        &app,
+        "POST",
+        "/records/recategorize-batch",
+        &alice_cookie,
+        json!({ "name_pattern": "7-11", "target_category_id": groceries_id, "dry_run": false }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let undo_token = body["undo_token"].as_str().expect("undo token").to_string();
+
+    let (status, _) = json_request(
+        &app,
+        "POST",
+        "/records/recategorize-batch/undo",
+        &bob_cookie,
+        json!({ "undo_token": undo_token }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(record_category(&app, &record_id).await, groceries_id);
+}
@@ -0,0 +1,255 @@
+/// Tests Z1-Z4: Spending summary by category
+///
+/// `GET /records/summary?start_date&end_date` sums the caller's records per
+/// category in SQL, with overall income, expense and net. Pending split shares
+/// are left out unless `include_pending=true`. The bot's `/summary YYYY-MM`
+/// turns a month into that range with `stats::month_date_range`.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::models::{CreateRecordPayload, RecordSummaryQuery, RecordSummaryResponse};
+use kash_server::{records, stats};
+use serde_json::Value;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+// ---- Helpers ----
+
+async fn summary(app: &common::TestApp, cookie: &str, query: &str) -> (StatusCode, Value) {
+    let (status, body) = common::auth_request(
+        &app.router,
+        "GET",
+        &format!("/records/summary?{query}"),
+        cookie,
+    )
+    .await
+    .expect("request");
+    let body = serde_json::from_str(&body).unwrap_or(Value::String(body));
+    (status, body)
+}
+
+async fn create(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    category: &str,
+    amount: f64,
+    date: &str,
+) {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id(user),
+        CreateRecordPayload {
+            name: format!("{category} on {date}"),
+            amount: money(amount),
+            currency: None,
+            category_id: scenario.category_id(user, category).to_string(),
+            date: date.to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record");
+}
+
+fn at(timestamp: &str) -> OffsetDateTime {
+    OffsetDateTime::parse(timestamp, &Rfc3339).unwrap()
+}
+
+// ---------------------------------------------------------------------------
+// Z1: Records in the range are totalled per category and overall
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z1_summary_groups_by_category() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z1", "bob_z1"])
+        .category("alice_z1", "Dining")
+        .category("alice_z1", "Groceries")
+        .income_category("alice_z1", "Salary")
+        .category("bob_z1", "Dining")
+        .build(&app)
+        .await;
+    for (category, amount, date) in [
+        ("Salary", 1000.0, "2025-03-01"),
+        ("Dining", 12.5, "2025-03-10"),
+        ("Groceries", 30.0, "2025-03-15"),
+        ("Dining", 7.5, "2025-03-31"),
+        ("Dining", 99.0, "2025-04-01"),
+    ] {
+        create(&app, &scenario, "alice_z1", category, amount, date).await;
+    }
+    create(&app, &scenario, "bob_z1", "Dining", 55.0, "2025-03-10").await;
+
+    let (status, body) = summary(
+        &app,
+        scenario.cookie("alice_z1"),
+        "start_date=2025-03-01&end_date=2025-03-31",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let summary: RecordSummaryResponse = serde_json::from_value(body).expect("summary");
+    let categories: Vec<(&str, bool, f64, u32)> = summary
+        .categories
+        .iter()
+        .map(|category| {
+            (
+                category.category_name.as_deref().unwrap_or_default(),
+                category.is_income,
+                category.total.to_decimal(),
+                category.record_count,
+            )
+        })
+        .collect();
+    assert_eq!(
+        categories,
+        vec![
+            ("Salary", true, 1000.0, 1),
+            ("Groceries", false, -30.0, 1),
+            ("Dining", false, -20.0, 2),
+        ]
+    );
+    assert_eq!(
+        summary.categories[2].category_id.as_deref(),
+        Some(scenario.category_id("alice_z1", "Dining"))
+    );
+    assert_eq!(summary.income, money(1000.0));
+    assert_eq!(summary.expense, money(-50.0));
+    assert_eq!(summary.net, money(950.0));
+    assert!(!summary.include_pending);
+}
+
+// ---------------------------------------------------------------------------
+// Z2: Pending split shares count only with include_pending=true
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z2_pending_records_are_opt_in() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z2", "bob_z2"])
+        .category("alice_z2", "Dining")
+        .category("bob_z2", "Dining")
+        .friend("alice_z2", "bob_z2")
+        .split("alice_z2", "Dining", 60.0, &[("bob_z2", 30.0)])
+        .build(&app)
+        .await;
+    let range = "start_date=2000-01-01&end_date=2099-12-31";
+
+    let (status, body) = summary(&app, scenario.cookie("bob_z2"), range).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let without: RecordSummaryResponse = serde_json::from_value(body).expect("summary");
+    assert!(without.categories.is_empty(), "{without:?}");
+    assert_eq!(without.net, money(0.0));
+
+    let (_, body) = summary(
+        &app,
+        scenario.cookie("bob_z2"),
+        &format!("{range}&include_pending=true"),
+    )
+    .await;
+    let with: RecordSummaryResponse = serde_json::from_value(body).expect("summary");
+    assert!(with.include_pending);
+    assert_eq!(with.categories.len(), 1);
+    assert_eq!(with.categories[0].record_count, 1);
+    assert_eq!(with.expense, money(-30.0));
+    assert_eq!(with.net, money(-30.0));
+
+    let (_, body) = summary(&app, scenario.cookie("alice_z2"), range).await;
+    let payer: RecordSummaryResponse = serde_json::from_value(body).expect("summary");
+    assert_eq!(
+        payer.categories.len(),
+        1,
+        "the payer's record is not pending"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z3: The range is required and validated; login is required
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z3_summary_validates_range() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z3"])
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z3");
+
+    for query in [
+        "start_date=2025-03-01",
+        "end_date=2025-03-31",
+        "start_date=2025-03-01&end_date=2025-02-30",
+        "start_date=2025-04-01&end_date=2025-03-31",
+        "start_date=2025-03-01&end_date=2025-03-31&include_pending=maybe",
+    ] {
+        let (status, _) = summary(&app, cookie, query).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+    }
+
+    let (status, _) = summary(&app, "", "start_date=2025-03-01&end_date=2025-03-31").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+// ---------------------------------------------------------------------------
+// Z4: A month covers its first to last day in the user's local time
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z4_month_range_and_local_month() {
+    assert_eq!(
+        stats::month_date_range("2024-02").expect("range"),
+        ("2024-02-01".to_string(), "2024-02-29".to_string())
+    );
+    assert_eq!(
+        stats::month_date_range("2025-12").expect("range"),
+        ("2025-12-01".to_string(), "2025-12-31".to_string())
+    );
+    for invalid in ["2025-13", "2025", "may", ""] {
+        assert!(stats::month_date_range(invalid).is_err(), "{invalid}");
+    }
+
+    let now = at("2025-03-31T20:00:00Z");
+    assert_eq!(stats::local_month(now, 0), "2025-03");
+    assert_eq!(stats::local_month(now, 8 * 60), "2025-04");
+    assert_eq!(
+        stats::local_month(at("2025-04-01T02:00:00Z"), -5 * 60),
+        "2025-03"
+    );
+
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z4"])
+        .category("alice_z4", "Dining")
+        .build(&app)
+        .await;
+    create(&app, &scenario, "alice_z4", "Dining", -100.0, "2025-02-28").await;
+    create(&app, &scenario, "alice_z4", "Dining", -40.0, "2025-03-01").await;
+    create(&app, &scenario, "alice_z4", "Dining", -60.0, "2025-03-31").await;
+    create(&app, &scenario, "alice_z4", "Dining", -200.0, "2025-04-01").await;
+
+    let (start_date, end_date) = stats::month_date_range("2025-03").expect("range");
+    let conn = app.state.main_db.read().await;
+    let summary = records::summarize_records_for_user(
+        &conn,
+        scenario.id("alice_z4"),
+        RecordSummaryQuery {
+            start_date,
+            end_date,
+            include_pending: None,
+        },
+    )
+    .await
+    .expect("summary");
+    assert_eq!(summary.expense, money(-100.0));
+    assert_eq!(summary.categories.len(), 1);
+    assert_eq!(summary.categories[0].record_count, 2);
+}
@@ -0,0 +1,339 @@
+/// Tests Z41-Z44: Record trash
+///
+/// `DELETE /records/{id}` moves a record to the trash instead of removing it:
+/// lists, lookups and summaries skip it, `GET /records/trash` shows it and
+/// `POST /records/{id}/restore` brings it back. The purge job removes records
+/// trashed `RECORD_TRASH_RETENTION_DAYS` ago. Split records whose split moved
+/// on while they were in the trash cannot be restored.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::constants::{ERROR_CODE_SPLIT_RECORD_UNRESTORABLE, RECORD_TRASH_RETENTION_DAYS};
+use kash_server::maintenance;
+use kash_server::models::{CreateRecordPayload, RecordTrashResponse};
+use kash_server::records;
+use serde_json::Value;
+use time::{Duration, OffsetDateTime};
+
+// ---- Helpers ----
+
+async fn request(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+) -> (StatusCode, Value) {
+    let (status, body) = common::auth_request(&app.router, method, uri, cookie)
+        .await
+        .expect("request");
+    let body = serde_json::from_str(&body).unwrap_or(Value::String(body));
+    (status, body)
+}
+
+async fn trash(app: &common::TestApp, cookie: &str) -> RecordTrashResponse {
+    let (status, body) = request(app, "GET", "/records/trash", cookie).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    serde_json::from_value(body).expect("trash response")
+}
+
+async fn record_ids(app: &common::TestApp, cookie: &str) -> Vec<String> {
+    let (status, body) = request(app, "GET", "/records", cookie).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    body["records"]
+        .as_array()
+        .expect("records array")
+        .iter()
+        .map(|record| record["id"].as_str().expect("id").to_string())
+        .collect()
+}
+
+async fn create(app: &common::TestApp, scenario: &Scenario, user: &str, name: &str) -> String {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id(user),
+        CreateRecordPayload {
+            name: name.to_string(),
+            amount: money(12.5),
+            currency: None,
+            category_id: scenario.category_id(user, "Dining").to_string(),
+            date: "2025-03-10".to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record")
+    .id
+}
+
+async fn execute(app: &common::TestApp, sql: &str, params: (&str, &str)) {
+    let conn = app.state.main_db.write().await;
+    conn.execute(sql, params).await.expect("execute");
+}
+
+// ---------------------------------------------------------------------------
+// Z41: Deleted records leave every list, show in the trash and can come back
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z41_delete_moves_record_to_trash() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z41", "bob_z41"])
+        .category("alice_z41", "Dining")
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z41");
+    let kept = create(&app, &scenario, "alice_z41", "Ramen").await;
+    let deleted = create(&app, &scenario, "alice_z41", "Sushi").await;
+
+    let (status, _) = request(&app, "DELETE", &format!("/records/{deleted}"), cookie).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(record_ids(&app, cookie).await, vec![kept.clone()]);
+    let (status, _) = request(&app, "GET", &format!("/records/{deleted}"), cookie).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = request(&app, "DELETE", &format!("/records/{deleted}"), cookie).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "already in the trash");
+    let (_, summary) = request(
+        &app,
+        "GET",
+        "/records/summary?start_date=2025-03-01&end_date=2025-03-31",
+        cookie,
+    )
+    .await;
+    assert_eq!(summary["expense"], -12.5);
+
+    let bin = trash(&app, cookie).await;
+    assert_eq!(bin.total_count, 1);
+    assert_eq!(bin.retention_days, RECORD_TRASH_RETENTION_DAYS);
+    assert_eq!(bin.records[0].record.id, deleted);
+    assert_eq!(bin.records[0].record.name, "Sushi");
+    assert!(!bin.records[0].deleted_at.is_empty());
+    assert_eq!(trash(&app, scenario.cookie("bob_z41")).await.total_count, 0);
+
+    let (status, _) = request(
+        &app,
+        "POST",
+        &format!("/records/{deleted}/restore"),
+        scenario.cookie("bob_z41"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "not bob's record");
+    let (status, body) =
+        request(&app, "POST", &format!("/records/{deleted}/restore"), cookie).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["id"], deleted.as_str());
+    let (status, _) = request(&app, "POST", &format!("/records/{deleted}/restore"), cookie).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "no longer in the trash");
+
+    let mut ids = record_ids(&app, cookie).await;
+    ids.sort();
+    let mut expected = vec![kept, deleted];
+    expected.sort();
+    assert_eq!(ids, expected);
+    assert_eq!(trash(&app, cookie).await.total_count, 0);
+
+    let (status, _) = request(&app, "GET", "/records/trash", "").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+// ---------------------------------------------------------------------------
+// Z42: The purge removes records trashed longer ago than the retention
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z42_purge_removes_old_trash() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z42"])
+        .category("alice_z42", "Dining")
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z42");
+    let old = create(&app, &scenario, "alice_z42", "Old").await;
+    let recent = create(&app, &scenario, "alice_z42", "Recent").await;
+    let live = create(&app, &scenario, "alice_z42", "Live").await;
+    for id in [&old, &recent] {
+        let (status, _) = request(&app, "DELETE", &format!("/records/{id}"), cookie).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+    execute(
+        &app,
+        "UPDATE records SET deleted_at = ? WHERE id = ?",
+        ("2000-01-01T00:00:00.000000000Z", &old),
+    )
+    .await;
+
+    let purged = maintenance::purge_deleted_records(&app.state.main_db, OffsetDateTime::now_utc())
+        .await
+        .expect("purge");
+    assert_eq!(purged, 1);
+    let bin = trash(&app, cookie).await;
+    assert_eq!(bin.total_count, 1);
+    assert_eq!(bin.records[0].record.id, recent);
+    let (status, _) = request(&app, "POST", &format!("/records/{old}/restore"), cookie).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let later = OffsetDateTime::now_utc() + Duration::days(RECORD_TRASH_RETENTION_DAYS + 1);
+    maintenance::purge_deleted_records(&app.state.main_db, later)
+        .await
+        .expect("purge");
+    assert_eq!(trash(&app, cookie).await.total_count, 0);
+    assert_eq!(record_ids(&app, cookie).await, vec![live]);
+}
+
+// ---------------------------------------------------------------------------
+// Z43: A share cannot come back without the payer's record or past a settle-up
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z43_split_share_restore_is_guarded() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z43", "bob_z43"])
+        .category("alice_z43", "Dining")
+        .category("bob_z43", "Dining")
+        .friend("alice_z43", "bob_z43")
+        .split("alice_z43", "Dining", 20.0, &[("bob_z43", 10.0)])
+        .split("alice_z43", "Dining", 40.0, &[("bob_z43", 20.0)])
+        .build(&app)
+        .await;
+    let alice = scenario.cookie("alice_z43");
+    let bob = scenario.cookie("bob_z43");
+    let first_share = scenario.splits[0].pending_record_ids[0].clone();
+    let first_payer = scenario.splits[0].payer_record_id.clone();
+    let unrestorable = |body: &Value| body["code"] == ERROR_CODE_SPLIT_RECORD_UNRESTORABLE;
+
+    // The payer's record goes to the trash while the share is there too
+    for (id, cookie) in [(&first_share, bob), (&first_payer, alice)] {
+        let (status, _) = request(&app, "DELETE", &format!("/records/{id}"), cookie).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+    let (status, body) = request(
+        &app,
+        "POST",
+        &format!("/records/{first_share}/restore"),
+        bob,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(unrestorable(&body), "{body}");
+    let (status, _) = request(
+        &app,
+        "POST",
+        &format!("/records/{first_payer}/restore"),
+        alice,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = request(
+        &app,
+        "POST",
+        &format!("/records/{first_share}/restore"),
+        bob,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // A settle-up between the two skips the trashed share, so it stays trashed
+    for split in &scenario.splits {
+        execute(
+            &app,
+            "UPDATE records SET pending = 0 WHERE id = ? AND split_id = ?",
+            (&split.pending_record_ids[0], &split.split_id),
+        )
+        .await;
+    }
+    let (status, _) = request(&app, "DELETE", &format!("/records/{first_share}"), bob).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, body) = request(
+        &app,
+        "PUT",
+        &format!("/splits/unsettled/{}/settle_all", scenario.id("bob_z43")),
+        alice,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, body) = request(
+        &app,
+        "POST",
+        &format!("/records/{first_share}/restore"),
+        bob,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(unrestorable(&body), "{body}");
+    assert_eq!(trash(&app, bob).await.total_count, 1);
+}
+
+// ---------------------------------------------------------------------------
+// Z44: Restores respect deleted categories and closed periods
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z44_restore_checks_category_and_period() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z44"])
+        .category("alice_z44", "Dining")
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z44");
+    let record = create(&app, &scenario, "alice_z44", "Ramen").await;
+    let (status, _) = request(&app, "DELETE", &format!("/records/{record}"), cookie).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    execute(
+        &app,
+        "INSERT INTO user_settings (user_id, closed_through, updated_at) VALUES (?, ?, '2025-04-01T00:00:00Z')",
+        (scenario.id("alice_z44"), "2025-03-31"),
+    )
+    .await;
+    let (status, _) = request(&app, "POST", &format!("/records/{record}/restore"), cookie).await;
+    assert_eq!(status, StatusCode::CONFLICT, "closed period");
+    let (status, _) = request(
+        &app,
+        "POST",
+        &format!("/records/{record}/restore?reopen=true"),
+        cookie,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = request(
+        &app,
+        "DELETE",
+        &format!("/records/{record}?reopen=true"),
+        cookie,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, body) = request(
+        &app,
+        "DELETE",
+        &format!(
+            "/categories/{}",
+            scenario.category_id("alice_z44", "Dining")
+        ),
+        cookie,
+    )
+    .await;
+    assert!(
+        status.is_success(),
+        "a trashed record does not hold the category: {body}"
+    );
+    let (status, body) = request(
+        &app,
+        "POST",
+        &format!("/records/{record}/restore?reopen=true"),
+        cookie,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    assert_eq!(trash(&app, cookie).await.total_count, 1);
+}
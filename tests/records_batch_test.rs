@@ -0,0 +1,202 @@
+/// Tests Z31-Z33: Batch record creation
+///
+/// `POST /records/batch` takes a JSON array of create payloads (at most
+/// `MAX_RECORDS_PER_BATCH`), validates every one before writing and inserts
+/// them in one transaction, returning the records in input order.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder};
+use common::send_json;
+use kash_server::constants::{ERROR_CODE_BAD_REQUEST, MAX_RECORDS_PER_BATCH};
+use kash_server::models::Record;
+use serde_json::{Value, json};
+
+// ---- Helpers ----
+
+async fn record_count(app: &common::TestApp, scenario: &Scenario, user: &str) -> usize {
+    let (status, body) =
+        common::auth_request(&app.router, "GET", "/records", scenario.cookie(user))
+            .await
+            .expect("list records");
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let body: Value = serde_json::from_str(&body).expect("json");
+    body["records"].as_array().expect("records array").len()
+}
+
+fn payload(name: &str, amount: f64, category_id: &str, date: &str) -> Value {
+    json!({ "name": name, "amount": amount, "category_id": category_id, "date": date })
+}
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob])
+        .category(&alice, "Dining")
+        .income_category(&alice, "Salary")
+        .category(&bob, "Dining")
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z31: Every payload becomes a record, returned in input order
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z31_batch_creates_records_in_order() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z31").await;
+    let dining = scenario.category_id("alice_z31", "Dining");
+    let salary = scenario.category_id("alice_z31", "Salary");
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/records/batch",
+        scenario.cookie("alice_z31"),
+        Some(json!([
+            payload("lunch", 180.0, dining, "2025-03-02"),
+            payload("pay", 1000.0, salary, "2025-03-01"),
+            payload(" coffee ", 60.0, dining, "2025-03-02"),
+        ])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let records: Vec<Record> = serde_json::from_value(body).expect("records");
+    let created: Vec<(&str, f64, &str)> = records
+        .iter()
+        .map(|record| {
+            (
+                record.name.as_str(),
+                record.amount.to_decimal(),
+                record.date.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        created,
+        vec![
+            ("lunch", -180.0, "2025-03-02"),
+            ("pay", 1000.0, "2025-03-01"),
+            ("coffee", -60.0, "2025-03-02"),
+        ]
+    );
+    assert!(records.windows(2).all(|pair| pair[0].seq < pair[1].seq));
+    assert_eq!(record_count(&app, &scenario, "alice_z31").await, 3);
+}
+
+// ---------------------------------------------------------------------------
+// Z32: One invalid payload or foreign category rejects the whole batch
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z32_batch_is_all_or_nothing() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z32").await;
+    let dining = scenario.category_id("alice_z32", "Dining");
+    let cookie = scenario.cookie("alice_z32");
+
+    for (batch, index) in [
+        (
+            json!([
+                payload("lunch", 180.0, dining, "2025-03-02"),
+                payload("coffee", 0.0, dining, "2025-03-02"),
+            ]),
+            "records[1]",
+        ),
+        (
+            json!([
+                payload("lunch", 180.0, dining, "2025-03-02"),
+                payload("taxi", 250.0, dining, "2025-03-02"),
+                payload(
+                    "bob's",
+                    5.0,
+                    scenario.category_id("bob_z32", "Dining"),
+                    "2025-03-02"
+                ),
+            ]),
+            "records[2]",
+        ),
+    ] {
+        let (status, body) = send_json(&app, "POST", "/records/batch", cookie, Some(batch)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+        assert_eq!(body["code"], ERROR_CODE_BAD_REQUEST, "{body}");
+        // The failing item's index leads the message
+        assert!(
+            body["message"]
+                .as_str()
+                .is_some_and(|message| message.starts_with(index)),
+            "{body}"
+        );
+    }
+    assert_eq!(record_count(&app, &scenario, "alice_z32").await, 0);
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        "/settings",
+        cookie,
+        Some(json!({ "closed_through": "2025-02-28" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/records/batch",
+        cookie,
+        Some(json!([
+            payload("lunch", 180.0, dining, "2025-03-02"),
+            payload("late", 9.0, dining, "2025-02-27"),
+        ])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        record_count(&app, &scenario, "alice_z32").await,
+        0,
+        "the first insert rolled back"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z33: Empty and oversized batches are 400; login is required
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z33_batch_size_is_bounded() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z33").await;
+    let dining = scenario.category_id("alice_z33", "Dining");
+    let cookie = scenario.cookie("alice_z33");
+
+    let too_many: Vec<Value> = (0..=MAX_RECORDS_PER_BATCH)
+        .map(|index| payload(&format!("meal {index}"), 1.0, dining, "2025-03-02"))
+        .collect();
+    for batch in [json!([]), json!(too_many)] {
+        let (status, _) = send_json(&app, "POST", "/records/batch", cookie, Some(batch)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/records/batch",
+        cookie,
+        Some(json!(too_many[..MAX_RECORDS_PER_BATCH])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    assert_eq!(body.as_array().map(Vec::len), Some(MAX_RECORDS_PER_BATCH));
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/records/batch",
+        "",
+        Some(json!([payload("lunch", 1.0, dining, "2025-03-02")])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
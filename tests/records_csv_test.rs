@@ -0,0 +1,208 @@
+/// Tests Z11-Z13: CSV export of records
+///
+/// `GET /records/export` streams the caller's records as `text/csv`, oldest
+/// first, in the same `start_date`/`end_date` range as `GET /records`. Names are
+/// quoted when they hold commas, quotes or line breaks.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::constants::{RECORDS_CSV_HEADER, RECORDS_CSV_PAGE_SIZE};
+use kash_server::models::CreateRecordPayload;
+use kash_server::records;
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+struct CsvResponse {
+    status: StatusCode,
+    content_type: Option<String>,
+    disposition: Option<String>,
+    body: String,
+}
+
+async fn export_csv(app: &common::TestApp, cookie: &str, query: &str) -> CsvResponse {
+    let request = Request::builder()
+        .uri(format!("/records/export{query}"))
+        .method("GET")
+        .header("cookie", cookie)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let header_text = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let content_type = header_text(header::CONTENT_TYPE);
+    let disposition = header_text(header::CONTENT_DISPOSITION);
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("body");
+    CsvResponse {
+        status,
+        content_type,
+        disposition,
+        body: String::from_utf8(bytes.to_vec()).expect("utf-8"),
+    }
+}
+
+async fn create(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    name: &str,
+    date: &str,
+) -> String {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id(user),
+        CreateRecordPayload {
+            name: name.to_string(),
+            amount: money(12.5),
+            currency: None,
+            category_id: scenario.category_id(user, "Dining").to_string(),
+            date: date.to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record")
+    .id
+}
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob])
+        .category(&alice, "Dining")
+        .category(&bob, "Dining")
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z11: Rows in the range, escaped, with a download filename
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z11_export_csv_rows() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z11").await;
+    let plain = create(&app, &scenario, "alice_z11", "Ramen", "2025-03-02").await;
+    let tricky = create(
+        &app,
+        &scenario,
+        "alice_z11",
+        "Lunch, \"the good one\"\nwith tea",
+        "2025-03-20",
+    )
+    .await;
+    create(&app, &scenario, "alice_z11", "April", "2025-04-01").await;
+    create(&app, &scenario, "bob_z11", "Bob's", "2025-03-10").await;
+
+    let response = export_csv(
+        &app,
+        scenario.cookie("alice_z11"),
+        "?start_date=2025-03-01&end_date=2025-03-31",
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    assert!(
+        response
+            .content_type
+            .as_deref()
+            .is_some_and(|value| value.starts_with("text/csv")),
+        "{:?}",
+        response.content_type
+    );
+    assert_eq!(
+        response.disposition.as_deref(),
+        Some("attachment; filename=\"records-2025-03.csv\"")
+    );
+    assert_eq!(
+        response.body,
+        format!(
+            "{RECORDS_CSV_HEADER}\n\
+             {plain},Ramen,-12.5,TWD,Dining,2025-03-02,false,false,,\n\
+             {tricky},\"Lunch, \"\"the good one\"\"\nwith tea\",-12.5,TWD,Dining,2025-03-20,false,false,,\n"
+        )
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z12: Exports longer than a page arrive complete and in order
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z12_export_csv_spans_pages() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z12").await;
+    let total = RECORDS_CSV_PAGE_SIZE as usize + 3;
+    for day in 0..total {
+        let date = format!("2024-{:02}-{:02}", day % 12 + 1, day % 28 + 1);
+        create(&app, &scenario, "alice_z12", &format!("Meal {day}"), &date).await;
+    }
+
+    let response = export_csv(&app, scenario.cookie("alice_z12"), "").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.disposition.as_deref(),
+        Some("attachment; filename=\"records.csv\"")
+    );
+    let rows: Vec<(&str, &str)> = response
+        .body
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let fields: Vec<&str> = line.split(',').collect();
+            (fields[5], fields[0])
+        })
+        .collect();
+    assert_eq!(rows.len(), total);
+    let mut sorted = rows.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(rows, sorted, "ordered by date and id with no repeats");
+}
+
+// ---------------------------------------------------------------------------
+// Z13: Invalid dates are 400; login is required
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z13_export_csv_validation() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z13").await;
+
+    for query in ["?start_date=2025-13-01", "?end_date=yesterday"] {
+        let response = export_csv(&app, scenario.cookie("alice_z13"), query).await;
+        assert_eq!(response.status, StatusCode::BAD_REQUEST, "{query}");
+    }
+    let response = export_csv(
+        &app,
+        scenario.cookie("alice_z13"),
+        "?start_date=2025-01-01&end_date=2025-06-30",
+    )
+    .await;
+    assert_eq!(response.body, format!("{RECORDS_CSV_HEADER}\n"));
+    assert_eq!(
+        response.disposition.as_deref(),
+        Some("attachment; filename=\"records-2025-01-01_2025-06-30.csv\"")
+    );
+
+    let response = export_csv(&app, "", "").await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,209 @@
+/// Tests Z391-Z393: Cursor pagination of GET /records
+///
+/// Each page carries an opaque `next_cursor` while more rows follow; passing it back
+/// as `cursor` continues after the last row sent, so records added meanwhile do not
+/// shift later pages. Cursor pages skip `total_count` unless `include_total=true`,
+/// and `offset` paging keeps working as before.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::constants::*;
+use kash_server::models::{CreateRecordPayload, GetRecordsResponse};
+use kash_server::records;
+use serde_json::Value;
+
+// ---- Helpers ----
+
+async fn list(app: &common::TestApp, cookie: &str, query: &str) -> (StatusCode, Value) {
+    let (status, body) =
+        common::auth_request(&app.router, "GET", &format!("/records?{query}"), cookie)
+            .await
+            .expect("request");
+    let body = serde_json::from_str(&body).unwrap_or(Value::String(body));
+    (status, body)
+}
+
+async fn page(app: &common::TestApp, cookie: &str, query: &str) -> GetRecordsResponse {
+    let (status, body) = list(app, cookie, query).await;
+    assert_eq!(status, StatusCode::OK, "{query}: {body}");
+    serde_json::from_value(body).expect("records page")
+}
+
+fn names(page: &GetRecordsResponse) -> Vec<String> {
+    page.records
+        .iter()
+        .map(|record| record.name.clone())
+        .collect()
+}
+
+async fn create(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    name: &str,
+    amount: f64,
+    date: &str,
+) {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id(user),
+        CreateRecordPayload {
+            name: name.to_string(),
+            amount: money(amount),
+            currency: None,
+            category_id: scenario.category_id(user, "Food").to_string(),
+            date: date.to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record");
+}
+
+/// Every page of `query` through `next_cursor`, `limit` at a time.
+async fn walk(app: &common::TestApp, cookie: &str, query: &str, limit: u32) -> Vec<String> {
+    let mut seen = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let cursor_param = cursor
+            .as_ref()
+            .map(|cursor| format!("&cursor={cursor}"))
+            .unwrap_or_default();
+        let page = page(app, cookie, &format!("{query}&limit={limit}{cursor_param}")).await;
+        assert!(page.records.len() <= limit as usize);
+        seen.extend(names(&page));
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => return seen,
+        }
+    }
+}
+
+async fn scenario(app: &common::TestApp, user: &str) -> Scenario {
+    let scenario = ScenarioBuilder::new()
+        .user(user)
+        .category(user, "Food")
+        .build(app)
+        .await;
+    for (name, amount, date) in [
+        ("Bagel", -4.0, "2026-03-01"),
+        ("apple", -2.5, "2026-03-02"),
+        ("Curry", -15.0, "2026-03-02"),
+        ("donut", -3.0, "2026-03-02"),
+        ("Eclair", -6.0, "2026-03-04"),
+        ("fig", -2.5, "2026-03-05"),
+        ("Gelato", -8.0, "2026-03-05"),
+    ] {
+        create(app, &scenario, user, name, amount, date).await;
+    }
+    scenario
+}
+
+// ---------------------------------------------------------------------------
+// Z391: Cursor pages cover every record once, even with inserts in between
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z391_cursor_pages_are_stable_under_inserts() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "alice_z391").await;
+    let cookie = scenario.cookie("alice_z391");
+
+    let everything = names(&page(&app, cookie, "limit=50").await);
+    assert_eq!(walk(&app, cookie, "sort_by=date", 3).await, everything);
+
+    let first = page(&app, cookie, "limit=3").await;
+    assert_eq!(first.total_count, Some(7));
+    let cursor = first.next_cursor.clone().expect("more pages");
+
+    // A newer record would push every offset page down by one
+    create(&app, &scenario, "alice_z391", "Hotpot", -30.0, "2026-03-06").await;
+    let second = page(&app, cookie, &format!("limit=3&cursor={cursor}")).await;
+    assert_eq!(second.total_count, None);
+    assert_eq!(names(&second), everything[3..6].to_vec());
+    let third = page(
+        &app,
+        cookie,
+        &format!("limit=3&cursor={}", second.next_cursor.unwrap()),
+    )
+    .await;
+    assert_eq!(names(&third), everything[6..].to_vec());
+    assert_eq!(third.next_cursor, None);
+
+    // Offset paging still counts every match
+    let by_offset = page(&app, cookie, "limit=3&offset=3").await;
+    assert_eq!(by_offset.total_count, Some(8));
+}
+
+// ---------------------------------------------------------------------------
+// Z392: Cursors follow every sort and combine with filters
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z392_cursor_follows_sort_and_filters() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "bob_z392").await;
+    let cookie = scenario.cookie("bob_z392");
+
+    for query in [
+        "sort_by=date&order=asc",
+        "sort_by=amount",
+        "sort_by=amount&order=asc",
+        "sort_by=name",
+        "sort_by=name&order=desc",
+        "sort_by=created",
+        "min_amount=-7&q=e",
+    ] {
+        let everything = names(&page(&app, cookie, &format!("{query}&limit=50")).await);
+        for limit in [1, 2, 4] {
+            assert_eq!(
+                walk(&app, cookie, query, limit).await,
+                everything,
+                "{query} limit={limit}"
+            );
+        }
+    }
+
+    let first = page(&app, cookie, "limit=2&include_total=false").await;
+    assert_eq!(first.total_count, None);
+    let cursor = first.next_cursor.unwrap();
+    let second = page(
+        &app,
+        cookie,
+        &format!("limit=2&cursor={cursor}&include_total=true"),
+    )
+    .await;
+    assert_eq!(second.total_count, Some(7));
+}
+
+// ---------------------------------------------------------------------------
+// Z393: Foreign, malformed and mixed cursors are rejected
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z393_bad_cursors_are_rejected() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "carol_z393").await;
+    let cookie = scenario.cookie("carol_z393");
+    let cursor = page(&app, cookie, "limit=2").await.next_cursor.unwrap();
+
+    for query in [
+        "cursor=not-a-cursor".to_string(),
+        "cursor=7b7d".to_string(),
+        format!("cursor={cursor}&sort_by=amount"),
+        format!("cursor={cursor}&order=asc"),
+    ] {
+        let (status, body) = list(&app, cookie, &query).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+        assert_eq!(body["code"], ERROR_CODE_INVALID_CURSOR, "{query}");
+    }
+
+    let (status, _) = list(&app, cookie, &format!("cursor={cursor}&offset=2")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,325 @@
+/// Tests Z191-Z194: Idempotent record creation
+///
+/// `POST /records` and `POST /records/batch` accept an optional `Idempotency-Key`
+/// header. A retry with the same key and payload replays the first response without
+/// creating anything; the same key with another payload is 409. Keys are scoped per
+/// endpoint, a failed request releases its key, and a reservation left behind by a
+/// crash is treated as unused once it is old enough. A retry arriving while the first
+/// request still runs waits for its response.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder};
+use kash_server::idempotency::{self, CachedOutcome, classify_entry};
+use kash_server::models::CreateRecordPayload;
+use kash_server::split_repo::IdempotencyEntry;
+use serde_json::{Value, json};
+use time::{Duration, OffsetDateTime, format_description::well_known::Rfc3339};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn post(
+    app: &common::TestApp,
+    uri: &str,
+    cookie: &str,
+    key: Option<&str>,
+    payload: Value,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header("cookie", cookie)
+        .header("content-type", "application/json");
+    if let Some(key) = key {
+        request = request.header("Idempotency-Key", key);
+    }
+    let request = request.body(Body::from(payload.to_string())).unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    (status, body)
+}
+
+async fn record_count(app: &common::TestApp, user_id: &str) -> i64 {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM records WHERE owner_user_id = ?",
+            [user_id],
+        )
+        .await
+        .unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+fn lunch(scenario: &Scenario, user: &str, amount: f64) -> Value {
+    json!({
+        "name": "Lunch",
+        "amount": amount,
+        "category_id": scenario.category_id(user, "Dining"),
+        "date": "2026-03-02",
+    })
+}
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    ScenarioBuilder::new()
+        .user(&alice)
+        .category(&alice, "Dining")
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z191: A retried POST /records replays; another payload under the key is 409
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z191_record_create_replays() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z191").await;
+    let (alice, cookie) = (scenario.id("alice_z191"), scenario.cookie("alice_z191"));
+    let payload = lunch(&scenario, "alice_z191", -12.5);
+
+    let (status, first) = post(&app, "/records", cookie, Some("z191-key"), payload.clone()).await;
+    assert_eq!(status, StatusCode::CREATED, "{first}");
+    let (status, replay) = post(&app, "/records", cookie, Some("z191-key"), payload.clone()).await;
+    assert_eq!(status, StatusCode::CREATED, "{replay}");
+    assert_eq!(replay, first);
+    assert_eq!(record_count(&app, alice).await, 1);
+
+    let (status, body) = post(
+        &app,
+        "/records",
+        cookie,
+        Some("z191-key"),
+        lunch(&scenario, "alice_z191", -13.0),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+    let (status, _) = post(&app, "/records", cookie, None, payload.clone()).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = post(&app, "/records", cookie, None, payload).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(
+        record_count(&app, alice).await,
+        3,
+        "no header, no deduplication"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z192: Batches replay too, and keys are scoped per endpoint
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z192_batch_create_replays_per_endpoint() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z192").await;
+    let (alice, cookie) = (scenario.id("alice_z192"), scenario.cookie("alice_z192"));
+    let batch = json!([
+        lunch(&scenario, "alice_z192", -10.0),
+        lunch(&scenario, "alice_z192", -20.0),
+    ]);
+
+    let (status, first) = post(
+        &app,
+        "/records/batch",
+        cookie,
+        Some("shared"),
+        batch.clone(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{first}");
+    let (status, replay) = post(&app, "/records/batch", cookie, Some("shared"), batch).await;
+    assert_eq!(status, StatusCode::CREATED, "{replay}");
+    assert_eq!(replay, first);
+    assert_eq!(record_count(&app, alice).await, 2);
+
+    let (status, body) = post(
+        &app,
+        "/records",
+        cookie,
+        Some("shared"),
+        lunch(&scenario, "alice_z192", -5.0),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    assert_eq!(record_count(&app, alice).await, 3);
+
+    let (status, body) = post(
+        &app,
+        "/records",
+        cookie,
+        Some(&"k".repeat(256)),
+        lunch(&scenario, "alice_z192", -5.0),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+}
+
+// ---------------------------------------------------------------------------
+// Z193: Failed requests and crashed reservations leave the key usable
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z193_failed_and_stale_keys_are_reusable() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z193").await;
+    let (alice, cookie) = (scenario.id("alice_z193"), scenario.cookie("alice_z193"));
+
+    let mut bad = lunch(&scenario, "alice_z193", -9.0);
+    bad["category_id"] = json!("missing-category");
+    let (status, _) = post(&app, "/records", cookie, Some("z193-failed"), bad).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, body) = post(
+        &app,
+        "/records",
+        cookie,
+        Some("z193-failed"),
+        lunch(&scenario, "alice_z193", -9.0),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "INSERT INTO idempotency_keys (id, key, user_id, endpoint, payload_hash, response_status, response_body, created_at, expires_at) \
+             VALUES ('z193-stale', 'z193-stale', ?, '/records', 'somehash', 0, NULL, '2026-01-01T00:00:00Z', '2999-01-01T00:00:00Z')",
+            [alice],
+        )
+        .await
+        .expect("insert stale reservation");
+    }
+    let (status, body) = post(
+        &app,
+        "/records",
+        cookie,
+        Some("z193-stale"),
+        lunch(&scenario, "alice_z193", -4.0),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    assert_eq!(record_count(&app, alice).await, 2);
+
+    let now = OffsetDateTime::now_utc();
+    let entry = |body: Option<&str>, digest: Option<&str>| IdempotencyEntry {
+        response_status: 201,
+        response_body: body.map(str::to_string),
+        payload_hash: "hash".to_string(),
+        response_digest: digest.map(str::to_string),
+        created_at: "2026-01-01T00:00:00Z".to_string(),
+    };
+    assert_eq!(classify_entry(None, "hash", now), CachedOutcome::Absent);
+    assert_eq!(
+        classify_entry(Some(entry(None, None)), "hash", now),
+        CachedOutcome::Stale
+    );
+    let in_flight = IdempotencyEntry {
+        created_at: (now - Duration::seconds(1)).format(&Rfc3339).unwrap(),
+        ..entry(None, None)
+    };
+    assert_eq!(
+        classify_entry(Some(in_flight), "hash", now),
+        CachedOutcome::InProgress
+    );
+    assert_eq!(
+        classify_entry(Some(entry(Some("{}"), None)), "other", now),
+        CachedOutcome::Conflict
+    );
+    assert_eq!(
+        classify_entry(Some(entry(Some(""), Some("digest"))), "hash", now),
+        CachedOutcome::ReplayUnavailable
+    );
+    assert_eq!(
+        classify_entry(Some(entry(Some("{}"), None)), "hash", now),
+        CachedOutcome::Replay {
+            status: 201,
+            body: "{}".to_string()
+        }
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z194: A retry during the first request waits for it and replays its response
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z194_retry_waits_for_in_flight_request() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z194").await;
+    let (alice, cookie) = (scenario.id("alice_z194"), scenario.cookie("alice_z194"));
+    let payload = lunch(&scenario, "alice_z194", -6.0);
+    let (_, first) = post(&app, "/records", cookie, None, payload.clone()).await;
+
+    // The first request holds the key and is still running
+    let payload_hash = idempotency::payload_hash(
+        &serde_json::from_value::<CreateRecordPayload>(payload.clone()).unwrap(),
+    )
+    .unwrap();
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "INSERT INTO idempotency_keys (id, key, user_id, endpoint, payload_hash, response_status, response_body, created_at, expires_at) \
+             VALUES ('z194', 'z194-key', ?, '/records', ?, 0, NULL, ?, '2999-01-01T00:00:00Z')",
+            libsql::params![
+                alice,
+                payload_hash,
+                OffsetDateTime::now_utc().format(&Rfc3339).unwrap()
+            ],
+        )
+        .await
+        .expect("insert in-flight reservation");
+    }
+
+    let retry = {
+        let app_router = app.router.clone();
+        let cookie = cookie.to_string();
+        let payload = payload.clone();
+        tokio::spawn(async move {
+            let request = Request::builder()
+                .uri("/records")
+                .method("POST")
+                .header("cookie", cookie)
+                .header("content-type", "application/json")
+                .header("Idempotency-Key", "z194-key")
+                .body(Body::from(payload.to_string()))
+                .unwrap();
+            let response = app_router.oneshot(request).await.unwrap();
+            let status = response.status();
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, serde_json::from_slice::<Value>(&bytes).unwrap())
+        })
+    };
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "UPDATE idempotency_keys SET response_status = 201, response_body = ? WHERE id = 'z194'",
+            [first.to_string()],
+        )
+        .await
+        .unwrap();
+    }
+
+    let (status, body) = retry.await.unwrap();
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    assert_eq!(body, first);
+    assert_eq!(
+        record_count(&app, alice).await,
+        1,
+        "the retry created nothing"
+    );
+}
@@ -0,0 +1,202 @@
+/// Tests Z21-Z23: CSV import of records
+///
+/// `POST /records/import` takes a `text/csv` body with a `name,amount,category,date`
+/// header and creates every row in one transaction. Any bad line rolls the whole
+/// file back and is reported by line number.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder};
+use kash_server::constants::DEFAULT_CSV_IMPORT_MAX_ROWS;
+use kash_server::models::{ImportRecordsResponse, Record};
+use serde_json::Value;
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn import_csv(
+    app: &common::TestApp,
+    cookie: &str,
+    query: &str,
+    csv: &str,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .uri(format!("/records/import{query}"))
+        .method("POST")
+        .header("cookie", cookie)
+        .header("content-type", "text/csv")
+        .body(Body::from(csv.to_string()))
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("body");
+    let body = String::from_utf8(bytes.to_vec()).expect("utf-8");
+    let body = serde_json::from_str(&body).unwrap_or(Value::String(body));
+    (status, body)
+}
+
+async fn list_records(app: &common::TestApp, scenario: &Scenario, user: &str) -> Vec<Record> {
+    let (status, body) =
+        common::auth_request(&app.router, "GET", "/records", scenario.cookie(user))
+            .await
+            .expect("list records");
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let body: Value = serde_json::from_str(&body).expect("json");
+    serde_json::from_value(body["records"].clone()).expect("records")
+}
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice])
+        .category(&alice, "Dining")
+        .income_category(&alice, "Salary")
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z21: Every row becomes a record, signed by its category
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z21_import_csv_creates_records() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z21").await;
+    let csv = "\u{feff}Date,Name,Amount,Category,Note\r\n\
+               2025-03-02,Ramen,12.5,dining,ignored\r\n\
+               2025-03-01,\"Pay, \"\"March\"\"\",1000,SALARY,\r\n\
+               \r\n";
+
+    let (status, body) = import_csv(&app, scenario.cookie("alice_z21"), "", csv).await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let response: ImportRecordsResponse = serde_json::from_value(body).expect("response");
+    assert_eq!(response.created, 2);
+    assert!(response.created_categories.is_empty());
+    assert!(response.errors.is_empty());
+
+    let mut records: Vec<(String, f64, Option<String>, String)> =
+        list_records(&app, &scenario, "alice_z21")
+            .await
+            .into_iter()
+            .map(|record| {
+                (
+                    record.name,
+                    record.amount.to_decimal(),
+                    record.category_id,
+                    record.date,
+                )
+            })
+            .collect();
+    records.sort_by(|a, b| a.3.cmp(&b.3));
+    assert_eq!(
+        records,
+        vec![
+            (
+                "Pay, \"March\"".to_string(),
+                1000.0,
+                Some(scenario.category_id("alice_z21", "Salary").to_string()),
+                "2025-03-01".to_string(),
+            ),
+            (
+                "Ramen".to_string(),
+                -12.5,
+                Some(scenario.category_id("alice_z21", "Dining").to_string()),
+                "2025-03-02".to_string(),
+            ),
+        ]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z22: One bad line rolls back the file and every failing line is reported
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z22_import_csv_is_all_or_nothing() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z22").await;
+    let csv = "name,amount,category,date\n\
+               Ramen,12.5,Dining,2025-03-02\n\
+               \"Two\nlines\",abc,Dining,2025-03-03\n\
+               Taxi,8,Transport,2025-03-04\n\
+               Tea,3,Dining,2025-02-30\n";
+
+    let (status, body) = import_csv(&app, scenario.cookie("alice_z22"), "", csv).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    let response: ImportRecordsResponse = serde_json::from_value(body).expect("response");
+    assert_eq!(response.created, 0);
+    let lines: Vec<usize> = response.errors.iter().map(|error| error.line).collect();
+    assert_eq!(lines, vec![3, 5, 6]);
+    assert!(response.errors[1].message.contains("Transport"));
+    assert!(list_records(&app, &scenario, "alice_z22").await.is_empty());
+
+    for (csv, expected) in [
+        ("", StatusCode::BAD_REQUEST),
+        (
+            "name,amount,date\nRamen,1,2025-03-02\n",
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            "name,amount,category,date\n\"Ramen,1,Dining,2025-03-02\n",
+            StatusCode::BAD_REQUEST,
+        ),
+    ] {
+        let (status, _) = import_csv(&app, scenario.cookie("alice_z22"), "", csv).await;
+        assert_eq!(status, expected, "{csv:?}");
+    }
+
+    let (status, _) = import_csv(&app, "", "", "name,amount,category,date\n").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+// ---------------------------------------------------------------------------
+// Z23: Missing categories can be created; the row cap is 413
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z23_import_csv_creates_missing_categories() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z23").await;
+    let csv = "name,amount,category,date\n\
+               Taxi,-8,Transport,2025-03-04\n\
+               Bus,-2,transport,2025-03-05\n\
+               Refund,15,Cashback,2025-03-06\n";
+
+    let (status, body) = import_csv(
+        &app,
+        scenario.cookie("alice_z23"),
+        "?create_missing_categories=true",
+        csv,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let response: ImportRecordsResponse = serde_json::from_value(body).expect("response");
+    assert_eq!(response.created, 3);
+    assert_eq!(response.created_categories, vec!["Transport", "Cashback"]);
+    let records = list_records(&app, &scenario, "alice_z23").await;
+    assert_eq!(records.len(), 3);
+    let transport: Vec<&Record> = records
+        .iter()
+        .filter(|record| record.name == "Taxi" || record.name == "Bus")
+        .collect();
+    assert_eq!(transport[0].category_id, transport[1].category_id);
+    assert!(
+        records
+            .iter()
+            .all(|record| (record.name == "Refund") == record.amount.is_positive())
+    );
+
+    let too_many = format!(
+        "name,amount,category,date\n{}",
+        "Tea,3,Dining,2025-03-02\n".repeat(DEFAULT_CSV_IMPORT_MAX_ROWS + 1)
+    );
+    let (status, _) = import_csv(&app, scenario.cookie("alice_z23"), "", &too_many).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(list_records(&app, &scenario, "alice_z23").await.len(), 3);
+}
@@ -0,0 +1,203 @@
+/// Tests Z51-Z53: Searching records
+///
+/// `GET /records?q&min_amount&max_amount` narrows the list to records whose name
+/// holds `q` (case-insensitive) and whose signed amount is in range, on top of
+/// the date, pending and settle filters. `total_count` counts the matches, so
+/// paging through a search adds up.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::models::{CreateRecordPayload, GetRecordsResponse};
+use kash_server::records;
+use serde_json::Value;
+
+// ---- Helpers ----
+
+async fn list(app: &common::TestApp, cookie: &str, query: &str) -> (StatusCode, Value) {
+    let (status, body) =
+        common::auth_request(&app.router, "GET", &format!("/records?{query}"), cookie)
+            .await
+            .expect("request");
+    let body = serde_json::from_str(&body).unwrap_or(Value::String(body));
+    (status, body)
+}
+
+async fn page(app: &common::TestApp, cookie: &str, query: &str) -> GetRecordsResponse {
+    let (status, body) = list(app, cookie, query).await;
+    assert_eq!(status, StatusCode::OK, "{query}: {body}");
+    serde_json::from_value(body).expect("records page")
+}
+
+fn names(page: &GetRecordsResponse) -> Vec<&str> {
+    page.records
+        .iter()
+        .map(|record| record.name.as_str())
+        .collect()
+}
+
+async fn create(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    name: &str,
+    amount: f64,
+    date: &str,
+) {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id(user),
+        CreateRecordPayload {
+            name: name.to_string(),
+            amount: money(amount),
+            currency: None,
+            category_id: scenario.category_id(user, "Transport").to_string(),
+            date: date.to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record");
+}
+
+// ---------------------------------------------------------------------------
+// Z51: q and the amount range combine with the date filters
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z51_search_combines_filters() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z51", "bob_z51"])
+        .category("alice_z51", "Transport")
+        .category("bob_z51", "Transport")
+        .build(&app)
+        .await;
+    for (name, amount, date) in [
+        ("Uber to airport", 40.0, "2025-03-02"),
+        ("uber eats", 15.0, "2025-03-20"),
+        ("Train", 8.0, "2025-03-21"),
+        ("UBER home", 22.0, "2025-04-05"),
+    ] {
+        create(&app, &scenario, "alice_z51", name, amount, date).await;
+    }
+    create(&app, &scenario, "bob_z51", "Uber", 30.0, "2025-03-10").await;
+    let cookie = scenario.cookie("alice_z51");
+
+    let all = page(&app, cookie, "q=uBeR").await;
+    assert_eq!(
+        names(&all),
+        vec!["UBER home", "uber eats", "Uber to airport"]
+    );
+    assert_eq!(all.total_count, Some(3));
+
+    let march = page(
+        &app,
+        cookie,
+        "q=uber&start_date=2025-03-01&end_date=2025-03-31",
+    )
+    .await;
+    assert_eq!(names(&march), vec!["uber eats", "Uber to airport"]);
+    assert_eq!(march.total_count, Some(2));
+
+    // Expenses are negative, so "more than 20 spent" is max_amount=-20
+    let large = page(&app, cookie, "q=uber&max_amount=-20").await;
+    assert_eq!(names(&large), vec!["UBER home", "Uber to airport"]);
+    let range = page(&app, cookie, "min_amount=-20&max_amount=-10").await;
+    assert_eq!(names(&range), vec!["uber eats"]);
+    assert_eq!(range.total_count, Some(1));
+
+    assert_eq!(
+        page(&app, cookie, "q=uber&pending=true").await.total_count,
+        Some(0)
+    );
+    assert_eq!(
+        page(&app, cookie, "q=%20%20").await.total_count,
+        Some(4),
+        "blank q"
+    );
+    assert_eq!(page(&app, cookie, "q=taxi").await.total_count, Some(0));
+}
+
+// ---------------------------------------------------------------------------
+// Z52: Paging through a search gives every match once with a stable total
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z52_search_pagination_total_count() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z52"])
+        .category("alice_z52", "Transport")
+        .build(&app)
+        .await;
+    for day in 1..=7 {
+        let date = format!("2025-03-{day:02}");
+        create(
+            &app,
+            &scenario,
+            "alice_z52",
+            &format!("Uber {day}"),
+            10.0,
+            &date,
+        )
+        .await;
+        create(
+            &app,
+            &scenario,
+            "alice_z52",
+            &format!("Bus {day}"),
+            2.0,
+            &date,
+        )
+        .await;
+    }
+    let cookie = scenario.cookie("alice_z52");
+
+    let mut seen = Vec::new();
+    for offset in [0, 3, 6] {
+        let page = page(&app, cookie, &format!("q=uber&limit=3&offset={offset}")).await;
+        assert_eq!(page.total_count, Some(7), "offset {offset}");
+        seen.extend(page.records.into_iter().map(|record| record.name));
+    }
+    let expected: Vec<String> = (1..=7).rev().map(|day| format!("Uber {day}")).collect();
+    assert_eq!(seen, expected);
+
+    let past_end = page(&app, cookie, "q=uber&limit=3&offset=9").await;
+    assert!(past_end.records.is_empty());
+    assert_eq!(past_end.total_count, Some(7));
+}
+
+// ---------------------------------------------------------------------------
+// Z53: Bad ranges and over-long queries are 400; login is required
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z53_search_validation() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z53"])
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z53");
+
+    let long = format!("q={}", "x".repeat(256));
+    for query in [
+        "min_amount=10&max_amount=5",
+        "min_amount=abc",
+        "max_amount=NaN",
+        "min_amount=inf",
+        long.as_str(),
+    ] {
+        let (status, _) = list(&app, cookie, query).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+    }
+
+    let (status, _) = list(&app, "", "q=uber").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
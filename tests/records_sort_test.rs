@@ -0,0 +1,184 @@
+/// Tests Z61-Z63: Sorting records
+///
+/// `GET /records?sort_by&order` orders the page by `date` (the default),
+/// `amount`, `name` (case-insensitive) or `created`. Ties break by the unique
+/// creation `seq`, so paging through equal values never repeats or skips a
+/// record. Anything outside those values is 400 before it reaches SQL.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::constants::ERROR_CODE_INVALID_SORT;
+use kash_server::models::{CreateRecordPayload, GetRecordsResponse};
+use kash_server::records;
+use serde_json::Value;
+
+// ---- Helpers ----
+
+async fn list(app: &common::TestApp, cookie: &str, query: &str) -> (StatusCode, Value) {
+    let (status, body) =
+        common::auth_request(&app.router, "GET", &format!("/records?{query}"), cookie)
+            .await
+            .expect("request");
+    let body = serde_json::from_str(&body).unwrap_or(Value::String(body));
+    (status, body)
+}
+
+async fn names(app: &common::TestApp, cookie: &str, query: &str) -> Vec<String> {
+    let (status, body) = list(app, cookie, query).await;
+    assert_eq!(status, StatusCode::OK, "{query}: {body}");
+    let page: GetRecordsResponse = serde_json::from_value(body).expect("records page");
+    page.records.into_iter().map(|record| record.name).collect()
+}
+
+async fn create(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    name: &str,
+    amount: f64,
+    date: &str,
+) {
+    records::create_record_for_user(
+        &app.state.main_db,
+        scenario.id(user),
+        CreateRecordPayload {
+            name: name.to_string(),
+            amount: money(amount),
+            currency: None,
+            category_id: scenario.category_id(user, "Dining").to_string(),
+            date: date.to_string(),
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: Vec::new(),
+        },
+        None,
+        false,
+    )
+    .await
+    .expect("create record");
+}
+
+async fn scenario(app: &common::TestApp, user: &str) -> Scenario {
+    ScenarioBuilder::new()
+        .users(&[user])
+        .category(user, "Dining")
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z61: Each sort field in both directions
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z61_sort_by_each_field() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "alice_z61").await;
+    for (name, amount, date) in [
+        ("bagel", 4.0, "2025-03-03"),
+        ("Coffee", 3.0, "2025-03-01"),
+        ("apple", 9.0, "2025-03-02"),
+    ] {
+        create(&app, &scenario, "alice_z61", name, amount, date).await;
+    }
+    let cookie = scenario.cookie("alice_z61");
+
+    assert_eq!(names(&app, cookie, "").await, ["bagel", "apple", "Coffee"]);
+    assert_eq!(
+        names(&app, cookie, "sort_by=date&order=asc").await,
+        ["Coffee", "apple", "bagel"]
+    );
+    assert_eq!(
+        names(&app, cookie, "sort_by=name").await,
+        ["apple", "bagel", "Coffee"],
+        "names default to A-Z, ignoring case"
+    );
+    assert_eq!(
+        names(&app, cookie, "sort_by=name&order=desc").await,
+        ["Coffee", "bagel", "apple"]
+    );
+    // Expenses are negative: ascending puts the largest expense first
+    assert_eq!(
+        names(&app, cookie, "sort_by=amount&order=asc").await,
+        ["apple", "bagel", "Coffee"]
+    );
+    assert_eq!(
+        names(&app, cookie, "sort_by=created").await,
+        ["apple", "Coffee", "bagel"]
+    );
+    assert_eq!(
+        names(&app, cookie, "sort_by=created&order=asc&limit=2").await,
+        ["bagel", "Coffee"]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z62: Pages over tied values neither repeat nor skip records
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z62_ties_page_without_duplicates() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "alice_z62").await;
+    for i in 0..10 {
+        create(
+            &app,
+            &scenario,
+            "alice_z62",
+            &format!("Meal {i}"),
+            5.0,
+            "2025-03-01",
+        )
+        .await;
+    }
+    let cookie = scenario.cookie("alice_z62");
+
+    for sort in ["date", "amount", "name", "created"] {
+        for order in ["asc", "desc"] {
+            let mut seen = Vec::new();
+            for offset in [0, 4, 8] {
+                let query = format!("sort_by={sort}&order={order}&limit=4&offset={offset}");
+                seen.extend(names(&app, cookie, &query).await);
+            }
+            let mut unique = seen.clone();
+            unique.sort();
+            unique.dedup();
+            assert_eq!(unique.len(), 10, "{sort} {order}: {seen:?}");
+            assert_eq!(
+                seen,
+                names(&app, cookie, &format!("sort_by={sort}&order={order}")).await
+            );
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Z63: Unknown fields and directions are 400 with the valid values
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z63_unknown_sort_is_rejected() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "alice_z63").await;
+    let cookie = scenario.cookie("alice_z63");
+
+    for query in [
+        "sort_by=price",
+        "sort_by=date%20DESC;%20DROP%20TABLE%20records",
+        "sort_by=Name",
+    ] {
+        let (status, body) = list(&app, cookie, query).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+        assert_eq!(body["code"], ERROR_CODE_INVALID_SORT, "{body}");
+        assert_eq!(body["details"]["field"], "sort_by", "{body}");
+    }
+    let (status, body) = list(&app, cookie, "sort_by=amount&order=up").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], ERROR_CODE_INVALID_SORT, "{body}");
+    assert_eq!(body["details"]["field"], "order", "{body}");
+
+    let (status, _) = list(&app, "", "sort_by=amount").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
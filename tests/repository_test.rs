@@ -0,0 +1,417 @@
+/// Tests P1-P5: Repository modules against a temp database
+///
+/// `friendship_repo`, `split_repo` and `record_repo` own the SQL the handlers
+/// used to inline. These tests call them directly on a connection, without
+/// going through HTTP, to pin down the invariants callers rely on: friendship
+/// pairs move together, settlement reads the same from either user, and the record
+/// filters agree between count and page.
+mod common;
+
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::constants::*;
+use kash_server::friendship_repo::{self, FriendDirection, FriendListKind};
+use kash_server::record_repo::{self, NewRecord, RecordFilter, RecordSort};
+use kash_server::split_repo;
+
+// ---- Helpers ----
+
+async fn users(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob])
+        .category(&alice, "Dining")
+        .category(&bob, "Food")
+        .build(app)
+        .await
+}
+
+async fn split_scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob])
+        .category(&alice, "Dining")
+        .category(&bob, "Food")
+        .friend(&alice, &bob)
+        .split(&alice, "Dining", 90.0, &[(&bob, 30.0)])
+        .build(app)
+        .await
+}
+
+fn all_dates<'a>(pending: Option<bool>, settle: Option<bool>) -> RecordFilter<'a> {
+    RecordFilter {
+        start_date: "0000-01-01",
+        end_date: "9999-12-31",
+        pending,
+        settle,
+        category_id: None,
+        name_contains: None,
+        min_amount: None,
+        max_amount: None,
+        tag: None,
+        sort: RecordSort::default(),
+        after: None,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// P1: Friendship pair operations always touch both directed rows
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn p1_friendship_pair_is_symmetric() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = users(&app, "p1").await;
+    let alice = scenario.id("alice_p1");
+    let bob = scenario.id("bob_p1");
+    let conn = app.state.main_db.write().await;
+
+    friendship_repo::insert_pending_pair(&conn, alice, bob, ("p1-ab", "p1-ba"), None)
+        .await
+        .expect("insert pair");
+
+    for (from, to) in [(alice, bob), (bob, alice)] {
+        assert!(
+            friendship_repo::has_open_relation(&conn, from, to)
+                .await
+                .expect("open relation")
+        );
+        let relation = friendship_repo::find_active_relation(&conn, from, to)
+            .await
+            .expect("find relation")
+            .expect("row exists");
+        assert!(relation.pending);
+        assert_eq!(relation.user_id, to);
+    }
+
+    let incoming = |user| {
+        friendship_repo::count_friends(
+            &conn,
+            user,
+            FriendListKind::Pending,
+            Some(FriendDirection::Incoming),
+            None,
+        )
+    };
+    assert_eq!(incoming(bob).await.expect("count"), 1, "recipient sees it");
+    assert_eq!(
+        incoming(alice).await.expect("count"),
+        0,
+        "requester does not"
+    );
+
+    friendship_repo::accept_pair(&conn, bob, alice)
+        .await
+        .expect("accept");
+    for (from, to) in [(alice, bob), (bob, alice)] {
+        assert!(
+            friendship_repo::is_accepted_friend(&conn, from, to)
+                .await
+                .expect("accepted")
+        );
+        let friends =
+            friendship_repo::list_friends(&conn, from, FriendListKind::Accepted, None, None, 10, 0)
+                .await
+                .expect("list");
+        assert_eq!(friends.len(), 1);
+        assert_eq!(friends[0].user_id, to);
+    }
+
+    let changed = friendship_repo::update_pair_status(
+        &conn,
+        bob,
+        alice,
+        FRIENDSHIP_STATUS_BLOCKED,
+        "2026-01-01T00:00:00Z",
+    )
+    .await
+    .expect("block");
+    assert_eq!(changed, 2);
+    assert_eq!(
+        friendship_repo::pair_statuses(&conn, alice, bob)
+            .await
+            .expect("statuses"),
+        vec![FRIENDSHIP_STATUS_BLOCKED, FRIENDSHIP_STATUS_BLOCKED]
+    );
+    assert!(
+        !friendship_repo::is_accepted_friend(&conn, alice, bob)
+            .await
+            .expect("accepted")
+    );
+    assert_eq!(
+        friendship_repo::count_pair_with_status(&conn, bob, alice, FRIENDSHIP_STATUS_BLOCKED)
+            .await
+            .expect("count"),
+        2
+    );
+
+    let deleted =
+        friendship_repo::delete_pair_with_status(&conn, alice, bob, FRIENDSHIP_STATUS_ACTIVE)
+            .await
+            .expect("delete active");
+    assert_eq!(deleted, 0, "status filter protects blocked rows");
+    let deleted =
+        friendship_repo::delete_pair_with_status(&conn, bob, alice, FRIENDSHIP_STATUS_BLOCKED)
+            .await
+            .expect("delete blocked");
+    assert_eq!(deleted, 2);
+    assert!(
+        friendship_repo::pair_statuses(&conn, alice, bob)
+            .await
+            .expect("statuses")
+            .is_empty()
+    );
+}
+
+// ---------------------------------------------------------------------------
+// P2: Retention pruning only removes rows changed at or before the cutoff
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn p2_prune_respects_status_and_cutoff() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = users(&app, "p2").await;
+    let alice = scenario.id("alice_p2");
+    let bob = scenario.id("bob_p2");
+    let conn = app.state.main_db.write().await;
+
+    friendship_repo::insert_pending_pair(&conn, alice, bob, ("p2-ab", "p2-ba"), None)
+        .await
+        .expect("insert pair");
+    friendship_repo::update_pair_status(
+        &conn,
+        alice,
+        bob,
+        FRIENDSHIP_STATUS_UNFRIENDED,
+        "2026-03-01T00:00:00Z",
+    )
+    .await
+    .expect("unfriend");
+
+    for (status, cutoff) in [
+        (FRIENDSHIP_STATUS_BLOCKED, "2026-04-01T00:00:00Z"),
+        (FRIENDSHIP_STATUS_UNFRIENDED, "2026-02-28T23:59:59Z"),
+    ] {
+        let deleted = friendship_repo::delete_with_status_changed_before(&conn, status, cutoff)
+            .await
+            .expect("prune");
+        assert_eq!(deleted, 0, "{status} before {cutoff}");
+    }
+
+    let deleted = friendship_repo::delete_with_status_changed_before(
+        &conn,
+        FRIENDSHIP_STATUS_UNFRIENDED,
+        "2026-03-01T00:00:00Z",
+    )
+    .await
+    .expect("prune");
+    assert_eq!(deleted, 2, "cutoff is inclusive");
+    assert!(
+        !friendship_repo::has_open_relation(&conn, bob, alice)
+            .await
+            .expect("open relation")
+    );
+}
+
+// ---------------------------------------------------------------------------
+// P3: Settling between two users works from either side of the pair
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn p3_settle_all_between_is_symmetric() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = split_scenario(&app, "p3").await;
+    let alice = scenario.id("alice_p3");
+    let bob = scenario.id("bob_p3");
+    let food = scenario.category_id("bob_p3", "Food");
+    let pending_id = &scenario.splits[0].pending_record_ids[0];
+    let conn = app.state.main_db.write().await;
+
+    assert_eq!(
+        split_repo::count_pending(&conn, bob).await.expect("count"),
+        1
+    );
+    let pending = split_repo::list_pending(&conn, bob, 10, 0)
+        .await
+        .expect("list pending");
+    assert_eq!(pending[0].record_id, *pending_id);
+    assert_eq!(pending[0].debtor_user_id.as_deref(), Some(bob));
+    assert_eq!(pending[0].creditor_user_id.as_deref(), Some(alice));
+    assert_eq!(pending[0].creditor_name, "alice_p3");
+
+    assert_eq!(
+        split_repo::count_unsettled_between(&conn, alice, bob)
+            .await
+            .expect("count"),
+        0,
+        "pending participant records are not yet owed"
+    );
+
+    record_repo::finalize_pending(&conn, bob, pending_id, food)
+        .await
+        .expect("finalize");
+    for (a, b) in [(alice, bob), (bob, alice)] {
+        assert_eq!(
+            split_repo::count_unsettled_between(&conn, a, b)
+                .await
+                .expect("count"),
+            1
+        );
+        let listed = split_repo::list_unsettled_between(&conn, a, b, 10, 0)
+            .await
+            .expect("list");
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].record_id, *pending_id);
+        assert_eq!(listed[0].debtor_name, "bob_p3");
+    }
+
+    let settled = split_repo::settle_all_between(&conn, bob, alice)
+        .await
+        .expect("settle");
+    assert_eq!(settled, 1);
+    assert_eq!(
+        split_repo::count_unsettled_between(&conn, alice, bob)
+            .await
+            .expect("count"),
+        0
+    );
+}
+
+// ---------------------------------------------------------------------------
+// P4: Record count and page apply the same filters
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn p4_record_count_and_list_agree() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = split_scenario(&app, "p4").await;
+    let bob = scenario.id("bob_p4");
+    let food = scenario.category_id("bob_p4", "Food");
+    let conn = app.state.main_db.write().await;
+
+    for (id, date) in [("p4-a", "2026-01-05"), ("p4-b", "2026-02-10")] {
+        record_repo::insert_record(
+            &conn,
+            &NewRecord {
+                id,
+                owner_user_id: bob,
+                name: "Lunch",
+                amount: money(-12.0),
+                currency: DEFAULT_CURRENCY_CODE,
+                category_id: food,
+                date,
+                created_via: CREATED_VIA_API,
+                original_amount: None,
+                original_currency: None,
+                note: None,
+                tags: &[],
+            },
+        )
+        .await
+        .expect("insert record");
+    }
+
+    for (pending, settle, expected) in [
+        (None, None, 3),
+        (Some(true), None, 1),
+        (Some(false), None, 2),
+        (None, Some(false), 3),
+        (None, Some(true), 0),
+        (Some(false), Some(false), 2),
+    ] {
+        let filter = all_dates(pending, settle);
+        let count = record_repo::count_records(&conn, bob, &filter)
+            .await
+            .expect("count");
+        let records = record_repo::list_records(&conn, bob, &filter, 50, 0)
+            .await
+            .expect("list");
+        assert_eq!(count, expected, "pending={pending:?} settle={settle:?}");
+        assert_eq!(
+            records.len() as u32,
+            count,
+            "pending={pending:?} settle={settle:?}"
+        );
+    }
+
+    let filter = RecordFilter {
+        start_date: "2026-02-01",
+        end_date: "2026-02-28",
+        pending: Some(false),
+        settle: None,
+        category_id: None,
+        name_contains: None,
+        min_amount: None,
+        max_amount: None,
+        tag: None,
+        sort: RecordSort::default(),
+        after: None,
+    };
+    let records = record_repo::list_records(&conn, bob, &filter, 50, 0)
+        .await
+        .expect("list");
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].id, "p4-b");
+
+    let recent = record_repo::list_recent(&conn, bob, 1)
+        .await
+        .expect("recent");
+    assert_eq!(recent[0].id, "p4-b", "newest by creation sequence");
+    let by_seq = record_repo::find_record_by_seq(&conn, bob, recent[0].seq)
+        .await
+        .expect("by seq");
+    assert_eq!(by_seq.map(|record| record.id).as_deref(), Some("p4-b"));
+}
+
+// ---------------------------------------------------------------------------
+// P5: Record lookups are scoped to their owner
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn p5_record_lookups_are_owner_scoped() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = split_scenario(&app, "p5").await;
+    let alice = scenario.id("alice_p5");
+    let bob = scenario.id("bob_p5");
+    let split = &scenario.splits[0];
+    let conn = app.state.main_db.read().await;
+
+    let split_id = record_repo::find_split_id(&conn, alice, &split.payer_record_id)
+        .await
+        .expect("split id");
+    assert_eq!(split_id, Some(Some(split.split_id.clone())));
+
+    let (record, split_id) =
+        record_repo::find_record_with_split_id(&conn, bob, &split.pending_record_ids[0])
+            .await
+            .expect("record")
+            .expect("bob owns it");
+    assert_eq!(record.amount, money(-30.0));
+    assert_eq!(split_id.as_deref(), Some(split.split_id.as_str()));
+
+    assert_eq!(
+        record_repo::find_split_id(&conn, bob, &split.payer_record_id)
+            .await
+            .expect("split id"),
+        None
+    );
+    assert!(
+        record_repo::find_record(&conn, alice, &split.pending_record_ids[0])
+            .await
+            .expect("record")
+            .is_none()
+    );
+    assert_eq!(
+        record_repo::find_pending_flag(&conn, bob, &split.pending_record_ids[0])
+            .await
+            .expect("pending"),
+        Some(true)
+    );
+    assert!(
+        record_repo::find_settlement_record(&conn, bob, &split.payer_record_id)
+            .await
+            .expect("settlement record")
+            .is_none()
+    );
+}
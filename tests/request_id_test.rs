@@ -0,0 +1,77 @@
+/// Tests Z241-Z242: Request ids
+///
+/// `request_log::layer` gives every response an `x-request-id`: a fresh UUID per
+/// request, or the one the client sent. Error responses carry it too, so a reported
+/// failure can be matched to its log line. No tracing subscriber is installed here.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, Response, StatusCode},
+};
+use kash_server::constants::REQUEST_ID_HEADER;
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(app: &common::TestApp, uri: &str, request_id: Option<&str>) -> Response<Body> {
+    let mut request = Request::builder().method("GET").uri(uri);
+    if let Some(request_id) = request_id {
+        request = request.header(REQUEST_ID_HEADER, request_id);
+    }
+    app.router
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+fn request_id(response: &Response<Body>) -> String {
+    response
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .expect("x-request-id header")
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+// ---------------------------------------------------------------------------
+// Z241: Each response, success or error, carries its own generated id
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z241_responses_carry_a_generated_request_id() {
+    let app = common::setup_test_app().await.expect("setup failed");
+
+    let ok = send(&app, "/", None).await;
+    assert_eq!(ok.status(), StatusCode::OK);
+    let unauthorized = send(&app, "/records", None).await;
+    assert_eq!(unauthorized.status(), StatusCode::UNAUTHORIZED);
+    let missing = send(&app, "/no-such-route", None).await;
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+    let ids = [
+        request_id(&ok),
+        request_id(&unauthorized),
+        request_id(&missing),
+    ];
+    for id in &ids {
+        uuid::Uuid::parse_str(id).expect("uuid request id");
+    }
+    assert_ne!(ids[0], ids[1]);
+    assert_ne!(ids[1], ids[2]);
+}
+
+// ---------------------------------------------------------------------------
+// Z242: A client-sent id is kept and echoed back
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z242_client_request_id_is_echoed() {
+    let app = common::setup_test_app().await.expect("setup failed");
+
+    let response = send(&app, "/records", Some("support-ticket-42")).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(request_id(&response), "support-ticket-42");
+}
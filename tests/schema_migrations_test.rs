@@ -0,0 +1,255 @@
+/// Tests Z291-Z293: Versioned schema migrations
+///
+/// `init_main_db` applies every entry of `database::MIGRATIONS` newer than the
+/// version recorded in `schema_version`, one transaction per migration, and refuses
+/// to open a database a newer build has already migrated past this one.
+use kash_server::constants::DEFAULT_CURRENCY_CODE;
+use kash_server::database::{self, MIGRATIONS};
+use libsql::{Builder, Connection};
+use std::path::Path;
+
+// ---- Helpers ----
+
+async fn open_raw(data_path: &str) -> Connection {
+    let db = Builder::new_local(Path::new(data_path).join("users.db"))
+        .build()
+        .await
+        .unwrap();
+    db.connect().unwrap()
+}
+
+async fn recorded_versions(conn: &Connection) -> Vec<u32> {
+    let mut rows = conn
+        .query("SELECT version FROM schema_version ORDER BY version", ())
+        .await
+        .unwrap();
+    let mut versions = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        versions.push(row.get::<u32>(0).unwrap());
+    }
+    versions
+}
+
+async fn has_table(conn: &Connection, table: &str) -> bool {
+    let mut rows = conn
+        .query(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?",
+            [table],
+        )
+        .await
+        .unwrap();
+    rows.next().await.unwrap().is_some()
+}
+
+async fn records_columns(conn: &Connection) -> Vec<String> {
+    let mut rows = conn.query("PRAGMA table_info(records)", ()).await.unwrap();
+    let mut columns = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        columns.push(row.get::<String>(1).unwrap());
+    }
+    columns
+}
+
+fn all_versions() -> Vec<u32> {
+    MIGRATIONS
+        .iter()
+        .map(|migration| migration.version)
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Z291: A fresh database records every version once; reopening applies nothing
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z291_fresh_database_is_at_latest_version() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let data_path = temp_dir.path().to_string_lossy().to_string();
+
+    let db = database::init_main_db(&data_path).await.expect("init");
+    {
+        let conn = db.read().await;
+        assert_eq!(recorded_versions(&conn).await, all_versions());
+        assert_eq!(
+            database::applied_schema_version(&conn).await.unwrap(),
+            database::latest_schema_version()
+        );
+        assert!(database::apply_migrations(&conn).await.unwrap().is_empty());
+    }
+    drop(db);
+
+    let db = database::init_main_db(&data_path).await.expect("reopen");
+    let conn = db.read().await;
+    assert_eq!(recorded_versions(&conn).await, all_versions());
+}
+
+// ---------------------------------------------------------------------------
+// Z292: A database from before the migrations gains their columns
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z292_legacy_database_is_migrated() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let data_path = temp_dir.path().to_string_lossy().to_string();
+    {
+        let conn = open_raw(&data_path).await;
+        conn.execute(
+            "CREATE TABLE records (
+                 id TEXT PRIMARY KEY, owner_user_id TEXT NOT NULL, name TEXT NOT NULL,
+                 amount REAL NOT NULL, category_id TEXT, date TEXT NOT NULL
+             )",
+            (),
+        )
+        .await
+        .unwrap();
+        conn.execute(
+            "INSERT INTO records (id, owner_user_id, name, amount, category_id, date)
+             VALUES ('rec-1', 'user-1', 'Lunch', -9.5, NULL, '2025-03-02'),
+                    ('rec-2', 'user-2', 'Ramen', -980, NULL, '2025-03-02')",
+            (),
+        )
+        .await
+        .unwrap();
+        conn.execute(
+            "CREATE TABLE user_settings (
+                 user_id TEXT PRIMARY KEY, closed_through TEXT, updated_at TEXT NOT NULL,
+                 currency_code TEXT
+             )",
+            (),
+        )
+        .await
+        .unwrap();
+        conn.execute(
+            "INSERT INTO user_settings (user_id, updated_at, currency_code) VALUES ('user-2', '', 'JPY')",
+            (),
+        )
+        .await
+        .unwrap();
+        conn.execute(
+            "CREATE TABLE categories (
+                 id TEXT PRIMARY KEY, owner_user_id TEXT NOT NULL, name TEXT NOT NULL,
+                 is_income BOOLEAN NOT NULL DEFAULT FALSE, parent_id TEXT, monthly_budget REAL,
+                 UNIQUE(owner_user_id, name)
+             )",
+            (),
+        )
+        .await
+        .unwrap();
+        conn.execute(
+            "INSERT INTO categories (id, owner_user_id, name, monthly_budget)
+             VALUES ('cat-1', 'user-1', 'Dining', 89.9), ('cat-2', 'user-1', 'Misc', NULL)",
+            (),
+        )
+        .await
+        .unwrap();
+        assert_eq!(database::applied_schema_version(&conn).await.unwrap(), 0);
+    }
+
+    let db = database::init_main_db(&data_path).await.expect("init");
+    let conn = db.read().await;
+    assert_eq!(recorded_versions(&conn).await, all_versions());
+    let columns = records_columns(&conn).await;
+    for column in [
+        "deleted_at",
+        "split_id",
+        "debtor_user_id",
+        "creditor_user_id",
+        "currency",
+        "split_full_total",
+    ] {
+        assert!(columns.iter().any(|name| name == column), "{column}");
+    }
+    let mut rows = conn
+        .query(
+            "SELECT name FROM records WHERE id = 'rec-1' AND deleted_at IS NULL AND split_id IS NULL",
+            (),
+        )
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().expect("record kept");
+    assert_eq!(row.get::<String>(0).unwrap(), "Lunch");
+    drop(rows);
+
+    // Records take their owner's currency setting, or the default without one,
+    // ...and amounts become integer cents
+    let mut rows = conn
+        .query("SELECT id, currency, amount FROM records ORDER BY id", ())
+        .await
+        .unwrap();
+    let mut migrated = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        migrated.push((
+            row.get::<String>(0).unwrap(),
+            row.get::<String>(1).unwrap(),
+            row.get::<i64>(2).unwrap(),
+        ));
+    }
+    assert_eq!(
+        migrated,
+        vec![
+            ("rec-1".to_string(), DEFAULT_CURRENCY_CODE.to_string(), -950),
+            ("rec-2".to_string(), "JPY".to_string(), -98000),
+        ]
+    );
+    let ddl: String = conn
+        .query("SELECT sql FROM sqlite_master WHERE name = 'records'", ())
+        .await
+        .unwrap()
+        .next()
+        .await
+        .unwrap()
+        .expect("records table")
+        .get(0)
+        .unwrap();
+    let ddl = ddl.split_whitespace().collect::<Vec<_>>().join(" ");
+    assert!(ddl.contains("amount INTEGER NOT NULL"), "{ddl}");
+
+    // Category budgets become integer cents too
+    let mut rows = conn
+        .query("SELECT monthly_budget FROM categories ORDER BY id", ())
+        .await
+        .unwrap();
+    let mut budgets = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        budgets.push(row.get::<Option<i64>>(0).unwrap());
+    }
+    assert_eq!(budgets, vec![Some(8990), None]);
+}
+
+// ---------------------------------------------------------------------------
+// Z293: A database newer than the build is refused and left as it was
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z293_newer_database_is_refused() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let data_path = temp_dir.path().to_string_lossy().to_string();
+    let future = database::latest_schema_version() + 1;
+    {
+        let conn = open_raw(&data_path).await;
+        conn.execute(
+            "CREATE TABLE schema_version (version INTEGER PRIMARY KEY, name TEXT NOT NULL, applied_at TEXT NOT NULL)",
+            (),
+        )
+        .await
+        .unwrap();
+        conn.execute(
+            "INSERT INTO schema_version (version, name, applied_at) VALUES (?, 'future', '2030-01-01')",
+            [future],
+        )
+        .await
+        .unwrap();
+    }
+
+    let Err(error) = database::init_main_db(&data_path).await else {
+        panic!("a newer schema must not open");
+    };
+    assert!(
+        error.to_string().contains(&format!("version {future}")),
+        "{error}"
+    );
+
+    let conn = open_raw(&data_path).await;
+    assert!(!has_table(&conn, "users").await, "nothing was created");
+    assert!(database::apply_migrations(&conn).await.is_err());
+}
@@ -1,4 +1,4 @@
-/// Tests A1-A4: Single shared DB schema
+/// Tests A1-A5: Single shared DB schema
 ///
 /// These tests verify the *target* schema after migration.
 /// They are expected to FAIL (red) until the migration is implemented.
@@ -19,8 +19,18 @@ async fn a1_single_db_init_creates_all_required_tables() {
         "friendship",
         "idempotency_keys",
         "records",
+        "record_tags",
         "categories",
         "telegram_users",
+        "bot_pending_actions",
+        "sessions",
+        "login_attempts",
+        "split_departures",
+        "two_factor_recovery_codes",
+        "two_factor_challenges",
+        "api_tokens",
+        "notifications",
+        "schema_version",
     ] {
         let mut rows = conn
             .query(
@@ -175,6 +185,47 @@ async fn a4_idempotency_keys_uniqueness_is_per_user_and_endpoint() {
     );
 }
 
+// ---------------------------------------------------------------------------
+// A5: Many users share the one database file; nothing is opened per user
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn a5_users_do_not_add_database_files() {
+    let app = common::setup_test_app().await.expect("setup failed");
+
+    for i in 0..20 {
+        let username = format!("user{i}_a5");
+        common::create_test_user(&app.state, &username, "pw")
+            .await
+            .expect("create user");
+        let cookie = common::login_user(&app.router, &username, "pw")
+            .await
+            .expect("login");
+        assert_eq!(
+            create_category_status(&app, &cookie, "Dining").await,
+            StatusCode::CREATED
+        );
+    }
+
+    let mut files: Vec<String> = std::fs::read_dir(&app.data_path)
+        .expect("read data dir")
+        .map(|entry| {
+            entry
+                .expect("dir entry")
+                .file_name()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    files.sort();
+    assert!(
+        files
+            .iter()
+            .all(|name| ["users.db", "users.db-wal", "users.db-shm"].contains(&name.as_str())),
+        "{files:?}"
+    );
+}
+
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
@@ -0,0 +1,155 @@
+/// Tests Z221-Z224: Database-backed session store
+///
+/// `session_store::LibsqlSessionStore` keeps sessions in the `sessions` table, so a
+/// new store over the same database (a restarted server) still knows them. Expired
+/// rows are neither loaded nor kept by `delete_expired_sessions`, and concurrent
+/// saves of one id always leave one whole record.
+mod common;
+
+use std::collections::HashMap;
+
+use kash_server::session_store::{LibsqlSessionStore, delete_expired_sessions};
+use time::{Duration, OffsetDateTime};
+use tower_sessions::{
+    SessionStore,
+    session::{Id, Record},
+};
+
+// ---- Helpers ----
+
+fn record(id: Id, key: &str, value: i64, expiry_date: OffsetDateTime) -> Record {
+    Record {
+        id,
+        data: HashMap::from([(key.to_string(), serde_json::json!(value))]),
+        expiry_date: expiry_date.replace_nanosecond(0).unwrap(),
+    }
+}
+
+async fn session_count(app: &common::TestApp) -> i64 {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query("SELECT COUNT(*) FROM sessions", ())
+        .await
+        .unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+// ---------------------------------------------------------------------------
+// Z221: A login is stored in the database and logout clears it
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z221_login_session_is_stored_in_database() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    common::create_test_user(&app.state, "alice", "password123")
+        .await
+        .unwrap();
+    let cookie = common::login_user(&app.router, "alice", "password123")
+        .await
+        .unwrap();
+    assert_eq!(session_count(&app).await, 1);
+
+    let (status, _) = common::auth_request(&app.router, "GET", "/auth/me", &cookie)
+        .await
+        .unwrap();
+    assert_eq!(status, axum::http::StatusCode::OK);
+
+    let (status, _) = common::auth_request(&app.router, "POST", "/auth/logout", &cookie)
+        .await
+        .unwrap();
+    assert_eq!(status, axum::http::StatusCode::NO_CONTENT);
+    let (status, _) = common::auth_request(&app.router, "GET", "/auth/me", &cookie)
+        .await
+        .unwrap();
+    assert_eq!(status, axum::http::StatusCode::UNAUTHORIZED);
+}
+
+// ---------------------------------------------------------------------------
+// Z222: A new store over the same database loads saved sessions; ids never collide
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z222_sessions_survive_a_new_store() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let expiry = OffsetDateTime::now_utc() + Duration::days(1);
+
+    let mut first = record(Id::default(), "visits", 1, expiry);
+    LibsqlSessionStore::new(app.state.main_db.clone())
+        .create(&mut first)
+        .await
+        .unwrap();
+
+    let restarted = LibsqlSessionStore::new(app.state.main_db.clone());
+    assert_eq!(
+        restarted.load(&first.id).await.unwrap(),
+        Some(first.clone())
+    );
+
+    // Creating with an id already in use picks a fresh one instead of overwriting
+    let mut second = record(first.id, "visits", 2, expiry);
+    restarted.create(&mut second).await.unwrap();
+    assert_ne!(second.id, first.id);
+    assert_eq!(restarted.load(&first.id).await.unwrap(), Some(first));
+    assert_eq!(restarted.load(&second.id).await.unwrap(), Some(second));
+}
+
+// ---------------------------------------------------------------------------
+// Z223: Expired sessions are not loaded and are deleted by the cleanup
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z223_expired_sessions_are_cleaned_up() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let store = LibsqlSessionStore::new(app.state.main_db.clone());
+    let now = OffsetDateTime::now_utc();
+
+    let expired = record(Id::default(), "visits", 1, now - Duration::minutes(1));
+    let active = record(Id::default(), "visits", 1, now + Duration::days(1));
+    store.save(&expired).await.unwrap();
+    store.save(&active).await.unwrap();
+
+    assert_eq!(store.load(&expired.id).await.unwrap(), None);
+    assert_eq!(
+        delete_expired_sessions(&app.state.main_db, now)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(session_count(&app).await, 1);
+    assert_eq!(store.load(&active.id).await.unwrap(), Some(active));
+}
+
+// ---------------------------------------------------------------------------
+// Z224: Concurrent saves of one session leave exactly one of the written records
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z224_concurrent_saves_keep_a_whole_record() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let store = LibsqlSessionStore::new(app.state.main_db.clone());
+    let id = Id::default();
+    let expiry = OffsetDateTime::now_utc() + Duration::days(1);
+
+    let writes: Vec<Record> = (0..20)
+        .map(|n| record(id, &format!("key{n}"), n, expiry + Duration::seconds(n)))
+        .collect();
+    let tasks: Vec<_> = writes
+        .iter()
+        .cloned()
+        .map(|write| {
+            let store = store.clone();
+            tokio::spawn(async move {
+                store.save(&write).await.unwrap();
+                store.load(&write.id).await.unwrap().expect("saved session")
+            })
+        })
+        .collect();
+    for task in tasks {
+        let loaded = task.await.unwrap();
+        assert!(writes.contains(&loaded), "{loaded:?}");
+    }
+
+    let last = store.load(&id).await.unwrap().expect("saved session");
+    assert!(writes.contains(&last), "{last:?}");
+    assert_eq!(session_count(&app).await, 1);
+}
@@ -0,0 +1,157 @@
+/// Tests Z171-Z173: Who may settle a record
+///
+/// `PUT /records/{id}/settle` settles the caller's own record, or a participant's share
+/// of a split the caller paid for. Other members of the split get 403; users outside it
+/// get 404, as do unknown and trashed records.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder};
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> (StatusCode, Value) {
+    let body = payload.map_or_else(Body::empty, |payload| Body::from(payload.to_string()));
+    let request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    (status, body)
+}
+
+async fn settle(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    record_id: &str,
+) -> (StatusCode, Value) {
+    send(
+        app,
+        "PUT",
+        &format!("/records/{record_id}/settle"),
+        scenario.cookie(user),
+        Some(json!({ "split_id": scenario.splits[0].split_id })),
+    )
+    .await
+}
+
+async fn is_settled(app: &common::TestApp, record_id: &str) -> bool {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query("SELECT settle FROM records WHERE id = ?", [record_id])
+        .await
+        .unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+/// Alice pays 90 with Bob and Carol; Dave is Alice's friend but not in the split.
+async fn three_way_split(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    let carol = format!("carol_{suffix}");
+    let dave = format!("dave_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob, &carol, &dave])
+        .category(&alice, "Dining")
+        .friend(&alice, &bob)
+        .friend(&alice, &carol)
+        .friend(&alice, &dave)
+        .split(&alice, "Dining", 90.0, &[(&bob, 30.0), (&carol, 30.0)])
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z171: The split's creditor settles a participant's share
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z171_creditor_settles_participant_share() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z171").await;
+    let bob_share = &scenario.splits[0].pending_record_ids[0];
+
+    let (status, body) = settle(&app, &scenario, "alice_z171", bob_share).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["id"], bob_share.as_str(), "{body}");
+    assert!(is_settled(&app, bob_share).await);
+
+    let (status, body) = settle(&app, &scenario, "alice_z171", bob_share).await;
+    assert_eq!(status, StatusCode::OK, "settling twice is a no-op: {body}");
+    let (status, body) = settle(&app, &scenario, "bob_z171", bob_share).await;
+    assert_eq!(status, StatusCode::OK, "the owner still may: {body}");
+}
+
+// ---------------------------------------------------------------------------
+// Z172: Other split members get 403, outsiders 404; nothing is settled
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z172_third_users_cannot_settle() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z172").await;
+    let split = &scenario.splits[0];
+    let bob_share = &split.pending_record_ids[0];
+
+    let (status, body) = settle(&app, &scenario, "carol_z172", bob_share).await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+    let (status, body) = settle(&app, &scenario, "bob_z172", &split.payer_record_id).await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "{body}");
+    for record_id in [bob_share, &split.payer_record_id] {
+        let (status, body) = settle(&app, &scenario, "dave_z172", record_id).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+    }
+
+    assert!(!is_settled(&app, bob_share).await);
+    assert!(!is_settled(&app, &split.payer_record_id).await);
+}
+
+// ---------------------------------------------------------------------------
+// Z173: Unknown and trashed records are 404 for everyone
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z173_missing_records_are_not_found() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z173").await;
+    let carol_share = &scenario.splits[0].pending_record_ids[1];
+
+    let (status, _) = settle(&app, &scenario, "alice_z173", "no-such-record").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = send(
+        &app,
+        "DELETE",
+        &format!("/records/{carol_share}"),
+        scenario.cookie("carol_z173"),
+        None,
+    )
+    .await;
+    assert!(status.is_success(), "{status} {body}");
+    for user in ["alice_z173", "carol_z173"] {
+        let (status, body) = settle(&app, &scenario, user, carol_share).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{user}: {body}");
+    }
+    assert!(!is_settled(&app, carol_share).await);
+}
@@ -0,0 +1,470 @@
+/// Tests U1-U5: Settle-up netting between split partners
+///
+/// `GET /friends/{id}/settle-up` nets every finalized, unsettled split record
+/// between the caller and a friend into one residual payment per currency. `POST`
+/// settles the previewed records on both sides and books each residual as a
+/// `settle_up` record in each user's books, in its currency, in one transaction.
+/// A plan that went stale since the preview is rejected with 409 and changes nothing.
+mod common;
+
+use axum::{http::StatusCode, response::Response};
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use common::send_request;
+use kash_server::constants::{CREATED_VIA_SETTLE_UP, DEFAULT_CURRENCY_CODE, SETTLE_UP_RECORD_NAME};
+use kash_server::models::{
+    CreateSplitPayload, CurrencySettleUp, SettleUpPayment, SettleUpPlan, SettleUpResponse,
+    SplitMode, SplitParticipant,
+};
+use kash_server::money::Money;
+use kash_server::splits;
+use serde_json::{Value, json};
+
+// ---- Helpers ----
+
+async fn body_json(response: Response) -> Value {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).unwrap_or(Value::Null)
+}
+
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob])
+        .category(&alice, "Shared")
+        .category(&bob, "Shared")
+        .friend(&alice, &bob)
+        .build(app)
+        .await
+}
+
+/// `payer` pays `total` and `debtor` owes `share` of it; the debtor confirms right away.
+async fn finalized_split(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    payer: &str,
+    debtor: &str,
+    key: &str,
+    total: f64,
+    share: f64,
+) -> String {
+    finalized_split_in(app, scenario, None, (payer, debtor), key, total, share).await
+}
+
+/// `finalized_split`, booked in `currency` when given.
+async fn finalized_split_in(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    currency: Option<&str>,
+    (payer, debtor): (&str, &str),
+    key: &str,
+    total: f64,
+    share: f64,
+) -> String {
+    let (status, created) = splits::create_split_for_user(
+        &app.state,
+        scenario.id(payer),
+        CreateSplitPayload {
+            idempotency_key: key.to_string(),
+            total_amount: money(total),
+            description: key.to_string(),
+            date: "2025-03-10".to_string(),
+            category_id: scenario.category_id(payer, "Shared").to_string(),
+            currency: currency.map(str::to_string),
+            split_mode: SplitMode::Exact,
+            splits: vec![SplitParticipant {
+                user_id: scenario.id(debtor).to_string(),
+                amount: money(share),
+                percentage: None,
+            }],
+            legacy_full_total: false,
+        },
+    )
+    .await
+    .expect("create split");
+    assert_eq!(status, StatusCode::CREATED);
+
+    let record_id = created.pending_record_ids[0].clone();
+    let response = send_request(
+        app,
+        "POST",
+        "/records/finalize-pending",
+        scenario.cookie(debtor),
+        Some(json!({
+            "record_id": record_id,
+            "category_id": scenario.category_id(debtor, "Shared"),
+        })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    record_id
+}
+
+async fn preview(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    friend: &str,
+) -> SettleUpPlan {
+    let response = send_request(
+        app,
+        "GET",
+        &format!("/friends/{}/settle-up", scenario.id(friend)),
+        scenario.cookie(user),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    serde_json::from_value(body_json(response).await).unwrap()
+}
+
+async fn execute(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    friend: &str,
+    plan: &SettleUpPlan,
+) -> Response {
+    send_request(
+        app,
+        "POST",
+        &format!("/friends/{}/settle-up", scenario.id(friend)),
+        scenario.cookie(user),
+        Some(json!({
+            "my_record_ids": plan.my_record_ids,
+            "friend_record_ids": plan.friend_record_ids,
+        })),
+    )
+    .await
+}
+
+/// `(settled, name, amount, created_via)` of a record.
+async fn record_state(app: &common::TestApp, record_id: &str) -> (bool, String, Money, String) {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT settle, name, amount, created_via FROM records WHERE id = ?",
+            [record_id],
+        )
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().expect("record exists");
+    (
+        row.get(0).unwrap(),
+        row.get(1).unwrap(),
+        Money::from_cents(row.get(2).unwrap()),
+        row.get(3).unwrap(),
+    )
+}
+
+// ---------------------------------------------------------------------------
+// U1: Offsetting debts net to one residual payment
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn u1_offsetting_debts_net_to_residual() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "u1").await;
+    let alice = scenario.id("alice_u1");
+    let bob = scenario.id("bob_u1");
+
+    // Alice owes 280 for utilities; Bob owes 300 for groceries.
+    let alice_owes = finalized_split(
+        &app,
+        &scenario,
+        "bob_u1",
+        "alice_u1",
+        "utilities",
+        560.0,
+        280.0,
+    )
+    .await;
+    let bob_owes = finalized_split(
+        &app,
+        &scenario,
+        "alice_u1",
+        "bob_u1",
+        "groceries",
+        600.0,
+        300.0,
+    )
+    .await;
+
+    let plan = preview(&app, &scenario, "alice_u1", "bob_u1").await;
+    assert_eq!(plan.my_record_ids, vec![alice_owes.clone()]);
+    assert_eq!(plan.friend_record_ids, vec![bob_owes.clone()]);
+    assert_eq!(
+        plan.currencies,
+        vec![CurrencySettleUp {
+            currency: DEFAULT_CURRENCY_CODE.to_string(),
+            you_owe: money(280.0),
+            they_owe: money(300.0),
+            residual: Some(SettleUpPayment {
+                from_user_id: bob.to_string(),
+                to_user_id: alice.to_string(),
+                amount: money(20.0),
+            }),
+        }]
+    );
+
+    // Bob sees the mirror image.
+    let mirrored = preview(&app, &scenario, "bob_u1", "alice_u1").await;
+    assert_eq!(mirrored.my_record_ids, plan.friend_record_ids);
+    assert_eq!(mirrored.currencies[0].residual, plan.currencies[0].residual);
+
+    let response = execute(&app, &scenario, "alice_u1", "bob_u1", &plan).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let result: SettleUpResponse = serde_json::from_value(body_json(response).await).unwrap();
+    assert_eq!(result.settled_count, 2);
+    assert_eq!(result.plan, plan);
+    assert_eq!(result.residual_record_ids.len(), 2);
+
+    assert!(record_state(&app, &alice_owes).await.0);
+    assert!(record_state(&app, &bob_owes).await.0);
+
+    let (_, name, amount, created_via) = record_state(&app, &result.residual_record_ids[0]).await;
+    assert_eq!(name, format!("{SETTLE_UP_RECORD_NAME} bob_u1"));
+    assert_eq!(amount, money(20.0), "Alice receives the residual");
+    assert_eq!(created_via, CREATED_VIA_SETTLE_UP);
+    let (_, name, amount, _) = record_state(&app, &result.residual_record_ids[1]).await;
+    assert_eq!(name, format!("{SETTLE_UP_RECORD_NAME} alice_u1"));
+    assert_eq!(amount, money(-20.0), "Bob pays the residual");
+
+    let after = preview(&app, &scenario, "alice_u1", "bob_u1").await;
+    assert!(after.my_record_ids.is_empty() && after.friend_record_ids.is_empty());
+    assert!(after.currencies.is_empty());
+}
+
+// ---------------------------------------------------------------------------
+// U2: One-sided and fully offsetting debts
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn u2_one_sided_and_exact_offsets() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "u2").await;
+    let alice = scenario.id("alice_u2");
+    let bob = scenario.id("bob_u2");
+
+    let first = finalized_split(&app, &scenario, "bob_u2", "alice_u2", "taxi", 100.0, 50.0).await;
+    let second = finalized_split(&app, &scenario, "bob_u2", "alice_u2", "movie", 24.5, 12.25).await;
+
+    let plan = preview(&app, &scenario, "alice_u2", "bob_u2").await;
+    assert_eq!(plan.my_record_ids, vec![first, second]);
+    assert!(plan.friend_record_ids.is_empty());
+    assert_eq!(
+        plan.currencies[0].residual,
+        Some(SettleUpPayment {
+            from_user_id: alice.to_string(),
+            to_user_id: bob.to_string(),
+            amount: money(62.25),
+        })
+    );
+
+    // Bob can execute it from his side too.
+    let mirrored = preview(&app, &scenario, "bob_u2", "alice_u2").await;
+    let response = execute(&app, &scenario, "bob_u2", "alice_u2", &mirrored).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let result: SettleUpResponse = serde_json::from_value(body_json(response).await).unwrap();
+    assert_eq!(result.settled_count, 2);
+    assert_eq!(
+        record_state(&app, &result.residual_record_ids[0]).await.2,
+        money(62.25)
+    );
+    assert_eq!(
+        record_state(&app, &result.residual_record_ids[1]).await.2,
+        money(-62.25)
+    );
+
+    // Debts that cancel exactly settle without a payment.
+    finalized_split(&app, &scenario, "bob_u2", "alice_u2", "lunch", 200.0, 100.0).await;
+    finalized_split(
+        &app, &scenario, "alice_u2", "bob_u2", "dinner", 200.0, 100.0,
+    )
+    .await;
+    let plan = preview(&app, &scenario, "alice_u2", "bob_u2").await;
+    assert_eq!(plan.currencies[0].residual, None);
+    let response = execute(&app, &scenario, "alice_u2", "bob_u2", &plan).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let result: SettleUpResponse = serde_json::from_value(body_json(response).await).unwrap();
+    assert_eq!(result.settled_count, 2);
+    assert!(result.residual_record_ids.is_empty());
+}
+
+// ---------------------------------------------------------------------------
+// U3: A stale plan is rejected and nothing changes
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn u3_stale_plan_is_rejected() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "u3").await;
+    let alice = scenario.id("alice_u3");
+
+    let owed = finalized_split(&app, &scenario, "bob_u3", "alice_u3", "rent", 1000.0, 500.0).await;
+    let plan = preview(&app, &scenario, "alice_u3", "bob_u3").await;
+
+    // A new split lands between preview and execute.
+    finalized_split(&app, &scenario, "alice_u3", "bob_u3", "power", 300.0, 150.0).await;
+
+    let response = execute(&app, &scenario, "alice_u3", "bob_u3", &plan).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert!(!record_state(&app, &owed).await.0, "rolled back");
+    {
+        let conn = app.state.main_db.read().await;
+        let mut rows = conn
+            .query(
+                "SELECT COUNT(*) FROM records WHERE owner_user_id = ? AND created_via = ?",
+                (alice, CREATED_VIA_SETTLE_UP),
+            )
+            .await
+            .unwrap();
+        let count: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+        assert_eq!(count, 0);
+    }
+
+    // Replaying an executed plan is stale too, so it cannot book the payment twice.
+    let fresh = preview(&app, &scenario, "alice_u3", "bob_u3").await;
+    let response = execute(&app, &scenario, "alice_u3", "bob_u3", &fresh).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = execute(&app, &scenario, "alice_u3", "bob_u3", &fresh).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+// ---------------------------------------------------------------------------
+// U4: Only accepted friends can settle up
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn u4_requires_accepted_friend() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_u4", "carol_u4"])
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_u4");
+
+    let response = send_request(
+        &app,
+        "GET",
+        &format!("/friends/{}/settle-up", scenario.id("carol_u4")),
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = send_request(
+        &app,
+        "GET",
+        &format!("/friends/{}/settle-up", scenario.id("alice_u4")),
+        cookie,
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+// ---------------------------------------------------------------------------
+// U5: Debts in different currencies settle separately
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn u5_currencies_settle_separately() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "u5").await;
+    let alice = scenario.id("alice_u5");
+    let bob = scenario.id("bob_u5");
+
+    // Alice owes 280 in the default currency; Bob owes 3000 yen.
+    finalized_split(
+        &app,
+        &scenario,
+        "bob_u5",
+        "alice_u5",
+        "utilities",
+        560.0,
+        280.0,
+    )
+    .await;
+    finalized_split_in(
+        &app,
+        &scenario,
+        Some("JPY"),
+        ("alice_u5", "bob_u5"),
+        "izakaya",
+        6000.0,
+        3000.0,
+    )
+    .await;
+
+    let plan = preview(&app, &scenario, "alice_u5", "bob_u5").await;
+    assert_eq!(
+        plan.currencies,
+        vec![
+            CurrencySettleUp {
+                currency: "JPY".to_string(),
+                you_owe: Money::ZERO,
+                they_owe: money(3000.0),
+                residual: Some(SettleUpPayment {
+                    from_user_id: bob.to_string(),
+                    to_user_id: alice.to_string(),
+                    amount: money(3000.0),
+                }),
+            },
+            CurrencySettleUp {
+                currency: DEFAULT_CURRENCY_CODE.to_string(),
+                you_owe: money(280.0),
+                they_owe: Money::ZERO,
+                residual: Some(SettleUpPayment {
+                    from_user_id: alice.to_string(),
+                    to_user_id: bob.to_string(),
+                    amount: money(280.0),
+                }),
+            },
+        ],
+        "the debts are not netted against each other"
+    );
+
+    let response = execute(&app, &scenario, "alice_u5", "bob_u5", &plan).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let result: SettleUpResponse = serde_json::from_value(body_json(response).await).unwrap();
+    assert_eq!(result.settled_count, 2);
+
+    let mut booked = Vec::new();
+    for record_id in &result.residual_record_ids {
+        let conn = app.state.main_db.read().await;
+        let mut rows = conn
+            .query(
+                "SELECT owner_user_id, amount, currency FROM records WHERE id = ?",
+                [record_id.as_str()],
+            )
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().expect("residual record");
+        booked.push((
+            row.get::<String>(0).unwrap(),
+            Money::from_cents(row.get(1).unwrap()),
+            row.get::<String>(2).unwrap(),
+        ));
+    }
+    assert_eq!(
+        booked,
+        vec![
+            (alice.to_string(), money(3000.0), "JPY".to_string()),
+            (bob.to_string(), money(-3000.0), "JPY".to_string()),
+            (
+                alice.to_string(),
+                money(-280.0),
+                DEFAULT_CURRENCY_CODE.to_string()
+            ),
+            (
+                bob.to_string(),
+                money(280.0),
+                DEFAULT_CURRENCY_CODE.to_string()
+            ),
+        ]
+    );
+}
@@ -11,6 +11,7 @@ use axum::{
     body::Body,
     http::{Request, StatusCode},
 };
+use common::fixtures::ScenarioBuilder;
 use serde_json::{Value, json};
 use tower::util::ServiceExt;
 
@@ -47,73 +48,6 @@ async fn json_request(
     (status, body)
 }
 
-async fn create_category(app: &common::TestApp, cookie: &str, name: &str) -> String {
-    let (status, body) = json_request(
-        app,
-        "POST",
-        "/categories",
-        cookie,
-        json!({ "name": name, "is_income": false }),
-    )
-    .await;
-    assert_eq!(status, StatusCode::CREATED, "create category");
-    body["id"].as_str().expect("category id").to_string()
-}
-
-async fn send_friend_request(app: &common::TestApp, cookie: &str, friend_username: &str) {
-    let (status, _) = json_request(
-        app,
-        "POST",
-        "/friends/request",
-        cookie,
-        json!({ "friend_username": friend_username }),
-    )
-    .await;
-    assert_eq!(
-        status,
-        StatusCode::CREATED,
-        "send friend request to {friend_username}"
-    );
-}
-
-async fn accept_friend(app: &common::TestApp, cookie: &str, friend_id: &str) {
-    let (status, _) = json_request(
-        app,
-        "POST",
-        "/friends/accept",
-        cookie,
-        json!({ "friend_id": friend_id }),
-    )
-    .await;
-    assert_eq!(status, StatusCode::OK, "accept friend");
-}
-
-#[allow(dead_code)]
-async fn create_split(
-    app: &common::TestApp,
-    alice_cookie: &str,
-    category_id: &str,
-    idempotency_key: &str,
-    bob_id: &str,
-    amount: f64,
-) -> (StatusCode, Value) {
-    json_request(
-        app,
-        "POST",
-        "/splits/create",
-        alice_cookie,
-        json!({
-            "idempotency_key": idempotency_key,
-            "total_amount": 90.0,
-            "description": "split test",
-            "date": "2026-02-20",
-            "category_id": category_id,
-            "splits": [{ "user_id": bob_id, "amount": amount }]
-        }),
-    )
-    .await
-}
-
 /// Count records in the shared DB for a given owner_user_id.
 /// After migration this queries the single main_db; before migration
 /// (per-user DBs) this will always return 0 for the shared DB, which
@@ -153,38 +87,24 @@ async fn record_ids_for_user(app: &common::TestApp, user_id: &str) -> Vec<String
 #[tokio::test]
 async fn d15_create_split_writes_all_records_atomically() {
     let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_d15", "bob_d15", "charlie_d15"])
+        .friend("alice_d15", "bob_d15")
+        .friend("alice_d15", "charlie_d15")
+        .category("alice_d15", "Dining")
+        .build(&app)
+        .await;
+    let alice_id = scenario.id("alice_d15");
+    let bob_id = scenario.id("bob_d15");
+    let charlie_id = scenario.id("charlie_d15");
+    let alice_cookie = scenario.cookie("alice_d15");
+    let cat = scenario.category_id("alice_d15", "Dining");
 
-    let alice_id = common::create_test_user(&app.state, "alice_d15", "pw")
-        .await
-        .expect("create alice");
-    let bob_id = common::create_test_user(&app.state, "bob_d15", "pw")
-        .await
-        .expect("create bob");
-    let charlie_id = common::create_test_user(&app.state, "charlie_d15", "pw")
-        .await
-        .expect("create charlie");
-
-    let alice_cookie = common::login_user(&app.router, "alice_d15", "pw")
-        .await
-        .expect("login alice");
-    let bob_cookie = common::login_user(&app.router, "bob_d15", "pw")
-        .await
-        .expect("login bob");
-    let charlie_cookie = common::login_user(&app.router, "charlie_d15", "pw")
-        .await
-        .expect("login charlie");
-
-    send_friend_request(&app, &alice_cookie, "bob_d15").await;
-    send_friend_request(&app, &alice_cookie, "charlie_d15").await;
-    accept_friend(&app, &bob_cookie, &alice_id).await;
-    accept_friend(&app, &charlie_cookie, &alice_id).await;
-
-    let cat = create_category(&app, &alice_cookie, "Dining").await;
     let (status, body) = json_request(
         &app,
         "POST",
         "/splits/create",
-        &alice_cookie,
+        alice_cookie,
         json!({
             "idempotency_key": "d15-split-1",
             "total_amount": 90.0,
@@ -210,16 +130,16 @@ async fn d15_create_split_writes_all_records_atomically() {
     assert_eq!(pending_ids.len(), 2);
 
     // All records live in the SINGLE shared DB, scoped by owner_user_id
-    let alice_count = count_records_for_user(&app, &alice_id).await;
-    let bob_count = count_records_for_user(&app, &bob_id).await;
-    let charlie_count = count_records_for_user(&app, &charlie_id).await;
+    let alice_count = count_records_for_user(&app, alice_id).await;
+    let bob_count = count_records_for_user(&app, bob_id).await;
+    let charlie_count = count_records_for_user(&app, charlie_id).await;
 
     assert_eq!(alice_count, 1, "alice must have 1 record (payer)");
     assert_eq!(bob_count, 1, "bob must have 1 record (pending)");
     assert_eq!(charlie_count, 1, "charlie must have 1 record (pending)");
 
     // Alice's payer record must be in the shared DB
-    let alice_ids = record_ids_for_user(&app, &alice_id).await;
+    let alice_ids = record_ids_for_user(&app, alice_id).await;
     assert!(
         alice_ids.contains(&payer_record_id),
         "payer record must be owned by alice in shared DB"
@@ -233,25 +153,16 @@ async fn d15_create_split_writes_all_records_atomically() {
 #[tokio::test]
 async fn d16_idempotency_same_key_same_payload_no_duplicate_writes() {
     let app = common::setup_test_app().await.expect("setup failed");
-
-    let alice_id = common::create_test_user(&app.state, "alice_d16", "pw")
-        .await
-        .expect("create alice");
-    let bob_id = common::create_test_user(&app.state, "bob_d16", "pw")
-        .await
-        .expect("create bob");
-
-    let alice_cookie = common::login_user(&app.router, "alice_d16", "pw")
-        .await
-        .expect("login alice");
-    let bob_cookie = common::login_user(&app.router, "bob_d16", "pw")
-        .await
-        .expect("login bob");
-
-    send_friend_request(&app, &alice_cookie, "bob_d16").await;
-    accept_friend(&app, &bob_cookie, &alice_id).await;
-
-    let cat = create_category(&app, &alice_cookie, "Dining").await;
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_d16", "bob_d16"])
+        .friend("alice_d16", "bob_d16")
+        .category("alice_d16", "Dining")
+        .build(&app)
+        .await;
+    let alice_id = scenario.id("alice_d16");
+    let bob_id = scenario.id("bob_d16");
+    let alice_cookie = scenario.cookie("alice_d16");
+    let cat = scenario.category_id("alice_d16", "Dining");
 
     let payload = json!({
         "idempotency_key": "d16-split-1",
@@ -267,14 +178,14 @@ async fn d16_idempotency_same_key_same_payload_no_duplicate_writes() {
         &app,
         "POST",
         "/splits/create",
-        &alice_cookie,
+        alice_cookie,
         payload.clone(),
     )
     .await;
     assert_eq!(s1, StatusCode::CREATED, "first request");
 
     // Second request — identical key + payload
-    let (s2, b2) = json_request(&app, "POST", "/splits/create", &alice_cookie, payload).await;
+    let (s2, b2) = json_request(&app, "POST", "/splits/create", alice_cookie, payload).await;
     assert_eq!(
         s2,
         StatusCode::CREATED,
@@ -283,8 +194,8 @@ async fn d16_idempotency_same_key_same_payload_no_duplicate_writes() {
     assert_eq!(b1, b2, "idempotent replay must return identical body");
 
     // Only ONE payer record and ONE pending record must exist in the shared DB
-    let alice_count = count_records_for_user(&app, &alice_id).await;
-    let bob_count = count_records_for_user(&app, &bob_id).await;
+    let alice_count = count_records_for_user(&app, alice_id).await;
+    let bob_count = count_records_for_user(&app, bob_id).await;
     assert_eq!(alice_count, 1, "no duplicate payer records");
     assert_eq!(bob_count, 1, "no duplicate pending records for bob");
 }
@@ -296,25 +207,15 @@ async fn d16_idempotency_same_key_same_payload_no_duplicate_writes() {
 #[tokio::test]
 async fn d17_idempotency_same_key_different_payload_conflicts() {
     let app = common::setup_test_app().await.expect("setup failed");
-
-    let alice_id = common::create_test_user(&app.state, "alice_d17", "pw")
-        .await
-        .expect("create alice");
-    let bob_id = common::create_test_user(&app.state, "bob_d17", "pw")
-        .await
-        .expect("create bob");
-
-    let alice_cookie = common::login_user(&app.router, "alice_d17", "pw")
-        .await
-        .expect("login alice");
-    let bob_cookie = common::login_user(&app.router, "bob_d17", "pw")
-        .await
-        .expect("login bob");
-
-    send_friend_request(&app, &alice_cookie, "bob_d17").await;
-    accept_friend(&app, &bob_cookie, &alice_id).await;
-
-    let cat = create_category(&app, &alice_cookie, "Dining").await;
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_d17", "bob_d17"])
+        .friend("alice_d17", "bob_d17")
+        .category("alice_d17", "Dining")
+        .build(&app)
+        .await;
+    let bob_id = scenario.id("bob_d17");
+    let alice_cookie = scenario.cookie("alice_d17");
+    let cat = scenario.category_id("alice_d17", "Dining");
 
     let first_payload = json!({
         "idempotency_key": "d17-split-1",
@@ -324,7 +225,7 @@ async fn d17_idempotency_same_key_different_payload_conflicts() {
         "category_id": cat,
         "splits": [{ "user_id": bob_id, "amount": 30.0 }]
     });
-    let (s1, _) = json_request(&app, "POST", "/splits/create", &alice_cookie, first_payload).await;
+    let (s1, _) = json_request(&app, "POST", "/splits/create", alice_cookie, first_payload).await;
     assert_eq!(s1, StatusCode::CREATED);
 
     // Same key, different payload
@@ -336,14 +237,7 @@ async fn d17_idempotency_same_key_different_payload_conflicts() {
         "category_id": cat,
         "splits": [{ "user_id": bob_id, "amount": 40.0 }]
     });
-    let (s2, _) = json_request(
-        &app,
-        "POST",
-        "/splits/create",
-        &alice_cookie,
-        second_payload,
-    )
-    .await;
+    let (s2, _) = json_request(&app, "POST", "/splits/create", alice_cookie, second_payload).await;
     assert_eq!(s2, StatusCode::CONFLICT, "different payload must conflict");
 }
 
@@ -354,27 +248,23 @@ async fn d17_idempotency_same_key_different_payload_conflicts() {
 #[tokio::test]
 async fn d18_create_split_rolls_back_on_failure() {
     let app = common::setup_test_app().await.expect("setup failed");
-
-    let alice_id = common::create_test_user(&app.state, "alice_d18", "pw")
-        .await
-        .expect("create alice");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_d18", "bob_d18"])
+        .category("alice_d18", "Dining")
+        .build(&app)
+        .await;
+    let alice_id = scenario.id("alice_d18");
     // bob is intentionally NOT a friend of alice → split must fail after alice
     // is validated but bob validation fails → should produce zero records
-    let bob_id = common::create_test_user(&app.state, "bob_d18", "pw")
-        .await
-        .expect("create bob");
-
-    let alice_cookie = common::login_user(&app.router, "alice_d18", "pw")
-        .await
-        .expect("login alice");
-
-    let cat = create_category(&app, &alice_cookie, "Dining").await;
+    let bob_id = scenario.id("bob_d18");
+    let alice_cookie = scenario.cookie("alice_d18");
+    let cat = scenario.category_id("alice_d18", "Dining");
 
     let (status, _) = json_request(
         &app,
         "POST",
         "/splits/create",
-        &alice_cookie,
+        alice_cookie,
         json!({
             "idempotency_key": "d18-split-1",
             "total_amount": 60.0,
@@ -392,8 +282,8 @@ async fn d18_create_split_rolls_back_on_failure() {
     );
 
     // Zero records must exist in the shared DB for both users
-    let alice_count = count_records_for_user(&app, &alice_id).await;
-    let bob_count = count_records_for_user(&app, &bob_id).await;
+    let alice_count = count_records_for_user(&app, alice_id).await;
+    let bob_count = count_records_for_user(&app, bob_id).await;
     assert_eq!(
         alice_count, 0,
         "alice must have no records after failed split"
@@ -408,25 +298,17 @@ async fn d18_create_split_rolls_back_on_failure() {
 #[tokio::test]
 async fn e19_no_duplicate_payer_record_on_retry() {
     let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_e19", "bob_e19"])
+        .friend("alice_e19", "bob_e19")
+        .category("alice_e19", "Dining")
+        .build(&app)
+        .await;
+    let alice_id = scenario.id("alice_e19");
+    let bob_id = scenario.id("bob_e19");
+    let alice_cookie = scenario.cookie("alice_e19");
+    let cat = scenario.category_id("alice_e19", "Dining");
 
-    let alice_id = common::create_test_user(&app.state, "alice_e19", "pw")
-        .await
-        .expect("create alice");
-    let bob_id = common::create_test_user(&app.state, "bob_e19", "pw")
-        .await
-        .expect("create bob");
-
-    let alice_cookie = common::login_user(&app.router, "alice_e19", "pw")
-        .await
-        .expect("login alice");
-    let bob_cookie = common::login_user(&app.router, "bob_e19", "pw")
-        .await
-        .expect("login bob");
-
-    send_friend_request(&app, &alice_cookie, "bob_e19").await;
-    accept_friend(&app, &bob_cookie, &alice_id).await;
-
-    let cat = create_category(&app, &alice_cookie, "Dining").await;
     let payload = json!({
         "idempotency_key": "e19-retry-split-1",
         "total_amount": 60.0,
@@ -441,7 +323,7 @@ async fn e19_no_duplicate_payer_record_on_retry() {
         &app,
         "POST",
         "/splits/create",
-        &alice_cookie,
+        alice_cookie,
         payload.clone(),
     )
     .await;
@@ -449,11 +331,11 @@ async fn e19_no_duplicate_payer_record_on_retry() {
         &app,
         "POST",
         "/splits/create",
-        &alice_cookie,
+        alice_cookie,
         payload.clone(),
     )
     .await;
-    let (s3, b3) = json_request(&app, "POST", "/splits/create", &alice_cookie, payload).await;
+    let (s3, b3) = json_request(&app, "POST", "/splits/create", alice_cookie, payload).await;
 
     assert_eq!(s1, StatusCode::CREATED);
     assert_eq!(s2, StatusCode::CREATED);
@@ -462,11 +344,11 @@ async fn e19_no_duplicate_payer_record_on_retry() {
     assert_eq!(b2, b3, "all retries must return same body");
 
     // Exactly 1 payer record for alice in the shared DB
-    let alice_count = count_records_for_user(&app, &alice_id).await;
+    let alice_count = count_records_for_user(&app, alice_id).await;
     assert_eq!(alice_count, 1, "no duplicate payer record after retries");
 
     // Exactly 1 pending record for bob
-    let bob_count = count_records_for_user(&app, &bob_id).await;
+    let bob_count = count_records_for_user(&app, bob_id).await;
     assert_eq!(bob_count, 1, "no duplicate pending record after retries");
 }
 
@@ -477,26 +359,27 @@ async fn e19_no_duplicate_payer_record_on_retry() {
 #[tokio::test]
 async fn e20_retry_fanout_endpoint_does_not_exist() {
     let app = common::setup_test_app().await.expect("setup failed");
-
-    common::create_test_user(&app.state, "alice_e20", "pw")
-        .await
-        .expect("create alice");
-    let alice_cookie = common::login_user(&app.router, "alice_e20", "pw")
-        .await
-        .expect("login alice");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_e20"])
+        .build(&app)
+        .await;
+    let alice_cookie = scenario.cookie("alice_e20");
 
     let (status, _) = json_request(
         &app,
         "POST",
         "/splits/retry-fanout",
-        &alice_cookie,
+        alice_cookie,
         json!({}),
     )
     .await;
-    assert_eq!(
-        status,
-        StatusCode::NOT_FOUND,
-        "retry-fanout endpoint must not exist"
+    // `/splits/{id}` is GET-only, so the path resolves but the method does not.
+    assert!(
+        matches!(
+            status,
+            StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED
+        ),
+        "retry-fanout endpoint must not exist, got {status}"
     );
 }
 
@@ -510,25 +393,17 @@ async fn e20_retry_fanout_endpoint_does_not_exist() {
 #[tokio::test]
 async fn e21_stale_null_body_idempotency_key_does_not_replay() {
     let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_e21", "bob_e21"])
+        .friend("alice_e21", "bob_e21")
+        .category("alice_e21", "Dining")
+        .build(&app)
+        .await;
+    let alice_id = scenario.id("alice_e21");
+    let bob_id = scenario.id("bob_e21");
+    let alice_cookie = scenario.cookie("alice_e21");
+    let cat = scenario.category_id("alice_e21", "Dining");
 
-    let alice_id = common::create_test_user(&app.state, "alice_e21", "pw")
-        .await
-        .expect("create alice");
-    let bob_id = common::create_test_user(&app.state, "bob_e21", "pw")
-        .await
-        .expect("create bob");
-
-    let alice_cookie = common::login_user(&app.router, "alice_e21", "pw")
-        .await
-        .expect("login alice");
-    let bob_cookie = common::login_user(&app.router, "bob_e21", "pw")
-        .await
-        .expect("login bob");
-
-    send_friend_request(&app, &alice_cookie, "bob_e21").await;
-    accept_friend(&app, &bob_cookie, &alice_id).await;
-
-    let cat = create_category(&app, &alice_cookie, "Dining").await;
     let key = "e21-stale-key-1";
 
     // Manually insert a "stale" idempotency entry with NULL response_body
@@ -545,7 +420,7 @@ async fn e21_stale_null_body_idempotency_key_does_not_replay() {
              VALUES (?, ?, ?, ?, ?, NULL, ?, ?)",
             (
                 key,
-                alice_id.as_str(),
+                alice_id,
                 "/splits/create",
                 "somehash",
                 201i64,
@@ -568,7 +443,7 @@ async fn e21_stale_null_body_idempotency_key_does_not_replay() {
         "category_id": cat,
         "splits": [{ "user_id": bob_id, "amount": 30.0 }]
     });
-    let (status, _) = json_request(&app, "POST", "/splits/create", &alice_cookie, payload).await;
+    let (status, _) = json_request(&app, "POST", "/splits/create", alice_cookie, payload).await;
 
     // Must NOT 500 with a "replay null body" panic
     assert_ne!(
@@ -578,8 +453,8 @@ async fn e21_stale_null_body_idempotency_key_does_not_replay() {
     );
 
     // Must NOT have created duplicate records either
-    let alice_count = count_records_for_user(&app, &alice_id).await;
-    let bob_count = count_records_for_user(&app, &bob_id).await;
+    let alice_count = count_records_for_user(&app, alice_id).await;
+    let bob_count = count_records_for_user(&app, bob_id).await;
     assert!(
         alice_count <= 1,
         "alice must have at most 1 record (no duplicates), got {alice_count}"
@@ -597,25 +472,17 @@ async fn e21_stale_null_body_idempotency_key_does_not_replay() {
 #[tokio::test]
 async fn f22_concurrent_split_same_key_single_write() {
     let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_f22", "bob_f22"])
+        .friend("alice_f22", "bob_f22")
+        .category("alice_f22", "Dining")
+        .build(&app)
+        .await;
+    let alice_id = scenario.id("alice_f22");
+    let bob_id = scenario.id("bob_f22");
+    let alice_cookie = scenario.cookie("alice_f22").to_string();
+    let cat = scenario.category_id("alice_f22", "Dining");
 
-    let alice_id = common::create_test_user(&app.state, "alice_f22", "pw")
-        .await
-        .expect("create alice");
-    let bob_id = common::create_test_user(&app.state, "bob_f22", "pw")
-        .await
-        .expect("create bob");
-
-    let alice_cookie = common::login_user(&app.router, "alice_f22", "pw")
-        .await
-        .expect("login alice");
-    let bob_cookie = common::login_user(&app.router, "bob_f22", "pw")
-        .await
-        .expect("login bob");
-
-    send_friend_request(&app, &alice_cookie, "bob_f22").await;
-    accept_friend(&app, &bob_cookie, &alice_id).await;
-
-    let cat = create_category(&app, &alice_cookie, "Dining").await;
     let shared_payload = Arc::new(json!({
         "idempotency_key": "f22-concurrent-split-1",
         "total_amount": 60.0,
@@ -670,8 +537,8 @@ async fn f22_concurrent_split_same_key_single_write() {
     }
 
     // Exactly 1 record per user in the shared DB
-    let alice_count = count_records_for_user(&app, &alice_id).await;
-    let bob_count = count_records_for_user(&app, &bob_id).await;
+    let alice_count = count_records_for_user(&app, alice_id).await;
+    let bob_count = count_records_for_user(&app, bob_id).await;
     assert_eq!(alice_count, 1, "exactly 1 payer record in shared DB");
     assert_eq!(bob_count, 1, "exactly 1 pending record in shared DB");
 }
@@ -683,48 +550,17 @@ async fn f22_concurrent_split_same_key_single_write() {
 #[tokio::test]
 async fn f23_concurrent_finalize_only_one_succeeds() {
     let app = common::setup_test_app().await.expect("setup failed");
-
-    let alice_id = common::create_test_user(&app.state, "alice_f23", "pw")
-        .await
-        .expect("create alice");
-    let bob_id = common::create_test_user(&app.state, "bob_f23", "pw")
-        .await
-        .expect("create bob");
-
-    let alice_cookie = common::login_user(&app.router, "alice_f23", "pw")
-        .await
-        .expect("login alice");
-    let bob_cookie = common::login_user(&app.router, "bob_f23", "pw")
-        .await
-        .expect("login bob");
-
-    send_friend_request(&app, &alice_cookie, "bob_f23").await;
-    accept_friend(&app, &bob_cookie, &alice_id).await;
-
-    let alice_cat = create_category(&app, &alice_cookie, "Dining").await;
-    let bob_cat = create_category(&app, &bob_cookie, "BobDining").await;
-
-    let (split_status, split_body) = json_request(
-        &app,
-        "POST",
-        "/splits/create",
-        &alice_cookie,
-        json!({
-            "idempotency_key": "f23-finalize-split-1",
-            "total_amount": 60.0,
-            "description": "f23 split",
-            "date": "2026-02-20",
-            "category_id": alice_cat,
-            "splits": [{ "user_id": bob_id, "amount": 30.0 }]
-        }),
-    )
-    .await;
-    assert_eq!(split_status, StatusCode::CREATED);
-
-    let pending_id = split_body["pending_record_ids"][0]
-        .as_str()
-        .expect("pending id")
-        .to_string();
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_f23", "bob_f23"])
+        .friend("alice_f23", "bob_f23")
+        .category("alice_f23", "Dining")
+        .category("bob_f23", "BobDining")
+        .split("alice_f23", "Dining", 60.0, &[("bob_f23", 30.0)])
+        .build(&app)
+        .await;
+    let bob_cookie = scenario.cookie("bob_f23").to_string();
+    let bob_cat = scenario.category_id("bob_f23", "BobDining");
+    let pending_id = scenario.splits[0].pending_record_ids[0].clone();
 
     let finalize_payload = Arc::new(json!({
         "record_id": pending_id,
@@ -761,8 +597,6 @@ async fn f23_concurrent_finalize_only_one_succeeds() {
         .count();
     assert_eq!(ok_count, 1, "exactly one finalize must succeed");
     assert_eq!(conflict_count, 2, "the other two must conflict");
-
-    let _ = (alice_id, bob_id);
 }
 
 // ---------------------------------------------------------------------------
@@ -772,51 +606,17 @@ async fn f23_concurrent_finalize_only_one_succeeds() {
 #[tokio::test]
 async fn f24_concurrent_settle_result_is_consistent() {
     let app = common::setup_test_app().await.expect("setup failed");
-
-    let alice_id = common::create_test_user(&app.state, "alice_f24", "pw")
-        .await
-        .expect("create alice");
-    let bob_id = common::create_test_user(&app.state, "bob_f24", "pw")
-        .await
-        .expect("create bob");
-
-    let alice_cookie = common::login_user(&app.router, "alice_f24", "pw")
-        .await
-        .expect("login alice");
-    let bob_cookie = common::login_user(&app.router, "bob_f24", "pw")
-        .await
-        .expect("login bob");
-
-    send_friend_request(&app, &alice_cookie, "bob_f24").await;
-    accept_friend(&app, &bob_cookie, &alice_id).await;
-
-    let alice_cat = create_category(&app, &alice_cookie, "Dining").await;
-
-    let (split_status, split_body) = json_request(
-        &app,
-        "POST",
-        "/splits/create",
-        &alice_cookie,
-        json!({
-            "idempotency_key": "f24-settle-split-1",
-            "total_amount": 60.0,
-            "description": "f24 split",
-            "date": "2026-02-20",
-            "category_id": alice_cat,
-            "splits": [{ "user_id": bob_id, "amount": 30.0 }]
-        }),
-    )
-    .await;
-    assert_eq!(split_status, StatusCode::CREATED);
-
-    let split_id = split_body["split_id"]
-        .as_str()
-        .expect("split_id")
-        .to_string();
-    let bob_record_id = split_body["pending_record_ids"][0]
-        .as_str()
-        .expect("pending id")
-        .to_string();
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_f24", "bob_f24"])
+        .friend("alice_f24", "bob_f24")
+        .category("alice_f24", "Dining")
+        .split("alice_f24", "Dining", 60.0, &[("bob_f24", 30.0)])
+        .build(&app)
+        .await;
+    let bob_id = scenario.id("bob_f24");
+    let bob_cookie = scenario.cookie("bob_f24").to_string();
+    let split_id = scenario.splits[0].split_id.clone();
+    let bob_record_id = scenario.splits[0].pending_record_ids[0].clone();
 
     let settle_payload = Arc::new(json!({ "split_id": split_id }));
 
@@ -858,13 +658,11 @@ async fn f24_concurrent_settle_result_is_consistent() {
     let mut rows = conn
         .query(
             "SELECT settle FROM records WHERE id = ? AND owner_user_id = ?",
-            [bob_record_id.as_str(), bob_id.as_str()],
+            [bob_record_id.as_str(), bob_id],
         )
         .await
         .expect("query settle status");
     let row = rows.next().await.expect("next").expect("row exists");
     let settled: bool = row.get(0).expect("settle flag");
     assert!(settled, "record must be settled in shared DB");
-
-    let _ = alice_id;
 }
@@ -0,0 +1,200 @@
+/// Tests Z141-Z143: Cancelling a split
+///
+/// `POST /splits/{id}/cancel` lets the initiator take back a split nobody has
+/// acted on: the payer's record and every pending participant record are deleted
+/// in one transaction and their ids returned. Once a participant accepted or
+/// settled their share it is 409; participants get 403 and outsiders 404.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder};
+use kash_server::constants::{SPLIT_NOT_CANCELLABLE_PREFIX, SPLIT_STATUS_CANCELLED};
+use kash_server::models::CancelSplitResponse;
+use kash_server::splits;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> (StatusCode, Value) {
+    let body = payload.map_or_else(Body::empty, |payload| Body::from(payload.to_string()));
+    let request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    (status, body)
+}
+
+async fn cancel(app: &common::TestApp, scenario: &Scenario, user: &str) -> (StatusCode, Value) {
+    let uri = format!("/splits/{}/cancel", scenario.splits[0].split_id);
+    send(app, "POST", &uri, scenario.cookie(user), None).await
+}
+
+async fn records_in_split(app: &common::TestApp, split_id: &str) -> i64 {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM records WHERE split_id = ?",
+            [split_id],
+        )
+        .await
+        .unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+async fn three_way_split(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    let carol = format!("carol_{suffix}");
+    let dave = format!("dave_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob, &carol, &dave])
+        .category(&alice, "Dining")
+        .category(&bob, "Dining")
+        .friend(&alice, &bob)
+        .friend(&alice, &carol)
+        .split(&alice, "Dining", 90.0, &[(&bob, 30.0), (&carol, 30.0)])
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z141: The initiator cancels an untouched split; every record goes
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z141_initiator_cancels_untouched_split() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z141").await;
+    let split = &scenario.splits[0];
+
+    // A participant trashing their pending record does not stop the cancel.
+    let (status, body) = send(
+        &app,
+        "DELETE",
+        &format!("/records/{}", split.pending_record_ids[1]),
+        scenario.cookie("carol_z141"),
+        None,
+    )
+    .await;
+    assert!(status.is_success(), "{status} {body}");
+
+    let (status, body) = cancel(&app, &scenario, "alice_z141").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let response: CancelSplitResponse = serde_json::from_value(body).expect("cancel");
+    assert_eq!(response.split_id, split.split_id);
+    assert_eq!(response.status, SPLIT_STATUS_CANCELLED);
+    let mut removed = response.removed_record_ids;
+    removed.sort();
+    let mut expected = vec![split.payer_record_id.clone()];
+    expected.extend(split.pending_record_ids.iter().cloned());
+    expected.sort();
+    assert_eq!(removed, expected);
+    assert_eq!(records_in_split(&app, &split.split_id).await, 0);
+
+    let (status, body) = send(
+        &app,
+        "GET",
+        "/splits/pending",
+        scenario.cookie("bob_z141"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["total_count"], 0, "{body}");
+
+    let (status, _) = cancel(&app, &scenario, "alice_z141").await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "a cancelled split is gone");
+}
+
+// ---------------------------------------------------------------------------
+// Z142: Accepted or settled shares make the split stand
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z142_accepted_split_cannot_be_cancelled() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z142").await;
+    let split = &scenario.splits[0];
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/records/finalize-pending",
+        scenario.cookie("bob_z142"),
+        Some(json!({
+            "record_id": split.pending_record_ids[0],
+            "category_id": scenario.category_id("bob_z142", "Dining"),
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let (status, body) = cancel(&app, &scenario, "alice_z142").await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(
+        body.as_str()
+            .is_some_and(|message| message.starts_with(SPLIT_NOT_CANCELLABLE_PREFIX)),
+        "{body}"
+    );
+    assert_eq!(records_in_split(&app, &split.split_id).await, 3);
+}
+
+// ---------------------------------------------------------------------------
+// Z143: Only the initiator may cancel; a closed period needs reopen
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z143_cancel_is_initiator_only() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z143").await;
+    let split = &scenario.splits[0];
+
+    let (status, _) = cancel(&app, &scenario, "bob_z143").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = cancel(&app, &scenario, "dave_z143").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = send(
+        &app,
+        "PUT",
+        "/settings",
+        scenario.cookie("alice_z143"),
+        Some(json!({ "closed_through": "2026-12-31" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, _) = cancel(&app, &scenario, "alice_z143").await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(records_in_split(&app, &split.split_id).await, 3);
+
+    let response = splits::cancel_split_for_user(
+        &app.state.main_db,
+        scenario.id("alice_z143"),
+        &split.split_id,
+        true,
+    )
+    .await
+    .expect("reopened cancel");
+    assert_eq!(response.removed_record_ids.len(), 3);
+    assert_eq!(records_in_split(&app, &split.split_id).await, 0);
+}
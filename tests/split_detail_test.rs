@@ -0,0 +1,203 @@
+/// Tests Z121-Z123: Split detail
+///
+/// `GET /splits/{id}` rebuilds a split from its records: the initiator, each
+/// participant's share and whether their record is still there, pending or settled,
+/// and a status that follows them. Only the initiator and the participants can read
+/// it; everyone else gets the same 404 as for an unknown id.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{FIXTURE_SPLIT_DATE, Scenario, ScenarioBuilder, money};
+use kash_server::constants::{
+    SPLIT_NOT_FOUND_MESSAGE, SPLIT_STATUS_COMPLETED, SPLIT_STATUS_INITIATED, SPLIT_STATUS_SETTLED,
+};
+use kash_server::models::{SplitDetail, SplitDetailParticipant};
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> (StatusCode, Value) {
+    let body = payload.map_or_else(Body::empty, |payload| Body::from(payload.to_string()));
+    let request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    (status, body)
+}
+
+async fn get_split(app: &common::TestApp, scenario: &Scenario, user: &str) -> SplitDetail {
+    let uri = format!("/splits/{}", scenario.splits[0].split_id);
+    let (status, body) = send(app, "GET", &uri, scenario.cookie(user), None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    serde_json::from_value(body).expect("split detail")
+}
+
+async fn three_way_split(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    let carol = format!("carol_{suffix}");
+    let dave = format!("dave_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob, &carol, &dave])
+        .category(&alice, "Dining")
+        .category(&bob, "Dining")
+        .friend(&alice, &bob)
+        .friend(&alice, &carol)
+        .friend(&alice, &dave)
+        .split(&alice, "Dining", 90.0, &[(&bob, 30.0), (&carol, 20.0)])
+        .build(app)
+        .await
+}
+
+fn participant(user_id: &str, username: &str, amount: f64) -> SplitDetailParticipant {
+    SplitDetailParticipant {
+        user_id: user_id.to_string(),
+        username: username.to_string(),
+        amount: money(amount),
+        record_exists: true,
+        pending: true,
+        settled: false,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Z121: The initiator and participants see the same split
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z121_split_detail_lists_participants() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z121").await;
+
+    let detail = get_split(&app, &scenario, "alice_z121").await;
+    assert_eq!(detail.split_id, scenario.splits[0].split_id);
+    assert_eq!(detail.status, SPLIT_STATUS_INITIATED);
+    assert_eq!(detail.total_amount, money(90.0));
+    assert_eq!(detail.description, "alice_z121 split");
+    assert_eq!(detail.date, FIXTURE_SPLIT_DATE);
+    assert_eq!(detail.initiator_user_id, scenario.id("alice_z121"));
+    assert_eq!(detail.initiator_name, "alice_z121");
+    assert_eq!(
+        detail.participants,
+        vec![
+            participant(scenario.id("bob_z121"), "bob_z121", 30.0),
+            participant(scenario.id("carol_z121"), "carol_z121", 20.0),
+        ]
+    );
+
+    for user in ["bob_z121", "carol_z121"] {
+        let seen = get_split(&app, &scenario, user).await;
+        assert_eq!(seen.initiator_user_id, detail.initiator_user_id);
+        assert_eq!(seen.participants, detail.participants);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Z122: Status follows finalizing, settling and trashing participant records
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z122_split_status_follows_participant_records() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z122").await;
+    let split = &scenario.splits[0];
+    let (bob_record, carol_record) = (&split.pending_record_ids[0], &split.pending_record_ids[1]);
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/records/finalize-pending",
+        scenario.cookie("bob_z122"),
+        Some(json!({
+            "record_id": bob_record,
+            "category_id": scenario.category_id("bob_z122", "Dining"),
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (status, body) = send(
+        &app,
+        "DELETE",
+        &format!("/records/{carol_record}"),
+        scenario.cookie("carol_z122"),
+        None,
+    )
+    .await;
+    assert!(status.is_success(), "{status} {body}");
+
+    let detail = get_split(&app, &scenario, "alice_z122").await;
+    assert_eq!(detail.status, SPLIT_STATUS_COMPLETED);
+    assert_eq!(
+        detail.total_amount,
+        money(90.0),
+        "trashed shares still count"
+    );
+    let bob = &detail.participants[0];
+    assert!(bob.record_exists && !bob.pending && !bob.settled);
+    let carol = &detail.participants[1];
+    assert!(!carol.record_exists);
+    assert_eq!(carol.amount, money(20.0));
+
+    let (status, body) = send(
+        &app,
+        "PUT",
+        &format!("/records/{bob_record}/settle"),
+        scenario.cookie("bob_z122"),
+        Some(json!({ "split_id": split.split_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let detail = get_split(&app, &scenario, "bob_z122").await;
+    assert_eq!(detail.status, SPLIT_STATUS_SETTLED);
+    assert!(detail.participants[0].settled);
+}
+
+// ---------------------------------------------------------------------------
+// Z123: Outsiders and unknown ids get the same 404
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z123_split_detail_is_hidden_from_outsiders() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z123").await;
+    let uri = format!("/splits/{}", scenario.splits[0].split_id);
+
+    let (status, body) = send(&app, "GET", &uri, scenario.cookie("dave_z123"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body, SPLIT_NOT_FOUND_MESSAGE);
+
+    let (status, body) = send(
+        &app,
+        "GET",
+        "/splits/no-such-split",
+        scenario.cookie("alice_z123"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body, SPLIT_NOT_FOUND_MESSAGE);
+
+    let (status, _) = send(&app, "GET", &uri, "", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
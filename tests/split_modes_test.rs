@@ -0,0 +1,259 @@
+/// Tests Z431-Z433: Equal and percentage splits
+///
+/// `POST /splits/create` takes a `split_mode`: `exact` (the default) uses each
+/// participant's `amount`, `equal` divides the total between the initiator and the
+/// participants, and `percentage` uses each entry's `percentage`, which must add up
+/// to 100. Leftover cents go to the initiator first. The mode is part of the
+/// idempotency payload.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder};
+use common::send_json;
+use kash_server::models::SplitDetail;
+use kash_server::money::Money;
+use serde_json::{Value, json};
+
+// ---- Helpers ----
+
+async fn three_friends(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    let carol = format!("carol_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob, &carol])
+        .category(&alice, "Dining")
+        .friend(&alice, &bob)
+        .friend(&alice, &carol)
+        .build(app)
+        .await
+}
+
+fn payload(scenario: &Scenario, suffix: &str, key: &str, mode: &str, splits: Value) -> Value {
+    json!({
+        "idempotency_key": key,
+        "total_amount": 100.0,
+        "description": "Dinner",
+        "date": "2025-10-05",
+        "category_id": scenario.category_id(&format!("alice_{suffix}"), "Dining"),
+        "split_mode": mode,
+        "splits": splits,
+    })
+}
+
+/// Each user's share from `GET /splits/{id}`, plus the payer's own record amount.
+async fn shares(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    suffix: &str,
+    created: &Value,
+) -> (Money, Vec<(String, Money)>) {
+    let cookie = scenario.cookie(&format!("alice_{suffix}"));
+    let uri = format!("/records/{}", created["payer_record_id"].as_str().unwrap());
+    let (_, payer) = send_json(app, "GET", &uri, cookie, None).await;
+    let payer: Money = serde_json::from_value(payer["amount"].clone()).expect("amount");
+    let uri = format!("/splits/{}", created["split_id"].as_str().unwrap());
+    let (status, body) = send_json(app, "GET", &uri, cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let detail: SplitDetail = serde_json::from_value(body).expect("split detail");
+    let participants = detail
+        .participants
+        .into_iter()
+        .map(|participant| (participant.username, participant.amount))
+        .collect();
+    (payer, participants)
+}
+
+// ---------------------------------------------------------------------------
+// Z431: An equal split gives the odd cent to the initiator
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z431_equal_split_divides_the_total() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_friends(&app, "z431").await;
+    let cookie = scenario.cookie("alice_z431");
+    let splits = json!([
+        { "user_id": scenario.id("bob_z431") },
+        { "user_id": scenario.id("carol_z431") },
+    ]);
+
+    let (status, created) = send_json(
+        &app,
+        "POST",
+        "/splits/create",
+        cookie,
+        Some(payload(&scenario, "z431", "z431-equal", "equal", splits)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{created}");
+    let (payer, participants) = shares(&app, &scenario, "z431", &created).await;
+    assert_eq!(payer, Money::from_cents(-3334));
+    assert_eq!(
+        participants,
+        vec![
+            ("bob_z431".to_string(), Money::from_cents(3333)),
+            ("carol_z431".to_string(), Money::from_cents(3333)),
+        ]
+    );
+
+    let with_amount = json!([{ "user_id": scenario.id("bob_z431"), "amount": 50.0 }]);
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/splits/create",
+        cookie,
+        Some(payload(
+            &scenario,
+            "z431",
+            "z431-amount",
+            "equal",
+            with_amount,
+        )),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body,
+        format!(
+            "Participant {}: equal splits take no amount or percentage",
+            scenario.id("bob_z431")
+        )
+    );
+
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/splits/create",
+        cookie,
+        Some(payload(&scenario, "z431", "z431-mode", "halves", json!([]))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "unknown mode");
+}
+
+// ---------------------------------------------------------------------------
+// Z432: A percentage split may include the initiator and must add up to 100
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z432_percentage_split_uses_each_share() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_friends(&app, "z432").await;
+    let cookie = scenario.cookie("alice_z432");
+
+    let splits = json!([
+        { "user_id": scenario.id("alice_z432"), "percentage": 50 },
+        { "user_id": scenario.id("bob_z432"), "percentage": 30 },
+        { "user_id": scenario.id("carol_z432"), "percentage": 20 },
+    ]);
+    let (status, created) = send_json(
+        &app,
+        "POST",
+        "/splits/create",
+        cookie,
+        Some(payload(&scenario, "z432", "z432-pct", "percentage", splits)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{created}");
+    assert_eq!(created["pending_record_ids"].as_array().unwrap().len(), 2);
+    let (payer, participants) = shares(&app, &scenario, "z432", &created).await;
+    assert_eq!(payer, Money::from_cents(-5000));
+    assert_eq!(
+        participants,
+        vec![
+            ("bob_z432".to_string(), Money::from_cents(3000)),
+            ("carol_z432".to_string(), Money::from_cents(2000)),
+        ]
+    );
+
+    for (splits, message) in [
+        (
+            json!([
+                { "user_id": scenario.id("bob_z432"), "percentage": 30 },
+                { "user_id": scenario.id("carol_z432"), "percentage": 20 },
+            ]),
+            "Percentages must add up to 100, not 50".to_string(),
+        ),
+        (
+            json!([{ "user_id": scenario.id("bob_z432"), "percentage": 120 }]),
+            format!(
+                "Participant {}: percentage must be above 0 and at most 100, with at most two decimal places",
+                scenario.id("bob_z432")
+            ),
+        ),
+        (
+            json!([{ "user_id": scenario.id("carol_z432") }]),
+            format!(
+                "Participant {}: percentage is required in percentage mode",
+                scenario.id("carol_z432")
+            ),
+        ),
+    ] {
+        let (status, body) = send_json(
+            &app,
+            "POST",
+            "/splits/create",
+            cookie,
+            Some(payload(
+                &scenario,
+                "z432",
+                "z432-invalid",
+                "percentage",
+                splits,
+            )),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{message}");
+        assert_eq!(body, message);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Z433: The mode is part of the idempotency payload
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z433_replay_must_repeat_the_mode() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_friends(&app, "z433").await;
+    let cookie = scenario.cookie("alice_z433");
+    let splits = json!([
+        { "user_id": scenario.id("bob_z433"), "amount": 50.0 },
+    ]);
+
+    let (status, first) = send_json(
+        &app,
+        "POST",
+        "/splits/create",
+        cookie,
+        Some(payload(
+            &scenario,
+            "z433",
+            "z433-key",
+            "exact",
+            splits.clone(),
+        )),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{first}");
+
+    // Leaving the mode out means `exact`, so the replay matches
+    let mut without_mode = payload(&scenario, "z433", "z433-key", "exact", splits);
+    without_mode.as_object_mut().unwrap().remove("split_mode");
+    let (status, replay) =
+        send_json(&app, "POST", "/splits/create", cookie, Some(without_mode)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(replay, first);
+
+    let equal = json!([{ "user_id": scenario.id("bob_z433") }]);
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        "/splits/create",
+        cookie,
+        Some(payload(&scenario, "z433", "z433-key", "equal", equal)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
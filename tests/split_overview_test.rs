@@ -0,0 +1,223 @@
+/// Tests Z131-Z133: Listing splits
+///
+/// `GET /splits` lists every split the caller paid for or owes on, newest first,
+/// with `role=initiator|participant` and `status` filters, paging and a
+/// `total_count`. Each entry carries how many live participant records are settled.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use kash_server::constants::{
+    SPLIT_STATUS_COMPLETED, SPLIT_STATUS_INITIATED, SPLIT_STATUS_SETTLED,
+};
+use kash_server::models::{SplitProgress, SplitSummaryListResponse};
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> (StatusCode, Value) {
+    let body = payload.map_or_else(Body::empty, |payload| Body::from(payload.to_string()));
+    let request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    (status, body)
+}
+
+async fn list(app: &common::TestApp, cookie: &str, query: &str) -> SplitSummaryListResponse {
+    let (status, body) = send(app, "GET", &format!("/splits{query}"), cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    serde_json::from_value(body).expect("splits")
+}
+
+fn split_ids(response: &SplitSummaryListResponse) -> Vec<&str> {
+    response
+        .splits
+        .iter()
+        .map(|split| split.split_id.as_str())
+        .collect()
+}
+
+/// Alice pays a dinner with Bob and Carol and a lunch with Bob, then Bob pays a coffee
+/// with Alice.
+async fn scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    let carol = format!("carol_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob, &carol])
+        .category(&alice, "Dining")
+        .category(&bob, "Dining")
+        .friend(&alice, &bob)
+        .friend(&alice, &carol)
+        .split(&alice, "Dining", 60.0, &[(&bob, 20.0), (&carol, 20.0)])
+        .split(&alice, "Dining", 30.0, &[(&bob, 15.0)])
+        .split(&bob, "Dining", 40.0, &[(&alice, 20.0)])
+        .build(app)
+        .await
+}
+
+async fn settle(app: &common::TestApp, cookie: &str, record_id: &str, split_id: &str) {
+    let (status, body) = send(
+        app,
+        "PUT",
+        &format!("/records/{record_id}/settle"),
+        cookie,
+        Some(json!({ "split_id": split_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+}
+
+// ---------------------------------------------------------------------------
+// Z131: Both sides of every split, filtered by role
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z131_list_splits_by_role() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z131").await;
+    let [dinner, lunch, coffee] = [0, 1, 2].map(|i| scenario.splits[i].split_id.as_str());
+    let cookie = scenario.cookie("alice_z131");
+
+    let all = list(&app, cookie, "").await;
+    assert_eq!(all.total_count, 3);
+    assert_eq!(split_ids(&all), vec![coffee, lunch, dinner]);
+    let roles: Vec<&str> = all.splits.iter().map(|split| split.role.as_str()).collect();
+    assert_eq!(roles, vec!["participant", "initiator", "initiator"]);
+    let first = &all.splits[2];
+    assert_eq!(first.total_amount, money(60.0));
+    assert_eq!(first.description, "alice_z131 split");
+    assert_eq!(first.initiator_user_id, scenario.id("alice_z131"));
+    assert_eq!(
+        first.progress,
+        SplitProgress {
+            participants_total: 2,
+            participants_settled: 0,
+            amount_outstanding: money(40.0),
+        }
+    );
+
+    let initiated = list(&app, cookie, "?role=initiator").await;
+    assert_eq!(
+        (initiated.total_count, split_ids(&initiated)),
+        (2, vec![lunch, dinner])
+    );
+    let owed = list(&app, cookie, "?role=participant").await;
+    assert_eq!((owed.total_count, split_ids(&owed)), (1, vec![coffee]));
+
+    let carol = list(&app, scenario.cookie("carol_z131"), "").await;
+    assert_eq!((carol.total_count, split_ids(&carol)), (1, vec![dinner]));
+    assert_eq!(carol.splits[0].role, "participant");
+}
+
+// ---------------------------------------------------------------------------
+// Z132: Status filter and settled rollup follow the participant records
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z132_list_splits_by_status() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z132").await;
+    let lunch = &scenario.splits[1];
+    let bob_lunch = &lunch.pending_record_ids[0];
+    let cookie = scenario.cookie("alice_z132");
+
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/records/finalize-pending",
+        scenario.cookie("bob_z132"),
+        Some(json!({
+            "record_id": bob_lunch,
+            "category_id": scenario.category_id("bob_z132", "Dining"),
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let completed = list(&app, cookie, &format!("?status={SPLIT_STATUS_COMPLETED}")).await;
+    assert_eq!(split_ids(&completed), vec![lunch.split_id.as_str()]);
+
+    settle(
+        &app,
+        scenario.cookie("bob_z132"),
+        bob_lunch,
+        &lunch.split_id,
+    )
+    .await;
+    let settled = list(&app, cookie, &format!("?status={SPLIT_STATUS_SETTLED}")).await;
+    assert_eq!(settled.total_count, 1);
+    assert_eq!(
+        settled.splits[0].progress,
+        SplitProgress {
+            participants_total: 1,
+            participants_settled: 1,
+            amount_outstanding: money(0.0),
+        }
+    );
+
+    let open = list(
+        &app,
+        cookie,
+        &format!("?role=initiator&status={SPLIT_STATUS_INITIATED}"),
+    )
+    .await;
+    assert_eq!(split_ids(&open), vec![scenario.splits[0].split_id.as_str()]);
+    assert_eq!(
+        list(&app, cookie, &format!("?status={SPLIT_STATUS_COMPLETED}"))
+            .await
+            .total_count,
+        0
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z133: Paging keeps total_count; bad filters are 400
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z133_list_splits_paging_and_validation() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = scenario(&app, "z133").await;
+    let cookie = scenario.cookie("alice_z133");
+
+    let page = list(&app, cookie, "?limit=2&offset=1").await;
+    assert_eq!((page.total_count, page.limit, page.offset), (3, 2, 1));
+    assert_eq!(
+        split_ids(&page),
+        vec![
+            scenario.splits[1].split_id.as_str(),
+            scenario.splits[0].split_id.as_str(),
+        ]
+    );
+
+    for query in ["?role=payer", "?status=done", "?status=Settled"] {
+        let (status, body) = send(&app, "GET", &format!("/splits{query}"), cookie, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}: {body}");
+    }
+
+    let stranger = ScenarioBuilder::new().user("dave_z133").build(&app).await;
+    let empty = list(&app, stranger.cookie("dave_z133"), "").await;
+    assert_eq!(empty.total_count, 0);
+    assert!(empty.splits.is_empty());
+}
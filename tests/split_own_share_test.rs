@@ -0,0 +1,245 @@
+/// Tests Z441-Z443: The initiator's own share of a split
+///
+/// The initiator's record holds only what they spent themselves: the remainder of the
+/// total, or their own entry when they list themselves in an exact split. What the
+/// participants owe is the split's `receivable`, tracked by their share records, so
+/// settling a participant never changes the initiator's expense. `legacy_full_total`
+/// books the initiator's record at the whole total for clients that expect it.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder};
+use common::send_json;
+use kash_server::models::{FriendBalancesResponse, SplitDetail};
+use kash_server::money::Money;
+use serde_json::{Value, json};
+
+// ---- Helpers ----
+
+async fn two_friends(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    let carol = format!("carol_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob, &carol])
+        .category(&alice, "Dining")
+        .friend(&alice, &bob)
+        .friend(&alice, &carol)
+        .build(app)
+        .await
+}
+
+async fn create_split(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    suffix: &str,
+    extra: Value,
+) -> (StatusCode, Value) {
+    let alice = format!("alice_{suffix}");
+    let mut payload = json!({
+        "idempotency_key": format!("{suffix}-split"),
+        "total_amount": 90.0,
+        "description": "Dinner",
+        "date": "2025-10-05",
+        "category_id": scenario.category_id(&alice, "Dining"),
+        "splits": [
+            { "user_id": scenario.id(&format!("bob_{suffix}")), "amount": 30.0 },
+            { "user_id": scenario.id(&format!("carol_{suffix}")), "amount": 20.0 },
+        ],
+    });
+    for (key, value) in extra.as_object().unwrap() {
+        payload[key] = value.clone();
+    }
+    send_json(
+        app,
+        "POST",
+        "/splits/create",
+        scenario.cookie(&alice),
+        Some(payload),
+    )
+    .await
+}
+
+/// The payer's own record amount and the split as `GET /splits/{id}` shows it.
+async fn payer_and_detail(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    suffix: &str,
+    created: &Value,
+) -> (Money, SplitDetail) {
+    let cookie = scenario.cookie(&format!("alice_{suffix}"));
+    let uri = format!("/records/{}", created["payer_record_id"].as_str().unwrap());
+    let (_, payer) = send_json(app, "GET", &uri, cookie, None).await;
+    let payer: Money = serde_json::from_value(payer["amount"].clone()).expect("amount");
+    let uri = format!("/splits/{}", created["split_id"].as_str().unwrap());
+    let (status, body) = send_json(app, "GET", &uri, cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    (payer, serde_json::from_value(body).expect("split detail"))
+}
+
+/// What each friend owes the initiator, by username.
+async fn owed_to_alice(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    suffix: &str,
+) -> Vec<(String, Money)> {
+    let cookie = scenario.cookie(&format!("alice_{suffix}"));
+    let (status, body) = send_json(app, "GET", "/friends/balances", cookie, None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let response: FriendBalancesResponse = serde_json::from_value(body).expect("balances");
+    let mut owed: Vec<(String, Money)> = response
+        .balances
+        .into_iter()
+        .map(|balance| {
+            let owed = balance
+                .currencies
+                .iter()
+                .map(|currency| currency.amount_owed_to_me)
+                .sum();
+            (balance.username, owed)
+        })
+        .collect();
+    owed.sort();
+    owed
+}
+
+// ---------------------------------------------------------------------------
+// Z441: The initiator's record holds only their own share
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z441_payer_record_is_own_share() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = two_friends(&app, "z441").await;
+
+    let (status, created) = create_split(&app, &scenario, "z441", json!({})).await;
+    assert_eq!(status, StatusCode::CREATED, "{created}");
+    let (payer, detail) = payer_and_detail(&app, &scenario, "z441", &created).await;
+    assert_eq!(payer, Money::from_cents(-4000), "the remainder is theirs");
+    assert_eq!(detail.total_amount, Money::from_cents(9000));
+    assert_eq!(detail.initiator_share, Money::from_cents(4000));
+    assert_eq!(detail.receivable, Money::from_cents(5000));
+    assert!(!detail.legacy_full_total);
+
+    // Listing themselves makes the share explicit, and it must then add up
+    let alice = scenario.id("alice_z441");
+    let (status, created) = create_split(
+        &app,
+        &scenario,
+        "z441",
+        json!({
+            "idempotency_key": "z441-self",
+            "splits": [
+                { "user_id": alice, "amount": 40.0 },
+                { "user_id": scenario.id("bob_z441"), "amount": 30.0 },
+                { "user_id": scenario.id("carol_z441"), "amount": 20.0 },
+            ],
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{created}");
+    assert_eq!(created["pending_record_ids"].as_array().unwrap().len(), 2);
+    let (payer, detail) = payer_and_detail(&app, &scenario, "z441", &created).await;
+    assert_eq!(payer, Money::from_cents(-4000));
+    assert_eq!(detail.initiator_share, Money::from_cents(4000));
+
+    let (status, body) = create_split(
+        &app,
+        &scenario,
+        "z441",
+        json!({
+            "idempotency_key": "z441-short",
+            "splits": [
+                { "user_id": alice, "amount": 10.0 },
+                { "user_id": scenario.id("bob_z441"), "amount": 30.0 },
+            ],
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body,
+        "Split amounts must add up to the total of 90 with the initiator's share, not 40"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z442: Settling a participant leaves the initiator's expense alone
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z442_settling_only_reduces_the_receivable() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = two_friends(&app, "z442").await;
+    let (status, created) = create_split(&app, &scenario, "z442", json!({})).await;
+    assert_eq!(status, StatusCode::CREATED, "{created}");
+    assert_eq!(
+        owed_to_alice(&app, &scenario, "z442").await,
+        vec![
+            ("bob_z442".to_string(), Money::from_cents(3000)),
+            ("carol_z442".to_string(), Money::from_cents(2000)),
+        ]
+    );
+
+    let uri = format!(
+        "/splits/{}/settle-all",
+        created["split_id"].as_str().unwrap()
+    );
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        &uri,
+        scenario.cookie("alice_z442"),
+        Some(json!({ "friend_id": scenario.id("bob_z442") })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let (payer, detail) = payer_and_detail(&app, &scenario, "z442", &created).await;
+    assert_eq!(payer, Money::from_cents(-4000), "expense unchanged");
+    assert_eq!(detail.initiator_share, Money::from_cents(4000));
+    assert_eq!(detail.receivable, Money::from_cents(2000));
+    assert_eq!(
+        owed_to_alice(&app, &scenario, "z442").await,
+        vec![
+            ("bob_z442".to_string(), Money::ZERO),
+            ("carol_z442".to_string(), Money::from_cents(2000)),
+        ]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z443: legacy_full_total books the whole total without double counting
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z443_legacy_full_total_keeps_the_old_payer_record() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = two_friends(&app, "z443").await;
+    let (status, created) = create_split(
+        &app,
+        &scenario,
+        "z443",
+        json!({ "legacy_full_total": true }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{created}");
+
+    let (payer, detail) = payer_and_detail(&app, &scenario, "z443", &created).await;
+    assert_eq!(payer, Money::from_cents(-9000));
+    assert!(detail.legacy_full_total);
+    assert_eq!(detail.total_amount, Money::from_cents(9000));
+    assert_eq!(detail.initiator_share, Money::from_cents(4000));
+    assert_eq!(detail.receivable, Money::from_cents(5000));
+    assert_eq!(
+        owed_to_alice(&app, &scenario, "z443").await,
+        vec![
+            ("bob_z443".to_string(), Money::from_cents(3000)),
+            ("carol_z443".to_string(), Money::from_cents(2000)),
+        ]
+    );
+
+    // The flag is part of the idempotency payload
+    let (status, _) = create_split(&app, &scenario, "z443", json!({})).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
@@ -0,0 +1,196 @@
+/// Tests S1-S3: Split status in the record list
+///
+/// `GET /records` attaches `split_progress` to the payer's record of a split and a
+/// `settled` flag to each participant's record. `records::attach_split_status`
+/// batches the lookups for the whole page, so the number of queries does not grow
+/// with the page size.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use common::send_request;
+use kash_server::models::{Record, SplitProgress};
+use kash_server::record_repo::{self, RecordFilter, RecordSort};
+use kash_server::records;
+use serde_json::{Value, json};
+
+// ---- Helpers ----
+
+async fn list_records(app: &common::TestApp, cookie: &str) -> Vec<Record> {
+    let response = send_request(app, "GET", "/records", cookie, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: Value = serde_json::from_slice(&bytes).unwrap();
+    serde_json::from_value(body["records"].clone()).unwrap()
+}
+
+fn find<'a>(records: &'a [Record], id: &str) -> &'a Record {
+    records
+        .iter()
+        .find(|record| record.id == id)
+        .unwrap_or_else(|| panic!("record {id} not listed"))
+}
+
+async fn settle(app: &common::TestApp, cookie: &str, record_id: &str, split_id: &str) {
+    let response = send_request(
+        app,
+        "PUT",
+        &format!("/records/{record_id}/settle"),
+        cookie,
+        Some(json!({ "split_id": split_id })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+async fn three_way_split(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    let carol = format!("carol_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob, &carol])
+        .category(&alice, "Dining")
+        .friend(&alice, &bob)
+        .friend(&alice, &carol)
+        .split(&alice, "Dining", 90.0, &[(&bob, 30.0), (&carol, 30.0)])
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// S1: The payer sees grouped progress that follows settlements
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn s1_payer_record_carries_split_progress() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "s1").await;
+    let split = &scenario.splits[0];
+
+    let records = list_records(&app, scenario.cookie("alice_s1")).await;
+    let payer = find(&records, &split.payer_record_id);
+    assert_eq!(
+        payer.split_progress,
+        Some(SplitProgress {
+            participants_total: 2,
+            participants_settled: 0,
+            amount_outstanding: money(60.0),
+        })
+    );
+    assert_eq!(payer.settled, None);
+
+    let bob_record = &split.pending_record_ids[0];
+    settle(&app, scenario.cookie("bob_s1"), bob_record, &split.split_id).await;
+
+    let records = list_records(&app, scenario.cookie("alice_s1")).await;
+    assert_eq!(
+        find(&records, &split.payer_record_id).split_progress,
+        Some(SplitProgress {
+            participants_total: 2,
+            participants_settled: 1,
+            amount_outstanding: money(30.0),
+        })
+    );
+}
+
+// ---------------------------------------------------------------------------
+// S2: Participants only see their own settled flag
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn s2_participant_record_carries_settled_flag() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "s2").await;
+    let split = &scenario.splits[0];
+    let bob_record = &split.pending_record_ids[0];
+
+    let records = list_records(&app, scenario.cookie("bob_s2")).await;
+    let record = find(&records, bob_record);
+    assert_eq!(record.settled, Some(false));
+    assert_eq!(record.split_progress, None);
+
+    settle(&app, scenario.cookie("bob_s2"), bob_record, &split.split_id).await;
+
+    let records = list_records(&app, scenario.cookie("bob_s2")).await;
+    assert_eq!(find(&records, bob_record).settled, Some(true));
+
+    let records = list_records(&app, scenario.cookie("carol_s2")).await;
+    assert_eq!(
+        find(&records, &split.pending_record_ids[1]).settled,
+        Some(false),
+        "Bob settling leaves Carol's record open"
+    );
+
+    let response = send_request(&app, "GET", "/records", scenario.cookie("bob_s2"), None).await;
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: Value = serde_json::from_slice(&bytes).unwrap();
+    assert!(body["records"][0].get("split_progress").is_none());
+}
+
+// ---------------------------------------------------------------------------
+// S3: The query count does not grow with the page size
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn s3_split_status_queries_stay_constant() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let mut builder = ScenarioBuilder::new()
+        .users(&["alice_s3", "bob_s3"])
+        .category("alice_s3", "Dining")
+        .friend("alice_s3", "bob_s3");
+    for i in 0..12 {
+        builder = builder.split(
+            "alice_s3",
+            "Dining",
+            20.0 + f64::from(i),
+            &[("bob_s3", 10.0)],
+        );
+    }
+    let scenario = builder.build(&app).await;
+    let alice = scenario.id("alice_s3");
+
+    let filter = RecordFilter {
+        start_date: "0000-01-01",
+        end_date: "9999-12-31",
+        pending: None,
+        settle: None,
+        category_id: None,
+        name_contains: None,
+        min_amount: None,
+        max_amount: None,
+        tag: None,
+        sort: RecordSort::default(),
+        after: None,
+    };
+    let conn = app.state.main_db.read().await;
+    for limit in [1, 4, 12] {
+        let mut page = record_repo::list_records(&conn, alice, &filter, limit, 0)
+            .await
+            .expect("list");
+        assert_eq!(page.len() as u32, limit);
+        let queries = records::attach_split_status(&conn, alice, &mut page)
+            .await
+            .expect("attach");
+        assert_eq!(queries, 2, "page size {limit}");
+        for record in &page {
+            let progress = record.split_progress.as_ref().expect("payer record");
+            assert_eq!(progress.participants_total, 1);
+            assert_eq!(progress.amount_outstanding, money(10.0));
+        }
+    }
+
+    let bob = scenario.id("bob_s3");
+    let mut page = record_repo::list_records(&conn, bob, &filter, 12, 0)
+        .await
+        .expect("list");
+    assert_eq!(page.len(), 12);
+    let queries = records::attach_split_status(&conn, bob, &mut page)
+        .await
+        .expect("attach");
+    assert_eq!(queries, 1, "no splits paid by Bob on the page");
+    assert!(page.iter().all(|record| record.settled == Some(false)));
+}
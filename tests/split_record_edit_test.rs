@@ -0,0 +1,296 @@
+/// Tests S1-S5: Split records cannot be edited into inconsistency
+///
+/// Records created by a split (the payer's own record and each participant's
+/// pending or finalized record) may only change `name` and `category_id`.
+/// Amount and date edits are refused with 409 `SPLIT_RECORD_IMMUTABLE`, both
+/// through `PUT /records/{id}` and through the guard the Telegram bot's
+/// `edit_record` tool calls.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder, money};
+use common::send_json;
+use kash_server::constants::{ERROR_CODE_SPLIT_RECORD_IMMUTABLE, SPLIT_RECORD_IMMUTABLE_MESSAGE};
+use kash_server::money::Money;
+use kash_server::records;
+use serde_json::json;
+
+// ---- Helpers ----
+
+async fn split_scenario(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob])
+        .category(&alice, "Dining")
+        .category(&bob, "Food")
+        .friend(&alice, &bob)
+        .split(&alice, "Dining", 90.0, &[(&bob, 30.0)])
+        .build(app)
+        .await
+}
+
+async fn record_row(app: &common::TestApp, record_id: &str) -> (String, Money, String) {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT name, amount, date FROM records WHERE id = ?",
+            [record_id],
+        )
+        .await
+        .expect("query record");
+    let row = rows.next().await.expect("read row").expect("record exists");
+    (
+        row.get(0).expect("name"),
+        Money::from_cents(row.get(1).expect("amount")),
+        row.get(2).expect("date"),
+    )
+}
+
+// ---------------------------------------------------------------------------
+// S1: Participant's pending record rejects amount and date edits
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn s1_pending_split_record_rejects_amount_and_date() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = split_scenario(&app, "s1").await;
+    let bob_cookie = scenario.cookie("bob_s1");
+    let record_id = &scenario.splits[0].pending_record_ids[0];
+    let uri = format!("/records/{record_id}");
+    let before = record_row(&app, record_id).await;
+
+    for payload in [
+        json!({ "amount": 10.0 }),
+        json!({ "date": "2026-02-21" }),
+        json!({ "amount": 10.0, "date": "2026-02-21" }),
+        json!({ "name": "Dinner", "amount": 10.0 }),
+        json!({ "category_id": scenario.category_id("bob_s1", "Food"), "date": "2026-02-21" }),
+    ] {
+        let (status, body) = send_json(&app, "PUT", &uri, bob_cookie, Some(payload.clone())).await;
+        assert_eq!(status, StatusCode::CONFLICT, "{payload}");
+        assert_eq!(body["code"], ERROR_CODE_SPLIT_RECORD_IMMUTABLE, "{payload}");
+    }
+
+    assert_eq!(record_row(&app, record_id).await, before, "nothing changed");
+}
+
+// ---------------------------------------------------------------------------
+// S2: Name and category edits are allowed, as are unchanged amount and date
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn s2_split_record_allows_name_and_category() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = split_scenario(&app, "s2").await;
+    let bob_cookie = scenario.cookie("bob_s2");
+    let food_id = scenario.category_id("bob_s2", "Food");
+    let record_id = &scenario.splits[0].pending_record_ids[0];
+    let uri = format!("/records/{record_id}");
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &uri,
+        bob_cookie,
+        Some(json!({ "name": "Dinner" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["name"], "Dinner");
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &uri,
+        bob_cookie,
+        Some(json!({ "name": "Team dinner", "category_id": food_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["category_id"], food_id);
+
+    let (_, amount, date) = record_row(&app, record_id).await;
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &uri,
+        bob_cookie,
+        Some(json!({ "name": "Dinner again", "amount": amount.abs(), "date": date })),
+    )
+    .await;
+    assert_eq!(
+        status,
+        StatusCode::OK,
+        "resubmitting the same values: {body}"
+    );
+    assert_eq!(body["amount"], json!(amount));
+}
+
+// ---------------------------------------------------------------------------
+// S3: A finalized participant record keeps the same protection
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn s3_finalized_split_record_rejects_amount_and_date() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = split_scenario(&app, "s3").await;
+    let bob_cookie = scenario.cookie("bob_s3");
+    let food_id = scenario.category_id("bob_s3", "Food");
+    let record_id = &scenario.splits[0].pending_record_ids[0];
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        "/records/finalize-pending",
+        bob_cookie,
+        Some(json!({ "record_id": record_id, "category_id": food_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let uri = format!("/records/{record_id}");
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &uri,
+        bob_cookie,
+        Some(json!({ "amount": 5.0 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], ERROR_CODE_SPLIT_RECORD_IMMUTABLE, "{body}");
+
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &uri,
+        bob_cookie,
+        Some(json!({ "date": "2026-03-01" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &uri,
+        bob_cookie,
+        Some(json!({ "name": "Dinner" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+}
+
+// ---------------------------------------------------------------------------
+// S4: The payer's own split record is protected too; plain records are not
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn s4_payer_record_protected_and_plain_records_unaffected() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = split_scenario(&app, "s4").await;
+    let alice_cookie = scenario.cookie("alice_s4");
+    let dining_id = scenario.category_id("alice_s4", "Dining");
+    let payer_uri = format!("/records/{}", scenario.splits[0].payer_record_id);
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &payer_uri,
+        alice_cookie,
+        Some(json!({ "amount": 100.0 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], ERROR_CODE_SPLIT_RECORD_IMMUTABLE, "{body}");
+
+    let (status, _) = send_json(
+        &app,
+        "PUT",
+        &payer_uri,
+        alice_cookie,
+        Some(json!({ "date": "2026-01-01" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &payer_uri,
+        alice_cookie,
+        Some(json!({ "name": "Dinner out" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let (status, record) = send_json(&app, "POST", "/records", alice_cookie, Some(json!({ "name": "Coffee", "amount": 4.0, "category_id": dining_id, "date": "2026-02-20" })))
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{record}");
+    let plain_uri = format!("/records/{}", record["id"].as_str().expect("record id"));
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &plain_uri,
+        alice_cookie,
+        Some(json!({ "amount": 5.0, "date": "2026-02-21" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+}
+
+// ---------------------------------------------------------------------------
+// S5: The guard used by the bot's edit path applies the same rules
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn s5_bot_edit_guard_matches_http_rules() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = split_scenario(&app, "s5").await;
+    let bob_id = scenario.id("bob_s5");
+    let record_id = &scenario.splits[0].pending_record_ids[0];
+
+    let (_, amount, _) = record_row(&app, record_id).await;
+
+    let conn = app.state.main_db.read().await;
+    let split_id = records::fetch_record_split_id(&conn, bob_id, record_id)
+        .await
+        .expect("split id");
+    assert_eq!(
+        split_id.as_deref(),
+        Some(scenario.splits[0].split_id.as_str())
+    );
+
+    for (amount_changed, date_changed, allowed) in [
+        (false, false, true),
+        (true, false, false),
+        (false, true, false),
+        (true, true, false),
+    ] {
+        let result =
+            records::guard_split_record_edit(split_id.as_deref(), amount_changed, date_changed);
+        assert_eq!(
+            result.is_ok(),
+            allowed,
+            "amount_changed={amount_changed} date_changed={date_changed}"
+        );
+        if let Err((status, message)) = result {
+            assert_eq!(status, StatusCode::CONFLICT);
+            assert_eq!(message, SPLIT_RECORD_IMMUTABLE_MESSAGE);
+        }
+    }
+
+    assert!(!records::amount_differs(None, amount));
+    assert!(!records::amount_differs(Some(amount.abs()), amount));
+    assert!(records::amount_differs(
+        Some(amount.abs() + money(1.0)),
+        amount
+    ));
+    assert!(records::guard_split_record_edit(None, true, true).is_ok());
+
+    let (status, _) = records::fetch_record_split_id(&conn, scenario.id("alice_s5"), record_id)
+        .await
+        .expect_err("other owners cannot probe the record");
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
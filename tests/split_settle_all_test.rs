@@ -0,0 +1,199 @@
+/// Tests Z161-Z163: Settling a whole split
+///
+/// `POST /splits/{id}/settle-all` lets the initiator mark every participant record of
+/// a split as repaid in one transaction, or only one friend's with `friend_id`.
+/// Repeating it is a no-op; participants get 403, outsiders 404, and a `friend_id`
+/// without a share in the split 400.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{Scenario, ScenarioBuilder};
+use kash_server::constants::{SPLIT_STATUS_COMPLETED, SPLIT_STATUS_SETTLED};
+use kash_server::models::SettleSplitResponse;
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn send(
+    app: &common::TestApp,
+    method: &str,
+    uri: &str,
+    cookie: &str,
+    payload: Option<Value>,
+) -> (StatusCode, Value) {
+    let body = payload.map_or_else(Body::empty, |payload| Body::from(payload.to_string()));
+    let request = Request::builder()
+        .uri(uri)
+        .method(method)
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(body)
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    (status, body)
+}
+
+async fn settle_all(
+    app: &common::TestApp,
+    scenario: &Scenario,
+    user: &str,
+    payload: Value,
+) -> (StatusCode, Value) {
+    let uri = format!("/splits/{}/settle-all", scenario.splits[0].split_id);
+    send(app, "POST", &uri, scenario.cookie(user), Some(payload)).await
+}
+
+async fn settled_in_split(app: &common::TestApp, split_id: &str) -> i64 {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM records WHERE split_id = ? AND settle = 1 AND settled_at IS NOT NULL",
+            [split_id],
+        )
+        .await
+        .unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+/// Alice pays 90 with Bob and Carol, both of whom accept their share.
+async fn three_way_split(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    let carol = format!("carol_{suffix}");
+    let dave = format!("dave_{suffix}");
+    let scenario = ScenarioBuilder::new()
+        .users(&[&alice, &bob, &carol, &dave])
+        .category(&alice, "Dining")
+        .category(&bob, "Dining")
+        .category(&carol, "Dining")
+        .friend(&alice, &bob)
+        .friend(&alice, &carol)
+        .friend(&alice, &dave)
+        .split(&alice, "Dining", 90.0, &[(&bob, 30.0), (&carol, 30.0)])
+        .build(app)
+        .await;
+
+    let split = &scenario.splits[0];
+    for (user, record_id) in [&bob, &carol].into_iter().zip(&split.pending_record_ids) {
+        let (status, body) = send(
+            app,
+            "POST",
+            "/records/finalize-pending",
+            scenario.cookie(user),
+            Some(json!({
+                "record_id": record_id,
+                "category_id": scenario.category_id(user, "Dining"),
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+    }
+    scenario
+}
+
+// ---------------------------------------------------------------------------
+// Z161: Without friend_id every share is settled; a repeat is a no-op
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z161_settle_all_participants() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z161").await;
+    let split = &scenario.splits[0];
+
+    let (status, body) = settle_all(&app, &scenario, "alice_z161", json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let response: SettleSplitResponse = serde_json::from_value(body).expect("settle-all");
+    assert_eq!(response.split_id, split.split_id);
+    assert_eq!(response.status, SPLIT_STATUS_SETTLED);
+    let mut settled = response.settled_record_ids;
+    settled.sort();
+    let mut expected = split.pending_record_ids.clone();
+    expected.sort();
+    assert_eq!(settled, expected);
+    assert_eq!(settled_in_split(&app, &split.split_id).await, 2);
+
+    let (status, body) = settle_all(&app, &scenario, "alice_z161", json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let response: SettleSplitResponse = serde_json::from_value(body).expect("repeat");
+    assert_eq!(response.status, SPLIT_STATUS_SETTLED);
+    assert!(response.settled_record_ids.is_empty());
+}
+
+// ---------------------------------------------------------------------------
+// Z162: friend_id narrows the settle to that participant's shares
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z162_settle_one_friend() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z162").await;
+    let split = &scenario.splits[0];
+    let bob = json!({ "friend_id": scenario.id("bob_z162") });
+
+    let (status, body) = settle_all(&app, &scenario, "alice_z162", bob.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let response: SettleSplitResponse = serde_json::from_value(body).expect("settle bob");
+    assert_eq!(response.status, SPLIT_STATUS_COMPLETED);
+    assert_eq!(
+        response.settled_record_ids,
+        vec![split.pending_record_ids[0].clone()]
+    );
+
+    let (status, body) = settle_all(&app, &scenario, "alice_z162", bob).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["settled_record_ids"], json!([]), "{body}");
+
+    let (status, body) = settle_all(
+        &app,
+        &scenario,
+        "alice_z162",
+        json!({ "friend_id": scenario.id("carol_z162") }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["status"], SPLIT_STATUS_SETTLED, "{body}");
+    assert_eq!(settled_in_split(&app, &split.split_id).await, 2);
+}
+
+// ---------------------------------------------------------------------------
+// Z163: Initiator only; friends outside the split are rejected
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z163_settle_all_is_initiator_only() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_way_split(&app, "z163").await;
+    let split = &scenario.splits[0];
+
+    let (status, _) = settle_all(&app, &scenario, "bob_z163", json!({})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = settle_all(&app, &scenario, "dave_z163", json!({})).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    for friend_id in [
+        scenario.id("dave_z163"),
+        scenario.id("alice_z163"),
+        "nobody",
+    ] {
+        let (status, body) = settle_all(
+            &app,
+            &scenario,
+            "alice_z163",
+            json!({ "friend_id": friend_id }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{friend_id}: {body}");
+    }
+    assert_eq!(settled_in_split(&app, &split.split_id).await, 0);
+}
@@ -0,0 +1,236 @@
+/// Tests Z461-Z463: Split messages for linked Telegram chats
+///
+/// Creating a split queues a `split_created` notice in `telegram_outbox` for each
+/// participant with a linked chat, and settling a share queues `split_settled` for the
+/// other side. Notices are written after the split is committed, store only ids, and
+/// are rendered when the bot drains the outbox. `telegram_split_notifications: false`
+/// in `PUT /settings` turns them off.
+mod common;
+
+use axum::http::StatusCode;
+use common::fixtures::{Scenario, ScenarioBuilder};
+use common::send_json;
+use kash_server::constants::*;
+use kash_server::money::Money;
+use kash_server::outbox::{
+    OutboxMessage, due_split_notice_messages, format_split_notice, mark_delivered,
+};
+use serde_json::{Value, json};
+use time::{Duration, OffsetDateTime};
+
+// ---- Helpers ----
+
+async fn link_telegram(app: &common::TestApp, user_id: &str, chat_id: &str) {
+    let conn = app.state.main_db.write().await;
+    conn.execute(
+        "INSERT INTO telegram_users (telegram_user_id, user_id, chat_id, created_at) VALUES (?, ?, ?, 0)",
+        (chat_id, user_id, chat_id),
+    )
+    .await
+    .unwrap();
+}
+
+/// Due split notices, delivered so the next call only sees new ones.
+async fn drain(app: &common::TestApp) -> Vec<OutboxMessage> {
+    let now = OffsetDateTime::now_utc() + Duration::seconds(1);
+    let conn = app.state.main_db.write().await;
+    let messages = due_split_notice_messages(&conn, now).await.expect("due");
+    for message in &messages {
+        mark_delivered(&conn, &message.entry_ids, now)
+            .await
+            .expect("deliver");
+    }
+    messages
+}
+
+fn sent(messages: &[OutboxMessage]) -> Vec<(&str, &str)> {
+    messages
+        .iter()
+        .flat_map(|message| {
+            message
+                .chat_ids
+                .iter()
+                .map(|chat_id| (chat_id.as_str(), message.text.as_str()))
+        })
+        .collect()
+}
+
+async fn create_split(app: &common::TestApp, scenario: &Scenario, suffix: &str) -> Value {
+    let alice = format!("alice_{suffix}");
+    let (status, body) = send_json(
+        app,
+        "POST",
+        "/splits/create",
+        scenario.cookie(&alice),
+        Some(json!({
+            "idempotency_key": format!("{suffix}-{}", uuid::Uuid::new_v4()),
+            "total_amount": 90.0,
+            "description": "Dinner",
+            "date": "2025-10-05",
+            "category_id": scenario.category_id(&alice, "Dining"),
+            "splits": [
+                { "user_id": scenario.id(&format!("bob_{suffix}")), "amount": 30.0 },
+                { "user_id": scenario.id(&format!("carol_{suffix}")), "amount": 25.5 },
+            ],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    body
+}
+
+async fn three_friends(app: &common::TestApp, suffix: &str) -> Scenario {
+    let alice = format!("alice_{suffix}");
+    let bob = format!("bob_{suffix}");
+    let carol = format!("carol_{suffix}");
+    ScenarioBuilder::new()
+        .users(&[&alice, &bob, &carol])
+        .category(&alice, "Dining")
+        .friend(&alice, &bob)
+        .friend(&alice, &carol)
+        .build(app)
+        .await
+}
+
+// ---------------------------------------------------------------------------
+// Z461: Notice wording
+// ---------------------------------------------------------------------------
+
+#[test]
+fn z461_split_notice_text() {
+    let share = Money::from_cents(-3000);
+    assert_eq!(
+        format_split_notice(
+            OUTBOX_KIND_SPLIT_CREATED,
+            "Alice",
+            "dinner",
+            share,
+            "TWD",
+            true
+        ),
+        "Alice added you to 'dinner' — you owe NT$30. Reply here to categorize it."
+    );
+    assert_eq!(
+        format_split_notice(
+            OUTBOX_KIND_SPLIT_SETTLED,
+            "Alice",
+            "dinner",
+            Money::from_cents(-2550),
+            "USD",
+            true
+        ),
+        "Alice marked your share of 'dinner' ($25.50) as settled."
+    );
+    assert_eq!(
+        format_split_notice(
+            OUTBOX_KIND_SPLIT_SETTLED,
+            "Bob",
+            "dinner",
+            share,
+            "TWD",
+            false
+        ),
+        "Bob settled their share of 'dinner' (NT$30)."
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Z462: Linked participants hear about new and settled splits
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z462_linked_participants_get_split_messages() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_friends(&app, "z462").await;
+    link_telegram(&app, scenario.id("alice_z462"), "4620").await;
+    link_telegram(&app, scenario.id("bob_z462"), "4621").await;
+
+    let created = create_split(&app, &scenario, "z462").await;
+    let messages = drain(&app).await;
+    assert_eq!(
+        sent(&messages),
+        vec![(
+            "4621",
+            "alice_z462 added you to 'Dinner' — you owe NT$30. Reply here to categorize it."
+        )],
+        "Carol has no linked chat"
+    );
+
+    // Bob settling his own share tells Alice
+    let bob_record = created["pending_record_ids"][0].as_str().unwrap();
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        &format!("/records/{bob_record}/settle"),
+        scenario.cookie("bob_z462"),
+        Some(json!({ "split_id": created["split_id"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        sent(&drain(&app).await),
+        vec![("4620", "bob_z462 settled their share of 'Dinner' (NT$30).")]
+    );
+
+    // A split cancelled before delivery is dropped unsent
+    let created = create_split(&app, &scenario, "z462").await;
+    let uri = format!("/splits/{}/cancel", created["split_id"].as_str().unwrap());
+    let (status, body) = send_json(&app, "POST", &uri, scenario.cookie("alice_z462"), None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let messages = drain(&app).await;
+    assert_eq!(messages.len(), 1);
+    assert!(sent(&messages).is_empty());
+    assert!(drain(&app).await.is_empty());
+}
+
+// ---------------------------------------------------------------------------
+// Z463: The setting turns split messages off
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z463_opted_out_users_get_no_split_messages() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = three_friends(&app, "z463").await;
+    link_telegram(&app, scenario.id("bob_z463"), "4631").await;
+    link_telegram(&app, scenario.id("carol_z463"), "4632").await;
+    let bob = scenario.cookie("bob_z463");
+
+    let (status, settings) = send_json(&app, "GET", "/settings", bob, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(settings["telegram_split_notifications"], Value::Null);
+
+    let (status, settings) = send_json(
+        &app,
+        "PUT",
+        "/settings",
+        bob,
+        Some(json!({ "telegram_split_notifications": false })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{settings}");
+    assert_eq!(settings["telegram_split_notifications"], json!(false));
+
+    create_split(&app, &scenario, "z463").await;
+    let messages = drain(&app).await;
+    assert_eq!(
+        messages
+            .iter()
+            .map(|message| message.chat_ids.clone())
+            .collect::<Vec<_>>(),
+        vec![vec!["4632".to_string()]],
+        "only Carol's message is queued"
+    );
+
+    let (status, settings) = send_json(
+        &app,
+        "PUT",
+        "/settings",
+        bob,
+        Some(json!({ "telegram_split_notifications": null })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{settings}");
+    assert_eq!(settings["telegram_split_notifications"], Value::Null);
+    create_split(&app, &scenario, "z463").await;
+    assert_eq!(drain(&app).await.len(), 2, "back on by default");
+}
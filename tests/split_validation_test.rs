@@ -1,21 +1,28 @@
-use kash_server::models::SplitParticipant;
+use kash_server::models::{SplitMode, SplitParticipant};
+use kash_server::money::{Money, MoneyError};
 use kash_server::utils::{calculate_split_amounts, validate_split_participants};
 
+fn money(amount: f64) -> Money {
+    Money::from_decimal(amount).expect("whole cents")
+}
+
 #[test]
 fn test_validate_split_participants_exact_match() {
     let splits = vec![
         SplitParticipant {
             user_id: "B".to_string(),
-            amount: 300.0,
+            amount: money(300.0),
+            percentage: None,
         },
         SplitParticipant {
             user_id: "C".to_string(),
-            amount: 200.0,
+            amount: money(200.0),
+            percentage: None,
         },
     ];
     let initiator = "A";
 
-    let result = validate_split_participants(&splits, initiator);
+    let result = validate_split_participants(&splits, initiator, SplitMode::Exact);
     assert!(
         result.is_ok(),
         "Valid splits should pass validation: {:?}",
@@ -28,16 +35,18 @@ fn test_validate_split_participants_with_remainder() {
     let splits = vec![
         SplitParticipant {
             user_id: "B".to_string(),
-            amount: 350.0,
+            amount: money(350.0),
+            percentage: None,
         },
         SplitParticipant {
             user_id: "C".to_string(),
-            amount: 250.0,
+            amount: money(250.0),
+            percentage: None,
         },
     ];
     let initiator = "A";
 
-    let result = validate_split_participants(&splits, initiator);
+    let result = validate_split_participants(&splits, initiator, SplitMode::Exact);
     assert!(
         result.is_ok(),
         "Valid splits with remainder should pass validation: {:?}",
@@ -50,16 +59,18 @@ fn test_validate_split_participants_duplicate_participant() {
     let splits = vec![
         SplitParticipant {
             user_id: "B".to_string(),
-            amount: 300.0,
+            amount: money(300.0),
+            percentage: None,
         },
         SplitParticipant {
             user_id: "B".to_string(),
-            amount: 200.0,
+            amount: money(200.0),
+            percentage: None,
         },
     ];
     let initiator = "A";
 
-    let result = validate_split_participants(&splits, initiator);
+    let result = validate_split_participants(&splits, initiator, SplitMode::Exact);
     assert!(
         result.is_err(),
         "Duplicate participants should fail validation"
@@ -72,91 +83,144 @@ fn test_validate_split_participants_initiator_in_splits() {
     let splits = vec![
         SplitParticipant {
             user_id: "A".to_string(),
-            amount: 300.0,
+            amount: money(300.0),
+            percentage: None,
         },
         SplitParticipant {
             user_id: "B".to_string(),
-            amount: 200.0,
+            amount: money(200.0),
+            percentage: None,
         },
     ];
     let initiator = "A";
 
-    let result = validate_split_participants(&splits, initiator);
-    assert!(
-        result.is_err(),
-        "Initiator appearing in splits should fail validation"
-    );
+    // Once, the entry is the initiator's own share
+    let result = validate_split_participants(&splits, initiator, SplitMode::Exact);
+    assert!(result.is_ok(), "{:?}", result.err());
+
+    let mut twice = splits.clone();
+    twice.push(splits[0].clone());
+    let result = validate_split_participants(&twice, initiator, SplitMode::Exact);
     assert_eq!(result.unwrap_err(), "Duplicate participant: A");
+
+    let mut negative = splits;
+    negative[0].amount = money(-1.0);
+    let result = validate_split_participants(&negative, initiator, SplitMode::Exact);
+    assert_eq!(
+        result.unwrap_err(),
+        "Participant A: amount must not be negative"
+    );
 }
 
 #[test]
-fn test_validate_split_participants_negative_amount() {
-    let splits = vec![SplitParticipant {
-        user_id: "B".to_string(),
-        amount: -100.0,
-    }];
-    let initiator = "A";
+fn test_calculate_split_amounts_with_initiator_share() {
+    let share = |user_id: &str, amount: f64| SplitParticipant {
+        user_id: user_id.to_string(),
+        amount: money(amount),
+        percentage: None,
+    };
+
+    let amounts = calculate_split_amounts(
+        money(90.0),
+        vec![share("B", 30.0), share("A", 40.0), share("C", 20.0)],
+        "A",
+        SplitMode::Exact,
+    )
+    .expect("explicit share");
+    assert_eq!(
+        amounts,
+        vec![
+            ("A".to_string(), money(40.0)),
+            ("B".to_string(), money(30.0)),
+            ("C".to_string(), money(20.0)),
+        ]
+    );
 
-    let result = validate_split_participants(&splits, initiator);
-    assert!(result.is_err(), "Negative amounts should fail validation");
-    assert_eq!(result.unwrap_err(), "Amount must be positive");
+    // A zero own share means the initiator only paid for the others
+    let amounts = calculate_split_amounts(
+        money(50.0),
+        vec![share("A", 0.0), share("B", 50.0)],
+        "A",
+        SplitMode::Exact,
+    )
+    .expect("zero own share");
+    assert_eq!(amounts[0], ("A".to_string(), Money::ZERO));
+
+    let result = calculate_split_amounts(
+        money(90.0),
+        vec![share("A", 40.0), share("B", 30.0)],
+        "A",
+        SplitMode::Exact,
+    );
+    assert_eq!(
+        result.unwrap_err(),
+        "Split amounts must add up to the total of 90 with the initiator's share, not 70"
+    );
 }
 
 #[test]
-fn test_validate_split_participants_zero_amount() {
+fn test_validate_split_participants_negative_amount() {
     let splits = vec![SplitParticipant {
         user_id: "B".to_string(),
-        amount: 0.0,
+        amount: money(-100.0),
+        percentage: None,
     }];
     let initiator = "A";
 
-    let result = validate_split_participants(&splits, initiator);
-    assert!(result.is_err(), "Zero amounts should fail validation");
-    assert_eq!(result.unwrap_err(), "Amount must be positive");
+    let result = validate_split_participants(&splits, initiator, SplitMode::Exact);
+    assert!(result.is_err(), "Negative amounts should fail validation");
+    assert_eq!(
+        result.unwrap_err(),
+        "Participant B: amount must be positive"
+    );
 }
 
 #[test]
-fn test_validate_split_participants_nan_amount() {
+fn test_validate_split_participants_zero_amount() {
     let splits = vec![SplitParticipant {
         user_id: "B".to_string(),
-        amount: f64::NAN,
+        amount: money(0.0),
+        percentage: None,
     }];
     let initiator = "A";
 
-    let result = validate_split_participants(&splits, initiator);
-    assert!(result.is_err(), "NaN amounts should fail validation");
-    assert_eq!(result.unwrap_err(), "Amount must be a valid finite number");
+    let result = validate_split_participants(&splits, initiator, SplitMode::Exact);
+    assert!(result.is_err(), "Zero amounts should fail validation");
+    assert_eq!(
+        result.unwrap_err(),
+        "Participant B: amount must be positive"
+    );
 }
 
 #[test]
-fn test_validate_split_participants_infinity_amount() {
-    let splits = vec![SplitParticipant {
-        user_id: "B".to_string(),
-        amount: f64::INFINITY,
-    }];
-    let initiator = "A";
-
-    let result = validate_split_participants(&splits, initiator);
-    assert!(result.is_err(), "Infinity amounts should fail validation");
-    assert_eq!(result.unwrap_err(), "Amount must be a valid finite number");
+fn test_split_amounts_must_be_whole_cents() {
+    assert_eq!(Money::from_decimal(f64::NAN), Err(MoneyError::Invalid));
+    assert_eq!(Money::from_decimal(f64::INFINITY), Err(MoneyError::Invalid));
+    assert_eq!("33.333".parse::<Money>(), Err(MoneyError::TooPrecise));
+    assert_eq!("33.30".parse::<Money>(), Ok(Money::from_cents(3330)));
+
+    let result = serde_json::from_str::<SplitParticipant>(r#"{"user_id":"B","amount":10.005}"#);
+    assert!(result.is_err(), "sub-cent shares are rejected when parsed");
 }
 
 #[test]
 fn test_calculate_split_amounts_exact_match() {
-    let total = 1000.0;
+    let total = money(1000.0);
     let splits = vec![
         SplitParticipant {
             user_id: "B".to_string(),
-            amount: 300.0,
+            amount: money(300.0),
+            percentage: None,
         },
         SplitParticipant {
             user_id: "C".to_string(),
-            amount: 200.0,
+            amount: money(200.0),
+            percentage: None,
         },
     ];
     let initiator = "A";
 
-    let result = calculate_split_amounts(total, splits, initiator);
+    let result = calculate_split_amounts(total, splits, initiator, SplitMode::Exact);
     assert!(result.is_ok(), "Exact match should succeed");
 
     let amounts = result.unwrap();
@@ -170,29 +234,39 @@ fn test_calculate_split_amounts_exact_match() {
 
     assert_eq!(
         map.get("A").copied(),
-        Some(500.0),
+        Some(money(500.0)),
         "Initiator should get 500.0"
     );
-    assert_eq!(map.get("B").copied(), Some(300.0), "B should get 300.0");
-    assert_eq!(map.get("C").copied(), Some(200.0), "C should get 200.0");
+    assert_eq!(
+        map.get("B").copied(),
+        Some(money(300.0)),
+        "B should get 300.0"
+    );
+    assert_eq!(
+        map.get("C").copied(),
+        Some(money(200.0)),
+        "C should get 200.0"
+    );
 }
 
 #[test]
 fn test_calculate_split_amounts_with_remainder() {
-    let total = 1000.0;
+    let total = money(1000.0);
     let splits = vec![
         SplitParticipant {
             user_id: "B".to_string(),
-            amount: 350.0,
+            amount: money(350.0),
+            percentage: None,
         },
         SplitParticipant {
             user_id: "C".to_string(),
-            amount: 250.0,
+            amount: money(250.0),
+            percentage: None,
         },
     ];
     let initiator = "A";
 
-    let result = calculate_split_amounts(total, splits, initiator);
+    let result = calculate_split_amounts(total, splits, initiator, SplitMode::Exact);
     assert!(result.is_ok(), "Remainder case should succeed");
 
     let amounts = result.unwrap();
@@ -205,47 +279,58 @@ fn test_calculate_split_amounts_with_remainder() {
 
     assert_eq!(
         map.get("A").copied(),
-        Some(400.0),
+        Some(money(400.0)),
         "Initiator should get 400.0"
     );
-    assert_eq!(map.get("B").copied(), Some(350.0), "B should get 350.0");
-    assert_eq!(map.get("C").copied(), Some(250.0), "C should get 250.0");
+    assert_eq!(
+        map.get("B").copied(),
+        Some(money(350.0)),
+        "B should get 350.0"
+    );
+    assert_eq!(
+        map.get("C").copied(),
+        Some(money(250.0)),
+        "C should get 250.0"
+    );
 
     // Verify sum equals total
-    let sum: f64 = map.values().sum();
-    assert_eq!(sum, 1000.0, "Sum should equal total");
+    let sum: Money = map.values().copied().sum();
+    assert_eq!(sum, money(1000.0), "Sum should equal total");
 }
 
 #[test]
 fn test_calculate_split_amounts_exceeds_total() {
-    let total = 1000.0;
+    let total = money(1000.0);
     let splits = vec![SplitParticipant {
         user_id: "B".to_string(),
-        amount: 1200.0,
+        amount: money(1200.0),
+        percentage: None,
     }];
     let initiator = "A";
 
-    let result = calculate_split_amounts(total, splits, initiator);
+    let result = calculate_split_amounts(total, splits, initiator, SplitMode::Exact);
     assert!(result.is_err(), "Sum exceeding total should fail");
     assert_eq!(result.unwrap_err(), "Split sum exceeds total");
 }
 
 #[test]
 fn test_calculate_split_amounts_sum_equals_total() {
-    let total = 1000.0;
+    let total = money(1000.0);
     let splits = vec![
         SplitParticipant {
             user_id: "B".to_string(),
-            amount: 600.0,
+            amount: money(600.0),
+            percentage: None,
         },
         SplitParticipant {
             user_id: "C".to_string(),
-            amount: 400.0,
+            amount: money(400.0),
+            percentage: None,
         },
     ];
     let initiator = "A";
 
-    let result = calculate_split_amounts(total, splits, initiator);
+    let result = calculate_split_amounts(total, splits, initiator, SplitMode::Exact);
     assert!(result.is_ok(), "Sum equal to total should succeed");
 
     let amounts = result.unwrap();
@@ -254,28 +339,42 @@ fn test_calculate_split_amounts_sum_equals_total() {
         map.insert(user_id, amount);
     }
 
-    assert_eq!(map.get("A").copied(), Some(0.0), "Initiator should get 0.0");
-    assert_eq!(map.get("B").copied(), Some(600.0), "B should get 600.0");
-    assert_eq!(map.get("C").copied(), Some(400.0), "C should get 400.0");
+    assert_eq!(
+        map.get("A").copied(),
+        Some(money(0.0)),
+        "Initiator should get 0.0"
+    );
+    assert_eq!(
+        map.get("B").copied(),
+        Some(money(600.0)),
+        "B should get 600.0"
+    );
+    assert_eq!(
+        map.get("C").copied(),
+        Some(money(400.0)),
+        "C should get 400.0"
+    );
 }
 
 #[test]
-fn test_calculate_split_amounts_floating_point_rounding() {
-    let total = 100.0;
+fn test_calculate_split_amounts_cent_remainder() {
+    let total = money(100.0);
     let splits = vec![
         SplitParticipant {
             user_id: "B".to_string(),
-            amount: 33.33,
+            amount: money(33.33),
+            percentage: None,
         },
         SplitParticipant {
             user_id: "C".to_string(),
-            amount: 33.33,
+            amount: money(33.33),
+            percentage: None,
         },
     ];
     let initiator = "A";
 
-    let result = calculate_split_amounts(total, splits, initiator);
-    assert!(result.is_ok(), "Floating point amounts should succeed");
+    let result = calculate_split_amounts(total, splits, initiator, SplitMode::Exact);
+    assert!(result.is_ok(), "Cent amounts should succeed");
 
     let amounts = result.unwrap();
     let mut map = std::collections::HashMap::new();
@@ -287,26 +386,30 @@ fn test_calculate_split_amounts_floating_point_rounding() {
     let c_amount = map.get("C").copied().unwrap();
     let a_amount = map.get("A").copied().unwrap();
 
-    // Verify amounts are rounded to 2 decimals
-    assert_eq!(b_amount, 33.33, "B amount should be 33.33");
-    assert_eq!(c_amount, 33.33, "C amount should be 33.33");
-    assert_eq!(a_amount, 33.34, "A (initiator) should get the remainder");
+    assert_eq!(b_amount, money(33.33), "B amount should be 33.33");
+    assert_eq!(c_amount, money(33.33), "C amount should be 33.33");
+    assert_eq!(
+        a_amount,
+        money(33.34),
+        "A (initiator) should get the remainder"
+    );
 
     // Verify sum equals total
     let sum = a_amount + b_amount + c_amount;
-    assert_eq!(sum, 100.0, "Sum should equal total");
+    assert_eq!(sum, money(100.0), "Sum should equal total");
 }
 
 #[test]
 fn test_calculate_split_amounts_single_participant() {
-    let total = 500.0;
+    let total = money(500.0);
     let splits = vec![SplitParticipant {
         user_id: "B".to_string(),
-        amount: 200.0,
+        amount: money(200.0),
+        percentage: None,
     }];
     let initiator = "A";
 
-    let result = calculate_split_amounts(total, splits, initiator);
+    let result = calculate_split_amounts(total, splits, initiator, SplitMode::Exact);
     assert!(result.is_ok(), "Single participant should succeed");
 
     let amounts = result.unwrap();
@@ -319,32 +422,39 @@ fn test_calculate_split_amounts_single_participant() {
 
     assert_eq!(
         map.get("A").copied(),
-        Some(300.0),
+        Some(money(300.0)),
         "Initiator should get 300.0"
     );
-    assert_eq!(map.get("B").copied(), Some(200.0), "B should get 200.0");
+    assert_eq!(
+        map.get("B").copied(),
+        Some(money(200.0)),
+        "B should get 200.0"
+    );
 }
 
 #[test]
 fn test_calculate_split_amounts_multiple_participants() {
-    let total = 1500.0;
+    let total = money(1500.0);
     let splits = vec![
         SplitParticipant {
             user_id: "B".to_string(),
-            amount: 400.0,
+            amount: money(400.0),
+            percentage: None,
         },
         SplitParticipant {
             user_id: "C".to_string(),
-            amount: 350.0,
+            amount: money(350.0),
+            percentage: None,
         },
         SplitParticipant {
             user_id: "D".to_string(),
-            amount: 300.0,
+            amount: money(300.0),
+            percentage: None,
         },
     ];
     let initiator = "A";
 
-    let result = calculate_split_amounts(total, splits, initiator);
+    let result = calculate_split_amounts(total, splits, initiator, SplitMode::Exact);
     assert!(result.is_ok(), "Multiple participants should succeed");
 
     let amounts = result.unwrap();
@@ -357,73 +467,76 @@ fn test_calculate_split_amounts_multiple_participants() {
 
     assert_eq!(
         map.get("A").copied(),
-        Some(450.0),
+        Some(money(450.0)),
         "Initiator should get 450.0"
     );
-    assert_eq!(map.get("B").copied(), Some(400.0), "B should get 400.0");
-    assert_eq!(map.get("C").copied(), Some(350.0), "C should get 350.0");
-    assert_eq!(map.get("D").copied(), Some(300.0), "D should get 300.0");
+    assert_eq!(
+        map.get("B").copied(),
+        Some(money(400.0)),
+        "B should get 400.0"
+    );
+    assert_eq!(
+        map.get("C").copied(),
+        Some(money(350.0)),
+        "C should get 350.0"
+    );
+    assert_eq!(
+        map.get("D").copied(),
+        Some(money(300.0)),
+        "D should get 300.0"
+    );
 
-    let sum: f64 = map.values().sum();
-    assert_eq!(sum, 1500.0, "Sum should equal total");
+    let sum: Money = map.values().copied().sum();
+    assert_eq!(sum, money(1500.0), "Sum should equal total");
 }
 
 #[test]
 fn test_calculate_split_amounts_invalid_total() {
-    let total = -1000.0;
+    let total = money(-1000.0);
     let splits = vec![SplitParticipant {
         user_id: "B".to_string(),
-        amount: 300.0,
+        amount: money(300.0),
+        percentage: None,
     }];
     let initiator = "A";
 
-    let result = calculate_split_amounts(total, splits, initiator);
+    let result = calculate_split_amounts(total, splits, initiator, SplitMode::Exact);
     assert!(result.is_err(), "Negative total should fail");
 }
 
 #[test]
 fn test_calculate_split_amounts_zero_total() {
-    let total = 0.0;
+    let total = money(0.0);
     let splits = vec![SplitParticipant {
         user_id: "B".to_string(),
-        amount: 300.0,
+        amount: money(300.0),
+        percentage: None,
     }];
     let initiator = "A";
 
-    let result = calculate_split_amounts(total, splits, initiator);
+    let result = calculate_split_amounts(total, splits, initiator, SplitMode::Exact);
     assert!(result.is_err(), "Zero total should fail");
 }
 
-#[test]
-fn test_calculate_split_amounts_nan_total() {
-    let total = f64::NAN;
-    let splits = vec![SplitParticipant {
-        user_id: "B".to_string(),
-        amount: 300.0,
-    }];
-    let initiator = "A";
-
-    let result = calculate_split_amounts(total, splits, initiator);
-    assert!(result.is_err(), "NaN total should fail");
-}
-
 #[test]
 fn test_remainder_to_initiator_happy_path() {
     // This test verifies the core requirement: remainder goes to initiator
-    let total = 1000.0;
+    let total = money(1000.0);
     let splits = vec![
         SplitParticipant {
             user_id: "B".to_string(),
-            amount: 350.0,
+            amount: money(350.0),
+            percentage: None,
         },
         SplitParticipant {
             user_id: "C".to_string(),
-            amount: 250.0,
+            amount: money(250.0),
+            percentage: None,
         },
     ];
     let initiator = "A";
 
-    let result = calculate_split_amounts(total, splits, initiator);
+    let result = calculate_split_amounts(total, splits, initiator, SplitMode::Exact);
     assert!(result.is_ok());
 
     let amounts = result.unwrap();
@@ -435,7 +548,7 @@ fn test_remainder_to_initiator_happy_path() {
     // 350 + 250 = 600, remainder = 1000 - 600 = 400 goes to initiator
     assert_eq!(
         map.get("A").copied(),
-        Some(400.0),
+        Some(money(400.0)),
         "Remainder must go to initiator"
     );
 }
@@ -445,25 +558,222 @@ fn test_split_validation_and_calculation_integration() {
     let splits = vec![
         SplitParticipant {
             user_id: "B".to_string(),
-            amount: 300.0,
+            amount: money(300.0),
+            percentage: None,
         },
         SplitParticipant {
             user_id: "C".to_string(),
-            amount: 200.0,
+            amount: money(200.0),
+            percentage: None,
         },
     ];
     let initiator = "A";
-    let total = 1000.0;
+    let total = money(1000.0);
 
     // First validate
-    let validation_result = validate_split_participants(&splits, initiator);
+    let validation_result = validate_split_participants(&splits, initiator, SplitMode::Exact);
     assert!(validation_result.is_ok());
 
     // Then calculate
-    let calc_result = calculate_split_amounts(total, splits, initiator);
+    let calc_result = calculate_split_amounts(total, splits, initiator, SplitMode::Exact);
     assert!(calc_result.is_ok());
 
     let amounts = calc_result.unwrap();
-    let sum: f64 = amounts.iter().map(|(_, amt)| amt).sum();
+    let sum: Money = amounts.iter().map(|(_, amt)| *amt).sum();
     assert_eq!(sum, total, "All amounts must sum to total");
 }
+
+fn equal_share(user_id: &str) -> SplitParticipant {
+    SplitParticipant {
+        user_id: user_id.to_string(),
+        amount: Money::ZERO,
+        percentage: None,
+    }
+}
+
+fn percentage_share(user_id: &str, percentage: f64) -> SplitParticipant {
+    SplitParticipant {
+        user_id: user_id.to_string(),
+        amount: Money::ZERO,
+        percentage: Some(percentage),
+    }
+}
+
+#[test]
+fn test_equal_split_gives_leftover_cents_to_initiator_first() {
+    let amounts = calculate_split_amounts(
+        money(100.0),
+        vec![equal_share("B"), equal_share("C")],
+        "A",
+        SplitMode::Equal,
+    )
+    .expect("equal split");
+    assert_eq!(
+        amounts,
+        vec![
+            ("A".to_string(), money(33.34)),
+            ("B".to_string(), money(33.33)),
+            ("C".to_string(), money(33.33)),
+        ]
+    );
+
+    // Two leftover cents go to the initiator and then the first participant
+    let amounts = calculate_split_amounts(
+        money(0.11),
+        vec![equal_share("B"), equal_share("C")],
+        "A",
+        SplitMode::Equal,
+    )
+    .expect("equal split");
+    let shares: Vec<Money> = amounts.iter().map(|(_, amount)| *amount).collect();
+    assert_eq!(shares, vec![money(0.04), money(0.04), money(0.03)]);
+
+    let result = calculate_split_amounts(
+        money(0.02),
+        vec![equal_share("B"), equal_share("C")],
+        "A",
+        SplitMode::Equal,
+    );
+    assert_eq!(
+        result.unwrap_err(),
+        "Participant C: share comes to less than a cent"
+    );
+}
+
+#[test]
+fn test_equal_split_rejects_amounts_and_percentages() {
+    let mut with_amount = equal_share("C");
+    with_amount.amount = money(10.0);
+    let result =
+        validate_split_participants(&[equal_share("B"), with_amount], "A", SplitMode::Equal);
+    assert_eq!(
+        result.unwrap_err(),
+        "Participant C: equal splits take no amount or percentage"
+    );
+
+    let result = validate_split_participants(&[percentage_share("B", 50.0)], "A", SplitMode::Equal);
+    assert!(result.unwrap_err().starts_with("Participant B: "));
+
+    let result = validate_split_participants(&[equal_share("A")], "A", SplitMode::Equal);
+    assert_eq!(result.unwrap_err(), "Duplicate participant: A");
+}
+
+#[test]
+fn test_percentage_split_with_initiator_entry() {
+    let amounts = calculate_split_amounts(
+        money(100.0),
+        vec![
+            percentage_share("B", 33.33),
+            percentage_share("A", 33.34),
+            percentage_share("C", 33.33),
+        ],
+        "A",
+        SplitMode::Percentage,
+    )
+    .expect("percentage split");
+    assert_eq!(
+        amounts,
+        vec![
+            ("A".to_string(), money(33.34)),
+            ("B".to_string(), money(33.33)),
+            ("C".to_string(), money(33.33)),
+        ]
+    );
+
+    // Without an entry the initiator's share is zero
+    let amounts = calculate_split_amounts(
+        money(10.0),
+        vec![percentage_share("B", 12.5), percentage_share("C", 87.5)],
+        "A",
+        SplitMode::Percentage,
+    )
+    .expect("percentage split");
+    assert_eq!(
+        amounts,
+        vec![
+            ("A".to_string(), Money::ZERO),
+            ("B".to_string(), money(1.25)),
+            ("C".to_string(), money(8.75)),
+        ]
+    );
+}
+
+#[test]
+fn test_percentage_split_rounds_deterministically() {
+    // 1/3 each of 0.10: the odd cent goes to the largest remainder, ties to the initiator
+    let amounts = calculate_split_amounts(
+        money(0.1),
+        vec![
+            percentage_share("A", 33.34),
+            percentage_share("B", 33.33),
+            percentage_share("C", 33.33),
+        ],
+        "A",
+        SplitMode::Percentage,
+    )
+    .expect("percentage split");
+    let sum: Money = amounts.iter().map(|(_, amount)| *amount).sum();
+    assert_eq!(sum, money(0.1));
+    assert_eq!(amounts[0], ("A".to_string(), money(0.04)));
+}
+
+#[test]
+fn test_percentage_split_validation_names_participant() {
+    for (percentage, valid) in [
+        (0.0, false),
+        (-5.0, false),
+        (100.01, false),
+        (12.345, false),
+        (f64::NAN, false),
+        (0.01, true),
+        (100.0, true),
+    ] {
+        let result = validate_split_participants(
+            &[percentage_share("B", percentage)],
+            "A",
+            SplitMode::Percentage,
+        );
+        assert_eq!(result.is_ok(), valid, "{percentage}");
+        if let Err(message) = result {
+            assert!(message.starts_with("Participant B: "), "{message}");
+        }
+    }
+
+    let result = validate_split_participants(&[equal_share("B")], "A", SplitMode::Percentage);
+    assert_eq!(
+        result.unwrap_err(),
+        "Participant B: percentage is required in percentage mode"
+    );
+    let result = validate_split_participants(
+        &[percentage_share("A", 50.0), percentage_share("A", 50.0)],
+        "A",
+        SplitMode::Percentage,
+    );
+    assert_eq!(result.unwrap_err(), "Duplicate participant: A");
+    let result = validate_split_participants(&[percentage_share("B", 50.0)], "A", SplitMode::Exact);
+    assert_eq!(
+        result.unwrap_err(),
+        "Participant B: percentage is only used in percentage mode"
+    );
+
+    let result = calculate_split_amounts(
+        money(100.0),
+        vec![percentage_share("B", 60.0), percentage_share("C", 30.0)],
+        "A",
+        SplitMode::Percentage,
+    );
+    assert_eq!(
+        result.unwrap_err(),
+        "Percentages must add up to 100, not 90"
+    );
+    let result = calculate_split_amounts(
+        money(0.5),
+        vec![percentage_share("B", 99.0), percentage_share("C", 1.0)],
+        "A",
+        SplitMode::Percentage,
+    );
+    assert_eq!(
+        result.unwrap_err(),
+        "Participant C: share comes to less than a cent"
+    );
+}
@@ -0,0 +1,133 @@
+/// Tests T1-T3: Startup self-test
+///
+/// Before binding the listener the server runs one create-category,
+/// create-record, read-back, delete cycle as the reserved `__selftest__` user.
+/// A broken schema aborts startup with the failing step, and the reserved user
+/// never shows up in the API: it is purged after the run, refused at
+/// registration and login, and excluded from user search.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use kash_server::auth;
+use kash_server::constants::SELFTEST_USERNAME;
+use kash_server::selftest::run_startup_self_test;
+use serde_json::json;
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn count_rows(app: &common::TestApp, sql: &str) -> i64 {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn.query(sql, ()).await.expect("count query");
+    let row = rows.next().await.expect("read row").expect("count row");
+    row.get(0).expect("count")
+}
+
+async fn post_json(app: &common::TestApp, uri: &str, payload: serde_json::Value) -> StatusCode {
+    let request = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(payload.to_string()))
+        .expect("build request");
+    app.router
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("execute request")
+        .status()
+}
+
+// ---------------------------------------------------------------------------
+// T1: The self-test passes on a fresh database and leaves nothing behind
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn t1_self_test_passes_and_cleans_up() {
+    let app = common::setup_test_app().await.expect("setup failed");
+
+    run_startup_self_test(&app.state.main_db)
+        .await
+        .expect("self-test should pass");
+    // A second run starts from a clean slate, as on the next deploy.
+    run_startup_self_test(&app.state.main_db)
+        .await
+        .expect("self-test should pass again");
+
+    assert_eq!(count_rows(&app, "SELECT COUNT(*) FROM users").await, 0);
+    assert_eq!(count_rows(&app, "SELECT COUNT(*) FROM categories").await, 0);
+    assert_eq!(count_rows(&app, "SELECT COUNT(*) FROM records").await, 0);
+}
+
+// ---------------------------------------------------------------------------
+// T2: A broken records table fails the self-test at the record step
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn t2_broken_schema_fails_with_step() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute("ALTER TABLE records DROP COLUMN original_currency", ())
+            .await
+            .expect("drop column");
+    }
+
+    let error = run_startup_self_test(&app.state.main_db)
+        .await
+        .expect_err("self-test should fail");
+    assert_eq!(error.step, "create record");
+    assert!(
+        error.to_string().contains("create record"),
+        "error names the step: {error}"
+    );
+
+    assert_eq!(count_rows(&app, "SELECT COUNT(*) FROM users").await, 0);
+    assert_eq!(count_rows(&app, "SELECT COUNT(*) FROM categories").await, 0);
+}
+
+// ---------------------------------------------------------------------------
+// T3: The reserved user cannot register, log in or be found
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn t3_reserved_user_is_invisible() {
+    let app = common::setup_test_app().await.expect("setup failed");
+
+    let status = post_json(
+        &app,
+        "/auth/register",
+        json!({ "username": SELFTEST_USERNAME, "password": "password123" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // As left behind by a self-test interrupted mid-run.
+    auth::create_user(&app.state.main_db, SELFTEST_USERNAME, "password123")
+        .await
+        .expect("create reserved user");
+    common::create_test_user(&app.state, "searcher_t3", "password123")
+        .await
+        .expect("create searcher");
+    let cookie = common::login_user(&app.router, "searcher_t3", "password123")
+        .await
+        .expect("login searcher");
+
+    let status = post_json(
+        &app,
+        "/auth/login",
+        json!({ "username": SELFTEST_USERNAME, "password": "password123" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body) =
+        common::auth_request(&app.router, "GET", "/friends/search?query=__s", &cookie)
+            .await
+            .expect("search");
+    assert_eq!(status, StatusCode::OK);
+    assert!(!body.contains(SELFTEST_USERNAME), "search result: {body}");
+}
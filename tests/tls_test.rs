@@ -0,0 +1,184 @@
+/// Tests Z331-Z333: Native TLS
+///
+/// With `TLS_CERT_PATH` and `TLS_KEY_PATH` set the server terminates HTTPS itself.
+/// The certificate is read again on SIGHUP or every `TLS_RELOAD_INTERVAL_SECS`; new
+/// handshakes get the new one, and a broken file keeps the old one in use.
+use axum::{Router, routing::get};
+use kash_server::config::{ConfigError, TlsConfig};
+use kash_server::tls::{CertificateResolver, TlsError, TlsListener};
+use rustls::pki_types::{CertificateDer, ServerName};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsConnector;
+
+// ---- Helpers ----
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls");
+
+/// Copies fixture pair `name` (`a` or `b`) to `cert.pem` / `key.pem` in `dir`.
+fn install_pair(dir: &Path, name: &str) {
+    std::fs::copy(
+        Path::new(FIXTURES).join(format!("cert_{name}.pem")),
+        dir.join("cert.pem"),
+    )
+    .unwrap();
+    std::fs::copy(
+        Path::new(FIXTURES).join(format!("key_{name}.pem")),
+        dir.join("key.pem"),
+    )
+    .unwrap();
+}
+
+fn tls_config(dir: &Path) -> TlsConfig {
+    TlsConfig {
+        cert_path: dir.join("cert.pem").to_string_lossy().to_string(),
+        key_path: dir.join("key.pem").to_string_lossy().to_string(),
+        reload_interval_secs: None,
+    }
+}
+
+fn fixture_cert(name: &str) -> CertificateDer<'static> {
+    let pem = std::fs::read(Path::new(FIXTURES).join(format!("cert_{name}.pem"))).unwrap();
+    rustls_pemfile::certs(&mut pem.as_slice())
+        .next()
+        .unwrap()
+        .unwrap()
+}
+
+/// A client trusting both fixture certificates, so either may be served.
+fn connector() -> TlsConnector {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(fixture_cert("a")).unwrap();
+    roots.add(fixture_cert("b")).unwrap();
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    TlsConnector::from(Arc::new(config))
+}
+
+async fn serve(resolver: &Arc<CertificateResolver>) -> std::net::SocketAddr {
+    let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let listener = TlsListener::new(tcp, resolver.server_config()).unwrap();
+    let addr = axum::serve::Listener::local_addr(&listener).unwrap();
+    let router = Router::new().route("/", get(|| async { "hello over tls" }));
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    addr
+}
+
+/// Makes one request and returns the served certificate and the raw response.
+async fn get_root(addr: std::net::SocketAddr) -> (CertificateDer<'static>, String) {
+    let tcp = TcpStream::connect(addr).await.unwrap();
+    let mut stream = connector()
+        .connect(ServerName::try_from("localhost").unwrap(), tcp)
+        .await
+        .expect("handshake");
+    let served = stream.get_ref().1.peer_certificates().unwrap()[0].clone();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.unwrap();
+    (served, String::from_utf8_lossy(&response).to_string())
+}
+
+// ---------------------------------------------------------------------------
+// Z331: Both paths or neither; the reload interval is a number of seconds
+// ---------------------------------------------------------------------------
+
+#[test]
+fn z331_tls_settings_pair_up() {
+    assert!(TlsConfig::new(None, None, None).unwrap().is_none());
+    assert!(TlsConfig::new(Some(" "), Some(""), None).unwrap().is_none());
+    assert!(matches!(
+        TlsConfig::new(Some("cert.pem"), None, None),
+        Err(ConfigError::InvalidTls(_))
+    ));
+    assert!(matches!(
+        TlsConfig::new(None, Some("key.pem"), None),
+        Err(ConfigError::InvalidTls(_))
+    ));
+    assert!(matches!(
+        TlsConfig::new(Some("cert.pem"), Some("key.pem"), Some("hourly")),
+        Err(ConfigError::InvalidTls(_))
+    ));
+
+    let tls = TlsConfig::new(Some("cert.pem"), Some("key.pem"), Some("3600"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(tls.cert_path, "cert.pem");
+    assert_eq!(tls.key_path, "key.pem");
+    assert_eq!(tls.reload_interval_secs, Some(3600));
+    let tls = TlsConfig::new(Some("cert.pem"), Some("key.pem"), Some("0"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(tls.reload_interval_secs, None);
+}
+
+// ---------------------------------------------------------------------------
+// Z332: Load errors name the file at fault
+// ---------------------------------------------------------------------------
+
+#[test]
+fn z332_load_errors_name_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let tls = tls_config(dir.path());
+
+    let err = CertificateResolver::load(tls.clone()).unwrap_err();
+    assert!(matches!(err, TlsError::Read { .. }));
+    assert!(err.to_string().contains(&tls.cert_path), "{err}");
+
+    // The key file where the certificate belongs
+    std::fs::copy(Path::new(FIXTURES).join("key_a.pem"), &tls.cert_path).unwrap();
+    std::fs::copy(Path::new(FIXTURES).join("key_a.pem"), &tls.key_path).unwrap();
+    let err = CertificateResolver::load(tls.clone()).unwrap_err();
+    assert!(matches!(err, TlsError::NoCertificate(_)));
+    assert!(err.to_string().contains(&tls.cert_path), "{err}");
+
+    std::fs::copy(Path::new(FIXTURES).join("cert_a.pem"), &tls.cert_path).unwrap();
+    std::fs::copy(Path::new(FIXTURES).join("cert_a.pem"), &tls.key_path).unwrap();
+    let err = CertificateResolver::load(tls.clone()).unwrap_err();
+    assert!(matches!(err, TlsError::NoPrivateKey(_)));
+    assert!(err.to_string().contains(&tls.key_path), "{err}");
+
+    // A valid key that belongs to another certificate
+    std::fs::copy(Path::new(FIXTURES).join("key_b.pem"), &tls.key_path).unwrap();
+    let err = CertificateResolver::load(tls.clone()).unwrap_err();
+    assert!(matches!(err, TlsError::InvalidKey { .. }));
+    assert!(err.to_string().contains(&tls.key_path), "{err}");
+}
+
+// ---------------------------------------------------------------------------
+// Z333: HTTPS is served and a reload swaps the certificate for new handshakes
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z333_serves_https_and_reloads_certificate() {
+    let dir = tempfile::tempdir().unwrap();
+    install_pair(dir.path(), "a");
+    let resolver = CertificateResolver::load(tls_config(dir.path())).unwrap();
+    let addr = serve(&resolver).await;
+
+    let (served, response) = get_root(addr).await;
+    assert_eq!(served, fixture_cert("a"));
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.ends_with("hello over tls"), "{response}");
+
+    install_pair(dir.path(), "b");
+    resolver.reload().expect("reload");
+    let (served, response) = get_root(addr).await;
+    assert_eq!(served, fixture_cert("b"));
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+
+    // A broken file is reported and the last good certificate stays in use
+    std::fs::write(dir.path().join("cert.pem"), "not a certificate").unwrap();
+    assert!(resolver.reload().is_err());
+    let (served, _) = get_root(addr).await;
+    assert_eq!(served, fixture_cert("b"));
+}
@@ -0,0 +1,329 @@
+/// Tests Z371-Z374: Two-factor login
+///
+/// `POST /auth/2fa/setup` hands out a TOTP secret that only counts once
+/// `POST /auth/2fa/verify` confirms a code. From then on `POST /auth/login` answers
+/// `202` with a challenge token and no session, and `POST /auth/login/2fa` trades the
+/// token and a TOTP or single-use recovery code for the session. Codes cannot be
+/// replayed, and disabling takes the password and a code.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use common::fixtures::{FIXTURE_PASSWORD, ScenarioBuilder};
+use kash_server::constants::*;
+use kash_server::two_factor::{decode_base32, totp_code};
+use serde_json::{Value, json};
+use time::{Duration, OffsetDateTime};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+/// Status, JSON body and the `set-cookie` header, if any.
+async fn post(
+    app: &common::TestApp,
+    uri: &str,
+    cookie: &str,
+    payload: Value,
+) -> (StatusCode, Value, Option<String>) {
+    let request = Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header("cookie", cookie)
+        .header("content-type", "application/json")
+        .body(Body::from(payload.to_string()))
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let set_cookie = response
+        .headers()
+        .get("set-cookie")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        set_cookie,
+    )
+}
+
+async fn login(app: &common::TestApp, username: &str) -> (StatusCode, Value, Option<String>) {
+    post(
+        app,
+        "/auth/login",
+        "",
+        json!({ "username": username, "password": FIXTURE_PASSWORD }),
+    )
+    .await
+}
+
+/// The code for `offset` steps from now.
+fn code_at(secret: &[u8], offset: i64) -> String {
+    totp_code(
+        secret,
+        OffsetDateTime::now_utc() + Duration::seconds(offset * TOTP_STEP_SECS),
+    )
+}
+
+/// Sets up and confirms two-factor login; the raw secret and the recovery codes.
+async fn enable_two_factor(app: &common::TestApp, cookie: &str) -> (Vec<u8>, Vec<String>) {
+    let (status, body, _) = post(app, "/auth/2fa/setup", cookie, json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let secret = decode_base32(body["secret"].as_str().unwrap()).expect("base32 secret");
+
+    let (status, body, _) = post(
+        app,
+        "/auth/2fa/verify",
+        cookie,
+        json!({ "code": code_at(&secret, 0) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let recovery_codes = body["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|code| code.as_str().unwrap().to_string())
+        .collect();
+    (secret, recovery_codes)
+}
+
+/// `POST /auth/login` for an account with two-factor login; the challenge token.
+async fn challenge(app: &common::TestApp, username: &str) -> String {
+    let (status, body, set_cookie) = login(app, username).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{body}");
+    assert_eq!(body["two_factor_required"], true);
+    assert!(set_cookie.is_none(), "no session before the second factor");
+    body["challenge_token"].as_str().unwrap().to_string()
+}
+
+async fn login_two_factor(
+    app: &common::TestApp,
+    token: &str,
+    code: &str,
+) -> (StatusCode, Value, Option<String>) {
+    post(
+        app,
+        "/auth/login/2fa",
+        "",
+        json!({ "challenge_token": token, "code": code }),
+    )
+    .await
+}
+
+// ---------------------------------------------------------------------------
+// Z371: Enabling two-factor login turns the login into two steps
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z371_login_needs_a_code_once_enabled() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_z371"])
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("alice_z371");
+
+    // An unconfirmed setup changes nothing
+    let (status, body, _) = post(&app, "/auth/2fa/setup", cookie, json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    let secret = body["secret"].as_str().unwrap();
+    let uri = body["otpauth_uri"].as_str().unwrap();
+    assert!(uri.starts_with("otpauth://totp/Kash:alice_z371?"), "{uri}");
+    assert!(uri.contains(&format!("secret={secret}")), "{uri}");
+    let (status, _, _) = login(&app, "alice_z371").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body, _) = post(
+        &app,
+        "/auth/2fa/verify",
+        cookie,
+        json!({ "code": "000000x" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], ERROR_CODE_INVALID_TWO_FACTOR_CODE);
+
+    let (secret, recovery_codes) = enable_two_factor(&app, cookie).await;
+    assert_eq!(recovery_codes.len(), TWO_FACTOR_RECOVERY_CODE_COUNT);
+    let (status, body, _) = post(&app, "/auth/2fa/setup", cookie, json!({})).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], ERROR_CODE_TWO_FACTOR_ALREADY_ENABLED);
+
+    let token = challenge(&app, "alice_z371").await;
+    // The code that confirmed the setup was used up there
+    let (status, body, _) = login_two_factor(&app, &token, &code_at(&secret, 0)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], ERROR_CODE_INVALID_TWO_FACTOR_CODE);
+
+    let (status, body, set_cookie) = login_two_factor(&app, &token, &code_at(&secret, 1)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["username"], "alice_z371");
+    let session = set_cookie.expect("session cookie");
+    let me = app
+        .router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/auth/me")
+                .header("cookie", session)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(me.status(), StatusCode::OK);
+
+    // A challenge is spent once it is used
+    let (status, body, _) = login_two_factor(&app, &token, &recovery_codes[0]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], ERROR_CODE_TWO_FACTOR_CHALLENGE_INVALID);
+}
+
+// ---------------------------------------------------------------------------
+// Z372: Recovery codes work once; challenges take a few codes at most
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z372_recovery_codes_are_single_use() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["bob_z372"])
+        .build(&app)
+        .await;
+    let (_, recovery_codes) = enable_two_factor(&app, scenario.cookie("bob_z372")).await;
+
+    // Typed back in capitals and without the dash
+    let typed = recovery_codes[3].replace('-', "").to_uppercase();
+    let token = challenge(&app, "bob_z372").await;
+    let (status, _, _) = login_two_factor(&app, &token, &typed).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let token = challenge(&app, "bob_z372").await;
+    let (status, body, _) = login_two_factor(&app, &token, &recovery_codes[3]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], ERROR_CODE_INVALID_TWO_FACTOR_CODE);
+
+    for _ in 1..TWO_FACTOR_CHALLENGE_MAX_ATTEMPTS {
+        let (status, _, _) = login_two_factor(&app, &token, "wrong-code").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, body, _) = login_two_factor(&app, &token, &recovery_codes[4]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], ERROR_CODE_TWO_FACTOR_CHALLENGE_INVALID);
+
+    let (status, body, _) = login_two_factor(&app, "not-a-token", &recovery_codes[4]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], ERROR_CODE_TWO_FACTOR_CHALLENGE_INVALID);
+}
+
+// ---------------------------------------------------------------------------
+// Z373: Disabling takes the password and a code
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z373_disable_requires_password_and_code() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["carol_z373"])
+        .build(&app)
+        .await;
+    let cookie = scenario.cookie("carol_z373");
+
+    let (status, body, _) = post(
+        &app,
+        "/auth/2fa/disable",
+        cookie,
+        json!({ "password": FIXTURE_PASSWORD, "code": "123456" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], ERROR_CODE_TWO_FACTOR_NOT_ENABLED);
+
+    let (_, recovery_codes) = enable_two_factor(&app, cookie).await;
+    let (status, _, _) = post(
+        &app,
+        "/auth/2fa/disable",
+        cookie,
+        json!({ "password": "not the password", "code": recovery_codes[0] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body, _) = post(
+        &app,
+        "/auth/2fa/disable",
+        cookie,
+        json!({ "password": FIXTURE_PASSWORD, "code": "wrong-code" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], ERROR_CODE_INVALID_TWO_FACTOR_CODE);
+
+    let (status, _, _) = post(
+        &app,
+        "/auth/2fa/disable",
+        cookie,
+        json!({ "password": FIXTURE_PASSWORD, "code": recovery_codes[0] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (status, body, set_cookie) = login(&app, "carol_z373").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(set_cookie.is_some());
+
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT COUNT(*) FROM two_factor_recovery_codes WHERE user_id = ?",
+            [scenario.id("carol_z373")],
+        )
+        .await
+        .unwrap();
+    let remaining: i64 = rows.next().await.unwrap().unwrap().get(0).unwrap();
+    assert_eq!(remaining, 0);
+}
+
+// ---------------------------------------------------------------------------
+// Z374: Secrets are sealed at rest; wrong codes count towards the lockout
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn z374_secret_is_sealed_and_wrong_codes_lock_the_account() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["dave_z374"])
+        .build(&app)
+        .await;
+    let (secret, _) = enable_two_factor(&app, scenario.cookie("dave_z374")).await;
+
+    {
+        let conn = app.state.main_db.read().await;
+        let mut rows = conn
+            .query(
+                "SELECT totp_secret FROM users WHERE id = ?",
+                [scenario.id("dave_z374")],
+            )
+            .await
+            .unwrap();
+        let stored: String = rows.next().await.unwrap().unwrap().get(0).unwrap();
+        let encoded = kash_server::two_factor::encode_base32(&secret);
+        assert!(!stored.contains(&encoded), "stored in the clear");
+        assert!(!stored.eq_ignore_ascii_case(&encoded));
+    }
+
+    // Correct passwords followed by wrong codes never reset the failure count
+    let mut failures = 0;
+    while failures < LOGIN_LOCKOUT_FAILURES {
+        let token = challenge(&app, "dave_z374").await;
+        let (status, _, _) = login_two_factor(&app, &token, "wrong-code").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        failures += 1;
+    }
+    let (status, _, _) = login(&app, "dave_z374").await;
+    assert_eq!(status, StatusCode::LOCKED);
+}
@@ -0,0 +1,413 @@
+/// Tests U1-U8: Username changes
+///
+/// `PATCH /auth/username` renames the logged-in user after re-checking their
+/// password. Everything else keys off the user id, so friends, search, `/auth/me`
+/// and the Telegram link see the new name immediately. Changes are limited to one
+/// per `USERNAME_CHANGE_COOLDOWN_DAYS` and recorded in `username_history`.
+mod common;
+
+use axum::{http::StatusCode, response::Response};
+use common::fixtures::{FIXTURE_PASSWORD, ScenarioBuilder};
+use common::send_request;
+use serde_json::{Value, json};
+
+// ---- Helpers ----
+
+async fn body_json(response: Response) -> Value {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).unwrap_or(Value::Null)
+}
+
+async fn change_username(
+    app: &common::TestApp,
+    cookie: &str,
+    new_username: &str,
+    password: &str,
+) -> Response {
+    send_request(
+        app,
+        "PATCH",
+        "/auth/username",
+        cookie,
+        Some(json!({ "new_username": new_username, "current_password": password })),
+    )
+    .await
+}
+
+fn set_cookie(response: &Response) -> String {
+    response
+        .headers()
+        .get("set-cookie")
+        .and_then(|v| v.to_str().ok())
+        .expect("session cookie")
+        .to_string()
+}
+
+async fn username_of(app: &common::TestApp, user_id: &str) -> String {
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query("SELECT name FROM users WHERE id = ?", [user_id])
+        .await
+        .unwrap();
+    rows.next().await.unwrap().unwrap().get(0).unwrap()
+}
+
+// ---------------------------------------------------------------------------
+// U1: Rename updates the user, rotates the session and writes history
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn u1_change_username_rotates_session_and_records_history() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alcie_u1"])
+        .build(&app)
+        .await;
+    let alice = scenario.id("alcie_u1");
+    let old_cookie = scenario.cookie("alcie_u1");
+
+    let response = change_username(&app, old_cookie, "alice_u1", FIXTURE_PASSWORD).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let new_cookie = set_cookie(&response);
+    let user = body_json(response).await;
+    assert_eq!(user["id"], alice);
+    assert_eq!(user["username"], "alice_u1");
+    assert_eq!(username_of(&app, alice).await, "alice_u1");
+
+    let response = send_request(&app, "GET", "/auth/me", &new_cookie, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_json(response).await["username"], "alice_u1");
+
+    let response = send_request(&app, "GET", "/auth/me", old_cookie, None).await;
+    assert_eq!(
+        response.status(),
+        StatusCode::UNAUTHORIZED,
+        "the pre-rename session id must no longer be valid"
+    );
+
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT old_name, new_name FROM username_history WHERE user_id = ?",
+            [alice],
+        )
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().expect("history row");
+    assert_eq!(row.get::<String>(0).unwrap(), "alcie_u1");
+    assert_eq!(row.get::<String>(1).unwrap(), "alice_u1");
+    assert!(rows.next().await.unwrap().is_none());
+}
+
+// ---------------------------------------------------------------------------
+// U2: Login works with the new name and rejects the old one
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn u2_login_uses_new_name() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["bob_old_u2"])
+        .build(&app)
+        .await;
+
+    let response = change_username(
+        &app,
+        scenario.cookie("bob_old_u2"),
+        "bob_new_u2",
+        FIXTURE_PASSWORD,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert!(
+        common::login_user(&app.router, "bob_new_u2", FIXTURE_PASSWORD)
+            .await
+            .is_ok()
+    );
+
+    let response = send_request(
+        &app,
+        "POST",
+        "/auth/login",
+        "",
+        Some(json!({ "username": "bob_old_u2", "password": FIXTURE_PASSWORD })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+// ---------------------------------------------------------------------------
+// U3: Policy, password and case-insensitive uniqueness are enforced
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn u3_rejects_invalid_taken_or_unauthenticated_changes() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["carol_u3", "Dave_u3"])
+        .build(&app)
+        .await;
+    let carol = scenario.id("carol_u3");
+    let cookie = scenario.cookie("carol_u3");
+
+    let response = change_username(&app, cookie, "dave_U3", FIXTURE_PASSWORD).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = change_username(&app, cookie, "carol_new", "wrong-password").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    for invalid in ["abc", "has space", "", "carol_u3"] {
+        let response = change_username(&app, cookie, invalid, FIXTURE_PASSWORD).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::BAD_REQUEST,
+            "username {invalid:?}"
+        );
+    }
+
+    let response = change_username(&app, "", "carol_new", FIXTURE_PASSWORD).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    assert_eq!(username_of(&app, carol).await, "carol_u3");
+
+    // Changing only the case of one's own name is allowed.
+    let response = change_username(&app, cookie, "Carol_U3", FIXTURE_PASSWORD).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(username_of(&app, carol).await, "Carol_U3");
+
+    // Registration applies the same rule
+    let response = send_request(
+        &app,
+        "POST",
+        "/auth/register",
+        "",
+        Some(json!({ "username": "dave_U3", "password": "correct horse battery" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+// ---------------------------------------------------------------------------
+// U4: One change per cooldown window
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn u4_second_change_within_cooldown_is_rate_limited() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new().users(&["erin_u4"]).build(&app).await;
+    let erin = scenario.id("erin_u4");
+
+    let response = change_username(
+        &app,
+        scenario.cookie("erin_u4"),
+        "erin_a_u4",
+        FIXTURE_PASSWORD,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = set_cookie(&response);
+
+    let response = change_username(&app, &cookie, "erin_b_u4", FIXTURE_PASSWORD).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(username_of(&app, erin).await, "erin_a_u4");
+
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "UPDATE users SET username_changed_at = '2020-01-01T00:00:00Z' WHERE id = ?",
+            [erin],
+        )
+        .await
+        .unwrap();
+    }
+
+    let response = change_username(&app, &cookie, "erin_b_u4", FIXTURE_PASSWORD).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(username_of(&app, erin).await, "erin_b_u4");
+}
+
+// ---------------------------------------------------------------------------
+// U5: Friends see the new name immediately
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn u5_friend_list_shows_new_name() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["frank_u5", "grace_u5"])
+        .friend("frank_u5", "grace_u5")
+        .build(&app)
+        .await;
+
+    let response = change_username(
+        &app,
+        scenario.cookie("frank_u5"),
+        "franklin_u5",
+        FIXTURE_PASSWORD,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = send_request(
+        &app,
+        "GET",
+        "/friends/list?pending=false",
+        scenario.cookie("grace_u5"),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let list = body_json(response).await;
+    let friends = list["friends"].as_array().expect("friends should be array");
+    assert_eq!(friends.len(), 1);
+    assert_eq!(friends[0]["user_id"], scenario.id("frank_u5"));
+    assert_eq!(friends[0]["nickname"], "franklin_u5");
+}
+
+// ---------------------------------------------------------------------------
+// U6: The Telegram link is keyed by user id and survives the rename
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn u6_telegram_link_survives_rename() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["heidi_u6"])
+        .build(&app)
+        .await;
+    let heidi = scenario.id("heidi_u6");
+
+    {
+        let conn = app.state.main_db.write().await;
+        conn.execute(
+            "INSERT INTO telegram_users (telegram_user_id, user_id, chat_id, created_at) \
+             VALUES ('4242', ?, '4242', 0)",
+            [heidi],
+        )
+        .await
+        .unwrap();
+    }
+
+    let response = change_username(
+        &app,
+        scenario.cookie("heidi_u6"),
+        "heidi_x_u6",
+        FIXTURE_PASSWORD,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let conn = app.state.main_db.read().await;
+    let mut rows = conn
+        .query(
+            "SELECT u.id, u.name FROM telegram_users t JOIN users u ON u.id = t.user_id \
+             WHERE t.telegram_user_id = '4242'",
+            (),
+        )
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().expect("telegram link");
+    assert_eq!(row.get::<String>(0).unwrap(), heidi);
+    assert_eq!(row.get::<String>(1).unwrap(), "heidi_x_u6");
+}
+
+// ---------------------------------------------------------------------------
+// U7: Two users racing for the same name: one wins, the other gets 409
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn u7_concurrent_claims_for_one_name() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["ivan_u7", "judy_u7"])
+        .build(&app)
+        .await;
+
+    let (ivan, judy) = tokio::join!(
+        change_username(
+            &app,
+            scenario.cookie("ivan_u7"),
+            "wanted_u7",
+            FIXTURE_PASSWORD
+        ),
+        change_username(
+            &app,
+            scenario.cookie("judy_u7"),
+            "Wanted_U7",
+            FIXTURE_PASSWORD
+        ),
+    );
+    let mut statuses = [ivan.status(), judy.status()];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::OK, StatusCode::CONFLICT]);
+
+    let names = [
+        username_of(&app, scenario.id("ivan_u7")).await,
+        username_of(&app, scenario.id("judy_u7")).await,
+    ];
+    let winners = names
+        .iter()
+        .filter(|name| name.eq_ignore_ascii_case("wanted_u7"))
+        .count();
+    assert_eq!(winners, 1, "{names:?}");
+}
+
+// ---------------------------------------------------------------------------
+// U8: Other sessions and searches see the new name without logging in again
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn u8_me_and_search_reflect_rename() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["kate_u8", "liam_u8"])
+        .build(&app)
+        .await;
+    let kate = scenario.id("kate_u8");
+    let other_session = common::login_user(&app.router, "kate_u8", FIXTURE_PASSWORD)
+        .await
+        .unwrap();
+
+    let response = change_username(
+        &app,
+        scenario.cookie("kate_u8"),
+        "katherine_u8",
+        FIXTURE_PASSWORD,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = send_request(&app, "GET", "/auth/me", &other_session, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let me = body_json(response).await;
+    assert_eq!(me["id"], kate);
+    assert_eq!(me["username"], "katherine_u8");
+    let response = send_request(&app, "GET", "/bootstrap", &other_session, None).await;
+    assert_eq!(
+        body_json(response).await["user"]["data"]["username"],
+        "katherine_u8"
+    );
+
+    // The stale session name no longer passes as a different user
+    let response = send_request(
+        &app,
+        "POST",
+        "/friends/request",
+        &other_session,
+        Some(json!({ "friend_username": "katherine_u8" })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let liam = scenario.cookie("liam_u8");
+    let response = send_request(&app, "GET", "/friends/search?query=katherine", liam, None).await;
+    let found = body_json(response).await;
+    assert_eq!(found[0]["id"], kate);
+    assert_eq!(found[0]["username"], "katherine_u8");
+
+    let response = send_request(&app, "GET", "/friends/search?query=kate_u", liam, None).await;
+    assert_eq!(body_json(response).await, json!([]));
+}
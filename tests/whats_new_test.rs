@@ -0,0 +1,260 @@
+/// Tests W1-W3: "While you were away" digest
+///
+/// Login moves `last_login_at` to `previous_login_at` and reports how many
+/// items `GET /whats-new` holds: incoming friend requests, splits others
+/// created with the user, records not entered through the API, and budget
+/// alerts, all created since the previous login. Fetching the digest empties
+/// it until the next login.
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    response::Response,
+};
+use common::fixtures::{FIXTURE_PASSWORD, ScenarioBuilder, money};
+use common::send_request;
+use kash_server::constants::{CREATED_VIA_API, CREATED_VIA_BOT_COMMAND, DEFAULT_CURRENCY_CODE};
+use kash_server::models::{
+    BudgetAlert, CreateSplitPayload, PublicUser, SplitMode, SplitParticipant, WhatsNew,
+    WhatsNewCounts,
+};
+use kash_server::record_repo::{self, NewRecord};
+use kash_server::{friends, outbox, splits};
+use serde_json::{Value, json};
+use tower::util::ServiceExt;
+
+// ---- Helpers ----
+
+async fn body_json(response: Response) -> Value {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).unwrap_or(Value::Null)
+}
+
+/// Logs in and returns the session cookie and the login response's digest counts.
+async fn login(app: &common::TestApp, username: &str) -> (String, WhatsNewCounts) {
+    let request = Request::builder()
+        .uri("/auth/login")
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({ "username": username, "password": FIXTURE_PASSWORD }).to_string(),
+        ))
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = response
+        .headers()
+        .get("set-cookie")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = body_json(response).await;
+    assert_eq!(body["username"], username);
+    (
+        cookie,
+        serde_json::from_value(body["whats_new"].clone()).unwrap(),
+    )
+}
+
+async fn whats_new(app: &common::TestApp, cookie: &str) -> WhatsNew {
+    let response = send_request(app, "GET", "/whats-new", cookie, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    serde_json::from_value(body_json(response).await).unwrap()
+}
+
+async fn insert_record(app: &common::TestApp, owner: &str, name: &str, created_via: &str) {
+    let conn = app.state.main_db.write().await;
+    record_repo::insert_record(
+        &conn,
+        &NewRecord {
+            id: &uuid::Uuid::new_v4().to_string(),
+            owner_user_id: owner,
+            name,
+            amount: money(-42.0),
+            currency: DEFAULT_CURRENCY_CODE,
+            category_id: "uncategorized",
+            date: "2025-03-10",
+            created_via,
+            original_amount: None,
+            original_currency: None,
+            note: None,
+            tags: &[],
+        },
+    )
+    .await
+    .unwrap();
+}
+
+// ---------------------------------------------------------------------------
+// W1: Activity between two logins shows up once
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn w1_digest_lists_activity_since_previous_login() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_w1", "bob_w1", "carol_w1"])
+        .category("bob_w1", "Dining")
+        .friend("bob_w1", "alice_w1")
+        .build(&app)
+        .await;
+    let alice = scenario.id("alice_w1");
+
+    // Everything below happens while Alice is away.
+    let carol = PublicUser {
+        id: scenario.id("carol_w1").to_string(),
+        username: "carol_w1".to_string(),
+    };
+    friends::send_friend_request_for_user(
+        &app.state.main_db,
+        &carol,
+        "alice_w1",
+        Some("it's Carol from climbing"),
+    )
+    .await
+    .expect("friend request");
+
+    let (status, _) = splits::create_split_for_user(
+        &app.state,
+        scenario.id("bob_w1"),
+        CreateSplitPayload {
+            idempotency_key: "key-w1".to_string(),
+            total_amount: money(90.0),
+            description: "Hotpot".to_string(),
+            date: "2025-03-10".to_string(),
+            category_id: scenario.category_id("bob_w1", "Dining").to_string(),
+            currency: None,
+            split_mode: SplitMode::Exact,
+            splits: vec![SplitParticipant {
+                user_id: alice.to_string(),
+                amount: money(30.0),
+                percentage: None,
+            }],
+            legacy_full_total: false,
+        },
+    )
+    .await
+    .expect("split");
+    assert_eq!(status, StatusCode::CREATED);
+
+    insert_record(&app, alice, "Bus pass", CREATED_VIA_BOT_COMMAND).await;
+    insert_record(&app, alice, "Typed by hand", CREATED_VIA_API).await;
+    insert_record(
+        &app,
+        scenario.id("bob_w1"),
+        "Not Alice's",
+        CREATED_VIA_BOT_COMMAND,
+    )
+    .await;
+
+    {
+        let conn = app.state.main_db.write().await;
+        outbox::enqueue_budget_alert(
+            &conn,
+            alice,
+            &BudgetAlert {
+                category_name: "Dining".to_string(),
+                spent: 950.0,
+                limit: 1000.0,
+            },
+            time::OffsetDateTime::now_utc(),
+        )
+        .await
+        .expect("alert");
+    }
+
+    let (cookie, counts) = login(&app, "alice_w1").await;
+    assert_eq!(
+        counts,
+        WhatsNewCounts {
+            friend_requests: 1,
+            splits: 1,
+            auto_records: 1,
+            budget_alerts: 1,
+        }
+    );
+
+    let digest = whats_new(&app, &cookie).await;
+    assert!(digest.since.is_some());
+    assert_eq!(digest.counts, counts);
+    assert_eq!(digest.friend_requests.len(), 1);
+    assert_eq!(digest.friend_requests[0].username, "carol_w1");
+    assert_eq!(
+        digest.friend_requests[0].message.as_deref(),
+        Some("it's Carol from climbing")
+    );
+    assert_eq!(digest.splits.len(), 1);
+    assert_eq!(digest.splits[0].description, "Hotpot");
+    assert_eq!(digest.splits[0].amount, money(30.0));
+    assert_eq!(digest.splits[0].requested_by_name, "bob_w1");
+    assert_eq!(digest.auto_records.len(), 1);
+    assert_eq!(digest.auto_records[0].name, "Bus pass");
+    assert_eq!(digest.auto_records[0].created_via, CREATED_VIA_BOT_COMMAND);
+    assert_eq!(digest.budget_alerts.len(), 1);
+    assert_eq!(digest.budget_alerts[0].category_name, "Dining");
+
+    // Bob's own split record is not news to Bob.
+    let (_, bob_counts) = login(&app, "bob_w1").await;
+    assert_eq!(bob_counts.splits, 0);
+    assert_eq!(bob_counts.auto_records, 1);
+}
+
+// ---------------------------------------------------------------------------
+// W2: The digest empties once fetched, until the next login
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn w2_digest_is_shown_once_per_login() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let scenario = ScenarioBuilder::new()
+        .users(&["alice_w2"])
+        .build(&app)
+        .await;
+    let alice = scenario.id("alice_w2");
+
+    insert_record(&app, alice, "Coffee", CREATED_VIA_BOT_COMMAND).await;
+    let (cookie, counts) = login(&app, "alice_w2").await;
+    assert_eq!(counts.total(), 1);
+
+    assert_eq!(whats_new(&app, &cookie).await.auto_records.len(), 1);
+    let again = whats_new(&app, &cookie).await;
+    assert!(again.since.is_none());
+    assert_eq!(again.counts.total(), 0);
+    assert!(again.auto_records.is_empty());
+
+    // Activity after the fetch waits for the next login, which only covers what is new.
+    insert_record(&app, alice, "Tea", CREATED_VIA_BOT_COMMAND).await;
+    assert_eq!(whats_new(&app, &cookie).await.counts.total(), 0);
+    let (cookie, counts) = login(&app, "alice_w2").await;
+    assert_eq!(counts.auto_records, 1);
+    let digest = whats_new(&app, &cookie).await;
+    assert_eq!(digest.auto_records.len(), 1);
+    assert_eq!(digest.auto_records[0].name, "Tea");
+
+    // A login with nothing new in between reports zero.
+    let (_, counts) = login(&app, "alice_w2").await;
+    assert_eq!(counts.total(), 0);
+}
+
+// ---------------------------------------------------------------------------
+// W3: A first login has no digest
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn w3_first_login_has_no_digest() {
+    let app = common::setup_test_app().await.expect("setup failed");
+    let user_id = common::create_test_user(&app.state, "alice_w3", FIXTURE_PASSWORD)
+        .await
+        .expect("create user");
+    insert_record(&app, &user_id, "Imported", CREATED_VIA_BOT_COMMAND).await;
+
+    let (cookie, counts) = login(&app, "alice_w3").await;
+    assert_eq!(counts.total(), 0);
+    let digest = whats_new(&app, &cookie).await;
+    assert!(digest.since.is_none());
+    assert!(digest.auto_records.is_empty());
+}